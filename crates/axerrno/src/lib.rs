@@ -89,6 +89,14 @@ pub enum AxError {
     Interrupted,
     /// Syscall timed out
     Timeout,
+    /// The file would exceed the maximum allowed size.
+    FileTooLarge,
+    /// The filesystem or mount point is read-only.
+    ReadOnlyFilesystem,
+    /// Too many levels of symbolic links were encountered.
+    FilesystemLoop,
+    /// Waiting for the resource would deadlock.
+    Deadlock,
 }
 
 /// A specialized [`Result`] type with [`AxError`] as the error type.
@@ -216,6 +224,10 @@ impl AxError {
             WriteZero => "Write zero",
             Interrupted => "Interrupted",
             Timeout => "Timeout",
+            FileTooLarge => "File too large",
+            ReadOnlyFilesystem => "Read-only filesystem",
+            FilesystemLoop => "Too many levels of symbolic links",
+            Deadlock => "Resource deadlock would occur",
         }
     }
 
@@ -269,6 +281,10 @@ impl From<AxError> for LinuxError {
             WouldBlock => LinuxError::EAGAIN,
             Interrupted => LinuxError::EINTR,
             Timeout => LinuxError::ETIME,
+            FileTooLarge => LinuxError::EFBIG,
+            ReadOnlyFilesystem => LinuxError::EROFS,
+            FilesystemLoop => LinuxError::ELOOP,
+            Deadlock => LinuxError::EDEADLK,
         }
     }
 }
@@ -291,13 +307,13 @@ mod tests {
     #[test]
     fn test_try_from() {
         let max_code = core::mem::variant_count::<AxError>() as i32;
-        assert_eq!(max_code, 24);
-        assert_eq!(max_code, AxError::Timeout.code());
+        assert_eq!(max_code, 28);
+        assert_eq!(max_code, AxError::Deadlock.code());
 
         assert_eq!(AxError::AddrInUse.code(), 1);
         assert_eq!(Ok(AxError::AddrInUse), AxError::try_from(1));
         assert_eq!(Ok(AxError::AlreadyExists), AxError::try_from(2));
-        assert_eq!(Ok(AxError::Timeout), AxError::try_from(max_code));
+        assert_eq!(Ok(AxError::Deadlock), AxError::try_from(max_code));
         assert_eq!(Err(max_code + 1), AxError::try_from(max_code + 1));
         assert_eq!(Err(0), AxError::try_from(0));
         assert_eq!(Err(-1), AxError::try_from(-1));
@@ -18,9 +18,12 @@ pub use self::null::NullDev;
 pub use self::random::RandomDev;
 pub use self::zero::ZeroDev;
 use alloc::sync::Arc;
-use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+use axfs_vfs::{FileSystemInfo, VfsNodeRef, VfsOps, VfsResult, PAGE_SIZE};
 use spin::once::Once;
 
+/// The magic number reported by `statfs`, `devtmpfs` is a tmpfs on Linux.
+const TMPFS_MAGIC: u64 = 0x0102_1994;
+
 /// A device filesystem that implements [`axfs_vfs::VfsOps`].
 pub struct DeviceFileSystem {
     parent: Once<VfsNodeRef>,
@@ -47,6 +50,11 @@ impl DeviceFileSystem {
     pub fn add(&self, name: &'static str, node: VfsNodeRef) {
         self.root.add(name, node);
     }
+
+    /// Remove a node from the root directory, returns the removed node.
+    pub fn remove(&self, name: &str) -> Option<VfsNodeRef> {
+        self.root.remove_node(name)
+    }
 }
 
 impl VfsOps for DeviceFileSystem {
@@ -59,6 +67,11 @@ impl VfsOps for DeviceFileSystem {
         Ok(())
     }
 
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        // device nodes take no space
+        Ok(FileSystemInfo::new(TMPFS_MAGIC, PAGE_SIZE as u64))
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         self.root.clone()
     }
@@ -2,11 +2,15 @@ use alloc::collections::BTreeMap;
 use alloc::sync::{Arc, Weak};
 use alloc::{string::String, vec::Vec};
 
-use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsDirEntry, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
 use axfs_vfs::{VfsError, VfsResult};
+use core::time::Duration;
 use spin::RwLock;
 
 use crate::file::FileNode;
+use crate::limits::FsLimits;
+use crate::meta::NodeMeta;
+use crate::symlink::SymlinkNode;
 use crate::Interrupts;
 
 /// The directory node in the RAM filesystem.
@@ -16,15 +20,21 @@ pub struct DirNode {
     this: Weak<DirNode>,
     parent: RwLock<Weak<dyn VfsNodeOps>>,
     children: RwLock<BTreeMap<String, VfsNodeRef>>,
+    meta: NodeMeta,
 }
 
 impl DirNode {
-    pub(super) fn new(parent: Option<Weak<dyn VfsNodeOps>>) -> Arc<Self> {
-        Arc::new_cyclic(|this| Self {
+    pub(super) fn new(
+        parent: Option<Weak<dyn VfsNodeOps>>,
+        limits: &Arc<FsLimits>,
+    ) -> VfsResult<Arc<Self>> {
+        let meta = NodeMeta::new(VfsNodePerm::default_dir(), limits)?;
+        Ok(Arc::new_cyclic(|this| Self {
             this: this.clone(),
             parent: RwLock::new(parent.unwrap_or_else(|| Weak::<Self>::new())),
             children: RwLock::new(BTreeMap::new()),
-        })
+            meta,
+        }))
     }
 
     pub(super) fn set_parent(&self, parent: Option<&VfsNodeRef>) {
@@ -47,22 +57,45 @@ impl DirNode {
             log::error!("AlreadyExists {}", name);
             return Err(VfsError::AlreadyExists);
         }
+        let limits = self.meta.limits();
         let node: VfsNodeRef = match ty {
             VfsNodeType::File => {
                 // 当前仅是将interrups作为一个特殊的节点，未来应该进行统一
                 if name == "interrupts" {
                     Arc::new(Interrupts)
                 } else {
-                    Arc::new(FileNode::new())
+                    Arc::new(FileNode::with_limits(limits)?)
                 }
             }
-            VfsNodeType::Dir => Self::new(Some(self.this.clone())),
+            VfsNodeType::Dir => Self::new(Some(self.this.clone()), limits)?,
             _ => return Err(VfsError::Unsupported),
         };
         self.children.write().insert(name.into(), node);
+        self.meta.touch_modified();
         Ok(())
     }
 
+    /// Adds an existing node with the given name in this directory.
+    fn insert_node(&self, name: &str, node: VfsNodeRef) -> VfsResult {
+        let mut children = self.children.write();
+        if children.contains_key(name) {
+            return Err(VfsError::AlreadyExists);
+        }
+        children.insert(name.into(), node);
+        self.meta.touch_modified();
+        Ok(())
+    }
+
+    /// Returns the subdirectory `name` to continue a path walk in.
+    fn walk_dir(&self, name: &str) -> VfsResult<VfsNodeRef> {
+        match name {
+            "" | "." => self.this.upgrade().map(|this| this as VfsNodeRef),
+            ".." => self.parent(),
+            _ => self.children.read().get(name).cloned(),
+        }
+        .ok_or(VfsError::NotFound)
+    }
+
     /// Removes a node by the given name in this directory.
     pub fn remove_node(&self, name: &str) -> VfsResult {
         let mut children = self.children.write();
@@ -72,14 +105,42 @@ impl DirNode {
                 return Err(VfsError::DirectoryNotEmpty);
             }
         }
+        if let Some(meta) = node_meta(node.as_ref()) {
+            meta.unlink();
+        }
         children.remove(name);
+        self.meta.touch_modified();
         Ok(())
     }
 }
 
 impl VfsNodeOps for DirNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_dir(4096, 0))
+        let mut attr = self.meta.fill(VfsNodeAttr::new_dir(4096, 0));
+        // "." and the entry in the parent, plus ".." of every subdirectory
+        let subdirs = self
+            .children
+            .read()
+            .values()
+            .filter(|node| node.as_any().is::<DirNode>())
+            .count();
+        attr.set_nlink(2 + subdirs as u32);
+        Ok(attr)
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
+    }
+
+    fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> VfsResult {
+        self.meta.chown(uid, gid);
+        Ok(())
+    }
+
+    fn chmod(&self, mode: VfsNodePerm) -> VfsResult {
+        self.meta.chmod(mode);
+        Ok(())
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
@@ -172,9 +233,53 @@ impl VfsNodeOps for DirNode {
         }
     }
 
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        log::debug!("symlink at ramfs: {} -> {}", path, target);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.walk_dir(name)?.symlink(rest, target)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else {
+            let symlink = SymlinkNode::with_limits(target, self.meta.limits())?;
+            self.insert_node(name, Arc::new(symlink))
+        }
+    }
+
+    fn link(&self, path: &str, src: &VfsNodeRef) -> VfsResult {
+        log::debug!("link at ramfs: {}", path);
+        let (name, rest) = split_path(path);
+        if let Some(rest) = rest {
+            self.walk_dir(name)?.link(rest, src)
+        } else if name.is_empty() || name == "." || name == ".." {
+            Err(VfsError::AlreadyExists)
+        } else if src.get_attr()?.is_dir() {
+            // hard links to directories would make the tree a graph
+            Err(VfsError::IsADirectory)
+        } else {
+            self.insert_node(name, src.clone())?;
+            if let Some(meta) = node_meta(src.as_ref()) {
+                meta.link();
+            }
+            Ok(())
+        }
+    }
+
     axfs_vfs::impl_vfs_dir_default! {}
 }
 
+/// Returns the metadata of a node created by the RAM filesystem.
+fn node_meta(node: &dyn VfsNodeOps) -> Option<&NodeMeta> {
+    let any = node.as_any();
+    if let Some(file) = any.downcast_ref::<FileNode>() {
+        Some(&file.meta)
+    } else if let Some(symlink) = any.downcast_ref::<SymlinkNode>() {
+        Some(&symlink.meta)
+    } else {
+        any.downcast_ref::<DirNode>().map(|dir| &dir.meta)
+    }
+}
+
 fn split_path(path: &str) -> (&str, Option<&str>) {
     let trimmed_path = path.trim_start_matches('/');
     trimmed_path.find('/').map_or((trimmed_path, None), |n| {
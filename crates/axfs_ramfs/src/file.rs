@@ -1,57 +1,261 @@
+use alloc::collections::btree_map::{BTreeMap, Entry};
+use alloc::sync::Arc;
 use alloc::vec::Vec;
-use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsResult};
+use axfs_vfs::{VfsError, VfsPage, VfsPageRef, PAGE_SIZE};
+use core::time::Duration;
 use spin::RwLock;
 
+use crate::limits::FsLimits;
+use crate::meta::NodeMeta;
+
+const PAGE: u64 = PAGE_SIZE as u64;
+
+/// The content of a file, made of pages with holes between them.
+///
+/// A missing page reads as zeros. The bytes of the pages past `size` are
+/// always zero, so that growing the file needs no clearing.
+struct Content {
+    size: u64,
+    pages: BTreeMap<u64, VfsPageRef>,
+}
+
+impl Content {
+    /// Returns the page `index`, allocating it in a hole.
+    fn page(&mut self, index: u64, limits: &FsLimits) -> VfsResult<&VfsPageRef> {
+        match self.pages.entry(index) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                limits.charge_pages(1)?;
+                match VfsPage::new() {
+                    Ok(page) => Ok(entry.insert(Arc::new(page))),
+                    Err(e) => {
+                        limits.uncharge_pages(1);
+                        Err(e)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the pages `start..end`.
+    fn free_pages(&mut self, start: u64, end: u64, limits: &FsLimits) {
+        let indices: Vec<u64> = self.pages.range(start..end).map(|(&i, _)| i).collect();
+        for index in &indices {
+            self.pages.remove(index);
+        }
+        limits.uncharge_pages(indices.len() as u64);
+    }
+
+    /// Clears the bytes `start..end`, which lie in a single page.
+    fn zero_range(&self, start: u64, end: u64) {
+        if let Some(page) = self.pages.get(&(start / PAGE)) {
+            page.zero((start % PAGE) as usize, (end - start) as usize);
+        }
+    }
+}
+
 /// The file node in the RAM filesystem.
 ///
+/// The content is kept in pages that are only allocated when written, so
+/// sparse files take no more memory than their data. Every page counts
+/// against the size limit of the filesystem.
+///
 /// It implements [`axfs_vfs::VfsNodeOps`].
 pub struct FileNode {
-    content: RwLock<Vec<u8>>,
+    content: RwLock<Content>,
+    pub(crate) meta: NodeMeta,
 }
 
 impl FileNode {
     /// To get the environment variables of the application
-    pub const fn new() -> Self {
-        Self {
-            content: RwLock::new(Vec::new()),
-        }
+    pub fn new() -> Self {
+        Self::with_limits(&FsLimits::unlimited()).unwrap()
+    }
+
+    /// Creates an empty file counted against `limits`.
+    pub(crate) fn with_limits(limits: &Arc<FsLimits>) -> VfsResult<Self> {
+        Ok(Self {
+            content: RwLock::new(Content {
+                size: 0,
+                pages: BTreeMap::new(),
+            }),
+            meta: NodeMeta::new(VfsNodePerm::default_file(), limits)?,
+        })
+    }
+}
+
+impl Default for FileNode {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl VfsNodeOps for FileNode {
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
-        Ok(VfsNodeAttr::new_file(self.content.read().len() as _, 0))
+        let content = self.content.read();
+        let blocks = content.pages.len() as u64 * (PAGE / 512);
+        let attr = VfsNodeAttr::new_file(content.size, blocks);
+        Ok(self.meta.fill(attr))
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
+    }
+
+    fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> VfsResult {
+        self.meta.chown(uid, gid);
+        Ok(())
+    }
+
+    fn chmod(&self, mode: VfsNodePerm) -> VfsResult {
+        self.meta.chmod(mode);
+        Ok(())
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
         let mut content = self.content.write();
-        if size < content.len() as u64 {
-            content.truncate(size as _);
-        } else {
-            content.resize(size as _, 0);
+        if size < content.size {
+            let limits = self.meta.limits();
+            content.free_pages(size.div_ceil(PAGE), u64::MAX, limits);
+            if size % PAGE != 0 {
+                content.zero_range(size, size.next_multiple_of(PAGE));
+            }
         }
+        content.size = size;
+        self.meta.touch_modified();
+        Ok(())
+    }
+
+    fn fsync(&self) -> VfsResult {
+        // the content only lives in memory
         Ok(())
     }
 
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
         let content = self.content.read();
-        let start = content.len().min(offset as usize);
-        let end = content.len().min(offset as usize + buf.len());
-        let src = &content[start..end];
-        buf[..src.len()].copy_from_slice(src);
-        Ok(src.len())
+        let len = content.size.saturating_sub(offset).min(buf.len() as u64) as usize;
+        let mut read = 0;
+        while read < len {
+            let pos = offset + read as u64;
+            let in_page = (pos % PAGE) as usize;
+            let dst = &mut buf[read..len.min(read + PAGE_SIZE - in_page)];
+            match content.pages.get(&(pos / PAGE)) {
+                Some(page) => {
+                    page.read(in_page, dst);
+                }
+                None => dst.fill(0),
+            }
+            read += dst.len();
+        }
+        Ok(len)
     }
 
     fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
-        let offset = offset as usize;
+        if offset.checked_add(buf.len() as u64).is_none() {
+            return Err(VfsError::InvalidInput);
+        }
+        let mut content = self.content.write();
+        let limits = self.meta.limits();
+        let mut written = 0;
+        while written < buf.len() {
+            let pos = offset + written as u64;
+            match content.page(pos / PAGE, limits) {
+                Ok(page) => written += page.write((pos % PAGE) as usize, &buf[written..]),
+                // write as much as fits before running out of space
+                Err(_) if written > 0 => break,
+                Err(e) => return Err(e),
+            }
+        }
+        content.size = content.size.max(offset + written as u64);
+        self.meta.touch_modified();
+        Ok(written)
+    }
+
+    fn allocate(&self, offset: u64, len: u64, keep_size: bool) -> VfsResult {
+        let end = offset.checked_add(len).ok_or(VfsError::InvalidInput)?;
         let mut content = self.content.write();
-        if offset + buf.len() > content.len() {
-            content.resize(offset + buf.len(), 0);
+        let limits = self.meta.limits();
+        let mut added = Vec::new();
+        for index in offset / PAGE..end.div_ceil(PAGE) {
+            if content.pages.contains_key(&index) {
+                continue;
+            }
+            if let Err(e) = content.page(index, limits).map(|_| ()) {
+                // nothing is allocated if not everything can be
+                for index in added {
+                    content.pages.remove(&index);
+                    limits.uncharge_pages(1);
+                }
+                return Err(e);
+            }
+            added.push(index);
         }
-        let dst = &mut content[offset..offset + buf.len()];
-        dst.copy_from_slice(&buf[..dst.len()]);
-        Ok(buf.len())
+        if !keep_size && end > content.size {
+            content.size = end;
+            self.meta.touch_modified();
+        }
+        Ok(())
+    }
+
+    fn punch_hole(&self, offset: u64, len: u64) -> VfsResult {
+        let end = offset.saturating_add(len);
+        let mut content = self.content.write();
+        let first = offset.div_ceil(PAGE);
+        let last = end / PAGE;
+        if first < last {
+            content.free_pages(first, last, self.meta.limits());
+        }
+        // clear what remains of the partially covered pages
+        let head_end = end.min(offset.next_multiple_of(PAGE));
+        if offset < head_end {
+            content.zero_range(offset, head_end);
+        }
+        let tail_start = offset.max(last * PAGE);
+        if head_end <= tail_start && tail_start < end {
+            content.zero_range(tail_start, end);
+        }
+        self.meta.touch_modified();
+        Ok(())
+    }
+
+    fn seek_data(&self, offset: u64, hole: bool) -> VfsResult<Option<u64>> {
+        let content = self.content.read();
+        if offset >= content.size {
+            return Ok(None);
+        }
+        let found = if hole {
+            let mut index = offset / PAGE;
+            while content.pages.contains_key(&index) {
+                index += 1;
+            }
+            Some((index * PAGE).max(offset).min(content.size))
+        } else {
+            content
+                .pages
+                .range(offset / PAGE..)
+                .next()
+                .map(|(&index, _)| (index * PAGE).max(offset))
+                .filter(|&pos| pos < content.size)
+        };
+        Ok(found)
+    }
+
+    fn get_page(&self, index: u64) -> VfsResult<VfsPageRef> {
+        let mut content = self.content.write();
+        if index >= content.size.div_ceil(PAGE) {
+            return Err(VfsError::InvalidInput);
+        }
+        content.page(index, self.meta.limits()).cloned()
     }
 
     impl_vfs_non_dir_default! {}
 }
+
+impl Drop for FileNode {
+    fn drop(&mut self) {
+        let pages = self.content.get_mut().pages.len() as u64;
+        self.meta.limits().uncharge_pages(pages);
+    }
+}
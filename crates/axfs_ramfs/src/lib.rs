@@ -9,35 +9,127 @@ extern crate alloc;
 mod dir;
 mod file;
 mod interrupts;
+mod limits;
+mod meta;
+mod symlink;
 #[cfg(test)]
 mod tests;
 
 pub use self::dir::DirNode;
 pub use self::file::FileNode;
 pub use self::interrupts::{Interrupts, INTERRUPT};
+pub use self::limits::FsLimits;
+pub use self::symlink::SymlinkNode;
 use alloc::sync::Arc;
-use axfs_vfs::{VfsNodeRef, VfsOps, VfsResult};
+use axfs_vfs::PAGE_SIZE;
+use axfs_vfs::{FileSystemInfo, VfsError, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsOps, VfsResult};
+use core::sync::atomic::{AtomicU64, Ordering};
 use spin::once::Once;
 
+/// The magic number of tmpfs reported by `statfs`.
+pub const TMPFS_MAGIC: u64 = 0x0102_1994;
+
+/// The next filesystem id to hand out.
+static NEXT_FSID: AtomicU64 = AtomicU64::new(1);
+
 /// A RAM filesystem that implements [`axfs_vfs::VfsOps`].
 pub struct RamFileSystem {
     parent: Once<VfsNodeRef>,
     root: Arc<DirNode>,
+    limits: Arc<FsLimits>,
+    magic: u64,
+    fsid: u64,
 }
 
 impl RamFileSystem {
     /// Create a new instance.
     pub fn new() -> Self {
+        let limits = FsLimits::unlimited();
         Self {
             parent: Once::new(),
-            root: DirNode::new(None),
+            root: DirNode::new(None, &limits).unwrap(),
+            limits,
+            magic: TMPFS_MAGIC,
+            fsid: NEXT_FSID.fetch_add(1, Ordering::Relaxed),
         }
     }
 
+    /// Reports the filesystem as the type with the magic number `magic` in
+    /// `statfs`, e.g. for a `proc` or `sysfs` kept in memory.
+    pub fn with_magic(mut self, magic: u64) -> Self {
+        self.magic = magic;
+        self
+    }
+
+    /// Create a new instance configured by the comma-separated mount options
+    /// of tmpfs:
+    ///
+    /// - `size=`: the maximum size of the content in bytes, rounded up to
+    ///   whole pages, optionally followed by `k`, `m` or `g`.
+    /// - `nr_blocks=`: the maximum size of the content in pages.
+    /// - `nr_inodes=`: the maximum number of nodes.
+    /// - `mode=`: the permission mode of the root directory, in octal.
+    /// - `uid=`, `gid=`: the owner of the root directory.
+    ///
+    /// A limit of `0` means unlimited, which is also the default.
+    pub fn with_options(options: &str) -> VfsResult<Self> {
+        let mut max_pages = 0;
+        let mut max_inodes = 0;
+        let mut mode = None;
+        let (mut uid, mut gid) = (None, None);
+        for option in options.split(',').filter(|option| !option.is_empty()) {
+            let (key, value) = option.split_once('=').ok_or(VfsError::InvalidInput)?;
+            let number =
+                |radix| u64::from_str_radix(value, radix).map_err(|_| VfsError::InvalidInput);
+            let id =
+                || number(10).and_then(|id| u32::try_from(id).map_err(|_| VfsError::InvalidInput));
+            match key {
+                "size" => max_pages = parse_size(value).ok_or(VfsError::InvalidInput)?,
+                "nr_blocks" => max_pages = number(10)?,
+                "nr_inodes" => max_inodes = number(10)?,
+                "mode" => mode = Some(number(8)?),
+                "uid" => uid = Some(id()?),
+                "gid" => gid = Some(id()?),
+                _ => return Err(VfsError::InvalidInput),
+            }
+        }
+        let limits = FsLimits::new(max_pages, max_inodes);
+        let root = DirNode::new(None, &limits)?;
+        if let Some(mode) = mode {
+            root.chmod(VfsNodePerm::from_bits_truncate(mode as u16))?;
+        }
+        root.chown(uid, gid)?;
+        Ok(Self {
+            parent: Once::new(),
+            root,
+            limits,
+            magic: TMPFS_MAGIC,
+            fsid: NEXT_FSID.fetch_add(1, Ordering::Relaxed),
+        })
+    }
+
     /// Returns the root directory node in [`Arc<DirNode>`](DirNode).
     pub fn root_dir_node(&self) -> Arc<DirNode> {
         self.root.clone()
     }
+
+    /// Returns the space and inodes the filesystem may use and uses.
+    pub fn limits(&self) -> &Arc<FsLimits> {
+        &self.limits
+    }
+}
+
+/// Parses a size in bytes with an optional binary suffix into a number of
+/// pages.
+fn parse_size(value: &str) -> Option<u64> {
+    let (digits, shift) = match value.as_bytes().last()? {
+        b'k' | b'K' => (&value[..value.len() - 1], 10),
+        b'm' | b'M' => (&value[..value.len() - 1], 20),
+        b'g' | b'G' => (&value[..value.len() - 1], 30),
+        _ => (value, 0),
+    };
+    let bytes = digits.parse::<u64>().ok()?.checked_mul(1 << shift)?;
+    Some(bytes.div_ceil(PAGE_SIZE as u64))
 }
 
 impl VfsOps for RamFileSystem {
@@ -50,6 +142,22 @@ impl VfsOps for RamFileSystem {
         Ok(())
     }
 
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        let mut info = FileSystemInfo::new(self.magic, PAGE_SIZE as u64);
+        // like tmpfs, a filesystem without limits reports no blocks or inodes
+        let limits = &self.limits;
+        if limits.max_pages() != u64::MAX {
+            let free = limits.max_pages().saturating_sub(limits.used_pages());
+            info.set_blocks(limits.max_pages(), free, free);
+        }
+        if limits.max_inodes() != u64::MAX {
+            let free = limits.max_inodes().saturating_sub(limits.used_inodes());
+            info.set_files(limits.max_inodes(), free);
+        }
+        info.set_fsid(self.fsid);
+        Ok(info)
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         self.root.clone()
     }
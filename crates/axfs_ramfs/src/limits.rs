@@ -0,0 +1,80 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axfs_vfs::{VfsError, VfsResult};
+
+/// The space and inodes a RAM filesystem may use, shared by all its nodes.
+///
+/// Every node takes an inode when created and gives it back when dropped, a
+/// file takes a page for each page of content it holds.
+pub struct FsLimits {
+    max_pages: u64,
+    max_inodes: u64,
+    pages: AtomicU64,
+    inodes: AtomicU64,
+}
+
+impl FsLimits {
+    /// Creates limits of `max_pages` pages of content and `max_inodes` nodes,
+    /// `0` meaning unlimited.
+    pub fn new(max_pages: u64, max_inodes: u64) -> Arc<Self> {
+        let unlimited_if_zero = |max| if max == 0 { u64::MAX } else { max };
+        Arc::new(Self {
+            max_pages: unlimited_if_zero(max_pages),
+            max_inodes: unlimited_if_zero(max_inodes),
+            pages: AtomicU64::new(0),
+            inodes: AtomicU64::new(0),
+        })
+    }
+
+    /// Creates limits that are never reached.
+    pub fn unlimited() -> Arc<Self> {
+        Self::new(0, 0)
+    }
+
+    /// Returns the maximum number of pages, `u64::MAX` if unlimited.
+    pub fn max_pages(&self) -> u64 {
+        self.max_pages
+    }
+
+    /// Returns the number of pages in use.
+    pub fn used_pages(&self) -> u64 {
+        self.pages.load(Ordering::Relaxed)
+    }
+
+    /// Returns the maximum number of inodes, `u64::MAX` if unlimited.
+    pub fn max_inodes(&self) -> u64 {
+        self.max_inodes
+    }
+
+    /// Returns the number of inodes in use.
+    pub fn used_inodes(&self) -> u64 {
+        self.inodes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn charge_pages(&self, n: u64) -> VfsResult {
+        charge(&self.pages, self.max_pages, n)
+    }
+
+    pub(crate) fn uncharge_pages(&self, n: u64) {
+        self.pages.fetch_sub(n, Ordering::Relaxed);
+    }
+
+    pub(crate) fn charge_inode(&self) -> VfsResult {
+        charge(&self.inodes, self.max_inodes, 1)
+    }
+
+    pub(crate) fn uncharge_inode(&self) {
+        self.inodes.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Adds `n` to `used`, failing with [`StorageFull`](VfsError::StorageFull) if
+/// it would exceed `max`.
+fn charge(used: &AtomicU64, max: u64, n: u64) -> VfsResult {
+    used.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+        used.checked_add(n).filter(|&total| total <= max)
+    })
+    .map(|_| ())
+    .map_err(|_| VfsError::StorageFull)
+}
@@ -0,0 +1,124 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+use axfs_vfs::{VfsNodeAttr, VfsNodePerm, VfsResult};
+use spin::RwLock;
+
+use crate::limits::FsLimits;
+
+/// The next inode number to hand out, shared by all RAM filesystems.
+static NEXT_INO: AtomicU64 = AtomicU64::new(1);
+
+/// The inode metadata kept by every node in the RAM filesystem.
+///
+/// It holds one of the inodes of the filesystem as long as the node lives.
+pub(crate) struct NodeMeta(RwLock<MetaInner>, Arc<FsLimits>);
+
+struct MetaInner {
+    perm: VfsNodePerm,
+    uid: u32,
+    gid: u32,
+    nlink: u32,
+    ino: u64,
+    atime: Duration,
+    mtime: Duration,
+    ctime: Duration,
+    btime: Duration,
+}
+
+impl NodeMeta {
+    /// Creates the metadata of a new node with the given permission, owned by
+    /// root, with one link and timestamped with the current time.
+    ///
+    /// Fails with [`StorageFull`](axfs_vfs::VfsError::StorageFull) if the
+    /// filesystem has no inode left.
+    pub fn new(perm: VfsNodePerm, limits: &Arc<FsLimits>) -> VfsResult<Self> {
+        limits.charge_inode()?;
+        let now = axfs_vfs::now();
+        let inner = RwLock::new(MetaInner {
+            perm,
+            uid: 0,
+            gid: 0,
+            nlink: 1,
+            ino: NEXT_INO.fetch_add(1, Ordering::Relaxed),
+            atime: now,
+            mtime: now,
+            ctime: now,
+            btime: now,
+        });
+        Ok(Self(inner, limits.clone()))
+    }
+
+    /// Returns the limits of the filesystem the node belongs to.
+    pub fn limits(&self) -> &Arc<FsLimits> {
+        &self.1
+    }
+
+    /// Copies the metadata into `attr`.
+    pub fn fill(&self, mut attr: VfsNodeAttr) -> VfsNodeAttr {
+        let meta = self.0.read();
+        attr.set_perm(meta.perm);
+        attr.set_owner(meta.uid, meta.gid);
+        attr.set_nlink(meta.nlink);
+        attr.set_ino(meta.ino);
+        attr.set_times(meta.atime, meta.mtime, meta.ctime);
+        attr.set_btime(meta.btime);
+        attr
+    }
+
+    /// Records a modification of the content.
+    pub fn touch_modified(&self) {
+        let mut meta = self.0.write();
+        meta.mtime = axfs_vfs::now();
+        meta.ctime = meta.mtime;
+    }
+
+    /// Records an additional directory entry referring to the node.
+    pub fn link(&self) {
+        let mut meta = self.0.write();
+        meta.nlink += 1;
+        meta.ctime = axfs_vfs::now();
+    }
+
+    /// Records the removal of a directory entry referring to the node.
+    pub fn unlink(&self) {
+        let mut meta = self.0.write();
+        meta.nlink = meta.nlink.saturating_sub(1);
+        meta.ctime = axfs_vfs::now();
+    }
+
+    pub fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) {
+        let mut meta = self.0.write();
+        if let Some(atime) = atime {
+            meta.atime = atime;
+        }
+        if let Some(mtime) = mtime {
+            meta.mtime = mtime;
+        }
+        meta.ctime = axfs_vfs::now();
+    }
+
+    pub fn chown(&self, uid: Option<u32>, gid: Option<u32>) {
+        let mut meta = self.0.write();
+        if let Some(uid) = uid {
+            meta.uid = uid;
+        }
+        if let Some(gid) = gid {
+            meta.gid = gid;
+        }
+        meta.ctime = axfs_vfs::now();
+    }
+
+    pub fn chmod(&self, perm: VfsNodePerm) {
+        let mut meta = self.0.write();
+        meta.perm = perm;
+        meta.ctime = axfs_vfs::now();
+    }
+}
+
+impl Drop for NodeMeta {
+    fn drop(&mut self) {
+        self.1.uncharge_inode();
+    }
+}
@@ -0,0 +1,66 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use axfs_vfs::{impl_vfs_non_dir_default, VfsNodeAttr, VfsNodeOps, VfsResult};
+use axfs_vfs::{VfsNodePerm, VfsNodeType};
+use core::time::Duration;
+
+use crate::limits::FsLimits;
+use crate::meta::NodeMeta;
+
+/// The symbolic link node in the RAM filesystem.
+///
+/// It implements [`axfs_vfs::VfsNodeOps`].
+pub struct SymlinkNode {
+    target: String,
+    pub(crate) meta: NodeMeta,
+}
+
+impl SymlinkNode {
+    /// Creates a symbolic link pointing to `target`.
+    pub fn new(target: &str) -> Self {
+        Self::with_limits(target, &FsLimits::unlimited()).unwrap()
+    }
+
+    /// Creates a symbolic link pointing to `target` counted against `limits`.
+    pub(crate) fn with_limits(target: &str, limits: &Arc<FsLimits>) -> VfsResult<Self> {
+        Ok(Self {
+            target: String::from(target),
+            meta: NodeMeta::new(VfsNodePerm::from_bits_truncate(0o777), limits)?,
+        })
+    }
+
+    /// Returns the path the link points to.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+}
+
+impl VfsNodeOps for SymlinkNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let attr = VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o777),
+            VfsNodeType::SymLink,
+            self.target.len() as _,
+            0,
+        );
+        Ok(self.meta.fill(attr))
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.meta.set_times(atime, mtime);
+        Ok(())
+    }
+
+    fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> VfsResult {
+        self.meta.chown(uid, gid);
+        Ok(())
+    }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        let len = buf.len().min(self.target.len());
+        buf[..len].copy_from_slice(&self.target.as_bytes()[..len]);
+        Ok(len)
+    }
+
+    impl_vfs_non_dir_default! {}
+}
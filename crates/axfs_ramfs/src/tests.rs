@@ -134,3 +134,92 @@ fn test_ramfs() {
     assert_eq!(root.remove("./foo"), Ok(()));
     assert!(ramfs.root_dir_node().get_entries().is_empty());
 }
+
+#[test]
+fn test_tmpfs_pages() {
+    const PAGE: u64 = axfs_vfs::PAGE_SIZE as u64;
+
+    let ramfs = RamFileSystem::with_options("nr_blocks=4,nr_inodes=3").unwrap();
+    let limits = ramfs.limits().clone();
+    let root = ramfs.root_dir();
+    root.create("sparse", VfsNodeType::File).unwrap();
+    let file = root.clone().lookup("sparse").unwrap();
+    assert_eq!(limits.used_inodes(), 2);
+
+    // a write past the end leaves a hole that reads as zeros
+    assert_eq!(file.write_at(2 * PAGE + 1, &[7; 4]).unwrap(), 4);
+    assert_eq!(limits.used_pages(), 1);
+    let attr = file.get_attr().unwrap();
+    assert_eq!(attr.size(), 2 * PAGE + 5);
+    assert_eq!(attr.blocks(), PAGE / 512);
+    let mut buf = [1; 8];
+    assert_eq!(file.read_at(PAGE, &mut buf).unwrap(), 8);
+    assert_eq!(buf, [0; 8]);
+    assert_eq!(file.read_at(2 * PAGE, &mut buf).unwrap(), 5);
+    assert_eq!(buf[..5], [0, 7, 7, 7, 7]);
+
+    assert_eq!(file.seek_data(0, false).unwrap(), Some(2 * PAGE));
+    assert_eq!(file.seek_data(10, true).unwrap(), Some(10));
+    assert_eq!(file.seek_data(2 * PAGE, true).unwrap(), Some(2 * PAGE + 5));
+    assert_eq!(file.seek_data(2 * PAGE + 5, false).unwrap(), None);
+
+    // punching a hole frees whole pages and clears partial ones
+    file.write_at(0, &[3; 8]).unwrap();
+    assert_eq!(limits.used_pages(), 2);
+    file.punch_hole(2, 2 * PAGE).unwrap();
+    assert_eq!(limits.used_pages(), 2);
+    assert_eq!(file.read_at(0, &mut buf).unwrap(), 8);
+    assert_eq!(buf, [3, 3, 0, 0, 0, 0, 0, 0]);
+    file.punch_hole(0, PAGE).unwrap();
+    assert_eq!(limits.used_pages(), 1);
+    assert_eq!(file.seek_data(0, false).unwrap(), Some(2 * PAGE));
+
+    // fallocate allocates everything or nothing
+    assert_eq!(
+        file.allocate(0, 5 * PAGE, false).err(),
+        Some(VfsError::StorageFull)
+    );
+    assert_eq!(limits.used_pages(), 1);
+    assert_eq!(file.get_attr().unwrap().size(), 2 * PAGE + 5);
+    file.allocate(0, 4 * PAGE, true).unwrap();
+    assert_eq!(limits.used_pages(), 4);
+    assert_eq!(file.get_attr().unwrap().size(), 2 * PAGE + 5);
+    assert_eq!(file.seek_data(0, true).unwrap(), Some(2 * PAGE + 5));
+
+    // the filesystem is full
+    root.create("full", VfsNodeType::File).unwrap();
+    let full = root.clone().lookup("full").unwrap();
+    assert_eq!(full.write_at(0, &[1]).err(), Some(VfsError::StorageFull));
+    assert_eq!(
+        root.create("inode", VfsNodeType::File).err(),
+        Some(VfsError::StorageFull)
+    );
+
+    // truncating gives the pages back, a write only stores what fits
+    file.truncate(PAGE + 1).unwrap();
+    assert_eq!(limits.used_pages(), 2);
+    assert_eq!(full.write_at(PAGE - 2, &[1; 4]).unwrap(), 4);
+    assert_eq!(limits.used_pages(), 4);
+    assert_eq!(full.write_at(2 * PAGE - 2, &[1; 4]).unwrap(), 2);
+    assert_eq!(full.get_attr().unwrap().size(), 2 * PAGE);
+
+    drop((file, full));
+    root.remove("sparse").unwrap();
+    root.remove("full").unwrap();
+    assert_eq!(limits.used_pages(), 0);
+    assert_eq!(limits.used_inodes(), 1);
+}
+
+#[test]
+fn test_tmpfs_options() {
+    let ramfs = RamFileSystem::with_options("size=5k,mode=1777,uid=3").unwrap();
+    assert_eq!(ramfs.limits().max_pages(), 2);
+    assert_eq!(ramfs.limits().max_inodes(), u64::MAX);
+    let attr = ramfs.root_dir().get_attr().unwrap();
+    assert_eq!(attr.perm().bits(), 0o1777);
+    assert_eq!(attr.uid(), 3);
+
+    assert!(RamFileSystem::with_options("size=1x").is_err());
+    assert!(RamFileSystem::with_options("bogus=1").is_err());
+    assert!(RamFileSystem::with_options("mode").is_err());
+}
@@ -0,0 +1,17 @@
+use core::time::Duration;
+use spin::Once;
+
+static CLOCK: Once<fn() -> Duration> = Once::new();
+
+/// Installs the clock used to timestamp nodes.
+///
+/// Only the first call takes effect.
+pub fn set_clock(clock: fn() -> Duration) {
+    CLOCK.call_once(|| clock);
+}
+
+/// Returns the current time of the installed clock, or zero if no clock has
+/// been installed yet.
+pub fn now() -> Duration {
+    CLOCK.get().map_or(Duration::ZERO, |clock| clock())
+}
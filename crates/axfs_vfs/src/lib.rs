@@ -1,8 +1,8 @@
 //! Virtual filesystem interfaces used by [ArceOS](https://github.com/rcore-os/arceos).
 //!
-//! A filesystem is a set of files and directories (symbol links are not
-//! supported currently), collectively referred to as **nodes**, which are
-//! conceptually similar to [inodes] in Linux. A file system needs to implement
+//! A filesystem is a set of files, directories and symbolic links,
+//! collectively referred to as **nodes**, which are conceptually similar to
+//! [inodes] in Linux. A file system needs to implement
 //! the [`VfsOps`] trait, its files and directories need to implement the
 //! [`VfsNodeOps`] trait.
 //!
@@ -22,15 +22,28 @@
 //! | [`open()`](VfsNodeOps::open) | Do something when the node is opened | both |
 //! | [`release()`](VfsNodeOps::release) | Do something when the node is closed | both |
 //! | [`get_attr()`](VfsNodeOps::get_attr) | Get the attributes of the node | both |
+//! | [`set_times()`](VfsNodeOps::set_times) | Set the access and modification times | both |
+//! | [`chown()`](VfsNodeOps::chown) | Change the owner and group of the node | both |
+//! | [`chmod()`](VfsNodeOps::chmod) | Change the permission mode of the node | both |
 //! | [`read_at()`](VfsNodeOps::read_at) | Read data from the file | file |
 //! | [`write_at()`](VfsNodeOps::write_at) | Write data to the file | file |
 //! | [`fsync()`](VfsNodeOps::fsync) | Synchronize the file data to disk | file |
 //! | [`truncate()`](VfsNodeOps::truncate) | Truncate the file | file |
+//! | [`allocate()`](VfsNodeOps::allocate) | Allocate space for a range of the file | file |
+//! | [`punch_hole()`](VfsNodeOps::punch_hole) | Deallocate a range of the file | file |
+//! | [`seek_data()`](VfsNodeOps::seek_data) | Find the next data or hole in the file | file |
+//! | [`get_page()`](VfsNodeOps::get_page) | Get a page of the file to map it | file |
+//! | [`readlink()`](VfsNodeOps::readlink) | Read the target of the symbolic link | symlink |
 //! | [`parent()`](VfsNodeOps::parent) | Get the parent directory | directory |
 //! | [`lookup()`](VfsNodeOps::lookup) | Lookup the node with the given path | directory |
 //! | [`create()`](VfsNodeOps::create) | Create a new node with the given path | directory |
 //! | [`remove()`](VfsNodeOps::remove) | Remove the node with the given path | directory |
 //! | [`read_dir()`](VfsNodeOps::read_dir) | Read directory entries | directory |
+//! | [`symlink()`](VfsNodeOps::symlink) | Create a symbolic link with the given path | directory |
+//! | [`link()`](VfsNodeOps::link) | Create a hard link with the given path | directory |
+//!
+//! Timestamps in [`VfsNodeAttr`] are durations since the epoch of the clock
+//! installed by [`set_clock()`], filesystems read it through [`now()`].
 //!
 //! [inodes]: https://en.wikipedia.org/wiki/Inode
 
@@ -38,15 +51,21 @@
 
 extern crate alloc;
 
+mod clock;
 mod macros;
+mod page;
 mod structs;
 
 pub mod path;
 
 use alloc::sync::Arc;
 use axerrno::{ax_err, AxError, AxResult};
+use core::time::Duration;
 
-pub use self::structs::{FileSystemInfo, VfsDirEntry, VfsNodeAttr, VfsNodePerm, VfsNodeType};
+pub use self::clock::{now, set_clock};
+pub use self::page::{VfsPage, VfsPageRef, PAGE_SIZE};
+pub use self::structs::{FileSystemFlags, FileSystemInfo};
+pub use self::structs::{VfsDirEntry, VfsNodeAttr, VfsNodePerm, VfsNodeType};
 
 /// A wrapper of [`Arc<dyn VfsNodeOps>`].
 pub type VfsNodeRef = Arc<dyn VfsNodeOps>;
@@ -100,6 +119,26 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Set the access and modification times of the node.
+    ///
+    /// A `None` leaves the corresponding time unchanged. The status change
+    /// time is updated to the current time.
+    fn set_times(&self, _atime: Option<Duration>, _mtime: Option<Duration>) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Change the owner and group of the node.
+    ///
+    /// A `None` leaves the corresponding id unchanged.
+    fn chown(&self, _uid: Option<u32>, _gid: Option<u32>) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Change the permission mode of the node.
+    fn chmod(&self, _mode: VfsNodePerm) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
     // file operations:
 
     /// Read data from the file at the given offset.
@@ -122,6 +161,56 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(InvalidInput)
     }
 
+    /// Allocate the storage for the bytes `offset..offset + len` of the file,
+    /// so that writing them later does not fail for lack of space.
+    ///
+    /// The file grows to cover the range unless `keep_size` is set.
+    fn allocate(&self, _offset: u64, _len: u64, _keep_size: bool) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Deallocate the storage of the bytes `offset..offset + len` of the file,
+    /// which read as zeros afterwards. The size of the file does not change.
+    fn punch_hole(&self, _offset: u64, _len: u64) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Find the first offset not before `offset` that starts data, or a hole
+    /// if `hole` is set.
+    ///
+    /// The end of the file counts as a hole. Return `None` if `offset` is past
+    /// the end of the file, or if there is no more data.
+    fn seek_data(&self, offset: u64, hole: bool) -> VfsResult<Option<u64>> {
+        let size = self.get_attr()?.size();
+        if offset >= size {
+            Ok(None)
+        } else if hole {
+            Ok(Some(size))
+        } else {
+            Ok(Some(offset))
+        }
+    }
+
+    /// Get the page holding the bytes from `index * PAGE_SIZE` of the file,
+    /// allocating it if needed.
+    ///
+    /// A file whose content lives in memory returns its own page, so that
+    /// shared mappings of the file see and make the same changes as `read_at`
+    /// and `write_at`.
+    fn get_page(&self, _index: u64) -> VfsResult<VfsPageRef> {
+        ax_err!(Unsupported)
+    }
+
+    // symbolic link operations:
+
+    /// Read the target of the symbolic link into `buf`.
+    ///
+    /// Return the number of bytes read, the target is truncated if `buf` is
+    /// too small.
+    fn readlink(&self, _buf: &mut [u8]) -> VfsResult<usize> {
+        ax_err!(InvalidInput)
+    }
+
     // directory operations:
 
     /// Get the parent directory of this directory.
@@ -160,6 +249,20 @@ pub trait VfsNodeOps: Send + Sync {
         ax_err!(Unsupported)
     }
 
+    /// Create a symbolic link with the given `path` in the directory, which
+    /// points to `target`.
+    ///
+    /// `target` is stored as is, it is not resolved.
+    fn symlink(&self, _path: &str, _target: &str) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
+    /// Create a hard link with the given `path` in the directory, which refers
+    /// to the existing node `src` on the same filesystem.
+    fn link(&self, _path: &str, _src: &VfsNodeRef) -> VfsResult {
+        ax_err!(Unsupported)
+    }
+
     /// Convert `&self` to [`&dyn Any`][1] that can use
     /// [`Any::downcast_ref`][2].
     ///
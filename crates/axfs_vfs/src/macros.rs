@@ -21,6 +21,10 @@ macro_rules! impl_vfs_dir_default {
             $crate::__priv::ax_err!(IsADirectory)
         }
 
+        fn seek_data(&self, _offset: u64, _hole: bool) -> $crate::VfsResult<Option<u64>> {
+            $crate::__priv::ax_err!(IsADirectory)
+        }
+
         #[inline]
         fn as_any(&self) -> &dyn core::any::Any {
             self
@@ -50,6 +54,14 @@ macro_rules! impl_vfs_non_dir_default {
             $crate::__priv::ax_err!(NotADirectory)
         }
 
+        fn symlink(&self, _path: &str, _target: &str) -> $crate::VfsResult {
+            $crate::__priv::ax_err!(NotADirectory)
+        }
+
+        fn link(&self, _path: &str, _src: &$crate::VfsNodeRef) -> $crate::VfsResult {
+            $crate::__priv::ax_err!(NotADirectory)
+        }
+
         fn read_dir(
             &self,
             _start_idx: usize,
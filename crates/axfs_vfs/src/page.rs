@@ -0,0 +1,67 @@
+use alloc::alloc::{alloc_zeroed, dealloc, Layout};
+use alloc::sync::Arc;
+use core::ptr::NonNull;
+
+use crate::{VfsError, VfsResult};
+
+/// The size of a [`VfsPage`].
+pub const PAGE_SIZE: usize = 0x1000;
+
+/// A page-aligned page of file content kept in memory.
+///
+/// The content is not protected by any lock: the filesystem serializes its own
+/// accesses, while a page mapped into an address space is also accessed
+/// directly by the user program, as a shared mapping is on any system.
+pub struct VfsPage(NonNull<u8>);
+
+unsafe impl Send for VfsPage {}
+unsafe impl Sync for VfsPage {}
+
+/// A reference-counted [`VfsPage`], shared by a file and the address spaces
+/// mapping it.
+pub type VfsPageRef = Arc<VfsPage>;
+
+const LAYOUT: Layout = match Layout::from_size_align(PAGE_SIZE, PAGE_SIZE) {
+    Ok(layout) => layout,
+    Err(_) => panic!("invalid page layout"),
+};
+
+impl VfsPage {
+    /// Allocates a zero-filled page.
+    pub fn new() -> VfsResult<Self> {
+        let ptr = unsafe { alloc_zeroed(LAYOUT) };
+        NonNull::new(ptr).map(Self).ok_or(VfsError::NoMemory)
+    }
+
+    /// Returns the address of the page.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.0.as_ptr()
+    }
+
+    /// Copies the bytes at `offset` in the page into `buf`, as many as fit in
+    /// both.
+    pub fn read(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(PAGE_SIZE.saturating_sub(offset));
+        unsafe { core::ptr::copy_nonoverlapping(self.as_ptr().add(offset), buf.as_mut_ptr(), len) };
+        len
+    }
+
+    /// Copies `buf` to `offset` in the page, as many bytes as fit in the page.
+    pub fn write(&self, offset: usize, buf: &[u8]) -> usize {
+        let len = buf.len().min(PAGE_SIZE.saturating_sub(offset));
+        unsafe { core::ptr::copy_nonoverlapping(buf.as_ptr(), self.as_ptr().add(offset), len) };
+        len
+    }
+
+    /// Clears `len` bytes at `offset` in the page, as many as fit in the page.
+    pub fn zero(&self, offset: usize, len: usize) {
+        let len = len.min(PAGE_SIZE.saturating_sub(offset));
+        unsafe { core::ptr::write_bytes(self.as_ptr().add(offset), 0, len) };
+    }
+}
+
+impl Drop for VfsPage {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.0.as_ptr(), LAYOUT) }
+    }
+}
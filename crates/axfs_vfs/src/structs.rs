@@ -1,8 +1,51 @@
-/// Filesystem attributes.
+use core::time::Duration;
+
+/// Filesystem attributes, as reported by `statfs`.
 ///
-/// Currently not used.
-#[non_exhaustive]
-pub struct FileSystemInfo;
+/// Block counts are in units of the block size. A filesystem without limits
+/// reports zero blocks and inodes, as pseudo filesystems do on Linux.
+#[derive(Debug, Clone, Copy)]
+pub struct FileSystemInfo {
+    /// Magic number of the filesystem type, e.g. `0xef53` for ext4.
+    fs_type: u64,
+    /// Size of a block, in bytes.
+    block_size: u64,
+    /// Total number of blocks.
+    blocks: u64,
+    /// Number of free blocks.
+    blocks_free: u64,
+    /// Number of free blocks available to unprivileged users.
+    blocks_avail: u64,
+    /// Total number of inodes.
+    files: u64,
+    /// Number of free inodes.
+    files_free: u64,
+    /// Filesystem id.
+    fsid: u64,
+    /// Maximum length of file names.
+    name_len: u64,
+    /// Mount flags.
+    flags: FileSystemFlags,
+}
+
+bitflags::bitflags! {
+    /// Mount flags of a filesystem, the `ST_*` flags of `statvfs`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct FileSystemFlags: u64 {
+        /// Mounted read-only.
+        const RDONLY = 0x1;
+        /// Set-user-id and set-group-id bits are ignored.
+        const NOSUID = 0x2;
+        /// Device files cannot be accessed.
+        const NODEV = 0x4;
+        /// Programs cannot be executed.
+        const NOEXEC = 0x8;
+        /// Writes are synced immediately.
+        const SYNCHRONOUS = 0x10;
+        /// Access times are not updated.
+        const NOATIME = 0x400;
+    }
+}
 
 /// Node (file/directory) attributes.
 #[allow(dead_code)]
@@ -16,12 +59,37 @@ pub struct VfsNodeAttr {
     size: u64,
     /// Number of 512B blocks allocated.
     blocks: u64,
+    /// Owner user id.
+    uid: u32,
+    /// Owner group id.
+    gid: u32,
+    /// Number of hard links.
+    nlink: u32,
+    /// Inode number, `0` if the filesystem does not provide one.
+    ino: u64,
+    /// Device number of a device node.
+    rdev: u64,
+    /// Time of last access.
+    atime: Duration,
+    /// Time of last modification of the content.
+    mtime: Duration,
+    /// Time of last change of the content or the attributes.
+    ctime: Duration,
+    /// Time of creation, `None` if the filesystem does not record it.
+    btime: Option<Duration>,
 }
 
 bitflags::bitflags! {
     /// Node (file/directory) permission mode.
     #[derive(Debug, Clone, Copy)]
     pub struct VfsNodePerm: u16 {
+        /// Set user id on execution.
+        const SET_UID = 0o4000;
+        /// Set group id on execution.
+        const SET_GID = 0o2000;
+        /// Restricted deletion in a directory.
+        const STICKY = 0o1000;
+
         /// Owner has read permission.
         const OWNER_READ = 0o400;
         /// Owner has write permission.
@@ -199,34 +267,35 @@ impl VfsNodeType {
 impl VfsNodeAttr {
     /// Creates a new `VfsNodeAttr` with the given permission mode, type, size
     /// and number of blocks.
+    ///
+    /// The node is owned by root, has one link and all timestamps are zero.
     pub const fn new(mode: VfsNodePerm, ty: VfsNodeType, size: u64, blocks: u64) -> Self {
         Self {
             mode,
             ty,
             size,
             blocks,
+            uid: 0,
+            gid: 0,
+            nlink: 1,
+            ino: 0,
+            rdev: 0,
+            atime: Duration::ZERO,
+            mtime: Duration::ZERO,
+            ctime: Duration::ZERO,
+            btime: None,
         }
     }
 
     /// Creates a new `VfsNodeAttr` for a file, with the default file permission.
     pub const fn new_file(size: u64, blocks: u64) -> Self {
-        Self {
-            mode: VfsNodePerm::default_file(),
-            ty: VfsNodeType::File,
-            size,
-            blocks,
-        }
+        Self::new(VfsNodePerm::default_file(), VfsNodeType::File, size, blocks)
     }
 
     /// Creates a new `VfsNodeAttr` for a directory, with the default directory
     /// permission.
     pub const fn new_dir(size: u64, blocks: u64) -> Self {
-        Self {
-            mode: VfsNodePerm::default_dir(),
-            ty: VfsNodeType::Dir,
-            size,
-            blocks,
-        }
+        Self::new(VfsNodePerm::default_dir(), VfsNodeType::Dir, size, blocks)
     }
 
     /// Returns the size of the node.
@@ -263,6 +332,190 @@ impl VfsNodeAttr {
     pub const fn is_dir(&self) -> bool {
         self.ty.is_dir()
     }
+
+    /// Whether the node is a symbolic link.
+    pub const fn is_symlink(&self) -> bool {
+        self.ty.is_symlink()
+    }
+
+    /// Returns the owner user id of the node.
+    pub const fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns the owner group id of the node.
+    pub const fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Sets the owner user id and group id of the node.
+    pub fn set_owner(&mut self, uid: u32, gid: u32) {
+        self.uid = uid;
+        self.gid = gid;
+    }
+
+    /// Returns the number of hard links to the node.
+    pub const fn nlink(&self) -> u32 {
+        self.nlink
+    }
+
+    /// Sets the number of hard links to the node.
+    pub fn set_nlink(&mut self, nlink: u32) {
+        self.nlink = nlink
+    }
+
+    /// Returns the inode number of the node, `0` if unknown.
+    pub const fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    /// Sets the inode number of the node.
+    pub fn set_ino(&mut self, ino: u64) {
+        self.ino = ino
+    }
+
+    /// Returns the device number of a device node.
+    pub const fn rdev(&self) -> u64 {
+        self.rdev
+    }
+
+    /// Sets the device number of a device node.
+    pub fn set_rdev(&mut self, rdev: u64) {
+        self.rdev = rdev
+    }
+
+    /// Returns the time of last access.
+    pub const fn atime(&self) -> Duration {
+        self.atime
+    }
+
+    /// Returns the time of last modification.
+    pub const fn mtime(&self) -> Duration {
+        self.mtime
+    }
+
+    /// Returns the time of last status change.
+    pub const fn ctime(&self) -> Duration {
+        self.ctime
+    }
+
+    /// Sets the access, modification and status change times of the node.
+    pub fn set_times(&mut self, atime: Duration, mtime: Duration, ctime: Duration) {
+        self.atime = atime;
+        self.mtime = mtime;
+        self.ctime = ctime;
+    }
+
+    /// Returns the time of creation, `None` if the filesystem does not record
+    /// it.
+    pub const fn btime(&self) -> Option<Duration> {
+        self.btime
+    }
+
+    /// Sets the time of creation of the node.
+    pub fn set_btime(&mut self, btime: Duration) {
+        self.btime = Some(btime)
+    }
+}
+
+impl FileSystemInfo {
+    /// Creates a new `FileSystemInfo` of the filesystem type with the magic
+    /// number `fs_type` and blocks of `block_size` bytes.
+    ///
+    /// The filesystem has no blocks or inodes, no id, no flags and names of up
+    /// to 255 bytes.
+    pub const fn new(fs_type: u64, block_size: u64) -> Self {
+        Self {
+            fs_type,
+            block_size,
+            blocks: 0,
+            blocks_free: 0,
+            blocks_avail: 0,
+            files: 0,
+            files_free: 0,
+            fsid: 0,
+            name_len: 255,
+            flags: FileSystemFlags::empty(),
+        }
+    }
+
+    /// Returns the magic number of the filesystem type.
+    pub const fn fs_type(&self) -> u64 {
+        self.fs_type
+    }
+
+    /// Returns the size of a block, in bytes.
+    pub const fn block_size(&self) -> u64 {
+        self.block_size
+    }
+
+    /// Returns the total number of blocks.
+    pub const fn blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    /// Returns the number of free blocks.
+    pub const fn blocks_free(&self) -> u64 {
+        self.blocks_free
+    }
+
+    /// Returns the number of free blocks available to unprivileged users.
+    pub const fn blocks_avail(&self) -> u64 {
+        self.blocks_avail
+    }
+
+    /// Sets the total, free and available numbers of blocks.
+    pub fn set_blocks(&mut self, blocks: u64, free: u64, avail: u64) {
+        self.blocks = blocks;
+        self.blocks_free = free;
+        self.blocks_avail = avail;
+    }
+
+    /// Returns the total number of inodes.
+    pub const fn files(&self) -> u64 {
+        self.files
+    }
+
+    /// Returns the number of free inodes.
+    pub const fn files_free(&self) -> u64 {
+        self.files_free
+    }
+
+    /// Sets the total and free numbers of inodes.
+    pub fn set_files(&mut self, files: u64, free: u64) {
+        self.files = files;
+        self.files_free = free;
+    }
+
+    /// Returns the filesystem id.
+    pub const fn fsid(&self) -> u64 {
+        self.fsid
+    }
+
+    /// Sets the filesystem id.
+    pub fn set_fsid(&mut self, fsid: u64) {
+        self.fsid = fsid
+    }
+
+    /// Returns the maximum length of file names.
+    pub const fn name_len(&self) -> u64 {
+        self.name_len
+    }
+
+    /// Sets the maximum length of file names.
+    pub fn set_name_len(&mut self, name_len: u64) {
+        self.name_len = name_len
+    }
+
+    /// Returns the mount flags.
+    pub const fn flags(&self) -> FileSystemFlags {
+        self.flags
+    }
+
+    /// Sets the mount flags.
+    pub fn set_flags(&mut self, flags: FileSystemFlags) {
+        self.flags = flags
+    }
 }
 
 impl VfsDirEntry {
@@ -28,6 +28,10 @@
 
 #![no_std]
 
+mod privilege;
+
+pub use privilege::Privileges;
+
 bitflags::bitflags! {
     /// Capabilities (access rights).
     #[derive(Default, Debug, Clone, Copy)]
@@ -41,6 +45,32 @@ bitflags::bitflags! {
     }
 }
 
+impl Cap {
+    /// Convert a 3-bit `rwx` group of a Unix file mode into access rights.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use capability::Cap;
+    ///
+    /// assert_eq!(Cap::from_mode_bits(0o6).bits(), (Cap::READ | Cap::WRITE).bits());
+    /// assert_eq!(Cap::from_mode_bits(0o1).bits(), Cap::EXECUTE.bits());
+    /// ```
+    pub const fn from_mode_bits(bits: u32) -> Self {
+        let mut cap = Self::empty();
+        if bits & 0o4 != 0 {
+            cap = cap.union(Self::READ);
+        }
+        if bits & 0o2 != 0 {
+            cap = cap.union(Self::WRITE);
+        }
+        if bits & 0o1 != 0 {
+            cap = cap.union(Self::EXECUTE);
+        }
+        cap
+    }
+}
+
 /// Error type for capability violation.
 #[derive(Debug, Default, Eq, PartialEq)]
 #[non_exhaustive]
@@ -0,0 +1,113 @@
+//! Privileges held by a process, in the style of POSIX capabilities.
+//!
+//! While [`Cap`](crate::Cap) describes the access rights attached to a single
+//! object, [`Privileges`] describes which permission checks a subject is
+//! allowed to bypass. The bit positions match the Linux capability numbers, so
+//! a set can be exchanged with user space through `capget`/`capset` unchanged.
+
+bitflags::bitflags! {
+    /// A set of privileges, numbered as the Linux capabilities.
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Privileges: u64 {
+        /// Make arbitrary changes to file UIDs and GIDs.
+        const CHOWN = 1 << 0;
+        /// Bypass file read, write, and execute permission checks.
+        const DAC_OVERRIDE = 1 << 1;
+        /// Bypass file read permission checks and directory read and execute
+        /// permission checks.
+        const DAC_READ_SEARCH = 1 << 2;
+        /// Bypass permission checks on operations that normally require the
+        /// file system UID of the process to match the UID of the file.
+        const FOWNER = 1 << 3;
+        /// Don't clear set-user-ID and set-group-ID mode bits when a file is
+        /// modified.
+        const FSETID = 1 << 4;
+        /// Bypass permission checks for sending signals.
+        const KILL = 1 << 5;
+        /// Make arbitrary manipulations of process GIDs and supplementary GID
+        /// list.
+        const SETGID = 1 << 6;
+        /// Make arbitrary manipulations of process UIDs.
+        const SETUID = 1 << 7;
+        /// Modify the capability sets of the process.
+        const SETPCAP = 1 << 8;
+        /// Set the immutable and append-only file attributes.
+        const LINUX_IMMUTABLE = 1 << 9;
+        /// Bind a socket to privileged ports (port numbers less than 1024).
+        const NET_BIND_SERVICE = 1 << 10;
+        /// Make socket broadcasts, and listen to multicasts.
+        const NET_BROADCAST = 1 << 11;
+        /// Perform various network-related operations.
+        const NET_ADMIN = 1 << 12;
+        /// Use RAW and PACKET sockets.
+        const NET_RAW = 1 << 13;
+        /// Lock memory.
+        const IPC_LOCK = 1 << 14;
+        /// Bypass permission checks for operations on System V IPC objects.
+        const IPC_OWNER = 1 << 15;
+        /// Load and unload kernel modules.
+        const SYS_MODULE = 1 << 16;
+        /// Perform I/O port operations.
+        const SYS_RAWIO = 1 << 17;
+        /// Use `chroot`.
+        const SYS_CHROOT = 1 << 18;
+        /// Trace arbitrary processes using `ptrace`.
+        const SYS_PTRACE = 1 << 19;
+        /// Use `acct`.
+        const SYS_PACCT = 1 << 20;
+        /// Perform a range of system administration operations.
+        const SYS_ADMIN = 1 << 21;
+        /// Use `reboot`.
+        const SYS_BOOT = 1 << 22;
+        /// Raise the nice value and change the scheduling policy of arbitrary
+        /// processes.
+        const SYS_NICE = 1 << 23;
+        /// Override resource limits.
+        const SYS_RESOURCE = 1 << 24;
+        /// Set the system clock.
+        const SYS_TIME = 1 << 25;
+        /// Use `vhangup`.
+        const SYS_TTY_CONFIG = 1 << 26;
+        /// Create special files using `mknod`.
+        const MKNOD = 1 << 27;
+        /// Establish leases on arbitrary files.
+        const LEASE = 1 << 28;
+        /// Write records to the kernel auditing log.
+        const AUDIT_WRITE = 1 << 29;
+        /// Configure the kernel auditing subsystem.
+        const AUDIT_CONTROL = 1 << 30;
+        /// Set file capabilities.
+        const SETFCAP = 1 << 31;
+        /// Override Mandatory Access Control.
+        const MAC_OVERRIDE = 1 << 32;
+        /// Allow MAC configuration or state changes.
+        const MAC_ADMIN = 1 << 33;
+        /// Perform privileged `syslog` operations.
+        const SYSLOG = 1 << 34;
+        /// Trigger something that will wake up the system.
+        const WAKE_ALARM = 1 << 35;
+        /// Employ features that can block system suspend.
+        const BLOCK_SUSPEND = 1 << 36;
+        /// Allow reading the audit log via a multicast netlink socket.
+        const AUDIT_READ = 1 << 37;
+        /// Employ performance monitoring mechanisms.
+        const PERFMON = 1 << 38;
+        /// Employ privileged BPF operations.
+        const BPF = 1 << 39;
+        /// Checkpoint and restore processes.
+        const CHECKPOINT_RESTORE = 1 << 40;
+    }
+}
+
+impl Privileges {
+    /// Privileges that only matter for file system access, which follow the
+    /// file system UID rather than the effective UID.
+    pub const FS_MASK: Self = Self::CHOWN
+        .union(Self::DAC_OVERRIDE)
+        .union(Self::DAC_READ_SEARCH)
+        .union(Self::FOWNER)
+        .union(Self::FSETID)
+        .union(Self::LINUX_IMMUTABLE)
+        .union(Self::MKNOD)
+        .union(Self::MAC_OVERRIDE);
+}
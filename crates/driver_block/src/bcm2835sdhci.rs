@@ -48,28 +48,30 @@ impl BaseDriverOps for SDHCIDriver {
 
 impl BlockDriverOps for SDHCIDriver {
     fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
-        if buf.len() < BLOCK_SIZE {
+        if buf.len() < BLOCK_SIZE || buf.len() % BLOCK_SIZE != 0 {
             return Err(DevError::InvalidParam);
         }
         let (prefix, aligned_buf, suffix) = unsafe { buf.align_to_mut::<u32>() };
         if !prefix.is_empty() || !suffix.is_empty() {
             return Err(DevError::InvalidParam);
         }
+        let count = (buf.len() / BLOCK_SIZE) as u32;
         self.0
-            .read_block(block_id as u32, 1, aligned_buf)
+            .read_block(block_id as u32, count, aligned_buf)
             .map_err(deal_sdhci_err)
     }
 
     fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
-        if buf.len() < BLOCK_SIZE {
+        if buf.len() < BLOCK_SIZE || buf.len() % BLOCK_SIZE != 0 {
             return Err(DevError::Io);
         }
         let (prefix, aligned_buf, suffix) = unsafe { buf.align_to::<u32>() };
         if !prefix.is_empty() || !suffix.is_empty() {
             return Err(DevError::InvalidParam);
         }
+        let count = (buf.len() / BLOCK_SIZE) as u32;
         self.0
-            .write_block(block_id as u32, 1, aligned_buf)
+            .write_block(block_id as u32, count, aligned_buf)
             .map_err(deal_sdhci_err)
     }
     fn flush(&mut self) -> DevResult {
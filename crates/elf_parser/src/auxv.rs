@@ -14,6 +14,7 @@ const AT_BASE: u8 = 7;
 #[allow(unused)]
 const AT_ENTRY: u8 = 9;
 const AT_RANDOM: u8 = 25;
+const AT_SYSINFO_EHDR: u8 = 33;
 
 /// To parse the elf file and get the auxv vectors
 ///
@@ -21,9 +22,11 @@ const AT_RANDOM: u8 = 25;
 ///
 /// * `elf` - The elf file
 /// * `elf_base_addr` - The base address of the elf file if the file will be loaded to the memory
+/// * `vdso_base` - The address where the vDSO image is mapped, if any
 pub fn get_auxv_vector(
     elf: &xmas_elf::ElfFile,
     elf_base_addr: Option<usize>,
+    vdso_base: Option<usize>,
 ) -> BTreeMap<u8, usize> {
     // Some elf will load ELF Header (offset == 0) to vaddr 0. In that case, base_addr will be added to all the LOAD.
     let elf_header_vaddr: usize = if let Some(header) = elf
@@ -55,6 +58,9 @@ pub fn get_auxv_vector(
     map.insert(AT_PHNUM, elf.header.pt2.ph_count() as usize);
     map.insert(AT_RANDOM, 0);
     map.insert(AT_PAGESZ, PAGE_SIZE_4K);
+    if let Some(vdso_base) = vdso_base {
+        map.insert(AT_SYSINFO_EHDR, vdso_base);
+    }
     map
 }
 /// To get the app stack and the information on the stack from the ELF file
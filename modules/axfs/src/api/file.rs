@@ -3,6 +3,7 @@ use core::fmt;
 
 use super::FileExt;
 use crate::fops;
+use axfs_vfs::VfsPageRef;
 
 /// A structure representing a type of file with accessors for each file type.
 /// It is returned by [`Metadata::file_type`] method.
@@ -184,6 +185,34 @@ impl File {
     pub fn truncate(&mut self, len: usize) -> Result<()> {
         self.inner.truncate(len as u64)
     }
+
+    /// Allocates the storage for the bytes `offset..offset + len`, growing
+    /// the file to cover them unless `keep_size` is set.
+    pub fn allocate(&self, offset: u64, len: u64, keep_size: bool) -> Result<()> {
+        self.inner.allocate(offset, len, keep_size)
+    }
+
+    /// Deallocates the bytes `offset..offset + len`, which read as zeros
+    /// afterwards.
+    pub fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        self.inner.punch_hole(offset, len)
+    }
+
+    /// Moves the cursor to the next data from `offset`, or to the next hole
+    /// if `hole` is set. Returns the new position, or `None` if there is none.
+    pub fn seek_data(&mut self, offset: u64, hole: bool) -> Result<Option<u64>> {
+        self.inner.seek_data(offset, hole)
+    }
+
+    /// Identifies the underlying node, as file locks do.
+    pub fn node_id(&self) -> usize {
+        self.inner.node_id()
+    }
+
+    /// Reports to the filesystem watchers that the file was closed.
+    pub fn notify_close(&self) {
+        self.inner.notify_close()
+    }
 }
 
 impl Read for File {
@@ -220,4 +249,8 @@ impl FileExt for File {
     fn executable(&self) -> bool {
         self.inner.executable()
     }
+
+    fn get_page(&self, index: u64) -> Result<VfsPageRef> {
+        self.inner.get_page(index)
+    }
 }
@@ -2,17 +2,37 @@
 
 mod dir;
 mod file;
+mod notify;
 
 pub mod port;
 
 pub use self::dir::{DirBuilder, DirEntry, ReadDir};
 pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
+pub use self::notify::{PollNotifier, PollWaker};
+pub use crate::block_cache::{
+    write_back_expired, BlockCacheStats, DIRTY_EXPIRE, WRITEBACK_INTERVAL,
+};
+pub use crate::file_lock::{
+    cancel_lock_wait, release_locks, release_owner_locks, set_lock, test_lock, unlock_range,
+    FileLock, LockKind, LockOwner,
+};
+pub use crate::fsnotify::{FsEvent, FsWatcher};
+#[cfg(feature = "devfs")]
+pub use crate::loop_dev::{
+    add_loop_device, free_loop_device, is_loop_control, loop_device_of, remove_loop_device,
+    LoopBacking, LoopDevice, MAX_LOOP_DEVICES,
+};
+pub use crate::mounts::FileSystemCtor;
+#[cfg(feature = "monolithic")]
+pub use crate::root::MountTableIf;
+pub use crate::root::{MountInfo, MountTable};
 use axerrno::AxResult;
 use axfs_vfs::VfsNodeRef;
+pub use axfs_vfs::{FileSystemFlags, FileSystemInfo, VfsPageRef};
 pub use axio::{Read, Seek, SeekFrom, Write};
 pub use port::*;
 
-use alloc::{string::String, vec::Vec};
+use alloc::{string::String, sync::Arc, vec::Vec};
 #[allow(unused_imports)]
 use axio::{self as io, prelude::*};
 
@@ -104,3 +124,174 @@ pub fn path_exists(path: &str) -> bool {
 pub fn lookup(path: &str) -> AxResult<VfsNodeRef> {
     crate::root::lookup(None, path)
 }
+
+/// Look up a file by a given path, without following a symbolic link at the
+/// end of the path.
+pub fn lookup_link(path: &str) -> AxResult<VfsNodeRef> {
+    crate::root::lookup_link(None, path)
+}
+
+/// Look up a file by a given path to change its attributes, following a
+/// symbolic link at the end of the path if `follow` is set.
+///
+/// Returns [`ReadOnlyFilesystem`](axerrno::AxError::ReadOnlyFilesystem) if the
+/// file is on a read-only mount.
+pub fn lookup_writable(path: &str, follow: bool) -> AxResult<VfsNodeRef> {
+    crate::root::lookup_writable(path, follow)
+}
+
+/// Returns the absolute form of a path with all symbolic links resolved.
+///
+/// The last component does not need to exist.
+pub fn real_path(path: &str) -> io::Result<String> {
+    crate::root::real_path(path)
+}
+
+/// Creates a new symbolic link at `path` pointing to `target`.
+///
+/// Returns [`Unsupported`](axerrno::AxError::Unsupported) if the filesystem
+/// has no symbolic links.
+pub fn symlink(target: &str, path: &str) -> io::Result<()> {
+    crate::root::symlink(target, path)
+}
+
+/// Reads the target of the symbolic link at `path`.
+pub fn read_link(path: &str) -> io::Result<String> {
+    crate::root::read_link(path)
+}
+
+/// Creates a new hard link `new` to the file at `old`.
+///
+/// A symbolic link at `old` is linked itself. Returns
+/// [`Unsupported`](axerrno::AxError::Unsupported) if the filesystem has no
+/// hard links or the paths are on different mount points.
+pub fn hard_link(old: &str, new: &str) -> io::Result<()> {
+    crate::root::hard_link(old, new)
+}
+
+/// Returns the statistics of the filesystem containing the file at `path`,
+/// following a symbolic link at the end of the path.
+///
+/// Filesystems that keep no statistics report only a block size.
+pub fn statfs(path: &str) -> AxResult<FileSystemInfo> {
+    crate::root::statfs(path)
+}
+
+/// Add a device node to the root directory of the devfs mounted on `/dev`.
+///
+/// Returns [`NotFound`](axerrno::AxError::NotFound) if the devfs has not been mounted yet.
+#[cfg(feature = "devfs")]
+pub fn add_device(name: &'static str, node: VfsNodeRef) -> AxResult {
+    let devfs = crate::mounts::DEVFS
+        .try_get()
+        .ok_or(axerrno::AxError::NotFound)?;
+    devfs.add(name, node);
+    Ok(())
+}
+
+/// Adds a read-only file at `path` in the sysfs mounted on `/sys`, e.g.
+/// `class/net/eth0/mtu`, creating the missing parent directories.
+///
+/// The content is generated by `show` each time the file is read.
+#[cfg(feature = "sysfs")]
+pub fn add_sysfs_attr(path: &str, show: impl Fn() -> String + Send + Sync + 'static) -> AxResult {
+    crate::sysfs::add_attr(path, show)
+}
+
+/// Adds `/sys/class/net/<name>` for the network interface numbered `index`.
+///
+/// `mtu` and `up` are called each time the attributes that depend on the MTU
+/// or on whether the interface is up are read.
+#[cfg(feature = "sysfs")]
+pub fn add_sysfs_net_device(
+    name: &str,
+    index: u32,
+    mac: [u8; 6],
+    loopback: bool,
+    mtu: impl Fn() -> usize + Send + Sync + 'static,
+    up: impl Fn() -> bool + Send + Sync + 'static,
+) -> AxResult {
+    crate::sysfs::add_net_device(name, index, mac, loopback, mtu, up)
+}
+
+/// Removes the file or directory at `path` in the sysfs mounted on `/sys`,
+/// along with everything in it.
+#[cfg(feature = "sysfs")]
+pub fn remove_sysfs_entry(path: &str) -> AxResult {
+    crate::sysfs::remove(path)
+}
+
+/// Mounts a new instance of the filesystem `fstype` at the directory `target`.
+///
+/// `source` is the block device or image file holding a disk filesystem such
+/// as `vfat`, other filesystems ignore it. `data` holds the comma-separated
+/// options of the filesystem, e.g. `size=` for `tmpfs`. Returns
+/// [`Unsupported`](axerrno::AxError::Unsupported) if `fstype` is unknown.
+pub fn mount(source: &str, target: &str, fstype: &str, data: &str, read_only: bool) -> AxResult {
+    crate::root::mount(source, target, fstype, data, read_only)
+}
+
+/// Makes the directory `source` visible at `target` as well.
+pub fn bind_mount(source: &str, target: &str, read_only: bool) -> AxResult {
+    crate::root::bind_mount(source, target, read_only)
+}
+
+/// Changes whether the mount point at `target` is read-only.
+pub fn remount(target: &str, read_only: bool) -> AxResult {
+    crate::root::remount(target, read_only)
+}
+
+/// Detaches the filesystem mounted at `target`.
+///
+/// Returns [`ResourceBusy`](axerrno::AxError::ResourceBusy) if files are
+/// open, working directories are, or other filesystems are mounted below it,
+/// unless `detach` is set, which detaches them as well. Files still open on
+/// them keep working.
+pub fn umount(target: &str, detach: bool) -> AxResult {
+    crate::root::umount(target, detach)
+}
+
+/// The mount table of the current mount namespace.
+pub fn mount_table() -> Arc<MountTable> {
+    crate::root::mount_table()
+}
+
+/// The mount table set up at boot, used by the initial mount namespace.
+pub fn init_mount_table() -> Arc<MountTable> {
+    crate::root::init_mount_table()
+}
+
+/// Registers `watcher` to be told about the changes made to all filesystems.
+///
+/// The watcher is removed automatically once it is dropped.
+pub fn add_fs_watcher(watcher: &Arc<dyn FsWatcher>) {
+    crate::fsnotify::add_watcher(watcher)
+}
+
+/// Reports a change to the file at the absolute path `path` that is not made
+/// through this crate, e.g. new permissions set on its node directly.
+pub fn notify_change(path: &str, event: FsEvent) {
+    crate::fsnotify::notify(path, event)
+}
+
+/// Writes the data cached for all disks back to the devices.
+pub fn sync() -> AxResult {
+    crate::block_cache::sync_all().map_err(|_| axerrno::AxError::Io)
+}
+
+/// The memory used to cache disk blocks.
+pub fn block_cache_stats() -> BlockCacheStats {
+    crate::block_cache::stats()
+}
+
+/// Sets how many blocks are cached for each disk, 2048 by default.
+///
+/// The least recently used blocks are evicted at once if a cache holds more.
+pub fn set_block_cache_capacity(blocks: usize) -> AxResult {
+    crate::block_cache::set_capacity(blocks).map_err(|_| axerrno::AxError::Io)
+}
+
+/// Makes the filesystem type `fstype` available to [`mount`].
+pub fn register_filesystem(fstype: &'static str, ctor: FileSystemCtor) {
+    crate::mounts::register_filesystem(fstype, ctor)
+}
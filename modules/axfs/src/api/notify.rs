@@ -0,0 +1,81 @@
+//! 文件就绪通知机制
+//!
+//! 支持事件驱动的 epoll/poll：文件在可读/可写/挂断等状态可能发生变化时调用
+//! [`PollNotifier::notify`]，唤醒所有登记在该文件上的等待者，等待者随后自行重新检查就绪状态。
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use axsync::Mutex;
+
+/// 在文件就绪状态变化时被唤醒的对象，如 epoll 实例或一次 poll 调用
+pub trait PollWaker: Send + Sync {
+    /// 文件就绪状态可能发生了变化
+    fn wake(&self);
+
+    /// 是否为独占唤醒者(EPOLLEXCLUSIVE)
+    ///
+    /// 一次通知中所有非独占唤醒者都会被唤醒，而独占唤醒者只会唤醒其中一个
+    fn exclusive(&self) -> bool {
+        false
+    }
+}
+
+/// 挂在文件上的唤醒者列表
+///
+/// 只保存弱引用，唤醒者被释放后会在下一次通知时自动移除
+pub struct PollNotifier {
+    wakers: Mutex<Vec<Weak<dyn PollWaker>>>,
+}
+
+impl PollNotifier {
+    /// 创建一个空的通知列表
+    pub const fn new() -> Self {
+        Self {
+            wakers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 登记一个唤醒者
+    pub fn register(&self, waker: &Arc<dyn PollWaker>) {
+        let mut wakers = self.wakers.lock();
+        if !wakers.iter().any(|w| same_waker(w, waker)) {
+            wakers.push(Arc::downgrade(waker));
+        }
+    }
+
+    /// 移除一个唤醒者
+    pub fn unregister(&self, waker: &Arc<dyn PollWaker>) {
+        self.wakers
+            .lock()
+            .retain(|w| w.strong_count() > 0 && !same_waker(w, waker));
+    }
+
+    /// 通知所有等待者文件状态发生了变化
+    pub fn notify(&self) {
+        let wakers: Vec<Arc<dyn PollWaker>> = {
+            let mut wakers = self.wakers.lock();
+            wakers.retain(|w| w.strong_count() > 0);
+            wakers.iter().filter_map(|w| w.upgrade()).collect()
+        };
+        // 唤醒过程中不持有锁，唤醒者可能会反过来注册或注销自己
+        let mut exclusive_woken = false;
+        for waker in wakers {
+            if waker.exclusive() {
+                if exclusive_woken {
+                    continue;
+                }
+                exclusive_woken = true;
+            }
+            waker.wake();
+        }
+    }
+}
+
+impl Default for PollNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn same_waker(weak: &Weak<dyn PollWaker>, waker: &Arc<dyn PollWaker>) -> bool {
+    core::ptr::eq(weak.as_ptr() as *const u8, Arc::as_ptr(waker) as *const u8)
+}
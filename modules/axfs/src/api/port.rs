@@ -2,6 +2,7 @@
 extern crate alloc;
 use alloc::string::String;
 use axerrno::{AxError, AxResult};
+use axfs_vfs::VfsPageRef;
 use axio::{Read, Seek, SeekFrom, Write};
 use core::any::Any;
 use log::debug;
@@ -208,6 +209,12 @@ pub trait FileExt: Read + Write + Seek + AsAny + Send + Sync {
 
     /// whether the file is executable
     fn executable(&self) -> bool;
+
+    /// 取得文件中第 `index` 页的内存页，使文件的共享映射可以直接映射该页
+    fn get_page(&self, _index: u64) -> AxResult<VfsPageRef> {
+        Err(AxError::Unsupported)
+    }
+
     /// Read from position without changing cursor.
     fn read_from_seek(&mut self, pos: SeekFrom, buf: &mut [u8]) -> AxResult<usize> {
         // get old position
@@ -346,6 +353,13 @@ pub trait FileIO: AsAny + Send + Sync {
     fn ioctl(&self, _request: usize, _arg1: usize) -> AxResult<()> {
         Err(AxError::Unsupported)
     }
+
+    /// 就绪状态变化的通知列表，epoll/poll 通过它挂起等待而不必轮询
+    ///
+    /// 返回 None 表示该文件不会主动通知，等待者需要定期重新检查其状态
+    fn poll_notifier(&self) -> Option<&super::PollNotifier> {
+        None
+    }
 }
 
 /// `FileExt` 需要满足 `AsAny` 的要求，即可以转化为 `Any` 类型，从而能够进行向下类型转换。
@@ -395,22 +409,3 @@ impl From<usize> for AccessMode {
         Self::from_bits_truncate(val as u16)
     }
 }
-
-/// IOCTL系统调用支持
-#[allow(missing_docs)]
-pub const TCGETS: usize = 0x5401;
-#[allow(missing_docs)]
-pub const TIOCGPGRP: usize = 0x540F;
-#[allow(missing_docs)]
-pub const TIOCSPGRP: usize = 0x5410;
-#[allow(missing_docs)]
-pub const TIOCGWINSZ: usize = 0x5413;
-#[repr(C)]
-#[derive(Clone, Copy, Default)]
-/// the size of the console window
-pub struct ConsoleWinSize {
-    ws_row: u16,
-    ws_col: u16,
-    ws_xpixel: u16,
-    ws_ypixel: u16,
-}
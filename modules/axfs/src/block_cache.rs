@@ -0,0 +1,456 @@
+//! A write-back cache of disk blocks between the filesystems and the block
+//! drivers.
+//!
+//! Every [`Disk`](crate::dev::Disk) reads and writes through a [`BlockCache`].
+//! Cached blocks are replaced in LRU order. Dirty blocks are written back when
+//! they are evicted, when they have been dirty for longer than
+//! [`DIRTY_EXPIRE`], and when the disks are synced. Consecutive blocks are
+//! transferred with a single multi-block driver request, and sequential reads
+//! prefetch a growing window of blocks past the requested ones.
+
+use alloc::{
+    boxed::Box,
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use axdriver::prelude::*;
+use axsync::Mutex;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+const BLOCK_SIZE: usize = 512;
+
+/// Default capacity of each cache in blocks, i.e. 1 MiB.
+pub const DEFAULT_CAPACITY: usize = 2048;
+
+/// Upper bound of the blocks transferred by one driver request.
+const MAX_BATCH: usize = 64;
+
+/// Upper bound of the blocks read ahead of a sequential reader.
+const MAX_READ_AHEAD: usize = 32;
+
+/// How often the caches look for expired dirty blocks.
+pub const WRITEBACK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a block may stay dirty before it is written back.
+pub const DIRTY_EXPIRE: Duration = Duration::from_secs(30);
+
+static CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_CAPACITY);
+static CACHED_BLOCKS: AtomicUsize = AtomicUsize::new(0);
+static DIRTY_BLOCKS: AtomicUsize = AtomicUsize::new(0);
+static WRITEBACK_BLOCKS: AtomicUsize = AtomicUsize::new(0);
+
+/// All caches in the order they were created.
+static CACHES: Mutex<Vec<Weak<BlockCache>>> = Mutex::new(Vec::new());
+
+/// Memory usage of all block caches, in bytes.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockCacheStats {
+    /// Size of the cached blocks.
+    pub cached: usize,
+    /// Size of the blocks modified but not written back yet.
+    pub dirty: usize,
+    /// Size of the blocks being written back.
+    pub writeback: usize,
+}
+
+/// Returns the memory usage of all block caches.
+pub fn stats() -> BlockCacheStats {
+    BlockCacheStats {
+        cached: CACHED_BLOCKS.load(Ordering::Relaxed) * BLOCK_SIZE,
+        dirty: DIRTY_BLOCKS.load(Ordering::Relaxed) * BLOCK_SIZE,
+        writeback: WRITEBACK_BLOCKS.load(Ordering::Relaxed) * BLOCK_SIZE,
+    }
+}
+
+/// Returns the capacity of each cache in blocks.
+pub fn capacity() -> usize {
+    CAPACITY.load(Ordering::Relaxed)
+}
+
+/// Sets the capacity of each cache in blocks, the caches holding more blocks
+/// are shrunk at once.
+///
+/// The capacity is raised to twice the largest driver request if it is
+/// smaller.
+pub fn set_capacity(blocks: usize) -> DevResult {
+    CAPACITY.store(blocks.max(2 * MAX_BATCH), Ordering::Relaxed);
+    for cache in live_caches() {
+        let mut inner = cache.inner.lock();
+        while inner.blocks.len() > capacity() {
+            inner.evict()?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes back the dirty blocks of all caches and flushes the devices.
+pub fn sync_all() -> DevResult {
+    let mut res = Ok(());
+    // A cache created later may store its blocks in a file on a disk cached
+    // earlier, e.g. a disk image mounted at runtime, so it goes first.
+    for cache in live_caches().iter().rev() {
+        if let Err(err) = cache.flush() {
+            warn!("failed to sync a block device: {:?}", err);
+            res = res.and(Err(err));
+        }
+    }
+    res
+}
+
+/// Writes back the blocks of all caches that have been dirty for longer than
+/// [`DIRTY_EXPIRE`].
+pub fn write_back_expired() {
+    let now = axhal::time::current_time();
+    for cache in live_caches().iter().rev() {
+        let mut inner = cache.inner.lock();
+        if let Err(err) = inner.write_back_expired(now) {
+            warn!(
+                "failed to write back {}: {:?}",
+                inner.dev.device_name(),
+                err
+            );
+        }
+    }
+}
+
+fn live_caches() -> Vec<Arc<BlockCache>> {
+    CACHES.lock().iter().filter_map(Weak::upgrade).collect()
+}
+
+/// A cached block.
+struct CachedBlock {
+    data: Box<[u8]>,
+    /// Position in the LRU order, larger is more recently used.
+    stamp: u64,
+    /// When the block was modified first since it was last written back,
+    /// `None` if it is the same as on the disk.
+    dirty_since: Option<Duration>,
+}
+
+struct CacheInner {
+    dev: Box<dyn BlockDriverOps>,
+    blocks: BTreeMap<u64, CachedBlock>,
+    /// Cached block IDs indexed by their LRU stamps.
+    lru: BTreeMap<u64, u64>,
+    next_stamp: u64,
+    /// The block a sequential reader would read next.
+    next_sequential: u64,
+    /// Current read-ahead window in blocks.
+    read_ahead: usize,
+    /// When to look for expired dirty blocks next.
+    next_writeback: Duration,
+}
+
+/// A write-back LRU cache of the blocks of a block device.
+pub struct BlockCache {
+    num_blocks: u64,
+    inner: Mutex<CacheInner>,
+}
+
+impl BlockCache {
+    /// Creates a cache on the block device `dev`, whose block size must be
+    /// 512 bytes.
+    pub fn new(dev: Box<dyn BlockDriverOps>) -> Arc<Self> {
+        assert_eq!(BLOCK_SIZE, dev.block_size());
+        let cache = Arc::new(Self {
+            num_blocks: dev.num_blocks(),
+            inner: Mutex::new(CacheInner {
+                dev,
+                blocks: BTreeMap::new(),
+                lru: BTreeMap::new(),
+                next_stamp: 0,
+                next_sequential: 0,
+                read_ahead: 0,
+                next_writeback: axhal::time::current_time() + WRITEBACK_INTERVAL,
+            }),
+        });
+        let mut caches = CACHES.lock();
+        caches.retain(|cache| cache.strong_count() > 0);
+        caches.push(Arc::downgrade(&cache));
+        cache
+    }
+
+    /// The number of blocks of the device.
+    pub fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    /// Reads the data at byte offset `pos` of the device into `buf`.
+    pub fn read_at(&self, pos: u64, buf: &mut [u8]) -> DevResult {
+        let mut inner = self.inner.lock();
+        inner.write_back_if_due();
+        let mut done = 0;
+        while done < buf.len() {
+            let cur = pos + done as u64;
+            let start = cur / BLOCK_SIZE as u64;
+            let offset = cur as usize % BLOCK_SIZE;
+            let count = (offset + buf.len() - done)
+                .div_ceil(BLOCK_SIZE)
+                .min(MAX_BATCH);
+            let read_ahead = inner.update_read_ahead(start, count);
+            inner.fill(start, count, read_ahead)?;
+            for id in start..start + count as u64 {
+                let offset = if id == start { offset } else { 0 };
+                let len = (BLOCK_SIZE - offset).min(buf.len() - done);
+                let data = &inner.blocks[&id].data;
+                buf[done..done + len].copy_from_slice(&data[offset..offset + len]);
+                done += len;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `buf` at byte offset `pos` of the device.
+    ///
+    /// The data stays in the cache until it is written back.
+    pub fn write_at(&self, pos: u64, buf: &[u8]) -> DevResult {
+        let mut inner = self.inner.lock();
+        inner.write_back_if_due();
+        let now = axhal::time::current_time();
+        let mut done = 0;
+        while done < buf.len() {
+            let cur = pos + done as u64;
+            let start = cur / BLOCK_SIZE as u64;
+            let offset = cur as usize % BLOCK_SIZE;
+            let count = (offset + buf.len() - done)
+                .div_ceil(BLOCK_SIZE)
+                .min(MAX_BATCH);
+            // only the blocks written in part need their old content
+            if offset != 0 {
+                inner.fill(start, 1, 0)?;
+            }
+            let last = start + count as u64 - 1;
+            let end = offset + buf.len() - done;
+            if end < count * BLOCK_SIZE {
+                inner.fill(last, 1, 0)?;
+            }
+            for id in start..=last {
+                let offset = if id == start { offset } else { 0 };
+                let len = (BLOCK_SIZE - offset).min(buf.len() - done);
+                inner.write_block(id, offset, &buf[done..done + len], now)?;
+                done += len;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes back all dirty blocks and flushes the device.
+    pub fn flush(&self) -> DevResult {
+        let mut inner = self.inner.lock();
+        inner.write_back(None)?;
+        inner.dev.flush()
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        let inner = self.inner.get_mut();
+        if let Err(err) = inner.write_back(None).and_then(|_| inner.dev.flush()) {
+            warn!(
+                "failed to write back {}: {:?}",
+                inner.dev.device_name(),
+                err
+            );
+        }
+        while let Some((&id, _)) = inner.blocks.first_key_value() {
+            inner.discard(id);
+        }
+    }
+}
+
+impl CacheInner {
+    fn stamp(&mut self) -> u64 {
+        self.next_stamp += 1;
+        self.next_stamp
+    }
+
+    /// Marks the block `id` as the most recently used one.
+    fn touch(&mut self, id: u64) {
+        let stamp = self.stamp();
+        if let Some(block) = self.blocks.get_mut(&id) {
+            self.lru.remove(&block.stamp);
+            block.stamp = stamp;
+            self.lru.insert(stamp, id);
+        }
+    }
+
+    /// Caches a block read from the disk, evicting others if the cache is full.
+    fn insert(&mut self, id: u64, data: &[u8]) -> DevResult {
+        while self.blocks.len() >= capacity() {
+            self.evict()?;
+        }
+        let stamp = self.stamp();
+        let block = CachedBlock {
+            data: data.into(),
+            stamp,
+            dirty_since: None,
+        };
+        self.blocks.insert(id, block);
+        self.lru.insert(stamp, id);
+        CACHED_BLOCKS.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Evicts the least recently used block, writing it back if it is dirty.
+    fn evict(&mut self) -> DevResult {
+        let Some((_, &id)) = self.lru.first_key_value() else {
+            return Ok(());
+        };
+        if self.blocks[&id].dirty_since.is_some() {
+            // the following dirty blocks are likely to be evicted soon as well
+            let mut count = 1;
+            while count < MAX_BATCH && self.is_dirty(id + count as u64) {
+                count += 1;
+            }
+            self.write_run(id, count)?;
+        }
+        self.discard(id);
+        Ok(())
+    }
+
+    /// Drops the block `id` from the cache without writing it back.
+    fn discard(&mut self, id: u64) {
+        if let Some(block) = self.blocks.remove(&id) {
+            self.lru.remove(&block.stamp);
+            CACHED_BLOCKS.fetch_sub(1, Ordering::Relaxed);
+            if block.dirty_since.is_some() {
+                DIRTY_BLOCKS.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn is_dirty(&self, id: u64) -> bool {
+        self.blocks
+            .get(&id)
+            .is_some_and(|block| block.dirty_since.is_some())
+    }
+
+    /// Updates the read-ahead window for a read of `count` blocks from
+    /// `start`, returns the number of blocks to prefetch after them.
+    fn update_read_ahead(&mut self, start: u64, count: usize) -> usize {
+        if start == self.next_sequential {
+            self.read_ahead = (self.read_ahead * 2).clamp(4, MAX_READ_AHEAD);
+        } else if start + 1 != self.next_sequential {
+            // reading on in the last block is still sequential
+            self.read_ahead = 0;
+        }
+        self.next_sequential = start + count as u64;
+        self.read_ahead
+    }
+
+    /// Makes sure the blocks `start..start + count` are cached, reading the
+    /// missing ones with as few driver requests as possible.
+    ///
+    /// A run of missing blocks reaching the end of the range is extended by up
+    /// to `read_ahead` blocks.
+    fn fill(&mut self, start: u64, count: usize, read_ahead: usize) -> DevResult {
+        let end = start + count as u64;
+        let mut id = start;
+        while id < end {
+            if self.blocks.contains_key(&id) {
+                // keep the cached blocks of the range from being evicted
+                self.touch(id);
+                id += 1;
+                continue;
+            }
+            let limit = (end + read_ahead as u64)
+                .min(self.dev.num_blocks())
+                .min(id + MAX_BATCH as u64);
+            let mut run_end = id + 1;
+            while run_end < limit && !self.blocks.contains_key(&run_end) {
+                run_end += 1;
+            }
+            let mut buf = vec![0u8; (run_end - id) as usize * BLOCK_SIZE];
+            self.dev.read_block(id, &mut buf)?;
+            for (i, data) in buf.chunks_exact(BLOCK_SIZE).enumerate() {
+                self.insert(id + i as u64, data)?;
+            }
+            id = run_end;
+        }
+        Ok(())
+    }
+
+    /// Writes `data` at `offset` of the block `id`, which must be cached
+    /// unless the whole block is written.
+    fn write_block(&mut self, id: u64, offset: usize, data: &[u8], now: Duration) -> DevResult {
+        if self.blocks.contains_key(&id) {
+            self.touch(id);
+        } else {
+            self.insert(id, data)?;
+        }
+        let block = self.blocks.get_mut(&id).unwrap();
+        block.data[offset..offset + data.len()].copy_from_slice(data);
+        if block.dirty_since.is_none() {
+            block.dirty_since = Some(now);
+            DIRTY_BLOCKS.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+
+    /// Writes back the dirty blocks, or only those dirty since `before` if it
+    /// is given.
+    fn write_back(&mut self, before: Option<Duration>) -> DevResult {
+        let ids: Vec<u64> = self
+            .blocks
+            .iter()
+            .filter(|(_, block)| match (block.dirty_since, before) {
+                (Some(since), Some(before)) => since <= before,
+                (dirty, _) => dirty.is_some(),
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        let mut i = 0;
+        while i < ids.len() {
+            let mut j = i + 1;
+            while j < ids.len() && ids[j] == ids[j - 1] + 1 && j - i < MAX_BATCH {
+                j += 1;
+            }
+            self.write_run(ids[i], j - i)?;
+            i = j;
+        }
+        Ok(())
+    }
+
+    fn write_back_expired(&mut self, now: Duration) -> DevResult {
+        match now.checked_sub(DIRTY_EXPIRE) {
+            Some(before) => self.write_back(Some(before)),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes back the expired dirty blocks if [`WRITEBACK_INTERVAL`] has
+    /// passed since the last time, for the systems without a flusher task.
+    fn write_back_if_due(&mut self) {
+        let now = axhal::time::current_time();
+        if now < self.next_writeback {
+            return;
+        }
+        self.next_writeback = now + WRITEBACK_INTERVAL;
+        if let Err(err) = self.write_back_expired(now) {
+            warn!("failed to write back {}: {:?}", self.dev.device_name(), err);
+        }
+    }
+
+    /// Writes the cached blocks `start..start + count` to the disk with one
+    /// request and marks them clean.
+    fn write_run(&mut self, start: u64, count: usize) -> DevResult {
+        let mut buf = Vec::with_capacity(count * BLOCK_SIZE);
+        for id in start..start + count as u64 {
+            buf.extend_from_slice(&self.blocks[&id].data);
+        }
+        WRITEBACK_BLOCKS.fetch_add(count, Ordering::Relaxed);
+        let res = self.dev.write_block(start, &buf);
+        WRITEBACK_BLOCKS.fetch_sub(count, Ordering::Relaxed);
+        res?;
+        for id in start..start + count as u64 {
+            let block = self.blocks.get_mut(&id).unwrap();
+            if block.dirty_since.take().is_some() {
+                DIRTY_BLOCKS.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+        Ok(())
+    }
+}
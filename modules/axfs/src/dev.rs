@@ -1,96 +1,180 @@
+use alloc::{boxed::Box, string::String, sync::Arc};
 use axdriver::prelude::*;
+use axerrno::{ax_err, AxResult};
+use axfs_vfs::VfsNodeRef;
+
+use crate::block_cache::BlockCache;
 
 const BLOCK_SIZE: usize = 512;
 
-/// A disk device with a cursor.
+/// A disk device with a cursor, reading and writing through a [`BlockCache`].
 pub struct Disk {
-    block_id: u64,
-    offset: usize,
-    dev: AxBlockDevice,
+    position: u64,
+    cache: Arc<BlockCache>,
 }
 
 impl Disk {
     /// Create a new disk.
     pub fn new(dev: AxBlockDevice) -> Self {
-        assert_eq!(BLOCK_SIZE, dev.block_size());
+        Self::from_driver(Box::new(DeviceWrapper(dev)))
+    }
+
+    /// Create a new disk on any block driver, e.g. a [`FileBlockDevice`].
+    pub fn from_driver(dev: Box<dyn BlockDriverOps>) -> Self {
         Self {
-            block_id: 0,
-            offset: 0,
-            dev,
+            position: 0,
+            cache: BlockCache::new(dev),
         }
     }
 
     /// Get the size of the disk.
     pub fn size(&self) -> u64 {
-        self.dev.num_blocks() * BLOCK_SIZE as u64
+        self.cache.num_blocks() * BLOCK_SIZE as u64
     }
 
     /// Get the position of the cursor.
     pub fn position(&self) -> u64 {
-        self.block_id * BLOCK_SIZE as u64 + self.offset as u64
+        self.position
     }
 
     /// Set the position of the cursor.
     pub fn set_position(&mut self, pos: u64) {
-        self.block_id = pos / BLOCK_SIZE as u64;
-        self.offset = pos as usize % BLOCK_SIZE;
-    }
-
-    /// Read within one block, returns the number of bytes read.
-    pub fn read_one(&mut self, buf: &mut [u8]) -> DevResult<usize> {
-        // info!("block id: {}", self.block_id);
-        let read_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev
-                .read_block(self.block_id, &mut buf[0..BLOCK_SIZE])?;
-            self.block_id += 1;
-            BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-            if start > BLOCK_SIZE {
-                info!("block size: {} start {}", BLOCK_SIZE, start);
-            }
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            buf[..count].copy_from_slice(&data[start..start + count]);
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
-        };
-        Ok(read_size)
-    }
-
-    /// Write within one block, returns the number of bytes written.
-    pub fn write_one(&mut self, buf: &[u8]) -> DevResult<usize> {
-        let write_size = if self.offset == 0 && buf.len() >= BLOCK_SIZE {
-            // whole block
-            self.dev.write_block(self.block_id, &buf[0..BLOCK_SIZE])?;
-            self.block_id += 1;
-            BLOCK_SIZE
-        } else {
-            // partial block
-            let mut data = [0u8; BLOCK_SIZE];
-            let start = self.offset;
-            let count = buf.len().min(BLOCK_SIZE - self.offset);
-
-            self.dev.read_block(self.block_id, &mut data)?;
-            data[start..start + count].copy_from_slice(&buf[..count]);
-            self.dev.write_block(self.block_id, &data)?;
-
-            self.offset += count;
-            if self.offset >= BLOCK_SIZE {
-                self.block_id += 1;
-                self.offset -= BLOCK_SIZE;
-            }
-            count
-        };
-        Ok(write_size)
+        self.position = pos;
+    }
+
+    /// Read from the cursor, returns the number of bytes read, which is less
+    /// than the length of `buf` only at the end of the disk.
+    pub fn read_data(&mut self, buf: &mut [u8]) -> DevResult<usize> {
+        let len = self.remaining(buf.len());
+        self.cache.read_at(self.position, &mut buf[..len])?;
+        self.position += len as u64;
+        Ok(len)
+    }
+
+    /// Write at the cursor, returns the number of bytes written, which is less
+    /// than the length of `buf` only at the end of the disk.
+    pub fn write_data(&mut self, buf: &[u8]) -> DevResult<usize> {
+        let len = self.remaining(buf.len());
+        self.cache.write_at(self.position, &buf[..len])?;
+        self.position += len as u64;
+        Ok(len)
+    }
+
+    /// Write the cached data back to the device.
+    pub fn sync(&mut self) -> DevResult {
+        self.cache.flush()
+    }
+
+    fn remaining(&self, len: usize) -> usize {
+        self.size().saturating_sub(self.position).min(len as u64) as usize
+    }
+}
+
+/// Wraps [`AxBlockDevice`], which is either a concrete driver or already a
+/// boxed trait object depending on the `dyn` feature of `axdriver`.
+struct DeviceWrapper(AxBlockDevice);
+
+impl BaseDriverOps for DeviceWrapper {
+    fn device_name(&self) -> &str {
+        self.0.device_name()
+    }
+
+    fn device_type(&self) -> DeviceType {
+        self.0.device_type()
+    }
+}
+
+impl BlockDriverOps for DeviceWrapper {
+    fn num_blocks(&self) -> u64 {
+        self.0.num_blocks()
+    }
+
+    fn block_size(&self) -> usize {
+        self.0.block_size()
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        self.0.read_block(block_id, buf)
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        self.0.write_block(block_id, buf)
+    }
+
+    fn flush(&mut self) -> DevResult {
+        self.0.flush()
+    }
+}
+
+/// A block device whose blocks are stored in a file or device node, e.g. a
+/// disk image mounted at runtime.
+pub struct FileBlockDevice {
+    name: String,
+    node: VfsNodeRef,
+    num_blocks: u64,
+}
+
+impl FileBlockDevice {
+    /// Create a block device on `node`, `name` is the path it was opened at.
+    ///
+    /// The size of the node is taken once, a trailing partial block is ignored.
+    /// The node is kept open until the device is dropped.
+    pub fn new(name: &str, node: VfsNodeRef) -> AxResult<Self> {
+        let attr = node.get_attr()?;
+        if attr.is_dir() {
+            return ax_err!(IsADirectory);
+        }
+        node.open()?;
+        Ok(Self {
+            name: String::from(name),
+            num_blocks: attr.size() / BLOCK_SIZE as u64,
+            node,
+        })
+    }
+}
+
+impl Drop for FileBlockDevice {
+    fn drop(&mut self) {
+        self.node.release().ok();
+    }
+}
+
+impl BaseDriverOps for FileBlockDevice {
+    fn device_name(&self) -> &str {
+        &self.name
+    }
+
+    fn device_type(&self) -> DeviceType {
+        DeviceType::Block
+    }
+}
+
+impl BlockDriverOps for FileBlockDevice {
+    fn num_blocks(&self) -> u64 {
+        self.num_blocks
+    }
+
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn read_block(&mut self, block_id: u64, buf: &mut [u8]) -> DevResult {
+        let offset = block_id * BLOCK_SIZE as u64;
+        let read = self.node.read_at(offset, buf).map_err(|_| DevError::Io)?;
+        // the blocks past the end of the file read as zeros
+        buf[read..].fill(0);
+        Ok(())
+    }
+
+    fn write_block(&mut self, block_id: u64, buf: &[u8]) -> DevResult {
+        let offset = block_id * BLOCK_SIZE as u64;
+        match self.node.write_at(offset, buf) {
+            Ok(written) if written == buf.len() => Ok(()),
+            _ => Err(DevError::Io),
+        }
+    }
+
+    fn flush(&mut self) -> DevResult {
+        self.node.fsync().map_err(|_| DevError::Io)
     }
 }
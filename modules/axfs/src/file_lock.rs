@@ -0,0 +1,405 @@
+//! Advisory file locks.
+//!
+//! Three kinds of owners hold locks on files, identified by their nodes (see
+//! [`File::node_id`](crate::api::File::node_id)), so that a lock is seen
+//! however the file was reached:
+//!
+//! - POSIX record locks (`fcntl(F_SETLK)`) are owned by a process. They cover
+//!   byte ranges and are released when the process closes any descriptor of
+//!   the file.
+//! - Open file description locks (`fcntl(F_OFD_SETLK)`) are like POSIX locks
+//!   but owned by an opened file, and conflict with POSIX locks.
+//! - `flock` locks cover the whole file, are owned by an opened file and never
+//!   conflict with the other two kinds.
+//!
+//! Nothing here blocks. A caller that wants to wait passes a waker to
+//! [`set_lock`], which is woken once locks on the file are released, then
+//! retries, and calls [`cancel_lock_wait`] when it is done waiting.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult};
+use axsync::Mutex;
+
+use crate::api::{PollNotifier, PollWaker};
+
+/// Whether a lock is shared or exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockKind {
+    /// A shared lock, any number of owners may hold one on the same bytes.
+    Read,
+    /// An exclusive lock.
+    Write,
+}
+
+/// The owner of a lock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LockOwner {
+    /// A POSIX record lock, owned by the process with the given id.
+    Posix(u64),
+    /// An open file description lock, owned by the opened file with the given
+    /// id.
+    Ofd(usize),
+    /// A `flock` lock, owned by the opened file with the given id.
+    Flock(usize),
+}
+
+impl LockOwner {
+    /// Whether locks of the two owners are kept apart from each other.
+    fn independent_of(&self, other: &Self) -> bool {
+        matches!(self, Self::Flock(_)) != matches!(other, Self::Flock(_))
+    }
+}
+
+/// A lock on the bytes `start..end` of a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileLock {
+    /// The owner of the lock.
+    pub owner: LockOwner,
+    /// Whether the lock is shared or exclusive.
+    pub kind: LockKind,
+    /// The first byte covered by the lock.
+    pub start: u64,
+    /// The byte after the last one covered, `u64::MAX` for the end of file
+    /// however far it grows.
+    pub end: u64,
+    /// The process reported to `F_GETLK` as holding the lock.
+    pub pid: i32,
+}
+
+impl FileLock {
+    fn overlaps(&self, start: u64, end: u64) -> bool {
+        self.start < end && start < self.end
+    }
+
+    /// Whether the lock prevents `other` from being taken.
+    fn conflicts_with(&self, other: &FileLock) -> bool {
+        self.owner != other.owner
+            && !self.owner.independent_of(&other.owner)
+            && (self.kind == LockKind::Write || other.kind == LockKind::Write)
+            && self.overlaps(other.start, other.end)
+    }
+}
+
+/// The locks held on a file and the callers waiting to take one.
+#[derive(Default)]
+struct FileLocks {
+    locks: Vec<FileLock>,
+    /// Woken whenever locks on the file are released.
+    waiters: Arc<PollNotifier>,
+}
+
+/// The locks held on each file, by node.
+static LOCKS: Mutex<BTreeMap<usize, FileLocks>> = Mutex::new(BTreeMap::new());
+
+/// The process each waiting process waits for, to detect deadlocks.
+static WAITING: Mutex<BTreeMap<u64, u64>> = Mutex::new(BTreeMap::new());
+
+/// Returns a lock held on the file of `node` that prevents `lock` from being
+/// taken.
+pub fn test_lock(node: usize, lock: &FileLock) -> Option<FileLock> {
+    LOCKS
+        .lock()
+        .get(&node)
+        .and_then(|file| file.locks.iter().find(|l| l.conflicts_with(lock)).copied())
+}
+
+/// Takes `lock` on the file of `node`, replacing the locks its owner holds on
+/// the same bytes.
+///
+/// Returns [`WouldBlock`](AxError::WouldBlock) if another owner holds a
+/// conflicting lock. If `waker` is given the caller is going to wait for that
+/// owner: the waker is woken once locks on the file are released, and
+/// [`Deadlock`](AxError::Deadlock) is returned instead if the owner is itself
+/// waiting, directly or not, for the caller.
+pub fn set_lock(node: usize, lock: FileLock, waker: Option<&Arc<dyn PollWaker>>) -> AxResult {
+    let mut all = LOCKS.lock();
+    let file = all.entry(node).or_default();
+    let Some(blocker) = file.locks.iter().find(|l| l.conflicts_with(&lock)) else {
+        let replaced = remove_range(&mut file.locks, lock.owner, lock.start, lock.end);
+        file.locks.push(lock);
+        merge(&mut file.locks, lock.owner);
+        // a write lock turned into a read lock may let waiters in
+        if replaced {
+            let waiters = Arc::clone(&file.waiters);
+            drop(all);
+            waiters.notify();
+        }
+        return Ok(());
+    };
+    let Some(waker) = waker else {
+        return Err(AxError::WouldBlock);
+    };
+    if let (LockOwner::Posix(pid), LockOwner::Posix(blocker)) = (lock.owner, blocker.owner) {
+        let mut waiting = WAITING.lock();
+        // follow the chain of processes waiting for each other
+        let mut next = Some(blocker);
+        while let Some(owner) = next {
+            if owner == pid {
+                waiting.remove(&pid);
+                return Err(AxError::Deadlock);
+            }
+            next = waiting.get(&owner).copied();
+        }
+        waiting.insert(pid, blocker);
+    }
+    file.waiters.register(waker);
+    Err(AxError::WouldBlock)
+}
+
+/// Releases the bytes `start..end` of the file of `node` locked by `owner`.
+pub fn unlock_range(node: usize, owner: LockOwner, start: u64, end: u64) {
+    let mut all = LOCKS.lock();
+    let Some(file) = all.get_mut(&node) else {
+        return;
+    };
+    remove_range(&mut file.locks, owner, start, end);
+    let waiters = Arc::clone(&file.waiters);
+    if file.locks.is_empty() {
+        all.remove(&node);
+    }
+    drop(all);
+    waiters.notify();
+}
+
+/// Releases all locks held by `owner` on the file of `node`.
+pub fn release_locks(node: usize, owner: LockOwner) {
+    unlock_range(node, owner, 0, u64::MAX);
+}
+
+/// Releases all locks held by `owner` on any file.
+pub fn release_owner_locks(owner: LockOwner) {
+    let mut waiters = Vec::new();
+    LOCKS.lock().retain(|_, file| {
+        let len = file.locks.len();
+        file.locks.retain(|l| l.owner != owner);
+        if file.locks.len() != len {
+            waiters.push(Arc::clone(&file.waiters));
+        }
+        !file.locks.is_empty()
+    });
+    stop_waiting(owner);
+    for waiters in waiters {
+        waiters.notify();
+    }
+}
+
+/// Records that `owner` no longer waits for a lock on the file of `node` with
+/// `waker`, whether it got the lock or gave up.
+pub fn cancel_lock_wait(node: usize, owner: LockOwner, waker: &Arc<dyn PollWaker>) {
+    if let Some(file) = LOCKS.lock().get(&node) {
+        file.waiters.unregister(waker);
+    }
+    stop_waiting(owner);
+}
+
+fn stop_waiting(owner: LockOwner) {
+    if let LockOwner::Posix(pid) = owner {
+        WAITING.lock().remove(&pid);
+    }
+}
+
+/// Removes the bytes `start..end` from the locks of `owner`, splitting the
+/// locks that cover more.
+///
+/// Returns whether any lock was removed or shrunk.
+fn remove_range(locks: &mut Vec<FileLock>, owner: LockOwner, start: u64, end: u64) -> bool {
+    let len = locks.len();
+    let mut rest = Vec::new();
+    locks.retain(|l| {
+        if l.owner != owner || !l.overlaps(start, end) {
+            return true;
+        }
+        if l.start < start {
+            rest.push(FileLock { end: start, ..*l });
+        }
+        if end < l.end {
+            rest.push(FileLock { start: end, ..*l });
+        }
+        false
+    });
+    let changed = locks.len() != len;
+    locks.extend(rest);
+    changed
+}
+
+/// Coalesces the adjacent or overlapping locks of `owner` of the same kind.
+fn merge(locks: &mut Vec<FileLock>, owner: LockOwner) {
+    let mut i = 0;
+    while i < locks.len() {
+        let lock = locks[i];
+        let touching = (0..locks.len()).find(|&j| {
+            let other = &locks[j];
+            j != i
+                && other.owner == owner
+                && lock.owner == owner
+                && other.kind == lock.kind
+                && other.start <= lock.end
+                && lock.start <= other.end
+        });
+        match touching {
+            Some(j) => {
+                let other = locks.swap_remove(j);
+                // `i` may have been moved to `j` by the removal
+                let i = if i == locks.len() { j } else { i };
+                locks[i].start = lock.start.min(other.start);
+                locks[i].end = lock.end.max(other.end);
+            }
+            None => i += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(axtask::init_scheduler); // to use `axsync::Mutex`
+    }
+
+    fn lock(owner: LockOwner, kind: LockKind, start: u64, end: u64) -> FileLock {
+        FileLock {
+            owner,
+            kind,
+            start,
+            end,
+            pid: 0,
+        }
+    }
+
+    fn ranges(node: usize, owner: LockOwner) -> Vec<(LockKind, u64, u64)> {
+        let mut ranges: Vec<_> = LOCKS
+            .lock()
+            .get(&node)
+            .map(|file| file.locks.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|l| l.owner == owner)
+            .map(|l| (l.kind, l.start, l.end))
+            .collect();
+        ranges.sort_by_key(|&(_, start, _)| start);
+        ranges
+    }
+
+    #[test]
+    fn test_split_and_merge() {
+        init();
+        let node = 1;
+        let owner = LockOwner::Posix(1);
+        set_lock(node, lock(owner, LockKind::Write, 0, 100), None).unwrap();
+        // a read lock in the middle splits the write lock
+        set_lock(node, lock(owner, LockKind::Read, 40, 60), None).unwrap();
+        assert_eq!(
+            ranges(node, owner),
+            [
+                (LockKind::Write, 0, 40),
+                (LockKind::Read, 40, 60),
+                (LockKind::Write, 60, 100)
+            ]
+        );
+        // unlocking splits the lock it falls into
+        unlock_range(node, owner, 70, 80);
+        assert_eq!(
+            ranges(node, owner),
+            [
+                (LockKind::Write, 0, 40),
+                (LockKind::Read, 40, 60),
+                (LockKind::Write, 60, 70),
+                (LockKind::Write, 80, 100)
+            ]
+        );
+        // adjacent and overlapping locks of the same kind are merged
+        set_lock(node, lock(owner, LockKind::Write, 40, 80), None).unwrap();
+        assert_eq!(ranges(node, owner), [(LockKind::Write, 0, 100)]);
+        set_lock(node, lock(owner, LockKind::Write, 100, u64::MAX), None).unwrap();
+        assert_eq!(ranges(node, owner), [(LockKind::Write, 0, u64::MAX)]);
+        release_locks(node, owner);
+        assert!(LOCKS.lock().get(&node).is_none());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        init();
+        let node = 2;
+        let (a, b) = (LockOwner::Posix(2), LockOwner::Posix(3));
+        set_lock(node, lock(a, LockKind::Read, 0, 10), None).unwrap();
+        set_lock(node, lock(b, LockKind::Read, 5, 15), None).unwrap();
+        let write = lock(b, LockKind::Write, 0, 10);
+        assert_eq!(test_lock(node, &write).map(|l| l.owner), Some(a));
+        assert_eq!(set_lock(node, write, None), Err(AxError::WouldBlock));
+        // flock locks never conflict with record locks
+        let flock = lock(LockOwner::Flock(1), LockKind::Write, 0, u64::MAX);
+        assert_eq!(set_lock(node, flock, None), Ok(()));
+        release_owner_locks(a);
+        assert_eq!(set_lock(node, write, None), Ok(()));
+        release_owner_locks(b);
+        release_owner_locks(LockOwner::Flock(1));
+        assert!(LOCKS.lock().get(&node).is_none());
+    }
+
+    #[derive(Default)]
+    struct Waker(core::sync::atomic::AtomicUsize);
+
+    impl PollWaker for Waker {
+        fn wake(&self) {
+            self.0.fetch_add(1, core::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_downgrade_wakes() {
+        init();
+        let node = 5;
+        let (a, b) = (LockOwner::Posix(6), LockOwner::Posix(7));
+        let waker = Arc::new(Waker::default());
+        let dyn_waker: Arc<dyn PollWaker> = waker.clone();
+        set_lock(node, lock(a, LockKind::Write, 0, 10), None).unwrap();
+        let wanted = lock(b, LockKind::Read, 0, 10);
+        assert_eq!(
+            set_lock(node, wanted, Some(&dyn_waker)),
+            Err(AxError::WouldBlock)
+        );
+        // turning the write lock into a read lock lets `b` in
+        set_lock(node, lock(a, LockKind::Read, 0, 10), None).unwrap();
+        assert_eq!(waker.0.load(core::sync::atomic::Ordering::Relaxed), 1);
+        assert_eq!(set_lock(node, wanted, None), Ok(()));
+        cancel_lock_wait(node, b, &dyn_waker);
+        release_owner_locks(a);
+        release_owner_locks(b);
+    }
+
+    #[test]
+    fn test_deadlock() {
+        init();
+        let (file1, file2) = (3, 4);
+        let (a, b) = (LockOwner::Posix(4), LockOwner::Posix(5));
+        let waker: Arc<dyn PollWaker> = Arc::new(Waker::default());
+        set_lock(file1, lock(a, LockKind::Write, 0, 10), None).unwrap();
+        set_lock(file2, lock(b, LockKind::Write, 0, 10), None).unwrap();
+        // `a` waits for `b`, so `b` may not wait for `a`
+        let wanted = lock(a, LockKind::Write, 0, 10);
+        assert_eq!(
+            set_lock(file2, wanted, Some(&waker)),
+            Err(AxError::WouldBlock)
+        );
+        let wanted = lock(b, LockKind::Write, 0, 10);
+        assert_eq!(
+            set_lock(file1, wanted, Some(&waker)),
+            Err(AxError::Deadlock)
+        );
+        // without waiting there is no deadlock
+        assert_eq!(set_lock(file1, wanted, None), Err(AxError::WouldBlock));
+        // once `a` gives up `b` may wait for it
+        cancel_lock_wait(file2, a, &waker);
+        assert_eq!(
+            set_lock(file1, wanted, Some(&waker)),
+            Err(AxError::WouldBlock)
+        );
+        cancel_lock_wait(file1, b, &waker);
+        release_owner_locks(a);
+        release_owner_locks(b);
+    }
+}
@@ -1,11 +1,15 @@
 //! Low-level filesystem operations.
 
+use alloc::string::String;
+use alloc::sync::Arc;
 use axerrno::{ax_err, ax_err_type, AxResult};
-use axfs_vfs::{VfsError, VfsNodeRef};
+use axfs_vfs::{VfsError, VfsNodeRef, VfsPageRef};
 use axio::SeekFrom;
 use capability::{Cap, WithCap};
 use core::fmt;
 
+use crate::fsnotify::{self, FsEvent};
+
 #[cfg(feature = "myfs")]
 pub use crate::dev::Disk;
 #[cfg(feature = "myfs")]
@@ -26,6 +30,8 @@ pub struct File {
     node: WithCap<VfsNodeRef>,
     is_append: bool,
     offset: u64,
+    /// The absolute path the file was opened at, for change notifications.
+    path: Option<String>,
 }
 
 /// An opened directory object, with open permissions and a cursor for
@@ -118,6 +124,9 @@ impl File {
         if !opts.is_valid() {
             return ax_err!(InvalidInput);
         }
+        if opts.write || opts.append || opts.truncate || opts.create || opts.create_new {
+            crate::root::check_writable(dir, path)?;
+        }
 
         let node_option = crate::root::lookup(dir, path);
         let node = if opts.create || opts.create_new {
@@ -148,14 +157,16 @@ impl File {
             return ax_err!(PermissionDenied);
         }
         node.open()?;
-        if opts.truncate {
-            node.truncate(0)?;
-        }
-        Ok(Self {
+        let file = Self {
             node: WithCap::new(node, access_cap),
             is_append: opts.append,
             offset: 0,
-        })
+            path: fsnotify::event_path(dir, path),
+        };
+        if opts.truncate {
+            file.truncate(0)?;
+        }
+        Ok(file)
     }
 
     /// Opens a file at the path relative to the current directory. Returns a
@@ -167,9 +178,46 @@ impl File {
     /// Truncates the file to the specified size.
     pub fn truncate(&self, size: u64) -> AxResult {
         self.node.access(Cap::WRITE)?.truncate(size)?;
+        self.notify(FsEvent::MODIFY);
+        Ok(())
+    }
+
+    /// Allocates the storage for the bytes `offset..offset + len`, growing the
+    /// file to cover them unless `keep_size` is set.
+    pub fn allocate(&self, offset: u64, len: u64, keep_size: bool) -> AxResult {
+        self.node
+            .access(Cap::WRITE)?
+            .allocate(offset, len, keep_size)?;
+        if !keep_size {
+            self.notify(FsEvent::MODIFY);
+        }
+        Ok(())
+    }
+
+    /// Deallocates the bytes `offset..offset + len`, which read as zeros
+    /// afterwards.
+    pub fn punch_hole(&self, offset: u64, len: u64) -> AxResult {
+        self.node.access(Cap::WRITE)?.punch_hole(offset, len)?;
+        self.notify(FsEvent::MODIFY);
         Ok(())
     }
 
+    /// Moves the cursor to the next data from `offset`, or to the next hole if
+    /// `hole` is set. Returns the new position, or `None` if there is none.
+    pub fn seek_data(&mut self, offset: u64, hole: bool) -> AxResult<Option<u64>> {
+        let found = self.node.access(Cap::empty())?.seek_data(offset, hole)?;
+        if let Some(pos) = found {
+            self.offset = pos;
+        }
+        Ok(found)
+    }
+
+    /// Gets the page holding the bytes from `index * PAGE_SIZE` of the file,
+    /// to map it into an address space.
+    pub fn get_page(&self, index: u64) -> AxResult<VfsPageRef> {
+        self.node.access(Cap::READ)?.get_page(index)
+    }
+
     /// Reads the file at the current position. Returns the number of bytes
     /// read.
     ///
@@ -202,6 +250,7 @@ impl File {
         };
         let write_len = node.write_at(self.offset, buf)?;
         self.offset += write_len as u64;
+        self.notify(FsEvent::MODIFY);
         Ok(write_len)
     }
 
@@ -212,6 +261,7 @@ impl File {
     pub fn write_at(&self, offset: u64, buf: &[u8]) -> AxResult<usize> {
         let node = self.node.access(Cap::WRITE)?;
         let write_len = node.write_at(offset, buf)?;
+        self.notify(FsEvent::MODIFY);
         Ok(write_len)
     }
 
@@ -257,6 +307,32 @@ impl File {
     pub fn executable(&self) -> bool {
         self.node.can_access(Cap::EXECUTE)
     }
+
+    /// The address of the underlying node, the same for every file opened on
+    /// it while any of them is open.
+    pub fn node_id(&self) -> usize {
+        Arc::as_ptr(unsafe { self.node.access_unchecked() }) as *const u8 as usize
+    }
+
+    fn notify(&self, event: FsEvent) {
+        if let Some(path) = &self.path {
+            fsnotify::notify(path, event);
+        }
+    }
+
+    /// Reports that the file was closed, as [`CLOSE_WRITE`](FsEvent::CLOSE_WRITE)
+    /// if it was opened for writing, or [`CLOSE_NOWRITE`](FsEvent::CLOSE_NOWRITE)
+    /// otherwise.
+    ///
+    /// This is left to the owner of the open file description, since the
+    /// kernel also opens files internally and those are not closed by users.
+    pub fn notify_close(&self) {
+        self.notify(if self.writable() {
+            FsEvent::CLOSE_WRITE
+        } else {
+            FsEvent::CLOSE_NOWRITE
+        });
+    }
 }
 
 impl Directory {
@@ -1,16 +1,28 @@
 use crate::alloc::string::String;
+use alloc::ffi::CString;
 use alloc::sync::Arc;
 use axerrno::AxError;
-use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
+use axfs_vfs::{FileSystemInfo, VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
+use core::time::Duration;
 use lwext4_rust::bindings::{
-    O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, SEEK_CUR, SEEK_END, SEEK_SET,
+    ext4_atime_get, ext4_atime_set, ext4_ctime_get, ext4_ctime_set, ext4_flink, ext4_fsymlink,
+    ext4_get_sblock, ext4_inode, ext4_mode_set, ext4_mount_point_stats, ext4_mount_stats,
+    ext4_mtime_get, ext4_mtime_set, ext4_owner_get, ext4_owner_set, ext4_raw_inode_fill,
+    ext4_readlink, ext4_sblock, O_CREAT, O_RDONLY, O_RDWR, O_TRUNC, O_WRONLY, SEEK_CUR, SEEK_END,
+    SEEK_SET,
 };
 use lwext4_rust::{Ext4BlockWrapper, Ext4File, InodeTypes, KernelDevOp};
 
 use crate::dev::Disk;
 const BLOCK_SIZE: usize = 512;
+/// The maximum length of a symbolic link target.
+const PATH_MAX: usize = 4096;
+/// The magic number of ext2, ext3 and ext4 reported by `statfs`.
+const EXT4_SUPER_MAGIC: u64 = 0xef53;
+/// The size of the extra inode fields up to the end of the creation time.
+const EXTRA_ISIZE_CRTIME: u16 = 20;
 
 #[allow(dead_code)]
 pub struct Ext4FileSystem {
@@ -45,6 +57,27 @@ impl Ext4FileSystem {
 impl VfsOps for Ext4FileSystem {
     // mount()
 
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        // lwext4 mounts its only volume at `/`
+        let mount_point = c_path("/")?;
+        let mut stats: ext4_mount_stats = unsafe { core::mem::zeroed() };
+        let mut sb: *mut ext4_sblock = core::ptr::null_mut();
+        unsafe {
+            ext4_result(ext4_mount_point_stats(mount_point.as_ptr(), &mut stats))?;
+            ext4_result(ext4_get_sblock(mount_point.as_ptr(), &mut sb))?;
+        }
+        let sb = unsafe { &*sb };
+        let reserved = ((sb.r_blocks_count_hi as u64) << 32) | sb.r_blocks_count_lo as u64;
+        let free = stats.free_blocks_count;
+        let mut info = FileSystemInfo::new(EXT4_SUPER_MAGIC, stats.block_size.into());
+        info.set_blocks(stats.blocks_count, free, free.saturating_sub(reserved));
+        info.set_files(stats.inodes_count.into(), stats.free_inodes_count.into());
+        // like Linux, the id folds the two halves of the volume UUID
+        let word = |i: usize| u32::from_le_bytes(sb.uuid[i..i + 4].try_into().unwrap()) as u64;
+        info.set_fsid((word(0) ^ word(8)) | ((word(4) ^ word(12)) << 32));
+        Ok(info)
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         debug!("Get root_dir");
         //let root_dir = unsafe { (*self.root.get()).as_ref().unwrap() };
@@ -91,6 +124,81 @@ impl FileWrapper {
         info!("dealt with full path: {}", fpath.as_str());
         fpath
     }
+
+    fn path(&self) -> String {
+        let file = self.0.lock();
+        String::from(file.get_path().to_str().unwrap())
+    }
+}
+
+fn c_path(path: &str) -> VfsResult<CString> {
+    CString::new(path).map_err(|_| AxError::InvalidInput)
+}
+
+/// Converts the return value of an lwext4 function.
+fn ext4_result(ret: i32) -> VfsResult {
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(ret.try_into().unwrap_or(AxError::Io))
+    }
+}
+
+fn read_link(path: &str, buf: &mut [u8]) -> VfsResult<usize> {
+    let path = c_path(path)?;
+    let mut len = 0;
+    ext4_result(unsafe {
+        ext4_readlink(
+            path.as_ptr(),
+            buf.as_mut_ptr() as _,
+            buf.len() as _,
+            &mut len,
+        )
+    })?;
+    Ok(len)
+}
+
+/// Reads the owner, timestamps, link count and inode number of `path` into
+/// `attr`.
+///
+/// The creation time is only kept by inodes large enough for it.
+fn read_inode_attr(path: &str, attr: &mut VfsNodeAttr) -> VfsResult {
+    let path = c_path(path)?;
+    let (mut uid, mut gid) = (0, 0);
+    let (mut atime, mut mtime, mut ctime) = (0, 0, 0);
+    let mut ino = 0;
+    let mut inode: ext4_inode = unsafe { core::mem::zeroed() };
+    unsafe {
+        ext4_result(ext4_owner_get(path.as_ptr(), &mut uid, &mut gid))?;
+        ext4_result(ext4_atime_get(path.as_ptr(), &mut atime))?;
+        ext4_result(ext4_mtime_get(path.as_ptr(), &mut mtime))?;
+        ext4_result(ext4_ctime_get(path.as_ptr(), &mut ctime))?;
+        ext4_result(ext4_raw_inode_fill(path.as_ptr(), &mut ino, &mut inode))?;
+    }
+    attr.set_owner(uid, gid);
+    attr.set_nlink(inode.links_count.into());
+    attr.set_ino(ino.into());
+    attr.set_times(
+        Duration::from_secs(atime.into()),
+        Duration::from_secs(mtime.into()),
+        Duration::from_secs(ctime.into()),
+    );
+    if inode.extra_isize >= EXTRA_ISIZE_CRTIME {
+        // the low two bits of the extra field extend the seconds
+        attr.set_btime(Duration::new(inode.crtime.into(), inode.crtime_extra >> 2));
+    }
+    Ok(())
+}
+
+/// Updates the modification and status change times of `path` after its
+/// content has changed.
+///
+/// ext4 inodes store timestamps in seconds.
+fn touch_modified(path: &str) -> VfsResult {
+    let path = c_path(path)?;
+    let now = axfs_vfs::now().as_secs() as u32;
+    ext4_result(unsafe { ext4_mtime_set(path.as_ptr(), now) })?;
+    ext4_result(unsafe { ext4_ctime_set(path.as_ptr(), now) })
 }
 
 /// The [`VfsNodeOps`] trait provides operations on a file or a directory.
@@ -99,7 +207,7 @@ impl VfsNodeOps for FileWrapper {
         let mut file = self.0.lock();
 
         let perm = file.file_mode_get().unwrap_or(0o755);
-        let perm = VfsNodePerm::from_bits_truncate((perm as u16) & 0o777);
+        let perm = VfsNodePerm::from_bits_truncate((perm as u16) & 0o7777);
 
         let vtype = file.file_type_get();
         let vtype = match vtype {
@@ -124,6 +232,11 @@ impl VfsNodeOps for FileWrapper {
             let fsize = file.file_size();
             let _ = file.file_close();
             fsize
+        } else if vtype == VfsNodeType::SymLink {
+            // the size of a symbolic link is the length of its target
+            let path = file.get_path();
+            let mut buf = [0; PATH_MAX];
+            read_link(path.to_str().unwrap(), &mut buf)? as u64
         } else {
             0 // DIR size ?
         };
@@ -137,7 +250,45 @@ impl VfsNodeOps for FileWrapper {
             blocks
         );
 
-        Ok(VfsNodeAttr::new(perm, vtype, size, blocks))
+        let mut attr = VfsNodeAttr::new(perm, vtype, size, blocks);
+        read_inode_attr(file.get_path().to_str().unwrap(), &mut attr)?;
+        Ok(attr)
+    }
+
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        let path = c_path(&self.path())?;
+        unsafe {
+            if let Some(atime) = atime {
+                ext4_result(ext4_atime_set(path.as_ptr(), atime.as_secs() as u32))?;
+            }
+            if let Some(mtime) = mtime {
+                ext4_result(ext4_mtime_set(path.as_ptr(), mtime.as_secs() as u32))?;
+            }
+            let now = axfs_vfs::now().as_secs() as u32;
+            ext4_result(ext4_ctime_set(path.as_ptr(), now))
+        }
+    }
+
+    fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> VfsResult {
+        let path = c_path(&self.path())?;
+        let (mut old_uid, mut old_gid) = (0, 0);
+        unsafe {
+            ext4_result(ext4_owner_get(path.as_ptr(), &mut old_uid, &mut old_gid))?;
+            let (uid, gid) = (uid.unwrap_or(old_uid), gid.unwrap_or(old_gid));
+            ext4_result(ext4_owner_set(path.as_ptr(), uid, gid))?;
+            let now = axfs_vfs::now().as_secs() as u32;
+            ext4_result(ext4_ctime_set(path.as_ptr(), now))
+        }
+    }
+
+    fn chmod(&self, mode: VfsNodePerm) -> VfsResult {
+        // lwext4 keeps the file type bits of the inode mode
+        let path = c_path(&self.path())?;
+        unsafe {
+            ext4_result(ext4_mode_set(path.as_ptr(), mode.mode()))?;
+            let now = axfs_vfs::now().as_secs() as u32;
+            ext4_result(ext4_ctime_set(path.as_ptr(), now))
+        }
     }
 
     fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
@@ -266,6 +417,9 @@ impl VfsNodeOps for FileWrapper {
         } else if file.check_inode_exist(fpath, InodeTypes::EXT4_DE_REG_FILE) {
             debug!("lookup new FILE FileWrapper");
             Ok(Arc::new(Self::new(fpath, InodeTypes::EXT4_DE_REG_FILE)))
+        } else if file.check_inode_exist(fpath, InodeTypes::EXT4_DE_SYMLINK) {
+            debug!("lookup new SYMLINK FileWrapper");
+            Ok(Arc::new(Self::new(fpath, InodeTypes::EXT4_DE_SYMLINK)))
         } else {
             Err(VfsError::NotFound)
         }
@@ -300,7 +454,9 @@ impl VfsNodeOps for FileWrapper {
         let r = file.file_write(buf);
 
         let _ = file.file_close();
-        r.map_err(|e| e.try_into().unwrap())
+        let len = r.map_err(|e| <i32 as TryInto<AxError>>::try_into(e).unwrap())?;
+        touch_modified(path)?;
+        Ok(len)
     }
 
     fn truncate(&self, size: u64) -> VfsResult {
@@ -314,7 +470,14 @@ impl VfsNodeOps for FileWrapper {
         let t = file.file_truncate(size);
 
         let _ = file.file_close();
-        t.map(|_v| ()).map_err(|e| e.try_into().unwrap())
+        t.map_err(|e| <i32 as TryInto<AxError>>::try_into(e).unwrap())?;
+        touch_modified(path)
+    }
+
+    fn fsync(&self) -> VfsResult {
+        // lwext4 writes its blocks through to the disk, only the block cache
+        // holds them back
+        crate::block_cache::sync_all().map_err(|_| AxError::Io)
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
@@ -324,6 +487,42 @@ impl VfsNodeOps for FileWrapper {
             .map(|_v| ())
             .map_err(|e| e.try_into().unwrap())
     }
+
+    fn readlink(&self, buf: &mut [u8]) -> VfsResult<usize> {
+        read_link(&self.path(), buf)
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        info!("symlink on Ext4fs: {} -> {}", path, target);
+        let fpath = self.path_deal_with(path);
+        if fpath.is_empty() {
+            return Err(AxError::AlreadyExists);
+        }
+        let (fpath, target) = (c_path(&fpath)?, c_path(target)?);
+        ext4_result(unsafe { ext4_fsymlink(target.as_ptr(), fpath.as_ptr()) })
+    }
+
+    fn link(&self, path: &str, src: &VfsNodeRef) -> VfsResult {
+        info!("link on Ext4fs: {}", path);
+        let fpath = self.path_deal_with(path);
+        if fpath.is_empty() {
+            return Err(AxError::AlreadyExists);
+        }
+        // a node of another filesystem can not be linked here
+        let src = src
+            .as_any()
+            .downcast_ref::<Self>()
+            .ok_or(AxError::Unsupported)?;
+        if src.0.lock().get_type() == InodeTypes::EXT4_DE_DIR {
+            return Err(AxError::IsADirectory);
+        }
+        let (src_path, fpath) = (c_path(&src.path())?, c_path(&fpath)?);
+        ext4_result(unsafe { ext4_flink(src_path.as_ptr(), fpath.as_ptr()) })
+    }
+
+    fn as_any(&self) -> &dyn core::any::Any {
+        self
+    }
 }
 
 impl Drop for FileWrapper {
@@ -339,40 +538,20 @@ impl KernelDevOp for Disk {
     //type DevType = Box<Disk>;
     type DevType = Disk;
 
-    fn read(dev: &mut Disk, mut buf: &mut [u8]) -> Result<usize, i32> {
+    fn read(dev: &mut Disk, buf: &mut [u8]) -> Result<usize, i32> {
         debug!("READ block device buf={}", buf.len());
-        let mut read_len = 0;
-        while !buf.is_empty() {
-            match dev.read_one(buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    let tmp = buf;
-                    buf = &mut tmp[n..];
-                    read_len += n;
-                }
-                Err(_e) => return Err(-1),
-            }
-        }
+        let read_len = dev.read_data(buf).map_err(|_| -1)?;
         debug!("READ rt len={}", read_len);
         Ok(read_len)
     }
-    fn write(dev: &mut Self::DevType, mut buf: &[u8]) -> Result<usize, i32> {
+    fn write(dev: &mut Self::DevType, buf: &[u8]) -> Result<usize, i32> {
         debug!("WRITE block device buf={}", buf.len());
-        let mut write_len = 0;
-        while !buf.is_empty() {
-            match dev.write_one(buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    buf = &buf[n..];
-                    write_len += n;
-                }
-                Err(_e) => return Err(-1),
-            }
-        }
+        let write_len = dev.write_data(buf).map_err(|_| -1)?;
         debug!("WRITE rt len={}", write_len);
         Ok(write_len)
     }
-    fn flush(_dev: &mut Self::DevType) -> Result<usize, i32> {
+    fn flush(dev: &mut Self::DevType) -> Result<usize, i32> {
+        dev.sync().map_err(|_| -1)?;
         Ok(0)
     }
     fn seek(dev: &mut Disk, off: i64, whence: i32) -> Result<i64, i32> {
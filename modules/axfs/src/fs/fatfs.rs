@@ -1,22 +1,42 @@
+use alloc::string::String;
 use alloc::sync::Arc;
 use core::cell::UnsafeCell;
+use core::time::Duration;
 
-use axfs_vfs::{VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
+use axfs_vfs::{FileSystemInfo, VfsDirEntry, VfsError, VfsNodePerm, VfsResult};
 use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
-use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
+use fatfs::{Date, DateTime, Dir, DirEntry, File, LossyOemCpConverter, Time, TimeProvider};
+use fatfs::{Read, Seek, SeekFrom, Write};
 
 use crate::dev::Disk;
 
 pub const BLOCK_SIZE: usize = 512;
 
+/// The magic number of FAT filesystems reported by `statfs`.
+const MSDOS_SUPER_MAGIC: u64 = 0x4d44;
+
 pub struct FatFileSystem {
-    inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
+    inner: fatfs::FileSystem<Disk, FatTimeProvider, LossyOemCpConverter>,
     root_dir: UnsafeCell<Option<VfsNodeRef>>,
 }
 
-pub struct FileWrapper<'a>(Mutex<File<'a, Disk, NullTimeProvider, LossyOemCpConverter>>);
-pub struct DirWrapper<'a>(Dir<'a, Disk, NullTimeProvider, LossyOemCpConverter>);
+/// The directory holding the entry of a node and the name of the entry, used
+/// to read the timestamps of the node. `None` for the root directory.
+type EntryRef<'a> = Option<(Dir<'a, Disk, FatTimeProvider, LossyOemCpConverter>, String)>;
+
+pub struct FileWrapper<'a>(
+    Mutex<File<'a, Disk, FatTimeProvider, LossyOemCpConverter>>,
+    EntryRef<'a>,
+);
+pub struct DirWrapper<'a>(
+    Dir<'a, Disk, FatTimeProvider, LossyOemCpConverter>,
+    EntryRef<'a>,
+);
+
+/// Stamps FAT directory entries with the time of [`axfs_vfs::now()`].
+#[derive(Debug, Clone, Copy)]
+pub struct FatTimeProvider;
 
 unsafe impl Sync for FatFileSystem {}
 unsafe impl Send for FatFileSystem {}
@@ -30,8 +50,9 @@ impl FatFileSystem {
     pub fn new(mut disk: Disk) -> Self {
         let opts = fatfs::FormatVolumeOptions::new();
         fatfs::format_volume(&mut disk, opts).expect("failed to format volume");
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
+        let opts = fatfs::FsOptions::new().time_provider(FatTimeProvider);
+        let inner =
+            fatfs::FileSystem::new(disk, opts).expect("failed to initialize FAT filesystem");
         Self {
             inner,
             root_dir: UnsafeCell::new(None),
@@ -40,28 +61,62 @@ impl FatFileSystem {
 
     #[cfg(not(feature = "use-ramdisk"))]
     pub fn new(disk: Disk) -> Self {
-        let inner = fatfs::FileSystem::new(disk, fatfs::FsOptions::new())
-            .expect("failed to initialize FAT filesystem");
-        Self {
+        Self::try_new(disk).expect("failed to initialize FAT filesystem")
+    }
+
+    /// Opens the FAT volume on `disk` without formatting it, fails if the disk
+    /// does not hold a valid FAT filesystem.
+    pub fn try_new(disk: Disk) -> VfsResult<Self> {
+        let opts = fatfs::FsOptions::new().time_provider(FatTimeProvider);
+        let inner = fatfs::FileSystem::new(disk, opts).map_err(as_vfs_err)?;
+        Ok(Self {
             inner,
             root_dir: UnsafeCell::new(None),
-        }
+        })
     }
 
     pub fn init(&'static self) {
         // must be called before later operations
-        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
+        unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir(), None)) }
     }
 
-    fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
-        Arc::new(FileWrapper(Mutex::new(file)))
+    fn new_file<'a>(
+        file: File<'a, Disk, FatTimeProvider, LossyOemCpConverter>,
+        entry: EntryRef<'a>,
+    ) -> Arc<FileWrapper<'a>> {
+        Arc::new(FileWrapper(Mutex::new(file), entry))
     }
 
-    fn new_dir(dir: Dir<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<DirWrapper> {
-        Arc::new(DirWrapper(dir))
+    fn new_dir<'a>(
+        dir: Dir<'a, Disk, FatTimeProvider, LossyOemCpConverter>,
+        entry: EntryRef<'a>,
+    ) -> Arc<DirWrapper<'a>> {
+        Arc::new(DirWrapper(dir, entry))
     }
 }
 
+/// Sets the timestamps of `attr` from the directory entry of the node.
+///
+/// FAT keeps no status change time, the modification time is reported instead.
+fn fill_times(entry: &EntryRef<'_>, attr: &mut VfsNodeAttr) {
+    let Some(entry) = entry.as_ref().and_then(|(dir, name)| find_entry(dir, name)) else {
+        return;
+    };
+    let accessed = DateTime::new(entry.accessed(), Time::new(0, 0, 0, 0));
+    let modified = from_fat_time(entry.modified());
+    attr.set_times(from_fat_time(accessed), modified, modified);
+    attr.set_btime(from_fat_time(entry.created()));
+}
+
+fn find_entry<'a>(
+    dir: &Dir<'a, Disk, FatTimeProvider, LossyOemCpConverter>,
+    name: &str,
+) -> Option<DirEntry<'a, Disk, FatTimeProvider, LossyOemCpConverter>> {
+    dir.iter()
+        .filter_map(Result::ok)
+        .find(|entry| entry.file_name().eq_ignore_ascii_case(name))
+}
+
 impl VfsNodeOps for FileWrapper<'static> {
     axfs_vfs::impl_vfs_non_dir_default! {}
 
@@ -70,7 +125,21 @@ impl VfsNodeOps for FileWrapper<'static> {
         let blocks = (size + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64;
         // FAT fs doesn't support permissions, we just set everything to 755
         let perm = VfsNodePerm::from_bits_truncate(0o755);
-        Ok(VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks))
+        let mut attr = VfsNodeAttr::new(perm, VfsNodeType::File, size, blocks);
+        fill_times(&self.1, &mut attr);
+        Ok(attr)
+    }
+
+    #[allow(deprecated)]
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        let mut file = self.0.lock();
+        if let Some(atime) = atime {
+            file.set_accessed(to_fat_time(atime).date);
+        }
+        if let Some(mtime) = mtime {
+            file.set_modified(to_fat_time(mtime));
+        }
+        file.flush().map_err(as_vfs_err)
     }
 
     fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
@@ -131,6 +200,11 @@ impl VfsNodeOps for FileWrapper<'static> {
         file.seek(SeekFrom::Start(size)).map_err(as_vfs_err)?; // TODO: more efficient
         file.truncate().map_err(as_vfs_err)
     }
+
+    fn fsync(&self) -> VfsResult {
+        // also writes back the directory entry and the blocks cached for the disk
+        self.0.lock().flush().map_err(as_vfs_err)
+    }
 }
 
 impl VfsNodeOps for DirWrapper<'static> {
@@ -138,18 +212,20 @@ impl VfsNodeOps for DirWrapper<'static> {
 
     fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
         // FAT fs doesn't support permissions, we just set everything to 755
-        Ok(VfsNodeAttr::new(
+        let mut attr = VfsNodeAttr::new(
             VfsNodePerm::from_bits_truncate(0o755),
             VfsNodeType::Dir,
             BLOCK_SIZE as u64,
             1,
-        ))
+        );
+        fill_times(&self.1, &mut attr);
+        Ok(attr)
     }
 
     fn parent(&self) -> Option<VfsNodeRef> {
         self.0
             .open_dir("..")
-            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir)))
+            .map_or(None, |dir| Some(FatFileSystem::new_dir(dir, None)))
     }
 
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
@@ -163,10 +239,14 @@ impl VfsNodeOps for DirWrapper<'static> {
         }
 
         // TODO: use `fatfs::Dir::find_entry`, but it's not public.
+        let entry = match path.rsplit_once('/') {
+            Some((parent, name)) => self.0.open_dir(parent).ok().map(|dir| (dir, name.into())),
+            None => Some((self.0.clone(), path.into())),
+        };
         if let Ok(file) = self.0.open_file(path) {
-            Ok(FatFileSystem::new_file(file))
+            Ok(FatFileSystem::new_file(file, entry))
         } else if let Ok(dir) = self.0.open_dir(path) {
-            Ok(FatFileSystem::new_dir(dir))
+            Ok(FatFileSystem::new_dir(dir, entry))
         } else {
             Err(VfsError::NotFound)
         }
@@ -240,51 +320,100 @@ impl VfsNodeOps for DirWrapper<'static> {
 }
 
 impl VfsOps for FatFileSystem {
+    fn statfs(&self) -> VfsResult<FileSystemInfo> {
+        // FAT allocates whole clusters and has no inode table
+        let stats = self.inner.stats().map_err(as_vfs_err)?;
+        let free = stats.free_clusters().into();
+        let mut info = FileSystemInfo::new(MSDOS_SUPER_MAGIC, stats.cluster_size().into());
+        info.set_blocks(stats.total_clusters().into(), free, free);
+        info.set_fsid(self.inner.volume_id().into());
+        Ok(info)
+    }
+
     fn root_dir(&self) -> VfsNodeRef {
         let root_dir = unsafe { (*self.root_dir.get()).as_ref().unwrap() };
         root_dir.clone()
     }
 }
 
+impl TimeProvider for FatTimeProvider {
+    fn get_current_date(&self) -> Date {
+        to_fat_time(axfs_vfs::now()).date
+    }
+
+    fn get_current_date_time(&self) -> DateTime {
+        to_fat_time(axfs_vfs::now())
+    }
+}
+
+/// Converts a duration since the Unix epoch to a FAT date and time, clamped to
+/// the years 1980 to 2107 that FAT can represent.
+fn to_fat_time(time: Duration) -> DateTime {
+    let secs = time.as_secs();
+    let (year, month, day) = civil_from_days(secs / 86400);
+    if year < 1980 {
+        return DateTime::new(Date::new(1980, 1, 1), Time::new(0, 0, 0, 0));
+    } else if year > 2107 {
+        return DateTime::new(Date::new(2107, 12, 31), Time::new(23, 59, 59, 999));
+    }
+    let secs = secs % 86400;
+    let time = Time::new(
+        (secs / 3600) as u16,
+        (secs / 60 % 60) as u16,
+        (secs % 60) as u16,
+        time.subsec_millis() as u16,
+    );
+    DateTime::new(Date::new(year as u16, month as u16, day as u16), time)
+}
+
+/// Converts a FAT date and time to a duration since the Unix epoch.
+fn from_fat_time(time: DateTime) -> Duration {
+    let days = days_from_civil(time.date.year.into(), time.date.month, time.date.day);
+    let secs = (time.time.hour as u64 * 60 + time.time.min as u64) * 60 + time.time.sec as u64;
+    Duration::from_secs(days * 86400 + secs) + Duration::from_millis(time.time.millis.into())
+}
+
+/// Returns the civil date of the given number of days since 1970-01-01.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468; // since 0000-03-01
+    let (era, doe) = (days / 146097, days % 146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = era * 400 + yoe + (month <= 2) as u64;
+    (year, month, day)
+}
+
+/// Returns the number of days since 1970-01-01 of the given civil date, which
+/// must not be earlier than that.
+fn days_from_civil(year: u64, month: u16, day: u16) -> u64 {
+    let (month, day) = (month as u64, day as u64);
+    let year = if month <= 2 { year - 1 } else { year };
+    let (era, yoe) = (year / 400, year % 400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
 impl fatfs::IoBase for Disk {
     type Error = ();
 }
 
 impl Read for Disk {
-    fn read(&mut self, mut buf: &mut [u8]) -> Result<usize, Self::Error> {
-        let mut read_len = 0;
-        while !buf.is_empty() {
-            match self.read_one(buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    let tmp = buf;
-                    buf = &mut tmp[n..];
-                    read_len += n;
-                }
-                Err(_) => return Err(()),
-            }
-        }
-        Ok(read_len)
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.read_data(buf).map_err(|_| ())
     }
 }
 
 impl Write for Disk {
-    fn write(&mut self, mut buf: &[u8]) -> Result<usize, Self::Error> {
-        let mut write_len = 0;
-        while !buf.is_empty() {
-            match self.write_one(buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    buf = &buf[n..];
-                    write_len += n;
-                }
-                Err(_) => return Err(()),
-            }
-        }
-        Ok(write_len)
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.write_data(buf).map_err(|_| ())
     }
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        self.sync().map_err(|_| ())
     }
 }
 
@@ -4,7 +4,6 @@ cfg_if::cfg_if! {
         /// The block size of the file system.
         pub const BLOCK_SIZE: usize = 512;
     } else if #[cfg(feature = "fatfs")] {
-        pub mod fatfs;
         pub use fatfs::BLOCK_SIZE;
     } else if #[cfg(feature = "ext4fs")] {
         pub mod ext4fs;
@@ -12,6 +11,10 @@ cfg_if::cfg_if! {
     }
 }
 
+// FAT volumes can also be mounted at runtime, even if it is not the root filesystem
+#[cfg(feature = "fatfs")]
+pub mod fatfs;
+
 #[cfg(feature = "devfs")]
 pub use axfs_devfs as devfs;
 
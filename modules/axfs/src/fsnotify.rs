@@ -0,0 +1,116 @@
+//! Notification of changes made to the filesystems, the basis of inotify.
+//!
+//! The operations of this crate report what happened to which path to every
+//! registered [`FsWatcher`]. The watchers decide themselves which paths they
+//! are interested in. Operations relative to a directory node other than the
+//! current directory are not reported, since their paths are unknown.
+
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use axfs_vfs::VfsNodeRef;
+use axsync::Mutex;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+bitflags::bitflags! {
+    /// Kinds of changes, with the same values as the inotify event masks.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct FsEvent: u32 {
+        /// The content of the file was modified.
+        const MODIFY = 0x2;
+        /// The metadata of the file, e.g. permissions or timestamps, changed.
+        const ATTRIB = 0x4;
+        /// A file opened for writing was closed.
+        const CLOSE_WRITE = 0x8;
+        /// A file not opened for writing was closed.
+        const CLOSE_NOWRITE = 0x10;
+        /// The file was renamed away from the path.
+        const MOVED_FROM = 0x40;
+        /// The file was renamed to the path.
+        const MOVED_TO = 0x80;
+        /// The file was created.
+        const CREATE = 0x100;
+        /// The file was removed.
+        const DELETE = 0x200;
+        /// The file is a directory.
+        const ISDIR = 0x4000_0000;
+    }
+}
+
+/// An observer of the changes made to the filesystems.
+pub trait FsWatcher: Send + Sync {
+    /// Something happened to the file at the absolute path `path`.
+    ///
+    /// A rename is reported as [`MOVED_FROM`](FsEvent::MOVED_FROM) on the old
+    /// path followed by [`MOVED_TO`](FsEvent::MOVED_TO) on the new one, both
+    /// with the same nonzero `cookie`. The cookie is 0 for other events.
+    fn handle(&self, path: &str, event: FsEvent, cookie: u32);
+}
+
+static WATCHERS: Mutex<Vec<Weak<dyn FsWatcher>>> = Mutex::new(Vec::new());
+
+/// Number of live watchers, so that nothing is done while nobody watches.
+static NUM_WATCHERS: AtomicUsize = AtomicUsize::new(0);
+
+static NEXT_COOKIE: AtomicU32 = AtomicU32::new(1);
+
+/// Registers `watcher`, it is removed automatically once dropped.
+pub fn add_watcher(watcher: &Arc<dyn FsWatcher>) {
+    let mut watchers = WATCHERS.lock();
+    watchers.retain(|w| w.strong_count() > 0);
+    watchers.push(Arc::downgrade(watcher));
+    NUM_WATCHERS.store(watchers.len(), Ordering::Relaxed);
+}
+
+fn dispatch(path: &str, event: FsEvent, cookie: u32) {
+    let watchers: Vec<Arc<dyn FsWatcher>> = {
+        let mut watchers = WATCHERS.lock();
+        watchers.retain(|w| w.strong_count() > 0);
+        NUM_WATCHERS.store(watchers.len(), Ordering::Relaxed);
+        watchers.iter().filter_map(|w| w.upgrade()).collect()
+    };
+    // the lock is not held, a watcher may register another one meanwhile
+    for watcher in watchers {
+        watcher.handle(path, event, cookie);
+    }
+}
+
+/// Reports `event` on the absolute path `path`.
+pub fn notify(path: &str, event: FsEvent) {
+    if NUM_WATCHERS.load(Ordering::Relaxed) > 0 {
+        dispatch(path, event, 0);
+    }
+}
+
+/// Reports that the file at `old` has been renamed to `new`.
+pub(crate) fn notify_move(old: &str, new: &str, is_dir: bool) {
+    if NUM_WATCHERS.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    let isdir = if is_dir {
+        FsEvent::ISDIR
+    } else {
+        FsEvent::empty()
+    };
+    let cookie = NEXT_COOKIE.fetch_add(1, Ordering::Relaxed).max(1);
+    dispatch(old, FsEvent::MOVED_FROM | isdir, cookie);
+    dispatch(new, FsEvent::MOVED_TO | isdir, cookie);
+}
+
+/// Reports `event` on `path` relative to `dir`.
+pub(crate) fn notify_at(dir: Option<&VfsNodeRef>, path: &str, event: FsEvent) {
+    if NUM_WATCHERS.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    if let Some(path) = event_path(dir, path) {
+        dispatch(&path, event, 0);
+    }
+}
+
+/// The absolute path of `path` relative to `dir`, if it can be known.
+pub(crate) fn event_path(dir: Option<&VfsNodeRef>, path: &str) -> Option<String> {
+    if dir.is_some() && !path.starts_with('/') {
+        return None;
+    }
+    crate::root::absolute_path(path).ok()
+}
@@ -10,11 +10,15 @@
 //!    **enabled** by default.
 //! - `ramfs`: Mount [`axfs_ramfs::RamFileSystem`] on `/tmp`. This feature is
 //!    **enabled** by default.
+//! - `sysfs`: Mount a sysfs describing the CPUs, block devices and network
+//!    interfaces on `/sys`. This feature is **enabled** by default.
 //! - `myfs`: Allow users to define their custom filesystems to override the
 //!    default. In this case, [`MyFileSystemIf`] is required to be implemented
 //!    to create and initialize other filesystems. This feature is **disabled** by
 //!    by default, but it will override other filesystem selection features if
 //!    both are enabled.
+//! - `monolithic`: Resolve paths in the mount table of the current mount
+//!    namespace, which is provided through [`api::MountTableIf`].
 //!
 //! [FAT]: https://en.wikipedia.org/wiki/File_Allocation_Table
 //! [`MyFileSystemIf`]: fops::MyFileSystemIf
@@ -26,10 +30,17 @@
 extern crate log;
 extern crate alloc;
 
+mod block_cache;
 mod dev;
+mod file_lock;
 mod fs;
+mod fsnotify;
+#[cfg(feature = "devfs")]
+mod loop_dev;
 mod mounts;
 mod root;
+#[cfg(feature = "sysfs")]
+mod sysfs;
 
 pub use fs::BLOCK_SIZE;
 pub mod api;
@@ -43,8 +54,19 @@ use axdriver::{prelude::*, AxDeviceContainer};
 /// Initializes filesystems by block devices.
 pub fn init_filesystems(mut blk_devs: AxDeviceContainer<AxBlockDevice>) {
     info!("Initialize filesystems...");
+    axfs_vfs::set_clock(axhal::time::current_time);
 
     let dev = blk_devs.take_one().expect("No block device found!");
     info!("  use block device 0: {:?}", dev.device_name());
-    self::root::init_rootfs(self::dev::Disk::new(dev));
+    #[cfg(feature = "sysfs")]
+    let (name, devno) = self::sysfs::disk_name(dev.device_name());
+    let disk = self::dev::Disk::new(dev);
+    #[cfg(feature = "sysfs")]
+    {
+        let size = disk.size();
+        if let Err(e) = self::sysfs::add_block_device(name, devno, move || size, || false) {
+            warn!("failed to add {} to sysfs: {:?}", name, e);
+        }
+    }
+    self::root::init_rootfs(disk);
 }
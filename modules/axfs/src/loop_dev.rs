@@ -0,0 +1,356 @@
+//! Loop devices, which present a regular file as a block device.
+//!
+//! `/dev/loopN` are the devices and `/dev/loop-control` hands out free ones.
+//! Once a disk image is attached, the device can be mounted like any other
+//! block device.
+
+use alloc::{boxed::Box, collections::BTreeMap, format, string::String, sync::Arc};
+use axerrno::{ax_err, AxResult};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsResult};
+use axsync::Mutex;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::fs::devfs::DeviceFileSystem;
+
+/// Number of loop devices created at boot.
+const INIT_LOOP_DEVICES: usize = 8;
+
+/// Upper bound of loop device numbers.
+pub const MAX_LOOP_DEVICES: usize = 256;
+
+const BLOCK_SIZE: u64 = 512;
+
+/// The major device number of loop devices.
+#[cfg(feature = "sysfs")]
+const LOOP_MAJOR: u32 = 7;
+
+/// The file a loop device is attached to.
+#[derive(Clone)]
+pub struct LoopBacking {
+    /// The backing file.
+    pub node: VfsNodeRef,
+    /// The path the backing file was opened at.
+    pub path: String,
+    /// Offset of the device data in the file.
+    pub offset: u64,
+    /// Size of the device in bytes, 0 means up to the end of the file.
+    pub size_limit: u64,
+    /// Whether the device refuses writes.
+    pub read_only: bool,
+    /// Whether the device is detached when it is closed for the last time.
+    pub autoclear: bool,
+}
+
+impl LoopBacking {
+    /// The size of the device in bytes.
+    fn size(&self) -> u64 {
+        let file_size = self.node.get_attr().map_or(0, |attr| attr.size());
+        let size = file_size.saturating_sub(self.offset);
+        if self.size_limit != 0 {
+            size.min(self.size_limit)
+        } else {
+            size
+        }
+    }
+}
+
+/// A loop device, `/dev/loopN`.
+pub struct LoopDevice {
+    index: usize,
+    backing: Mutex<Option<LoopBacking>>,
+    /// Number of open files and filesystems using the device.
+    open_count: AtomicUsize,
+}
+
+impl LoopDevice {
+    fn new(index: usize) -> Self {
+        Self {
+            index,
+            backing: Mutex::new(None),
+            open_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// The device number `N` of `/dev/loopN`.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The size of the device in bytes, 0 if the device is free.
+    pub fn size(&self) -> u64 {
+        self.backing.lock().as_ref().map_or(0, |b| b.size())
+    }
+
+    /// The attached file, `None` if the device is free.
+    pub fn backing(&self) -> Option<LoopBacking> {
+        self.backing.lock().clone()
+    }
+
+    /// Attaches the file `node` opened at `path`.
+    ///
+    /// Returns [`ResourceBusy`](axerrno::AxError::ResourceBusy) if a file is
+    /// already attached.
+    pub fn attach(&self, node: VfsNodeRef, path: &str, read_only: bool) -> AxResult {
+        let ty = node.get_attr()?.file_type();
+        if !(ty.is_file() || ty.is_block_device()) {
+            return ax_err!(InvalidInput, "a loop device needs a regular file");
+        }
+        let mut backing = self.backing.lock();
+        if backing.is_some() {
+            return ax_err!(ResourceBusy);
+        }
+        *backing = Some(LoopBacking {
+            node,
+            path: String::from(path),
+            offset: 0,
+            size_limit: 0,
+            read_only,
+            autoclear: false,
+        });
+        Ok(())
+    }
+
+    /// Detaches the attached file.
+    ///
+    /// A filesystem mounted on the device sees I/O errors afterwards.
+    /// Returns [`NotFound`](axerrno::AxError::NotFound) if the device is free.
+    pub fn detach(&self) -> AxResult {
+        match self.backing.lock().take() {
+            Some(backing) => {
+                backing.node.fsync().ok();
+                Ok(())
+            }
+            None => ax_err!(NotFound),
+        }
+    }
+
+    /// Changes the offset, size limit and autoclear flag of the attached file.
+    pub fn set_status(&self, offset: u64, size_limit: u64, autoclear: bool) -> AxResult {
+        match self.backing.lock().as_mut() {
+            Some(backing) => {
+                backing.offset = offset;
+                backing.size_limit = size_limit;
+                backing.autoclear = autoclear;
+                Ok(())
+            }
+            None => ax_err!(NotFound),
+        }
+    }
+}
+
+impl VfsNodeOps for LoopDevice {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let size = self.size();
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::BlockDevice,
+            size,
+            size.div_ceil(BLOCK_SIZE),
+        ))
+    }
+
+    fn open(&self) -> VfsResult {
+        self.open_count.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
+    fn release(&self) -> VfsResult {
+        if self.open_count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let mut backing = self.backing.lock();
+            if backing.as_ref().is_some_and(|b| b.autoclear) {
+                backing.take();
+            }
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let backing = match self.backing() {
+            Some(backing) => backing,
+            None => return Ok(0),
+        };
+        let size = backing.size();
+        if offset >= size {
+            return Ok(0);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        backing
+            .node
+            .read_at(backing.offset + offset, &mut buf[..len])
+    }
+
+    fn write_at(&self, offset: u64, buf: &[u8]) -> VfsResult<usize> {
+        let backing = match self.backing() {
+            Some(backing) => backing,
+            None => return ax_err!(NotFound),
+        };
+        if backing.read_only {
+            return ax_err!(PermissionDenied);
+        }
+        let size = backing.size();
+        if offset >= size {
+            return ax_err!(StorageFull);
+        }
+        let len = buf.len().min((size - offset) as usize);
+        backing.node.write_at(backing.offset + offset, &buf[..len])
+    }
+
+    fn fsync(&self) -> VfsResult {
+        match self.backing() {
+            Some(backing) => backing.node.fsync(),
+            None => Ok(()),
+        }
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// `/dev/loop-control`, all requests go through ioctl.
+struct LoopControl;
+
+impl VfsNodeOps for LoopControl {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o660),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+static LOOP_DEVICES: Mutex<BTreeMap<usize, Arc<LoopDevice>>> = Mutex::new(BTreeMap::new());
+
+static LOOP_CONTROL: Mutex<Option<VfsNodeRef>> = Mutex::new(None);
+
+/// Creates `/dev/loop-control` and the first loop devices.
+pub(crate) fn init(devfs: &DeviceFileSystem) {
+    let control: VfsNodeRef = Arc::new(LoopControl);
+    devfs.add("loop-control", control.clone());
+    *LOOP_CONTROL.lock() = Some(control);
+    let mut devices = LOOP_DEVICES.lock();
+    for index in 0..INIT_LOOP_DEVICES {
+        let dev = Arc::new(LoopDevice::new(index));
+        devfs.add(device_name(index), dev.clone());
+        #[cfg(feature = "sysfs")]
+        add_to_sysfs(&dev);
+        devices.insert(index, dev);
+    }
+}
+
+/// Adds `/sys/block/loopN`, with the attached file in its `loop` directory.
+#[cfg(feature = "sysfs")]
+fn add_to_sysfs(dev: &Arc<LoopDevice>) {
+    let name = format!("loop{}", dev.index);
+    let devno = (LOOP_MAJOR, dev.index as u32);
+    let show = |f: fn(&LoopBacking) -> String| {
+        let dev = dev.clone();
+        move || dev.backing().map_or(String::new(), |b| f(&b))
+    };
+    let size = {
+        let dev = dev.clone();
+        move || dev.size()
+    };
+    let read_only = {
+        let dev = dev.clone();
+        move || dev.backing().is_some_and(|b| b.read_only)
+    };
+    let result = crate::sysfs::add_block_device(&name, devno, size, read_only).and_then(|_| {
+        let attr = |attr: &str, f: fn(&LoopBacking) -> String| {
+            crate::sysfs::add_attr(&format!("block/{}/loop/{}", name, attr), show(f))
+        };
+        attr("backing_file", |b| format!("{}\n", b.path))?;
+        attr("offset", |b| format!("{}\n", b.offset))?;
+        attr("sizelimit", |b| format!("{}\n", b.size_limit))?;
+        attr("autoclear", |b| format!("{}\n", b.autoclear as u8))
+    });
+    if let Err(e) = result {
+        warn!("failed to add {} to sysfs: {:?}", name, e);
+    }
+}
+
+fn device_name(index: usize) -> &'static str {
+    // the names of devfs nodes live as long as the filesystem
+    Box::leak(format!("loop{}", index).into_boxed_str())
+}
+
+fn same_node(node: &VfsNodeRef, other: &VfsNodeRef) -> bool {
+    Arc::as_ptr(node) as *const () == Arc::as_ptr(other) as *const ()
+}
+
+/// Whether `node` is `/dev/loop-control`.
+pub fn is_loop_control(node: &VfsNodeRef) -> bool {
+    LOOP_CONTROL
+        .lock()
+        .as_ref()
+        .is_some_and(|control| same_node(node, control))
+}
+
+/// The loop device `node` refers to, if any.
+pub fn loop_device_of(node: &VfsNodeRef) -> Option<Arc<LoopDevice>> {
+    LOOP_DEVICES
+        .lock()
+        .values()
+        .find(|dev| same_node(node, &(dev.clone() as VfsNodeRef)))
+        .cloned()
+}
+
+/// Creates `/dev/loop<index>`.
+pub fn add_loop_device(index: usize) -> AxResult<Arc<LoopDevice>> {
+    if index >= MAX_LOOP_DEVICES {
+        return ax_err!(InvalidInput);
+    }
+    let devfs = crate::mounts::DEVFS
+        .try_get()
+        .ok_or(axerrno::AxError::NotFound)?;
+    let mut devices = LOOP_DEVICES.lock();
+    if devices.contains_key(&index) {
+        return ax_err!(AlreadyExists);
+    }
+    let dev = Arc::new(LoopDevice::new(index));
+    devfs.add(device_name(index), dev.clone());
+    #[cfg(feature = "sysfs")]
+    add_to_sysfs(&dev);
+    devices.insert(index, dev.clone());
+    Ok(dev)
+}
+
+/// Removes `/dev/loop<index>`, which must be free.
+pub fn remove_loop_device(index: usize) -> AxResult {
+    let devfs = crate::mounts::DEVFS
+        .try_get()
+        .ok_or(axerrno::AxError::NotFound)?;
+    let mut devices = LOOP_DEVICES.lock();
+    match devices.get(&index) {
+        Some(dev) if dev.backing.lock().is_some() => ax_err!(ResourceBusy),
+        Some(_) => {
+            devices.remove(&index);
+            devfs.remove(&format!("loop{}", index));
+            #[cfg(feature = "sysfs")]
+            crate::sysfs::remove_block_device(
+                &format!("loop{}", index),
+                (LOOP_MAJOR, index as u32),
+            )
+            .ok();
+            Ok(())
+        }
+        None => ax_err!(NotFound),
+    }
+}
+
+/// Returns a free loop device, creating one if all are in use.
+pub fn free_loop_device() -> AxResult<Arc<LoopDevice>> {
+    let next = {
+        let devices = LOOP_DEVICES.lock();
+        if let Some(dev) = devices.values().find(|dev| dev.backing.lock().is_none()) {
+            return Ok(dev.clone());
+        }
+        (0..MAX_LOOP_DEVICES).find(|index| !devices.contains_key(index))
+    };
+    match next {
+        Some(index) => add_loop_device(index),
+        None => ax_err!(StorageFull),
+    }
+}
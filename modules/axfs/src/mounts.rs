@@ -1,8 +1,88 @@
-use alloc::sync::Arc;
+use alloc::{sync::Arc, vec::Vec};
+use axerrno::{ax_err, AxResult};
 use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
+use axsync::Mutex;
 
 use crate::fs;
 
+/// The magic number of `proc` reported by `statfs`.
+#[cfg(feature = "procfs")]
+const PROC_SUPER_MAGIC: u64 = 0x9fa0;
+
+/// Creates a filesystem instance from the device or image file at `source`.
+pub type FileSystemCtor = fn(source: &str) -> AxResult<Arc<dyn VfsOps>>;
+
+/// Filesystem types provided by other modules, e.g. `devpts`.
+static FILESYSTEMS: Mutex<Vec<(&'static str, FileSystemCtor)>> = Mutex::new(Vec::new());
+
+pub(crate) fn register_filesystem(fstype: &'static str, ctor: FileSystemCtor) {
+    let mut filesystems = FILESYSTEMS.lock();
+    filesystems.retain(|(name, _)| *name != fstype);
+    filesystems.push((fstype, ctor));
+}
+
+/// Creates a new instance of the filesystem `fstype` to be mounted at runtime,
+/// configured by the mount options `data`.
+///
+/// Returns [`Unsupported`](axerrno::AxError::Unsupported) if the type is unknown.
+pub(crate) fn new_filesystem(
+    fstype: &str,
+    source: &str,
+    #[allow(unused_variables)] data: &str,
+) -> AxResult<Arc<dyn VfsOps>> {
+    let registered = FILESYSTEMS
+        .lock()
+        .iter()
+        .find(|(name, _)| *name == fstype)
+        .map(|(_, ctor)| *ctor);
+    if let Some(ctor) = registered {
+        return ctor(source);
+    }
+    match fstype {
+        #[cfg(feature = "ramfs")]
+        "tmpfs" | "ramfs" => Ok(ramfs(data)?),
+        #[cfg(feature = "procfs")]
+        "proc" => Ok(procfs()?),
+        #[cfg(feature = "sysfs")]
+        "sysfs" => Ok(crate::sysfs::sysfs()?),
+        // there is only one device filesystem, like `devtmpfs` on Linux
+        #[cfg(feature = "devfs")]
+        "devtmpfs" => match DEVFS.try_get() {
+            Some(devfs) => Ok(devfs.clone()),
+            None => ax_err!(Unsupported),
+        },
+        #[cfg(feature = "fatfs")]
+        "vfat" | "fat" | "msdos" => Ok(fatfs(source)?),
+        // lwext4 keeps a single volume registered at its own `/`, so another
+        // ext4 volume cannot be opened while the root filesystem is ext4
+        "ext4" | "ext3" | "ext2" => ax_err!(Unsupported, "cannot mount another ext4 volume"),
+        _ => ax_err!(Unsupported, "unknown filesystem type"),
+    }
+}
+
+/// Opens the FAT volume stored in the block device or image file at `source`.
+#[cfg(feature = "fatfs")]
+fn fatfs(source: &str) -> AxResult<Arc<fs::fatfs::FatFileSystem>> {
+    // The nodes of a FAT filesystem borrow it for `'static`, so the volumes
+    // mounted at runtime are kept here and never freed.
+    static FAT_VOLUMES: Mutex<Vec<Arc<fs::fatfs::FatFileSystem>>> = Mutex::new(Vec::new());
+
+    let node = crate::root::lookup(None, source)?;
+    let dev = crate::dev::FileBlockDevice::new(source, node)?;
+    let disk = crate::dev::Disk::from_driver(alloc::boxed::Box::new(dev));
+    let fat = Arc::new(fs::fatfs::FatFileSystem::try_new(disk)?);
+    FAT_VOLUMES.lock().push(fat.clone());
+    // SAFETY: `FAT_VOLUMES` holds a reference forever
+    let volume: &'static fs::fatfs::FatFileSystem = unsafe { &*Arc::as_ptr(&fat) };
+    volume.init();
+    Ok(fat)
+}
+
+/// 挂载在 `/dev` 的设备文件系统，其他模块可以在运行时向其中添加设备
+#[cfg(feature = "devfs")]
+pub(crate) static DEVFS: lazy_init::LazyInit<Arc<fs::devfs::DeviceFileSystem>> =
+    lazy_init::LazyInit::new();
+
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
@@ -18,29 +98,32 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     foo_dir.add("bar", Arc::new(bar));
     devfs.add("random", Arc::new(random));
     devfs.add("urandom", Arc::new(urandom));
+    crate::loop_dev::init(&devfs);
+    // where a tmpfs is mounted for POSIX shared memory
+    devfs.mkdir("shm");
     #[cfg(feature = "monolithic")]
     {
         // 添加dev文件系统下的配置文件
         // busybox的时候要用到
         // devfs不支持可修改的file，因此取巧直接用了ramfs提供的file实现
-        let testshm = fs::ramfs::FileNode::new();
         let testrtc = fs::ramfs::FileNode::new();
-        let shm_dir = devfs.mkdir("shm");
-        shm_dir.add("testshm", Arc::new(testshm));
         let rtc_dir = devfs.mkdir("misc");
         rtc_dir.add("rtc", Arc::new(testrtc));
     }
-    Arc::new(devfs)
+    let devfs = Arc::new(devfs);
+    DEVFS.init_by(devfs.clone());
+    devfs
 }
 
+/// Creates a tmpfs configured by the mount options `options`.
 #[cfg(feature = "ramfs")]
-pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
-    Arc::new(fs::ramfs::RamFileSystem::new())
+pub(crate) fn ramfs(options: &str) -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
+    Ok(Arc::new(fs::ramfs::RamFileSystem::with_options(options)?))
 }
 
 #[cfg(feature = "procfs")]
 pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let procfs = fs::ramfs::RamFileSystem::new();
+    let procfs = fs::ramfs::RamFileSystem::new().with_magic(PROC_SUPER_MAGIC);
     let proc_root = procfs.root_dir();
 
     // Create /proc/sys/net/core/somaxconn
@@ -72,35 +155,3 @@ pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     }
     Ok(Arc::new(procfs))
 }
-
-#[cfg(feature = "sysfs")]
-pub(crate) fn sysfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
-    let sysfs = fs::ramfs::RamFileSystem::new();
-    let sys_root = sysfs.root_dir();
-
-    // Create /sys/kernel/mm/transparent_hugepage/enabled
-    sys_root.create("kernel", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage", VfsNodeType::Dir)?;
-    sys_root.create("kernel/mm/transparent_hugepage/enabled", VfsNodeType::File)?;
-    let file_hp = sys_root
-        .clone()
-        .lookup("./kernel/mm/transparent_hugepage/enabled")?;
-    file_hp.write_at(0, b"always [madvise] never\n")?;
-
-    // Create /sys/devices/system/clocksource/clocksource0/current_clocksource
-    sys_root.create("devices", VfsNodeType::Dir)?;
-    sys_root.create("devices/system", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource", VfsNodeType::Dir)?;
-    sys_root.create("devices/system/clocksource/clocksource0", VfsNodeType::Dir)?;
-    sys_root.create(
-        "devices/system/clocksource/clocksource0/current_clocksource",
-        VfsNodeType::File,
-    )?;
-    let file_cc = sys_root
-        .clone()
-        .lookup("devices/system/clocksource/clocksource0/current_clocksource")?;
-    file_cc.write_at(0, b"tsc\n")?;
-
-    Ok(Arc::new(sysfs))
-}
@@ -1,113 +1,436 @@
 //! Root directory of the filesystem
 //!
-//! TODO: it doesn't work very well if the mount points have containment relationships.
+//! The filesystems attached to the directory tree are recorded in a
+//! [`MountTable`]. Each mount namespace has a table of its own, and a path is
+//! resolved in the table of the current namespace at the deepest mount point
+//! containing it, so mount points can be nested.
+//!
+//! Symbolic links are followed by the root directory before a path is split at
+//! its mount point, so a link may point into another filesystem.
 
 use alloc::{
+    format,
     string::{String, ToString},
     sync::Arc,
     vec::Vec,
 };
 use axerrno::{ax_err, AxError, AxResult};
-use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
+use axfs_vfs::{FileSystemFlags, FileSystemInfo, PAGE_SIZE};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps, VfsResult};
 use axsync::Mutex;
+use core::time::Duration;
 use lazy_init::LazyInit;
 
+use crate::fsnotify::{self, FsEvent};
 use crate::{api::FileType, fs, mounts};
 
+/// Maximum number of symbolic links followed when resolving a path.
+const MAX_SYMLINKS: usize = 40;
+
+/// Maximum length of the target of a symbolic link.
+const PATH_MAX: usize = 4096;
+
 static CURRENT_DIR_PATH: Mutex<String> = Mutex::new(String::new());
 static CURRENT_DIR: LazyInit<Mutex<VfsNodeRef>> = LazyInit::new();
 
+/// Description of a mount point, as listed in `/proc/mounts`.
+#[derive(Clone, Debug)]
+pub struct MountInfo {
+    /// The device or file the filesystem was mounted from.
+    pub source: String,
+    /// The absolute path of the mount point.
+    pub path: String,
+    /// The filesystem type, e.g. `vfat` or `tmpfs`.
+    pub fstype: String,
+    /// Whether the files under the mount point can not be modified.
+    pub read_only: bool,
+}
+
+#[derive(Clone)]
 struct MountPoint {
-    path: &'static str,
+    info: MountInfo,
     fs: Arc<dyn VfsOps>,
+    /// The directory shown at the mount point, which is the root of `fs`
+    /// unless it is a bind mount of a subdirectory.
+    root: VfsNodeRef,
+}
+
+/// The mount points of a mount namespace, except the root filesystem.
+pub struct MountTable {
+    mounts: Mutex<Vec<MountPoint>>,
+}
+
+/// Resolves the mount table of the current task.
+#[cfg(feature = "monolithic")]
+#[crate_interface::def_interface]
+pub trait MountTableIf {
+    /// Returns the mount table of the mount namespace the current task is in,
+    /// or `None` for the table set up at boot.
+    fn current_mount_table() -> Option<Arc<MountTable>>;
+
+    /// Whether a task in the current mount namespace has a file open or its
+    /// working directory at or under the canonical absolute `path`.
+    fn mount_in_use(path: &str) -> bool;
 }
 
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
-    mounts: Vec<MountPoint>,
+    init_table: Arc<MountTable>,
+}
+
+/// A path split at the deepest mount point containing it.
+struct MountedPath {
+    /// The filesystem mounted there.
+    fs: Arc<dyn VfsOps>,
+    /// The directory shown at the mount point.
+    root: VfsNodeRef,
+    read_only: bool,
+    /// The mount point, `/` for the root filesystem.
+    mount_path: String,
+    /// The rest of the path, relative to `root`.
+    rest: String,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(info: MountInfo, fs: Arc<dyn VfsOps>, root: VfsNodeRef) -> Self {
+        Self { info, fs, root }
     }
 }
 
 impl Drop for MountPoint {
     fn drop(&mut self) {
-        self.fs.umount().ok();
+        // bind mounts and copies in other namespaces may still use the filesystem
+        if Arc::strong_count(&self.fs) == 1 {
+            self.fs.umount().ok();
+        }
+    }
+}
+
+/// Whether `path` is `dir` or inside it, both are canonical absolute paths.
+fn is_under(path: &str, dir: &str) -> bool {
+    dir == "/"
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+impl MountTable {
+    const fn new() -> Self {
+        Self {
+            mounts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Creates a table with the same mount points, for a new mount namespace.
+    pub fn copy(&self) -> Arc<Self> {
+        Arc::new(Self {
+            mounts: Mutex::new(self.mounts.lock().clone()),
+        })
     }
+
+    /// Lists the mount points in the order they were mounted.
+    pub fn mounts(&self) -> Vec<MountInfo> {
+        self.mounts
+            .lock()
+            .iter()
+            .map(|mp| mp.info.clone())
+            .collect()
+    }
+
+    /// Whether something is mounted at the canonical absolute `path`.
+    pub fn contains(&self, path: &str) -> bool {
+        self.mounts.lock().iter().any(|mp| mp.info.path == path)
+    }
+
+    /// Changes whether the topmost mount at `path` is read-only.
+    fn remount(&self, path: &str, read_only: bool) -> AxResult {
+        let mut mounts = self.mounts.lock();
+        match mounts.iter_mut().rev().find(|mp| mp.info.path == path) {
+            Some(mp) => {
+                mp.info.read_only = read_only;
+                Ok(())
+            }
+            None => ax_err!(InvalidInput, "not a mount point"),
+        }
+    }
+
+    /// Detaches the topmost mount at `path`.
+    ///
+    /// Unless `detach` is set, fails with
+    /// [`ResourceBusy`](AxError::ResourceBusy) if files are open or working
+    /// directories are under the mount point, or if other filesystems are
+    /// mounted under it. With `detach`, the mounts under it are detached as
+    /// well, and the files still open on them keep working.
+    fn umount(&self, path: &str, detach: bool) -> AxResult {
+        if !detach && mount_in_use(path) {
+            return ax_err!(ResourceBusy);
+        }
+        let mut mounts = self.mounts.lock();
+        let idx = match mounts.iter().rposition(|mp| mp.info.path == path) {
+            Some(idx) => idx,
+            None => return ax_err!(InvalidInput, "not a mount point"),
+        };
+        // the mounts made later under the mount point are hidden by it
+        let nested = |mp: &MountPoint| mp.info.path != path && is_under(&mp.info.path, path);
+        if !detach && mounts[idx + 1..].iter().any(nested) {
+            return ax_err!(ResourceBusy);
+        }
+        let removed = mounts.remove(idx);
+        if detach {
+            let mut i = idx;
+            while i < mounts.len() {
+                if nested(&mounts[i]) {
+                    mounts.remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+        drop(mounts);
+        drop(removed);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "monolithic")]
+fn current_mount_table() -> Option<Arc<MountTable>> {
+    crate_interface::call_interface!(MountTableIf::current_mount_table)
+}
+
+#[cfg(not(feature = "monolithic"))]
+fn current_mount_table() -> Option<Arc<MountTable>> {
+    None
+}
+
+#[cfg(feature = "monolithic")]
+fn mount_in_use(path: &str) -> bool {
+    crate_interface::call_interface!(MountTableIf::mount_in_use, path)
+}
+
+/// Open files are not tracked without processes, only the working directory
+/// keeps a mount busy.
+#[cfg(not(feature = "monolithic"))]
+fn mount_in_use(path: &str) -> bool {
+    is_under(CURRENT_DIR_PATH.lock().trim_end_matches('/'), path)
 }
 
 impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
+    pub fn new(main_fs: Arc<dyn VfsOps>) -> Self {
         Self {
             main_fs,
-            mounts: Vec::new(),
+            init_table: Arc::new(MountTable::new()),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    /// The mount table of the current mount namespace.
+    fn table(&self) -> Arc<MountTable> {
+        current_mount_table().unwrap_or_else(|| self.init_table.clone())
+    }
+
+    /// Attaches `fs` at `info.path`, showing `root` at the mount point.
+    ///
+    /// The mount point is created if it does not exist.
+    fn mount(
+        &self,
+        table: &MountTable,
+        info: MountInfo,
+        fs: Arc<dyn VfsOps>,
+        root: VfsNodeRef,
+    ) -> AxResult {
+        if !info.path.starts_with('/') {
+            return ax_err!(InvalidInput, "mount path must start with '/'");
+        }
+        let path = axfs_vfs::path::canonicalize(&info.path);
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
-        if !path.starts_with('/') {
-            return ax_err!(InvalidInput, "mount path must start with '/'");
-        }
-        if self.mounts.iter().any(|mp| mp.path == path) {
-            return ax_err!(InvalidInput, "mount point already exists");
+        let mount_point = match self.lookup_in(table, &path) {
+            Err(AxError::NotFound) => {
+                let parent = self.lookup_mounted_fs(table, &path);
+                if parent.read_only {
+                    return ax_err!(ReadOnlyFilesystem);
+                }
+                parent.root.create(&parent.rest, FileType::Dir)?;
+                self.lookup_in(table, &path)?
+            }
+            result => result?,
+        };
+        if !mount_point.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory);
         }
-        // create the mount point in the main filesystem if it does not exist
-        self.main_fs.root_dir().create(path, FileType::Dir)?;
-        fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        fs.mount(&path, mount_point)?;
+        let info = MountInfo { path, ..info };
+        table.mounts.lock().push(MountPoint::new(info, fs, root));
         Ok(())
     }
 
-    pub fn _umount(&mut self, path: &str) {
-        self.mounts.retain(|mp| mp.path != path);
+    /// Attaches the directory at `source` at `target` as well.
+    fn bind(&self, table: &MountTable, source: &str, target: &str, read_only: bool) -> AxResult {
+        let source = axfs_vfs::path::canonicalize(source);
+        let (fs, fstype) = {
+            let mounts = table.mounts.lock();
+            match mounts
+                .iter()
+                .filter(|mp| is_under(&source, &mp.info.path))
+                .max_by_key(|mp| mp.info.path.len())
+            {
+                Some(mp) => (mp.fs.clone(), mp.info.fstype.clone()),
+                None => (self.main_fs.clone(), String::from("rootfs")),
+            }
+        };
+        let root = self.lookup_in(table, &source)?;
+        if !root.get_attr()?.is_dir() {
+            return ax_err!(NotADirectory);
+        }
+        let info = MountInfo {
+            source,
+            path: target.to_string(),
+            fstype,
+            read_only,
+        };
+        self.mount(table, info, fs, root)
     }
 
-    pub fn contains(&self, path: &str) -> bool {
-        self.mounts.iter().any(|mp| mp.path == path)
+    /// Splits the absolute `path` at the deepest mount point of `table`
+    /// containing it. The topmost one wins if several are mounted there.
+    fn lookup_mounted_fs(&self, table: &MountTable, path: &str) -> MountedPath {
+        debug!("lookup at root: {}", path);
+        let path = axfs_vfs::path::canonicalize(&format!("/{}", path));
+        let mounts = table.mounts.lock();
+        // `max_by_key` returns the last one of equal keys, the topmost mount
+        match mounts
+            .iter()
+            .filter(|mp| is_under(&path, &mp.info.path))
+            .max_by_key(|mp| mp.info.path.len())
+        {
+            Some(mp) => MountedPath {
+                fs: mp.fs.clone(),
+                root: mp.root.clone(),
+                read_only: mp.info.read_only,
+                rest: path[mp.info.path.len()..].trim_start_matches('/').into(),
+                mount_path: mp.info.path.clone(),
+            },
+            None => MountedPath {
+                fs: self.main_fs.clone(),
+                root: self.main_fs.root_dir(),
+                read_only: false,
+                rest: path.trim_start_matches('/').into(),
+                mount_path: "/".into(),
+            },
+        }
     }
 
-    fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
-    where
-        F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
-    {
-        debug!("lookup at root: {}", path);
-        let path = path.trim_matches('/');
-        if let Some(rest) = path.strip_prefix("./") {
-            return self.lookup_mounted_fs(rest, f);
+    fn lookup_in(&self, table: &MountTable, path: &str) -> AxResult<VfsNodeRef> {
+        let mounted = self.lookup_mounted_fs(table, path);
+        if mounted.rest.is_empty() {
+            Ok(mounted.root)
+        } else {
+            mounted.root.lookup(&mounted.rest)
         }
+    }
 
-        let mut idx = 0;
-        let mut max_len = 0;
-
-        // Find the filesystem that has the longest mounted path match
-        // TODO: more efficient, e.g. trie
-
-        for (i, mp) in self.mounts.iter().enumerate() {
-            // skip the first '/'
-            // two conditions
-            // 1. path == mp.path, e.g. dev
-            // 2. path == mp.path + '/', e.g. dev/
-            let prev = mp.path[1..].to_string() + "/";
-            if path.starts_with(&mp.path[1..])
-                && (path.len() == prev.len() - 1 || path.starts_with(&prev))
-                && prev.len() > max_len
-            {
-                max_len = mp.path.len() - 1;
-                idx = i;
+    /// Replaces the symbolic links in the absolute `path` with their targets.
+    ///
+    /// The path is walked one component at a time, so that a `..` after a
+    /// link leaves the directory the link points to rather than the one
+    /// holding it. The last component is only followed if `follow` is set or
+    /// it is followed by a slash. It does not need to exist, so that the
+    /// result can name a file to be created. Fails with
+    /// [`FilesystemLoop`](AxError::FilesystemLoop) if more than
+    /// [`MAX_SYMLINKS`] links are met.
+    fn resolve(&self, table: &MountTable, path: &str, follow: bool) -> AxResult<String> {
+        // most paths contain no links, and a lookup fails at the first one
+        if !path.ends_with('/') && !path.split('/').any(|name| name == "..") {
+            let path = axfs_vfs::path::canonicalize(&format!("/{}", path));
+            match self.lookup_in(table, &path) {
+                Ok(node) if !follow || !node.get_attr()?.is_symlink() => return Ok(path),
+                _ => {}
+            }
+        }
+        // the components left to walk, the next one at the end
+        let mut pending: Vec<String> = path.split('/').rev().map(String::from).collect();
+        let mut resolved: Vec<String> = Vec::new();
+        let mut links = 0;
+        while let Some(name) = pending.pop() {
+            match name.as_str() {
+                "" | "." => continue,
+                ".." => {
+                    resolved.pop();
+                    continue;
+                }
+                _ => {}
+            }
+            // trailing slashes do not make another component
+            let last = pending.iter().all(|name| name.is_empty());
+            let current = format!("/{}", resolved.join("/"));
+            let current = format!("{}/{}", current.trim_end_matches('/'), name);
+            let node = match self.lookup_in(table, &current) {
+                Ok(node) => node,
+                Err(AxError::NotFound) if last => return Ok(current),
+                Err(e) => return Err(e),
+            };
+            let attr = node.get_attr()?;
+            if attr.is_symlink() && (follow || !pending.is_empty()) {
+                links += 1;
+                if links > MAX_SYMLINKS {
+                    return ax_err!(FilesystemLoop);
+                }
+                let target = read_link_of(&node)?;
+                // an absolute target starts over from the root, a relative
+                // one at the directory holding the link
+                if target.starts_with('/') {
+                    resolved.clear();
+                }
+                pending.extend(target.split('/').rev().map(String::from));
+                continue;
+            }
+            if !pending.is_empty() && !attr.is_dir() {
+                return ax_err!(NotADirectory);
+            }
+            resolved.push(name);
+        }
+        Ok(format!("/{}", resolved.join("/")))
+    }
+
+    /// Looks up the absolute `path`, following a symbolic link at the end
+    /// only if `follow` is set.
+    fn lookup_path(&self, path: &str, follow: bool) -> AxResult<VfsNodeRef> {
+        let table = self.table();
+        let path = self.resolve(&table, path, follow)?;
+        self.lookup_in(&table, &path)
+    }
+
+    /// Finds the mount showing the directory `dir`, by walking up from it to
+    /// the root of a mount point. Returns whether that mount is read-only.
+    ///
+    /// Directories of filesystems that do not know their parents are only
+    /// matched if they are the root of a mount point, and are considered to
+    /// be on the root filesystem otherwise.
+    fn is_node_read_only(&self, table: &MountTable, dir: &VfsNodeRef) -> bool {
+        let mounts = table.mounts.lock();
+        let mut node = Some(dir.clone());
+        while let Some(current) = node {
+            if let Some(mp) = mounts.iter().rev().find(|mp| same_node(&mp.root, &current)) {
+                return mp.info.read_only;
             }
+            node = current.parent();
         }
-        if max_len == 0 {
-            f(self.main_fs.clone(), path) // not matched any mount point
+        false
+    }
+
+    /// Splits `path` for a modification, fails if it is on a read-only mount.
+    ///
+    /// A symbolic link at the end of `path` is not followed.
+    fn lookup_writable(&self, path: &str) -> AxResult<MountedPath> {
+        let table = self.table();
+        let path = self.resolve(&table, path, false)?;
+        let mounted = self.lookup_mounted_fs(&table, &path);
+        if mounted.read_only {
+            ax_err!(ReadOnlyFilesystem)
         } else {
-            f(self.mounts[idx].fs.clone(), &path[max_len..]) // matched at `idx`
+            Ok(mounted)
         }
     }
 }
@@ -119,44 +442,83 @@ impl VfsNodeOps for RootDirectory {
         self.main_fs.root_dir().get_attr()
     }
 
+    fn set_times(&self, atime: Option<Duration>, mtime: Option<Duration>) -> VfsResult {
+        self.main_fs.root_dir().set_times(atime, mtime)
+    }
+
+    fn chown(&self, uid: Option<u32>, gid: Option<u32>) -> VfsResult {
+        self.main_fs.root_dir().chown(uid, gid)
+    }
+
+    fn chmod(&self, mode: VfsNodePerm) -> VfsResult {
+        self.main_fs.root_dir().chmod(mode)
+    }
+
     fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
-        self.lookup_mounted_fs(path, |fs, rest_path| {
-            let dir = fs.root_dir();
-            dir.lookup(rest_path)
-        })
+        self.lookup_path(path, true)
     }
 
     fn create(&self, path: &str, ty: VfsNodeType) -> VfsResult {
-        self.lookup_mounted_fs(path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                Ok(()) // already exists
-            } else {
-                fs.root_dir().create(rest_path, ty)
-            }
-        })
+        let mounted = self.lookup_writable(path)?;
+        if mounted.rest.is_empty() {
+            Ok(()) // already exists
+        } else {
+            mounted.root.create(&mounted.rest, ty)
+        }
     }
 
     fn remove(&self, path: &str) -> VfsResult {
-        self.lookup_mounted_fs(path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot remove mount points
-            } else {
-                fs.root_dir().remove(rest_path)
-            }
-        })
+        let mounted = self.lookup_writable(path)?;
+        if mounted.rest.is_empty() {
+            ax_err!(PermissionDenied) // cannot remove mount points
+        } else {
+            mounted.root.remove(&mounted.rest)
+        }
     }
 
     fn rename(&self, src_path: &str, dst_path: &str) -> VfsResult {
-        self.lookup_mounted_fs(src_path, |fs, rest_path| {
-            if rest_path.is_empty() {
-                ax_err!(PermissionDenied) // cannot rename mount points
-            } else {
-                fs.root_dir().rename(rest_path, dst_path)
-            }
-        })
+        let src = self.lookup_writable(src_path)?;
+        let dst = self.lookup_writable(dst_path)?;
+        if src.rest.is_empty() || dst.rest.is_empty() {
+            ax_err!(PermissionDenied) // cannot rename mount points
+        } else if src.mount_path != dst.mount_path {
+            ax_err!(Unsupported, "cannot rename across mount points")
+        } else {
+            src.root.rename(&src.rest, &dst.rest)
+        }
+    }
+
+    fn symlink(&self, path: &str, target: &str) -> VfsResult {
+        let mounted = self.lookup_writable(path)?;
+        if mounted.rest.is_empty() {
+            ax_err!(AlreadyExists)
+        } else {
+            mounted.root.symlink(&mounted.rest, target)
+        }
+    }
+
+    fn link(&self, path: &str, src: &VfsNodeRef) -> VfsResult {
+        let mounted = self.lookup_writable(path)?;
+        if mounted.rest.is_empty() {
+            ax_err!(AlreadyExists)
+        } else {
+            mounted.root.link(&mounted.rest, src)
+        }
     }
 }
 
+/// Whether `a` and `b` are the same node, ignoring their vtables.
+fn same_node(a: &VfsNodeRef, b: &VfsNodeRef) -> bool {
+    core::ptr::eq(Arc::as_ptr(a) as *const u8, Arc::as_ptr(b) as *const u8)
+}
+
+fn read_link_of(node: &VfsNodeRef) -> AxResult<String> {
+    let mut buf = alloc::vec![0; PATH_MAX];
+    let len = node.readlink(&mut buf)?;
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| AxError::InvalidData)
+}
+
 pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
@@ -173,33 +535,47 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
         }
     }
 
-    let mut root_dir = RootDirectory::new(main_fs);
+    let root_dir = RootDirectory::new(main_fs);
+    let table = root_dir.init_table.clone();
+    #[allow(unused_variables)]
+    let mount_at = |path: &str, fstype: &str, fs: Arc<dyn VfsOps>| {
+        let info = MountInfo {
+            source: fstype.into(),
+            path: path.into(),
+            fstype: fstype.into(),
+            read_only: false,
+        };
+        let root = fs.root_dir();
+        root_dir.mount(&table, info, fs, root)
+    };
 
     #[cfg(feature = "devfs")]
-    root_dir
-        .mount("/dev", mounts::devfs())
-        .expect("failed to mount devfs at /dev");
+    mount_at("/dev", "devtmpfs", mounts::devfs()).expect("failed to mount devfs at /dev");
 
+    // the options are fixed, so creating the filesystems does not fail
     #[cfg(feature = "ramfs")]
-    root_dir
-        .mount("/tmp", mounts::ramfs())
+    mount_at("/tmp", "tmpfs", mounts::ramfs("mode=1777").unwrap())
         .expect("failed to mount ramfs at /tmp");
 
     #[cfg(feature = "ramfs")]
-    root_dir
-        .mount("/var", mounts::ramfs())
-        .expect("failed to mount ramfs at /tmp");
+    mount_at("/var", "tmpfs", mounts::ramfs("").unwrap()).expect("failed to mount ramfs at /var");
+
+    #[cfg(feature = "ramfs")]
+    mount_at("/run", "tmpfs", mounts::ramfs("mode=755").unwrap())
+        .expect("failed to mount ramfs at /run");
+
+    #[cfg(all(feature = "ramfs", feature = "devfs"))]
+    mount_at("/dev/shm", "tmpfs", mounts::ramfs("mode=1777").unwrap())
+        .expect("failed to mount ramfs at /dev/shm");
 
     // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
-    root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
+    mount_at("/proc", "proc", mounts::procfs().unwrap()) // should not fail
         .expect("fail to mount procfs at /proc");
 
-    // Mount another ramfs as sysfs
+    // Mount the sysfs, which is shared by every mount of `sysfs`
     #[cfg(feature = "sysfs")]
-    root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
+    mount_at("/sys", "sysfs", crate::sysfs::sysfs().unwrap()) // should not fail
         .expect("fail to mount sysfs at /sys");
 
     ROOT_DIR.init_by(Arc::new(root_dir));
@@ -215,6 +591,16 @@ fn parent_node_of(dir: Option<&VfsNodeRef>, path: &str) -> VfsNodeRef {
     }
 }
 
+/// Joins a relative `path` to the current directory, leaving `..` to be
+/// collapsed once the links before it are replaced.
+fn joined_path(path: &str) -> String {
+    if path.starts_with('/') {
+        path.into()
+    } else {
+        CURRENT_DIR_PATH.lock().clone() + path
+    }
+}
+
 pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     if path.starts_with('/') {
         Ok(axfs_vfs::path::canonicalize(path))
@@ -224,11 +610,52 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
-pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+/// Looks up `path` relative to the directory node `dir`, following the
+/// symbolic links on the way, and the one at its end if `follow` is set.
+///
+/// Lookups stay in the filesystem of `dir` until a link to an absolute path
+/// is met, which continues from the root directory.
+fn lookup_at(dir: &VfsNodeRef, path: &str, follow: bool) -> AxResult<VfsNodeRef> {
+    // the components left to walk, the next one at the end
+    let mut pending: Vec<String> = path.split('/').rev().map(String::from).collect();
+    let mut node = dir.clone();
+    let mut links = 0;
+    while let Some(name) = pending.pop() {
+        if name.is_empty() {
+            continue;
+        }
+        let next = node.clone().lookup(&name)?;
+        if (follow || !pending.is_empty()) && next.get_attr()?.is_symlink() {
+            links += 1;
+            if links > MAX_SYMLINKS {
+                return ax_err!(FilesystemLoop);
+            }
+            let target = read_link_of(&next)?;
+            if target.starts_with('/') {
+                pending.push(target);
+                let rest: Vec<String> = pending.into_iter().rev().collect();
+                return ROOT_DIR.lookup_path(&rest.join("/"), follow);
+            }
+            // a relative target starts at the directory holding the link
+            pending.extend(target.split('/').rev().map(String::from));
+            continue;
+        }
+        node = next;
+    }
+    Ok(node)
+}
+
+/// Looks up `path`, following a symbolic link at its end if `follow` is set.
+fn lookup_node(dir: Option<&VfsNodeRef>, path: &str, follow: bool) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
     }
-    let node = parent_node_of(dir, path).lookup(path)?;
+    // a trailing slash always follows the link to the directory
+    let follow = follow || path.ends_with('/');
+    let node = match dir {
+        Some(dir) if !path.starts_with('/') => lookup_at(dir, path, follow)?,
+        _ => ROOT_DIR.lookup_path(&joined_path(path), follow)?,
+    };
     if path.ends_with('/') && !node.get_attr()?.is_dir() {
         ax_err!(NotADirectory)
     } else {
@@ -236,6 +663,14 @@ pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRe
     }
 }
 
+pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    lookup_node(dir, path, true)
+}
+
+pub(crate) fn lookup_link(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
+    lookup_node(dir, path, false)
+}
+
 pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
@@ -244,26 +679,33 @@ pub(crate) fn create_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsN
     }
     let parent = parent_node_of(dir, path);
     parent.create(path, VfsNodeType::File)?;
+    fsnotify::notify_at(dir, path, FsEvent::CREATE);
     parent.lookup(path)
 }
 
 pub(crate) fn create_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     match lookup(dir, path) {
         Ok(_) => ax_err!(AlreadyExists),
-        Err(AxError::NotFound) => parent_node_of(dir, path).create(path, VfsNodeType::Dir),
+        Err(AxError::NotFound) => {
+            parent_node_of(dir, path).create(path, VfsNodeType::Dir)?;
+            fsnotify::notify_at(dir, path, FsEvent::CREATE | FsEvent::ISDIR);
+            Ok(())
+        }
         Err(e) => Err(e),
     }
 }
 
 pub(crate) fn remove_file(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
-    let node = lookup(dir, path)?;
+    let node = lookup_link(dir, path)?;
     let attr = node.get_attr()?;
     if attr.is_dir() {
         ax_err!(IsADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, path).remove(path)?;
+        fsnotify::notify_at(dir, path, FsEvent::DELETE);
+        Ok(())
     }
 }
 
@@ -281,27 +723,91 @@ pub(crate) fn remove_dir(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
     {
         return ax_err!(InvalidInput);
     }
-    if ROOT_DIR.contains(&absolute_path(path)?) {
+    if ROOT_DIR.table().contains(&absolute_path(path)?) {
         return ax_err!(PermissionDenied);
     }
 
-    let node = lookup(dir, path)?;
+    let node = lookup_link(dir, path)?;
     let attr = node.get_attr()?;
     if !attr.is_dir() {
         ax_err!(NotADirectory)
     } else if !attr.perm().owner_writable() {
         ax_err!(PermissionDenied)
     } else {
-        parent_node_of(dir, path).remove(path)
+        parent_node_of(dir, path).remove(path)?;
+        fsnotify::notify_at(dir, path, FsEvent::DELETE | FsEvent::ISDIR);
+        Ok(())
+    }
+}
+
+/// Fails with [`ReadOnlyFilesystem`](AxError::ReadOnlyFilesystem) if `path`
+/// is on a read-only mount point.
+///
+/// A path relative to `dir` is checked against the mount showing `dir`, as
+/// lookups from a directory node stay in its filesystem.
+pub(crate) fn check_writable(dir: Option<&VfsNodeRef>, path: &str) -> AxResult {
+    match dir {
+        Some(dir) if !path.starts_with('/') => {
+            if ROOT_DIR.is_node_read_only(&ROOT_DIR.table(), dir) {
+                ax_err!(ReadOnlyFilesystem)
+            } else {
+                Ok(())
+            }
+        }
+        _ => ROOT_DIR.lookup_writable(&joined_path(path)).map(|_| ()),
     }
 }
 
+pub(crate) fn mount(
+    source: &str,
+    target: &str,
+    fstype: &str,
+    data: &str,
+    read_only: bool,
+) -> AxResult {
+    let table = ROOT_DIR.table();
+    let target = ROOT_DIR.resolve(&table, &joined_path(target), true)?;
+    let fs = mounts::new_filesystem(fstype, source, data)?;
+    let info = MountInfo {
+        source: source.into(),
+        path: target,
+        fstype: fstype.into(),
+        read_only,
+    };
+    let root = fs.root_dir();
+    ROOT_DIR.mount(&table, info, fs, root)
+}
+
+pub(crate) fn bind_mount(source: &str, target: &str, read_only: bool) -> AxResult {
+    let table = ROOT_DIR.table();
+    let source = ROOT_DIR.resolve(&table, &joined_path(source), true)?;
+    let target = ROOT_DIR.resolve(&table, &joined_path(target), true)?;
+    ROOT_DIR.bind(&table, &source, &target, read_only)
+}
+
+pub(crate) fn remount(target: &str, read_only: bool) -> AxResult {
+    ROOT_DIR.table().remount(&absolute_path(target)?, read_only)
+}
+
+pub(crate) fn umount(target: &str, detach: bool) -> AxResult {
+    ROOT_DIR.table().umount(&absolute_path(target)?, detach)
+}
+
+pub(crate) fn mount_table() -> Arc<MountTable> {
+    ROOT_DIR.table()
+}
+
+pub(crate) fn init_mount_table() -> Arc<MountTable> {
+    ROOT_DIR.init_table.clone()
+}
+
 pub(crate) fn current_dir() -> AxResult<String> {
     Ok(CURRENT_DIR_PATH.lock().clone())
 }
 
 pub(crate) fn set_current_dir(path: &str) -> AxResult {
-    let mut abs_path = absolute_path(path)?;
+    // kept with its links replaced, as `getcwd` reports it
+    let mut abs_path = ROOT_DIR.resolve(&ROOT_DIR.table(), &joined_path(path), true)?;
     if !abs_path.ends_with('/') {
         abs_path += "/";
     }
@@ -325,9 +831,234 @@ pub(crate) fn set_current_dir(path: &str) -> AxResult {
 }
 
 pub(crate) fn rename(old: &str, new: &str) -> AxResult {
-    if parent_node_of(None, new).lookup(new).is_ok() {
+    if lookup_link(None, new).is_ok() {
         warn!("dst file already exist, now remove it");
         remove_file(None, new)?;
     }
-    parent_node_of(None, old).rename(old, new)
+    let is_dir = lookup_link(None, old)?.get_attr()?.is_dir();
+    parent_node_of(None, old).rename(old, new)?;
+    fsnotify::notify_move(&absolute_path(old)?, &absolute_path(new)?, is_dir);
+    Ok(())
+}
+
+pub(crate) fn symlink(target: &str, path: &str) -> AxResult {
+    if target.is_empty() {
+        return ax_err!(NotFound);
+    }
+    let path = absolute_path(path)?;
+    ROOT_DIR.symlink(&path, target)?;
+    fsnotify::notify(&path, FsEvent::CREATE);
+    Ok(())
+}
+
+pub(crate) fn real_path(path: &str) -> AxResult<String> {
+    ROOT_DIR.resolve(&ROOT_DIR.table(), &joined_path(path), true)
+}
+
+pub(crate) fn read_link(path: &str) -> AxResult<String> {
+    read_link_of(&lookup_link(None, path)?)
+}
+
+pub(crate) fn hard_link(old: &str, new: &str) -> AxResult {
+    let table = ROOT_DIR.table();
+    let old = ROOT_DIR.resolve(&table, &joined_path(old), false)?;
+    let new = ROOT_DIR.resolve(&table, &joined_path(new), false)?;
+    let src_mount = ROOT_DIR.lookup_mounted_fs(&table, &old).mount_path;
+    let dst_mount = ROOT_DIR.lookup_mounted_fs(&table, &new).mount_path;
+    if src_mount != dst_mount {
+        return ax_err!(Unsupported, "cannot link across mount points");
+    }
+    let src = ROOT_DIR.lookup_in(&table, &old)?;
+    ROOT_DIR.link(&new, &src)?;
+    // the link count of the file has changed as well
+    fsnotify::notify(&old, FsEvent::ATTRIB);
+    fsnotify::notify(&new, FsEvent::CREATE);
+    Ok(())
+}
+
+pub(crate) fn lookup_writable(path: &str, follow: bool) -> AxResult<VfsNodeRef> {
+    let table = ROOT_DIR.table();
+    let path = ROOT_DIR.resolve(&table, &joined_path(path), follow)?;
+    if ROOT_DIR.lookup_mounted_fs(&table, &path).read_only {
+        return ax_err!(ReadOnlyFilesystem);
+    }
+    ROOT_DIR.lookup_in(&table, &path)
+}
+
+pub(crate) fn statfs(path: &str) -> AxResult<FileSystemInfo> {
+    let table = ROOT_DIR.table();
+    let path = ROOT_DIR.resolve(&table, &joined_path(path), true)?;
+    ROOT_DIR.lookup_in(&table, &path)?;
+    let mounted = ROOT_DIR.lookup_mounted_fs(&table, &path);
+    let mut info = match mounted.fs.statfs() {
+        Ok(info) => info,
+        // filesystems keeping no statistics have nothing but a block size
+        Err(AxError::Unsupported) => FileSystemInfo::new(0, PAGE_SIZE as u64),
+        Err(e) => return Err(e),
+    };
+    if mounted.read_only {
+        info.set_flags(info.flags() | FileSystemFlags::RDONLY);
+    }
+    Ok(info)
+}
+
+#[cfg(all(test, feature = "ramfs"))]
+mod tests {
+    use super::*;
+    use axfs_ramfs::RamFileSystem;
+
+    fn init() {
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(axtask::init_scheduler); // to use `axsync::Mutex`
+    }
+
+    fn ramfs() -> Arc<dyn VfsOps> {
+        Arc::new(RamFileSystem::new())
+    }
+
+    fn mount_at(root: &RootDirectory, path: &str, fs: &Arc<dyn VfsOps>, read_only: bool) {
+        let info = MountInfo {
+            source: "none".into(),
+            path: path.into(),
+            fstype: "tmpfs".into(),
+            read_only,
+        };
+        root.mount(&root.init_table, info, fs.clone(), fs.root_dir())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_mount_lookup() {
+        init();
+        let root = RootDirectory::new(ramfs());
+        let table = root.init_table.clone();
+        let (mnt, inner) = (ramfs(), ramfs());
+        mount_at(&root, "/mnt", &mnt, false);
+        mount_at(&root, "/mnt/inner/", &inner, true);
+        assert!(table.contains("/mnt/inner"));
+        assert!(!table.contains("/mnt/inner/"));
+
+        // the deepest mount point containing the path wins
+        let mounted = root.lookup_mounted_fs(&table, "/mnt/inner/a//b");
+        assert_eq!(mounted.mount_path, "/mnt/inner");
+        assert_eq!(mounted.rest, "a/b");
+        assert!(mounted.read_only);
+        assert!(same_node(&mounted.root, &inner.root_dir()));
+        let mounted = root.lookup_mounted_fs(&table, "/mnt/innerfile");
+        assert_eq!(mounted.mount_path, "/mnt");
+        assert_eq!(mounted.rest, "innerfile");
+        let mounted = root.lookup_mounted_fs(&table, "/etc/../mnt/./inner");
+        assert_eq!(mounted.mount_path, "/mnt/inner");
+        assert_eq!(mounted.rest, "");
+        let mounted = root.lookup_mounted_fs(&table, "/etc");
+        assert_eq!(mounted.mount_path, "/");
+        assert_eq!(mounted.rest, "etc");
+        assert!(!mounted.read_only);
+
+        // directories are matched to the mount showing them
+        inner.root_dir().create("dir", VfsNodeType::Dir).unwrap();
+        let dir = root.lookup_in(&table, "/mnt/inner/dir").unwrap();
+        assert!(root.is_node_read_only(&table, &dir));
+        assert!(!root.is_node_read_only(&table, &mnt.root_dir()));
+        assert!(!root.is_node_read_only(&table, &root.main_fs.root_dir()));
+        assert_eq!(
+            root.lookup_writable("/mnt/inner/dir/file").err(),
+            Some(AxError::ReadOnlyFilesystem)
+        );
+
+        // a later mount at the same point hides the earlier ones
+        let top = ramfs();
+        mount_at(&root, "/mnt", &top, true);
+        let mounted = root.lookup_mounted_fs(&table, "/mnt/inner");
+        assert!(same_node(&mounted.root, &top.root_dir()));
+        assert_eq!(mounted.rest, "inner");
+        table.remount("/mnt", false).unwrap();
+        assert!(!root.lookup_mounted_fs(&table, "/mnt").read_only);
+        let paths: Vec<String> = table.mounts().into_iter().map(|mp| mp.path).collect();
+        assert_eq!(paths, ["/mnt", "/mnt/inner", "/mnt"]);
+    }
+
+    #[test]
+    fn test_resolve_links() {
+        init();
+        let root = RootDirectory::new(ramfs());
+        let table = root.init_table.clone();
+        let fs_root = root.main_fs.root_dir();
+        fs_root.create("a", VfsNodeType::Dir).unwrap();
+        fs_root.create("a/b", VfsNodeType::Dir).unwrap();
+        fs_root.create("a/b/file", VfsNodeType::File).unwrap();
+        fs_root.symlink("link", "a/b").unwrap();
+        fs_root.symlink("loop", "loop").unwrap();
+
+        // `..` after a link leaves the directory the link points to
+        assert_eq!(root.resolve(&table, "/link/..", true).unwrap(), "/a");
+        assert_eq!(
+            root.resolve(&table, "link/file", true).unwrap(),
+            "/a/b/file"
+        );
+        assert_eq!(
+            root.resolve(&table, "/link/../new", false).unwrap(),
+            "/a/new"
+        );
+        // the last link is followed if asked to or before a slash
+        assert_eq!(root.resolve(&table, "/link", false).unwrap(), "/link");
+        assert_eq!(root.resolve(&table, "/link", true).unwrap(), "/a/b");
+        assert_eq!(root.resolve(&table, "/link/", false).unwrap(), "/a/b");
+        assert_eq!(
+            root.resolve(&table, "/a/b/file/..", true).err(),
+            Some(AxError::NotADirectory)
+        );
+        assert_eq!(
+            root.resolve(&table, "/loop/file", true).err(),
+            Some(AxError::FilesystemLoop)
+        );
+
+        // links are followed in lookups from a directory node as well
+        fs_root.symlink("a/up", "..").unwrap();
+        let a = fs_root.clone().lookup("a").unwrap();
+        let file = fs_root.clone().lookup("a/b/file").unwrap();
+        assert!(same_node(
+            &lookup_at(&a, "up/link/file", true).unwrap(),
+            &file
+        ));
+        assert!(same_node(
+            &lookup_at(&a, "up/link/../b/file", false).unwrap(),
+            &file
+        ));
+        let up = lookup_at(&a, "up", false).unwrap();
+        assert!(up.get_attr().unwrap().is_symlink());
+        assert!(same_node(&lookup_at(&a, "up", true).unwrap(), &fs_root));
+    }
+
+    #[test]
+    fn test_umount() {
+        init();
+        let root = RootDirectory::new(ramfs());
+        let table = root.init_table.clone();
+        mount_at(&root, "/mnt", &ramfs(), false);
+        mount_at(&root, "/mnt/inner", &ramfs(), false);
+        mount_at(&root, "/other", &ramfs(), false);
+
+        assert_eq!(
+            table.umount("/mnt/none", false).err(),
+            Some(AxError::InvalidInput)
+        );
+        // a working directory under the mount point keeps it busy
+        *CURRENT_DIR_PATH.lock() = "/mnt/inner/".into();
+        assert_eq!(
+            table.umount("/mnt/inner", false).err(),
+            Some(AxError::ResourceBusy)
+        );
+        CURRENT_DIR_PATH.lock().clear();
+        // so do the mounts under it, unless they are detached as well
+        assert_eq!(
+            table.umount("/mnt", false).err(),
+            Some(AxError::ResourceBusy)
+        );
+        table.umount("/mnt", true).unwrap();
+        let paths: Vec<String> = table.mounts().into_iter().map(|mp| mp.path).collect();
+        assert_eq!(paths, ["/other"]);
+        table.umount("/other", false).unwrap();
+        assert!(table.mounts().is_empty());
+    }
 }
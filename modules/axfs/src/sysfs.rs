@@ -0,0 +1,384 @@
+//! The sysfs mounted on `/sys`, describing the CPUs, block devices and network
+//! interfaces of the system.
+//!
+//! Attribute files are generated each time they are read, so that they follow
+//! the state of the devices. The CPUs come from the device tree if there is
+//! one, the block devices are added by this module as they are set up, the
+//! network interfaces with [`add_net_device`], and other modules add their
+//! devices with [`add_attr`].
+//!
+//! There is a single instance, shared by every mount of `sysfs`.
+
+use alloc::{boxed::Box, format, string::String, sync::Arc, vec, vec::Vec};
+use axfs_ramfs::{DirNode, RamFileSystem};
+use axfs_vfs::{VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType};
+use axfs_vfs::{VfsOps, VfsResult};
+use axsync::Mutex;
+
+/// The magic number of sysfs reported by `statfs`.
+const SYSFS_MAGIC: u64 = 0x6265_6572;
+
+/// The unit of the sizes of block devices.
+const SECTOR_SIZE: u64 = 512;
+
+/// A read-only file whose content is generated when it is read.
+struct Attr(Box<dyn Fn() -> String + Send + Sync>);
+
+impl VfsNodeOps for Attr {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        // the size is unknown until read, Linux reports a page
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o444),
+            VfsNodeType::File,
+            4096,
+            0,
+        ))
+    }
+
+    fn read_at(&self, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let content = (self.0)();
+        let bytes = content.as_bytes();
+        let start = offset.min(bytes.len() as u64) as usize;
+        let len = buf.len().min(bytes.len() - start);
+        buf[..len].copy_from_slice(&bytes[start..start + len]);
+        Ok(len)
+    }
+
+    fn write_at(&self, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        Err(VfsError::PermissionDenied)
+    }
+
+    fn truncate(&self, _size: u64) -> VfsResult {
+        Err(VfsError::PermissionDenied)
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+static SYSFS: Mutex<Option<Arc<RamFileSystem>>> = Mutex::new(None);
+
+/// Returns the sysfs, creating it on first use.
+pub(crate) fn sysfs() -> VfsResult<Arc<RamFileSystem>> {
+    let mut sysfs = SYSFS.lock();
+    if let Some(sysfs) = sysfs.as_ref() {
+        return Ok(sysfs.clone());
+    }
+    let new = Arc::new(RamFileSystem::new().with_magic(SYSFS_MAGIC));
+    let root = new.root_dir();
+    add_static(
+        &root,
+        "kernel/mm/transparent_hugepage/enabled",
+        "always [madvise] never\n",
+    )?;
+    add_static(
+        &root,
+        "devices/system/clocksource/clocksource0/current_clocksource",
+        "tsc\n",
+    )?;
+    add_cpus(&root)?;
+    add_device_tree(&root)?;
+    *sysfs = Some(new.clone());
+    Ok(new)
+}
+
+/// Adds a read-only file at `path` in the sysfs, whose content is generated
+/// by `show` each time it is read. The missing parent directories are
+/// created.
+pub(crate) fn add_attr(path: &str, show: impl Fn() -> String + Send + Sync + 'static) -> VfsResult {
+    add_node(&sysfs()?.root_dir(), path, Arc::new(Attr(Box::new(show))))
+}
+
+/// Removes the file, symbolic link or directory at `path` in the sysfs, along
+/// with everything in it.
+pub(crate) fn remove(path: &str) -> VfsResult {
+    remove_tree(&sysfs()?.root_dir(), path.trim_matches('/'))
+}
+
+/// Adds `/sys/block/<name>` for the block device numbered `major:minor`, with
+/// `size` returning its size in bytes and `read_only` whether it refuses
+/// writes.
+pub(crate) fn add_block_device(
+    name: &str,
+    (major, minor): (u32, u32),
+    size: impl Fn() -> u64 + Send + Sync + 'static,
+    read_only: impl Fn() -> bool + Send + Sync + 'static,
+) -> VfsResult {
+    let root = sysfs()?.root_dir();
+    let dir = format!("block/{}", name);
+    let devno = format!("{}:{}\n", major, minor);
+    add_node(&root, &format!("{}/dev", dir), attr(move || devno.clone()))?;
+    add_node(
+        &root,
+        &format!("{}/size", dir),
+        attr(move || format!("{}\n", size() / SECTOR_SIZE)),
+    )?;
+    add_node(
+        &root,
+        &format!("{}/ro", dir),
+        attr(move || format!("{}\n", read_only() as u8)),
+    )?;
+    add_static(&root, &format!("{}/removable", dir), "0\n")?;
+    for queue_attr in [
+        "logical_block_size",
+        "physical_block_size",
+        "hw_sector_size",
+    ] {
+        add_static(&root, &format!("{}/queue/{}", dir, queue_attr), "512\n")?;
+    }
+    add_static(&root, &format!("{}/queue/rotational", dir), "0\n")?;
+    // the other views of the device, as on Linux
+    let target = format!("../../block/{}", name);
+    make_dirs(&root, "class/block")?.symlink(name, &target)?;
+    make_dirs(&root, "dev/block")?.symlink(&format!("{}:{}", major, minor), &target)
+}
+
+/// Removes `/sys/block/<name>` of the block device numbered `major:minor`.
+pub(crate) fn remove_block_device(name: &str, (major, minor): (u32, u32)) -> VfsResult {
+    let root = sysfs()?.root_dir();
+    remove_tree(&root, &format!("dev/block/{}:{}", major, minor))?;
+    remove_tree(&root, &format!("class/block/{}", name))?;
+    remove_tree(&root, &format!("block/{}", name))
+}
+
+/// Adds `/sys/class/net/<name>` for the network interface numbered `index`,
+/// with `mtu` returning its MTU and `up` whether it is up.
+pub(crate) fn add_net_device(
+    name: &str,
+    index: u32,
+    mac: [u8; 6],
+    loopback: bool,
+    mtu: impl Fn() -> usize + Send + Sync + 'static,
+    up: impl Fn() -> bool + Send + Sync + 'static,
+) -> VfsResult {
+    const IFF_UP: u32 = 0x1;
+    const IFF_BROADCAST: u32 = 0x2;
+    const IFF_LOOPBACK: u32 = 0x8;
+    const IFF_RUNNING: u32 = 0x40;
+    const IFF_MULTICAST: u32 = 0x1000;
+    const ARPHRD_ETHER: u32 = 1;
+    const ARPHRD_LOOPBACK: u32 = 772;
+
+    let root = sysfs()?.root_dir();
+    let dir = format!("class/net/{}", name);
+    let (ty, flags, broadcast) = if loopback {
+        (ARPHRD_LOOPBACK, IFF_LOOPBACK, [0; 6])
+    } else {
+        (ARPHRD_ETHER, IFF_BROADCAST | IFF_MULTICAST, [0xff; 6])
+    };
+    let hw_addr = |addr: [u8; 6]| {
+        let bytes: Vec<_> = addr.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}\n", bytes.join(":"))
+    };
+    add_static(&root, &format!("{}/address", dir), &hw_addr(mac))?;
+    add_static(&root, &format!("{}/broadcast", dir), &hw_addr(broadcast))?;
+    add_static(&root, &format!("{}/addr_len", dir), "6\n")?;
+    add_static(&root, &format!("{}/ifindex", dir), &format!("{}\n", index))?;
+    add_static(&root, &format!("{}/iflink", dir), &format!("{}\n", index))?;
+    add_static(&root, &format!("{}/type", dir), &format!("{}\n", ty))?;
+    add_node(
+        &root,
+        &format!("{}/mtu", dir),
+        attr(move || format!("{}\n", mtu())),
+    )?;
+    let up = Arc::new(up);
+    let is_up = up.clone();
+    add_node(
+        &root,
+        &format!("{}/flags", dir),
+        attr(move || {
+            let state = if is_up() { IFF_UP | IFF_RUNNING } else { 0 };
+            format!("{:#x}\n", flags | state)
+        }),
+    )?;
+    let is_up = up.clone();
+    add_node(
+        &root,
+        &format!("{}/operstate", dir),
+        attr(move || {
+            // as on Linux, the loopback interface has no operational state
+            match (is_up(), loopback) {
+                (true, true) => String::from("unknown\n"),
+                (true, false) => String::from("up\n"),
+                (false, _) => String::from("down\n"),
+            }
+        }),
+    )?;
+    add_node(
+        &root,
+        &format!("{}/carrier", dir),
+        attr(move || format!("{}\n", up() as u8)),
+    )
+}
+
+/// The Linux name and device number of a disk, from the name of its driver.
+pub(crate) fn disk_name(driver: &str) -> (&'static str, (u32, u32)) {
+    match driver {
+        "virtio-blk" => ("vda", (254, 0)),
+        "ramdisk" => ("ram0", (1, 0)),
+        "bcm2835_sdhci" => ("mmcblk0", (179, 0)),
+        _ => ("sda", (8, 0)),
+    }
+}
+
+/// Where a CPU stands in the topology of the system.
+struct CpuTopology {
+    core_id: usize,
+    package_id: usize,
+}
+
+/// Returns the topology of the CPUs in use, from the device tree if there is
+/// one.
+fn cpu_topology() -> Vec<CpuTopology> {
+    if of::get_fdt_ptr().is_some() {
+        let cpus: Vec<_> = of::cpus()
+            .take(axconfig::SMP)
+            .map(|cpu| {
+                // the ids are MPIDR values: the lowest affinity level is the
+                // core, the upper ones are the cluster
+                let hwid = cpu.ids().first();
+                CpuTopology {
+                    core_id: hwid & 0xff,
+                    package_id: (hwid >> 8) & 0xffff,
+                }
+            })
+            .collect();
+        if !cpus.is_empty() {
+            return cpus;
+        }
+    }
+    (0..axconfig::SMP)
+        .map(|id| CpuTopology {
+            core_id: id,
+            package_id: 0,
+        })
+        .collect()
+}
+
+/// Adds `/sys/devices/system/cpu`.
+fn add_cpus(root: &VfsNodeRef) -> VfsResult {
+    let cpus = cpu_topology();
+    let all = cpu_list((0..cpus.len()).collect());
+    for name in ["online", "possible", "present"] {
+        add_static(root, &format!("devices/system/cpu/{}", name), &all)?;
+    }
+    add_static(
+        root,
+        "devices/system/cpu/kernel_max",
+        &format!("{}\n", axconfig::SMP - 1),
+    )?;
+    for (id, cpu) in cpus.iter().enumerate() {
+        let dir = format!("devices/system/cpu/cpu{}", id);
+        // the boot CPU cannot be taken offline and has no `online`
+        if id != 0 {
+            add_static(root, &format!("{}/online", dir), "1\n")?;
+        }
+        let package: Vec<usize> = (0..cpus.len())
+            .filter(|&other| cpus[other].package_id == cpu.package_id)
+            .collect();
+        let attrs = [
+            ("core_id", format!("{}\n", cpu.core_id)),
+            ("physical_package_id", format!("{}\n", cpu.package_id)),
+            ("cluster_id", format!("{}\n", cpu.package_id)),
+            // there are no hardware threads
+            ("thread_siblings", cpu_mask(&[id], cpus.len())),
+            ("thread_siblings_list", cpu_list(vec![id])),
+            ("core_cpus", cpu_mask(&[id], cpus.len())),
+            ("core_cpus_list", cpu_list(vec![id])),
+            ("core_siblings", cpu_mask(&package, cpus.len())),
+            ("core_siblings_list", cpu_list(package.clone())),
+            ("package_cpus", cpu_mask(&package, cpus.len())),
+            ("package_cpus_list", cpu_list(package.clone())),
+        ];
+        for (name, value) in attrs {
+            add_static(root, &format!("{}/topology/{}", dir, name), &value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Adds `/sys/firmware/devicetree/base/model` if there is a device tree.
+fn add_device_tree(root: &VfsNodeRef) -> VfsResult {
+    if of::get_fdt_ptr().is_none() {
+        return Ok(());
+    }
+    // properties are shown raw, strings end with a NUL
+    let model = format!("{}\0", of::machin_name());
+    add_static(root, "firmware/devicetree/base/model", &model)
+}
+
+/// Formats sorted CPU numbers as ranges, e.g. `0-3,6\n`.
+fn cpu_list(ids: Vec<usize>) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for id in ids {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == id => *end = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+    let ranges: Vec<String> = ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                format!("{}", start)
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect();
+    format!("{}\n", ranges.join(","))
+}
+
+/// Formats CPU numbers as a hexadecimal mask in comma-separated groups of 32
+/// bits, e.g. `0000000f\n`.
+fn cpu_mask(ids: &[usize], ncpus: usize) -> String {
+    let mut groups = vec![0u32; ncpus.div_ceil(32).max(1)];
+    for &id in ids {
+        groups[id / 32] |= 1 << (id % 32);
+    }
+    let groups: Vec<String> = groups.iter().rev().map(|g| format!("{:08x}", g)).collect();
+    format!("{}\n", groups.join(","))
+}
+
+fn attr(show: impl Fn() -> String + Send + Sync + 'static) -> VfsNodeRef {
+    Arc::new(Attr(Box::new(show)))
+}
+
+/// Adds a file at `path` that always reads `content`.
+fn add_static(root: &VfsNodeRef, path: &str, content: &str) -> VfsResult {
+    let content = String::from(content);
+    add_node(root, path, attr(move || content.clone()))
+}
+
+/// Adds `node` at `path`, creating the missing parent directories.
+fn add_node(root: &VfsNodeRef, path: &str, node: VfsNodeRef) -> VfsResult {
+    let path = path.trim_matches('/');
+    let (dir, name) = path.rsplit_once('/').unwrap_or(("", path));
+    make_dirs(root, dir)?.link(name, &node)
+}
+
+/// Returns the directory at `path`, creating it and its missing parents.
+fn make_dirs(root: &VfsNodeRef, path: &str) -> VfsResult<VfsNodeRef> {
+    let mut dir = root.clone();
+    for name in path.split('/').filter(|name| !name.is_empty()) {
+        dir = match dir.clone().lookup(name) {
+            Ok(node) => node,
+            Err(VfsError::NotFound) => {
+                dir.create(name, VfsNodeType::Dir)?;
+                dir.lookup(name)?
+            }
+            Err(e) => return Err(e),
+        };
+    }
+    Ok(dir)
+}
+
+/// Removes the node at `path` and everything in it.
+fn remove_tree(root: &VfsNodeRef, path: &str) -> VfsResult {
+    let node = root.clone().lookup(path)?;
+    if let Some(dir) = node.as_any().downcast_ref::<DirNode>() {
+        for name in dir.get_entries() {
+            remove_tree(&node, &name)?;
+        }
+    }
+    root.remove(path)
+}
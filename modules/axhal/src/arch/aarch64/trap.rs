@@ -1,3 +1,5 @@
+#[cfg(feature = "monolithic")]
+use core::arch::asm;
 use core::arch::global_asm;
 
 use aarch64_cpu::registers::{ESR_EL1, FAR_EL1, SP_EL1};
@@ -41,6 +43,51 @@ extern "C" {
     fn ret_to_first_user(sp: usize);
 }
 
+/// `SPSR_EL1.SS`: execute one instruction after returning to user space, then
+/// take a software step exception.
+#[cfg(feature = "monolithic")]
+const SPSR_SS: usize = 1 << 21;
+
+/// `MDSCR_EL1.SS`: software step enabled.
+#[cfg(feature = "monolithic")]
+const MDSCR_SS: u64 = 1 << 0;
+
+/// `MDSCR_EL1.KDE`: debug exceptions enabled in EL1.
+#[cfg(feature = "monolithic")]
+const MDSCR_KDE: u64 = 1 << 13;
+
+/// Whether software step was enabled when returning to user space.
+#[cfg(all(feature = "monolithic", feature = "signal"))]
+fn user_single_step() -> bool {
+    let mdscr: u64;
+    unsafe { asm!("mrs {}, mdscr_el1", out(reg) mdscr) };
+    mdscr & MDSCR_SS != 0
+}
+
+/// Enables software step for the thread returning to user space with `tf` if
+/// `SPSR_EL1.SS` is set in it, and disables it otherwise.
+///
+/// `MDSCR_EL1` is shared by all threads on the CPU, so it is set on every
+/// return to user space.
+#[cfg(feature = "monolithic")]
+fn update_single_step(tf: &TrapFrame) {
+    let mut mdscr: u64;
+    unsafe { asm!("mrs {}, mdscr_el1", out(reg) mdscr) };
+    let enable = tf.spsr & SPSR_SS != 0;
+    if enable == (mdscr & MDSCR_SS != 0) {
+        return;
+    }
+    if enable {
+        // never step the kernel, and unlock the OS lock that masks debug
+        // exceptions after reset
+        mdscr = (mdscr | MDSCR_SS) & !MDSCR_KDE;
+        unsafe { asm!("msr oslar_el1, xzr") };
+    } else {
+        mdscr &= !MDSCR_SS;
+    }
+    unsafe { asm!("msr mdscr_el1, {}; isb", in(reg) mdscr) };
+}
+
 #[no_mangle]
 fn invalid_exception(tf: &TrapFrame, kind: TrapKind, source: TrapSource) {
     panic!(
@@ -122,6 +169,15 @@ fn handle_el1h_64_error_exception(tf: &mut TrapFrame) {
 #[cfg(feature = "monolithic")]
 fn handle_el0t_64_sync_exception(tf: &mut TrapFrame) {
     let esr = ESR_EL1.extract();
+    // the stepped instruction completed but trapped, e.g. `svc`, so the step is
+    // reported after handling it
+    #[cfg(feature = "signal")]
+    let stepped = user_single_step()
+        && tf.spsr & SPSR_SS == 0
+        && !matches!(
+            esr.read_as_enum(ESR_EL1::EC),
+            Some(ESR_EL1::EC::Value::SoftwareStepLowerEL)
+        );
 
     match esr.read_as_enum(ESR_EL1::EC) {
         Some(ESR_EL1::EC::Value::SVC64) => {
@@ -137,6 +193,10 @@ fn handle_el0t_64_sync_exception(tf: &mut TrapFrame) {
             );
             tf.r[0] = result as usize;
         }
+        #[cfg(feature = "signal")]
+        Some(ESR_EL1::EC::Value::Brk64) => crate::trap::handle_user_breakpoint(false),
+        #[cfg(feature = "signal")]
+        Some(ESR_EL1::EC::Value::SoftwareStepLowerEL) => crate::trap::handle_user_breakpoint(true),
         Some(ESR_EL1::EC::Value::DataAbortLowerEL) => {
             let far = FAR_EL1.get() as usize;
             enable_irqs();
@@ -159,9 +219,15 @@ fn handle_el0t_64_sync_exception(tf: &mut TrapFrame) {
         }
     }
 
+    #[cfg(feature = "signal")]
+    if stepped {
+        crate::trap::handle_user_breakpoint(true);
+    }
+
     handle_signal();
 
     disable_irqs();
+    update_single_step(tf);
 }
 
 #[no_mangle]
@@ -172,9 +238,10 @@ fn handle_el0t_64_sync_exception(tf: &mut TrapFrame) {
 
 #[no_mangle]
 #[cfg(feature = "monolithic")]
-fn handle_el0t_64_irq_exception(_tf: &TrapFrame) {
+fn handle_el0t_64_irq_exception(tf: &TrapFrame) {
     crate::trap::handle_irq_extern(0, true);
     handle_signal();
+    update_single_step(tf);
 }
 
 #[no_mangle]
@@ -235,6 +302,7 @@ pub fn first_into_user(kernel_sp: usize, frame_base: usize) -> ! {
     crate::arch::flush_icache_all();
     //crate::arch::flush_dcache_all();
     assert_eq!(kernel_base, frame_base);
+    update_single_step(unsafe { &*(frame_base as *const TrapFrame) });
     unsafe {
         ret_to_first_user(kernel_base);
     };
@@ -6,7 +6,7 @@ mod trap;
 #[cfg(feature = "monolithic")]
 pub use trap::first_into_user;
 
-pub use self::context::{GeneralRegisters, TaskContext, TrapFrame};
+pub use self::context::{FpState, GeneralRegisters, TaskContext, TrapFrame};
 use memory_addr::{PhysAddr, VirtAddr};
 use riscv::asm;
 use riscv::register::{satp, sstatus, stvec};
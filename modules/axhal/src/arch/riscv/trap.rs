@@ -40,6 +40,8 @@ fn riscv_trap_handler(tf: &mut TrapFrame, from_user: bool) {
     #[cfg(feature = "monolithic")]
     axfs_ramfs::INTERRUPT.lock().record(scause.code());
     match scause.cause() {
+        #[cfg(feature = "signal")]
+        Trap::Exception(E::Breakpoint) if from_user => crate::trap::handle_user_breakpoint(false),
         Trap::Exception(E::Breakpoint) => handle_breakpoint(&mut tf.sepc),
         Trap::Interrupt(_) => crate::trap::handle_irq_extern(scause.bits(), from_user),
         #[cfg(feature = "monolithic")]
@@ -142,7 +142,7 @@ struct ContextSwitchFrame {
 /// See <https://www.felixcloutier.com/x86/fxsave> for more details.
 #[allow(missing_docs)]
 #[repr(C, align(16))]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct FxsaveArea {
     pub fcw: u16,
     pub fsw: u16,
@@ -159,6 +159,21 @@ pub struct FxsaveArea {
 
 static_assertions::const_assert_eq!(core::mem::size_of::<FxsaveArea>(), 512);
 
+#[cfg(feature = "fp_simd")]
+impl FxsaveArea {
+    /// Saves the FP/SIMD states of the current CPU into this area.
+    #[inline]
+    pub fn save(&mut self) {
+        unsafe { core::arch::x86_64::_fxsave64(self as *mut _ as *mut u8) }
+    }
+
+    /// Loads the FP/SIMD states of the current CPU from this area.
+    #[inline]
+    pub fn restore(&self) {
+        unsafe { core::arch::x86_64::_fxrstor64(self as *const _ as *const u8) }
+    }
+}
+
 /// Extended state of a task, such as FP/SIMD states.
 pub struct ExtendedState {
     /// Memory region for the FXSAVE/FXRSTOR instruction.
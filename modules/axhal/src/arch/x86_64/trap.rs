@@ -56,6 +56,10 @@ fn x86_trap_handler(tf: &mut TrapFrame) {
                 );
             }
         }
+        #[cfg(feature = "signal")]
+        BREAKPOINT_VECTOR | DEBUG_VECTOR if tf.is_user() => {
+            crate::trap::handle_user_breakpoint(tf.vector == DEBUG_VECTOR as u64)
+        }
         BREAKPOINT_VECTOR => debug!("#BP @ {:#x} ", tf.rip),
         GENERAL_PROTECTION_FAULT_VECTOR => {
             panic!(
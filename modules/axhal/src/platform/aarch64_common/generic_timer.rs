@@ -13,6 +13,14 @@ pub fn current_ticks() -> u64 {
     CNTPCT_EL0.get()
 }
 
+/// Returns the raw counter value at which [`current_ticks`] reads zero.
+///
+/// Used by user space (e.g. the vDSO) that reads the counter directly.
+#[inline]
+pub const fn ticks_base() -> u64 {
+    0
+}
+
 /// Converts hardware ticks to nanoseconds.
 #[inline]
 pub fn ticks_to_nanos(ticks: u64) -> u64 {
@@ -51,6 +59,12 @@ pub(crate) fn init_early() {
 }
 
 pub(crate) fn init_percpu() {
+    // Allow EL0 to read the physical and virtual counters (CNTKCTL_EL1.EL0PCTEN/EL0VCTEN).
+    unsafe {
+        let cntkctl: u64;
+        core::arch::asm!("mrs {}, cntkctl_el1", out(reg) cntkctl);
+        core::arch::asm!("msr cntkctl_el1, {}", in(reg) cntkctl | 0b11);
+    }
     #[cfg(feature = "irq")]
     {
         CNTP_CTL_EL0.write(CNTP_CTL_EL0::ENABLE::SET);
@@ -39,6 +39,11 @@ pub mod time {
         0
     }
 
+    /// Returns the raw counter value at which [`current_ticks`] reads zero.
+    pub fn ticks_base() -> u64 {
+        0
+    }
+
     /// Converts hardware ticks to nanoseconds.
     pub fn ticks_to_nanos(ticks: u64) -> u64 {
         ticks
@@ -8,6 +8,14 @@ pub fn current_ticks() -> u64 {
     time::read() as u64
 }
 
+/// Returns the raw counter value at which [`current_ticks`] reads zero.
+///
+/// Used by user space (e.g. the vDSO) that reads the counter directly.
+#[inline]
+pub const fn ticks_base() -> u64 {
+    0
+}
+
 /// Converts hardware ticks to nanoseconds.
 #[inline]
 pub const fn ticks_to_nanos(ticks: u64) -> u64 {
@@ -29,6 +37,8 @@ pub fn set_oneshot_timer(deadline_ns: u64) {
 }
 
 pub(super) fn init_percpu() {
+    // Allow user space to read the `time` CSR.
+    unsafe { riscv::register::scounteren::set_tm() };
     #[cfg(feature = "irq")]
     sbi_rt::set_timer(0);
 }
@@ -14,6 +14,13 @@ pub fn current_ticks() -> u64 {
     unsafe { core::arch::x86_64::_rdtsc() - INIT_TICK }
 }
 
+/// Returns the raw counter value at which [`current_ticks`] reads zero.
+///
+/// Used by user space (e.g. the vDSO) that reads the counter directly.
+pub fn ticks_base() -> u64 {
+    unsafe { INIT_TICK }
+}
+
 /// Converts hardware ticks to nanoseconds.
 pub fn ticks_to_nanos(ticks: u64) -> u64 {
     ticks * 1_000 / unsafe { CPU_FREQ_MHZ }
@@ -12,7 +12,7 @@ pub type TimeValue = Duration;
 pub use crate::platform::irq::TIMER_IRQ_NUM;
 #[cfg(feature = "irq")]
 pub use crate::platform::time::set_oneshot_timer;
-pub use crate::platform::time::{current_ticks, nanos_to_ticks, ticks_to_nanos};
+pub use crate::platform::time::{current_ticks, nanos_to_ticks, ticks_base, ticks_to_nanos};
 
 /// Number of milliseconds in a second.
 pub const MILLIS_PER_SEC: u64 = 1_000;
@@ -27,6 +27,10 @@ pub trait TrapHandler {
     #[cfg(feature = "signal")]
     /// Handles signals.
     fn handle_signal();
+
+    #[cfg(feature = "signal")]
+    /// Handles breakpoint and single-step traps from user space.
+    fn handle_user_breakpoint(single_step: bool);
 }
 
 /// Call the external IRQ handler.
@@ -55,3 +59,10 @@ pub(crate) fn handle_page_fault(addr: VirtAddr, flags: MappingFlags) {
 pub(crate) fn handle_signal() {
     call_interface!(TrapHandler::handle_signal);
 }
+
+/// 用户态断点与单步执行的处理函数
+#[allow(dead_code)]
+#[cfg(feature = "signal")]
+pub(crate) fn handle_user_breakpoint(single_step: bool) {
+    call_interface!(TrapHandler::handle_user_breakpoint, single_step);
+}
@@ -128,6 +128,25 @@ impl MapArea {
 
         debug!("page index {}", page_index);
 
+        // A shared mapping of a file kept in memory maps the page of the file itself, which stays
+        // owned by the file.
+        if let Some(page) = self
+            .backend
+            .as_mut()
+            .and_then(|backend| backend.shared_page(page_index))
+        {
+            page_table
+                .map_overwrite(
+                    addr.align_down_4k(),
+                    virt_to_phys(VirtAddr::from(page.as_ptr() as usize)),
+                    axhal::paging::PageSize::Size4K,
+                    self.flags,
+                )
+                .expect("Map in page fault handler failed");
+            axhal::arch::flush_tlb(addr.align_down_4k().into());
+            return true;
+        }
+
         // Allocate new page
         let mut page = PhysPage::alloc().expect("Error allocating new phys page for page fault");
 
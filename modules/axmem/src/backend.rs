@@ -1,11 +1,22 @@
 use alloc::boxed::Box;
-use axfs::api::{File, FileExt};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use axfs::api::{File, FileExt, VfsPageRef};
+use axhal::mem::PAGE_SIZE_4K;
 use axio::{Read, Seek, SeekFrom};
 
 /// File backend for Lazy load `MapArea`. `file` should be a file holding a offset value. Normally,
 /// `MemBackend` won't share a file with other things, so we use a `Box` here.
 pub struct MemBackend {
     file: Box<dyn FileExt>,
+    /// the path of the file, used to describe the mapping (e.g. in core dumps)
+    path: Option<String>,
+    /// whether the mapping is shared, so that the pages of the file itself are mapped when the
+    /// file provides them
+    shared: bool,
+    /// the pages of the file mapped into the area by their index in the file, kept alive as long
+    /// as the mapping
+    mapped: BTreeMap<u64, VfsPageRef>,
 }
 
 impl MemBackend {
@@ -13,7 +24,47 @@ impl MemBackend {
     pub fn new(mut file: Box<dyn FileExt>, offset: u64) -> Self {
         let _ = file.seek(SeekFrom::Start(offset)).unwrap();
 
-        Self { file }
+        Self {
+            file,
+            path: None,
+            shared: false,
+            mapped: BTreeMap::new(),
+        }
+    }
+
+    /// Record whether the mapping is shared with the file.
+    pub fn with_shared(mut self, shared: bool) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// Get the page of the file to map at `page_index` in a shared mapping, if the file keeps its
+    /// content in pages of its own (e.g. on tmpfs). Changes made through the page are then seen
+    /// by the file and by every other mapping of it.
+    pub fn shared_page(&mut self, page_index: usize) -> Option<VfsPageRef> {
+        if !self.shared {
+            return None;
+        }
+        let offset = self.file.seek(SeekFrom::Current(0)).ok()?;
+        if offset % PAGE_SIZE_4K as u64 != 0 {
+            return None;
+        }
+        let index = offset / PAGE_SIZE_4K as u64 + page_index as u64;
+        let page = self.file.get_page(index).ok()?;
+        // a page faulted in again replaces the one mapped before
+        self.mapped.insert(index, page.clone());
+        Some(page)
+    }
+
+    /// Record the path of the file.
+    pub fn with_path(mut self, path: String) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// the path of the file, if recorded.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
     }
 
     /// clone a new `MemBackend` with a delta offset of the file of the original `MemBackend`.
@@ -57,6 +108,9 @@ impl Clone for MemBackend {
 
         Self {
             file: Box::new(file),
+            path: self.path.clone(),
+            shared: self.shared,
+            mapped: self.mapped.clone(),
         }
     }
 }
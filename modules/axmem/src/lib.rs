@@ -11,7 +11,7 @@ extern crate alloc;
 use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use core::sync::atomic::{AtomicI32, Ordering};
 use page_table_entry::GenericPTE;
-use shared::SharedMem;
+pub use shared::SharedMem;
 use spinlock::SpinNoIrq;
 #[macro_use]
 extern crate log;
@@ -415,6 +415,18 @@ impl MemorySet {
         self.owned_mem.clear();
     }
 
+    /// 按起始地址的顺序遍历进程自己的内存区域
+    pub fn areas_mut(&mut self) -> impl Iterator<Item = &mut MapArea> {
+        self.owned_mem.values_mut()
+    }
+
+    /// 遍历挂载的共享内存，返回起始地址、大小与映射标志
+    pub fn attached_areas(&self) -> impl Iterator<Item = (VirtAddr, usize, MappingFlags)> + '_ {
+        self.attached_mem
+            .iter()
+            .map(|(start, flags, mem)| (*start, mem.size(), *flags))
+    }
+
     /// Query the page table to get the physical address, flags and page size of the given virtual
     pub fn query(&self, vaddr: VirtAddr) -> AxResult<(PhysAddr, MappingFlags, PageSize)> {
         if let Ok((paddr, flags, size)) = self.page_table.query(vaddr) {
@@ -498,6 +510,15 @@ impl MemorySet {
 }
 
 impl MemorySet {
+    /// 判断某一个虚拟地址所在的页面是否已经分配了物理页面。
+    ///
+    /// lazy 分配且尚未访问过的页面返回假，且不会为其分配物理页面。
+    pub fn is_populated(&self, addr: VirtAddr) -> bool {
+        self.page_table
+            .get_entry_mut(addr)
+            .map_or(false, |(entry, _)| entry.is_present())
+    }
+
     /// 判断某一个虚拟地址是否在内存集中。
     /// 若当前虚拟地址在内存集中，且对应的是lazy分配，暂未分配物理页的情况下，
     /// 则为其分配物理页面。
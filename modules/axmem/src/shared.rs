@@ -92,4 +92,29 @@ impl SharedMemInfo {
             l_pid: 0,
         }
     }
+
+    /// Return the user id of the owner.
+    pub fn uid(&self) -> u32 {
+        self.perm.uid
+    }
+
+    /// Return the group id of the owner.
+    pub fn gid(&self) -> u32 {
+        self.perm.gid
+    }
+
+    /// Return the user id of the creator.
+    pub fn cuid(&self) -> u32 {
+        self.perm.cuid
+    }
+
+    /// Return the group id of the creator.
+    pub fn cgid(&self) -> u32 {
+        self.perm.cgid
+    }
+
+    /// Return the permission bits.
+    pub fn mode(&self) -> u16 {
+        self.perm.mode
+    }
 }
@@ -9,6 +9,9 @@
 //! - [`TcpSocket`]: A TCP socket that provides POSIX-like APIs.
 //! - [`UdpSocket`]: A UDP socket that provides POSIX-like APIs.
 //! - [`dns_query`]: Function for DNS query.
+//! - [`interfaces`]: The network interfaces, e.g. to describe them in sysfs.
+//! - [`NetNamespace`]: An isolated set of sockets and interfaces. Sockets
+//!   created by `new` belong to the initial namespace.
 //!
 //! # Cargo Features
 //!
@@ -34,11 +37,15 @@ cfg_if::cfg_if! {
 
 mod netlink_impl;
 
-pub use self::netlink_impl::NetlinkSocket;
 pub use self::net_impl::TcpSocket;
 pub use self::net_impl::UdpSocket;
 pub use self::net_impl::{bench_receive, bench_transmit};
 pub use self::net_impl::{dns_query, from_core_sockaddr, into_core_sockaddr, poll_interfaces};
+pub use self::net_impl::{has_watched_sockets, set_poll_waker, MAX_POLL_INTERVAL};
+pub use self::net_impl::{interfaces, InterfaceInfo};
+pub use self::net_impl::{NetNamespace, INIT_NET_NS_ID};
+pub use self::net_impl::{SocketWaker, POLL_INTERVAL};
+pub use self::netlink_impl::NetlinkSocket;
 pub use smoltcp::time::Duration;
 pub use smoltcp::wire::{IpAddress as IpAddr, IpEndpoint, Ipv4Address as Ipv4Addr};
 
@@ -1,3 +1,4 @@
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use axerrno::{ax_err_type, AxError, AxResult};
 use core::net::IpAddr;
@@ -7,41 +8,44 @@ use smoltcp::socket::dns::{self, GetQueryResultError, StartQueryError};
 use smoltcp::wire::DnsQueryType;
 
 use super::addr::into_core_ipaddr;
-use super::{SocketSetWrapper, SOCKET_SET};
+use super::{NetNamespace, SocketSetWrapper};
 
 /// A DNS socket.
 struct DnsSocket {
     handle: Option<SocketHandle>,
+    ns: Arc<NetNamespace>,
 }
 
 impl DnsSocket {
     #[allow(clippy::new_without_default)]
-    /// Creates a new DNS socket.
+    /// Creates a new DNS socket in the initial network namespace.
     pub fn new() -> Self {
+        let ns = NetNamespace::init_ns();
         let socket = SocketSetWrapper::new_dns_socket();
-        let handle = Some(SOCKET_SET.add(socket));
-        Self { handle }
+        let handle = Some(ns.socket_set.add(socket));
+        Self { handle, ns }
     }
 
     #[allow(dead_code)]
     /// Update the list of DNS servers, will replace all existing servers.
     pub fn update_servers(self, servers: &[smoltcp::wire::IpAddress]) {
-        SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(self.handle.unwrap(), |socket| {
-            socket.update_servers(servers)
-        });
+        self.ns
+            .socket_set
+            .with_socket_mut::<dns::Socket, _, _>(self.handle.unwrap(), |socket| {
+                socket.update_servers(servers)
+            });
     }
 
     /// Query a address with given DNS query type.
     pub fn query(&self, name: &str, query_type: DnsQueryType) -> AxResult<Vec<IpAddr>> {
         // let local_addr = self.local_addr.unwrap_or_else(f);
         let handle = self.handle.ok_or_else(|| ax_err_type!(InvalidInput))?;
-        #[cfg(not(feature = "ip"))]
-        let iface = &super::ETH0.iface;
-        #[cfg(feature = "ip")]
-        let iface = super::LOOPBACK.try_get().unwrap();
-        let query_handle = SOCKET_SET
+        let query_handle = self
+            .ns
+            .socket_set
             .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
-                socket.start_query(iface.lock().context(), name, query_type)
+                self.ns
+                    .with_iface(|iface| socket.start_query(iface.context(), name, query_type))
             })
             .map_err(|e| match e {
                 StartQueryError::NoFreeSlot => {
@@ -55,15 +59,18 @@ impl DnsSocket {
                 }
             })?;
         loop {
-            SOCKET_SET.poll_interfaces();
-            match SOCKET_SET.with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
-                socket.get_query_result(query_handle).map_err(|e| match e {
-                    GetQueryResultError::Pending => AxError::WouldBlock,
-                    GetQueryResultError::Failed => {
-                        ax_err_type!(ConnectionRefused, "socket query() failed")
-                    }
-                })
-            }) {
+            self.ns.poll();
+            match self
+                .ns
+                .socket_set
+                .with_socket_mut::<dns::Socket, _, _>(handle, |socket| {
+                    socket.get_query_result(query_handle).map_err(|e| match e {
+                        GetQueryResultError::Pending => AxError::WouldBlock,
+                        GetQueryResultError::Failed => {
+                            ax_err_type!(ConnectionRefused, "socket query() failed")
+                        }
+                    })
+                }) {
                 Ok(n) => {
                     let mut res = Vec::with_capacity(n.capacity());
                     for ip in n {
@@ -81,7 +88,7 @@ impl DnsSocket {
 impl Drop for DnsSocket {
     fn drop(&mut self) {
         if let Some(handle) = self.handle {
-            SOCKET_SET.remove(handle);
+            self.ns.socket_set.remove(handle);
         }
     }
 }
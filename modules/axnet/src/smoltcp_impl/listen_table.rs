@@ -1,4 +1,4 @@
-use alloc::{boxed::Box, collections::VecDeque};
+use alloc::{boxed::Box, collections::BTreeMap, collections::VecDeque, vec::Vec};
 use core::ops::{Deref, DerefMut};
 
 use axerrno::{ax_err, AxError, AxResult};
@@ -7,7 +7,7 @@ use smoltcp::iface::{SocketHandle, SocketSet};
 use smoltcp::socket::tcp::{self, State};
 use smoltcp::wire::{IpAddress, IpEndpoint, IpListenEndpoint};
 
-use super::{SocketSetWrapper, LISTEN_QUEUE_SIZE, SOCKET_SET};
+use super::{SocketSetWrapper, SocketWaker, LISTEN_QUEUE_SIZE};
 
 const PORT_NUM: usize = 65536;
 
@@ -33,16 +33,11 @@ impl ListenTableEntry {
     }
 }
 
-impl Drop for ListenTableEntry {
-    fn drop(&mut self) {
-        for &handle in &self.syn_queue {
-            SOCKET_SET.remove(handle);
-        }
-    }
-}
-
 pub struct ListenTable {
     tcp: Box<[Mutex<Option<Box<ListenTableEntry>>>]>,
+    /// The wakers of the listening ports, with the number of connections
+    /// ready to accept when last checked.
+    wakers: Mutex<BTreeMap<u16, (SocketWaker, usize)>>,
 }
 
 impl ListenTable {
@@ -54,7 +49,10 @@ impl ListenTable {
             }
             buf.assume_init()
         };
-        Self { tcp }
+        Self {
+            tcp,
+            wakers: Mutex::new(BTreeMap::new()),
+        }
     }
 
     pub fn can_listen(&self, port: u16) -> bool {
@@ -73,27 +71,77 @@ impl ListenTable {
         }
     }
 
-    pub fn unlisten(&self, port: u16) {
+    /// Stops listening on the port and drops the pending connections from
+    /// `sockets`, the socket set of the namespace that owns this table.
+    pub fn unlisten(&self, port: u16, sockets: &SocketSetWrapper) {
         debug!("TCP socket unlisten on {}", port);
-        *self.tcp[port as usize].lock() = None;
+        if self.wakers.lock().remove(&port).is_some() {
+            super::unwatch_socket();
+        }
+        if let Some(entry) = self.tcp[port as usize].lock().take() {
+            for &handle in &entry.syn_queue {
+                sockets.remove(handle);
+            }
+        }
     }
 
-    pub fn can_accept(&self, port: u16) -> AxResult<bool> {
+    /// Calls `waker` whenever a poll adds connections ready to accept on the
+    /// port.
+    pub fn set_waker(&self, port: u16, waker: SocketWaker) {
+        if self.wakers.lock().insert(port, (waker, 0)).is_none() {
+            super::watch_socket();
+        }
+    }
+
+    /// Calls the wakers of the ports whose number of connections ready to
+    /// accept changed since the last call, returning whether any was called.
+    pub(crate) fn wake_acceptable(&self, sockets: &SocketSetWrapper) -> bool {
+        let mut woken = Vec::new();
+        for (&port, (waker, last)) in self.wakers.lock().iter_mut() {
+            // the entry is not held while looking at the sockets, as the
+            // interface takes them in the opposite order
+            let handles: Vec<SocketHandle> = match self.tcp[port as usize].lock().deref() {
+                Some(entry) => entry.syn_queue.iter().copied().collect(),
+                None => continue,
+            };
+            let ready = handles
+                .into_iter()
+                .filter(|&handle| is_connected(handle, sockets))
+                .count();
+            if core::mem::replace(last, ready) != ready {
+                woken.push(waker.clone());
+            }
+        }
+        let changed = !woken.is_empty();
+        for waker in woken {
+            waker();
+        }
+        changed
+    }
+
+    pub fn can_accept(&self, port: u16, sockets: &SocketSetWrapper) -> AxResult<bool> {
         if let Some(entry) = self.tcp[port as usize].lock().deref() {
-            Ok(entry.syn_queue.iter().any(|&handle| is_connected(handle)))
+            Ok(entry
+                .syn_queue
+                .iter()
+                .any(|&handle| is_connected(handle, sockets)))
         } else {
             ax_err!(InvalidInput, "socket accept() failed: not listen")
         }
     }
 
-    pub fn accept(&self, port: u16) -> AxResult<(SocketHandle, (IpEndpoint, IpEndpoint))> {
+    pub fn accept(
+        &self,
+        port: u16,
+        sockets: &SocketSetWrapper,
+    ) -> AxResult<(SocketHandle, (IpEndpoint, IpEndpoint))> {
         if let Some(entry) = self.tcp[port as usize].lock().deref_mut() {
             let syn_queue = &mut entry.syn_queue;
             let (idx, addr_tuple) = syn_queue
                 .iter()
                 .enumerate()
                 .find_map(|(idx, &handle)| {
-                    is_connected(handle).then(|| (idx, get_addr_tuple(handle)))
+                    is_connected(handle, sockets).then(|| (idx, get_addr_tuple(handle, sockets)))
                 })
                 .ok_or(AxError::WouldBlock)?; // wait for connection
             if idx > 0 {
@@ -139,14 +187,14 @@ impl ListenTable {
     }
 }
 
-fn is_connected(handle: SocketHandle) -> bool {
-    SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
+fn is_connected(handle: SocketHandle, sockets: &SocketSetWrapper) -> bool {
+    sockets.with_socket::<tcp::Socket, _, _>(handle, |socket| {
         !matches!(socket.state(), State::Listen | State::SynReceived)
     })
 }
 
-fn get_addr_tuple(handle: SocketHandle) -> (IpEndpoint, IpEndpoint) {
-    SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
+fn get_addr_tuple(handle: SocketHandle, sockets: &SocketSetWrapper) -> (IpEndpoint, IpEndpoint) {
+    sockets.with_socket::<tcp::Socket, _, _>(handle, |socket| {
         (
             socket.local_endpoint().unwrap(),
             socket.remote_endpoint().unwrap(),
@@ -1,27 +1,34 @@
-use alloc::{collections::VecDeque, vec, vec::Vec};
+use alloc::{collections::VecDeque, sync::Arc, vec, vec::Vec};
 use smoltcp::{
     iface::SocketSet,
     phy::{Device, DeviceCapabilities, Medium},
     time::Instant,
 };
 
-use crate::net_impl::LISTEN_TABLE;
+use super::listen_table::ListenTable;
 
 pub(crate) struct LoopbackDev {
     pub(crate) queue: VecDeque<Vec<u8>>,
     medium: Medium,
+    /// The listen table of the namespace that owns this device.
+    listen_table: Arc<ListenTable>,
 }
 
 impl LoopbackDev {
-    pub fn new(medium: Medium) -> Self {
+    pub fn new(medium: Medium, listen_table: Arc<ListenTable>) -> Self {
         Self {
             queue: VecDeque::new(),
             medium,
+            listen_table,
         }
     }
 }
 
-fn snoop_tcp_from_ip(buffer: &[u8], sockets: &mut SocketSet) -> Result<(), smoltcp::wire::Error> {
+fn snoop_tcp_from_ip(
+    buffer: &[u8],
+    listen_table: &ListenTable,
+    sockets: &mut SocketSet,
+) -> Result<(), smoltcp::wire::Error> {
     use crate::SocketAddr;
     use smoltcp::wire::{IpProtocol, Ipv4Packet, TcpPacket};
 
@@ -34,21 +41,22 @@ fn snoop_tcp_from_ip(buffer: &[u8], sockets: &mut SocketSet) -> Result<(), smolt
         let is_first = tcp_packet.syn() && !tcp_packet.ack();
         if is_first {
             // create a socket for the first incoming TCP packet, as the later accept() returns.
-            LISTEN_TABLE.incoming_tcp_packet(src_addr.into(), dst_addr.into(), sockets);
+            listen_table.incoming_tcp_packet(src_addr.into(), dst_addr.into(), sockets);
         }
     }
     Ok(())
 }
 
-pub(crate) struct RxTokenScoop {
+pub(crate) struct RxTokenScoop<'a> {
     buffer: Vec<u8>,
+    listen_table: &'a ListenTable,
 }
 
 pub(crate) struct TxToken<'a> {
     queue: &'a mut VecDeque<Vec<u8>>,
 }
 
-impl smoltcp::phy::RxToken for RxTokenScoop {
+impl<'a> smoltcp::phy::RxToken for RxTokenScoop<'a> {
     fn consume<R, F>(mut self, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R,
@@ -57,7 +65,7 @@ impl smoltcp::phy::RxToken for RxTokenScoop {
     }
 
     fn preprocess(&self, sockets: &mut SocketSet<'_>) {
-        snoop_tcp_from_ip(&self.buffer, sockets).ok();
+        snoop_tcp_from_ip(&self.buffer, self.listen_table, sockets).ok();
     }
 }
 
@@ -74,8 +82,8 @@ impl<'a> smoltcp::phy::TxToken for TxToken<'a> {
 }
 
 impl Device for LoopbackDev {
-    type RxToken<'a> = RxTokenScoop;
-    type TxToken<'a> = TxToken<'a>;
+    type RxToken<'a> = RxTokenScoop<'a> where Self: 'a;
+    type TxToken<'a> = TxToken<'a> where Self: 'a;
 
     fn capabilities(&self) -> DeviceCapabilities {
         let mut cap = DeviceCapabilities::default();
@@ -87,11 +95,14 @@ impl Device for LoopbackDev {
     }
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        self.queue.pop_front().map(move |buffer| {
-            let rx = Self::RxToken { buffer };
-            let tx = Self::TxToken {
-                queue: &mut self.queue,
+        let listen_table = &self.listen_table;
+        let queue = &mut self.queue;
+        queue.pop_front().map(move |buffer| {
+            let rx = RxTokenScoop {
+                buffer,
+                listen_table,
             };
+            let tx = TxToken { queue };
             (rx, tx)
         })
     }
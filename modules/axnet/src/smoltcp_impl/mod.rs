@@ -2,28 +2,29 @@ mod addr;
 mod bench;
 mod dns;
 mod listen_table;
+mod loopback;
+mod netns;
 
 mod tcp;
 mod udp;
-use alloc::vec;
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
 use core::cell::RefCell;
 use core::ops::DerefMut;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
 use axdriver::prelude::*;
 use axhal::time::{current_time_nanos, NANOS_PER_MICROS};
 use axsync::Mutex;
 use driver_net::{DevError, NetBufPtr};
-use lazy_init::LazyInit;
 use smoltcp::iface::{Config, Interface, SocketHandle, SocketSet};
 use smoltcp::phy::{Device, DeviceCapabilities, Medium, RxToken, TxToken};
 use smoltcp::socket::{self, AnySocket, Socket};
 use smoltcp::time::Instant;
 use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr};
 
-use self::listen_table::ListenTable;
-
 pub use self::dns::dns_query;
+pub use self::netns::{NetNamespace, INIT_NET_NS_ID};
 pub use self::tcp::TcpSocket;
 pub use self::udp::UdpSocket;
 pub use addr::{from_core_sockaddr, into_core_sockaddr};
@@ -47,26 +48,87 @@ const UDP_RX_BUF_LEN: usize = 64 * 1024;
 const UDP_TX_BUF_LEN: usize = 64 * 1024;
 const LISTEN_QUEUE_SIZE: usize = 512;
 
-static LISTEN_TABLE: LazyInit<ListenTable> = LazyInit::new();
-static SOCKET_SET: LazyInit<SocketSetWrapper> = LazyInit::new();
+/// How often the interfaces are polled in the background while sockets are
+/// active.
+///
+/// The NICs raise no interrupts, so packets are only received when the
+/// interfaces are polled. Each poll wakes the sockets whose readiness changed.
+pub const POLL_INTERVAL: core::time::Duration = core::time::Duration::from_millis(1);
+
+/// The longest interval between background polls. The interval doubles up to
+/// this after each poll that wakes no socket.
+pub const MAX_POLL_INTERVAL: core::time::Duration = core::time::Duration::from_millis(16);
+
+/// The number of sockets and listening ports with a waker, i.e. the ones a
+/// background poll may have to wake.
+static WATCHED_SOCKETS: AtomicUsize = AtomicUsize::new(0);
+
+/// Called when the first socket gets a waker, to resume background polling.
+static POLL_WAKER: Mutex<Option<SocketWaker>> = Mutex::new(None);
 
 cfg_if::cfg_if! {
-    if #[cfg(feature = "ip")] {
-        mod loopback;
-        static LOOPBACK_DEV: LazyInit<Mutex<LoopbackDev>> = LazyInit::new();
-        static LOOPBACK: LazyInit<Mutex<Interface>> = LazyInit::new();
-        use self::loopback::LoopbackDev;
-    }
-    else {
+    if #[cfg(not(feature = "ip"))] {
         const IP: &str = env_or_default!("AX_IP");
         const GATEWAY: &str = env_or_default!("AX_GW");
         const IP_PREFIX: u8 = 24;
 
+        use lazy_init::LazyInit;
         static ETH0: LazyInit<InterfaceWrapper> = LazyInit::new();
     }
 }
 
-struct SocketSetWrapper<'a>(Mutex<SocketSet<'a>>);
+/// Called when the readiness of a socket may have changed, e.g. to wake the
+/// tasks polling it.
+pub type SocketWaker = Arc<dyn Fn() + Send + Sync>;
+
+/// What a waiter can observe of a socket, compared before and after a poll.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Readiness {
+    tcp_state: Option<socket::tcp::State>,
+    recv_queue: usize,
+    send_queue: usize,
+    can_recv: bool,
+    can_send: bool,
+}
+
+impl Readiness {
+    fn of(socket: &Socket) -> Self {
+        match socket {
+            Socket::Tcp(s) => Self {
+                tcp_state: Some(s.state()),
+                recv_queue: s.recv_queue(),
+                send_queue: s.send_queue(),
+                can_recv: s.can_recv(),
+                can_send: s.can_send(),
+            },
+            Socket::Udp(s) => Self {
+                tcp_state: None,
+                recv_queue: 0,
+                send_queue: 0,
+                can_recv: s.can_recv(),
+                can_send: s.can_send(),
+            },
+            _ => Self {
+                tcp_state: None,
+                recv_queue: 0,
+                send_queue: 0,
+                can_recv: false,
+                can_send: false,
+            },
+        }
+    }
+}
+
+struct WatchedSocket {
+    waker: SocketWaker,
+    last: Option<Readiness>,
+}
+
+struct SocketSetWrapper<'a> {
+    sockets: Mutex<SocketSet<'a>>,
+    /// The sockets that want to know when their readiness changes.
+    watched: Mutex<BTreeMap<SocketHandle, WatchedSocket>>,
+}
 
 struct DeviceWrapper {
     inner: RefCell<AxNetDevice>, // use `RefCell` is enough since it's wrapped in `Mutex` in `InterfaceWrapper`.
@@ -81,7 +143,10 @@ struct InterfaceWrapper {
 
 impl<'a> SocketSetWrapper<'a> {
     fn new() -> Self {
-        Self(Mutex::new(SocketSet::new(vec![])))
+        Self {
+            sockets: Mutex::new(SocketSet::new(vec![])),
+            watched: Mutex::new(BTreeMap::new()),
+        }
     }
 
     pub fn new_tcp_socket() -> socket::tcp::Socket<'a> {
@@ -108,7 +173,7 @@ impl<'a> SocketSetWrapper<'a> {
     }
 
     pub fn add<T: AnySocket<'a>>(&self, socket: T) -> SocketHandle {
-        let handle = self.0.lock().add(socket);
+        let handle = self.sockets.lock().add(socket);
         debug!("socket {}: created", handle);
         handle
     }
@@ -117,7 +182,7 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&T) -> R,
     {
-        let set = self.0.lock();
+        let set = self.sockets.lock();
         let socket = set.get(handle);
         f(socket)
     }
@@ -126,13 +191,13 @@ impl<'a> SocketSetWrapper<'a> {
     where
         F: FnOnce(&mut T) -> R,
     {
-        let mut set = self.0.lock();
+        let mut set = self.sockets.lock();
         let socket = set.get_mut(handle);
         f(socket)
     }
 
     pub fn bind_check(&self, addr: IpAddress, port: u16) -> AxResult {
-        let mut sockets = self.0.lock();
+        let mut sockets = self.sockets.lock();
         error!("checking addr: {:?}, port: {}", addr, port);
         for item in sockets.iter_mut() {
             match item.1 {
@@ -148,26 +213,75 @@ impl<'a> SocketSetWrapper<'a> {
         Ok(())
     }
 
-    pub fn poll_interfaces(&self) {
-        #[cfg(feature = "ip")]
+    pub fn remove(&self, handle: SocketHandle) {
+        if self.watched.lock().remove(&handle).is_some() {
+            unwatch_socket();
+        }
+        self.sockets.lock().remove(handle);
+        debug!("socket {}: destroyed", handle);
+    }
+
+    /// Calls `waker` whenever a poll changes the readiness of the socket.
+    pub fn set_waker(&self, handle: SocketHandle, waker: SocketWaker) {
+        let watched = WatchedSocket { waker, last: None };
+        if self.watched.lock().insert(handle, watched).is_none() {
+            watch_socket();
+        }
+    }
+
+    /// Calls the wakers of the sockets whose readiness changed since the last
+    /// call, which is made after every poll of the interfaces.
+    ///
+    /// Returns whether any waker was called.
+    fn wake_changed(&self) -> bool {
+        let mut woken = Vec::new();
         {
-            let timestamp =
-                Instant::from_micros_const((current_time_nanos() / NANOS_PER_MICROS) as i64);
-            let mut sockets = self.0.lock();
-            LOOPBACK
-                .lock()
-                .poll(timestamp, LOOPBACK_DEV.lock().deref_mut(), &mut sockets);
+            let sockets = self.sockets.lock();
+            let mut watched = self.watched.lock();
+            for (handle, socket) in sockets.iter() {
+                if let Some(watch) = watched.get_mut(&handle) {
+                    let now = Readiness::of(socket);
+                    if watch.last.replace(now) != Some(now) {
+                        woken.push(watch.waker.clone());
+                    }
+                }
+            }
         }
-        #[cfg(not(feature = "ip"))]
-        ETH0.poll(&self.0);
+        // the wakers may take the locks of the socket set again
+        let changed = !woken.is_empty();
+        for waker in woken {
+            waker();
+        }
+        changed
     }
+}
 
-    pub fn remove(&self, handle: SocketHandle) {
-        self.0.lock().remove(handle);
-        debug!("socket {}: destroyed", handle);
+/// Records that a socket or listening port got a waker.
+pub(crate) fn watch_socket() {
+    if WATCHED_SOCKETS.fetch_add(1, Ordering::AcqRel) == 0 {
+        let waker = POLL_WAKER.lock().clone();
+        if let Some(waker) = waker {
+            waker();
+        }
     }
 }
 
+/// Records that a socket or listening port with a waker went away.
+pub(crate) fn unwatch_socket() {
+    WATCHED_SOCKETS.fetch_sub(1, Ordering::AcqRel);
+}
+
+/// Whether any socket has a waker, i.e. whether background polling is needed.
+pub fn has_watched_sockets() -> bool {
+    WATCHED_SOCKETS.load(Ordering::Acquire) != 0
+}
+
+/// Sets the function called when the first socket gets a waker, so that an
+/// idle background poller can resume.
+pub fn set_poll_waker(waker: SocketWaker) {
+    *POLL_WAKER.lock() = Some(waker);
+}
+
 #[allow(unused)]
 impl InterfaceWrapper {
     fn new(name: &'static str, dev: AxNetDevice, ether_addr: EthernetAddress) -> Self {
@@ -326,7 +440,10 @@ fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smolt
         let is_first = tcp_packet.syn() && !tcp_packet.ack();
         if is_first {
             // create a socket for the first incoming TCP packet, as the later accept() returns.
-            LISTEN_TABLE.incoming_tcp_packet(src_addr, dst_addr, sockets);
+            // The NIC only belongs to the initial network namespace.
+            netns::INIT_NET_NS
+                .listen_table
+                .incoming_tcp_packet(src_addr, dst_addr, sockets);
         }
     }
     Ok(())
@@ -335,9 +452,49 @@ fn snoop_tcp_packet(buf: &[u8], sockets: &mut SocketSet<'_>) -> Result<(), smolt
 /// Poll the network stack.
 ///
 /// It may receive packets from the NIC and process them, and transmit queued
-/// packets to the NIC.
-pub fn poll_interfaces() {
-    SOCKET_SET.poll_interfaces();
+/// packets to the NIC. Interfaces of all network namespaces are polled.
+///
+/// Returns whether the readiness of any socket with a waker changed.
+pub fn poll_interfaces() -> bool {
+    netns::poll_all()
+}
+
+/// A network interface of the initial network namespace.
+#[derive(Clone, Debug)]
+pub struct InterfaceInfo {
+    /// The name, e.g. `eth0`.
+    pub name: &'static str,
+    /// The index, starting from 1 for the loopback interface.
+    pub index: u32,
+    /// The hardware address, all zeros for the loopback interface.
+    pub mac: [u8; 6],
+    /// The maximum transmission unit in bytes.
+    pub mtu: usize,
+    /// Whether it is the loopback interface.
+    pub loopback: bool,
+}
+
+/// Lists the network interfaces of the initial network namespace.
+pub fn interfaces() -> Vec<InterfaceInfo> {
+    #[allow(unused_mut)]
+    let mut interfaces = vec![InterfaceInfo {
+        name: "lo",
+        index: 1,
+        mac: [0; 6],
+        mtu: 65536,
+        loopback: true,
+    }];
+    #[cfg(not(feature = "ip"))]
+    if let Some(eth0) = ETH0.try_get() {
+        interfaces.push(InterfaceInfo {
+            name: eth0.name,
+            index: 2,
+            mac: eth0.ether_addr.0,
+            mtu: STANDARD_MTU,
+            loopback: false,
+        });
+    }
+    interfaces
 }
 
 /// Benchmark raw socket transmit bandwidth.
@@ -353,28 +510,6 @@ pub fn bench_receive() {
 }
 
 pub(crate) fn init(_net_dev: AxNetDevice) {
-    #[cfg(feature = "ip")]
-    {
-        let mut device = LoopbackDev::new(Medium::Ip);
-        let config = Config::new(smoltcp::wire::HardwareAddress::Ip);
-
-        let mut iface = Interface::new(
-            config,
-            &mut device,
-            Instant::from_micros_const((current_time_nanos() / NANOS_PER_MICROS) as i64),
-        );
-        iface.update_ip_addrs(|ip_addrs| {
-            ip_addrs
-                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
-                .unwrap();
-        });
-
-        let multicast_addr = IpAddress::v4(239, 255, 0, 1);
-        let _= iface.join_multicast_group(&mut device, multicast_addr, Instant::from_micros_const((current_time_nanos() / NANOS_PER_MICROS) as i64));
-        LOOPBACK.init_by(Mutex::new(iface));
-        LOOPBACK_DEV.init_by(Mutex::new(device));
-    }
-
     #[cfg(not(feature = "ip"))]
     {
         let ether_addr = EthernetAddress(_net_dev.mac_address().0);
@@ -392,6 +527,5 @@ pub(crate) fn init(_net_dev: AxNetDevice) {
         info!("  gateway:  {}", gateway);
     }
 
-    SOCKET_SET.init_by(SocketSetWrapper::new());
-    LISTEN_TABLE.init_by(ListenTable::new());
+    netns::init();
 }
@@ -0,0 +1,166 @@
+//! Network namespaces.
+//!
+//! Every namespace owns a socket set, a TCP listen table and a loopback
+//! interface, so sockets in different namespaces can bind the same ports
+//! without seeing each other. A socket remembers the namespace it was created
+//! in, and [`poll_all`] polls the interfaces of all live namespaces.
+//!
+//! Without the `ip` feature, the initial namespace uses the NIC (`eth0`)
+//! instead of a loopback interface.
+
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::ops::DerefMut;
+
+use axhal::time::{current_time_nanos, NANOS_PER_MICROS};
+use axsync::Mutex;
+use lazy_init::LazyInit;
+use smoltcp::iface::{Config, Interface};
+use smoltcp::phy::Medium;
+use smoltcp::time::Instant;
+use smoltcp::wire::{HardwareAddress, IpAddress, IpCidr};
+
+use super::listen_table::ListenTable;
+use super::loopback::LoopbackDev;
+use super::SocketSetWrapper;
+
+/// The identifier of the initial network namespace.
+pub const INIT_NET_NS_ID: u64 = 0xF000_0000;
+
+pub(crate) static INIT_NET_NS: LazyInit<Arc<NetNamespace>> = LazyInit::new();
+
+/// All namespaces that may still have sockets to poll.
+static NET_NAMESPACES: Mutex<Vec<Weak<NetNamespace>>> = Mutex::new(Vec::new());
+
+struct LoopbackInterface {
+    dev: Mutex<LoopbackDev>,
+    iface: Mutex<Interface>,
+}
+
+impl LoopbackInterface {
+    fn new(listen_table: Arc<ListenTable>) -> Self {
+        let mut dev = LoopbackDev::new(Medium::Ip, listen_table);
+        let config = Config::new(HardwareAddress::Ip);
+        let mut iface = Interface::new(config, &mut dev, current_instant());
+        iface.update_ip_addrs(|ip_addrs| {
+            ip_addrs
+                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+                .unwrap();
+        });
+        let multicast_addr = IpAddress::v4(239, 255, 0, 1);
+        let _ = iface.join_multicast_group(&mut dev, multicast_addr, current_instant());
+        Self {
+            dev: Mutex::new(dev),
+            iface: Mutex::new(iface),
+        }
+    }
+}
+
+/// An isolated network stack: sockets, listen table and interfaces.
+pub struct NetNamespace {
+    id: u64,
+    pub(crate) socket_set: SocketSetWrapper<'static>,
+    pub(crate) listen_table: Arc<ListenTable>,
+    /// `None` only for the initial namespace when it uses the NIC.
+    loopback: Option<LoopbackInterface>,
+}
+
+impl NetNamespace {
+    /// Creates a new namespace that has only a loopback interface.
+    pub fn new(id: u64) -> Arc<Self> {
+        Self::with_loopback(id, true)
+    }
+
+    fn with_loopback(id: u64, loopback: bool) -> Arc<Self> {
+        let listen_table = Arc::new(ListenTable::new());
+        let loopback = loopback.then(|| LoopbackInterface::new(listen_table.clone()));
+        let ns = Arc::new(Self {
+            id,
+            socket_set: SocketSetWrapper::new(),
+            listen_table,
+            loopback,
+        });
+        let mut namespaces = NET_NAMESPACES.lock();
+        namespaces.retain(|ns| ns.strong_count() > 0);
+        namespaces.push(Arc::downgrade(&ns));
+        ns
+    }
+
+    /// Returns the initial namespace, which is created with the network
+    /// subsystem.
+    pub fn init_ns() -> Arc<Self> {
+        INIT_NET_NS.clone()
+    }
+
+    /// Returns the identifier of the namespace.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Calls `f` with the interface that sockets of this namespace use.
+    pub(crate) fn with_iface<R>(&self, f: impl FnOnce(&mut Interface) -> R) -> R {
+        match &self.loopback {
+            Some(lo) => f(&mut lo.iface.lock()),
+            #[cfg(not(feature = "ip"))]
+            None => f(&mut super::ETH0.iface.lock()),
+            #[cfg(feature = "ip")]
+            None => unreachable!("namespace without interfaces"),
+        }
+    }
+
+    /// Joins a multicast group on the loopback interface.
+    pub(crate) fn join_multicast_group(&self, addr: IpAddress) {
+        if let Some(lo) = &self.loopback {
+            let _ = lo.iface.lock().join_multicast_group(
+                lo.dev.lock().deref_mut(),
+                addr,
+                current_instant(),
+            );
+        }
+    }
+
+    /// Polls the interfaces of this namespace, returning whether any waker was
+    /// called.
+    pub(crate) fn poll(&self) -> bool {
+        match &self.loopback {
+            Some(lo) => {
+                let mut sockets = self.socket_set.sockets.lock();
+                lo.iface
+                    .lock()
+                    .poll(current_instant(), lo.dev.lock().deref_mut(), &mut sockets);
+            }
+            #[cfg(not(feature = "ip"))]
+            None => super::ETH0.poll(&self.socket_set.sockets),
+            #[cfg(feature = "ip")]
+            None => {}
+        }
+        let changed = self.socket_set.wake_changed();
+        self.listen_table.wake_acceptable(&self.socket_set) || changed
+    }
+}
+
+fn current_instant() -> Instant {
+    Instant::from_micros_const((current_time_nanos() / NANOS_PER_MICROS) as i64)
+}
+
+/// Polls the interfaces of all live namespaces, returning whether any waker
+/// was called.
+pub(crate) fn poll_all() -> bool {
+    let namespaces: Vec<Arc<NetNamespace>> = NET_NAMESPACES
+        .lock()
+        .iter()
+        .filter_map(Weak::upgrade)
+        .collect();
+    let mut changed = false;
+    for ns in namespaces {
+        changed |= ns.poll();
+    }
+    changed
+}
+
+pub(crate) fn init() {
+    INIT_NET_NS.init_by(NetNamespace::with_loopback(
+        INIT_NET_NS_ID,
+        cfg!(feature = "ip"),
+    ));
+}
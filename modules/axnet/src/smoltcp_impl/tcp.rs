@@ -1,3 +1,4 @@
+use alloc::sync::Arc;
 use core::cell::UnsafeCell;
 use core::net::SocketAddr;
 use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
@@ -13,7 +14,8 @@ use smoltcp::socket::tcp::{self, ConnectError, State};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{from_core_sockaddr, into_core_sockaddr, is_unspecified, UNSPECIFIED_ENDPOINT};
-use super::{SocketSetWrapper, LISTEN_TABLE, SOCKET_SET};
+use super::listen_table::ListenTable;
+use super::{NetNamespace, SocketSetWrapper, SocketWaker};
 
 // State transitions:
 // CLOSED -(connect)-> BUSY -> CONNECTING -> CONNECTED -(shutdown)-> BUSY -> CLOSED
@@ -43,27 +45,38 @@ pub struct TcpSocket {
     local_addr: UnsafeCell<IpEndpoint>,
     peer_addr: UnsafeCell<IpEndpoint>,
     nonblock: AtomicBool,
+    waker: Mutex<Option<SocketWaker>>,
+    ns: Arc<NetNamespace>,
 }
 
 unsafe impl Sync for TcpSocket {}
 
 impl TcpSocket {
-    /// Creates a new TCP socket.
-    pub const fn new() -> Self {
+    /// Creates a new TCP socket in the initial network namespace.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self::new_in(NetNamespace::init_ns())
+    }
+
+    /// Creates a new TCP socket in the given network namespace.
+    pub fn new_in(ns: Arc<NetNamespace>) -> Self {
         Self {
             state: AtomicU8::new(STATE_CLOSED),
             handle: UnsafeCell::new(None),
             local_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             peer_addr: UnsafeCell::new(UNSPECIFIED_ENDPOINT),
             nonblock: AtomicBool::new(false),
+            waker: Mutex::new(None),
+            ns,
         }
     }
 
     /// Creates a new TCP socket that is already connected.
-    const fn new_connected(
+    fn new_connected(
         handle: SocketHandle,
         local_addr: IpEndpoint,
         peer_addr: IpEndpoint,
+        ns: Arc<NetNamespace>,
     ) -> Self {
         Self {
             state: AtomicU8::new(STATE_CONNECTED),
@@ -71,6 +84,8 @@ impl TcpSocket {
             local_addr: UnsafeCell::new(local_addr),
             peer_addr: UnsafeCell::new(peer_addr),
             nonblock: AtomicBool::new(false),
+            waker: Mutex::new(None),
+            ns,
         }
     }
 
@@ -118,6 +133,20 @@ impl TcpSocket {
         self.nonblock.store(nonblocking, Ordering::Release);
     }
 
+    /// Calls `waker` whenever the readiness of the socket may have changed,
+    /// for the connection or for the listening port.
+    pub fn set_waker(&self, waker: SocketWaker) {
+        *self.waker.lock() = Some(waker.clone());
+        // SAFETY: the handle and the address are only written before the
+        // state becomes connected or listening.
+        if let Some(handle) = unsafe { self.handle.get().read() } {
+            self.ns.socket_set.set_waker(handle, waker);
+        } else if self.is_listening() {
+            let local_port = unsafe { self.local_addr.get().read().port };
+            self.ns.listen_table.set_waker(local_port, waker);
+        }
+    }
+
     /// Connects to the given address and port.
     ///
     /// The local port is generated automatically.
@@ -125,20 +154,19 @@ impl TcpSocket {
         self.update_state(STATE_CLOSED, STATE_CONNECTING, || {
             // SAFETY: no other threads can read or write these fields.
             let handle = unsafe { self.handle.get().read() }
-                .unwrap_or_else(|| SOCKET_SET.add(SocketSetWrapper::new_tcp_socket()));
+                .unwrap_or_else(|| self.ns.socket_set.add(SocketSetWrapper::new_tcp_socket()));
 
             // TODO: check remote addr unreachable
             let remote_endpoint = from_core_sockaddr(remote_addr);
             let bound_endpoint = self.bound_endpoint()?;
-            #[cfg(not(feature = "ip"))]
-            let iface = &super::ETH0.iface;
-
-            #[cfg(feature = "ip")]
-            let iface = super::LOOPBACK.try_get().unwrap();
-            let (local_endpoint, remote_endpoint) = SOCKET_SET
+            let (local_endpoint, remote_endpoint) = self
+                .ns
+                .socket_set
                 .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                    socket
-                        .connect(iface.lock().context(), remote_endpoint, bound_endpoint)
+                    self.ns
+                        .with_iface(|iface| {
+                            socket.connect(iface.context(), remote_endpoint, bound_endpoint)
+                        })
                         .or_else(|e| match e {
                             ConnectError::InvalidState => {
                                 ax_err!(BadState, "socket connect() failed")
@@ -159,6 +187,9 @@ impl TcpSocket {
                 self.peer_addr.get().write(remote_endpoint);
                 self.handle.get().write(Some(handle));
             }
+            if let Some(waker) = self.waker.lock().clone() {
+                self.ns.socket_set.set_waker(handle, waker);
+            }
             Ok(())
         })
         .unwrap_or_else(|_| ax_err!(AlreadyExists, "socket connect() failed: already connected"))?; // EISCONN
@@ -193,7 +224,7 @@ impl TcpSocket {
         self.update_state(STATE_CLOSED, STATE_CLOSED, || {
             // TODO: check addr is available
             if local_addr.port() == 0 {
-                local_addr.set_port(get_ephemeral_port()?);
+                local_addr.set_port(get_ephemeral_port(&self.ns.listen_table)?);
             }
             // SAFETY: no other threads can read or write `self.local_addr` as we
             // have changed the state to `BUSY`.
@@ -219,7 +250,10 @@ impl TcpSocket {
             unsafe {
                 (*self.local_addr.get()).port = bound_endpoint.port;
             }
-            LISTEN_TABLE.listen(bound_endpoint)?;
+            self.ns.listen_table.listen(bound_endpoint)?;
+            if let Some(waker) = self.waker.lock().clone() {
+                self.ns.listen_table.set_waker(bound_endpoint.port, waker);
+            }
             debug!("TCP socket listening on {}", bound_endpoint);
             Ok(())
         })
@@ -240,9 +274,17 @@ impl TcpSocket {
         // SAFETY: `self.local_addr` should be initialized after `bind()`.
         let local_port = unsafe { self.local_addr.get().read().port };
         self.block_on(|| {
-            let (handle, (local_addr, peer_addr)) = LISTEN_TABLE.accept(local_port)?;
+            let (handle, (local_addr, peer_addr)) = self
+                .ns
+                .listen_table
+                .accept(local_port, &self.ns.socket_set)?;
             debug!("TCP socket accepted a new connection {}", peer_addr);
-            Ok(TcpSocket::new_connected(handle, local_addr, peer_addr))
+            Ok(TcpSocket::new_connected(
+                handle,
+                local_addr,
+                peer_addr,
+                self.ns.clone(),
+            ))
         })
     }
 
@@ -253,12 +295,14 @@ impl TcpSocket {
             // SAFETY: `self.handle` should be initialized in a connected socket, and
             // no other threads can read or write it.
             let handle = unsafe { self.handle.get().read().unwrap() };
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                debug!("TCP socket {}: shutting down", handle);
-                socket.close();
-            });
+            self.ns
+                .socket_set
+                .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    debug!("TCP socket {}: shutting down", handle);
+                    socket.close();
+                });
             unsafe { self.local_addr.get().write(UNSPECIFIED_ENDPOINT) }; // clear bound address
-            SOCKET_SET.poll_interfaces();
+            self.ns.poll();
             Ok(())
         })
         .unwrap_or(Ok(()))?;
@@ -269,8 +313,10 @@ impl TcpSocket {
             // and no other threads can read or write it.
             let local_port = unsafe { self.local_addr.get().read().port };
             unsafe { self.local_addr.get().write(UNSPECIFIED_ENDPOINT) }; // clear bound address
-            LISTEN_TABLE.unlisten(local_port);
-            SOCKET_SET.poll_interfaces();
+            self.ns
+                .listen_table
+                .unlisten(local_port, &self.ns.socket_set);
+            self.ns.poll();
             Ok(())
         })
         .unwrap_or(Ok(()))?;
@@ -291,8 +337,10 @@ impl TcpSocket {
             Some(h) => h,
             None => return,
         };
-        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| socket.close());
-        SOCKET_SET.poll_interfaces();
+        self.ns
+            .socket_set
+            .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| socket.close());
+        self.ns.poll();
     }
 
     /// Receives data from the socket, stores it in the given buffer.
@@ -306,25 +354,27 @@ impl TcpSocket {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
         self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() {
-                    // not open
-                    ax_err!(ConnectionRefused, "socket recv() failed")
-                } else if !socket.may_recv() {
-                    // connection closed
-                    Ok(0)
-                } else if socket.recv_queue() > 0 {
-                    // data available
-                    // TODO: use socket.recv(|buf| {...})
-                    let len = socket
-                        .recv_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-                    Ok(len)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
-                }
-            })
+            self.ns
+                .socket_set
+                .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    if !socket.is_active() {
+                        // not open
+                        ax_err!(ConnectionRefused, "socket recv() failed")
+                    } else if !socket.may_recv() {
+                        // connection closed
+                        Ok(0)
+                    } else if socket.recv_queue() > 0 {
+                        // data available
+                        // TODO: use socket.recv(|buf| {...})
+                        let len = socket
+                            .recv_slice(buf)
+                            .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
+                        Ok(len)
+                    } else {
+                        // no more data
+                        Err(AxError::WouldBlock)
+                    }
+                })
         })
     }
     /// Receives data from the socket, stores it in the given buffer.
@@ -342,29 +392,31 @@ impl TcpSocket {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
         self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() {
-                    // not open
-                    ax_err!(ConnectionRefused, "socket recv() failed")
-                } else if !socket.may_recv() {
-                    // connection closed
-                    Ok(0)
-                } else if socket.recv_queue() > 0 {
-                    // data available
-                    // TODO: use socket.recv(|buf| {...})
-                    let len = socket
-                        .recv_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
-                    Ok(len)
-                } else {
-                    // no more data
-                    if current_ticks() > expire_at {
-                        Err(AxError::Timeout)
+            self.ns
+                .socket_set
+                .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    if !socket.is_active() {
+                        // not open
+                        ax_err!(ConnectionRefused, "socket recv() failed")
+                    } else if !socket.may_recv() {
+                        // connection closed
+                        Ok(0)
+                    } else if socket.recv_queue() > 0 {
+                        // data available
+                        // TODO: use socket.recv(|buf| {...})
+                        let len = socket
+                            .recv_slice(buf)
+                            .map_err(|_| ax_err_type!(BadState, "socket recv() failed"))?;
+                        Ok(len)
                     } else {
-                        Err(AxError::WouldBlock)
+                        // no more data
+                        if current_ticks() > expire_at {
+                            Err(AxError::Timeout)
+                        } else {
+                            Err(AxError::WouldBlock)
+                        }
                     }
-                }
-            })
+                })
         })
     }
 
@@ -379,22 +431,24 @@ impl TcpSocket {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
         self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-                if !socket.is_active() || !socket.may_send() {
-                    // closed by remote
-                    ax_err!(ConnectionReset, "socket send() failed")
-                } else if socket.can_send() {
-                    // connected, and the tx buffer is not full
-                    // TODO: use socket.send(|buf| {...})
-                    let len = socket
-                        .send_slice(buf)
-                        .map_err(|_| ax_err_type!(BadState, "socket send() failed"))?;
-                    Ok(len)
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
-            })
+            self.ns
+                .socket_set
+                .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                    if !socket.is_active() || !socket.may_send() {
+                        // closed by remote
+                        ax_err!(ConnectionReset, "socket send() failed")
+                    } else if socket.can_send() {
+                        // connected, and the tx buffer is not full
+                        // TODO: use socket.send(|buf| {...})
+                        let len = socket
+                            .send_slice(buf)
+                            .map_err(|_| ax_err_type!(BadState, "socket send() failed"))?;
+                        Ok(len)
+                    } else {
+                        // tx buffer is full
+                        Err(AxError::WouldBlock)
+                    }
+                })
         })
     }
 
@@ -419,9 +473,11 @@ impl TcpSocket {
             return Err(AxError::NotConnected);
         };
 
-        SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
-            socket.set_nagle_enabled(enabled)
-        });
+        self.ns
+            .socket_set
+            .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| {
+                socket.set_nagle_enabled(enabled)
+            });
 
         Ok(())
     }
@@ -431,9 +487,10 @@ impl TcpSocket {
         let handle = unsafe { self.handle.get().read() };
 
         match handle {
-            Some(handle) => {
-                SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| socket.nagle_enabled())
-            }
+            Some(handle) => self
+                .ns
+                .socket_set
+                .with_socket::<tcp::Socket, _, _>(handle, |socket| socket.nagle_enabled()),
             // Nagle algorithm will be enabled by default once the socket is created
             None => true,
         }
@@ -448,9 +505,10 @@ impl TcpSocket {
         let handle = unsafe { self.handle.get().read() };
 
         match handle {
-            Some(handle) => {
-                SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| f(Some(socket)))
-            }
+            Some(handle) => self
+                .ns
+                .socket_set
+                .with_socket::<tcp::Socket, _, _>(handle, |socket| f(Some(socket))),
             None => f(None),
         }
     }
@@ -464,9 +522,10 @@ impl TcpSocket {
         let handle = unsafe { self.handle.get().read() };
 
         match handle {
-            Some(handle) => {
-                SOCKET_SET.with_socket_mut::<tcp::Socket, _, _>(handle, |socket| f(Some(socket)))
-            }
+            Some(handle) => self
+                .ns
+                .socket_set
+                .with_socket_mut::<tcp::Socket, _, _>(handle, |socket| f(Some(socket))),
             None => f(None),
         }
     }
@@ -535,7 +594,7 @@ impl TcpSocket {
         let port = if local_addr.port != 0 {
             local_addr.port
         } else {
-            get_ephemeral_port()?
+            get_ephemeral_port(&self.ns.listen_table)?
         };
         assert_ne!(port, 0);
         let addr = if !is_unspecified(local_addr.addr) {
@@ -549,8 +608,10 @@ impl TcpSocket {
     fn poll_connect(&self) -> AxResult<PollState> {
         // SAFETY: `self.handle` should be initialized above.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        let writable =
-            SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| match socket.state() {
+        let writable = self
+            .ns
+            .socket_set
+            .with_socket::<tcp::Socket, _, _>(handle, |socket| match socket.state() {
                 State::SynSent => false, // wait for connection
                 State::Established => {
                     self.set_state(STATE_CONNECTED); // connected
@@ -579,19 +640,24 @@ impl TcpSocket {
     fn poll_stream(&self) -> AxResult<PollState> {
         // SAFETY: `self.handle` should be initialized in a connected socket.
         let handle = unsafe { self.handle.get().read().unwrap() };
-        SOCKET_SET.with_socket::<tcp::Socket, _, _>(handle, |socket| {
-            Ok(PollState {
-                readable: !socket.may_recv() || socket.can_recv(),
-                writable: !socket.may_send() || socket.can_send(),
+        self.ns
+            .socket_set
+            .with_socket::<tcp::Socket, _, _>(handle, |socket| {
+                Ok(PollState {
+                    readable: !socket.may_recv() || socket.can_recv(),
+                    writable: !socket.may_send() || socket.can_send(),
+                })
             })
-        })
     }
 
     fn poll_listener(&self) -> AxResult<PollState> {
         // SAFETY: `self.local_addr` should be initialized in a listening socket.
         let local_addr = unsafe { self.local_addr.get().read() };
         Ok(PollState {
-            readable: LISTEN_TABLE.can_accept(local_addr.port)?,
+            readable: self
+                .ns
+                .listen_table
+                .can_accept(local_addr.port, &self.ns.socket_set)?,
             writable: false,
         })
     }
@@ -609,7 +675,7 @@ impl TcpSocket {
             f()
         } else {
             loop {
-                SOCKET_SET.poll_interfaces();
+                self.ns.poll();
                 match f() {
                     Ok(t) => return Ok(t),
                     Err(AxError::WouldBlock) => axtask::yield_now(),
@@ -641,12 +707,12 @@ impl Drop for TcpSocket {
         self.shutdown().ok();
         // Safe because we have mut reference to `self`.
         if let Some(handle) = unsafe { self.handle.get().read() } {
-            SOCKET_SET.remove(handle);
+            self.ns.socket_set.remove(handle);
         }
     }
 }
 
-fn get_ephemeral_port() -> AxResult<u16> {
+fn get_ephemeral_port(listen_table: &ListenTable) -> AxResult<u16> {
     const PORT_START: u16 = 0xc000;
     const PORT_END: u16 = 0xffff;
     static CURR: Mutex<u16> = Mutex::new(PORT_START);
@@ -661,7 +727,7 @@ fn get_ephemeral_port() -> AxResult<u16> {
         } else {
             *curr += 1;
         }
-        if LISTEN_TABLE.can_listen(port) {
+        if listen_table.can_listen(port) {
             return Ok(port);
         }
         tries += 1;
@@ -1,5 +1,5 @@
+use alloc::sync::Arc;
 use core::net::SocketAddr;
-use core::ops::DerefMut;
 use core::sync::atomic::{AtomicBool, Ordering};
 
 use axerrno::{ax_err, ax_err_type, AxError, AxResult};
@@ -13,11 +13,8 @@ use smoltcp::socket::udp::{self, BindError, SendError};
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
 use super::addr::{from_core_sockaddr, into_core_sockaddr, is_unspecified, UNSPECIFIED_ENDPOINT};
-use super::{SocketSetWrapper, SOCKET_SET};
+use super::{NetNamespace, SocketSetWrapper, SocketWaker};
 
-use crate::net_impl::{LOOPBACK, LOOPBACK_DEV};
-use smoltcp::time::Instant;
-use crate::net_impl::{current_time_nanos, NANOS_PER_MICROS};
 use smoltcp::wire::IpAddress as IpAddr;
 
 /// A UDP socket that provides POSIX-like APIs.
@@ -27,23 +24,35 @@ pub struct UdpSocket {
     peer_addr: RwLock<Option<IpEndpoint>>,
     nonblock: AtomicBool,
     reuse_addr: AtomicBool,
+    ns: Arc<NetNamespace>,
 }
 
 impl UdpSocket {
-    /// Creates a new UDP socket.
+    /// Creates a new UDP socket in the initial network namespace.
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        Self::new_in(NetNamespace::init_ns())
+    }
+
+    /// Creates a new UDP socket in the given network namespace.
+    pub fn new_in(ns: Arc<NetNamespace>) -> Self {
         let socket = SocketSetWrapper::new_udp_socket();
-        let handle = SOCKET_SET.add(socket);
+        let handle = ns.socket_set.add(socket);
         Self {
             handle,
             local_addr: RwLock::new(None),
             peer_addr: RwLock::new(None),
             nonblock: AtomicBool::new(false),
             reuse_addr: AtomicBool::new(false),
+            ns,
         }
     }
 
+    /// Calls `waker` whenever a poll changes the readiness of the socket.
+    pub fn set_waker(&self, waker: SocketWaker) {
+        self.ns.socket_set.set_waker(self.handle, waker);
+    }
+
     /// Returns the local address and port, or
     /// [`Err(NotConnected)`](AxError::NotConnected) if not connected.
     pub fn local_addr(&self) -> AxResult<SocketAddr> {
@@ -116,15 +125,19 @@ impl UdpSocket {
 
         if !self.is_reuse_addr() {
             // Check if the address is already in use
-            SOCKET_SET.bind_check(local_endpoint.addr, local_endpoint.port)?;
+            self.ns
+                .socket_set
+                .bind_check(local_endpoint.addr, local_endpoint.port)?;
         }
 
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            socket.bind(endpoint).or_else(|e| match e {
-                BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
-                BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
-            })
-        })?;
+        self.ns
+            .socket_set
+            .with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+                socket.bind(endpoint).or_else(|e| match e {
+                    BindError::InvalidState => ax_err!(AlreadyExists, "socket bind() failed"),
+                    BindError::Unaddressable => ax_err!(InvalidInput, "socket bind() failed"),
+                })
+            })?;
 
         *self_local_addr = Some(local_endpoint);
         debug!("UDP socket {}: bound on {}", self.handle, endpoint);
@@ -220,11 +233,13 @@ impl UdpSocket {
 
     /// Close the socket.
     pub fn shutdown(&self) -> AxResult {
-        SOCKET_SET.poll_interfaces();
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            debug!("UDP socket {}: shutting down", self.handle);
-            socket.close();
-        });
+        self.ns.poll();
+        self.ns
+            .socket_set
+            .with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+                debug!("UDP socket {}: shutting down", self.handle);
+                socket.close();
+            });
         Ok(())
     }
 
@@ -236,27 +251,30 @@ impl UdpSocket {
                 writable: false,
             });
         }
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            Ok(PollState {
-                readable: socket.can_recv(),
-                writable: socket.can_send(),
+        self.ns
+            .socket_set
+            .with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+                Ok(PollState {
+                    readable: socket.can_recv(),
+                    writable: socket.can_send(),
+                })
             })
-        })
     }
 
     pub fn set_socket_ttl(&self, ttl: u8) {
-        SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-            socket.set_hop_limit(Some(ttl))
-        });
+        self.ns
+            .socket_set
+            .with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+                socket.set_hop_limit(Some(ttl))
+            });
     }
 
     pub fn add_membership(&self, multicast_addr: IpAddr, interface_addr: IpAddr) {
-        let timestamp = Instant::from_micros_const((current_time_nanos() / NANOS_PER_MICROS) as i64);
         debug!(
             "setsockopt IP_ADD_MEMBERSHIP: multiaddr: {}, interfaceaddr: {}",
             multicast_addr, interface_addr
         );
-        let _ = LOOPBACK.lock().join_multicast_group(LOOPBACK_DEV.lock().deref_mut(), multicast_addr, timestamp);
+        self.ns.join_multicast_group(multicast_addr);
     }
 }
 
@@ -275,25 +293,27 @@ impl UdpSocket {
         }
         // info!("send to addr: {:?}", remote_endpoint);
         self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if !socket.is_open() {
-                    // not connected
-                    ax_err!(NotConnected, "socket send() failed")
-                } else if socket.can_send() {
-                    socket
-                        .send_slice(buf, remote_endpoint)
-                        .map_err(|e| match e {
-                            SendError::BufferFull => AxError::WouldBlock,
-                            SendError::Unaddressable => {
-                                ax_err_type!(ConnectionRefused, "socket send() failed")
-                            }
-                        })?;
-                    Ok(buf.len())
-                } else {
-                    // tx buffer is full
-                    Err(AxError::WouldBlock)
-                }
-            })
+            self.ns
+                .socket_set
+                .with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+                    if !socket.is_open() {
+                        // not connected
+                        ax_err!(NotConnected, "socket send() failed")
+                    } else if socket.can_send() {
+                        socket
+                            .send_slice(buf, remote_endpoint)
+                            .map_err(|e| match e {
+                                SendError::BufferFull => AxError::WouldBlock,
+                                SendError::Unaddressable => {
+                                    ax_err_type!(ConnectionRefused, "socket send() failed")
+                                }
+                            })?;
+                        Ok(buf.len())
+                    } else {
+                        // tx buffer is full
+                        Err(AxError::WouldBlock)
+                    }
+                })
         })
     }
 
@@ -305,18 +325,20 @@ impl UdpSocket {
             return ax_err!(NotConnected, "socket send() failed");
         }
         self.block_on(|| {
-            SOCKET_SET.with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
-                if !socket.is_open() {
-                    // not bound
-                    ax_err!(NotConnected, "socket recv() failed")
-                } else if socket.can_recv() {
-                    // data available
-                    op(socket)
-                } else {
-                    // no more data
-                    Err(AxError::WouldBlock)
-                }
-            })
+            self.ns
+                .socket_set
+                .with_socket_mut::<udp::Socket, _, _>(self.handle, |socket| {
+                    if !socket.is_open() {
+                        // not bound
+                        ax_err!(NotConnected, "socket recv() failed")
+                    } else if socket.can_recv() {
+                        // data available
+                        op(socket)
+                    } else {
+                        // no more data
+                        Err(AxError::WouldBlock)
+                    }
+                })
         })
     }
 
@@ -328,7 +350,7 @@ impl UdpSocket {
             f()
         } else {
             loop {
-                SOCKET_SET.poll_interfaces();
+                self.ns.poll();
                 match f() {
                     Ok(t) => return Ok(t),
                     Err(AxError::WouldBlock) => axtask::yield_now(),
@@ -344,7 +366,7 @@ impl UdpSocket {
     ///
     /// Or it will return the result of the given function.
     pub fn with_socket<R>(&self, f: impl FnOnce(&udp::Socket) -> R) -> R {
-        SOCKET_SET.with_socket(self.handle, |s| f(s))
+        self.ns.socket_set.with_socket(self.handle, |s| f(s))
     }
 }
 
@@ -367,7 +389,7 @@ impl Write for UdpSocket {
 impl Drop for UdpSocket {
     fn drop(&mut self) {
         self.shutdown().ok();
-        SOCKET_SET.remove(self.handle);
+        self.ns.socket_set.remove(self.handle);
     }
 }
 
@@ -0,0 +1,119 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn main() {
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    if !matches!(arch.as_str(), "riscv64" | "aarch64" | "x86_64") {
+        return;
+    }
+    println!("cargo:rerun-if-changed=vdso/vdso.lds");
+    println!("cargo:rerun-if-changed=vdso/{}", arch);
+    println!("cargo:rerun-if-env-changed=LD_LLD");
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    build_vdso(&arch, &out_dir);
+}
+
+/// 汇编 `vdso/<arch>/vdso.S`，再用 lld 链接成 `$OUT_DIR/vdso.so`
+///
+/// 汇编使用 rustc 内置的汇编器，链接使用工具链自带的 rust-lld，不依赖工具链之外的程序。
+/// 也可以通过环境变量 `LD_LLD` 指定其他的 lld
+fn build_vdso(arch: &str, out_dir: &Path) {
+    let obj = assemble(arch, out_dir);
+    let so = out_dir.join("vdso.so");
+
+    let mut ld = match env::var("LD_LLD") {
+        Ok(ld_lld) => Command::new(ld_lld),
+        Err(_) => {
+            let mut ld = Command::new(rust_lld());
+            ld.args(["-flavor", "gnu"]);
+            ld
+        }
+    };
+    ld.args([
+        "-shared",
+        "-soname=linux-vdso.so.1",
+        "--hash-style=both",
+        "--build-id=none",
+        "-z",
+        "max-page-size=4096",
+        "-T",
+        "vdso/vdso.lds",
+    ])
+    .arg(format!("--version-script=vdso/{}/vdso.map", arch))
+    .arg("-o")
+    .arg(&so)
+    .arg(&obj);
+    run(ld);
+}
+
+/// 将汇编源文件放进 `global_asm!`，由 rustc 编译成目标文件 `$OUT_DIR/vdso.o`
+fn assemble(arch: &str, out_dir: &Path) -> PathBuf {
+    let src = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap())
+        .join("vdso")
+        .join(arch)
+        .join("vdso.S");
+    // 源文件使用 GNU 汇编语法
+    let options = if arch == "x86_64" {
+        ", options(att_syntax)"
+    } else {
+        ""
+    };
+    let wrapper = out_dir.join("vdso_asm.rs");
+    fs::write(
+        &wrapper,
+        format!(
+            "#![no_std]\ncore::arch::global_asm!(include_str!({:?}){});\n",
+            src, options
+        ),
+    )
+    .unwrap();
+
+    let obj = out_dir.join("vdso.o");
+    let mut rustc = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".into()));
+    rustc
+        .args([
+            "--edition",
+            "2021",
+            "--crate-type",
+            "lib",
+            "--crate-name",
+            "vdso",
+        ])
+        .args(["--emit", "obj", "-C", "relocation-model=pic"])
+        .arg("--target")
+        .arg(env::var("TARGET").unwrap())
+        .arg("-o")
+        .arg(&obj)
+        .arg(&wrapper);
+    run(rustc);
+    obj
+}
+
+/// 工具链自带的 rust-lld 的路径
+fn rust_lld() -> PathBuf {
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    let output = Command::new(rustc)
+        .args(["--print", "sysroot"])
+        .output()
+        .expect("failed to run rustc");
+    let sysroot = String::from_utf8(output.stdout).unwrap();
+    let lld = Path::new(sysroot.trim())
+        .join("lib/rustlib")
+        .join(env::var("HOST").unwrap())
+        .join("bin/rust-lld");
+    assert!(
+        lld.exists(),
+        "rust-lld is not found at {}, set LD_LLD to an lld to link the vDSO",
+        lld.display()
+    );
+    lld
+}
+
+fn run(mut cmd: Command) {
+    let status = cmd
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run {:?}: {}", cmd, err));
+    assert!(status.success(), "{:?} failed with {}", cmd, status);
+}
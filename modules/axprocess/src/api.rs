@@ -4,12 +4,13 @@ use core::str::from_utf8;
 extern crate alloc;
 use alloc::sync::Arc;
 use alloc::{
+    collections::BTreeMap,
     string::{String, ToString},
     vec,
     vec::Vec,
 };
-use axconfig::{MAX_USER_HEAP_SIZE, MAX_USER_STACK_SIZE, USER_HEAP_BASE, USER_STACK_TOP};
-use axerrno::{AxError, AxResult};
+use axconfig::{MAX_USER_HEAP_SIZE, USER_HEAP_BASE, USER_STACK_TOP};
+use axerrno::{AxError, AxResult, LinuxError};
 use axhal::mem::VirtAddr;
 use axhal::paging::MappingFlags;
 use axhal::KERNEL_PROCESS_ID;
@@ -18,18 +19,23 @@ use axmem::MemorySet;
 #[cfg(feature = "signal")]
 use axsignal::signal_no::SignalNo;
 use axsync::Mutex;
-use axtask::{current, yield_now, CurrentTask, TaskId, TaskState, IDLE_TASK, RUN_QUEUE};
+use axtask::{current, yield_now, CurrentTask, TaskId, TaskState, IDLE_TASK};
 use elf_parser::{
     get_app_stack_region, get_auxv_vector, get_elf_entry, get_elf_segments, get_relocate_pairs,
 };
 use xmas_elf::program::SegmentData;
 
-use crate::flags::WaitStatus;
+use crate::binfmt;
+use crate::flags::{ChildState, WaitFlags, WaitResult, WaitStatus, WaitTarget};
 use crate::futex::clear_wait;
+use crate::job::JobEvent;
 use crate::link::real_path;
 use crate::process::{Process, PID2PC, TID2TASK};
 #[cfg(feature = "signal")]
-use crate::signal::{send_signal_to_process, send_signal_to_thread};
+use crate::signal::{child_info, send_signal_info_to_process, send_signal_info_to_thread};
+use crate::vdso;
+#[cfg(feature = "signal")]
+use axsignal::info::{SigInfo, SEGV_MAPERR};
 
 /// 初始化内核调度进程
 pub fn init_kernel_process() {
@@ -42,6 +48,7 @@ pub fn init_kernel_process() {
     ));
 
     axtask::init_scheduler();
+    vdso::update_clock_source();
     kernel_process.tasks.lock().push(Arc::clone(unsafe {
         IDLE_TASK.current_ref_raw().get_unchecked()
     }));
@@ -57,6 +64,13 @@ pub fn current_process() -> Arc<Process> {
     current_process
 }
 
+/// 进程退出时由上层模块清理以进程号为索引的状态，避免被复用该进程号的新进程继承
+#[crate_interface::def_interface]
+pub trait ProcessExitIf {
+    /// 进程 `pid` 的主线程退出、进程成为僵尸进程时调用
+    fn process_exit(pid: u64);
+}
+
 /// 退出当前任务
 pub fn exit_current_task(exit_code: i32) -> ! {
     let process = current_process();
@@ -78,8 +92,18 @@ pub fn exit_current_task(exit_code: i32) -> ! {
     if current_task.get_sig_child() || current_task.is_leader() {
         let parent = process.get_parent();
         if parent != KERNEL_PROCESS_ID {
-            // 发送sigchild
-            send_signal_to_process(parent as isize, 17).unwrap();
+            // 发送sigchild，被信号杀死的进程报告 CLD_KILLED
+            let state = match process.get_term_signal() {
+                0 => ChildState::Exited(exit_code),
+                signal => process.killed_state(signal),
+            };
+            // 报告给父进程的是其所见的进程号
+            let pid = PID2PC
+                .lock()
+                .get(&parent)
+                .map_or(process.pid(), |parent| parent.pid_to_user(process.pid()));
+            let info = child_info(pid, process.cred.lock().uid, state);
+            send_signal_info_to_process(parent as isize, info).unwrap();
         }
     }
     // clear_child_tid 的值不为 0，则将这个用户地址处的值写为0
@@ -112,22 +136,55 @@ pub fn exit_current_task(exit_code: i32) -> ! {
             }
         }
         TID2TASK.lock().remove(&curr_id);
+        process.pid_ns().detach(curr_id);
         process.set_exit_code(exit_code);
 
         process.set_zombie(true);
 
         process.tasks.lock().clear();
         process.fd_manager.fd_table.lock().clear();
+        crate_interface::call_interface!(ProcessExitIf::process_exit(process.pid()));
+        // 进程持有的记录锁随进程退出而释放
+        axfs::api::release_owner_locks(axfs::api::LockOwner::Posix(process.pid()));
+        // 会话首进程退出时挂断控制终端
+        crate::tty::session_leader_exit(&process);
         #[cfg(feature = "signal")]
         process.signal_modules.lock().clear();
 
+        // 解除当前进程作为跟踪者的跟踪关系
+        #[cfg(feature = "signal")]
+        crate::ptrace::release_tracees(process.pid());
+
+        // 退出可能使同一会话中的进程组成为孤儿进程组
+        #[cfg(feature = "signal")]
+        crate::signal::kill_orphaned_pgrps(&process);
+
+        // PID 命名空间的 1 号进程退出时，命名空间中的其余进程都被杀死
+        if process.is_ns_init() {
+            let pid_ns = process.pid_ns();
+            pid_ns.set_dead();
+            #[cfg(feature = "signal")]
+            for pid in pid_ns.members() {
+                if pid != process.pid() {
+                    let _ = crate::signal::send_signal_to_process(
+                        pid as isize,
+                        SignalNo::SIGKILL as isize,
+                    );
+                }
+            }
+        }
+
         let mut pid2pc = PID2PC.lock();
-        let kernel_process = pid2pc.get(&KERNEL_PROCESS_ID).unwrap();
-        // 将子进程交给idle进程
+        // 将子进程交给所在 PID 命名空间的 1 号进程，没有时交给idle进程
         // process.memory_set = Arc::clone(&kernel_process.memory_set);
+        let reaper = process
+            .child_reaper()
+            .and_then(|pid| pid2pc.get(&pid))
+            .filter(|reaper| !reaper.get_zombie())
+            .unwrap_or_else(|| pid2pc.get(&KERNEL_PROCESS_ID).unwrap());
         for child in process.children.lock().deref() {
-            child.set_parent(KERNEL_PROCESS_ID);
-            kernel_process.children.lock().push(Arc::clone(child));
+            child.set_parent(reaper.pid());
+            reaper.children.lock().push(Arc::clone(child));
         }
         if let Some(parent_process) = pid2pc.get(&process.get_parent()) {
             parent_process.set_vfork_block(false);
@@ -137,6 +194,7 @@ pub fn exit_current_task(exit_code: i32) -> ! {
         drop(process);
     } else {
         TID2TASK.lock().remove(&curr_id);
+        process.pid_ns().detach(curr_id);
         // 从进程中删除当前线程
         let mut tasks = process.tasks.lock();
         let len = tasks.len();
@@ -149,22 +207,31 @@ pub fn exit_current_task(exit_code: i32) -> ! {
         drop(tasks);
         #[cfg(feature = "signal")]
         process.signal_modules.lock().remove(&curr_id);
+        #[cfg(feature = "signal")]
+        process.ptrace.remove_thread(curr_id);
         drop(process);
     }
-    RUN_QUEUE.lock().exit_current(exit_code);
+    axtask::exit(exit_code);
 }
 
-/// 返回应用程序入口，用户栈底，用户堆底
+/// 返回应用程序入口，用户栈底，用户堆底以及传给程序的辅助向量
+///
+/// `stack_size` 为用户栈的大小，由 RLIMIT_STACK 决定
 pub fn load_app(
     name: String,
-    mut args: Vec<String>,
+    args: Vec<String>,
     envs: &Vec<String>,
     memory_set: &mut MemorySet,
-) -> AxResult<(VirtAddr, VirtAddr, VirtAddr)> {
-    if name.ends_with(".sh") {
-        args = [vec![String::from("busybox"), String::from("sh")], args].concat();
-        return load_app("busybox".to_string(), args, envs, memory_set);
-    }
+    stack_size: usize,
+) -> AxResult<(VirtAddr, VirtAddr, VirtAddr, BTreeMap<u8, usize>)> {
+    // 脚本等需要解释器的文件先找到最终要加载的 ELF 文件
+    let prm = binfmt::resolve(&name, args).map_err(|err| match err {
+        LinuxError::ENOENT => AxError::NotFound,
+        LinuxError::EACCES => AxError::PermissionDenied,
+        _ => AxError::InvalidData,
+    })?;
+    let name = prm.path;
+    let mut args = prm.args;
     let elf_data = if let Ok(ans) = axfs::api::read(name.as_str()) {
         ans
     } else {
@@ -187,7 +254,7 @@ pub fn load_app(
         let interp_path = interp_path.trim_matches(char::from(0)).to_string();
         let real_interp_path = real_path(&interp_path);
         args = [vec![real_interp_path.clone()], args].concat();
-        return load_app(real_interp_path, args, envs, memory_set);
+        return load_app(real_interp_path, args, envs, memory_set, stack_size);
     }
     info!("args: {:?}", args);
     let elf_base_addr = Some(0x400_0000);
@@ -229,12 +296,13 @@ pub fn load_app(
         heap_start + MAX_USER_HEAP_SIZE
     );
 
-    let auxv = get_auxv_vector(&elf, elf_base_addr);
+    let vdso_base = vdso::map_vdso(memory_set)?;
+    let auxv = get_auxv_vector(&elf, elf_base_addr, vdso_base.map(usize::from));
 
     let stack_top = VirtAddr::from(USER_STACK_TOP);
-    let stack_size = MAX_USER_STACK_SIZE;
 
-    let (stack_data, stack_bottom) = get_app_stack_region(args, envs, auxv, stack_top, stack_size);
+    let (stack_data, stack_bottom) =
+        get_app_stack_region(args, envs, auxv.clone(), stack_top, stack_size);
     memory_set.new_region(
         stack_top,
         stack_size,
@@ -247,7 +315,7 @@ pub fn load_app(
         stack_top,
         stack_top + stack_size
     );
-    Ok((entry, stack_bottom.into(), heap_start))
+    Ok((entry, stack_bottom.into(), heap_start, auxv))
 }
 
 /// 当从内核态到用户态时，统计对应进程的时间信息
@@ -288,7 +356,10 @@ pub fn handle_page_fault(addr: VirtAddr, flags: MappingFlags) {
         axhal::arch::flush_tlb(None);
     } else {
         #[cfg(feature = "signal")]
-        let _ = send_signal_to_thread(current().id().as_u64() as isize, SignalNo::SIGSEGV as isize);
+        let _ = send_signal_info_to_thread(
+            current().id().as_u64() as isize,
+            SigInfo::from_fault(SignalNo::SIGSEGV as i32, SEGV_MAPERR, addr.as_usize()),
+        );
     }
 }
 
@@ -300,51 +371,106 @@ pub fn handle_page_fault(addr: VirtAddr, flags: MappingFlags) {
 ///
 /// 保证传入的 ptr 是有效的
 pub unsafe fn wait_pid(pid: isize, exit_code_ptr: *mut i32) -> Result<u64, WaitStatus> {
+    let result = wait_child(WaitTarget::from(pid), WaitFlags::WEXITED)?;
+    if !exit_code_ptr.is_null() {
+        unsafe {
+            // 因为没有切换页表，所以可以直接填写
+            *exit_code_ptr = result.state.wait_status();
+        }
+    }
+    Ok(result.pid)
+}
+
+/// 在当前进程的子进程与被跟踪进程中，查找一次满足 `options` 的状态变化
+///
+/// 被跟踪进程的跟踪停止总会被报告；子进程的退出、停止与继续分别需要
+/// `WEXITED`、`WUNTRACED` 与 `WCONTINUED`。除非指定了 `WNOWAIT`，
+/// 报告过的状态变化会被取走，已退出的子进程也会从父进程中删除。
+///
+/// 没有可报告的状态变化时返回对应子进程的状态
+pub fn wait_child(target: WaitTarget, options: WaitFlags) -> Result<WaitResult, WaitStatus> {
     // 获取当前进程
     let curr_process = current_process();
-    let mut exit_task_id: usize = 0;
-    let mut answer_id: u64 = 0;
+    let consume = !options.contains(WaitFlags::WNOWAIT);
     let mut answer_status = WaitStatus::NotExist;
-    for (index, child) in curr_process.children.lock().iter().enumerate() {
-        if pid == -1 {
-            // 任意一个进程结束都可以的
+    let wanted = |process: &Process| match target {
+        WaitTarget::Any => true,
+        WaitTarget::Pid(pid) => process.pid() == pid,
+        WaitTarget::Pgid(0) => process.get_pgid() == curr_process.get_pgid(),
+        WaitTarget::Pgid(pgid) => process.get_pgid() == pgid,
+    };
+    // 先检查被当前进程跟踪的进程是否进入了跟踪停止
+    #[cfg(feature = "signal")]
+    {
+        let tracees: Vec<Arc<Process>> = PID2PC
+            .lock()
+            .values()
+            .filter(|process| {
+                process.ptrace.tracer() == Some(curr_process.pid()) && wanted(process.as_ref())
+            })
+            .cloned()
+            .collect();
+        for tracee in tracees {
             answer_status = WaitStatus::Running;
-            if let Some(exit_code) = child.get_code_if_exit() {
-                answer_status = WaitStatus::Exited;
-                info!("wait pid _{}_ with code _{}_", child.pid(), exit_code);
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        // 因为没有切换页表，所以可以直接填写
-                        *exit_code_ptr = exit_code << 8;
-                    }
-                }
-                answer_id = child.pid();
-                break;
+            if let Some((tid, status)) = tracee.ptrace.take_unreported_stop(consume) {
+                info!("wait pid _{}_ with ptrace stop _{:#x}_", tid, status);
+                return Ok(WaitResult {
+                    pid: tid,
+                    uid: tracee.cred.lock().uid,
+                    state: ChildState::Trapped(status),
+                });
             }
-        } else if child.pid() == pid as u64 {
-            // 找到了对应的进程
-            if let Some(exit_code) = child.get_code_if_exit() {
-                answer_status = WaitStatus::Exited;
-                info!("wait pid _{}_ with code _{:?}_", child.pid(), exit_code);
-                exit_task_id = index;
-                if !exit_code_ptr.is_null() {
-                    unsafe {
-                        *exit_code_ptr = exit_code << 8;
-                        // 用于WEXITSTATUS设置编码
-                    }
-                }
-                answer_id = child.pid();
-            } else {
-                answer_status = WaitStatus::Running;
+        }
+    }
+    let mut children = curr_process.children.lock();
+    let mut exited = None;
+    for (index, child) in children.iter().enumerate() {
+        if !wanted(child.as_ref()) {
+            continue;
+        }
+        answer_status = WaitStatus::Running;
+        let uid = child.cred.lock().uid;
+        if let Some(state) = child.get_exit_state() {
+            if !options.contains(WaitFlags::WEXITED) {
+                continue;
             }
+            info!("wait pid _{}_ with state _{:?}_", child.pid(), state);
+            exited = Some((
+                index,
+                WaitResult {
+                    pid: child.pid(),
+                    uid,
+                    state,
+                },
+            ));
             break;
         }
+        let event = child.job.take_event(
+            |event| match event {
+                JobEvent::Stopped(_) => options.contains(WaitFlags::WUNTRACED),
+                JobEvent::Continued => options.contains(WaitFlags::WCONTINUED),
+            },
+            consume,
+        );
+        if let Some(event) = event {
+            info!("wait pid _{}_ with job event _{:?}_", child.pid(), event);
+            let state = match event {
+                JobEvent::Stopped(signal) => ChildState::Stopped(signal),
+                JobEvent::Continued => ChildState::Continued,
+            };
+            return Ok(WaitResult {
+                pid: child.pid(),
+                uid,
+                state,
+            });
+        }
     }
     // 若进程成功结束，需要将其从父进程的children中删除
-    if answer_status == WaitStatus::Exited {
-        curr_process.children.lock().remove(exit_task_id);
-        return Ok(answer_id);
+    if let Some((index, result)) = exited {
+        if consume {
+            children.remove(index);
+        }
+        return Ok(result);
     }
     Err(answer_status)
 }
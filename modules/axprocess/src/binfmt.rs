@@ -0,0 +1,465 @@
+//! 可执行文件格式的识别与解释器分派
+//!
+//! execve 加载程序前先读取文件开头的 `BINPRM_BUF_SIZE` 字节，依次判断：
+//! 1. ELF 文件直接加载，其 `PT_INTERP` 由 `load_app` 处理
+//! 2. 以 `#!` 开头的脚本交给其中指定的解释器
+//! 3. 与注册项匹配的文件交给注册项指定的解释器，注册串的格式与 Linux 的 binfmt_misc 相同
+//!
+//! 解释器本身也可以是脚本或需要解释器的文件，但嵌套不能超过 `BINPRM_MAX_RECURSION` 层。
+extern crate alloc;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult, LinuxError};
+use axfs::api::{File, Read};
+use axsync::Mutex;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::cred;
+use crate::link::real_path;
+
+/// 识别文件格式时读取的文件开头长度
+pub const BINPRM_BUF_SIZE: usize = 256;
+
+/// 解释器嵌套的最大层数
+pub const BINPRM_MAX_RECURSION: usize = 4;
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+bitflags::bitflags! {
+    /// 注册项的标志
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct BinfmtFlags: u32 {
+        /// `P`：保留原来的 argv[0]，解释器的参数为 `解释器 文件 argv[0] argv[1] ...`
+        const PRESERVE_ARGV0 = 1 << 0;
+        /// `O`：以文件描述符的形式将文件传给解释器，暂不支持
+        const OPEN_BINARY = 1 << 1;
+        /// `C`：按照原来的文件而非解释器计算新的身份
+        const CREDENTIALS = 1 << 2;
+        /// `F`：注册时就确定解释器
+        const FIX_BINARY = 1 << 3;
+    }
+}
+
+/// 注册项匹配文件的方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinfmtMatch {
+    /// 文件偏移 `offset` 处的内容与掩码按位与之后等于 `magic`
+    Magic {
+        /// 魔数在文件中的偏移
+        offset: usize,
+        /// 魔数
+        magic: Vec<u8>,
+        /// 掩码，与魔数等长
+        mask: Option<Vec<u8>>,
+    },
+    /// 文件名的扩展名，不含 `.`
+    Extension(String),
+}
+
+/// binfmt_misc 风格的注册项
+#[derive(Debug, Clone)]
+pub struct BinfmtEntry {
+    /// 注册项的名字
+    pub name: String,
+    /// 匹配文件的方式
+    pub matcher: BinfmtMatch,
+    /// 解释器的路径
+    pub interpreter: String,
+    /// 标志
+    pub flags: BinfmtFlags,
+    /// 是否启用
+    pub enabled: bool,
+}
+
+/// 解析注册串中的魔数或掩码，支持 `\xHH` 与 `\\` 转义
+fn unescape(field: &str) -> AxResult<Vec<u8>> {
+    let bytes = field.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'\\' {
+            result.push(bytes[index]);
+            index += 1;
+        } else if bytes.get(index + 1) == Some(&b'x') {
+            let hex = field
+                .get(index + 2..index + 4)
+                .ok_or(AxError::InvalidInput)?;
+            result.push(u8::from_str_radix(hex, 16).map_err(|_| AxError::InvalidInput)?);
+            index += 4;
+        } else if bytes.get(index + 1) == Some(&b'\\') {
+            result.push(b'\\');
+            index += 2;
+        } else {
+            return Err(AxError::InvalidInput);
+        }
+    }
+    Ok(result)
+}
+
+impl BinfmtEntry {
+    /// 解析注册串 `:name:type:offset:magic:mask:interpreter:flags`
+    ///
+    /// 第一个字符作为分隔符。`type` 为 `M` 时按魔数匹配，为 `E` 时按扩展名匹配，
+    /// 此时 `magic` 字段为扩展名，`offset` 与 `mask` 必须为空
+    pub fn parse(spec: &str) -> AxResult<Self> {
+        let spec = spec.trim_end_matches('\n');
+        let delimiter = spec.chars().next().ok_or(AxError::InvalidInput)?;
+        let fields: Vec<&str> = spec[delimiter.len_utf8()..].split(delimiter).collect();
+        if fields.len() < 6 || fields.len() > 7 {
+            return Err(AxError::InvalidInput);
+        }
+        let name = fields[0];
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(AxError::InvalidInput);
+        }
+        let (offset, magic, mask) = (fields[2], fields[3], fields[4]);
+        let matcher = match fields[1] {
+            "M" => {
+                let offset = if offset.is_empty() {
+                    0
+                } else {
+                    offset.parse().map_err(|_| AxError::InvalidInput)?
+                };
+                let magic = unescape(magic)?;
+                let mask = if mask.is_empty() {
+                    None
+                } else {
+                    Some(unescape(mask)?)
+                };
+                if magic.is_empty()
+                    || offset + magic.len() > BINPRM_BUF_SIZE
+                    || mask.as_ref().is_some_and(|mask| mask.len() != magic.len())
+                {
+                    return Err(AxError::InvalidInput);
+                }
+                BinfmtMatch::Magic {
+                    offset,
+                    magic,
+                    mask,
+                }
+            }
+            "E" => {
+                if !offset.is_empty() || !mask.is_empty() || magic.is_empty() || magic.contains('/')
+                {
+                    return Err(AxError::InvalidInput);
+                }
+                BinfmtMatch::Extension(magic.to_string())
+            }
+            _ => return Err(AxError::InvalidInput),
+        };
+        let interpreter = fields[5];
+        if interpreter.is_empty() {
+            return Err(AxError::InvalidInput);
+        }
+        let mut flags = BinfmtFlags::empty();
+        for flag in fields.get(6).copied().unwrap_or("").chars() {
+            flags |= match flag {
+                'P' => BinfmtFlags::PRESERVE_ARGV0,
+                'O' => BinfmtFlags::OPEN_BINARY,
+                'C' => BinfmtFlags::CREDENTIALS,
+                'F' => BinfmtFlags::FIX_BINARY,
+                _ => return Err(AxError::InvalidInput),
+            };
+        }
+        if flags.contains(BinfmtFlags::OPEN_BINARY) {
+            // 需要通过 AT_EXECFD 传递文件描述符
+            return Err(AxError::Unsupported);
+        }
+        Ok(Self {
+            name: name.to_string(),
+            matcher,
+            interpreter: interpreter.to_string(),
+            flags,
+            enabled: true,
+        })
+    }
+
+    /// 判断给定的文件是否由该注册项处理
+    pub fn matches(&self, path: &str, head: &[u8]) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        match &self.matcher {
+            BinfmtMatch::Magic {
+                offset,
+                magic,
+                mask,
+            } => {
+                let Some(data) = head.get(*offset..*offset + magic.len()) else {
+                    return false;
+                };
+                data.iter().enumerate().all(|(index, byte)| {
+                    let mask = mask.as_ref().map_or(0xff, |mask| mask[index]);
+                    byte & mask == magic[index]
+                })
+            }
+            BinfmtMatch::Extension(extension) => {
+                let file_name = path.rsplit('/').next().unwrap_or(path);
+                file_name
+                    .rsplit_once('.')
+                    .is_some_and(|(_, now)| now == extension)
+            }
+        }
+    }
+
+    /// 按照 Linux 中 `/proc/sys/fs/binfmt_misc/<name>` 的格式描述该注册项
+    pub fn describe(&self) -> String {
+        let mut text = String::from(if self.enabled {
+            "enabled\n"
+        } else {
+            "disabled\n"
+        });
+        text += &format!("interpreter {}\n", self.interpreter);
+        text += "flags: ";
+        for (flag, name) in [
+            (BinfmtFlags::PRESERVE_ARGV0, 'P'),
+            (BinfmtFlags::OPEN_BINARY, 'O'),
+            (BinfmtFlags::CREDENTIALS, 'C'),
+            (BinfmtFlags::FIX_BINARY, 'F'),
+        ] {
+            if self.flags.contains(flag) {
+                text.push(name);
+            }
+        }
+        text.push('\n');
+        let hex = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        };
+        match &self.matcher {
+            BinfmtMatch::Magic {
+                offset,
+                magic,
+                mask,
+            } => {
+                text += &format!("offset {}\nmagic {}\n", offset, hex(magic));
+                if let Some(mask) = mask {
+                    text += &format!("mask {}\n", hex(mask));
+                }
+            }
+            BinfmtMatch::Extension(extension) => {
+                text += &format!("extension .{}\n", extension);
+            }
+        }
+        text
+    }
+}
+
+static ENTRIES: Mutex<Vec<BinfmtEntry>> = Mutex::new(Vec::new());
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// 添加注册项，同名的注册项已经存在时返回 `AlreadyExists`
+pub fn register(entry: BinfmtEntry) -> AxResult<()> {
+    if entry.flags.contains(BinfmtFlags::FIX_BINARY) && !axfs::api::path_exists(&entry.interpreter)
+    {
+        return Err(AxError::NotFound);
+    }
+    let mut entries = ENTRIES.lock();
+    if entries.iter().any(|now| now.name == entry.name) {
+        return Err(AxError::AlreadyExists);
+    }
+    entries.push(entry);
+    Ok(())
+}
+
+/// 删除注册项
+pub fn unregister(name: &str) -> AxResult<()> {
+    let mut entries = ENTRIES.lock();
+    let index = entries
+        .iter()
+        .position(|entry| entry.name == name)
+        .ok_or(AxError::NotFound)?;
+    entries.remove(index);
+    Ok(())
+}
+
+/// 删除所有注册项
+pub fn clear() {
+    ENTRIES.lock().clear();
+}
+
+/// 启用或停用注册项
+pub fn set_entry_enabled(name: &str, enabled: bool) -> AxResult<()> {
+    let mut entries = ENTRIES.lock();
+    let entry = entries
+        .iter_mut()
+        .find(|entry| entry.name == name)
+        .ok_or(AxError::NotFound)?;
+    entry.enabled = enabled;
+    Ok(())
+}
+
+/// 获取注册项
+pub fn entry(name: &str) -> Option<BinfmtEntry> {
+    ENTRIES
+        .lock()
+        .iter()
+        .find(|entry| entry.name == name)
+        .cloned()
+}
+
+/// 所有注册项的名字
+pub fn entry_names() -> Vec<String> {
+    ENTRIES
+        .lock()
+        .iter()
+        .map(|entry| entry.name.clone())
+        .collect()
+}
+
+/// 注册项整体是否启用
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// 启用或停用所有注册项，不影响 `#!` 脚本
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Release)
+}
+
+/// 一次 execve 最终要加载的程序
+#[derive(Debug, Clone)]
+pub struct BinPrm {
+    /// 最终加载的 ELF 文件
+    pub path: String,
+    /// 改写后的参数
+    pub args: Vec<String>,
+    /// 计算新身份时依据的文件
+    ///
+    /// 通常为最终加载的文件，因此脚本的 S_ISUID 不会生效；注册项带有 `C` 标志时为被解释的文件
+    pub cred_path: String,
+}
+
+/// 读取文件开头用于识别格式的内容
+fn read_head(path: &str) -> Result<Vec<u8>, LinuxError> {
+    let mut file = File::open(path).map_err(|err| match err {
+        AxError::IsADirectory => LinuxError::EACCES,
+        err => err.into(),
+    })?;
+    let mut head = [0u8; BINPRM_BUF_SIZE];
+    let mut len = 0;
+    while len < head.len() {
+        match file.read(&mut head[len..])? {
+            0 => break,
+            now => len += now,
+        }
+    }
+    Ok(head[..len].to_vec())
+}
+
+/// 解析 `#!interpreter [arg]`，解释器之后的内容整体作为一个参数
+///
+/// 不是脚本时返回 `None`
+fn parse_shebang(head: &[u8]) -> Result<Option<(String, Option<String>)>, LinuxError> {
+    let Some(line) = head.strip_prefix(b"#!") else {
+        return Ok(None);
+    };
+    let (line, truncated) = match line.iter().position(|&byte| byte == b'\n') {
+        Some(end) => (&line[..end], false),
+        None => (line, true),
+    };
+    let line = core::str::from_utf8(line).map_err(|_| LinuxError::ENOEXEC)?;
+    let is_blank = |c: char| c == ' ' || c == '\t';
+    let line = line.trim_start_matches(is_blank);
+    let (interpreter, arg) = match line.find(is_blank) {
+        Some(end) => (&line[..end], line[end..].trim_matches(is_blank)),
+        // 解释器的路径被缓冲区截断
+        None if truncated => return Err(LinuxError::ENOEXEC),
+        None => (line, ""),
+    };
+    if interpreter.is_empty() {
+        return Err(LinuxError::ENOEXEC);
+    }
+    let arg = (!arg.is_empty()).then(|| arg.to_string());
+    Ok(Some((interpreter.to_string(), arg)))
+}
+
+/// 解释器需要具有执行权限
+fn check_interpreter(path: &str) -> Result<(), LinuxError> {
+    match cred::check_exec(path) {
+        Err(AxError::PermissionDenied) => Err(LinuxError::EACCES),
+        _ => Ok(()),
+    }
+}
+
+/// 确定 execve 最终要加载的 ELF 文件及其参数
+///
+/// 脚本的参数被改写为 `解释器 [参数] 脚本路径 argv[1] ...`。无法识别的文件返回 `ENOEXEC`，
+/// 解释器嵌套过深时返回 `ELOOP`
+pub fn resolve(path: &str, args: Vec<String>) -> Result<BinPrm, LinuxError> {
+    let mut prm = BinPrm {
+        path: path.to_string(),
+        args,
+        cred_path: path.to_string(),
+    };
+    for depth in 0..=BINPRM_MAX_RECURSION {
+        let head = read_head(&prm.path)?;
+        if head.starts_with(ELF_MAGIC) {
+            return Ok(prm);
+        }
+        if depth == BINPRM_MAX_RECURSION {
+            return Err(LinuxError::ELOOP);
+        }
+        let rest = prm.args.iter().skip(1).cloned();
+        if let Some((interpreter, arg)) = parse_shebang(&head)? {
+            let interpreter = real_path(&interpreter);
+            check_interpreter(&interpreter)?;
+            let mut args = Vec::from([interpreter.clone()]);
+            args.extend(arg);
+            args.push(prm.path.clone());
+            args.extend(rest);
+            prm = BinPrm {
+                path: interpreter.clone(),
+                args,
+                cred_path: interpreter,
+            };
+            continue;
+        }
+        let entry = if is_enabled() {
+            ENTRIES
+                .lock()
+                .iter()
+                .find(|entry| entry.matches(&prm.path, &head))
+                .cloned()
+        } else {
+            None
+        };
+        if let Some(entry) = entry {
+            let interpreter = real_path(&entry.interpreter);
+            check_interpreter(&interpreter)?;
+            let mut args = Vec::from([interpreter.clone(), prm.path.clone()]);
+            if entry.flags.contains(BinfmtFlags::PRESERVE_ARGV0) {
+                args.extend(prm.args.iter().take(1).cloned());
+            }
+            args.extend(rest);
+            let cred_path = if entry.flags.contains(BinfmtFlags::CREDENTIALS) {
+                prm.path.clone()
+            } else {
+                interpreter.clone()
+            };
+            prm = BinPrm {
+                path: interpreter,
+                args,
+                cred_path,
+            };
+            continue;
+        }
+        if prm.path.ends_with(".sh") {
+            // 兼容没有 `#!` 的测试脚本，交给 busybox sh 执行
+            let mut args = Vec::from([String::from("busybox"), String::from("sh"), prm.path]);
+            args.extend(rest);
+            prm = BinPrm {
+                path: String::from("busybox"),
+                args,
+                cred_path: String::from("busybox"),
+            };
+            continue;
+        }
+        return Err(LinuxError::ENOEXEC);
+    }
+    Err(LinuxError::ELOOP)
+}
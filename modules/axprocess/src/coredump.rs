@@ -0,0 +1,643 @@
+//! 进程被默认动作为 Core 的信号杀死时生成 ELF 格式的 core 文件
+//!
+//! 文件名由 `core_pattern` 决定，可以通过 `/proc/sys/kernel/core_pattern` 读写，
+//! 大小受 RLIMIT_CORE 限制，超出的部分会被截断。文件包含：
+//!
+//! - 一个 PT_NOTE：每个线程的 NT_PRSTATUS，以及 NT_PRPSINFO、NT_AUXV 与 NT_FILE
+//! - 每个内存区域对应一个 PT_LOAD，不可读的区域只记录地址范围
+//!
+//! 布局与 Linux 一致，可以直接用 gdb 加载。
+//!
+//! 生成 core 文件前会让进程中的其余线程在返回用户态前停下，保证读到的寄存器不再变化；
+//! 尚未分配物理页面的 lazy 页面不会被分配，在文件中留下空洞。
+extern crate alloc;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult};
+use axfs::api::{File, Seek, SeekFrom, Write};
+use axhal::mem::PAGE_SIZE_4K;
+use axhal::paging::MappingFlags;
+use axhal::time::current_time;
+use axlog::{info, warn};
+use axsignal::info::SigInfo;
+use axsync::Mutex;
+use axtask::{yield_now, AxTaskRef, TaskState, WaitQueue};
+use core::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use core::time::Duration;
+
+use crate::process::Process;
+use crate::ptrace::UserRegs;
+use crate::rlimit::RLIMIT_CORE;
+
+/// core_pattern 的最大长度，与 Linux 的 `CORENAME_MAX_SIZE` 一致
+const CORE_PATTERN_MAX_LEN: usize = 127;
+
+static CORE_PATTERN: Mutex<String> = Mutex::new(String::new());
+
+/// 等待其余线程停下的最长时间，超时后不再等待仍在内核中运行的线程
+const STOP_THREADS_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// 生成 core 文件的线程与进程中其余线程的同步
+///
+/// 转储期间其余线程在处理信号时停下，转储结束后以导致转储的信号退出
+pub struct CoreDumpSync {
+    /// 是否正在生成 core 文件，供等待队列在持有 `RUN_QUEUE` 时检查
+    dumping: AtomicBool,
+    /// 导致转储的信号
+    signal: AtomicI32,
+    /// 等待转储结束的线程
+    wait_queue: WaitQueue,
+}
+
+impl Default for CoreDumpSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CoreDumpSync {
+    /// 创建未在转储的同步模块
+    pub fn new() -> Self {
+        Self {
+            dumping: AtomicBool::new(false),
+            signal: AtomicI32::new(0),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// 是否有线程正在生成 core 文件
+    pub fn is_dumping(&self) -> bool {
+        self.dumping.load(Ordering::Acquire)
+    }
+
+    /// 导致转储的信号
+    pub fn signal(&self) -> i32 {
+        self.signal.load(Ordering::Acquire)
+    }
+
+    /// 当前线程暂停，直到转储结束
+    pub fn wait_for_dump(&self) {
+        self.wait_queue.wait_until(|| !self.is_dumping());
+    }
+
+    fn begin(&self, signal: i32) {
+        self.signal.store(signal, Ordering::Release);
+        self.dumping.store(true, Ordering::Release);
+    }
+
+    fn finish(&self) {
+        self.dumping.store(false, Ordering::Release);
+        self.wait_queue.notify_all(false);
+    }
+}
+
+/// 等待进程中的其余线程停下
+///
+/// 线程在返回用户态前处理信号时会因转储而阻塞，已经阻塞在系统调用中的线程不会再修改 trap 上下文。
+/// 仍在内核中运行且超时未停下的线程不再等待
+fn stop_other_threads(process: &Process) {
+    let current = axtask::current().id().as_u64();
+    let deadline = current_time() + STOP_THREADS_TIMEOUT;
+    while process.tasks.lock().iter().any(|task| {
+        task.id().as_u64() != current
+            && !matches!(task.state(), TaskState::Blocked | TaskState::Exited)
+    }) {
+        if current_time() >= deadline {
+            warn!(
+                "dumping core of process {} with threads still running",
+                process.pid()
+            );
+            return;
+        }
+        yield_now();
+    }
+}
+
+/// 当前的 core_pattern，默认为 `core`
+pub fn core_pattern() -> String {
+    let pattern = CORE_PATTERN.lock();
+    if pattern.is_empty() {
+        String::from("core")
+    } else {
+        pattern.clone()
+    }
+}
+
+/// 设置 core_pattern，末尾的换行会被去掉
+pub fn set_core_pattern(pattern: &str) -> AxResult {
+    let pattern = pattern.trim_end_matches('\n');
+    if pattern.len() > CORE_PATTERN_MAX_LEN {
+        return Err(AxError::InvalidInput);
+    }
+    *CORE_PATTERN.lock() = pattern.to_string();
+    Ok(())
+}
+
+/// 展开 core_pattern 中的格式说明符，得到 core 文件的路径
+///
+/// 支持 `%p`/`%P`（进程号）、`%i`/`%I`（线程号）、`%u`、`%g`、`%s`、`%t`、`%e`、`%E` 与 `%%`，
+/// 其余说明符被忽略。以 `|` 开头的管道模式不支持，返回 None
+fn core_file_path(process: &Process, signal: i32) -> Option<String> {
+    let pattern = core_pattern();
+    if pattern.starts_with('|') {
+        warn!("piping core dumps to a program is not supported");
+        return None;
+    }
+    let exe = process.get_file_path();
+    let comm: String = exe
+        .rsplit('/')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .take(15)
+        .collect();
+    let cred = process.cred();
+    let mut path = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            path.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('%') => path.push('%'),
+            Some('p') | Some('P') => path += &process.pid().to_string(),
+            Some('i') | Some('I') => path += &axtask::current().id().as_u64().to_string(),
+            Some('u') => path += &cred.uid.to_string(),
+            Some('g') => path += &cred.gid.to_string(),
+            Some('s') => path += &signal.to_string(),
+            Some('t') => path += &axhal::time::current_time().as_secs().to_string(),
+            Some('e') => path += &comm,
+            Some('E') => path += &exe.replace('/', "!"),
+            _ => {}
+        }
+    }
+    if path.is_empty() {
+        return None;
+    }
+    if !path.starts_with('/') {
+        path = format!("{}/{}", process.get_cwd().trim_end_matches('/'), path);
+    }
+    Some(path)
+}
+
+const EI_NIDENT: usize = 16;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+const ET_CORE: u16 = 4;
+#[cfg(target_arch = "x86_64")]
+const EM_CURRENT: u16 = 62;
+#[cfg(target_arch = "aarch64")]
+const EM_CURRENT: u16 = 183;
+#[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))]
+const EM_CURRENT: u16 = 243;
+
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const NT_PRSTATUS: u32 = 1;
+const NT_PRPSINFO: u32 = 3;
+const NT_AUXV: u32 = 6;
+const NT_FILE: u32 = 0x4649_4c45;
+
+#[repr(C)]
+struct Elf64Ehdr {
+    e_ident: [u8; EI_NIDENT],
+    e_type: u16,
+    e_machine: u16,
+    e_version: u32,
+    e_entry: u64,
+    e_phoff: u64,
+    e_shoff: u64,
+    e_flags: u32,
+    e_ehsize: u16,
+    e_phentsize: u16,
+    e_phnum: u16,
+    e_shentsize: u16,
+    e_shnum: u16,
+    e_shstrndx: u16,
+}
+
+#[repr(C)]
+struct Elf64Phdr {
+    p_type: u32,
+    p_flags: u32,
+    p_offset: u64,
+    p_vaddr: u64,
+    p_paddr: u64,
+    p_filesz: u64,
+    p_memsz: u64,
+    p_align: u64,
+}
+
+/// 与 Linux 的 `struct elf_prstatus` 一致
+#[repr(C)]
+struct ElfPrStatus {
+    si_signo: i32,
+    si_code: i32,
+    si_errno: i32,
+    pr_cursig: i16,
+    _pad0: i16,
+    pr_sigpend: u64,
+    pr_sighold: u64,
+    pr_pid: i32,
+    pr_ppid: i32,
+    pr_pgrp: i32,
+    pr_sid: i32,
+    /// 依次为 pr_utime、pr_stime、pr_cutime 与 pr_cstime，当前均为 0
+    pr_times: [[i64; 2]; 4],
+    pr_reg: UserRegs,
+    pr_fpvalid: i32,
+    _pad1: i32,
+}
+
+/// 与 Linux 的 `struct elf_prpsinfo` 一致
+#[repr(C)]
+struct ElfPrPsInfo {
+    pr_state: u8,
+    pr_sname: u8,
+    pr_zomb: u8,
+    pr_nice: i8,
+    _pad0: u32,
+    pr_flag: u64,
+    pr_uid: u32,
+    pr_gid: u32,
+    pr_pid: i32,
+    pr_ppid: i32,
+    pr_pgrp: i32,
+    pr_sid: i32,
+    pr_fname: [u8; 16],
+    pr_psargs: [u8; 80],
+}
+
+/// 将没有隐式填充的结构体视为字节序列
+fn as_bytes<T>(value: &T) -> &[u8] {
+    unsafe {
+        core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+    }
+}
+
+/// 复制字符串，超出的部分截断，并保留结尾的 `\0`
+fn copy_str(dst: &mut [u8], src: &str) {
+    let len = src.len().min(dst.len() - 1);
+    dst[..len].copy_from_slice(&src.as_bytes()[..len]);
+}
+
+/// 追加一条名字为 `CORE` 的 note
+fn push_note(notes: &mut Vec<u8>, note_type: u32, desc: &[u8]) {
+    const NAME: &[u8] = b"CORE\0";
+    notes.extend_from_slice(&(NAME.len() as u32).to_ne_bytes());
+    notes.extend_from_slice(&(desc.len() as u32).to_ne_bytes());
+    notes.extend_from_slice(&note_type.to_ne_bytes());
+    notes.extend_from_slice(NAME);
+    notes.resize(notes.len().next_multiple_of(4), 0);
+    notes.extend_from_slice(desc);
+    notes.resize(notes.len().next_multiple_of(4), 0);
+}
+
+/// 需要写入 core 文件的内存区域
+struct DumpRegion {
+    start: usize,
+    end: usize,
+    flags: MappingFlags,
+    /// 是否为进程自己的区域，此时未分配的页面需要先分配再读取
+    owned: bool,
+    /// 映射的文件与起始偏移
+    file: Option<(String, u64)>,
+}
+
+impl DumpRegion {
+    /// 只有可读的区域才写入内容
+    fn dumpable(&self) -> bool {
+        self.flags.contains(MappingFlags::READ)
+    }
+
+    fn phdr_flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.flags.contains(MappingFlags::READ) {
+            flags |= PF_R;
+        }
+        if self.flags.contains(MappingFlags::WRITE) {
+            flags |= PF_W;
+        }
+        if self.flags.contains(MappingFlags::EXECUTE) {
+            flags |= PF_X;
+        }
+        flags
+    }
+}
+
+fn collect_regions(process: &Process) -> Vec<DumpRegion> {
+    let mut memory_set = process.memory_set.lock();
+    let mut regions: Vec<DumpRegion> = memory_set
+        .areas_mut()
+        .map(|area| DumpRegion {
+            start: area.vaddr.as_usize(),
+            end: area.end_va().as_usize(),
+            flags: area.flags,
+            owned: true,
+            file: area.backend.as_mut().and_then(|backend| {
+                let path = backend.path()?.to_string();
+                let offset = backend.seek(SeekFrom::Current(0)).ok()?;
+                Some((path, offset))
+            }),
+        })
+        .collect();
+    regions.extend(
+        memory_set
+            .attached_areas()
+            .map(|(start, size, flags)| DumpRegion {
+                start: start.as_usize(),
+                end: start.as_usize() + size,
+                flags,
+                owned: false,
+                file: None,
+            }),
+    );
+    regions.sort_by_key(|region| region.start);
+    regions
+}
+
+/// 构造某个线程的 NT_PRSTATUS
+fn prstatus(process: &Process, task: &AxTaskRef, info: Option<&SigInfo>) -> ElfPrStatus {
+    let tid = task.id().as_u64();
+    let (sigpend, sighold) = process
+        .signal_modules
+        .lock()
+        .get(&tid)
+        .map_or((0, 0), |module| {
+            (module.signal_set.pending, module.signal_set.mask)
+        });
+    #[cfg(target_arch = "x86_64")]
+    let tls = task.get_tls_force();
+    #[cfg(not(target_arch = "x86_64"))]
+    let tls = 0;
+    let trap_frame = unsafe { &*task.get_first_trap_frame() };
+    ElfPrStatus {
+        si_signo: info.map_or(0, |info| info.si_signo),
+        si_code: info.map_or(0, |info| info.si_code),
+        si_errno: info.map_or(0, |info| info.si_errno),
+        pr_cursig: info.map_or(0, |info| info.si_signo as i16),
+        _pad0: 0,
+        pr_sigpend: sigpend as u64,
+        pr_sighold: sighold as u64,
+        pr_pid: tid as i32,
+        pr_ppid: process.get_parent() as i32,
+        pr_pgrp: process.get_pgid() as i32,
+        pr_sid: process.get_sid() as i32,
+        pr_times: [[0; 2]; 4],
+        pr_reg: UserRegs::from_trap_frame(trap_frame, None, tls),
+        pr_fpvalid: 0,
+        _pad1: 0,
+    }
+}
+
+fn prpsinfo(process: &Process) -> ElfPrPsInfo {
+    let exe = process.get_file_path();
+    let cred = process.cred();
+    let mut info = ElfPrPsInfo {
+        pr_state: 0,
+        pr_sname: b'R',
+        pr_zomb: 0,
+        pr_nice: 0,
+        _pad0: 0,
+        pr_flag: 0,
+        pr_uid: cred.uid,
+        pr_gid: cred.gid,
+        pr_pid: process.pid() as i32,
+        pr_ppid: process.get_parent() as i32,
+        pr_pgrp: process.get_pgid() as i32,
+        pr_sid: process.get_sid() as i32,
+        pr_fname: [0; 16],
+        pr_psargs: [0; 80],
+    };
+    copy_str(&mut info.pr_fname, exe.rsplit('/').next().unwrap_or(""));
+    copy_str(&mut info.pr_psargs, &exe);
+    info
+}
+
+/// NT_FILE：映射了文件的区域，偏移以页为单位
+fn file_note(regions: &[DumpRegion]) -> Vec<u8> {
+    let files: Vec<_> = regions
+        .iter()
+        .filter_map(|region| region.file.as_ref().map(|file| (region, file)))
+        .collect();
+    let mut desc = Vec::new();
+    desc.extend_from_slice(&(files.len() as u64).to_ne_bytes());
+    desc.extend_from_slice(&(PAGE_SIZE_4K as u64).to_ne_bytes());
+    for (region, (_, offset)) in files.iter() {
+        desc.extend_from_slice(&(region.start as u64).to_ne_bytes());
+        desc.extend_from_slice(&(region.end as u64).to_ne_bytes());
+        desc.extend_from_slice(&(offset / PAGE_SIZE_4K as u64).to_ne_bytes());
+    }
+    for (_, (path, _)) in files.iter() {
+        desc.extend_from_slice(path.as_bytes());
+        desc.push(0);
+    }
+    desc
+}
+
+fn build_notes(process: &Process, info: &SigInfo, regions: &[DumpRegion]) -> Vec<u8> {
+    let mut notes = Vec::new();
+    // 当前线程的 NT_PRSTATUS 放在最前面，gdb 将其视为触发信号的线程
+    let current = axtask::current();
+    let mut tasks: Vec<AxTaskRef> = process
+        .tasks
+        .lock()
+        .iter()
+        .filter(|task| task.state() != TaskState::Exited)
+        .cloned()
+        .collect();
+    tasks.sort_by_key(|task| task.id().as_u64() != current.id().as_u64());
+    for task in tasks.iter() {
+        let info = (task.id().as_u64() == current.id().as_u64()).then_some(info);
+        push_note(
+            &mut notes,
+            NT_PRSTATUS,
+            as_bytes(&prstatus(process, task, info)),
+        );
+    }
+    push_note(&mut notes, NT_PRPSINFO, as_bytes(&prpsinfo(process)));
+
+    let mut auxv = Vec::new();
+    for (key, value) in process.auxv.lock().iter() {
+        auxv.extend_from_slice(&(*key as u64).to_ne_bytes());
+        auxv.extend_from_slice(&(*value as u64).to_ne_bytes());
+    }
+    // AT_NULL
+    auxv.extend_from_slice(&[0; 16]);
+    push_note(&mut notes, NT_AUXV, &auxv);
+
+    push_note(&mut notes, NT_FILE, &file_note(regions));
+    notes
+}
+
+/// 写入 core 文件，写满 RLIMIT_CORE 后丢弃之后的内容
+struct CoreWriter {
+    file: File,
+    written: u64,
+    limit: u64,
+}
+
+impl CoreWriter {
+    fn write(&mut self, data: &[u8]) -> AxResult {
+        let len = (self.limit - self.written).min(data.len() as u64) as usize;
+        if len == 0 {
+            return Ok(());
+        }
+        self.file.write_all(&data[..len])?;
+        self.written += len as u64;
+        Ok(())
+    }
+
+    /// 跳过 `len` 字节，在文件中留下空洞
+    fn skip(&mut self, len: u64) -> AxResult {
+        let len = (self.limit - self.written).min(len);
+        if len == 0 {
+            return Ok(());
+        }
+        self.written += len;
+        self.file.seek(SeekFrom::Start(self.written))?;
+        Ok(())
+    }
+
+    fn pad_to(&mut self, offset: u64) -> AxResult {
+        while self.written < offset.min(self.limit) {
+            let len = (offset - self.written).min(PAGE_SIZE_4K as u64) as usize;
+            self.write(&[0; PAGE_SIZE_4K][..len])?;
+        }
+        Ok(())
+    }
+}
+
+fn write_core(process: &Process, info: &SigInfo, path: &str, limit: u64) -> AxResult {
+    let regions = collect_regions(process);
+    let notes = build_notes(process, info, &regions);
+
+    let phnum = regions.len() + 1;
+    let ehdr_size = core::mem::size_of::<Elf64Ehdr>();
+    let phdr_size = core::mem::size_of::<Elf64Phdr>();
+    let notes_offset = ehdr_size + phnum * phdr_size;
+    let data_offset = (notes_offset + notes.len()).next_multiple_of(PAGE_SIZE_4K);
+
+    let mut e_ident = [0; EI_NIDENT];
+    e_ident[..4].copy_from_slice(b"\x7fELF");
+    e_ident[4] = ELFCLASS64;
+    e_ident[5] = ELFDATA2LSB;
+    e_ident[6] = EV_CURRENT;
+    let ehdr = Elf64Ehdr {
+        e_ident,
+        e_type: ET_CORE,
+        e_machine: EM_CURRENT,
+        e_version: EV_CURRENT as u32,
+        e_entry: 0,
+        e_phoff: ehdr_size as u64,
+        e_shoff: 0,
+        e_flags: 0,
+        e_ehsize: ehdr_size as u16,
+        e_phentsize: phdr_size as u16,
+        e_phnum: phnum as u16,
+        e_shentsize: 0,
+        e_shnum: 0,
+        e_shstrndx: 0,
+    };
+
+    let mut file = File::options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    file.set_len(0)?;
+    let mut writer = CoreWriter {
+        file,
+        written: 0,
+        limit,
+    };
+    writer.write(as_bytes(&ehdr))?;
+    writer.write(as_bytes(&Elf64Phdr {
+        p_type: PT_NOTE,
+        p_flags: 0,
+        p_offset: notes_offset as u64,
+        p_vaddr: 0,
+        p_paddr: 0,
+        p_filesz: notes.len() as u64,
+        p_memsz: 0,
+        p_align: 0,
+    }))?;
+    let mut offset = data_offset;
+    for region in regions.iter() {
+        let size = region.end - region.start;
+        let filesz = if region.dumpable() { size } else { 0 };
+        writer.write(as_bytes(&Elf64Phdr {
+            p_type: PT_LOAD,
+            p_flags: region.phdr_flags(),
+            p_offset: offset as u64,
+            p_vaddr: region.start as u64,
+            p_paddr: 0,
+            p_filesz: filesz as u64,
+            p_memsz: size as u64,
+            p_align: PAGE_SIZE_4K as u64,
+        }))?;
+        offset += filesz;
+    }
+    writer.write(&notes)?;
+    writer.pad_to(data_offset as u64)?;
+
+    for region in regions.iter().filter(|region| region.dumpable()) {
+        for page in (region.start..region.end).step_by(PAGE_SIZE_4K) {
+            if writer.written >= writer.limit {
+                break;
+            }
+            // 尚未访问过的 lazy 页面内容全为零，不为其分配物理页面
+            let present = !region.owned || process.memory_set.lock().is_populated(page.into());
+            if present {
+                // 当前仍处于该进程的地址空间中，可以直接读取用户内存
+                let data = unsafe { core::slice::from_raw_parts(page as *const u8, PAGE_SIZE_4K) };
+                writer.write(data)?;
+            } else {
+                writer.skip(PAGE_SIZE_4K as u64)?;
+            }
+        }
+    }
+    // 末尾的空洞需要通过设置文件长度补齐
+    writer.file.set_len(writer.written)?;
+    Ok(())
+}
+
+/// 为当前进程生成 core 文件，`info` 为导致进程终止的信号
+///
+/// 只能由该进程的线程调用，转储期间其余线程会停下。
+/// RLIMIT_CORE 小于一页或无法确定文件路径时不生成，成功写入时返回真
+pub fn dump_core(process: &Process, info: &SigInfo) -> bool {
+    let limit = process.get_rlimit(RLIMIT_CORE).rlim_cur;
+    if limit < PAGE_SIZE_4K as u64 {
+        return false;
+    }
+    let path = match core_file_path(process, info.si_signo) {
+        Some(path) => path,
+        None => return false,
+    };
+    process.core_dump.begin(info.si_signo);
+    stop_other_threads(process);
+    let result = write_core(process, info, &path, limit);
+    process.core_dump.finish();
+    match result {
+        Ok(()) => {
+            info!("process {} dumped core to {}", process.pid(), path);
+            true
+        }
+        Err(err) => {
+            warn!(
+                "failed to dump core of process {}: {:?}",
+                process.pid(),
+                err
+            );
+            false
+        }
+    }
+}
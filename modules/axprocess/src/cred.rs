@@ -0,0 +1,507 @@
+//! 进程的用户与用户组身份，以及基于身份的权限检查
+//!
+//! 每个进程持有真实、有效、保存的与文件系统的用户号和组号，以及附加组列表。
+//! 特权以 [`Privileges`] 表示，其编号与 Linux 的 capability 一致：
+//! 有效用户号为 0 时进程拥有全部特权，切换到普通用户后特权随之清除。
+//!
+//! 底层文件系统大多不保存文件的属主与权限位，因此与链接模块类似，
+//! 这里按路径记录被修改过或由进程新建的文件的属主与权限。
+//! 未被记录的文件属于 root，权限位取文件系统报告的值。
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use axerrno::{AxError, AxResult};
+use axfs::api::{FsEvent, OpenFlags};
+use axfs_vfs::VfsNodePerm;
+use axsync::Mutex;
+pub use capability::{Cap, Privileges};
+use core::sync::atomic::Ordering;
+
+use crate::{current_process, Process};
+
+/// 设置用户号位
+pub const S_ISUID: u32 = 0o4000;
+/// 设置组号位
+pub const S_ISGID: u32 = 0o2000;
+/// 粘滞位
+pub const S_ISVTX: u32 = 0o1000;
+
+/// 附加组数目的上限
+pub const NGROUPS_MAX: usize = 65536;
+
+/// 进程的身份信息
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    /// 真实用户号
+    pub uid: u32,
+    /// 有效用户号，用于大部分权限检查
+    pub euid: u32,
+    /// 保存的用户号
+    pub suid: u32,
+    /// 文件系统用户号，用于文件访问的权限检查
+    pub fsuid: u32,
+    /// 真实组号
+    pub gid: u32,
+    /// 有效组号
+    pub egid: u32,
+    /// 保存的组号
+    pub sgid: u32,
+    /// 文件系统组号
+    pub fsgid: u32,
+    /// 附加组
+    pub groups: Vec<u32>,
+    /// 可继承的特权
+    pub cap_inheritable: Privileges,
+    /// 允许拥有的特权
+    pub cap_permitted: Privileges,
+    /// 当前生效的特权
+    pub cap_effective: Privileges,
+}
+
+impl Credentials {
+    /// root 用户的身份，拥有全部特权
+    pub fn root() -> Self {
+        Self {
+            uid: 0,
+            euid: 0,
+            suid: 0,
+            fsuid: 0,
+            gid: 0,
+            egid: 0,
+            sgid: 0,
+            fsgid: 0,
+            groups: Vec::new(),
+            cap_inheritable: Privileges::empty(),
+            cap_permitted: Privileges::all(),
+            cap_effective: Privileges::all(),
+        }
+    }
+
+    /// 是否拥有给定的特权
+    pub fn capable(&self, privilege: Privileges) -> bool {
+        self.cap_effective.contains(privilege)
+    }
+
+    /// 给定的组号是否为文件系统组号或附加组之一
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.fsgid == gid || self.groups.contains(&gid)
+    }
+
+    /// 用户号发生变化后，按照 Linux 的规则调整特权
+    ///
+    /// - 真实、有效、保存的用户号均不再为 0 时，清除允许与生效的特权
+    /// - 有效用户号从 0 变为非 0 时，清除生效的特权；从非 0 变为 0 时，生效的特权恢复为允许的特权
+    /// - 文件系统用户号的变化只影响与文件系统相关的特权
+    pub fn fixup_privileges(&mut self, old: &Credentials) {
+        let was_root = old.uid == 0 || old.euid == 0 || old.suid == 0;
+        let is_root = self.uid == 0 || self.euid == 0 || self.suid == 0;
+        if was_root && !is_root {
+            self.cap_permitted = Privileges::empty();
+            self.cap_effective = Privileges::empty();
+        }
+        if old.euid == 0 && self.euid != 0 {
+            self.cap_effective = Privileges::empty();
+        }
+        if old.euid != 0 && self.euid == 0 {
+            self.cap_effective = self.cap_permitted;
+        }
+        if old.fsuid == 0 && self.fsuid != 0 {
+            self.cap_effective &= !Privileges::FS_MASK;
+        }
+        if old.fsuid != 0 && self.fsuid == 0 {
+            self.cap_effective |= self.cap_permitted & Privileges::FS_MASK;
+        }
+    }
+
+    /// 按照文件的属主与权限位，计算对该文件拥有的访问权限，不考虑特权
+    pub fn mode_access(&self, owner: &FileOwner) -> Cap {
+        let bits = if self.fsuid == owner.uid {
+            owner.mode >> 6
+        } else if self.in_group(owner.gid) {
+            owner.mode >> 3
+        } else {
+            owner.mode
+        };
+        Cap::from_mode_bits(bits & 0o7)
+    }
+
+    /// 是否能以 `want` 的方式访问给定的文件
+    pub fn may_access(&self, owner: &FileOwner, want: Cap) -> bool {
+        if self.mode_access(owner).contains(want) {
+            return true;
+        }
+        // 执行普通文件时，至少要有一个执行位被设置
+        if self.capable(Privileges::DAC_OVERRIDE)
+            && (!want.contains(Cap::EXECUTE) || owner.is_dir || owner.mode & 0o111 != 0)
+        {
+            return true;
+        }
+        let read_search = if owner.is_dir {
+            Cap::READ | Cap::EXECUTE
+        } else {
+            Cap::READ
+        };
+        self.capable(Privileges::DAC_READ_SEARCH) && read_search.contains(want)
+    }
+
+    /// 是否为文件的属主，或拥有等同于属主的特权
+    pub fn owns(&self, owner: &FileOwner) -> bool {
+        self.fsuid == owner.uid || self.capable(Privileges::FOWNER)
+    }
+
+    /// 是否能以 `want` 的方式访问给定的 System V IPC 对象
+    pub fn ipc_access(&self, perm: &IpcPerm, want: Cap) -> bool {
+        let bits = if self.euid == perm.uid || self.euid == perm.cuid {
+            perm.mode >> 6
+        } else if self.egid == perm.gid || self.egid == perm.cgid || self.groups.contains(&perm.gid)
+        {
+            perm.mode >> 3
+        } else {
+            perm.mode
+        };
+        Cap::from_mode_bits(bits & 0o7).contains(want) || self.capable(Privileges::IPC_OWNER)
+    }
+
+    /// 是否能向拥有身份 `target` 的进程发送信号
+    pub fn can_signal(&self, target: &Credentials) -> bool {
+        self.capable(Privileges::KILL)
+            || self.uid == target.uid
+            || self.uid == target.suid
+            || self.euid == target.uid
+            || self.euid == target.suid
+    }
+
+    /// 是否能跟踪拥有身份 `target` 的进程
+    ///
+    /// 要求双方的用户号与组号完全一致，且目标的特权不多于自身
+    pub fn can_ptrace(&self, target: &Credentials) -> bool {
+        if self.capable(Privileges::SYS_PTRACE) {
+            return true;
+        }
+        let same_user = [target.uid, target.euid, target.suid]
+            .iter()
+            .all(|&uid| uid == self.uid);
+        let same_group = [target.gid, target.egid, target.sgid]
+            .iter()
+            .all(|&gid| gid == self.gid);
+        same_user && same_group && self.cap_permitted.contains(target.cap_permitted)
+    }
+
+    /// 执行新程序时更新身份
+    ///
+    /// 可执行文件设置了 S_ISUID 或 S_ISGID 时，有效与保存的用户号或组号变为文件的属主。
+    /// `secure` 为假时不提升身份，例如进程正在被跟踪。
+    /// 之后按有效用户号重新计算特权：root 获得全部特权，普通用户没有特权
+    pub fn exec(&mut self, file: &FileOwner, secure: bool) {
+        if secure && file.mode & S_ISUID != 0 {
+            self.euid = file.uid;
+        }
+        if secure && file.mode & S_ISGID != 0 && file.mode & 0o010 != 0 {
+            self.egid = file.gid;
+        }
+        self.suid = self.euid;
+        self.fsuid = self.euid;
+        self.sgid = self.egid;
+        self.fsgid = self.egid;
+        if self.uid == 0 || self.euid == 0 {
+            self.cap_permitted = Privileges::all();
+        } else {
+            self.cap_permitted = self.cap_inheritable & self.cap_permitted;
+        }
+        self.cap_effective = if self.euid == 0 {
+            self.cap_permitted
+        } else {
+            Privileges::empty()
+        };
+    }
+}
+
+/// System V IPC 对象的属主与权限，对应 `struct ipc_perm` 中与权限相关的部分
+#[derive(Clone, Copy, Debug)]
+pub struct IpcPerm {
+    /// 属主用户号
+    pub uid: u32,
+    /// 属主组号
+    pub gid: u32,
+    /// 创建者用户号
+    pub cuid: u32,
+    /// 创建者组号
+    pub cgid: u32,
+    /// 权限位
+    pub mode: u32,
+}
+
+/// 文件的属主与权限
+#[derive(Clone, Copy, Debug)]
+pub struct FileOwner {
+    /// 属主用户号
+    pub uid: u32,
+    /// 属主组号
+    pub gid: u32,
+    /// 权限位，包括 S_ISUID、S_ISGID 与粘滞位
+    pub mode: u32,
+    /// 是否为目录
+    pub is_dir: bool,
+}
+
+/// 文件系统不支持修改属主与权限时，在内存中记录的文件属主与权限，以规范化后的绝对路径为键
+static FILE_OWNERS: Mutex<BTreeMap<String, FileOwner>> = Mutex::new(BTreeMap::new());
+
+/// 去掉目录路径末尾的 '/'，使同一个文件只对应一个键
+fn normalize(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/"
+    } else {
+        trimmed
+    }
+}
+
+/// 获取文件的属主与权限
+pub fn file_owner(path: &str) -> AxResult<FileOwner> {
+    let path = normalize(path);
+    let attr = axfs::api::lookup(path)?.get_attr()?;
+    let is_dir = attr.is_dir();
+    if let Some(owner) = FILE_OWNERS.lock().get(path) {
+        return Ok(FileOwner { is_dir, ..*owner });
+    }
+    Ok(FileOwner {
+        uid: attr.uid(),
+        gid: attr.gid(),
+        mode: attr.perm().bits() as u32,
+        is_dir,
+    })
+}
+
+/// 记录文件的属主与权限
+///
+/// 优先写入文件系统中文件的 inode，文件系统不支持时记录在内存中
+pub fn set_file_owner(path: &str, owner: FileOwner) {
+    let path = normalize(path);
+    if let Ok(node) = axfs::api::lookup(path) {
+        let perm = VfsNodePerm::from_bits_truncate(owner.mode as u16);
+        if node.chown(Some(owner.uid), Some(owner.gid)).is_ok() && node.chmod(perm).is_ok() {
+            FILE_OWNERS.lock().remove(path);
+            return;
+        }
+    }
+    FILE_OWNERS.lock().insert(path.to_string(), owner);
+}
+
+/// 文件被删除时移除其记录
+pub fn remove_file_owner(path: &str) {
+    FILE_OWNERS.lock().remove(normalize(path));
+}
+
+/// 文件被重命名时移动其记录
+pub fn rename_file_owner(old: &str, new: &str) {
+    let mut owners = FILE_OWNERS.lock();
+    owners.remove(normalize(new));
+    if let Some(owner) = owners.remove(normalize(old)) {
+        owners.insert(normalize(new).to_string(), owner);
+    }
+}
+
+/// 若有记录，则用记录的属主与权限覆盖 stat 的结果
+pub fn fill_stat(path: &str, uid: &mut u32, gid: &mut u32, mode: &mut u32) {
+    if let Some(owner) = FILE_OWNERS.lock().get(normalize(path)) {
+        *uid = owner.uid;
+        *gid = owner.gid;
+        *mode = (*mode & !0o7777) | (owner.mode & 0o7777);
+    }
+}
+
+/// 检查拥有身份 `cred` 的进程能否查找给定路径，即对路径上的每一级目录都有执行权限
+///
+/// 不存在的目录不做检查，由调用者报告文件不存在
+pub fn search_as(cred: &Credentials, path: &str) -> AxResult<()> {
+    if cred.capable(Privileges::DAC_READ_SEARCH) {
+        return Ok(());
+    }
+    let path = normalize(path);
+    for (index, _) in path.match_indices('/') {
+        let dir = &path[..=index];
+        if let Ok(owner) = file_owner(dir) {
+            if !cred.may_access(&owner, Cap::EXECUTE) {
+                return Err(AxError::PermissionDenied);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 检查拥有身份 `cred` 的进程能否以 `want` 的方式访问给定路径
+pub fn access_as(cred: &Credentials, path: &str, want: Cap) -> AxResult<()> {
+    search_as(cred, path)?;
+    let owner = file_owner(path)?;
+    if cred.may_access(&owner, want) {
+        Ok(())
+    } else {
+        Err(AxError::PermissionDenied)
+    }
+}
+
+/// 检查当前进程能否查找给定路径
+pub fn check_search(path: &str) -> AxResult<()> {
+    search_as(&current_process().cred(), path)
+}
+
+/// 检查当前进程能否以 `want` 的方式访问给定路径
+pub fn check_access(path: &str, want: Cap) -> AxResult<()> {
+    access_as(&current_process().cred(), path, want)
+}
+
+/// 路径所在的目录
+fn parent_dir(path: &str) -> &str {
+    let path = normalize(path);
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(index) => &path[..index],
+    }
+}
+
+/// 检查当前进程能否在给定路径处创建或删除文件，即对其所在目录有写与执行权限
+///
+/// 所在目录设置了粘滞位时，只有文件或目录的属主能删除其中的文件
+pub fn check_modify_entry(path: &str, removing: bool) -> AxResult<()> {
+    let dir = parent_dir(path);
+    check_access(dir, Cap::WRITE | Cap::EXECUTE)?;
+    if removing {
+        let dir_owner = file_owner(dir)?;
+        if dir_owner.mode & S_ISVTX != 0 {
+            let cred = current_process().cred();
+            if let Ok(owner) = file_owner(path) {
+                if !cred.owns(&owner) && !cred.owns(&dir_owner) {
+                    return Err(AxError::PermissionDenied);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 检查当前进程能否以给定的标志打开路径
+pub fn check_open(path: &str, flags: OpenFlags) -> AxResult<()> {
+    if !axfs::api::path_exists(path) {
+        if flags.creatable() {
+            check_modify_entry(path, false)?;
+        }
+        return Ok(());
+    }
+    let mut want = Cap::empty();
+    if flags.readable() {
+        want |= Cap::READ;
+    }
+    if flags.writable() {
+        want |= Cap::WRITE;
+    }
+    check_access(path, want)
+}
+
+/// 记录当前进程新建的文件
+///
+/// 属主为进程的文件系统用户号；所在目录设置了 S_ISGID 时属组继承目录，否则为进程的文件系统组号。
+/// 权限位为 `mode` 去掉进程 umask 中的位
+pub fn file_created(path: &str, mode: u32, is_dir: bool) {
+    let process = current_process();
+    let cred = process.cred();
+    let umask = process.fd_manager.get_mask() as u32;
+    let mut mode = mode & 0o7777 & !umask;
+    let gid = match file_owner(parent_dir(path)) {
+        Ok(dir) if dir.mode & S_ISGID != 0 => {
+            // 新建的目录同样继承 S_ISGID
+            if is_dir {
+                mode |= S_ISGID;
+            }
+            dir.gid
+        }
+        _ => cred.fsgid,
+    };
+    set_file_owner(
+        path,
+        FileOwner {
+            uid: cred.fsuid,
+            gid,
+            mode,
+            is_dir,
+        },
+    );
+}
+
+/// 修改文件的权限位，只有属主或拥有 FOWNER 特权的进程可以修改
+///
+/// 进程不属于文件的属组且没有 FSETID 特权时，S_ISGID 被清除
+pub fn chmod(path: &str, mode: u32) -> AxResult<()> {
+    let cred = current_process().cred();
+    let mut owner = file_owner(path)?;
+    if !cred.owns(&owner) {
+        return Err(AxError::PermissionDenied);
+    }
+    let mut mode = mode & 0o7777;
+    if !owner.is_dir && !cred.in_group(owner.gid) && !cred.capable(Privileges::FSETID) {
+        mode &= !S_ISGID;
+    }
+    owner.mode = mode;
+    set_file_owner(path, owner);
+    axfs::api::notify_change(normalize(path), FsEvent::ATTRIB);
+    Ok(())
+}
+
+/// 修改文件的属主与属组，为 None 的一项保持不变
+///
+/// 修改属主需要 CHOWN 特权；属主可以把属组改为自己所在的组。
+/// 修改普通文件的属主或属组会清除 S_ISUID 与 S_ISGID
+pub fn chown(path: &str, uid: Option<u32>, gid: Option<u32>) -> AxResult<()> {
+    let cred = current_process().cred();
+    let mut owner = file_owner(path)?;
+    let privileged = cred.capable(Privileges::CHOWN);
+    if let Some(uid) = uid {
+        if uid != owner.uid && !privileged {
+            return Err(AxError::PermissionDenied);
+        }
+    }
+    if let Some(gid) = gid {
+        if gid != owner.gid && !privileged && !(cred.fsuid == owner.uid && cred.in_group(gid)) {
+            return Err(AxError::PermissionDenied);
+        }
+    }
+    if uid.is_none() && gid.is_none() {
+        return Ok(());
+    }
+    owner.uid = uid.unwrap_or(owner.uid);
+    owner.gid = gid.unwrap_or(owner.gid);
+    if !owner.is_dir {
+        owner.mode &= !S_ISUID;
+        if owner.mode & 0o010 != 0 {
+            owner.mode &= !S_ISGID;
+        }
+    }
+    set_file_owner(path, owner);
+    axfs::api::notify_change(normalize(path), FsEvent::ATTRIB);
+    Ok(())
+}
+
+/// 检查当前进程能否执行给定的文件
+pub fn check_exec(path: &str) -> AxResult<()> {
+    check_access(path, Cap::EXECUTE)?;
+    if file_owner(path)?.is_dir {
+        return Err(AxError::PermissionDenied);
+    }
+    Ok(())
+}
+
+/// 进程执行新程序后，根据可执行文件的 S_ISUID 与 S_ISGID 更新其身份
+pub fn exec_credentials(process: &Process, path: &str) {
+    let Ok(file) = file_owner(path) else {
+        return;
+    };
+    // 被跟踪或设置了 no_new_privs 的进程执行 setuid 程序时不提升身份
+    #[cfg(feature = "signal")]
+    let secure = !process.ptrace.is_traced();
+    #[cfg(not(feature = "signal"))]
+    let secure = true;
+    let secure = secure && !process.no_new_privs.load(Ordering::Acquire);
+    process.cred.lock().exec(&file, secure);
+}
@@ -1,6 +1,6 @@
 //! todo 重构fd_table, fd_allocator
 extern crate alloc;
-use core::sync::atomic::{AtomicI32, AtomicU64};
+use core::sync::atomic::AtomicI32;
 
 use alloc::string::String;
 use alloc::sync::Arc;
@@ -14,33 +14,20 @@ use crate::stdio::{Stdin, Stdout};
 pub struct FdManager {
     /// 保存文件描述符的数组
     pub fd_table: Mutex<Vec<Option<Arc<dyn FileIO>>>>,
-    /// 保存文件描述符的数组的最大长度
-    pub limit: AtomicU64,
     /// 创建文件时的mode的掩码
     umask: AtomicI32,
     pub cwd: Mutex<String>,
 }
 
 impl FdManager {
-    pub fn new(fd_table: Vec<Option<Arc<dyn FileIO>>>, limit: usize) -> Self {
+    pub fn new(fd_table: Vec<Option<Arc<dyn FileIO>>>) -> Self {
         Self {
             fd_table: Mutex::new(fd_table),
-            limit: AtomicU64::new(limit as u64),
             umask: AtomicI32::new(0o022),
             cwd: Mutex::new(String::from("/")),
         }
     }
 
-    pub fn get_limit(&self) -> u64 {
-        self.limit.load(core::sync::atomic::Ordering::Acquire)
-    }
-
-    pub fn set_limit(&self, new_limit: u64) {
-        self.limit
-            .store(new_limit, core::sync::atomic::Ordering::Release)
-    }
-
-    #[allow(unused)]
     pub fn get_mask(&self) -> i32 {
         self.umask.load(core::sync::atomic::Ordering::Acquire)
     }
@@ -4,7 +4,7 @@ use bitflags::*;
 
 bitflags! {
     /// 用于 sys_clone 的选项
-    #[derive(Debug, Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct CloneFlags: u32 {
         /// .
         const CLONE_NEWTIME = 1 << 7;
@@ -26,7 +26,7 @@ bitflags! {
         const CLONE_PARENT = 1 << 15;
         /// 作为一个“线程”被创建。具体来说，它同 CLONE_PARENT 一样设置 ppid，且不可被 wait
         const CLONE_THREAD = 1 << 16;
-        /// 子任务使用新的命名空间。目前还未用到
+        /// 子任务使用新的挂载命名空间
         const CLONE_NEWNS = 1 << 17;
         /// 子任务共享同一组信号量。用于 sys_semop
         const CLONE_SYSVSEM = 1 << 18;
@@ -42,6 +42,20 @@ bitflags! {
         const CLONE_UNTRACED = 1 << 23;
         /// 要求在子任务的一个地址写入子任务的 tid
         const CLONE_CHILD_SETTID = 1 << 24;
+        /// 子任务使用新的 cgroup 命名空间，目前不支持
+        const CLONE_NEWCGROUP = 1 << 25;
+        /// 子任务使用新的 UTS 命名空间，即独立的主机名与域名
+        const CLONE_NEWUTS = 1 << 26;
+        /// 子任务使用新的 IPC 命名空间，目前不支持
+        const CLONE_NEWIPC = 1 << 27;
+        /// 子任务使用新的用户命名空间，目前不支持
+        const CLONE_NEWUSER = 1 << 28;
+        /// 子任务使用新的 PID 命名空间，并成为其中的 1 号进程
+        const CLONE_NEWPID = 1 << 29;
+        /// 子任务使用新的网络命名空间
+        const CLONE_NEWNET = 1 << 30;
+        /// 与父任务共享 IO 上下文，目前忽略
+        const CLONE_IO = 1 << 31;
     }
 }
 
@@ -55,3 +69,93 @@ pub enum WaitStatus {
     /// 找不到对应的子任务
     NotExist,
 }
+
+bitflags! {
+    /// 指定 wait4 与 waitid 等待的状态变化
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct WaitFlags: u32 {
+        /// 不挂起当前进程，直接返回
+        const WNOHANG = 1 << 0;
+        /// 报告因信号停止的子进程
+        const WUNTRACED = 1 << 1;
+        /// waitid 中与 `WUNTRACED` 含义相同
+        const WSTOPPED = 1 << 1;
+        /// 报告已经退出的子进程，wait4 总是隐含这一选项
+        const WEXITED = 1 << 2;
+        /// 报告因 SIGCONT 继续运行的子进程
+        const WCONTINUED = 1 << 3;
+        /// 只查看状态变化而不将其取走，之后仍可以再次等待到它
+        const WNOWAIT = 1 << 24;
+        /// 不等待同一线程组中其他线程的子进程
+        const __WNOTHREAD = 1 << 29;
+        /// 等待所有子进程，无论其退出时发送什么信号
+        const __WALL = 1 << 30;
+        /// 只等待退出时不发送 SIGCHLD 的子进程
+        const __WCLONE = 1 << 31;
+    }
+}
+
+/// wait 系列系统调用等待的子进程
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitTarget {
+    /// 任意子进程
+    Any,
+    /// 指定进程号的子进程
+    Pid(u64),
+    /// 指定进程组中的子进程，进程组号为 0 时代表与当前进程同组
+    Pgid(u64),
+}
+
+impl From<isize> for WaitTarget {
+    /// 按照 wait4 的约定解释 pid：-1 代表任意子进程，0 代表同组的子进程，
+    /// 小于 -1 代表进程组 -pid 中的子进程
+    fn from(pid: isize) -> Self {
+        match pid {
+            -1 => Self::Any,
+            pid if pid <= 0 => Self::Pgid(pid.unsigned_abs() as u64),
+            pid => Self::Pid(pid as u64),
+        }
+    }
+}
+
+/// 子进程的一次状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChildState {
+    /// 以给定的退出码正常退出
+    Exited(i32),
+    /// 被给定的信号杀死
+    Killed(i32),
+    /// 被给定的信号杀死并生成了 core 文件
+    Dumped(i32),
+    /// 因给定的信号停止
+    Stopped(i32),
+    /// 进入跟踪停止，携带报告给跟踪者的 wait 状态
+    Trapped(i32),
+    /// 因 SIGCONT 继续运行
+    Continued,
+}
+
+impl ChildState {
+    /// wait4 写入用户空间的状态，编码与 Linux 一致，可以直接用 `WIFEXITED` 等宏解析
+    pub fn wait_status(&self) -> i32 {
+        match *self {
+            Self::Exited(code) => (code & 0xff) << 8,
+            Self::Killed(signal) => signal & 0x7f,
+            Self::Dumped(signal) => (signal & 0x7f) | 0x80,
+            Self::Stopped(signal) => (signal << 8) | 0x7f,
+            Self::Trapped(status) => status,
+            Self::Continued => 0xffff,
+        }
+    }
+}
+
+/// wait 系列系统调用取得的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitResult {
+    /// 状态发生变化的进程号，跟踪停止时为停止的线程号
+    pub pid: u64,
+    /// 该进程的真实用户号
+    pub uid: u32,
+    /// 发生的状态变化
+    pub state: ChildState,
+}
@@ -0,0 +1,99 @@
+//! 作业控制：进程的停止与继续
+//!
+//! 停止信号（SIGSTOP、SIGTSTP、SIGTTIN、SIGTTOU）的默认动作会使整个进程停止（组停止）：
+//! 处理该信号的线程将进程标记为停止并通知父进程，进程中的其余线程在下一次处理信号时也会停下，
+//! 直到进程收到 SIGCONT 或 SIGKILL。
+//!
+//! 停止与继续事件会一直保留，直到父进程通过 wait 系列调用取走。
+use axsync::Mutex;
+use axtask::WaitQueue;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// 尚未通过 wait 系列调用报告给父进程的作业控制事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobEvent {
+    /// 进程因给定的信号而停止
+    Stopped(i32),
+    /// 停止的进程因 SIGCONT 继续运行
+    Continued,
+}
+
+/// 进程的作业控制状态
+pub struct JobControl {
+    /// 是否处于停止状态，供等待队列在持有 `RUN_QUEUE` 时检查
+    stopped: AtomicBool,
+    /// 尚未报告的事件，新的事件会覆盖旧的事件
+    event: Mutex<Option<JobEvent>>,
+    /// 因进程停止而暂停的线程
+    wait_queue: WaitQueue,
+}
+
+impl Default for JobControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobControl {
+    /// 创建处于运行状态的作业控制模块
+    pub fn new() -> Self {
+        Self {
+            stopped: AtomicBool::new(false),
+            event: Mutex::new(None),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// 进程是否处于停止状态
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// 因给定的信号停止进程
+    ///
+    /// 若进程此前没有停止则记录停止事件并返回真，此时需要通知父进程
+    pub fn stop(&self, signal: i32) -> bool {
+        let mut event = self.event.lock();
+        if self.stopped.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        *event = Some(JobEvent::Stopped(signal));
+        true
+    }
+
+    /// 使停止的进程继续运行，并唤醒所有因此暂停的线程
+    ///
+    /// `report` 为假时不产生继续事件，供 SIGKILL 唤醒进程使用。
+    /// 若进程此前处于停止状态则返回真
+    pub fn resume(&self, report: bool) -> bool {
+        let mut event = self.event.lock();
+        if !self.stopped.swap(false, Ordering::AcqRel) {
+            return false;
+        }
+        *event = if report {
+            Some(JobEvent::Continued)
+        } else {
+            None
+        };
+        drop(event);
+        self.wait_queue.notify_all(false);
+        true
+    }
+
+    /// 查看尚未报告的事件，若其满足 `wanted` 则返回它
+    ///
+    /// `consume` 为真时同时将事件标记为已报告
+    pub fn take_event(&self, wanted: impl Fn(JobEvent) -> bool, consume: bool) -> Option<JobEvent> {
+        let mut event = self.event.lock();
+        let now = (*event).filter(|now| wanted(*now))?;
+        if consume {
+            *event = None;
+        }
+        Some(now)
+    }
+
+    /// 当前线程暂停，直到进程继续运行
+    pub fn wait_while_stopped(&self) {
+        self.wait_queue.wait_until(|| !self.is_stopped());
+    }
+}
@@ -6,11 +6,25 @@ pub use api::*;
 mod process;
 pub use process::{Process, PID2PC, TID2TASK};
 
+pub mod binfmt;
+pub mod cred;
 pub mod flags;
 pub mod futex;
+pub mod job;
 pub mod link;
+pub mod namespace;
+pub mod pidfd;
+pub mod rlimit;
 mod stdio;
+pub mod tty;
+pub mod vdso;
 
+#[cfg(feature = "signal")]
+pub mod coredump;
 mod fd_manager;
 #[cfg(feature = "signal")]
+pub mod ptrace;
+#[cfg(feature = "signal")]
+pub mod seccomp;
+#[cfg(feature = "signal")]
 pub mod signal;
@@ -1,5 +1,8 @@
 //! 模拟的链接、挂载模块
 //! fat32本身不支持符号链接和硬链接，两个指向相同文件的目录条目将会被chkdsk报告为交叉链接并修复
+//!
+//! ext4 与 ramfs 等文件系统通过 VFS 的 `symlink` 与 `link` 原生支持链接，
+//! 这里用路径映射模拟的链接只用于不支持链接的文件系统
 extern crate alloc;
 use alloc::collections::BTreeMap;
 use alloc::format;
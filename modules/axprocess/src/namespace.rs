@@ -0,0 +1,553 @@
+//! 进程的命名空间
+//!
+//! 支持四类命名空间，均可通过 clone 的 `CLONE_NEW*` 标志、unshare 或 setns 进入：
+//!
+//! - PID 命名空间：进程在所属的命名空间及其所有祖先命名空间中各有一个进程号。
+//!   内核内部始终使用全局进程号，系统调用在边界处通过 [`Process::pid_to_user`] 与
+//!   [`Process::pid_from_user`] 进行转换，线程号与进程号统一编号，也以同样的方式转换。
+//!   命名空间的 1 号进程退出时，其中其余进程都会被杀死，之后不能再在其中创建进程。
+//! - 挂载命名空间：保存挂载表，创建时复制原命名空间的挂载表
+//! - UTS 命名空间：保存主机名与域名
+//! - 网络命名空间：拥有独立的回环网卡与套接字集合，由 axnet 实现
+//!
+//! 创建或进入命名空间需要 `CAP_SYS_ADMIN`。
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use axerrno::{AxError, AxResult};
+use axfs::api::{MountTable, MountTableIf};
+use axsync::Mutex;
+
+use crate::cred::Privileges;
+use crate::flags::CloneFlags;
+use crate::{Process, PID2PC};
+
+#[cfg(feature = "net")]
+pub use axnet::NetNamespace;
+
+/// PID 命名空间的最大嵌套层数
+const MAX_PID_NS_LEVEL: usize = 32;
+
+/// 默认的主机名
+const DEFAULT_NODENAME: &str = "Starry - machine[0]";
+
+/// 默认的域名
+const DEFAULT_DOMAINNAME: &str = "https://github.com/Azure-stars/arceos";
+
+/// 下一个分配的命名空间编号，初始网络命名空间使用 `0xF000_0000`
+static NEXT_NS_ID: AtomicU64 = AtomicU64::new(0xF000_0001);
+
+fn alloc_ns_id() -> u64 {
+    NEXT_NS_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+lazy_static::lazy_static! {
+    static ref ROOT_PID_NS: Arc<PidNamespace> = Arc::new(PidNamespace::new(None));
+    static ref ROOT_MNT_NS: Arc<MountNamespace> = Arc::new(MountNamespace::new(None));
+    static ref ROOT_UTS_NS: Arc<UtsNamespace> = Arc::new(UtsNamespace::new(
+        DEFAULT_NODENAME.to_string(),
+        DEFAULT_DOMAINNAME.to_string(),
+    ));
+}
+
+#[derive(Default)]
+struct PidNamespaceInner {
+    /// 全局进程号到本命名空间进程号
+    to_local: BTreeMap<u64, u64>,
+    /// 本命名空间进程号到全局进程号
+    to_global: BTreeMap<u64, u64>,
+    /// 下一个分配的进程号
+    next_pid: u64,
+    /// 1 号进程的全局进程号
+    init: Option<u64>,
+    /// 1 号进程已经退出
+    dead: bool,
+}
+
+/// PID 命名空间
+///
+/// 根命名空间中的进程号即为全局进程号，不保存映射
+pub struct PidNamespace {
+    id: u64,
+    parent: Option<Arc<PidNamespace>>,
+    level: usize,
+    inner: Mutex<PidNamespaceInner>,
+}
+
+impl PidNamespace {
+    fn new(parent: Option<Arc<PidNamespace>>) -> Self {
+        let level = parent.as_ref().map_or(0, |parent| parent.level + 1);
+        Self {
+            id: alloc_ns_id(),
+            parent,
+            level,
+            inner: Mutex::new(PidNamespaceInner {
+                next_pid: 1,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// 根 PID 命名空间
+    pub fn root() -> Arc<Self> {
+        ROOT_PID_NS.clone()
+    }
+
+    /// 命名空间编号
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    fn is_root(&self) -> bool {
+        self.parent.is_none()
+    }
+
+    /// 创建一个子命名空间
+    pub fn new_child(self: &Arc<Self>) -> AxResult<Arc<Self>> {
+        if self.level >= MAX_PID_NS_LEVEL {
+            return Err(AxError::StorageFull);
+        }
+        Ok(Arc::new(Self::new(Some(self.clone()))))
+    }
+
+    /// 依次返回本命名空间及其所有非根的祖先命名空间
+    fn chain(self: &Arc<Self>) -> impl Iterator<Item = Arc<Self>> {
+        core::iter::successors(Some(self.clone()), |ns| ns.parent.clone())
+            .filter(|ns| !ns.is_root())
+    }
+
+    /// 在本命名空间及所有祖先命名空间中为全局进程号为 `global` 的进程分配进程号
+    ///
+    /// 线程号与进程号统一编号，线程也通过本函数分配其在命名空间中的线程号
+    ///
+    /// 命名空间的 1 号进程已经退出时返回 `NoMemory`
+    pub fn attach(self: &Arc<Self>, global: u64) -> AxResult {
+        if self.chain().any(|ns| ns.inner.lock().dead) {
+            return Err(AxError::NoMemory);
+        }
+        for ns in self.chain() {
+            let mut inner = ns.inner.lock();
+            let local = inner.next_pid;
+            inner.next_pid += 1;
+            inner.to_local.insert(global, local);
+            inner.to_global.insert(local, global);
+            if local == 1 {
+                inner.init = Some(global);
+            }
+        }
+        Ok(())
+    }
+
+    /// 释放进程在本命名空间及所有祖先命名空间中的进程号
+    pub fn detach(self: &Arc<Self>, global: u64) {
+        for ns in self.chain() {
+            let mut inner = ns.inner.lock();
+            if let Some(local) = inner.to_local.remove(&global) {
+                inner.to_global.remove(&local);
+            }
+        }
+    }
+
+    /// 全局进程号在本命名空间中对应的进程号，不可见时返回 `None`
+    pub fn pid_of(&self, global: u64) -> Option<u64> {
+        if self.is_root() {
+            return Some(global);
+        }
+        self.inner.lock().to_local.get(&global).copied()
+    }
+
+    /// 本命名空间中的进程号对应的全局进程号
+    pub fn global_pid(&self, local: u64) -> Option<u64> {
+        if self.is_root() {
+            return Some(local);
+        }
+        self.inner.lock().to_global.get(&local).copied()
+    }
+
+    /// 1 号进程的全局进程号，根命名空间没有 1 号进程
+    pub fn init_pid(&self) -> Option<u64> {
+        self.inner.lock().init
+    }
+
+    /// 本命名空间中所有进程与线程的全局编号，包括子命名空间中的进程
+    pub fn members(&self) -> Vec<u64> {
+        self.inner.lock().to_global.values().copied().collect()
+    }
+
+    /// 标记 1 号进程已经退出
+    pub fn set_dead(&self) {
+        self.inner.lock().dead = true;
+    }
+
+    /// 本命名空间是否为 `other` 或其祖先
+    pub fn is_ancestor_of(self: &Arc<Self>, other: &Arc<Self>) -> bool {
+        let mut ns = other.clone();
+        while ns.level > self.level {
+            ns = ns.parent.clone().unwrap();
+        }
+        Arc::ptr_eq(self, &ns)
+    }
+}
+
+/// 挂载命名空间
+pub struct MountNamespace {
+    id: u64,
+    /// 挂载表，None 表示启动时建立的初始挂载表。
+    /// 命名空间在文件系统初始化之前就已创建，因此初始挂载表需要延迟获取
+    table: Option<Arc<MountTable>>,
+}
+
+impl MountNamespace {
+    fn new(table: Option<Arc<MountTable>>) -> Self {
+        Self {
+            id: alloc_ns_id(),
+            table,
+        }
+    }
+
+    /// 命名空间编号
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 命名空间的挂载表
+    pub fn table(&self) -> Arc<MountTable> {
+        self.table
+            .clone()
+            .unwrap_or_else(axfs::api::init_mount_table)
+    }
+
+    /// 复制当前的挂载表，创建一个新的命名空间
+    pub fn copy(&self) -> Arc<Self> {
+        Arc::new(Self::new(Some(self.table().copy())))
+    }
+}
+
+/// 为 axfs 提供当前进程的挂载表
+struct MountTableImpl;
+
+#[crate_interface::impl_interface]
+impl MountTableIf for MountTableImpl {
+    fn current_mount_table() -> Option<Arc<MountTable>> {
+        let task = axtask::current_may_uninit()?;
+        let process = PID2PC.lock().get(&task.get_process_id()).cloned()?;
+        process.mnt_ns().table.clone()
+    }
+
+    fn mount_in_use(path: &str) -> bool {
+        let mnt_ns_id = crate::current_process().mnt_ns().id();
+        PID2PC.lock().values().any(|process| {
+            if process.mnt_ns().id() != mnt_ns_id {
+                return false;
+            }
+            if is_under(&process.fd_manager.cwd.lock(), path) {
+                axlog::debug!("{} is the working directory of {}", path, process.pid());
+                return true;
+            }
+            process
+                .fd_manager
+                .fd_table
+                .lock()
+                .iter()
+                .flatten()
+                .any(|file| is_under(&file.get_path(), path))
+        })
+    }
+}
+
+/// 路径 `path` 是否为目录 `dir` 或位于其中，二者均为规范化的绝对路径
+fn is_under(path: &str, dir: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    path == dir
+        || path
+            .strip_prefix(dir)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// UTS 命名空间
+pub struct UtsNamespace {
+    id: u64,
+    /// 主机名
+    pub nodename: Mutex<String>,
+    /// 域名
+    pub domainname: Mutex<String>,
+}
+
+impl UtsNamespace {
+    fn new(nodename: String, domainname: String) -> Self {
+        Self {
+            id: alloc_ns_id(),
+            nodename: Mutex::new(nodename),
+            domainname: Mutex::new(domainname),
+        }
+    }
+
+    /// 命名空间编号
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 复制当前的主机名与域名，创建一个新的命名空间
+    pub fn copy(&self) -> Arc<Self> {
+        Arc::new(Self::new(
+            self.nodename.lock().clone(),
+            self.domainname.lock().clone(),
+        ))
+    }
+}
+
+/// 指向某个命名空间的引用，即 `/proc/<pid>/ns/` 下的文件所代表的对象
+#[derive(Clone)]
+pub enum NsRef {
+    /// PID 命名空间
+    Pid(Arc<PidNamespace>),
+    /// 挂载命名空间
+    Mnt(Arc<MountNamespace>),
+    /// UTS 命名空间
+    Uts(Arc<UtsNamespace>),
+    #[cfg(feature = "net")]
+    /// 网络命名空间
+    Net(Arc<NetNamespace>),
+}
+
+impl NsRef {
+    /// 命名空间编号
+    pub fn id(&self) -> u64 {
+        match self {
+            NsRef::Pid(ns) => ns.id(),
+            NsRef::Mnt(ns) => ns.id(),
+            NsRef::Uts(ns) => ns.id(),
+            #[cfg(feature = "net")]
+            NsRef::Net(ns) => ns.id(),
+        }
+    }
+
+    /// 命名空间对应的 clone 标志
+    pub fn flag(&self) -> CloneFlags {
+        match self {
+            NsRef::Pid(_) => CloneFlags::CLONE_NEWPID,
+            NsRef::Mnt(_) => CloneFlags::CLONE_NEWNS,
+            NsRef::Uts(_) => CloneFlags::CLONE_NEWUTS,
+            #[cfg(feature = "net")]
+            NsRef::Net(_) => CloneFlags::CLONE_NEWNET,
+        }
+    }
+
+    /// `/proc/<pid>/ns/` 下的文件名
+    pub fn name(&self) -> &'static str {
+        match self {
+            NsRef::Pid(_) => "pid",
+            NsRef::Mnt(_) => "mnt",
+            NsRef::Uts(_) => "uts",
+            #[cfg(feature = "net")]
+            NsRef::Net(_) => "net",
+        }
+    }
+}
+
+/// 一个进程所处的全部命名空间
+#[derive(Clone)]
+pub struct Namespaces {
+    /// 进程所属的 PID 命名空间，创建后不再改变
+    pub pid: Arc<PidNamespace>,
+    /// 子进程将要进入的 PID 命名空间，由 unshare 与 setns 修改
+    pub pid_for_children: Arc<PidNamespace>,
+    /// 挂载命名空间
+    pub mnt: Arc<MountNamespace>,
+    /// UTS 命名空间
+    pub uts: Arc<UtsNamespace>,
+    #[cfg(feature = "net")]
+    /// 网络命名空间，`None` 表示初始命名空间。内核进程创建时网络尚未初始化，因此延迟获取
+    pub net: Option<Arc<NetNamespace>>,
+}
+
+impl Default for Namespaces {
+    fn default() -> Self {
+        Self {
+            pid: PidNamespace::root(),
+            pid_for_children: PidNamespace::root(),
+            mnt: ROOT_MNT_NS.clone(),
+            uts: ROOT_UTS_NS.clone(),
+            #[cfg(feature = "net")]
+            net: None,
+        }
+    }
+}
+
+/// 目前支持的命名空间标志
+fn supported_flags() -> CloneFlags {
+    let flags = CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS | CloneFlags::CLONE_NEWUTS;
+    #[cfg(feature = "net")]
+    let flags = flags | CloneFlags::CLONE_NEWNET;
+    flags
+}
+
+/// 所有命名空间标志
+fn namespace_flags() -> CloneFlags {
+    CloneFlags::CLONE_NEWNS
+        | CloneFlags::CLONE_NEWCGROUP
+        | CloneFlags::CLONE_NEWUTS
+        | CloneFlags::CLONE_NEWIPC
+        | CloneFlags::CLONE_NEWUSER
+        | CloneFlags::CLONE_NEWPID
+        | CloneFlags::CLONE_NEWNET
+        | CloneFlags::CLONE_NEWTIME
+}
+
+impl Namespaces {
+    /// 按照 `flags` 新建命名空间，其余命名空间保持不变
+    ///
+    /// `CLONE_NEWPID` 只改变 `pid_for_children`
+    fn unshare(&self, flags: CloneFlags) -> AxResult<Self> {
+        let mut ns = self.clone();
+        if flags.contains(CloneFlags::CLONE_NEWPID) {
+            ns.pid_for_children = self.pid_for_children.new_child()?;
+        }
+        if flags.contains(CloneFlags::CLONE_NEWNS) {
+            ns.mnt = self.mnt.copy();
+        }
+        if flags.contains(CloneFlags::CLONE_NEWUTS) {
+            ns.uts = self.uts.copy();
+        }
+        #[cfg(feature = "net")]
+        if flags.contains(CloneFlags::CLONE_NEWNET) {
+            ns.net = Some(NetNamespace::new(alloc_ns_id()));
+        }
+        Ok(ns)
+    }
+}
+
+/// 检查命名空间标志是否受支持以及调用者是否有权限
+fn check_namespace_flags(process: &Process, flags: CloneFlags) -> AxResult {
+    let flags = flags & namespace_flags();
+    if flags.is_empty() {
+        return Ok(());
+    }
+    if !supported_flags().contains(flags) {
+        return Err(AxError::InvalidInput);
+    }
+    if !process.cred().capable(Privileges::SYS_ADMIN) {
+        return Err(AxError::PermissionDenied);
+    }
+    Ok(())
+}
+
+impl Process {
+    /// 进程所处的全部命名空间
+    pub fn namespaces(&self) -> Namespaces {
+        self.namespaces.lock().clone()
+    }
+
+    /// 进程所属的 PID 命名空间
+    pub fn pid_ns(&self) -> Arc<PidNamespace> {
+        self.namespaces.lock().pid.clone()
+    }
+
+    /// 进程的挂载命名空间
+    pub fn mnt_ns(&self) -> Arc<MountNamespace> {
+        self.namespaces.lock().mnt.clone()
+    }
+
+    /// 进程的 UTS 命名空间
+    pub fn uts_ns(&self) -> Arc<UtsNamespace> {
+        self.namespaces.lock().uts.clone()
+    }
+
+    #[cfg(feature = "net")]
+    /// 进程的网络命名空间，新建的套接字属于这一命名空间
+    pub fn net_ns(&self) -> Arc<NetNamespace> {
+        self.namespaces
+            .lock()
+            .net
+            .clone()
+            .unwrap_or_else(NetNamespace::init_ns)
+    }
+
+    /// 进程所处的某一类命名空间，`flag` 为对应的 clone 标志
+    pub fn ns_ref(&self, flag: CloneFlags) -> Option<NsRef> {
+        let ns = self.namespaces.lock();
+        if flag == CloneFlags::CLONE_NEWPID {
+            Some(NsRef::Pid(ns.pid.clone()))
+        } else if flag == CloneFlags::CLONE_NEWNS {
+            Some(NsRef::Mnt(ns.mnt.clone()))
+        } else if flag == CloneFlags::CLONE_NEWUTS {
+            Some(NsRef::Uts(ns.uts.clone()))
+        } else {
+            #[cfg(feature = "net")]
+            if flag == CloneFlags::CLONE_NEWNET {
+                drop(ns);
+                return Some(NsRef::Net(self.net_ns()));
+            }
+            None
+        }
+    }
+
+    /// 将全局进程号转换为本进程所见的进程号，不可见的进程为 0
+    pub fn pid_to_user(&self, global: u64) -> u64 {
+        self.pid_ns().pid_of(global).unwrap_or(0)
+    }
+
+    /// 将本进程所见的进程号转换为全局进程号
+    pub fn pid_from_user(&self, local: u64) -> Option<u64> {
+        self.pid_ns().global_pid(local)
+    }
+
+    /// clone 时为子进程决定命名空间，并在 PID 命名空间中为其分配进程号
+    pub(crate) fn clone_namespaces(&self, flags: CloneFlags, pid: u64) -> AxResult<Namespaces> {
+        check_namespace_flags(self, flags)?;
+        let mut ns = self.namespaces().unshare(flags)?;
+        ns.pid = ns.pid_for_children.clone();
+        ns.pid.attach(pid)?;
+        Ok(ns)
+    }
+
+    /// 让进程离开当前的命名空间，进入按照 `flags` 新建的命名空间
+    ///
+    /// 与 clone 不同，`CLONE_NEWPID` 只影响之后创建的子进程
+    pub fn unshare(&self, flags: CloneFlags) -> AxResult {
+        check_namespace_flags(self, flags)?;
+        let mut ns = self.namespaces.lock();
+        *ns = ns.unshare(flags)?;
+        Ok(())
+    }
+
+    /// 进入给定的命名空间
+    ///
+    /// 进入 PID 命名空间时，目标必须是当前 PID 命名空间或其后代，且只影响之后创建的子进程
+    pub fn setns(&self, target: NsRef) -> AxResult {
+        if !self.cred().capable(Privileges::SYS_ADMIN) {
+            return Err(AxError::PermissionDenied);
+        }
+        let mut ns = self.namespaces.lock();
+        match target {
+            NsRef::Pid(pid_ns) => {
+                if !ns.pid.is_ancestor_of(&pid_ns) {
+                    return Err(AxError::InvalidInput);
+                }
+                ns.pid_for_children = pid_ns;
+            }
+            NsRef::Mnt(mnt_ns) => ns.mnt = mnt_ns,
+            NsRef::Uts(uts_ns) => ns.uts = uts_ns,
+            #[cfg(feature = "net")]
+            NsRef::Net(net_ns) => ns.net = Some(net_ns),
+        }
+        Ok(())
+    }
+
+    /// 进程是否为其 PID 命名空间中的 1 号进程
+    pub fn is_ns_init(&self) -> bool {
+        let pid_ns = self.pid_ns();
+        !pid_ns.is_root() && pid_ns.init_pid() == Some(self.pid())
+    }
+
+    /// 进程退出时接管其子进程的进程，即其 PID 命名空间的 1 号进程
+    ///
+    /// 进程本身就是 1 号进程或处于根命名空间时返回 `None`，此时由内核进程接管
+    pub(crate) fn child_reaper(&self) -> Option<u64> {
+        self.pid_ns().init_pid().filter(|&init| init != self.pid())
+    }
+}
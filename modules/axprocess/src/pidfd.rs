@@ -0,0 +1,78 @@
+//! 指向进程的文件描述符（pidfd）
+//!
+//! 由 `pidfd_open` 或带有 `CLONE_PIDFD` 的 clone 创建。进程退出后文件变为可读，
+//! 可以配合 poll 等待进程退出，也可以作为 `waitid(P_PIDFD)` 的参数。
+extern crate alloc;
+use alloc::sync::Arc;
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axsync::Mutex;
+
+use crate::process::Process;
+
+/// 指向进程的文件描述符
+pub struct PidFd {
+    process: Arc<Process>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl PidFd {
+    /// 创建指向给定进程的文件，`flags` 中只有 `NON_BLOCK` 有意义
+    pub fn new(process: Arc<Process>, flags: OpenFlags) -> Self {
+        Self {
+            process,
+            flags: Mutex::new(flags | OpenFlags::CLOEXEC),
+        }
+    }
+
+    /// 指向的进程
+    pub fn process(&self) -> &Arc<Process> {
+        &self.process
+    }
+
+    /// 是否以非阻塞方式打开，此时 `waitid(P_PIDFD)` 在进程未退出时不等待
+    pub fn is_nonblocking(&self) -> bool {
+        self.flags.lock().contains(OpenFlags::NON_BLOCK)
+    }
+}
+
+impl FileIO for PidFd {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    // 进程退出后文件变为可读
+    fn ready_to_read(&self) -> bool {
+        self.process.get_zombie()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        let mut now = self.flags.lock();
+        now.set(OpenFlags::NON_BLOCK, flags.contains(OpenFlags::NON_BLOCK));
+        true
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+}
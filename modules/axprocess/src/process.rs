@@ -3,7 +3,10 @@ extern crate alloc;
 use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
-use alloc::{collections::BTreeMap, string::String};
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+};
 use axerrno::{AxError, AxResult};
 use axfs::api::{FileIO, OpenFlags};
 use axhal::arch::{write_page_table_root0, TrapFrame};
@@ -16,20 +19,33 @@ use axsync::Mutex;
 use axtask::{current, AxTaskRef, TaskId, TaskInner, RUN_QUEUE};
 use core::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering};
 
+#[cfg(feature = "signal")]
+use crate::coredump::CoreDumpSync;
+use crate::cred::Credentials;
 use crate::fd_manager::FdManager;
-use crate::flags::CloneFlags;
+use crate::flags::{ChildState, CloneFlags};
 use crate::futex::FutexRobustList;
+use crate::job::JobControl;
+use crate::namespace::Namespaces;
+#[cfg(feature = "signal")]
+use crate::ptrace::Ptrace;
+use crate::rlimit::ResourceLimits;
+#[cfg(feature = "signal")]
+use crate::seccomp::Seccomp;
 #[cfg(feature = "signal")]
-use crate::signal::SignalModule;
+use crate::signal::{SignalModule, SignalWait};
 use crate::stdio::{Stderr, Stdin, Stdout};
+use crate::tty::{attach_console, Tty};
+use crate::vdso::map_vdso;
 use crate::{load_app, yield_now_task};
+#[cfg(feature = "signal")]
+use axsignal::signal_no::SignalNo;
 
 /// Map from task id to arc pointer of task
 pub static TID2TASK: Mutex<BTreeMap<u64, AxTaskRef>> = Mutex::new(BTreeMap::new());
 
 /// Map from process id to arc pointer of process
 pub static PID2PC: Mutex<BTreeMap<u64, Arc<Process>>> = Mutex::new(BTreeMap::new());
-const FD_LIMIT_ORIGIN: usize = 1025;
 
 #[cfg(feature = "signal")]
 extern "C" {
@@ -83,6 +99,58 @@ pub struct Process {
 
     /// 该进程可执行文件所在的路径
     pub file_path: Mutex<String>,
+
+    #[cfg(feature = "signal")]
+    /// 进程跟踪模块
+    pub ptrace: Ptrace,
+
+    #[cfg(feature = "signal")]
+    /// 生成 core 文件时与其余线程的同步
+    pub core_dump: CoreDumpSync,
+
+    /// 进程组号
+    pub pgid: AtomicU64,
+
+    /// 会话号
+    pub sid: AtomicU64,
+
+    /// 控制终端
+    pub ctty: Mutex<Option<Arc<Tty>>>,
+
+    /// 导致进程终止的信号，正常退出时为 0
+    pub term_signal: AtomicI32,
+
+    /// 作业控制状态
+    pub job: JobControl,
+
+    /// 用户与用户组身份
+    pub cred: Mutex<Credentials>,
+
+    /// 被信号杀死时是否生成了 core 文件
+    pub core_dumped: AtomicBool,
+
+    /// 资源限制
+    pub rlimits: Mutex<ResourceLimits>,
+
+    /// 被 mlock 锁定的页面
+    pub locked_pages: Mutex<BTreeSet<usize>>,
+
+    /// 加载程序时传入的辅助向量，生成 core 文件时使用
+    pub auxv: Mutex<BTreeMap<u8, usize>>,
+
+    /// 是否设置了 no_new_privs，设置后 exec 不再提升身份，且不能撤销
+    pub no_new_privs: AtomicBool,
+
+    #[cfg(feature = "signal")]
+    /// seccomp 模式与过滤器
+    pub seccomp: Mutex<Seccomp>,
+
+    #[cfg(feature = "signal")]
+    /// 在 sigtimedwait 中等待信号的线程
+    pub signal_wait: SignalWait,
+
+    /// 进程所处的命名空间
+    pub namespaces: Mutex<Namespaces>,
 }
 
 impl Process {
@@ -157,6 +225,83 @@ impl Process {
         (*self.file_path.lock()).clone()
     }
 
+    /// get the process group id
+    pub fn get_pgid(&self) -> u64 {
+        self.pgid.load(Ordering::Acquire)
+    }
+
+    /// set the process group id
+    pub fn set_pgid(&self, pgid: u64) {
+        self.pgid.store(pgid, Ordering::Release)
+    }
+
+    /// get the session id
+    pub fn get_sid(&self) -> u64 {
+        self.sid.load(Ordering::Acquire)
+    }
+
+    /// set the session id
+    pub fn set_sid(&self, sid: u64) {
+        self.sid.store(sid, Ordering::Release)
+    }
+
+    /// whether the process is the leader of its session
+    pub fn is_session_leader(&self) -> bool {
+        self.get_sid() == self.pid
+    }
+
+    /// get the controlling terminal of the process
+    pub fn get_ctty(&self) -> Option<Arc<Tty>> {
+        self.ctty.lock().clone()
+    }
+
+    /// set the controlling terminal of the process
+    pub fn set_ctty(&self, tty: Option<Arc<Tty>>) {
+        *self.ctty.lock() = tty;
+    }
+
+    /// whether the given terminal is the controlling terminal of the process
+    pub fn is_controlling_tty(&self, tty: &Tty) -> bool {
+        self.ctty
+            .lock()
+            .as_ref()
+            .is_some_and(|ctty| core::ptr::eq(Arc::as_ptr(ctty), tty))
+    }
+
+    /// whether the process is stopped by a job control signal
+    pub fn is_stopped(&self) -> bool {
+        self.job.is_stopped()
+    }
+
+    /// get the signal that terminated the process, 0 if it exited normally
+    pub fn get_term_signal(&self) -> i32 {
+        self.term_signal.load(Ordering::Acquire)
+    }
+
+    /// set the signal that terminated the process
+    pub fn set_term_signal(&self, signal: i32) {
+        self.term_signal.store(signal, Ordering::Release)
+    }
+
+    /// 进程被给定的信号杀死时报告给父进程的状态，生成了 core 文件时为 `Dumped`
+    pub fn killed_state(&self, signal: i32) -> ChildState {
+        if self.core_dumped.load(Ordering::Acquire) {
+            ChildState::Dumped(signal)
+        } else {
+            ChildState::Killed(signal)
+        }
+    }
+
+    /// get a copy of the credentials of the process
+    pub fn cred(&self) -> Credentials {
+        self.cred.lock().clone()
+    }
+
+    /// replace the credentials of the process
+    pub fn set_cred(&self, cred: Credentials) {
+        *self.cred.lock() = cred;
+    }
+
     /// 若进程运行完成，则获取其返回码
     /// 若正在运行（可能上锁或没有上锁），则返回None
     pub fn get_code_if_exit(&self) -> Option<i32> {
@@ -165,6 +310,24 @@ impl Process {
         }
         None
     }
+
+    /// 若进程已经结束，则获取 wait 系列调用报告的退出状态
+    pub fn get_exit_state(&self) -> Option<ChildState> {
+        if !self.get_zombie() {
+            return None;
+        }
+        Some(match self.get_term_signal() {
+            0 => ChildState::Exited(self.get_exit_code()),
+            signal => self.killed_state(signal),
+        })
+    }
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        // 进程被回收后才释放其在 PID 命名空间中的进程号
+        self.namespaces.lock().pid.detach(self.pid);
+    }
 }
 
 impl Process {
@@ -186,12 +349,32 @@ impl Process {
             memory_set,
             heap_bottom: AtomicU64::new(heap_bottom),
             heap_top: AtomicU64::new(heap_bottom),
-            fd_manager: FdManager::new(fd_table, FD_LIMIT_ORIGIN),
+            fd_manager: FdManager::new(fd_table),
             #[cfg(feature = "signal")]
             signal_modules: Mutex::new(BTreeMap::new()),
             robust_list: Mutex::new(BTreeMap::new()),
             blocked_by_vfork: Mutex::new(false),
             file_path: Mutex::new(String::new()),
+            #[cfg(feature = "signal")]
+            ptrace: Ptrace::new(),
+            #[cfg(feature = "signal")]
+            core_dump: CoreDumpSync::new(),
+            pgid: AtomicU64::new(pid),
+            sid: AtomicU64::new(pid),
+            ctty: Mutex::new(None),
+            term_signal: AtomicI32::new(0),
+            job: JobControl::new(),
+            cred: Mutex::new(Credentials::root()),
+            core_dumped: AtomicBool::new(false),
+            rlimits: Mutex::new(ResourceLimits::default()),
+            locked_pages: Mutex::new(BTreeSet::new()),
+            auxv: Mutex::new(BTreeMap::new()),
+            no_new_privs: AtomicBool::new(false),
+            #[cfg(feature = "signal")]
+            seccomp: Mutex::new(Seccomp::default()),
+            #[cfg(feature = "signal")]
+            signal_wait: SignalWait::new(),
+            namespaces: Mutex::new(Namespaces::default()),
         }
     }
     /// 根据给定参数创建一个新的进程，作为应用程序初始进程
@@ -223,13 +406,18 @@ impl Process {
             };
         }
 
-        let (entry, user_stack_bottom, heap_bottom) =
-            if let Ok(ans) = load_app(path.clone(), args, envs, &mut memory_set) {
-                ans
-            } else {
-                error!("Failed to load app {}", path);
-                return Err(AxError::NotFound);
-            };
+        let (entry, user_stack_bottom, heap_bottom, auxv) = if let Ok(ans) = load_app(
+            path.clone(),
+            args,
+            envs,
+            &mut memory_set,
+            ResourceLimits::default().user_stack_size(),
+        ) {
+            ans
+        } else {
+            error!("Failed to load app {}", path);
+            return Err(AxError::NotFound);
+        };
         let new_process = Arc::new(Self::new(
             TaskId::new().as_u64(),
             KERNEL_PROCESS_ID,
@@ -250,6 +438,7 @@ impl Process {
                 })),
             ],
         ));
+        *new_process.auxv.lock() = auxv;
         let new_task = TaskInner::new(
             || {},
             path,
@@ -281,6 +470,8 @@ impl Process {
         PID2PC
             .lock()
             .insert(new_process.pid(), Arc::clone(&new_process));
+        // 初始进程是一个新会话的首进程，以控制台为控制终端
+        attach_console(&new_process);
         // 将其作为内核进程的子进程
         match PID2PC.lock().get(&KERNEL_PROCESS_ID) {
             Some(kernel_process) => {
@@ -369,13 +560,18 @@ impl Process {
         } else {
             args
         };
-        let (entry, user_stack_bottom, heap_bottom) =
-            if let Ok(ans) = load_app(name.clone(), args, envs, &mut self.memory_set.lock()) {
-                ans
-            } else {
-                error!("Failed to load app {}", name);
-                return Err(AxError::NotFound);
-            };
+        let (entry, user_stack_bottom, heap_bottom, auxv) = if let Ok(ans) = load_app(
+            name.clone(),
+            args,
+            envs,
+            &mut self.memory_set.lock(),
+            self.rlimits.lock().user_stack_size(),
+        ) {
+            ans
+        } else {
+            error!("Failed to load app {}", name);
+            return Err(AxError::NotFound);
+        };
         // 切换了地址空间， 需要切换token
         let page_table_token = if self.pid == KERNEL_PROCESS_ID {
             0
@@ -388,6 +584,9 @@ impl Process {
             };
             // 清空用户堆，重置堆顶
         }
+        *self.auxv.lock() = auxv;
+        // 内存锁定不会保留到新程序中
+        self.locked_pages.lock().clear();
         // 重置用户堆
         self.set_heap_bottom(heap_bottom.as_usize() as u64);
         self.set_heap_top(heap_bottom.as_usize() as u64);
@@ -448,6 +647,18 @@ impl Process {
         //     // 任务过多，手动特判结束，用来作为QEMU内存不足的应对方法
         //     return Err(AxError::NoMemory);
         // }
+        self.check_nproc()?;
+        // 线程必须与所在进程处于相同的命名空间
+        if flags.contains(CloneFlags::CLONE_THREAD)
+            && flags.intersects(
+                CloneFlags::CLONE_NEWNS
+                    | CloneFlags::CLONE_NEWUTS
+                    | CloneFlags::CLONE_NEWPID
+                    | CloneFlags::CLONE_NEWNET,
+            )
+        {
+            return Err(AxError::InvalidInput);
+        }
         // 是否共享虚拟地址空间
         let new_memory_set = if flags.contains(CloneFlags::CLONE_VM) {
             Arc::clone(&self.memory_set)
@@ -472,6 +683,7 @@ impl Process {
                         | MappingFlags::WRITE,
                 )?;
             }
+            map_vdso(&mut memory_set.lock())?;
             memory_set
         };
 
@@ -483,6 +695,12 @@ impl Process {
             // 新建一个进程，并且设计进程之间的父子关系
             TaskId::new().as_u64()
         };
+        // 新进程的命名空间，同时在 PID 命名空间中分配进程号
+        let namespaces = if flags.contains(CloneFlags::CLONE_THREAD) {
+            None
+        } else {
+            Some(self.clone_namespaces(flags, process_id)?)
+        };
         // 决定父进程是谁
         let parent_id = if flags.contains(CloneFlags::CLONE_PARENT) {
             // 创建兄弟关系，此时以self的父进程作为自己的父进程
@@ -501,6 +719,11 @@ impl Process {
             #[cfg(feature = "signal")]
             sig_child,
         );
+        if flags.contains(CloneFlags::CLONE_THREAD) {
+            // 同一进程中的线程共同计入 RLIMIT_CPU
+            new_task.share_process_cpu_time(&current());
+        }
+        self.apply_cpu_limit(&new_task);
         #[cfg(target_arch = "x86_64")]
         if tls == 0 {
             unsafe {
@@ -508,6 +731,14 @@ impl Process {
             }
         }
         debug!("new task:{}", new_task.id().as_u64());
+        // 线程号与进程号在同一 PID 命名空间中统一编号，线程位于所属进程的命名空间
+        let tid_ns = namespaces
+            .as_ref()
+            .map_or_else(|| self.pid_ns(), |ns| ns.pid.clone());
+        tid_ns.attach(new_task.id().as_u64())?;
+        // 写入父进程与子任务地址空间的线程号，分别为二者所见的编号
+        let parent_tid = self.pid_to_user(new_task.id().as_u64()) as i32;
+        let child_tid = tid_ns.pid_of(new_task.id().as_u64()).unwrap_or(0) as i32;
         TID2TASK
             .lock()
             .insert(new_task.id().as_u64(), Arc::clone(&new_task));
@@ -537,7 +768,7 @@ impl Process {
             & self.manual_alloc_for_lazy(ptid.into()).is_ok()
         {
             unsafe {
-                *(ptid as *mut i32) = new_task.id().as_u64() as i32;
+                *(ptid as *mut i32) = parent_tid;
             }
         }
         // 若包含CLONE_CHILD_SETTID或者CLONE_CHILD_CLEARTID
@@ -560,7 +791,7 @@ impl Process {
                     // 正常分配了地址
                     unsafe {
                         *(ctid as *mut i32) = if flags.contains(CloneFlags::CLONE_CHILD_SETTID) {
-                            new_task.id().as_u64() as i32
+                            child_tid
                         } else {
                             0
                         }
@@ -579,7 +810,7 @@ impl Process {
                         unsafe {
                             *(vaddr as *mut i32) = if flags.contains(CloneFlags::CLONE_CHILD_SETTID)
                             {
-                                new_task.id().as_u64() as i32
+                                child_tid
                             } else {
                                 0
                             }
@@ -599,6 +830,8 @@ impl Process {
         // 若创建的是进程，则返回进程的id
         // 若创建的是线程，则返回线程的id
         let return_id: u64;
+        #[cfg(feature = "signal")]
+        let mut ptrace_event = None;
         // 决定是创建线程还是进程
         if flags.contains(CloneFlags::CLONE_THREAD) {
             // // 若创建的是线程，那么不用新建进程
@@ -628,15 +861,49 @@ impl Process {
                 self.get_heap_bottom(),
                 self.fd_manager.fd_table.lock().clone(),
             ));
+            // 子进程与父进程处于同一进程组与会话，并共享控制终端
+            new_process.set_pgid(self.get_pgid());
+            new_process.set_sid(self.get_sid());
+            new_process.set_ctty(self.get_ctty());
+            new_process.set_cred(self.cred());
+            new_process.set_file_path(self.get_file_path());
+            *new_process.auxv.lock() = self.auxv.lock().clone();
+            *new_process.rlimits.lock() = self.rlimits.lock().clone();
+            *new_process.namespaces.lock() = namespaces.unwrap();
+            // no_new_privs 与 seccomp 过滤器都会被子进程继承
+            new_process
+                .no_new_privs
+                .store(self.no_new_privs.load(Ordering::Acquire), Ordering::Release);
+            #[cfg(feature = "signal")]
+            {
+                *new_process.seccomp.lock() = self.seccomp.lock().clone();
+            }
             // 记录该进程，防止被回收
             PID2PC.lock().insert(process_id, Arc::clone(&new_process));
             new_process.tasks.lock().push(Arc::clone(&new_task));
             // 若是新建了进程，那么需要把进程的父子关系进行记录
             #[cfg(feature = "signal")]
-            new_process.signal_modules.lock().insert(
-                new_task.id().as_u64(),
-                SignalModule::init_signal(Some(new_handler)),
-            );
+            {
+                // 子进程继承父线程的信号掩码与备用信号栈
+                let mut signal_module = SignalModule::init_signal(Some(new_handler));
+                if let Some(parent_module) =
+                    self.signal_modules.lock().get(&current().id().as_u64())
+                {
+                    signal_module.signal_set.mask = parent_module.signal_set.mask;
+                    signal_module.alt_stack = parent_module.alt_stack;
+                }
+                ptrace_event = self.ptrace.inherit(&new_process.ptrace, flags, sig_child);
+                if new_process.ptrace.is_traced() {
+                    // 被自动跟踪的子进程在开始运行时进入信号递送停止
+                    signal_module
+                        .signal_set
+                        .try_add_signal(SignalNo::SIGSTOP as usize);
+                }
+                new_process
+                    .signal_modules
+                    .lock()
+                    .insert(new_task.id().as_u64(), signal_module);
+            }
 
             new_process
                 .robust_list
@@ -677,6 +944,10 @@ impl Process {
         new_task.set_trap_context(trap_frame);
         new_task.set_trap_in_kernel_stack();
         RUN_QUEUE.lock().add_task(new_task);
+        #[cfg(feature = "signal")]
+        if let Some(event) = ptrace_event {
+            crate::ptrace::event_stop(self, event, return_id as usize);
+        }
         // 判断是否为VFORK
         if flags.contains(CloneFlags::CLONE_VFORK) {
             self.set_vfork_block(true);
@@ -710,12 +981,13 @@ impl Process {
 impl Process {
     /// 为进程分配一个文件描述符
     pub fn alloc_fd(&self, fd_table: &mut Vec<Option<Arc<dyn FileIO>>>) -> AxResult<usize> {
-        for (i, fd) in fd_table.iter().enumerate() {
+        let limit = self.fd_limit();
+        for (i, fd) in fd_table.iter().enumerate().take(limit) {
             if fd.is_none() {
                 return Ok(i);
             }
         }
-        if fd_table.len() >= self.fd_manager.get_limit() as usize {
+        if fd_table.len() >= limit {
             debug!("fd table is full");
             return Err(AxError::StorageFull);
         }
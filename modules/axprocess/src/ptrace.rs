@@ -0,0 +1,827 @@
+//! 进程跟踪（ptrace）在内核中的支持
+//!
+//! 跟踪关系以进程为单位建立，停止状态则按线程记录：被跟踪进程中的线程遇到跟踪停止时
+//! 只暂停自身，直到跟踪者通过 `PTRACE_CONT` 等请求恢复它。停止状态通过 `wait4` 报告给跟踪者。
+//!
+//! 会产生跟踪停止的场景：
+//! 1. 信号递送前（信号递送停止）
+//! 2. 以 `PTRACE_SYSCALL` 恢复后，系统调用的进入与返回处（系统调用停止）
+//! 3. 开启对应选项后的 fork/clone/exec 事件，以及 seccomp 过滤器要求通知跟踪者时（事件停止）
+extern crate alloc;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult};
+use axhal::arch::TrapFrame;
+use axhal::mem::{phys_to_virt, VirtAddr, PAGE_SIZE_4K};
+use axsignal::info::{SigInfo, CLD_TRAPPED, SI_KERNEL, SI_USER};
+use axsignal::signal_no::SignalNo;
+use axsync::{Mutex, MutexGuard};
+use axtask::{current, AxTaskRef, WaitQueue};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::current_process;
+use crate::flags::CloneFlags;
+use crate::process::{Process, PID2PC, TID2TASK};
+use crate::signal::{send_signal_info_to_process, send_signal_info_to_thread};
+
+bitflags::bitflags! {
+    /// `PTRACE_SETOPTIONS` 设置的选项
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct PtraceOptions: u32 {
+        /// 系统调用停止时报告 `SIGTRAP | 0x80`
+        const TRACESYSGOOD = 1;
+        /// 跟踪 fork 产生的子进程
+        const TRACEFORK = 1 << 1;
+        /// 跟踪 vfork 产生的子进程
+        const TRACEVFORK = 1 << 2;
+        /// 跟踪 clone 产生的子进程
+        const TRACECLONE = 1 << 3;
+        /// exec 时产生事件停止，而非发送 SIGTRAP
+        const TRACEEXEC = 1 << 4;
+        /// vfork 的子进程释放父进程时产生事件停止
+        const TRACEVFORKDONE = 1 << 5;
+        /// 进程退出前产生事件停止
+        const TRACEEXIT = 1 << 6;
+        /// seccomp 返回 SECCOMP_RET_TRACE 时产生事件停止
+        const TRACESECCOMP = 1 << 7;
+        /// 跟踪者退出时杀死被跟踪进程
+        const EXITKILL = 1 << 20;
+    }
+}
+
+/// fork 事件
+pub const PTRACE_EVENT_FORK: u32 = 1;
+/// vfork 事件
+pub const PTRACE_EVENT_VFORK: u32 = 2;
+/// clone 事件
+pub const PTRACE_EVENT_CLONE: u32 = 3;
+/// exec 事件
+pub const PTRACE_EVENT_EXEC: u32 = 4;
+/// seccomp 过滤器返回 `SECCOMP_RET_TRACE` 的事件
+pub const PTRACE_EVENT_SECCOMP: u32 = 7;
+
+/// 被跟踪进程恢复运行的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResumeMode {
+    /// 正常运行，直到下一次信号递送停止
+    #[default]
+    Continue,
+    /// 在系统调用的进入与返回处停止
+    Syscall,
+    /// 执行一条指令后停止
+    SingleStep,
+}
+
+/// 一次跟踪停止
+#[derive(Clone, Copy)]
+pub struct PtraceStop {
+    /// 停止的线程
+    pub tid: u64,
+    /// 通过 wait4 报告的状态
+    pub status: i32,
+    /// 导致停止的信号信息，可以通过 `PTRACE_GETSIGINFO` 与 `PTRACE_SETSIGINFO` 访问
+    pub info: SigInfo,
+    /// 是否已经通过 wait4 报告给跟踪者
+    pub reported: bool,
+}
+
+/// 被跟踪进程中单个线程的跟踪状态
+#[derive(Default)]
+pub struct ThreadTrace {
+    /// 恢复运行的方式
+    pub mode: ResumeMode,
+    /// 当前的跟踪停止
+    pub stop: Option<PtraceStop>,
+    /// 恢复运行时要递送的信号，0 代表不递送
+    pub resume_signal: usize,
+    /// `PTRACE_GETEVENTMSG` 返回的内容
+    pub event_msg: usize,
+    /// 处于系统调用停止之间时，当前系统调用的编号
+    pub syscall_nr: Option<usize>,
+    /// 是否处于跟踪停止，供等待队列在持有 `RUN_QUEUE` 时检查
+    stopped: Arc<AtomicBool>,
+}
+
+impl ThreadTrace {
+    /// 是否处于跟踪停止
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+}
+
+/// 进程的跟踪状态
+pub struct PtraceState {
+    /// 跟踪者的进程号
+    pub tracer: Option<u64>,
+    /// 跟踪选项
+    pub options: PtraceOptions,
+    /// 各个线程的跟踪状态，以线程号为键
+    pub threads: BTreeMap<u64, ThreadTrace>,
+}
+
+impl PtraceState {
+    /// 获取给定线程的跟踪状态，不存在时新建
+    pub fn thread(&mut self, tid: u64) -> &mut ThreadTrace {
+        self.threads.entry(tid).or_default()
+    }
+
+    /// 选出跟踪请求作用的停止线程
+    ///
+    /// 给定的线程处于跟踪停止时选择它，否则以进程号指代进程时选择任意一个停止的线程
+    pub fn stopped_thread(&self, tid: u64) -> Option<u64> {
+        match self.threads.get(&tid) {
+            Some(thread) if thread.is_stopped() => Some(tid),
+            _ => self
+                .threads
+                .iter()
+                .find(|(_, thread)| thread.is_stopped())
+                .map(|(&tid, _)| tid),
+        }
+    }
+}
+
+/// 进程的跟踪模块
+pub struct Ptrace {
+    state: Mutex<PtraceState>,
+    wait_queue: WaitQueue,
+}
+
+impl Default for Ptrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ptrace {
+    /// 创建未被跟踪的跟踪模块
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(PtraceState {
+                tracer: None,
+                options: PtraceOptions::empty(),
+                threads: BTreeMap::new(),
+            }),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// 获取跟踪状态
+    pub fn state(&self) -> MutexGuard<PtraceState> {
+        self.state.lock()
+    }
+
+    /// 是否正在被跟踪
+    pub fn is_traced(&self) -> bool {
+        self.state.lock().tracer.is_some()
+    }
+
+    /// 跟踪者的进程号
+    pub fn tracer(&self) -> Option<u64> {
+        self.state.lock().tracer
+    }
+
+    /// 是否有线程处于跟踪停止
+    pub fn is_stopped(&self) -> bool {
+        self.state
+            .lock()
+            .threads
+            .values()
+            .any(|thread| thread.is_stopped())
+    }
+
+    /// 建立跟踪关系，若已经被跟踪则返回 `PermissionDenied`
+    pub fn attach(&self, tracer: u64, options: PtraceOptions) -> AxResult<()> {
+        let mut state = self.state.lock();
+        if state.tracer.is_some() {
+            return Err(AxError::PermissionDenied);
+        }
+        state.tracer = Some(tracer);
+        state.options = options;
+        for thread in state.threads.values_mut() {
+            thread.mode = ResumeMode::Continue;
+            thread.event_msg = 0;
+        }
+        Ok(())
+    }
+
+    /// 解除跟踪关系，处于跟踪停止的线程都以给定的信号恢复运行
+    pub fn detach(&self, signal: usize) {
+        let mut state = self.state.lock();
+        state.tracer = None;
+        state.options = PtraceOptions::empty();
+        for thread in state.threads.values_mut() {
+            thread.mode = ResumeMode::Continue;
+            thread.syscall_nr = None;
+        }
+        drop(state);
+        self.resume_all(signal);
+    }
+
+    /// 恢复处于跟踪停止的线程，若线程没有停止则返回 `BadState`
+    pub fn resume(&self, tid: u64, mode: ResumeMode, signal: usize) -> AxResult<()> {
+        let mut state = self.state.lock();
+        let thread = state
+            .threads
+            .get_mut(&tid)
+            .filter(|thread| thread.is_stopped())
+            .ok_or(AxError::BadState)?;
+        thread.mode = mode;
+        thread.resume_signal = signal;
+        thread.stopped.store(false, Ordering::Release);
+        drop(state);
+        self.wait_queue.notify_all(false);
+        Ok(())
+    }
+
+    /// 以给定的信号恢复所有处于跟踪停止的线程
+    fn resume_all(&self, signal: usize) {
+        let mut state = self.state.lock();
+        for thread in state.threads.values_mut() {
+            if thread.is_stopped() {
+                thread.mode = ResumeMode::Continue;
+                thread.resume_signal = signal;
+                thread.stopped.store(false, Ordering::Release);
+            }
+        }
+        drop(state);
+        self.wait_queue.notify_all(false);
+    }
+
+    /// 被跟踪进程收到 SIGKILL 时，无论是否停止都要让其继续运行以便退出
+    pub fn wake_for_kill(&self) {
+        self.resume_all(0);
+    }
+
+    /// 线程退出时删除其跟踪状态
+    pub fn remove_thread(&self, tid: u64) {
+        self.state.lock().threads.remove(&tid);
+    }
+
+    /// 取出一个还没有报告给跟踪者的停止，返回停止的线程与 wait 状态
+    ///
+    /// `consume` 为假时只查看而不将其标记为已报告
+    pub fn take_unreported_stop(&self, consume: bool) -> Option<(u64, i32)> {
+        let mut state = self.state.lock();
+        state
+            .threads
+            .values_mut()
+            .filter(|thread| thread.is_stopped())
+            .filter_map(|thread| thread.stop.as_mut())
+            .find(|stop| !stop.reported)
+            .map(|stop| {
+                stop.reported = consume;
+                (stop.tid, stop.status)
+            })
+    }
+
+    /// 子进程创建时决定是否跟踪子进程
+    ///
+    /// 子进程需要被跟踪时为其建立与父进程相同的跟踪关系。若父进程需要报告事件停止，返回对应的事件
+    pub fn inherit(&self, child: &Ptrace, flags: CloneFlags, sig_child: bool) -> Option<u32> {
+        let state = self.state.lock();
+        let tracer = state.tracer?;
+        if flags.contains(CloneFlags::CLONE_UNTRACED) {
+            return None;
+        }
+        let (event, option) = if flags.contains(CloneFlags::CLONE_VFORK) {
+            (PTRACE_EVENT_VFORK, PtraceOptions::TRACEVFORK)
+        } else if sig_child {
+            (PTRACE_EVENT_FORK, PtraceOptions::TRACEFORK)
+        } else {
+            (PTRACE_EVENT_CLONE, PtraceOptions::TRACECLONE)
+        };
+        let report = state.options.contains(option);
+        if report || flags.contains(CloneFlags::CLONE_PTRACE) {
+            let _ = child.attach(tracer, state.options);
+        }
+        report.then_some(event)
+    }
+}
+
+/// 当前线程进入跟踪停止，直到跟踪者将其恢复
+///
+/// 返回跟踪者恢复时要求递送的信号。若恢复时的信号与停止时的信号相同，则保留原有的信号信息
+fn stop_current(process: &Process, status: i32, info: SigInfo) -> Option<SigInfo> {
+    let tid = current().id().as_u64();
+    let (tracer, stopped) = {
+        let mut state = process.ptrace.state.lock();
+        let tracer = state.tracer?;
+        let thread = state.thread(tid);
+        thread.stop = Some(PtraceStop {
+            tid,
+            status,
+            info,
+            reported: false,
+        });
+        thread.resume_signal = 0;
+        thread.stopped.store(true, Ordering::Release);
+        (tracer, Arc::clone(&thread.stopped))
+    };
+    // 通知跟踪者，其在 wait4 中会发现这次停止，si_pid 是跟踪者所见的进程号
+    let si_pid = PID2PC
+        .lock()
+        .get(&tracer)
+        .map_or(0, |tracer| tracer.pid_to_user(process.pid()));
+    let _ = send_signal_info_to_process(
+        tracer as isize,
+        SigInfo::from_child(CLD_TRAPPED, si_pid as u32, 0, (status >> 8) & 0xff),
+    );
+    process
+        .ptrace
+        .wait_queue
+        .wait_until(|| !stopped.load(Ordering::Acquire));
+
+    let mut state = process.ptrace.state.lock();
+    let thread = state.thread(tid);
+    let stop = thread.stop.take();
+    let signal = core::mem::take(&mut thread.resume_signal);
+    if signal == 0 {
+        return None;
+    }
+    match stop {
+        Some(stop) if stop.info.si_signo as usize == signal => Some(stop.info),
+        _ => Some(SigInfo::new(signal as i32, SI_USER)),
+    }
+}
+
+/// 将跟踪者在恢复时要求递送的信号发送给当前线程
+fn deliver_resume_signal(info: Option<SigInfo>) {
+    if let Some(info) = info {
+        let _ = send_signal_info_to_thread(current().id().as_u64() as isize, info);
+    }
+}
+
+/// 被跟踪进程递送信号前的信号递送停止
+///
+/// 返回实际需要递送的信号，`None` 代表跟踪者取消了这个信号
+pub fn signal_stop(process: &Process, info: SigInfo) -> Option<SigInfo> {
+    if !process.ptrace.is_traced() {
+        return Some(info);
+    }
+    stop_current(process, (info.si_signo << 8) | 0x7f, info)
+}
+
+/// 系统调用停止时报告的状态
+fn syscall_stop_status(process: &Process) -> i32 {
+    let mut signal = SignalNo::SIGTRAP as i32;
+    if process
+        .ptrace
+        .state()
+        .options
+        .contains(PtraceOptions::TRACESYSGOOD)
+    {
+        signal |= 0x80;
+    }
+    (signal << 8) | 0x7f
+}
+
+/// 系统调用进入时的跟踪停止
+///
+/// 返回跟踪者修改后的系统调用号与参数。若系统调用号被改为 -1，则返回 `None`，代表跳过这次调用
+pub fn syscall_enter(syscall_id: usize, args: [usize; 6]) -> Option<(usize, [usize; 6])> {
+    let process = current_process();
+    let tid = current().id().as_u64();
+    {
+        let mut state = process.ptrace.state();
+        if state.tracer.is_none() {
+            return Some((syscall_id, args));
+        }
+        let thread = state.thread(tid);
+        if thread.mode != ResumeMode::Syscall {
+            return Some((syscall_id, args));
+        }
+        thread.syscall_nr = Some(syscall_id);
+    }
+    let trap_frame = unsafe { &mut *current().get_first_trap_frame() };
+    // 与 Linux 一致，进入时的返回值寄存器为 -ENOSYS
+    #[cfg(target_arch = "x86_64")]
+    trap_frame.set_ret_code(-(axerrno::LinuxError::ENOSYS.code() as isize) as usize);
+    let status = syscall_stop_status(&process);
+    let info = SigInfo::new(SignalNo::SIGTRAP as i32, SI_KERNEL);
+    deliver_resume_signal(stop_current(&process, status, info));
+
+    let syscall_nr = process
+        .ptrace
+        .state()
+        .thread(tid)
+        .syscall_nr
+        .unwrap_or(syscall_id);
+    let (syscall_id, args) = syscall_args(trap_frame, syscall_nr);
+    if syscall_id == usize::MAX {
+        None
+    } else {
+        Some((syscall_id, args))
+    }
+}
+
+/// 系统调用返回时的跟踪停止
+///
+/// 返回跟踪者修改后的返回值
+pub fn syscall_exit(ret: isize) -> isize {
+    let process = current_process();
+    let tid = current().id().as_u64();
+    {
+        let mut state = process.ptrace.state();
+        let traced = state.tracer.is_some();
+        let Some(thread) = state.threads.get_mut(&tid) else {
+            return ret;
+        };
+        if thread.syscall_nr.is_none() {
+            return ret;
+        }
+        if !traced || thread.mode != ResumeMode::Syscall {
+            thread.syscall_nr = None;
+            return ret;
+        }
+    }
+    let trap_frame = unsafe { &mut *current().get_first_trap_frame() };
+    trap_frame.set_ret_code(ret as usize);
+    let status = syscall_stop_status(&process);
+    let info = SigInfo::new(SignalNo::SIGTRAP as i32, SI_KERNEL);
+    deliver_resume_signal(stop_current(&process, status, info));
+    process.ptrace.state().thread(tid).syscall_nr = None;
+    trap_frame.get_ret_code() as isize
+}
+
+/// 在当前线程上报告事件停止
+pub fn event_stop(process: &Process, event: u32, msg: usize) {
+    {
+        let mut state = process.ptrace.state();
+        if state.tracer.is_none() {
+            return;
+        }
+        state.thread(current().id().as_u64()).event_msg = msg;
+    }
+    let status = ((SignalNo::SIGTRAP as i32 | (event << 8) as i32) << 8) | 0x7f;
+    let info = SigInfo::new(SignalNo::SIGTRAP as i32, SI_KERNEL);
+    deliver_resume_signal(stop_current(process, status, info));
+}
+
+/// seccomp 过滤器返回 `SECCOMP_RET_TRACE` 时的事件停止，`data` 为返回值中携带的数据
+///
+/// 没有跟踪者或跟踪者未开启 `PTRACE_O_TRACESECCOMP` 时返回 `None`，
+/// 否则返回跟踪者修改后的系统调用号与参数。系统调用号被改为 -1 时代表跳过这次调用
+pub fn seccomp_event(
+    process: &Process,
+    syscall_id: usize,
+    data: u32,
+) -> Option<(usize, [usize; 6])> {
+    let tid = current().id().as_u64();
+    // 已经处于系统调用停止之间时，系统调用号仍需留给返回时的停止
+    let in_syscall = {
+        let mut state = process.ptrace.state();
+        if state.tracer.is_none() || !state.options.contains(PtraceOptions::TRACESECCOMP) {
+            return None;
+        }
+        // 停止期间跟踪者可以通过修改寄存器改变系统调用号
+        state.thread(tid).syscall_nr.replace(syscall_id).is_some()
+    };
+    let trap_frame = unsafe { &mut *current().get_first_trap_frame() };
+    #[cfg(target_arch = "x86_64")]
+    trap_frame.set_ret_code(-(axerrno::LinuxError::ENOSYS.code() as isize) as usize);
+    event_stop(process, PTRACE_EVENT_SECCOMP, data as usize);
+
+    let syscall_nr = {
+        let mut state = process.ptrace.state();
+        let thread = state.thread(tid);
+        if in_syscall {
+            thread.syscall_nr
+        } else {
+            thread.syscall_nr.take()
+        }
+    };
+    Some(syscall_args(trap_frame, syscall_nr.unwrap_or(syscall_id)))
+}
+
+/// exec 成功后的处理
+///
+/// 开启了 `PTRACE_O_TRACEEXEC` 时产生事件停止，否则向自身发送 SIGTRAP
+pub fn exec_event(process: &Process) {
+    let options = {
+        let state = process.ptrace.state();
+        if state.tracer.is_none() {
+            return;
+        }
+        state.options
+    };
+    let tid = current().id().as_u64();
+    if options.contains(PtraceOptions::TRACEEXEC) {
+        event_stop(process, PTRACE_EVENT_EXEC, tid as usize);
+    } else {
+        let info = SigInfo::new(SignalNo::SIGTRAP as i32, SI_USER);
+        let _ = send_signal_info_to_thread(tid as isize, info);
+    }
+}
+
+/// 跟踪者退出时解除其所有跟踪关系
+///
+/// 设置了 `PTRACE_O_EXITKILL` 的被跟踪进程会被杀死
+pub fn release_tracees(tracer: u64) {
+    let tracees: Vec<Arc<Process>> = PID2PC
+        .lock()
+        .values()
+        .filter(|process| process.ptrace.tracer() == Some(tracer))
+        .cloned()
+        .collect();
+    for tracee in tracees {
+        let exit_kill = tracee
+            .ptrace
+            .state()
+            .options
+            .contains(PtraceOptions::EXITKILL);
+        tracee.ptrace.detach(0);
+        if exit_kill {
+            let info = SigInfo::new(SignalNo::SIGKILL as i32, SI_KERNEL);
+            let _ = send_signal_info_to_process(tracee.pid() as isize, info);
+        }
+    }
+}
+
+/// 根据线程号找到被给定跟踪者跟踪的进程
+pub fn find_tracee(tid: u64, tracer: u64) -> AxResult<Arc<Process>> {
+    let process = find_process_by_tid(tid)?;
+    if process.ptrace.tracer() != Some(tracer) {
+        return Err(AxError::NotFound);
+    }
+    Ok(process)
+}
+
+/// 根据线程号找到所在的进程
+pub fn find_process_by_tid(tid: u64) -> AxResult<Arc<Process>> {
+    let pid = match TID2TASK.lock().get(&tid) {
+        Some(task) => task.get_process_id(),
+        None => tid,
+    };
+    PID2PC.lock().get(&pid).cloned().ok_or(AxError::NotFound)
+}
+
+/// 获取跟踪请求作用的处于跟踪停止的线程，选择方式见 [`PtraceState::stopped_thread`]
+///
+/// 线程保持停止期间，可以安全地访问其内核栈上的 trap 上下文
+pub fn stopped_task(process: &Process, tid: u64) -> AxResult<AxTaskRef> {
+    let tid = process
+        .ptrace
+        .state()
+        .stopped_thread(tid)
+        .ok_or(AxError::BadState)?;
+    TID2TASK.lock().get(&tid).cloned().ok_or(AxError::NotFound)
+}
+
+/// 读写被跟踪进程地址空间中的数据
+///
+/// 通过页表查询到物理地址后经由内核的线性映射访问，因此可以写入只读的代码段（如设置断点）
+pub fn access_memory(process: &Process, addr: usize, buf: &mut [u8], write: bool) -> AxResult<()> {
+    let mut memory_set = process.memory_set.lock();
+    let mut done = 0;
+    while done < buf.len() {
+        let vaddr = VirtAddr::from(addr + done);
+        memory_set.manual_alloc_for_lazy(vaddr)?;
+        let (paddr, _, _) = memory_set.query(vaddr)?;
+        let len = (buf.len() - done).min(PAGE_SIZE_4K - vaddr.as_usize() % PAGE_SIZE_4K);
+        let kernel_ptr = phys_to_virt(paddr).as_mut_ptr();
+        unsafe {
+            if write {
+                core::ptr::copy_nonoverlapping(buf[done..].as_ptr(), kernel_ptr, len);
+            } else {
+                core::ptr::copy_nonoverlapping(kernel_ptr, buf[done..].as_mut_ptr(), len);
+            }
+        }
+        done += len;
+    }
+    Ok(())
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "x86_64")] {
+        /// `PTRACE_GETREGS` 使用的寄存器布局，与 Linux 的 `user_regs_struct` 一致
+        #[repr(C)]
+        #[derive(Debug, Default, Clone, Copy)]
+        #[allow(missing_docs)]
+        pub struct UserRegs {
+            pub r15: u64,
+            pub r14: u64,
+            pub r13: u64,
+            pub r12: u64,
+            pub rbp: u64,
+            pub rbx: u64,
+            pub r11: u64,
+            pub r10: u64,
+            pub r9: u64,
+            pub r8: u64,
+            pub rax: u64,
+            pub rcx: u64,
+            pub rdx: u64,
+            pub rsi: u64,
+            pub rdi: u64,
+            pub orig_rax: u64,
+            pub rip: u64,
+            pub cs: u64,
+            pub eflags: u64,
+            pub rsp: u64,
+            pub ss: u64,
+            pub fs_base: u64,
+            pub gs_base: u64,
+            pub ds: u64,
+            pub es: u64,
+            pub fs: u64,
+            pub gs: u64,
+        }
+
+        /// 用户可以修改的标志位：CF、PF、AF、ZF、SF、TF、DF、OF、RF、AC
+        const USER_RFLAGS_MASK: u64 = 0x50dd5;
+        /// 单步执行标志
+        const RFLAGS_TF: u64 = 1 << 8;
+
+        impl UserRegs {
+            /// 从 trap 上下文构造，`syscall_nr` 为处于系统调用停止时的系统调用号
+            pub fn from_trap_frame(tf: &TrapFrame, syscall_nr: Option<usize>, tls: usize) -> Self {
+                Self {
+                    r15: tf.r15,
+                    r14: tf.r14,
+                    r13: tf.r13,
+                    r12: tf.r12,
+                    rbp: tf.rbp,
+                    rbx: tf.rbx,
+                    r11: tf.r11,
+                    r10: tf.r10,
+                    r9: tf.r9,
+                    r8: tf.r8,
+                    rax: tf.rax,
+                    rcx: tf.rcx,
+                    rdx: tf.rdx,
+                    rsi: tf.rsi,
+                    rdi: tf.rdi,
+                    orig_rax: syscall_nr.map_or(u64::MAX, |nr| nr as u64),
+                    rip: tf.rip,
+                    cs: tf.cs,
+                    eflags: tf.rflags,
+                    rsp: tf.rsp,
+                    ss: tf.ss,
+                    fs_base: tls as u64,
+                    ..Default::default()
+                }
+            }
+
+            /// 将寄存器写回 trap 上下文，段寄存器与特权标志位保持不变
+            pub fn apply(&self, tf: &mut TrapFrame) {
+                tf.r15 = self.r15;
+                tf.r14 = self.r14;
+                tf.r13 = self.r13;
+                tf.r12 = self.r12;
+                tf.rbp = self.rbp;
+                tf.rbx = self.rbx;
+                tf.r11 = self.r11;
+                tf.r10 = self.r10;
+                tf.r9 = self.r9;
+                tf.r8 = self.r8;
+                tf.rax = self.rax;
+                tf.rcx = self.rcx;
+                tf.rdx = self.rdx;
+                tf.rsi = self.rsi;
+                tf.rdi = self.rdi;
+                tf.rip = self.rip;
+                tf.rflags = (tf.rflags & !USER_RFLAGS_MASK) | (self.eflags & USER_RFLAGS_MASK);
+                tf.rsp = self.rsp;
+            }
+
+            /// 处于系统调用停止时被修改后的系统调用号
+            pub fn syscall_nr(&self) -> usize {
+                self.orig_rax as usize
+            }
+        }
+
+        /// 从 trap 上下文中取出系统调用号与参数
+        fn syscall_args(tf: &TrapFrame, syscall_nr: usize) -> (usize, [usize; 6]) {
+            (
+                syscall_nr,
+                [
+                    tf.rdi as usize,
+                    tf.rsi as usize,
+                    tf.rdx as usize,
+                    tf.r10 as usize,
+                    tf.r8 as usize,
+                    tf.r9 as usize,
+                ],
+            )
+        }
+
+        /// 设置单步执行，返回当前架构是否支持
+        pub fn set_single_step(tf: &mut TrapFrame, enable: bool) -> bool {
+            if enable {
+                tf.rflags |= RFLAGS_TF;
+            } else {
+                tf.rflags &= !RFLAGS_TF;
+            }
+            true
+        }
+    } else if #[cfg(any(target_arch = "riscv32", target_arch = "riscv64"))] {
+        use axhal::arch::GeneralRegisters;
+
+        /// `PTRACE_GETREGSET` 使用的寄存器布局，与 Linux 的 `user_regs_struct` 一致
+        #[repr(C)]
+        #[derive(Debug, Default, Clone, Copy)]
+        pub struct UserRegs {
+            /// 用户态 pc
+            pub pc: usize,
+            /// x1 ~ x31
+            pub regs: GeneralRegisters,
+        }
+
+        impl UserRegs {
+            /// 从 trap 上下文构造
+            pub fn from_trap_frame(tf: &TrapFrame, _syscall_nr: Option<usize>, _tls: usize) -> Self {
+                Self {
+                    pc: tf.sepc,
+                    regs: tf.regs,
+                }
+            }
+
+            /// 将寄存器写回 trap 上下文
+            pub fn apply(&self, tf: &mut TrapFrame) {
+                tf.sepc = self.pc;
+                tf.regs = self.regs;
+            }
+
+            /// 处于系统调用停止时被修改后的系统调用号
+            pub fn syscall_nr(&self) -> usize {
+                self.regs.a7
+            }
+        }
+
+        /// 从 trap 上下文中取出系统调用号与参数
+        fn syscall_args(tf: &TrapFrame, _syscall_nr: usize) -> (usize, [usize; 6]) {
+            let regs = &tf.regs;
+            (regs.a7, [regs.a0, regs.a1, regs.a2, regs.a3, regs.a4, regs.a5])
+        }
+
+        /// 设置单步执行，返回当前架构是否支持
+        ///
+        /// RISC-V 没有硬件单步执行
+        pub fn set_single_step(_tf: &mut TrapFrame, enable: bool) -> bool {
+            !enable
+        }
+    } else if #[cfg(target_arch = "aarch64")] {
+        /// `PTRACE_GETREGSET` 使用的寄存器布局，与 Linux 的 `user_pt_regs` 一致
+        #[repr(C)]
+        #[derive(Debug, Default, Clone, Copy)]
+        pub struct UserRegs {
+            /// x0 ~ x30
+            pub regs: [usize; 31],
+            /// 用户栈指针
+            pub sp: usize,
+            /// 用户态 pc
+            pub pc: usize,
+            /// 处理器状态
+            pub pstate: usize,
+        }
+
+        /// 用户可以修改的 NZCV 标志位
+        const USER_PSTATE_MASK: usize = 0xf000_0000;
+
+        impl UserRegs {
+            /// 从 trap 上下文构造
+            pub fn from_trap_frame(tf: &TrapFrame, _syscall_nr: Option<usize>, _tls: usize) -> Self {
+                Self {
+                    regs: tf.r,
+                    sp: tf.usp,
+                    pc: tf.elr,
+                    pstate: tf.spsr,
+                }
+            }
+
+            /// 将寄存器写回 trap 上下文，特权状态保持不变
+            pub fn apply(&self, tf: &mut TrapFrame) {
+                tf.r = self.regs;
+                tf.usp = self.sp;
+                tf.elr = self.pc;
+                tf.spsr = (tf.spsr & !USER_PSTATE_MASK) | (self.pstate & USER_PSTATE_MASK);
+            }
+
+            /// 处于系统调用停止时被修改后的系统调用号
+            pub fn syscall_nr(&self) -> usize {
+                self.regs[8]
+            }
+        }
+
+        /// 从 trap 上下文中取出系统调用号与参数
+        fn syscall_args(tf: &TrapFrame, _syscall_nr: usize) -> (usize, [usize; 6]) {
+            (tf.r[8], [tf.r[0], tf.r[1], tf.r[2], tf.r[3], tf.r[4], tf.r[5]])
+        }
+
+        /// SPSR.SS，返回用户态后执行一条指令即触发单步异常
+        const SPSR_SS: usize = 1 << 21;
+
+        /// 设置单步执行，返回当前架构是否支持
+        ///
+        /// 返回用户态时 axhal 会按照 SPSR.SS 设置 MDSCR_EL1.SS
+        pub fn set_single_step(tf: &mut TrapFrame, enable: bool) -> bool {
+            if enable {
+                tf.spsr |= SPSR_SS;
+            } else {
+                tf.spsr &= !SPSR_SS;
+            }
+            true
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! 进程的资源限制
+//!
+//! 每个进程保存一整套 `RLIMIT_*`，fork 时由子进程继承，exec 后保持不变。各项限制的生效位置：
+//!
+//! - RLIMIT_CPU：按进程中所有线程的 CPU 时间之和检查，超过软上限发送 SIGXCPU，超过硬上限发送 SIGKILL
+//! - RLIMIT_FSIZE：写入或截断普通文件时检查，超出时发送 SIGXFSZ
+//! - RLIMIT_STACK：exec 时决定用户栈的大小
+//! - RLIMIT_CORE：生成 core 文件时的大小上限
+//! - RLIMIT_NPROC：clone 时限制同一用户的任务数
+//! - RLIMIT_NOFILE：分配文件描述符时检查
+//! - RLIMIT_MEMLOCK：mlock 时检查锁定的内存总量。内存不会被换出，锁定只记录页面并提前分配物理页
+//!
+//! 其余限制只保存，不生效。
+use axconfig::MAX_USER_STACK_SIZE;
+use axerrno::{AxError, AxResult};
+use axhal::mem::PAGE_SIZE_4K;
+
+use crate::cred::Privileges;
+use crate::{current_process, Process, PID2PC};
+
+/// CPU 时间，单位为秒
+pub const RLIMIT_CPU: usize = 0;
+/// 可以创建的文件的最大大小
+pub const RLIMIT_FSIZE: usize = 1;
+/// 数据段的最大大小
+pub const RLIMIT_DATA: usize = 2;
+/// 用户栈的最大大小
+pub const RLIMIT_STACK: usize = 3;
+/// core 文件的最大大小
+pub const RLIMIT_CORE: usize = 4;
+/// 常驻内存的最大大小
+pub const RLIMIT_RSS: usize = 5;
+/// 同一真实用户可以拥有的任务数
+pub const RLIMIT_NPROC: usize = 6;
+/// 文件描述符的最大值加一
+pub const RLIMIT_NOFILE: usize = 7;
+/// 可以锁定的内存的最大大小
+pub const RLIMIT_MEMLOCK: usize = 8;
+/// 地址空间的最大大小
+pub const RLIMIT_AS: usize = 9;
+/// 文件锁的数目
+pub const RLIMIT_LOCKS: usize = 10;
+/// 可以排队的信号数目
+pub const RLIMIT_SIGPENDING: usize = 11;
+/// POSIX 消息队列的最大字节数
+pub const RLIMIT_MSGQUEUE: usize = 12;
+/// nice 值的上限
+pub const RLIMIT_NICE: usize = 13;
+/// 实时优先级的上限
+pub const RLIMIT_RTPRIO: usize = 14;
+/// 实时任务不阻塞地运行的最长时间，单位为微秒
+pub const RLIMIT_RTTIME: usize = 15;
+/// 资源限制的种类数
+pub const RLIM_NLIMITS: usize = 16;
+
+/// 不限制
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// 文件描述符数目的默认软上限
+pub const NOFILE_DEFAULT: u64 = 1025;
+/// 文件描述符数目的默认硬上限
+const NOFILE_DEFAULT_MAX: u64 = 4096;
+/// 文件描述符数目的硬上限所能达到的最大值
+const NR_OPEN: u64 = 1 << 20;
+/// 同一用户任务数的默认上限
+const NPROC_DEFAULT: u64 = 4096;
+/// 可以锁定的内存的默认上限
+const MEMLOCK_DEFAULT: u64 = 8 * 1024 * 1024;
+/// 用户栈的默认上限
+const STACK_DEFAULT: u64 = 8 * 1024 * 1024;
+/// POSIX 消息队列的默认上限
+const MSGQUEUE_DEFAULT: u64 = 819200;
+/// 排队信号数目的默认上限
+const SIGPENDING_DEFAULT: u64 = 1024;
+
+/// 一项资源限制，布局与 Linux 的 `struct rlimit` 一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RLimit {
+    /// 软上限
+    pub rlim_cur: u64,
+    /// 硬上限
+    pub rlim_max: u64,
+}
+
+impl RLimit {
+    /// 新建一项资源限制
+    pub const fn new(rlim_cur: u64, rlim_max: u64) -> Self {
+        Self { rlim_cur, rlim_max }
+    }
+
+    /// 不限制
+    pub const fn infinity() -> Self {
+        Self::new(RLIM_INFINITY, RLIM_INFINITY)
+    }
+}
+
+/// 进程的全部资源限制
+#[derive(Clone, Debug)]
+pub struct ResourceLimits([RLimit; RLIM_NLIMITS]);
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        let mut limits = [RLimit::infinity(); RLIM_NLIMITS];
+        limits[RLIMIT_STACK] = RLimit::new(STACK_DEFAULT, RLIM_INFINITY);
+        limits[RLIMIT_CORE] = RLimit::new(0, RLIM_INFINITY);
+        limits[RLIMIT_NPROC] = RLimit::new(NPROC_DEFAULT, NPROC_DEFAULT);
+        limits[RLIMIT_NOFILE] = RLimit::new(NOFILE_DEFAULT, NOFILE_DEFAULT_MAX);
+        limits[RLIMIT_MEMLOCK] = RLimit::new(MEMLOCK_DEFAULT, MEMLOCK_DEFAULT);
+        limits[RLIMIT_SIGPENDING] = RLimit::new(SIGPENDING_DEFAULT, SIGPENDING_DEFAULT);
+        limits[RLIMIT_MSGQUEUE] = RLimit::new(MSGQUEUE_DEFAULT, MSGQUEUE_DEFAULT);
+        limits[RLIMIT_NICE] = RLimit::new(0, 0);
+        limits[RLIMIT_RTPRIO] = RLimit::new(0, 0);
+        Self(limits)
+    }
+}
+
+impl ResourceLimits {
+    /// 获取一项资源限制
+    pub fn get(&self, resource: usize) -> RLimit {
+        self.0[resource]
+    }
+
+    /// exec 时用户栈的大小，由 RLIMIT_STACK 决定，不超过地址空间布局预留的大小
+    pub fn user_stack_size(&self) -> usize {
+        let limit = self.get(RLIMIT_STACK).rlim_cur;
+        (limit.min(MAX_USER_STACK_SIZE as u64) as usize / PAGE_SIZE_4K * PAGE_SIZE_4K)
+            .max(PAGE_SIZE_4K)
+    }
+}
+
+/// 将秒数换算为纳秒，用于设置任务的 CPU 时间上限
+fn cpu_limit_ns(secs: u64) -> usize {
+    if secs == RLIM_INFINITY {
+        usize::MAX
+    } else {
+        secs.saturating_mul(1_000_000_000) as usize
+    }
+}
+
+impl Process {
+    /// 获取一项资源限制
+    pub fn get_rlimit(&self, resource: usize) -> RLimit {
+        self.rlimits.lock().get(resource)
+    }
+
+    /// 修改一项资源限制，权限按照调用者的身份检查
+    ///
+    /// 软上限大于硬上限时返回 InvalidInput；调用者没有 CAP_SYS_RESOURCE 却要提高硬上限时返回 PermissionDenied
+    pub fn set_rlimit(&self, resource: usize, new: RLimit) -> AxResult {
+        if resource >= RLIM_NLIMITS || new.rlim_cur > new.rlim_max {
+            return Err(AxError::InvalidInput);
+        }
+        if resource == RLIMIT_NOFILE && new.rlim_max > NR_OPEN {
+            return Err(AxError::PermissionDenied);
+        }
+        let mut rlimits = self.rlimits.lock();
+        if new.rlim_max > rlimits.get(resource).rlim_max
+            && !current_process().cred().capable(Privileges::SYS_RESOURCE)
+        {
+            return Err(AxError::PermissionDenied);
+        }
+        rlimits.0[resource] = new;
+        drop(rlimits);
+        if resource == RLIMIT_CPU {
+            for task in self.tasks.lock().iter() {
+                task.set_cpu_limit(cpu_limit_ns(new.rlim_cur), cpu_limit_ns(new.rlim_max));
+            }
+        }
+        Ok(())
+    }
+
+    /// 按照 RLIMIT_CPU 设置新任务的 CPU 时间上限
+    pub(crate) fn apply_cpu_limit(&self, task: &axtask::AxTaskRef) {
+        let limit = self.get_rlimit(RLIMIT_CPU);
+        task.set_cpu_limit(cpu_limit_ns(limit.rlim_cur), cpu_limit_ns(limit.rlim_max));
+    }
+
+    /// clone 前检查 RLIMIT_NPROC，同一真实用户的任务数达到上限时返回 WouldBlock
+    ///
+    /// root 用户以及拥有 CAP_SYS_RESOURCE 或 CAP_SYS_ADMIN 的进程不受限制
+    pub(crate) fn check_nproc(&self) -> AxResult {
+        let limit = self.get_rlimit(RLIMIT_NPROC).rlim_cur;
+        let cred = self.cred();
+        if limit == RLIM_INFINITY
+            || cred.uid == 0
+            || cred.capable(Privileges::SYS_RESOURCE)
+            || cred.capable(Privileges::SYS_ADMIN)
+        {
+            return Ok(());
+        }
+        let count: usize = PID2PC
+            .lock()
+            .values()
+            .filter(|process| !process.get_zombie() && process.cred().uid == cred.uid)
+            .map(|process| process.tasks.lock().len())
+            .sum();
+        if count as u64 >= limit {
+            return Err(AxError::WouldBlock);
+        }
+        Ok(())
+    }
+
+    /// 可以使用的文件描述符数目
+    pub fn fd_limit(&self) -> usize {
+        self.get_rlimit(RLIMIT_NOFILE).rlim_cur as usize
+    }
+
+    /// 锁定 `[start, start + len)` 所在的页面，`populate` 为真时立即分配物理页
+    ///
+    /// 锁定总量超过 RLIMIT_MEMLOCK 时返回 NoMemory；上限为 0 时返回 PermissionDenied。
+    /// 拥有 CAP_IPC_LOCK 的进程不受限制
+    pub fn lock_memory(&self, start: usize, len: usize, populate: bool) -> AxResult {
+        let end = (start + len).div_ceil(PAGE_SIZE_4K) * PAGE_SIZE_4K;
+        let start = start / PAGE_SIZE_4K * PAGE_SIZE_4K;
+        let limit = self.get_rlimit(RLIMIT_MEMLOCK).rlim_cur;
+        let unlimited = limit == RLIM_INFINITY || self.cred().capable(Privileges::IPC_LOCK);
+        if limit == 0 && !unlimited {
+            return Err(AxError::PermissionDenied);
+        }
+        let mut locked_pages = self.locked_pages.lock();
+        let new_pages = (start..end)
+            .step_by(PAGE_SIZE_4K)
+            .filter(|page| !locked_pages.contains(page))
+            .count();
+        if !unlimited && ((locked_pages.len() + new_pages) * PAGE_SIZE_4K) as u64 > limit {
+            return Err(AxError::NoMemory);
+        }
+        if populate {
+            self.manual_alloc_range_for_lazy(start.into(), end.into())
+                .map_err(|_| AxError::NoMemory)?;
+        }
+        locked_pages.extend((start..end).step_by(PAGE_SIZE_4K));
+        Ok(())
+    }
+
+    /// 解除 `[start, start + len)` 所在页面的锁定
+    pub fn unlock_memory(&self, start: usize, len: usize) {
+        let end = (start + len).div_ceil(PAGE_SIZE_4K) * PAGE_SIZE_4K;
+        let start = start / PAGE_SIZE_4K * PAGE_SIZE_4K;
+        self.locked_pages
+            .lock()
+            .retain(|page| !(start..end).contains(page));
+    }
+}
+
+/// 检查在 `offset` 处写入 `len` 字节是否超出当前进程的 RLIMIT_FSIZE，返回允许写入的字节数
+///
+/// 写入的起始位置已经达到上限时，向当前线程发送 SIGXFSZ 并返回 FileTooLarge
+pub fn limit_file_write(offset: u64, len: usize) -> AxResult<usize> {
+    let limit = current_process().get_rlimit(RLIMIT_FSIZE).rlim_cur;
+    if limit == RLIM_INFINITY || len == 0 {
+        return Ok(len);
+    }
+    if offset >= limit {
+        file_too_large();
+        return Err(AxError::FileTooLarge);
+    }
+    Ok(len.min((limit - offset) as usize))
+}
+
+/// 检查将文件扩展到 `size` 字节是否超出当前进程的 RLIMIT_FSIZE
+///
+/// 超出时向当前线程发送 SIGXFSZ 并返回 FileTooLarge
+pub fn check_file_size(size: u64) -> AxResult {
+    let limit = current_process().get_rlimit(RLIMIT_FSIZE).rlim_cur;
+    if limit != RLIM_INFINITY && size > limit {
+        file_too_large();
+        return Err(AxError::FileTooLarge);
+    }
+    Ok(())
+}
+
+fn file_too_large() {
+    #[cfg(feature = "signal")]
+    {
+        use axsignal::signal_no::SignalNo;
+        let tid = axtask::current().id().as_u64() as isize;
+        let _ = crate::signal::send_signal_to_thread(tid, SignalNo::SIGXFSZ as isize);
+    }
+}
@@ -0,0 +1,782 @@
+//! seccomp：用 classic BPF 程序过滤进程发起的系统调用
+//!
+//! 进程可以通过 `prctl(PR_SET_SECCOMP)` 或 `seccomp(2)` 进入两种模式之一：
+//!
+//! - 严格模式：只允许 read、write、exit 与 rt_sigreturn，其余系统调用会导致进程被 SIGKILL 杀死
+//! - 过滤模式：每次系统调用前，以 [`SeccompData`] 为输入依次运行已安装的过滤器，
+//!   取优先级最高的返回值决定如何处理该系统调用
+//!
+//! 过滤器在安装时经过校验，只允许 seccomp 可用的指令子集，且所有跳转都必须向前，因此程序一定会终止。
+//! 过滤器只能追加不能移除，fork 时被子进程继承，exec 后仍然保留。
+//!
+//! Linux 中过滤器属于线程，这里属于整个进程，相当于总是带有 `SECCOMP_FILTER_FLAG_TSYNC`。
+extern crate alloc;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult, LinuxError};
+use axlog::{info, warn};
+use axsignal::{
+    info::{SigInfo, SI_KERNEL},
+    signal_no::SignalNo,
+};
+use axtask::current;
+use core::sync::atomic::Ordering;
+
+use crate::cred::Privileges;
+use crate::process::Process;
+use crate::signal::{force_default_action, send_signal_info_to_thread, send_signal_to_thread};
+
+/// 未启用 seccomp
+pub const SECCOMP_MODE_DISABLED: usize = 0;
+/// 严格模式
+pub const SECCOMP_MODE_STRICT: usize = 1;
+/// 过滤模式
+pub const SECCOMP_MODE_FILTER: usize = 2;
+
+/// `seccomp(2)` 的操作：进入严格模式
+pub const SECCOMP_SET_MODE_STRICT: usize = 0;
+/// `seccomp(2)` 的操作：安装过滤器
+pub const SECCOMP_SET_MODE_FILTER: usize = 1;
+/// `seccomp(2)` 的操作：查询是否支持某个返回动作
+pub const SECCOMP_GET_ACTION_AVAIL: usize = 2;
+/// `seccomp(2)` 的操作：查询用户通知相关结构的大小
+pub const SECCOMP_GET_NOTIF_SIZES: usize = 3;
+
+/// 将过滤器同步到进程的所有线程
+pub const SECCOMP_FILTER_FLAG_TSYNC: usize = 1 << 0;
+/// 记录除 ALLOW 以外的动作
+pub const SECCOMP_FILTER_FLAG_LOG: usize = 1 << 1;
+/// 不启用推测执行缓解措施
+pub const SECCOMP_FILTER_FLAG_SPEC_ALLOW: usize = 1 << 2;
+/// 返回用于接收用户通知的文件描述符
+pub const SECCOMP_FILTER_FLAG_NEW_LISTENER: usize = 1 << 3;
+/// 同步失败时返回 ESRCH
+pub const SECCOMP_FILTER_FLAG_TSYNC_ESRCH: usize = 1 << 4;
+
+/// 杀死整个进程
+pub const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+/// 杀死当前线程
+pub const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
+/// 发送 SIGSYS
+pub const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+/// 返回给定的错误码
+pub const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+/// 通知用户态的监听者
+pub const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+/// 通知跟踪者
+pub const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+/// 记录后允许
+pub const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+/// 允许
+pub const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+/// 返回值中表示动作的部分
+pub const SECCOMP_RET_ACTION_FULL: u32 = 0xffff_0000;
+/// 返回值中携带的数据
+pub const SECCOMP_RET_DATA: u32 = 0x0000_ffff;
+
+/// 单个过滤器的最大指令数
+pub const BPF_MAXINSNS: usize = 4096;
+/// 所有过滤器的指令总数上限，每个过滤器额外计 4 条
+const MAX_INSNS_PER_PATH: usize = 1 << 15;
+/// 错误码的最大值
+const MAX_ERRNO: u32 = 4095;
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH_CURRENT: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH_CURRENT: u32 = 0xc000_00b7;
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH_CURRENT: u32 = 0xc000_00f3;
+#[cfg(target_arch = "riscv32")]
+const AUDIT_ARCH_CURRENT: u32 = 0x4000_00f3;
+
+/// 严格模式下允许的系统调用：read、write、exit 与 rt_sigreturn
+#[cfg(target_arch = "x86_64")]
+const STRICT_SYSCALLS: [usize; 4] = [0, 1, 60, 15];
+#[cfg(not(target_arch = "x86_64"))]
+const STRICT_SYSCALLS: [usize; 4] = [63, 64, 93, 139];
+
+/// 一条 classic BPF 指令，布局与 Linux 的 `struct sock_filter` 一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SockFilter {
+    /// 操作码
+    pub code: u16,
+    /// 条件成立时跳过的指令数
+    pub jt: u8,
+    /// 条件不成立时跳过的指令数
+    pub jf: u8,
+    /// 立即数
+    pub k: u32,
+}
+
+/// 用户传入的 BPF 程序，布局与 Linux 的 `struct sock_fprog` 一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SockFprog {
+    /// 指令数
+    pub len: u16,
+    /// 指令数组的地址
+    pub filter: *const SockFilter,
+}
+
+/// 过滤器的输入，布局与 Linux 的 `struct seccomp_data` 一致
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SeccompData {
+    /// 系统调用号
+    pub nr: i32,
+    /// `AUDIT_ARCH_*`
+    pub arch: u32,
+    /// 系统调用指令之后的地址
+    pub instruction_pointer: u64,
+    /// 系统调用参数
+    pub args: [u64; 6],
+}
+
+// classic BPF 的指令类别
+const BPF_LD: u16 = 0x00;
+const BPF_LDX: u16 = 0x01;
+const BPF_ST: u16 = 0x02;
+const BPF_STX: u16 = 0x03;
+const BPF_ALU: u16 = 0x04;
+const BPF_JMP: u16 = 0x05;
+const BPF_RET: u16 = 0x06;
+const BPF_MISC: u16 = 0x07;
+
+// 访存宽度与寻址方式
+const BPF_W: u16 = 0x00;
+const BPF_IMM: u16 = 0x00;
+const BPF_ABS: u16 = 0x20;
+const BPF_MEM: u16 = 0x60;
+const BPF_LEN: u16 = 0x80;
+
+// 运算
+const BPF_ADD: u16 = 0x00;
+const BPF_SUB: u16 = 0x10;
+const BPF_MUL: u16 = 0x20;
+const BPF_DIV: u16 = 0x30;
+const BPF_OR: u16 = 0x40;
+const BPF_AND: u16 = 0x50;
+const BPF_LSH: u16 = 0x60;
+const BPF_RSH: u16 = 0x70;
+const BPF_NEG: u16 = 0x80;
+const BPF_MOD: u16 = 0x90;
+const BPF_XOR: u16 = 0xa0;
+
+// 跳转
+const BPF_JA: u16 = 0x00;
+const BPF_JEQ: u16 = 0x10;
+const BPF_JGT: u16 = 0x20;
+const BPF_JGE: u16 = 0x30;
+const BPF_JSET: u16 = 0x40;
+
+// 操作数来源
+const BPF_K: u16 = 0x00;
+const BPF_X: u16 = 0x08;
+const BPF_A: u16 = 0x10;
+
+// 寄存器间传送
+const BPF_TAX: u16 = 0x00;
+const BPF_TXA: u16 = 0x80;
+
+/// 暂存区的字数
+const BPF_MEMWORDS: usize = 16;
+
+/// 校验 BPF 程序是否可以作为 seccomp 过滤器
+///
+/// 与 Linux 一致，除了指令本身合法、跳转不越界、最后一条指令为返回之外，
+/// 还要求读取暂存区之前该位置在所有路径上都已被写入
+fn check_filter(prog: &[SockFilter]) -> AxResult {
+    if prog.is_empty() || prog.len() > BPF_MAXINSNS {
+        return Err(AxError::InvalidInput);
+    }
+    let len = prog.len();
+    // 每条指令处已经写入的暂存区
+    let mut valid_masks = alloc::vec![u16::MAX; len];
+    let mut mem_valid: u16 = 0;
+    for (pc, insn) in prog.iter().enumerate() {
+        mem_valid &= valid_masks[pc];
+        let class = insn.code & 0x07;
+        let jump_target = |offset: u32| -> AxResult<usize> {
+            let target = pc + 1 + offset as usize;
+            if target >= len {
+                return Err(AxError::InvalidInput);
+            }
+            Ok(target)
+        };
+        match insn.code {
+            // 只允许按字读取 seccomp_data
+            code if code == BPF_LD | BPF_W | BPF_ABS => {
+                if insn.k % 4 != 0 || insn.k as usize >= core::mem::size_of::<SeccompData>() {
+                    return Err(AxError::InvalidInput);
+                }
+            }
+            code if code == BPF_LD | BPF_W | BPF_LEN || code == BPF_LDX | BPF_W | BPF_LEN => {}
+            code if code == BPF_LD | BPF_IMM || code == BPF_LDX | BPF_IMM => {}
+            code if code == BPF_LD | BPF_MEM || code == BPF_LDX | BPF_MEM => {
+                if insn.k as usize >= BPF_MEMWORDS || mem_valid & (1 << insn.k) == 0 {
+                    return Err(AxError::InvalidInput);
+                }
+            }
+            code if code == BPF_ST || code == BPF_STX => {
+                if insn.k as usize >= BPF_MEMWORDS {
+                    return Err(AxError::InvalidInput);
+                }
+                mem_valid |= 1 << insn.k;
+            }
+            code if class == BPF_ALU => {
+                let op = code & 0xf0;
+                let src = code & 0x08;
+                if code & !0xf8 != BPF_ALU || (op == BPF_NEG && src != BPF_K) {
+                    return Err(AxError::InvalidInput);
+                }
+                match op {
+                    BPF_ADD | BPF_SUB | BPF_MUL | BPF_OR | BPF_AND | BPF_XOR | BPF_NEG => {}
+                    BPF_DIV | BPF_MOD => {
+                        if src == BPF_K && insn.k == 0 {
+                            return Err(AxError::InvalidInput);
+                        }
+                    }
+                    BPF_LSH | BPF_RSH => {
+                        if src == BPF_K && insn.k >= 32 {
+                            return Err(AxError::InvalidInput);
+                        }
+                    }
+                    _ => return Err(AxError::InvalidInput),
+                }
+            }
+            code if code == BPF_JMP | BPF_JA => {
+                valid_masks[jump_target(insn.k)?] &= mem_valid;
+                // 跳转之后的指令只能经由其他跳转到达，由 valid_masks 记录
+                mem_valid = u16::MAX;
+            }
+            code if class == BPF_JMP => {
+                if code & !0xf8 != BPF_JMP
+                    || !matches!(code & 0xf0, BPF_JEQ | BPF_JGT | BPF_JGE | BPF_JSET)
+                {
+                    return Err(AxError::InvalidInput);
+                }
+                valid_masks[jump_target(insn.jt as u32)?] &= mem_valid;
+                valid_masks[jump_target(insn.jf as u32)?] &= mem_valid;
+                mem_valid = u16::MAX;
+            }
+            code if code == BPF_RET | BPF_K || code == BPF_RET | BPF_A => {
+                mem_valid = u16::MAX;
+            }
+            code if code == BPF_MISC | BPF_TAX || code == BPF_MISC | BPF_TXA => {}
+            _ => return Err(AxError::InvalidInput),
+        }
+    }
+    if prog[len - 1].code & 0x07 != BPF_RET {
+        return Err(AxError::InvalidInput);
+    }
+    Ok(())
+}
+
+/// 以给定的输入运行已经校验过的程序，返回其返回值
+fn run_filter(prog: &[SockFilter], data: &SeccompData) -> u32 {
+    let words: &[u32] = unsafe {
+        core::slice::from_raw_parts(
+            data as *const SeccompData as *const u32,
+            core::mem::size_of::<SeccompData>() / 4,
+        )
+    };
+    let mut a: u32 = 0;
+    let mut x: u32 = 0;
+    let mut mem = [0_u32; BPF_MEMWORDS];
+    let mut pc = 0;
+    loop {
+        let insn = prog[pc];
+        pc += 1;
+        let k = insn.k;
+        match insn.code & 0x07 {
+            BPF_LD => {
+                a = match insn.code & 0xe0 {
+                    BPF_ABS => words[k as usize / 4],
+                    BPF_LEN => core::mem::size_of::<SeccompData>() as u32,
+                    BPF_MEM => mem[k as usize],
+                    _ => k,
+                }
+            }
+            BPF_LDX => {
+                x = match insn.code & 0xe0 {
+                    BPF_LEN => core::mem::size_of::<SeccompData>() as u32,
+                    BPF_MEM => mem[k as usize],
+                    _ => k,
+                }
+            }
+            BPF_ST => mem[k as usize] = a,
+            BPF_STX => mem[k as usize] = x,
+            BPF_ALU => {
+                let src = if insn.code & BPF_X != 0 { x } else { k };
+                a = match insn.code & 0xf0 {
+                    BPF_ADD => a.wrapping_add(src),
+                    BPF_SUB => a.wrapping_sub(src),
+                    BPF_MUL => a.wrapping_mul(src),
+                    // 与 Linux 一致，除以为 0 的 X 时程序返回 0
+                    BPF_DIV | BPF_MOD if src == 0 => return 0,
+                    BPF_DIV => a / src,
+                    BPF_MOD => a % src,
+                    BPF_OR => a | src,
+                    BPF_AND => a & src,
+                    BPF_LSH => a.checked_shl(src).unwrap_or(0),
+                    BPF_RSH => a.checked_shr(src).unwrap_or(0),
+                    BPF_NEG => a.wrapping_neg(),
+                    _ => a ^ src,
+                }
+            }
+            BPF_JMP => {
+                let src = if insn.code & BPF_X != 0 { x } else { k };
+                let taken = match insn.code & 0xf0 {
+                    BPF_JA => {
+                        pc += k as usize;
+                        continue;
+                    }
+                    BPF_JEQ => a == src,
+                    BPF_JGT => a > src,
+                    BPF_JGE => a >= src,
+                    _ => a & src != 0,
+                };
+                pc += if taken {
+                    insn.jt as usize
+                } else {
+                    insn.jf as usize
+                };
+            }
+            BPF_RET => return if insn.code & BPF_A != 0 { a } else { k },
+            _ => {
+                if insn.code & BPF_TXA != 0 {
+                    a = x;
+                } else {
+                    x = a;
+                }
+            }
+        }
+    }
+}
+
+/// 安装的一个过滤器
+pub struct SeccompFilter {
+    prog: Vec<SockFilter>,
+    /// 是否记录除 ALLOW 以外的动作
+    log: bool,
+}
+
+/// 进程的 seccomp 状态
+#[derive(Clone, Default)]
+pub struct Seccomp {
+    /// 当前模式，见 `SECCOMP_MODE_*`
+    mode: usize,
+    /// 已安装的过滤器，后安装的在后
+    filters: Vec<Arc<SeccompFilter>>,
+}
+
+impl Seccomp {
+    /// 当前模式
+    pub fn mode(&self) -> usize {
+        self.mode
+    }
+
+    /// 依次运行所有过滤器，返回优先级最高的返回值，以及产生该返回值的过滤器是否要求记录
+    ///
+    /// 动作部分按有符号数比较，越小优先级越高
+    fn run(&self, data: &SeccompData) -> (u32, bool) {
+        let mut result = (SECCOMP_RET_ALLOW, false);
+        for filter in self.filters.iter().rev() {
+            let ret = run_filter(&filter.prog, data);
+            if ((ret & SECCOMP_RET_ACTION_FULL) as i32)
+                < ((result.0 & SECCOMP_RET_ACTION_FULL) as i32)
+            {
+                result = (ret, filter.log);
+            }
+        }
+        result
+    }
+}
+
+/// 安装过滤器或进入严格模式前检查权限
+///
+/// 与 Linux 一致，需要设置了 no_new_privs 或拥有 CAP_SYS_ADMIN，
+/// 否则非特权进程可以借助过滤器干扰 setuid 程序
+fn check_privileges(process: &Process) -> AxResult {
+    if process.no_new_privs.load(Ordering::Acquire) || process.cred().capable(Privileges::SYS_ADMIN)
+    {
+        Ok(())
+    } else {
+        Err(AxError::PermissionDenied)
+    }
+}
+
+/// 让进程进入严格模式
+pub fn set_mode_strict(process: &Process) -> AxResult {
+    let mut seccomp = process.seccomp.lock();
+    match seccomp.mode {
+        SECCOMP_MODE_DISABLED => {
+            seccomp.mode = SECCOMP_MODE_STRICT;
+            Ok(())
+        }
+        SECCOMP_MODE_STRICT => Ok(()),
+        _ => Err(AxError::InvalidInput),
+    }
+}
+
+/// 为进程追加一个过滤器
+///
+/// 程序不合法时返回 InvalidInput，指令总数超过上限时返回 NoMemory，权限不足时返回 PermissionDenied
+pub fn attach_filter(process: &Process, prog: Vec<SockFilter>, flags: usize) -> AxResult {
+    const SUPPORTED_FLAGS: usize = SECCOMP_FILTER_FLAG_TSYNC
+        | SECCOMP_FILTER_FLAG_LOG
+        | SECCOMP_FILTER_FLAG_SPEC_ALLOW
+        | SECCOMP_FILTER_FLAG_TSYNC_ESRCH;
+    if flags & !SUPPORTED_FLAGS != 0 {
+        return Err(AxError::InvalidInput);
+    }
+    check_privileges(process)?;
+    check_filter(&prog)?;
+    let mut seccomp = process.seccomp.lock();
+    if seccomp.mode == SECCOMP_MODE_STRICT {
+        return Err(AxError::InvalidInput);
+    }
+    let total: usize = seccomp
+        .filters
+        .iter()
+        .map(|filter| filter.prog.len() + 4)
+        .sum();
+    if total + prog.len() + 4 > MAX_INSNS_PER_PATH {
+        return Err(AxError::NoMemory);
+    }
+    seccomp.mode = SECCOMP_MODE_FILTER;
+    seccomp.filters.push(Arc::new(SeccompFilter {
+        prog,
+        log: flags & SECCOMP_FILTER_FLAG_LOG != 0,
+    }));
+    Ok(())
+}
+
+/// 是否支持给定的返回动作，供 `SECCOMP_GET_ACTION_AVAIL` 使用
+pub fn action_available(action: u32) -> bool {
+    matches!(
+        action,
+        SECCOMP_RET_KILL_PROCESS
+            | SECCOMP_RET_KILL_THREAD
+            | SECCOMP_RET_TRAP
+            | SECCOMP_RET_ERRNO
+            | SECCOMP_RET_TRACE
+            | SECCOMP_RET_LOG
+            | SECCOMP_RET_ALLOW
+    )
+}
+
+/// 以不可被捕获、忽略或阻塞的信号结束当前进程，信号在返回用户态前处理
+fn force_signal(process: &Process, info: SigInfo) {
+    let signal = SignalNo::from(info.si_signo as usize);
+    force_default_action(process, signal);
+    let tid = current().id().as_u64() as isize;
+    if send_signal_info_to_thread(tid, info).is_err() {
+        send_signal_to_thread(tid, SignalNo::SIGKILL as isize).unwrap();
+    }
+}
+
+/// 当前线程是否有未决的 SIGKILL
+fn kill_pending(process: &Process) -> bool {
+    let tid = current().id().as_u64();
+    process
+        .signal_modules
+        .lock()
+        .get(&tid)
+        .is_some_and(|module| {
+            module.signal_set.pending & (1 << (SignalNo::SIGKILL as usize - 1)) != 0
+        })
+}
+
+/// 在分发系统调用之前运行当前进程的 seccomp 过滤器
+///
+/// 返回 None 时照常执行该系统调用，否则跳过该系统调用并以返回的值作为其返回值。
+/// 跟踪者在 seccomp 停止中修改的系统调用号与参数会写回 `syscall_id` 与 `args`
+pub fn syscall_filter(
+    process: &Process,
+    syscall_id: &mut usize,
+    args: &mut [usize; 6],
+) -> Option<isize> {
+    run_filters(process, syscall_id, args, false)
+}
+
+/// 运行过滤器并执行其返回的动作
+///
+/// `recheck_after_trace` 为真时为跟踪者修改系统调用之后的再次检查，此时 `SECCOMP_RET_TRACE` 视为允许
+fn run_filters(
+    process: &Process,
+    syscall_id: &mut usize,
+    args: &mut [usize; 6],
+    recheck_after_trace: bool,
+) -> Option<isize> {
+    let seccomp = process.seccomp.lock();
+    let enosys = -(LinuxError::ENOSYS.code() as isize);
+    match seccomp.mode {
+        SECCOMP_MODE_DISABLED => return None,
+        SECCOMP_MODE_STRICT => {
+            drop(seccomp);
+            if STRICT_SYSCALLS.contains(syscall_id) {
+                return None;
+            }
+            warn!(
+                "seccomp: syscall {} is not allowed in strict mode",
+                syscall_id
+            );
+            force_signal(process, SigInfo::new(SignalNo::SIGKILL as i32, SI_KERNEL));
+            return Some(enosys);
+        }
+        _ => {}
+    }
+    let trap_frame = unsafe { &*current().get_first_trap_frame() };
+    let data = SeccompData {
+        nr: *syscall_id as i32,
+        arch: AUDIT_ARCH_CURRENT,
+        instruction_pointer: trap_frame.get_pc() as u64,
+        args: args.map(|arg| arg as u64),
+    };
+    let (ret, log) = seccomp.run(&data);
+    drop(seccomp);
+    let action = ret & SECCOMP_RET_ACTION_FULL;
+    let value = ret & SECCOMP_RET_DATA;
+    if log && action != SECCOMP_RET_ALLOW {
+        info!(
+            "seccomp: pid {} syscall {} action {:#x}",
+            process.pid(),
+            syscall_id,
+            ret
+        );
+    }
+    match action {
+        SECCOMP_RET_ALLOW | SECCOMP_RET_LOG => None,
+        SECCOMP_RET_ERRNO => Some(-(value.min(MAX_ERRNO) as isize)),
+        SECCOMP_RET_TRAP => {
+            let info = SigInfo::from_seccomp(
+                data.instruction_pointer as usize,
+                data.nr,
+                data.arch,
+                value as i32,
+            );
+            let _ = send_signal_info_to_thread(current().id().as_u64() as isize, info);
+            Some(enosys)
+        }
+        SECCOMP_RET_TRACE if recheck_after_trace => None,
+        SECCOMP_RET_TRACE => {
+            // 没有开启 PTRACE_O_TRACESECCOMP 的跟踪者时，与 Linux 一致返回 ENOSYS
+            let Some((new_id, new_args)) =
+                crate::ptrace::seccomp_event(process, *syscall_id, value)
+            else {
+                return Some(enosys);
+            };
+            if kill_pending(process) {
+                return Some(enosys);
+            }
+            // 跟踪者将系统调用号改为 -1 时跳过这次调用，返回值由跟踪者设置
+            if new_id == usize::MAX {
+                let trap_frame = unsafe { &*current().get_first_trap_frame() };
+                return Some(trap_frame.get_ret_code() as isize);
+            }
+            // 跟踪者修改后的系统调用需要再经过一次过滤
+            *syscall_id = new_id;
+            *args = new_args;
+            run_filters(process, syscall_id, args, true)
+        }
+        // 没有用户态监听者，与 Linux 在这种情况下的行为一致
+        SECCOMP_RET_USER_NOTIF => Some(enosys),
+        // KILL_THREAD 同样结束整个进程
+        _ => {
+            warn!(
+                "seccomp: killing process {} on syscall {}",
+                process.pid(),
+                syscall_id
+            );
+            force_signal(
+                process,
+                SigInfo::from_seccomp(data.instruction_pointer as usize, data.nr, data.arch, 0),
+            );
+            Some(enosys)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn stmt(code: u16, k: u32) -> SockFilter {
+        SockFilter {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    const fn jump(code: u16, k: u32, jt: u8, jf: u8) -> SockFilter {
+        SockFilter { code, jt, jf, k }
+    }
+
+    fn data(nr: i32, args: [u64; 6]) -> SeccompData {
+        SeccompData {
+            nr,
+            arch: AUDIT_ARCH_CURRENT,
+            instruction_pointer: 0x1000,
+            args,
+        }
+    }
+
+    /// 检查架构后拒绝 `nr` 号系统调用，其余允许
+    fn deny(nr: u32, ret: u32) -> Vec<SockFilter> {
+        alloc::vec![
+            stmt(BPF_LD | BPF_W | BPF_ABS, 4),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, AUDIT_ARCH_CURRENT, 1, 0),
+            stmt(BPF_RET | BPF_K, SECCOMP_RET_KILL_PROCESS),
+            stmt(BPF_LD | BPF_W | BPF_ABS, 0),
+            jump(BPF_JMP | BPF_JEQ | BPF_K, nr, 0, 1),
+            stmt(BPF_RET | BPF_K, ret),
+            stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+        ]
+    }
+
+    #[test]
+    fn test_check_filter() {
+        let ret_allow = stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW);
+        assert!(check_filter(&[ret_allow]).is_ok());
+        assert!(check_filter(&deny(39, SECCOMP_RET_ERRNO | 1)).is_ok());
+        // 空程序、超长程序以及最后一条不是返回的程序
+        assert!(check_filter(&[]).is_err());
+        assert!(check_filter(&alloc::vec![ret_allow; BPF_MAXINSNS + 1]).is_err());
+        assert!(check_filter(&[stmt(BPF_LD | BPF_IMM, 0)]).is_err());
+        // 读取 seccomp_data 时必须按字对齐且不越界
+        assert!(check_filter(&[stmt(BPF_LD | BPF_W | BPF_ABS, 2), ret_allow]).is_err());
+        assert!(check_filter(&[stmt(BPF_LD | BPF_W | BPF_ABS, 64), ret_allow]).is_err());
+        assert!(check_filter(&[stmt(BPF_LD | BPF_W | BPF_ABS, 60), ret_allow]).is_ok());
+        // 跳转不能越过程序末尾
+        assert!(check_filter(&[stmt(BPF_JMP | BPF_JA, 1), ret_allow]).is_err());
+        assert!(check_filter(&[jump(BPF_JMP | BPF_JEQ | BPF_K, 0, 0, 1), ret_allow]).is_err());
+        // 除以常数 0 与移位超过 31 位
+        assert!(check_filter(&[stmt(BPF_ALU | BPF_DIV | BPF_K, 0), ret_allow]).is_err());
+        assert!(check_filter(&[stmt(BPF_ALU | BPF_MOD | BPF_K, 0), ret_allow]).is_err());
+        assert!(check_filter(&[stmt(BPF_ALU | BPF_LSH | BPF_K, 32), ret_allow]).is_err());
+        assert!(check_filter(&[stmt(BPF_ALU | BPF_NEG | BPF_X, 0), ret_allow]).is_err());
+        // seccomp 不支持的指令
+        assert!(check_filter(&[stmt(BPF_LD | BPF_W | 0x40, 0), ret_allow]).is_err());
+        assert!(check_filter(&[stmt(BPF_ALU | 0xb0, 0), ret_allow]).is_err());
+    }
+
+    #[test]
+    fn test_check_filter_memory() {
+        let ret_a = stmt(BPF_RET | BPF_A, 0);
+        // 读取暂存区之前必须写入，且下标不能越界
+        assert!(check_filter(&[stmt(BPF_LD | BPF_MEM, 0), ret_a]).is_err());
+        assert!(check_filter(&[stmt(BPF_ST, 0), stmt(BPF_LD | BPF_MEM, 0), ret_a]).is_ok());
+        assert!(check_filter(&[stmt(BPF_ST, 16), ret_a]).is_err());
+        assert!(check_filter(&[stmt(BPF_STX, 3), stmt(BPF_LDX | BPF_MEM, 4), ret_a]).is_err());
+        // 只在一条路径上写入时，汇合处不能读取
+        let one_path = [
+            jump(BPF_JMP | BPF_JEQ | BPF_K, 0, 0, 1),
+            stmt(BPF_ST, 0),
+            stmt(BPF_LD | BPF_MEM, 0),
+            ret_a,
+        ];
+        assert!(check_filter(&one_path).is_err());
+        let both_paths = [
+            jump(BPF_JMP | BPF_JEQ | BPF_K, 0, 0, 2),
+            stmt(BPF_ST, 0),
+            stmt(BPF_JMP | BPF_JA, 1),
+            stmt(BPF_ST, 0),
+            stmt(BPF_LD | BPF_MEM, 0),
+            ret_a,
+        ];
+        assert!(check_filter(&both_paths).is_ok());
+    }
+
+    #[test]
+    fn test_run_filter() {
+        let prog = deny(39, SECCOMP_RET_ERRNO | 1);
+        assert_eq!(run_filter(&prog, &data(39, [0; 6])), SECCOMP_RET_ERRNO | 1);
+        assert_eq!(run_filter(&prog, &data(40, [0; 6])), SECCOMP_RET_ALLOW);
+        let mut other_arch = data(39, [0; 6]);
+        other_arch.arch = 0x4000_0003;
+        assert_eq!(run_filter(&prog, &other_arch), SECCOMP_RET_KILL_PROCESS);
+
+        // 参数的低 32 位位于偏移 16 处，高 32 位位于偏移 20 处
+        let arg = [
+            stmt(BPF_LD | BPF_W | BPF_ABS, 20),
+            jump(BPF_JMP | BPF_JSET | BPF_K, 0x8000_0000, 0, 1),
+            stmt(BPF_RET | BPF_K, SECCOMP_RET_ERRNO | 22),
+            stmt(BPF_LD | BPF_W | BPF_ABS, 16),
+            stmt(BPF_RET | BPF_A, 0),
+        ];
+        assert!(check_filter(&arg).is_ok());
+        let high = data(0, [0x8000_0000_0000_0005, 0, 0, 0, 0, 0]);
+        assert_eq!(run_filter(&arg, &high), SECCOMP_RET_ERRNO | 22);
+        assert_eq!(run_filter(&arg, &data(0, [5, 0, 0, 0, 0, 0])), 5);
+    }
+
+    #[test]
+    fn test_run_filter_alu() {
+        let prog = [
+            stmt(BPF_LD | BPF_IMM, 6),
+            stmt(BPF_MISC | BPF_TAX, 0),
+            stmt(BPF_ALU | BPF_MUL | BPF_X, 0),
+            stmt(BPF_ALU | BPF_ADD | BPF_K, 4),
+            stmt(BPF_ST, 1),
+            stmt(BPF_ALU | BPF_MOD | BPF_K, 7),
+            stmt(BPF_MISC | BPF_TAX, 0),
+            stmt(BPF_LD | BPF_MEM, 1),
+            stmt(BPF_ALU | BPF_SUB | BPF_X, 0),
+            stmt(BPF_ALU | BPF_LSH | BPF_K, 1),
+            stmt(BPF_RET | BPF_A, 0),
+        ];
+        assert!(check_filter(&prog).is_ok());
+        // ((6 * 6 + 4) - (40 % 7)) << 1
+        assert_eq!(run_filter(&prog, &data(0, [0; 6])), 70);
+
+        // 除以为 0 的 X 时程序返回 0
+        let div_zero = [
+            stmt(BPF_LD | BPF_IMM, 1),
+            stmt(BPF_LDX | BPF_IMM, 0),
+            stmt(BPF_ALU | BPF_DIV | BPF_X, 0),
+            stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+        ];
+        assert!(check_filter(&div_zero).is_ok());
+        assert_eq!(run_filter(&div_zero, &data(0, [0; 6])), 0);
+
+        let len = [
+            stmt(BPF_LDX | BPF_W | BPF_LEN, 0),
+            stmt(BPF_MISC | BPF_TXA, 0),
+            stmt(BPF_ALU | BPF_NEG, 0),
+            stmt(BPF_RET | BPF_A, 0),
+        ];
+        assert!(check_filter(&len).is_ok());
+        assert_eq!(run_filter(&len, &data(0, [0; 6])), 64_u32.wrapping_neg());
+    }
+
+    #[test]
+    fn test_filter_priority() {
+        let filter = |prog: Vec<SockFilter>| Arc::new(SeccompFilter { prog, log: false });
+        let seccomp = Seccomp {
+            mode: SECCOMP_MODE_FILTER,
+            filters: alloc::vec![
+                filter(deny(39, SECCOMP_RET_TRACE | 7)),
+                filter(deny(39, SECCOMP_RET_ERRNO | 1)),
+                filter(deny(40, SECCOMP_RET_LOG)),
+            ],
+        };
+        // 动作优先级高的返回值胜出，与过滤器的安装顺序无关
+        assert_eq!(seccomp.run(&data(39, [0; 6])).0, SECCOMP_RET_ERRNO | 1);
+        assert_eq!(seccomp.run(&data(40, [0; 6])).0, SECCOMP_RET_LOG);
+        assert_eq!(seccomp.run(&data(41, [0; 6])).0, SECCOMP_RET_ALLOW);
+        // KILL_PROCESS 的最高位为 1，按有符号数比较时优先级最高
+        let kill = Seccomp {
+            mode: SECCOMP_MODE_FILTER,
+            filters: alloc::vec![
+                filter(deny(39, SECCOMP_RET_KILL_PROCESS)),
+                filter(deny(39, SECCOMP_RET_KILL_THREAD)),
+            ],
+        };
+        assert_eq!(kill.run(&data(39, [0; 6])).0, SECCOMP_RET_KILL_PROCESS);
+    }
+}
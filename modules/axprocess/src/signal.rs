@@ -1,22 +1,28 @@
 //! 负责处理进程中与信号相关的内容
 extern crate alloc;
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use axerrno::{AxError, AxResult};
-use axhal::{arch::TrapFrame, cpu::this_cpu_id, KERNEL_PROCESS_ID};
+use axhal::{arch::TrapFrame, cpu::this_cpu_id, mem::VirtAddr, KERNEL_PROCESS_ID};
 use axlog::{info, warn};
 use axsignal::{
     action::{SigActionFlags, SignalDefault, SIG_IGN},
-    info::SigInfo,
+    info::{
+        SigInfo, CLD_CONTINUED, CLD_DUMPED, CLD_EXITED, CLD_KILLED, CLD_STOPPED, CLD_TRAPPED,
+        SI_KERNEL, TRAP_BRKPT, TRAP_TRACE,
+    },
     signal_no::SignalNo,
-    ucontext::SignalUserContext,
+    ucontext::{SignalStack, SignalUserContext, SS_AUTODISARM, SS_ONSTACK},
     SignalHandler, SignalSet,
 };
 use axsync::Mutex;
-use axtask::{SignalCaller, TaskState, RUN_QUEUE};
+use axtask::{SignalCaller, TaskState, WaitQueue, RUN_QUEUE};
+use core::sync::atomic::{AtomicU64, Ordering};
 
 /// 信号处理模块，进程间不共享
 pub struct SignalModule {
-    /// 是否存在siginfo
+    /// 用户栈上是否保存了 ucontext，sigreturn 时需要据此恢复上下文
     pub sig_info: bool,
     /// 保存的trap上下文
     pub last_trap_frame_for_signal: Option<TrapFrame>,
@@ -24,6 +30,8 @@ pub struct SignalModule {
     pub signal_handler: Arc<Mutex<SignalHandler>>,
     /// 未决信号集
     pub signal_set: SignalSet,
+    /// 通过 sigaltstack 设置的备用信号栈
+    pub alt_stack: SignalStack,
 }
 
 impl SignalModule {
@@ -39,15 +47,57 @@ impl SignalModule {
             last_trap_frame_for_signal,
             signal_handler,
             signal_set,
+            alt_stack: SignalStack::default(),
         }
     }
 }
 
+/// 阻塞在 sigtimedwait 上、等待进程产生新信号的线程
+pub struct SignalWait {
+    /// 进程产生信号的次数，供等待队列在持有 `RUN_QUEUE` 时检查
+    generation: AtomicU64,
+    wait_queue: WaitQueue,
+}
+
+impl Default for SignalWait {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalWait {
+    /// 创建空的等待队列
+    pub fn new() -> Self {
+        Self {
+            generation: AtomicU64::new(0),
+            wait_queue: WaitQueue::new(),
+        }
+    }
+
+    /// 当前的信号产生次数，需要在检查未决信号之前读取
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// 进程产生了新的信号，唤醒所有等待者
+    pub fn notify(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.wait_queue.notify_all(false);
+    }
+
+    /// 阻塞直到 `generation` 之后产生了新的信号、到达 `deadline` 或者被唤醒
+    pub fn wait(&self, generation: u64, deadline: Option<axhal::time::TimeValue>) {
+        self.wait_queue
+            .wait_once_unless(deadline, || self.generation() != generation);
+    }
+}
+
 const USER_SIGNAL_PROTECT: usize = 512;
 
 use crate::{
     current_process, current_task, exit_current_task,
-    process::{PID2PC, TID2TASK},
+    flags::ChildState,
+    process::{Process, PID2PC, TID2TASK},
 };
 
 /// 将保存的trap上下文填入内核栈中
@@ -71,8 +121,36 @@ pub fn load_trap_for_signal() -> bool {
             let sp = (*now_trap_frame).get_sp();
             *now_trap_frame = old_trap_frame;
             if signal_module.sig_info {
-                let pc = (*(sp as *const SignalUserContext)).get_pc();
-                (*now_trap_frame).set_pc(pc);
+                let ucontext = sp as *const SignalUserContext;
+                let readable = current_process.manual_alloc_type_for_lazy(ucontext).is_ok()
+                    && (*ucontext).user_fp_range().map_or(true, |(start, end)| {
+                        current_process
+                            .manual_alloc_range_for_lazy(
+                                VirtAddr::from(start),
+                                VirtAddr::from(end - 1),
+                            )
+                            .is_ok()
+                    });
+                if !readable {
+                    // 用户提供的上下文不可访问，不做任何恢复，并发送 SIGSEGV
+                    warn!("bad signal frame at {:#x}", sp);
+                    signal_module
+                        .signal_set
+                        .try_add_signal(SignalNo::SIGSEGV as usize);
+                    return true;
+                }
+                let ucontext = &*ucontext;
+                // 信号处理函数可能修改了ucontext，以其中的内容为准
+                ucontext.restore(&mut *now_trap_frame);
+                signal_module.signal_set.mask = ucontext.get_mask()
+                    & !(1 << (SignalNo::SIGKILL as usize - 1))
+                    & !(1 << (SignalNo::SIGSTOP as usize - 1));
+                let mut stack = ucontext.get_stack();
+                if stack.flags & SS_AUTODISARM != 0 && signal_module.alt_stack.is_disabled() {
+                    // 进入处理函数时被自动禁用的备用信号栈，此时重新启用
+                    stack.flags &= !SS_ONSTACK;
+                    signal_module.alt_stack = stack;
+                }
             }
         }
         true
@@ -86,6 +164,7 @@ fn terminate_process(signal: SignalNo) {
     let current_task = current_task();
     warn!("Terminate process: {}", current_task.get_process_id());
     if current_task.is_leader() {
+        current_process().set_term_signal(signal as i32);
         exit_current_task(signal as i32);
     } else {
         // 此时应当关闭当前进程
@@ -113,15 +192,39 @@ pub fn handle_signals() {
         // 内核进程不处理信号
         return;
     }
+    // 其他线程正在生成 core 文件时在此停下，转储结束后随进程一同退出
+    if process.core_dump.is_dumping() {
+        process.core_dump.wait_for_dump();
+        let signal = process.core_dump.signal();
+        terminate_process(SignalNo::from(signal as usize));
+    }
+    // 进程处于组停止时，其余线程也在此暂停，直到进程继续运行
+    if process.job.is_stopped() {
+        process.job.wait_while_stopped();
+    }
     let mut signal_modules = process.signal_modules.lock();
 
     let signal_module = signal_modules.get_mut(&current_task.id().as_u64()).unwrap();
-    let signal_set = &mut signal_module.signal_set;
-    let sig_num = if let Some(sig_num) = signal_set.get_one_signal() {
-        sig_num
+    let mut sig_info = if let Some(sig_info) = signal_module.signal_set.get_one_signal_info() {
+        sig_info
     } else {
         return;
     };
+    if sig_info.si_signo != SignalNo::SIGKILL as i32 && process.ptrace.is_traced() {
+        // 被跟踪的进程在递送信号前停止，由跟踪者决定实际递送的信号
+        drop(signal_modules);
+        sig_info = match crate::ptrace::signal_stop(&process, sig_info) {
+            Some(sig_info) => sig_info,
+            None => return,
+        };
+        signal_modules = process.signal_modules.lock();
+    }
+    let signal_module = match signal_modules.get_mut(&current_task.id().as_u64()) {
+        Some(signal_module) => signal_module,
+        None => return,
+    };
+    let signal_set = &mut signal_module.signal_set;
+    let sig_num = sig_info.si_signo as usize;
     info!(
         "cpu: {}, task: {}, handler signal: {}",
         this_cpu_id(),
@@ -164,12 +267,27 @@ pub fn handle_signals() {
                 terminate_process(signal);
             }
             SignalDefault::Stop => {
-                unimplemented!();
+                // 停止不需要调用处理函数，保存的trap上下文不再需要
+                load_trap_for_signal();
+                group_stop(&process, signal);
             }
             SignalDefault::Cont => {
-                unimplemented!();
+                // 进程在 SIGCONT 产生时就已经继续运行，此处按忽略处理
+                load_trap_for_signal();
             }
             SignalDefault::Core => {
+                // 多个线程同时收到信号时只由一个线程生成 core 文件，其余线程等待其写完
+                if process
+                    .core_dumped
+                    .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+                {
+                    if !crate::coredump::dump_core(&process, &sig_info) {
+                        process.core_dumped.store(false, Ordering::Release);
+                    }
+                } else {
+                    process.core_dump.wait_for_dump();
+                }
                 terminate_process(signal);
             }
         }
@@ -177,7 +295,9 @@ pub fn handle_signals() {
     }
     let action = action.unwrap();
     if action.sa_handler == SIG_IGN {
-        // 忽略处理
+        // 忽略处理，保存的trap上下文不再需要
+        drop(signal_handler);
+        signal_module.last_trap_frame_for_signal = None;
         return;
     }
     // 此时需要调用信号处理函数，注意调用的方式是：
@@ -190,8 +310,24 @@ pub fn handle_signals() {
     // 注意是直接修改内核栈上的内容
     let trap_frame = unsafe { &mut *(current_task.get_first_trap_frame()) };
 
-    // // 新的trap上下文的sp指针位置，由于SIGINFO会存放内容，所以需要开个保护区域
-    let mut sp = trap_frame.get_sp() - USER_SIGNAL_PROTECT;
+    // 选择处理函数使用的栈：若要求在备用信号栈上执行且当前不在备用栈上，则切换到备用栈
+    let alt_stack = signal_module.alt_stack;
+    let user_sp = trap_frame.get_sp();
+    let on_alt_stack = action.sa_flags.contains(SigActionFlags::SA_ONSTACK)
+        && !alt_stack.is_disabled()
+        && !alt_stack.contains(user_sp);
+    let (mut sp, uc_stack) = if on_alt_stack {
+        let mut stack = alt_stack;
+        stack.flags |= SS_ONSTACK;
+        (alt_stack.top(), stack)
+    } else {
+        // 新的trap上下文的sp指针位置，由于SIGINFO会存放内容，所以需要开个保护区域
+        let mut stack = alt_stack;
+        if alt_stack.contains(user_sp) {
+            stack.flags |= SS_ONSTACK;
+        }
+        (user_sp - USER_SIGNAL_PROTECT, stack)
+    };
     let restorer = if let Some(addr) = action.get_storer() {
         addr
     } else {
@@ -202,48 +338,70 @@ pub fn handle_signals() {
         "restorer :{:#x}, handler: {:#x}",
         restorer, action.sa_handler
     );
+
+    // 依次存放siginfo与ucontext，注意16字节对齐
+    let info_addr = (sp - core::mem::size_of::<SigInfo>()) & !0xf;
+    sp = (info_addr - core::mem::size_of::<SignalUserContext>()) & !0xf;
+    let ucontext_addr = sp;
+    #[cfg(target_arch = "x86_64")]
+    {
+        // 为返回地址预留位置
+        sp -= core::mem::size_of::<usize>();
+    }
+    let frame_end = info_addr + core::mem::size_of::<SigInfo>();
+    if process
+        .manual_alloc_range_for_lazy(VirtAddr::from(sp), VirtAddr::from(frame_end))
+        .is_err()
+    {
+        // 无法在用户栈上构造信号帧，直接结束进程
+        warn!("failed to build signal frame at {:#x}", sp);
+        drop(signal_handler);
+        signal_module.last_trap_frame_for_signal = None;
+        drop(signal_modules);
+        terminate_process(SignalNo::SIGSEGV);
+        return;
+    }
+
+    unsafe {
+        *(info_addr as *mut SigInfo) = sig_info;
+        let ucontext = &mut *(ucontext_addr as *mut SignalUserContext);
+        *ucontext = SignalUserContext::init(trap_frame, mask, uc_stack);
+        // 浮点状态需要在ucontext写入用户栈之后再保存
+        ucontext.save_fp_state();
+    }
+    signal_module.sig_info = true;
+
     #[cfg(not(target_arch = "x86_64"))]
     trap_frame.set_ra(restorer);
 
-    let old_pc = trap_frame.get_pc();
-
     trap_frame.set_pc(action.sa_handler);
-    // 传参
+    // 传参，若带有SA_SIGINFO，则函数原型为fn(sig: SignalNo, info: &SigInfo, ucontext: &mut UContext)
     trap_frame.set_arg0(sig_num);
-    // 若带有SIG_INFO参数，则函数原型为fn(sig: SignalNo, info: &SigInfo, ucontext: &mut UContext)
-    if action.sa_flags.contains(SigActionFlags::SA_SIGINFO) {
-        // current_task.set_siginfo(true);
-        signal_module.sig_info = true;
-        // 注意16字节对齐
-        sp = (sp - core::mem::size_of::<SigInfo>()) & !0xf;
-        let info = SigInfo {
-            si_signo: sig_num as i32,
-            ..Default::default()
-        };
-        unsafe {
-            *(sp as *mut SigInfo) = info;
-        }
-        trap_frame.set_arg1(sp);
-
-        // 接下来存储ucontext
-        sp = (sp - core::mem::size_of::<SignalUserContext>()) & !0xf;
-
-        let ucontext = SignalUserContext::init(old_pc, mask);
-        unsafe {
-            *(sp as *mut SignalUserContext) = ucontext;
-        }
-        trap_frame.set_arg2(sp);
-    }
+    trap_frame.set_arg1(info_addr);
+    trap_frame.set_arg2(ucontext_addr);
 
     #[cfg(target_arch = "x86_64")]
     unsafe {
         // set return rip
-        sp -= core::mem::size_of::<usize>();
         *(sp as *mut usize) = restorer;
     }
 
     trap_frame.set_user_sp(sp);
+
+    // 处理函数执行期间屏蔽sa_mask中的信号，除非指定SA_NODEFER，否则也屏蔽当前信号
+    let mut handler_mask = mask | action.sa_mask;
+    if !action.sa_flags.contains(SigActionFlags::SA_NODEFER) {
+        handler_mask |= 1 << (sig_num - 1);
+    }
+    let reset_hand = action.sa_flags.contains(SigActionFlags::SA_RESETHAND);
     drop(signal_handler);
+    signal_module.signal_set.mask = handler_mask;
+    if on_alt_stack && alt_stack.flags & SS_AUTODISARM != 0 {
+        signal_module.alt_stack = SignalStack::default();
+    }
+    if reset_hand {
+        signal_module.signal_handler.lock().handlers[sig_num - 1] = None;
+    }
     drop(signal_modules);
 }
 
@@ -267,11 +425,18 @@ pub fn signal_return() -> isize {
 ///
 /// 默认发送到该进程下的主线程
 pub fn send_signal_to_process(pid: isize, signum: isize) -> AxResult<()> {
-    let mut pid2pc = PID2PC.lock();
-    if !pid2pc.contains_key(&(pid as u64)) {
-        return Err(axerrno::AxError::NotFound);
-    }
-    let process = pid2pc.get_mut(&(pid as u64)).unwrap();
+    send_signal_info_to_process(pid, SigInfo::new(signum as i32, SI_KERNEL))
+}
+
+/// 发送携带信息的信号到指定的进程
+///
+/// 默认发送到该进程下的主线程。实时信号排队已满时返回 `WouldBlock`
+pub fn send_signal_info_to_process(pid: isize, info: SigInfo) -> AxResult<()> {
+    let process = if let Some(process) = PID2PC.lock().get(&(pid as u64)) {
+        Arc::clone(process)
+    } else {
+        return Err(AxError::NotFound);
+    };
     let mut now_id: Option<u64> = None;
     for task in process.tasks.lock().iter_mut() {
         if task.is_leader() {
@@ -281,8 +446,13 @@ pub fn send_signal_to_process(pid: isize, signum: isize) -> AxResult<()> {
     }
     if now_id.is_some() {
         let mut signal_modules = process.signal_modules.lock();
+        discard_conflicting_signals(&mut signal_modules, info.si_signo);
         let signal_module = signal_modules.get_mut(&now_id.unwrap()).unwrap();
-        signal_module.signal_set.try_add_signal(signum as usize);
+        if !signal_module.signal_set.try_add_signal_info(info) {
+            return Err(AxError::WouldBlock);
+        }
+        drop(signal_modules);
+        signal_generated(&process, info.si_signo);
         let tid2task = TID2TASK.lock();
         let main_task = Arc::clone(tid2task.get(&now_id.unwrap()).unwrap());
         // 如果这个时候对应的线程是处于休眠状态的，则唤醒之，进入信号处理阶段
@@ -293,8 +463,231 @@ pub fn send_signal_to_process(pid: isize, signum: isize) -> AxResult<()> {
     Ok(())
 }
 
+/// 获取进程组中所有存活进程的进程号
+pub fn process_group_members(pgid: u64) -> Vec<u64> {
+    PID2PC
+        .lock()
+        .values()
+        .filter(|process| process.get_pgid() == pgid && !process.get_zombie())
+        .map(|process| process.pid())
+        .collect()
+}
+
+/// 发送信号到指定进程组中的所有进程
+///
+/// 进程组中没有存活的进程时返回 `NotFound`
+pub fn send_signal_to_process_group(pgid: u64, signum: isize) -> AxResult<()> {
+    let members = process_group_members(pgid);
+    if members.is_empty() {
+        return Err(AxError::NotFound);
+    }
+    for pid in members {
+        let _ = send_signal_to_process(pid as isize, signum);
+    }
+    Ok(())
+}
+
+/// 判断进程组在进程 `exiting` 退出后是否成为孤儿进程组
+///
+/// 若进程组中每个成员的父进程要么属于同一进程组，要么属于其他会话，
+/// 则没有进程能再对该进程组进行作业控制，该进程组为孤儿进程组
+fn is_orphaned_pgrp(pid2pc: &BTreeMap<u64, Arc<Process>>, pgid: u64, exiting: u64) -> bool {
+    pid2pc
+        .values()
+        .filter(|process| {
+            process.get_pgid() == pgid && process.pid() != exiting && !process.get_zombie()
+        })
+        .all(|process| match pid2pc.get(&process.get_parent()) {
+            Some(parent) if parent.pid() != exiting && !parent.get_zombie() => {
+                parent.get_pgid() == pgid || parent.get_sid() != process.get_sid()
+            }
+            _ => true,
+        })
+}
+
+/// 进程组当前是否为孤儿进程组
+pub(crate) fn is_orphaned_process_group(pgid: u64) -> bool {
+    is_orphaned_pgrp(&PID2PC.lock(), pgid, 0)
+}
+
+/// 进程组中是否有处于停止状态的进程
+fn has_stopped_jobs(pid2pc: &BTreeMap<u64, Arc<Process>>, pgid: u64) -> bool {
+    pid2pc
+        .values()
+        .any(|process| process.get_pgid() == pgid && process.is_stopped())
+}
+
+/// 进程退出时，处理因此成为孤儿的进程组
+///
+/// 按照 POSIX 的规定，新成为孤儿的进程组中若有停止的进程，则向整个进程组发送 SIGHUP 与 SIGCONT，
+/// 避免其永远停止下去。可能受影响的是退出进程自身所在的进程组，以及其子进程所在的进程组
+pub(crate) fn kill_orphaned_pgrps(process: &Process) {
+    let pid = process.pid();
+    let mut orphaned: Vec<u64> = Vec::new();
+    {
+        let pid2pc = PID2PC.lock();
+        // 退出进程原本在会话内的其他进程组中的父进程维系着自身所在的进程组
+        let mut candidates: Vec<u64> = Vec::new();
+        if let Some(parent) = pid2pc.get(&process.get_parent()) {
+            if parent.get_pgid() != process.get_pgid() && parent.get_sid() == process.get_sid() {
+                candidates.push(process.get_pgid());
+            }
+        }
+        // 退出进程维系着子进程所在的、同一会话中的其他进程组
+        for child in process.children.lock().iter() {
+            if child.get_pgid() != process.get_pgid() && child.get_sid() == process.get_sid() {
+                candidates.push(child.get_pgid());
+            }
+        }
+        for pgid in candidates {
+            if !orphaned.contains(&pgid)
+                && is_orphaned_pgrp(&pid2pc, pgid, pid)
+                && has_stopped_jobs(&pid2pc, pgid)
+            {
+                orphaned.push(pgid);
+            }
+        }
+    }
+    for pgid in orphaned {
+        info!("process group {} is orphaned by the exit of {}", pgid, pid);
+        let _ = send_signal_to_process_group(pgid, SignalNo::SIGHUP as isize);
+        let _ = send_signal_to_process_group(pgid, SignalNo::SIGCONT as isize);
+    }
+}
+
+/// 是否为停止信号
+fn is_stop_signal(signo: i32) -> bool {
+    signo == SignalNo::SIGSTOP as i32
+        || signo == SignalNo::SIGTSTP as i32
+        || signo == SignalNo::SIGTTIN as i32
+        || signo == SignalNo::SIGTTOU as i32
+}
+
+/// 构造子进程状态变化时发送给父进程的 SIGCHLD，其中的 si_code 与 si_status 也用于 waitid
+pub fn child_info(pid: u64, uid: u32, state: ChildState) -> SigInfo {
+    let (code, status) = match state {
+        ChildState::Exited(code) => (CLD_EXITED, code & 0xff),
+        ChildState::Killed(signal) => (CLD_KILLED, signal),
+        ChildState::Dumped(signal) => (CLD_DUMPED, signal),
+        ChildState::Stopped(signal) => (CLD_STOPPED, signal),
+        ChildState::Trapped(status) => (CLD_TRAPPED, (status >> 8) & 0xff),
+        ChildState::Continued => (CLD_CONTINUED, SignalNo::SIGCONT as i32),
+    };
+    SigInfo::from_child(code, pid as u32, uid, status)
+}
+
+/// 子进程停止或继续运行时通知父进程
+///
+/// 父进程为 SIGCHLD 设置了 `SA_NOCLDSTOP` 时不发送
+fn notify_parent(process: &Process, state: ChildState) {
+    let parent = match PID2PC.lock().get(&process.get_parent()) {
+        Some(parent) if parent.pid() != KERNEL_PROCESS_ID => Arc::clone(parent),
+        _ => return,
+    };
+    let no_cld_stop = parent
+        .signal_modules
+        .lock()
+        .values()
+        .next()
+        .is_some_and(|signal_module| {
+            signal_module
+                .signal_handler
+                .lock()
+                .get_action(SignalNo::SIGCHLD as usize)
+                .is_some_and(|action| action.sa_flags.contains(SigActionFlags::SA_NOCLDSTOP))
+        });
+    if no_cld_stop {
+        return;
+    }
+    let info = child_info(process.pid(), process.cred.lock().uid, state);
+    let _ = send_signal_info_to_process(parent.pid() as isize, info);
+}
+
+/// 停止信号的默认动作：停止整个进程，直到其收到 SIGCONT 或 SIGKILL
+///
+/// 孤儿进程组中的进程不受终端产生的停止信号影响，这些信号被直接丢弃
+fn group_stop(process: &Process, signal: SignalNo) {
+    if signal != SignalNo::SIGSTOP && is_orphaned_process_group(process.get_pgid()) {
+        return;
+    }
+    if process.job.stop(signal as i32) {
+        info!(
+            "process {} stopped by signal {}",
+            process.pid(),
+            signal as usize
+        );
+        notify_parent(process, ChildState::Stopped(signal as i32));
+    }
+    process.job.wait_while_stopped();
+}
+
+/// 信号产生时丢弃与其冲突的未决信号
+///
+/// SIGCONT 会丢弃所有未决的停止信号，停止信号也会丢弃未决的 SIGCONT
+fn discard_conflicting_signals(signal_modules: &mut BTreeMap<u64, SignalModule>, signo: i32) {
+    let conflicting: &[SignalNo] = if signo == SignalNo::SIGCONT as i32 {
+        &[
+            SignalNo::SIGSTOP,
+            SignalNo::SIGTSTP,
+            SignalNo::SIGTTIN,
+            SignalNo::SIGTTOU,
+        ]
+    } else if is_stop_signal(signo) {
+        &[SignalNo::SIGCONT]
+    } else {
+        return;
+    };
+    for signal_module in signal_modules.values_mut() {
+        for signal in conflicting {
+            signal_module.signal_set.discard_signal(*signal as usize);
+        }
+    }
+}
+
+/// 信号产生时立即生效的动作，无论信号是否被屏蔽或忽略
+///
+/// SIGCONT 使停止的进程继续运行并通知父进程，SIGKILL 则唤醒处于任意停止中的进程以便其退出
+fn signal_generated(process: &Process, signo: i32) {
+    process.signal_wait.notify();
+    if signo == SignalNo::SIGKILL as i32 {
+        process.ptrace.wake_for_kill();
+        process.job.resume(false);
+    } else if signo == SignalNo::SIGCONT as i32 && process.job.resume(true) {
+        info!("process {} continued", process.pid());
+        notify_parent(process, ChildState::Continued);
+    }
+}
+
 /// 发送信号到指定的线程
 pub fn send_signal_to_thread(tid: isize, signum: isize) -> AxResult<()> {
+    send_signal_info_to_thread(tid, SigInfo::new(signum as i32, SI_KERNEL))
+}
+
+/// 用户态执行断点指令或单步执行完一条指令后，向当前线程发送 SIGTRAP
+///
+/// 被跟踪的线程会在处理该信号时进入 signal-delivery-stop，否则按默认动作结束进程
+pub fn send_trap_to_current(single_step: bool) {
+    let code = if single_step { TRAP_TRACE } else { TRAP_BRKPT };
+    let tid = current_task().id().as_u64();
+    let info = SigInfo::new(SignalNo::SIGTRAP as i32, code);
+    let _ = send_signal_info_to_thread(tid as isize, info);
+}
+
+/// 将给定信号恢复为默认处理并在所有线程中解除阻塞，使其随后一定按默认动作处理
+///
+/// 用于内核强制结束进程的场合，例如 seccomp 过滤器要求杀死进程
+pub fn force_default_action(process: &Process, signal: SignalNo) {
+    let sig_num = signal as usize;
+    for signal_module in process.signal_modules.lock().values_mut() {
+        signal_module.signal_handler.lock().handlers[sig_num - 1] = None;
+        signal_module.signal_set.mask &= !(1 << (sig_num - 1));
+    }
+}
+
+/// 发送携带信息的信号到指定的线程
+///
+/// 实时信号排队已满时返回 `WouldBlock`
+pub fn send_signal_info_to_thread(tid: isize, info: SigInfo) -> AxResult<()> {
     let tid2task = TID2TASK.lock();
     let task = if let Some(task) = tid2task.get(&(tid as u64)) {
         Arc::clone(task)
@@ -314,8 +707,13 @@ pub fn send_signal_to_thread(tid: isize, signum: isize) -> AxResult<()> {
     if !signal_modules.contains_key(&(tid as u64)) {
         return Err(axerrno::AxError::NotFound);
     }
+    discard_conflicting_signals(&mut signal_modules, info.si_signo);
     let signal_module = signal_modules.get_mut(&(tid as u64)).unwrap();
-    signal_module.signal_set.try_add_signal(signum as usize);
+    if !signal_module.signal_set.try_add_signal_info(info) {
+        return Err(AxError::WouldBlock);
+    }
+    drop(signal_modules);
+    signal_generated(&process, info.si_signo);
     // 如果这个时候对应的线程是处于休眠状态的，则唤醒之，进入信号处理阶段
     if task.state() == TaskState::Blocked {
         RUN_QUEUE.lock().unblock_task(task, false);
@@ -1,13 +1,10 @@
 use axerrno::{AxError, AxResult};
-use axfs::api::port::{
-    ConsoleWinSize, FileExt, FileIO, FileIOType, OpenFlags, TCGETS, TIOCGPGRP, TIOCGWINSZ,
-    TIOCSPGRP,
-};
-use axhal::console::{getchar, write_bytes};
+use axfs::api::port::{FileExt, FileIO, FileIOType, OpenFlags};
+use axhal::console::write_bytes;
 use axio::{Read, Seek, SeekFrom, Write};
-use axlog::warn;
 use axsync::Mutex;
-use axtask::yield_now;
+
+use crate::tty::console;
 /// stdin file for getting chars from console
 pub struct Stdin {
     pub flags: Mutex<OpenFlags>,
@@ -23,34 +20,18 @@ pub struct Stderr {
     pub flags: Mutex<OpenFlags>,
 }
 
-fn stdin_read(buf: &mut [u8]) -> AxResult<usize> {
-    let ch: u8;
-    loop {
-        match getchar() {
-            Some(c) => {
-                ch = c;
-                break;
-            }
-            None => {
-                yield_now();
-                continue;
-            }
-        }
-    }
-    unsafe {
-        buf.as_mut_ptr().write_volatile(ch);
-    }
-    Ok(1)
+/// 标准输入输出均为控制台终端，读写经过其行规程处理
+fn stdin_read(buf: &mut [u8], nonblock: bool) -> AxResult<usize> {
+    console().read(buf, nonblock)
 }
 
 fn stdout_write(buf: &[u8]) -> AxResult<usize> {
-    write_bytes(buf);
-    Ok(buf.len())
+    console().write(buf, false)
 }
 
 impl Read for Stdin {
     fn read(&mut self, buf: &mut [u8]) -> AxResult<usize> {
-        stdin_read(buf)
+        stdin_read(buf, false)
     }
 }
 
@@ -83,7 +64,8 @@ impl FileExt for Stdin {
 
 impl FileIO for Stdin {
     fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
-        stdin_read(buf)
+        let nonblock = self.flags.lock().contains(OpenFlags::NON_BLOCK);
+        stdin_read(buf, nonblock)
     }
 
     fn get_type(&self) -> FileIOType {
@@ -91,7 +73,7 @@ impl FileIO for Stdin {
     }
 
     fn ready_to_read(&self) -> bool {
-        true
+        console().ready_to_read()
     }
 
     fn ready_to_write(&self) -> bool {
@@ -111,29 +93,7 @@ impl FileIO for Stdin {
     }
 
     fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
-        match request {
-            TIOCGWINSZ => {
-                let winsize = data as *mut ConsoleWinSize;
-                unsafe {
-                    *winsize = ConsoleWinSize::default();
-                }
-                Ok(())
-            }
-            TCGETS | TIOCSPGRP => {
-                warn!("stdin TCGETS | TIOCSPGRP, pretend to be tty.");
-                // pretend to be tty
-                Ok(())
-            }
-
-            TIOCGPGRP => {
-                warn!("stdin TIOCGPGRP, pretend to be have a tty process group.");
-                unsafe {
-                    *(data as *mut u32) = 0;
-                }
-                Ok(())
-            }
-            _ => Err(AxError::Unsupported),
-        }
+        console().ioctl(request, data, false)
     }
 
     fn set_status(&self, flags: OpenFlags) -> bool {
@@ -220,6 +180,10 @@ impl FileIO for Stdout {
         FileIOType::Stdout
     }
 
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        console().ioctl(request, data, false)
+    }
+
     fn ready_to_read(&self) -> bool {
         false
     }
@@ -290,8 +254,7 @@ impl FileExt for Stderr {
 
 impl FileIO for Stderr {
     fn write(&self, buf: &[u8]) -> AxResult<usize> {
-        write_bytes(buf);
-        Ok(buf.len())
+        stdout_write(buf)
     }
 
     /// Stderr is always flushed
@@ -315,6 +278,10 @@ impl FileIO for Stderr {
         FileIOType::Stderr
     }
 
+    fn ioctl(&self, request: usize, data: usize) -> AxResult<()> {
+        console().ioctl(request, data, false)
+    }
+
     fn ready_to_read(&self) -> bool {
         false
     }
@@ -0,0 +1,399 @@
+//! N_TTY 行规程
+//!
+//! 负责终端输入的规范模式行编辑、回显、控制字符识别以及输出的后处理。
+//! 行规程本身不涉及进程，产生的信号由上层发送给终端的前台进程组
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use super::termios::*;
+
+/// 规范模式下一行的最大长度，超出的字符会被丢弃
+const MAX_CANON: usize = 4095;
+
+/// 非规范模式下输入队列的最大长度
+const MAX_INPUT: usize = 4096;
+
+/// 行规程识别出的需要产生信号的控制字符
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TtySignal {
+    /// INTR 字符，产生 SIGINT
+    Interrupt,
+    /// QUIT 字符，产生 SIGQUIT
+    Quit,
+    /// SUSP 字符，产生 SIGTSTP
+    Suspend,
+}
+
+/// N_TTY 行规程的状态
+pub struct LineDiscipline {
+    /// 规范模式下已经结束、可以读取的行。以 EOF 结束的行不包含 EOF 字符
+    lines: VecDeque<Vec<u8>>,
+    /// 规范模式下正在编辑的行
+    editing: Vec<u8>,
+    /// 非规范模式下可以读取的数据
+    raw: VecDeque<u8>,
+    /// 下一个字符按字面输入
+    literal_next: bool,
+    /// 输出是否被 STOP 字符暂停
+    stopped: bool,
+    /// 输出的当前列号，用于回显删除与 ONOCR
+    column: usize,
+}
+
+fn is_utf8_continuation(ch: u8) -> bool {
+    ch & 0xc0 == 0x80
+}
+
+impl LineDiscipline {
+    /// 创建一个空的行规程
+    pub const fn new() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            editing: Vec::new(),
+            raw: VecDeque::new(),
+            literal_next: false,
+            stopped: false,
+            column: 0,
+        }
+    }
+
+    /// 输出是否被暂停
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// 暂停或恢复输出
+    pub fn set_stopped(&mut self, stopped: bool) {
+        self.stopped = stopped;
+    }
+
+    /// 可以读取的字节数
+    pub fn input_len(&self, termios: &Termios) -> usize {
+        if termios.is_canonical() {
+            self.lines.iter().map(|line| line.len()).sum()
+        } else {
+            self.raw.len()
+        }
+    }
+
+    /// 是否有数据可读。规范模式下要求至少有一个完整的行
+    pub fn readable(&self, termios: &Termios) -> bool {
+        if termios.is_canonical() {
+            !self.lines.is_empty()
+        } else {
+            !self.raw.is_empty()
+        }
+    }
+
+    /// 丢弃所有尚未读取的输入
+    pub fn flush_input(&mut self) {
+        self.lines.clear();
+        self.editing.clear();
+        self.raw.clear();
+        self.literal_next = false;
+    }
+
+    /// 终端属性发生变化时调整缓冲区，使规范模式与非规范模式之间的切换不丢失输入
+    pub fn change_mode(&mut self, old: &Termios, new: &Termios) {
+        match (old.is_canonical(), new.is_canonical()) {
+            (true, false) => {
+                for line in self.lines.drain(..) {
+                    self.raw.extend(line);
+                }
+                self.raw.extend(self.editing.drain(..));
+            }
+            (false, true) => {
+                self.editing.extend(self.raw.drain(..));
+            }
+            _ => {}
+        }
+    }
+
+    /// 读取输入。规范模式下一次最多读取一行
+    pub fn read(&mut self, termios: &Termios, buf: &mut [u8]) -> usize {
+        if termios.is_canonical() {
+            let Some(mut line) = self.lines.pop_front() else {
+                return 0;
+            };
+            let len = line.len().min(buf.len());
+            buf[..len].copy_from_slice(&line[..len]);
+            if len < line.len() {
+                // 没有读完的部分留到下一次读取
+                self.lines.push_front(line.split_off(len));
+            }
+            len
+        } else {
+            let len = self.raw.len().min(buf.len());
+            for (dst, src) in buf.iter_mut().zip(self.raw.drain(..len)) {
+                *dst = src;
+            }
+            len
+        }
+    }
+
+    /// 回显一个字符，控制字符在 ECHOCTL 下显示为 `^X`
+    fn echo_char(&self, termios: &Termios, ch: u8, echo: &mut Vec<u8>) {
+        let lflag = termios.lflag();
+        if !lflag.contains(LocalFlags::ECHO) {
+            return;
+        }
+        if lflag.contains(LocalFlags::ECHOCTL) && is_ctrl_char(ch) {
+            echo.push(b'^');
+            echo.push(ch ^ 0x40);
+        } else {
+            echo.push(ch);
+        }
+    }
+
+    /// 回显时一个字符所占的列数
+    fn echo_width(termios: &Termios, ch: u8) -> usize {
+        if termios.lflag().contains(LocalFlags::ECHOCTL) && is_ctrl_char(ch) {
+            2
+        } else if is_utf8_continuation(ch) {
+            0
+        } else {
+            1
+        }
+    }
+
+    /// 删除正在编辑的行中的最后一个字符，返回是否删除成功
+    fn erase_char(&mut self, termios: &Termios, echo: &mut Vec<u8>) -> bool {
+        let utf8 = termios.iflag().contains(InputFlags::IUTF8);
+        let mut width = 0;
+        while let Some(ch) = self.editing.pop() {
+            width += Self::echo_width(termios, ch);
+            if !(utf8 && is_utf8_continuation(ch)) {
+                break;
+            }
+        }
+        if width == 0 {
+            return false;
+        }
+        let lflag = termios.lflag();
+        if lflag.contains(LocalFlags::ECHO) && lflag.contains(LocalFlags::ECHOE) {
+            for _ in 0..width {
+                echo.extend_from_slice(b"\x08 \x08");
+            }
+        }
+        true
+    }
+
+    /// 处理终端收到的一个字符
+    ///
+    /// 需要回显的内容追加到 `echo` 中，由上层经过输出处理后写回终端。
+    /// 若字符为产生信号的控制字符，则返回对应的信号
+    pub fn receive(&mut self, termios: &Termios, ch: u8, echo: &mut Vec<u8>) -> Option<TtySignal> {
+        let iflag = termios.iflag();
+        let lflag = termios.lflag();
+        let mut ch = ch;
+        if iflag.contains(InputFlags::ISTRIP) {
+            ch &= 0x7f;
+        }
+
+        if self.literal_next {
+            self.literal_next = false;
+            if lflag.contains(LocalFlags::ECHO) && lflag.contains(LocalFlags::ECHOCTL) {
+                // 删除之前回显的 `^`
+                echo.extend_from_slice(b"\x08");
+            }
+            self.push_input(termios, ch, echo);
+            return None;
+        }
+
+        if iflag.contains(InputFlags::IXON) {
+            if termios.is_cc(VSTOP, ch) {
+                self.stopped = true;
+                return None;
+            }
+            if termios.is_cc(VSTART, ch) {
+                self.stopped = false;
+                return None;
+            }
+            if iflag.contains(InputFlags::IXANY) {
+                self.stopped = false;
+            }
+        }
+
+        if lflag.contains(LocalFlags::ISIG) {
+            let signal = if termios.is_cc(VINTR, ch) {
+                Some(TtySignal::Interrupt)
+            } else if termios.is_cc(VQUIT, ch) {
+                Some(TtySignal::Quit)
+            } else if termios.is_cc(VSUSP, ch) {
+                Some(TtySignal::Suspend)
+            } else {
+                None
+            };
+            if let Some(signal) = signal {
+                if !lflag.contains(LocalFlags::NOFLSH) {
+                    self.flush_input();
+                }
+                self.echo_char(termios, ch, echo);
+                return Some(signal);
+            }
+        }
+
+        match ch {
+            b'\r' if iflag.contains(InputFlags::IGNCR) => return None,
+            b'\r' if iflag.contains(InputFlags::ICRNL) => ch = b'\n',
+            b'\n' if iflag.contains(InputFlags::INLCR) => ch = b'\r',
+            _ => {}
+        }
+        if iflag.contains(InputFlags::IUCLC) && lflag.contains(LocalFlags::IEXTEN) {
+            ch = ch.to_ascii_lowercase();
+        }
+
+        if lflag.contains(LocalFlags::IEXTEN) && termios.is_cc(VLNEXT, ch) {
+            self.literal_next = true;
+            if lflag.contains(LocalFlags::ECHO) && lflag.contains(LocalFlags::ECHOCTL) {
+                echo.push(b'^');
+            }
+            return None;
+        }
+
+        if !termios.is_canonical() {
+            self.push_input(termios, ch, echo);
+            return None;
+        }
+
+        if termios.is_cc(VERASE, ch) {
+            self.erase_char(termios, echo);
+            return None;
+        }
+        if termios.is_cc(VKILL, ch) {
+            if lflag.contains(LocalFlags::ECHOKE) && lflag.contains(LocalFlags::ECHOE) {
+                while self.erase_char(termios, echo) {}
+            } else {
+                self.editing.clear();
+                self.echo_char(termios, ch, echo);
+                if lflag.contains(LocalFlags::ECHOK) && lflag.contains(LocalFlags::ECHO) {
+                    echo.push(b'\n');
+                }
+            }
+            return None;
+        }
+        if lflag.contains(LocalFlags::IEXTEN) {
+            if termios.is_cc(VWERASE, ch) {
+                // 先删除单词后面的空白，再删除单词本身
+                while self.editing.last().is_some_and(|c| c.is_ascii_whitespace()) {
+                    self.erase_char(termios, echo);
+                }
+                while self
+                    .editing
+                    .last()
+                    .is_some_and(|c| !c.is_ascii_whitespace())
+                {
+                    self.erase_char(termios, echo);
+                }
+                return None;
+            }
+            if termios.is_cc(VREPRINT, ch) {
+                if lflag.contains(LocalFlags::ECHO) {
+                    self.echo_char(termios, ch, echo);
+                    echo.push(b'\n');
+                    for &c in self.editing.iter() {
+                        self.echo_char(termios, c, echo);
+                    }
+                }
+                return None;
+            }
+        }
+        if termios.is_cc(VEOF, ch) {
+            // EOF 结束当前行但不放入输入中，空行即表示文件结束
+            self.lines.push_back(core::mem::take(&mut self.editing));
+            return None;
+        }
+        self.push_input(termios, ch, echo);
+        None
+    }
+
+    /// 将一个普通字符放入输入中
+    fn push_input(&mut self, termios: &Termios, ch: u8, echo: &mut Vec<u8>) {
+        let lflag = termios.lflag();
+        let is_newline = ch == b'\n' || termios.is_cc(VEOL, ch) || termios.is_cc(VEOL2, ch);
+        if termios.is_canonical() {
+            if self.editing.len() >= MAX_CANON && !is_newline {
+                return;
+            }
+            self.editing.push(ch);
+            if is_newline {
+                self.lines.push_back(core::mem::take(&mut self.editing));
+            }
+        } else {
+            if self.raw.len() >= MAX_INPUT {
+                return;
+            }
+            self.raw.push_back(ch);
+        }
+        if ch == b'\n' {
+            if lflag.contains(LocalFlags::ECHO)
+                || (lflag.contains(LocalFlags::ECHONL) && termios.is_canonical())
+            {
+                echo.push(b'\n');
+            }
+        } else {
+            self.echo_char(termios, ch, echo);
+        }
+    }
+
+    /// 按照输出模式处理要写到终端的数据
+    pub fn process_output(&mut self, termios: &Termios, buf: &[u8], out: &mut Vec<u8>) {
+        let oflag = termios.oflag();
+        if !oflag.contains(OutputFlags::OPOST) {
+            out.extend_from_slice(buf);
+            return;
+        }
+        for &ch in buf {
+            let ch = if oflag.contains(OutputFlags::OLCUC) {
+                ch.to_ascii_uppercase()
+            } else {
+                ch
+            };
+            match ch {
+                b'\n' => {
+                    if oflag.contains(OutputFlags::ONLCR) {
+                        out.push(b'\r');
+                        self.column = 0;
+                    }
+                    if oflag.contains(OutputFlags::ONLRET) {
+                        self.column = 0;
+                    }
+                    out.push(b'\n');
+                }
+                b'\r' => {
+                    if oflag.contains(OutputFlags::ONOCR) && self.column == 0 {
+                        continue;
+                    }
+                    if oflag.contains(OutputFlags::OCRNL) {
+                        out.push(b'\n');
+                        if oflag.contains(OutputFlags::ONLRET) {
+                            self.column = 0;
+                        }
+                    } else {
+                        out.push(b'\r');
+                        self.column = 0;
+                    }
+                }
+                b'\t' => {
+                    self.column = (self.column | 7) + 1;
+                    out.push(ch);
+                }
+                0x08 => {
+                    self.column = self.column.saturating_sub(1);
+                    out.push(ch);
+                }
+                _ => {
+                    if !is_ctrl_char(ch) && !is_utf8_continuation(ch) {
+                        self.column += 1;
+                    }
+                    out.push(ch);
+                }
+            }
+        }
+    }
+}
+
+/// 是否为需要以 `^X` 形式回显的控制字符，制表符与换行除外
+fn is_ctrl_char(ch: u8) -> bool {
+    (ch < 0x20 && ch != b'\t' && ch != b'\n') || ch == 0x7f
+}
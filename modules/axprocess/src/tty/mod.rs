@@ -0,0 +1,889 @@
+//! 终端子系统
+//!
+//! 每个终端由一个 [`Tty`] 表示，输入经过 N_TTY 行规程处理后供进程读取，
+//! 输出经过输出处理后交给终端驱动。当前的终端驱动有两种：
+//! - 控制台，即 `/dev/console`，对应串口输入输出
+//! - 伪终端，即 `/dev/ptmx` 与 `/dev/pts/<n>`，主设备端由用户进程读写
+//!
+//! 终端可以成为一个会话的控制终端，并记录该会话的前台进程组。
+//! 终端产生的 SIGINT、SIGQUIT、SIGTSTP 以及后台进程访问终端时的 SIGTTIN、SIGTTOU 都发送给对应的进程组
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags, PollNotifier, PollWaker};
+use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeType, VfsOps, VfsResult};
+use axhal::console::{getchar, write_bytes};
+use axhal::time::{current_time, TimeValue};
+use axsync::Mutex;
+use axtask::WaitQueue;
+use lazy_static::lazy_static;
+
+use crate::{current_process, Process, PID2PC};
+
+mod ldisc;
+mod pty;
+pub mod termios;
+
+use ldisc::LineDiscipline;
+pub use pty::{DevPtsFs, PtyMaster};
+use termios::*;
+
+/// 终端驱动，负责终端与实际设备之间的数据传输
+pub trait TtyDriver: Send + Sync {
+    /// 将经过输出处理的数据写到设备
+    fn write(&self, buf: &[u8]);
+
+    /// 从设备中取出已经到达的输入并交给终端处理
+    ///
+    /// 对于输入由其他方主动送入的设备，如伪终端，无需实现
+    fn poll_input(&self, _tty: &Tty) {}
+
+    /// 终端的就绪状态变化时是否会主动通知等待者
+    fn can_notify(&self) -> bool {
+        true
+    }
+
+    /// 终端的最后一个文件描述符被关闭
+    fn last_close(&self) {}
+}
+
+/// 控制台驱动，通过轮询串口获取输入
+struct ConsoleDriver;
+
+impl TtyDriver for ConsoleDriver {
+    fn write(&self, buf: &[u8]) {
+        write_bytes(buf);
+    }
+
+    fn poll_input(&self, tty: &Tty) {
+        let mut input = Vec::new();
+        while let Some(ch) = getchar() {
+            input.push(ch);
+        }
+        if !input.is_empty() {
+            tty.receive(&input);
+        }
+    }
+
+    fn can_notify(&self) -> bool {
+        // 串口输入需要轮询，等待者需要定期重新检查
+        false
+    }
+}
+
+struct TtyInner {
+    termios: Termios,
+    winsize: WinSize,
+    ldisc: LineDiscipline,
+    /// 以该终端为控制终端的会话
+    session: Option<u64>,
+    /// 前台进程组
+    foreground: Option<u64>,
+    /// 终端是否已经被挂断，挂断后读取返回文件结束，写入返回 EIO
+    hung_up: bool,
+}
+
+/// 一个终端设备
+pub struct Tty {
+    name: String,
+    driver: Box<dyn TtyDriver>,
+    inner: Mutex<TtyInner>,
+    /// 打开该终端的文件描述符数目
+    open_count: AtomicUsize,
+    notifier: PollNotifier,
+}
+
+lazy_static! {
+    static ref CONSOLE: Arc<Tty> = Tty::new(String::from("console"), Box::new(ConsoleDriver));
+    static ref DEVPTS: Arc<DevPtsFs> = Arc::new(DevPtsFs::new());
+}
+
+/// 系统控制台对应的终端
+pub fn console() -> Arc<Tty> {
+    CONSOLE.clone()
+}
+
+/// 从控制台取出已经到达的输入
+///
+/// 控制台没有输入中断，由返回用户态前的检查调用，使前台进程在不读取终端时也能收到 Ctrl-C 等信号
+pub fn poll_console() {
+    CONSOLE.driver.poll_input(&CONSOLE);
+}
+
+/// 读取用户地址处的值
+fn read_user<T: Copy>(addr: usize) -> AxResult<T> {
+    let ptr = addr as *const T;
+    if ptr.is_null() || current_process().manual_alloc_type_for_lazy(ptr).is_err() {
+        return Err(AxError::BadAddress);
+    }
+    Ok(unsafe { *ptr })
+}
+
+/// 向用户地址写入一个值
+fn write_user<T>(addr: usize, value: T) -> AxResult<()> {
+    let ptr = addr as *mut T;
+    if ptr.is_null()
+        || current_process()
+            .manual_alloc_type_for_lazy(ptr as *const T)
+            .is_err()
+    {
+        return Err(AxError::BadAddress);
+    }
+    unsafe {
+        *ptr = value;
+    }
+    Ok(())
+}
+
+/// 等待不会主动通知的终端（控制台）时，重新检查输入的间隔
+const POLL_RECHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 阻塞在终端读写上的线程，登记在终端的 [`PollNotifier`] 上，就绪状态变化时被唤醒
+///
+/// 析构时自动注销
+struct TtyWait<'a> {
+    waiter: Arc<TtyWaiter>,
+    waker: Arc<dyn PollWaker>,
+    notifier: &'a PollNotifier,
+}
+
+struct TtyWaiter {
+    /// 自上次 [`TtyWait::prepare`] 以来是否收到过通知
+    pending: AtomicBool,
+    wait_queue: WaitQueue,
+}
+
+impl PollWaker for TtyWaiter {
+    fn wake(&self) {
+        self.pending.store(true, Ordering::SeqCst);
+        self.wait_queue.notify_all(true);
+    }
+}
+
+impl<'a> TtyWait<'a> {
+    /// 在 `notifier` 上登记当前线程
+    fn new(notifier: &'a PollNotifier) -> Self {
+        let waiter = Arc::new(TtyWaiter {
+            pending: AtomicBool::new(false),
+            wait_queue: WaitQueue::new(),
+        });
+        let waker: Arc<dyn PollWaker> = waiter.clone();
+        notifier.register(&waker);
+        Self {
+            waiter,
+            waker,
+            notifier,
+        }
+    }
+
+    /// 检查就绪状态前调用，此后收到的通知都会使下一次等待立即返回
+    fn prepare(&self) {
+        self.waiter.pending.store(false, Ordering::SeqCst);
+    }
+
+    /// 阻塞直到收到通知、到达 `deadline` 或者被信号打断
+    fn wait(&self, deadline: Option<TimeValue>) {
+        let waiter = &self.waiter;
+        waiter
+            .wait_queue
+            .wait_once_unless(deadline, || waiter.pending.load(Ordering::SeqCst));
+    }
+}
+
+impl Drop for TtyWait<'_> {
+    fn drop(&mut self) {
+        self.notifier.unregister(&self.waker);
+    }
+}
+
+/// 当前线程是否有待处理的信号，有则阻塞的操作应当被打断
+fn interrupted() -> bool {
+    #[cfg(feature = "signal")]
+    if current_process().have_signals().is_some() {
+        return true;
+    }
+    false
+}
+
+#[cfg(feature = "signal")]
+fn send_to_group(pgid: u64, signal: axsignal::signal_no::SignalNo) {
+    let _ = crate::signal::send_signal_to_process_group(pgid, signal as isize);
+}
+
+/// 当前线程是否忽略或阻塞了给定的信号
+#[cfg(feature = "signal")]
+fn signal_ignored_or_blocked(process: &Process, signal: axsignal::signal_no::SignalNo) -> bool {
+    let tid = crate::current_task().id().as_u64();
+    let signal_modules = process.signal_modules.lock();
+    let Some(signal_module) = signal_modules.get(&tid) else {
+        return false;
+    };
+    if signal_module.signal_set.mask & (1 << (signal as usize - 1)) != 0 {
+        return true;
+    }
+    let handler = signal_module.signal_handler.lock();
+    handler
+        .get_action(signal as usize)
+        .is_some_and(|action| action.sa_handler == axsignal::action::SIG_IGN)
+}
+
+impl Tty {
+    /// 创建一个使用给定驱动的终端
+    pub fn new(name: String, driver: Box<dyn TtyDriver>) -> Arc<Self> {
+        Arc::new(Self {
+            name,
+            driver,
+            inner: Mutex::new(TtyInner {
+                termios: Termios::default(),
+                winsize: WinSize::default(),
+                ldisc: LineDiscipline::new(),
+                session: None,
+                foreground: None,
+                hung_up: false,
+            }),
+            open_count: AtomicUsize::new(0),
+            notifier: PollNotifier::new(),
+        })
+    }
+
+    /// 终端的名称，如 `console`、`pts/0`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 以该终端为控制终端的会话
+    pub fn session(&self) -> Option<u64> {
+        self.inner.lock().session
+    }
+
+    /// 终端的前台进程组
+    pub fn foreground(&self) -> Option<u64> {
+        self.inner.lock().foreground
+    }
+
+    /// 记录一次打开
+    pub fn open(&self) {
+        self.open_count.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// 记录一次关闭，最后一次关闭时通知驱动
+    pub fn close(&self) {
+        if self.open_count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.driver.last_close();
+        }
+    }
+
+    /// 当前打开该终端的文件描述符数目
+    pub fn open_count(&self) -> usize {
+        self.open_count.load(Ordering::Acquire)
+    }
+
+    /// 终端收到来自设备的输入，如串口输入或伪终端主设备的写入
+    pub fn receive(&self, input: &[u8]) {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        let mut echo = Vec::new();
+        let mut signals = Vec::new();
+        for &ch in input {
+            if let Some(signal) = inner.ldisc.receive(&inner.termios, ch, &mut echo) {
+                signals.push(signal);
+            }
+        }
+        let mut output = Vec::new();
+        if !echo.is_empty() {
+            inner
+                .ldisc
+                .process_output(&inner.termios, &echo, &mut output);
+        }
+        let foreground = inner.foreground;
+        drop(inner);
+        if !output.is_empty() {
+            self.driver.write(&output);
+        }
+        #[cfg(feature = "signal")]
+        if let Some(pgid) = foreground {
+            use axsignal::signal_no::SignalNo;
+            for signal in signals {
+                let signal = match signal {
+                    ldisc::TtySignal::Interrupt => SignalNo::SIGINT,
+                    ldisc::TtySignal::Quit => SignalNo::SIGQUIT,
+                    ldisc::TtySignal::Suspend => SignalNo::SIGTSTP,
+                };
+                send_to_group(pgid, signal);
+            }
+        }
+        #[cfg(not(feature = "signal"))]
+        let _ = (foreground, signals);
+        self.notifier.notify();
+    }
+
+    /// 检查当前进程是否为该终端所属会话中的后台进程
+    ///
+    /// 后台进程读终端，或在设置了 TOSTOP 时写终端、修改终端属性，会使其所在进程组收到 SIGTTIN 或 SIGTTOU。
+    /// 若进程忽略或阻塞了该信号，则读取返回 EIO，写入照常进行。
+    /// 孤儿进程组收到的停止信号会被丢弃，因此其中的后台进程访问终端时直接返回 EIO，
+    /// 而不是反复产生无效的信号
+    #[cfg(feature = "signal")]
+    fn job_control(&self, is_read: bool) -> AxResult<()> {
+        use axsignal::signal_no::SignalNo;
+        let process = current_process();
+        if !process.is_controlling_tty(self) {
+            return Ok(());
+        }
+        let pgid = process.get_pgid();
+        if self
+            .foreground()
+            .map_or(true, |foreground| foreground == pgid)
+        {
+            return Ok(());
+        }
+        let signal = if is_read {
+            SignalNo::SIGTTIN
+        } else {
+            SignalNo::SIGTTOU
+        };
+        if signal_ignored_or_blocked(&process, signal) {
+            return if is_read { Err(AxError::Io) } else { Ok(()) };
+        }
+        if crate::signal::is_orphaned_process_group(pgid) {
+            return Err(AxError::Io);
+        }
+        send_to_group(pgid, signal);
+        Err(AxError::Interrupted)
+    }
+
+    /// 没有信号时不进行作业控制
+    #[cfg(not(feature = "signal"))]
+    fn job_control(&self, _is_read: bool) -> AxResult<()> {
+        Ok(())
+    }
+
+    /// 非规范模式下，读取是否已经满足 VMIN 与 VTIME 的要求
+    fn raw_read_ready(inner: &TtyInner, len: usize, waited: Duration) -> bool {
+        let min = inner.termios.c_cc[VMIN] as usize;
+        let time = Duration::from_millis(inner.termios.c_cc[VTIME] as u64 * 100);
+        let available = inner.ldisc.input_len(&inner.termios);
+        match (min, time.is_zero()) {
+            (0, true) => true,
+            (0, false) => available > 0 || waited >= time,
+            (min, true) => available >= min.min(len),
+            (min, false) => available >= min.min(len) || (available > 0 && waited >= time),
+        }
+    }
+
+    /// 读取终端输入
+    pub fn read(&self, buf: &mut [u8], nonblock: bool) -> AxResult<usize> {
+        self.job_control(true)?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let start = current_time();
+        let wait = TtyWait::new(&self.notifier);
+        loop {
+            // 先清除通知再检查，此后到达的输入会使等待立即返回
+            wait.prepare();
+            self.driver.poll_input(self);
+            let mut inner = self.inner.lock();
+            let inner = &mut *inner;
+            let ready = if inner.termios.is_canonical() {
+                inner.ldisc.readable(&inner.termios)
+            } else {
+                Self::raw_read_ready(inner, buf.len(), current_time() - start)
+            };
+            if ready {
+                let len = inner.ldisc.read(&inner.termios, buf);
+                drop(inner);
+                self.notifier.notify();
+                return Ok(len);
+            }
+            if inner.hung_up {
+                return Ok(0);
+            }
+            if nonblock {
+                return Err(AxError::WouldBlock);
+            }
+            // 非规范模式下 VTIME 到期时读取也会返回
+            let vtime = Duration::from_millis(inner.termios.c_cc[VTIME] as u64 * 100);
+            let deadline =
+                (!inner.termios.is_canonical() && !vtime.is_zero()).then_some(start + vtime);
+            drop(inner);
+            if interrupted() {
+                return Err(AxError::Interrupted);
+            }
+            wait.wait(self.recheck_deadline(deadline));
+        }
+    }
+
+    /// 向终端写入数据
+    pub fn write(&self, buf: &[u8], nonblock: bool) -> AxResult<usize> {
+        if self
+            .inner
+            .lock()
+            .termios
+            .lflag()
+            .contains(LocalFlags::TOSTOP)
+        {
+            self.job_control(false)?;
+        }
+        let wait = TtyWait::new(&self.notifier);
+        loop {
+            wait.prepare();
+            let mut inner = self.inner.lock();
+            if inner.hung_up {
+                return Err(AxError::Io);
+            }
+            if !inner.ldisc.is_stopped() {
+                let inner = &mut *inner;
+                let mut output = Vec::with_capacity(buf.len());
+                inner.ldisc.process_output(&inner.termios, buf, &mut output);
+                drop(inner);
+                self.driver.write(&output);
+                return Ok(buf.len());
+            }
+            // 输出被 STOP 字符暂停，等待 START 字符
+            if nonblock {
+                return Err(AxError::WouldBlock);
+            }
+            drop(inner);
+            if interrupted() {
+                return Err(AxError::Interrupted);
+            }
+            self.driver.poll_input(self);
+            wait.wait(self.recheck_deadline(None));
+        }
+    }
+
+    /// 阻塞等待的截止时间，不会主动通知的终端需要定期醒来重新检查输入
+    fn recheck_deadline(&self, deadline: Option<TimeValue>) -> Option<TimeValue> {
+        if self.driver.can_notify() {
+            return deadline;
+        }
+        let recheck = current_time() + POLL_RECHECK_INTERVAL;
+        Some(deadline.map_or(recheck, |deadline| deadline.min(recheck)))
+    }
+
+    /// 是否有数据可读，挂断后总是可读
+    pub fn ready_to_read(&self) -> bool {
+        self.driver.poll_input(self);
+        let inner = self.inner.lock();
+        if inner.hung_up {
+            return true;
+        }
+        if inner.termios.is_canonical() {
+            inner.ldisc.readable(&inner.termios)
+        } else {
+            inner.termios.c_cc[VMIN] == 0 || inner.ldisc.readable(&inner.termios)
+        }
+    }
+
+    /// 是否可以写入
+    pub fn ready_to_write(&self) -> bool {
+        let inner = self.inner.lock();
+        inner.hung_up || !inner.ldisc.is_stopped()
+    }
+
+    /// 是否已经被挂断
+    pub fn is_hung_up(&self) -> bool {
+        self.inner.lock().hung_up
+    }
+
+    /// 就绪状态变化的通知列表，控制台需要轮询因此没有
+    pub fn poll_notifier(&self) -> Option<&PollNotifier> {
+        if self.driver.can_notify() {
+            Some(&self.notifier)
+        } else {
+            None
+        }
+    }
+
+    /// 挂断终端：向会话首进程与前台进程组发送 SIGHUP 与 SIGCONT，并解除与会话的关联
+    pub fn hangup(&self) {
+        let mut inner = self.inner.lock();
+        inner.hung_up = true;
+        let session = inner.session.take();
+        let foreground = inner.foreground.take();
+        drop(inner);
+        if let Some(sid) = session {
+            detach_session(sid, self);
+            #[cfg(feature = "signal")]
+            {
+                use axsignal::signal_no::SignalNo;
+                let _ =
+                    crate::signal::send_signal_to_process(sid as isize, SignalNo::SIGHUP as isize);
+                if let Some(pgid) = foreground {
+                    send_to_group(pgid, SignalNo::SIGHUP);
+                    send_to_group(pgid, SignalNo::SIGCONT);
+                }
+            }
+        }
+        #[cfg(not(feature = "signal"))]
+        let _ = foreground;
+        self.notifier.notify();
+    }
+
+    /// 将终端设置为给定会话首进程的控制终端
+    ///
+    /// `steal` 为真时可以从其他会话夺取该终端
+    fn attach(self: &Arc<Self>, process: &Process, steal: bool) -> AxResult<()> {
+        if !process.is_session_leader() || process.get_ctty().is_some() {
+            return Err(AxError::PermissionDenied);
+        }
+        let mut inner = self.inner.lock();
+        if let Some(sid) = inner.session {
+            if sid == process.get_sid() {
+                return Ok(());
+            }
+            if !steal {
+                return Err(AxError::PermissionDenied);
+            }
+            drop(inner);
+            detach_session(sid, self);
+            inner = self.inner.lock();
+        }
+        inner.session = Some(process.get_sid());
+        inner.foreground = Some(process.get_pgid());
+        inner.hung_up = false;
+        drop(inner);
+        process.set_ctty(Some(self.clone()));
+        Ok(())
+    }
+
+    /// 打开终端时，若调用者是没有控制终端的会话首进程，且终端不属于其他会话，则成为其控制终端
+    pub fn attach_on_open(self: &Arc<Self>, flags: OpenFlags) {
+        if flags.contains(OpenFlags::NOCTTY) {
+            return;
+        }
+        let process = current_process();
+        if process.is_session_leader() && process.get_ctty().is_none() && self.session().is_none() {
+            let _ = self.attach(&process, false);
+        }
+    }
+
+    /// 处理终端相关的 ioctl 请求
+    ///
+    /// `from_master` 表示请求来自伪终端的主设备端，此时不要求终端是调用者的控制终端
+    pub fn ioctl(self: &Arc<Self>, request: usize, arg: usize, from_master: bool) -> AxResult<()> {
+        let process = current_process();
+        let is_ctty = from_master || process.is_controlling_tty(self);
+        match request {
+            TCGETS => write_user(arg, self.inner.lock().termios),
+            TCSETS | TCSETSW | TCSETSF => {
+                let termios: Termios = read_user(arg)?;
+                self.job_control(false)?;
+                let mut inner = self.inner.lock();
+                let inner = &mut *inner;
+                if request == TCSETSF {
+                    inner.ldisc.flush_input();
+                }
+                inner.ldisc.change_mode(&inner.termios, &termios);
+                inner.termios = termios;
+                Ok(())
+            }
+            TIOCGWINSZ => write_user(arg, self.inner.lock().winsize),
+            TIOCSWINSZ => {
+                let winsize: WinSize = read_user(arg)?;
+                let mut inner = self.inner.lock();
+                if inner.winsize == winsize {
+                    return Ok(());
+                }
+                inner.winsize = winsize;
+                let foreground = inner.foreground;
+                drop(inner);
+                #[cfg(feature = "signal")]
+                if let Some(pgid) = foreground {
+                    send_to_group(pgid, axsignal::signal_no::SignalNo::SIGWINCH);
+                }
+                #[cfg(not(feature = "signal"))]
+                let _ = foreground;
+                Ok(())
+            }
+            TIOCGPGRP => {
+                if !is_ctty {
+                    return Err(AxError::Unsupported);
+                }
+                let foreground = self.foreground().unwrap_or(0);
+                write_user(arg, foreground as i32)
+            }
+            TIOCSPGRP => {
+                if !is_ctty {
+                    return Err(AxError::Unsupported);
+                }
+                let pgid: i32 = read_user(arg)?;
+                if pgid < 0 {
+                    return Err(AxError::InvalidInput);
+                }
+                self.job_control(false)?;
+                let session = self.session();
+                let exists = PID2PC.lock().values().any(|process| {
+                    process.get_pgid() == pgid as u64 && Some(process.get_sid()) == session
+                });
+                if !exists {
+                    return Err(AxError::PermissionDenied);
+                }
+                self.inner.lock().foreground = Some(pgid as u64);
+                Ok(())
+            }
+            TIOCGSID => {
+                if !is_ctty {
+                    return Err(AxError::Unsupported);
+                }
+                let session = self.session().ok_or(AxError::Unsupported)?;
+                write_user(arg, session as i32)
+            }
+            TIOCSCTTY => self.attach(&process, arg == 1),
+            TIOCNOTTY => {
+                if !process.is_controlling_tty(self) {
+                    return Err(AxError::Unsupported);
+                }
+                if process.is_session_leader() {
+                    // 会话首进程放弃控制终端时，整个会话都失去控制终端
+                    let mut inner = self.inner.lock();
+                    inner.session = None;
+                    let foreground = inner.foreground.take();
+                    drop(inner);
+                    detach_session(process.get_sid(), self);
+                    #[cfg(feature = "signal")]
+                    if let Some(pgid) = foreground {
+                        use axsignal::signal_no::SignalNo;
+                        send_to_group(pgid, SignalNo::SIGHUP);
+                        send_to_group(pgid, SignalNo::SIGCONT);
+                    }
+                    #[cfg(not(feature = "signal"))]
+                    let _ = foreground;
+                } else {
+                    process.set_ctty(None);
+                }
+                Ok(())
+            }
+            FIONREAD => {
+                let inner = self.inner.lock();
+                let len = inner.ldisc.input_len(&inner.termios);
+                drop(inner);
+                write_user(arg, len as i32)
+            }
+            TIOCOUTQ => write_user(arg, 0i32),
+            TCFLSH => {
+                self.job_control(false)?;
+                match arg {
+                    TCIFLUSH | TCIOFLUSH => self.inner.lock().ldisc.flush_input(),
+                    TCOFLUSH => {}
+                    _ => return Err(AxError::InvalidInput),
+                }
+                Ok(())
+            }
+            TCXONC => {
+                self.job_control(false)?;
+                match arg {
+                    TCOOFF => self.inner.lock().ldisc.set_stopped(true),
+                    TCOON => self.inner.lock().ldisc.set_stopped(false),
+                    _ => {}
+                }
+                self.notifier.notify();
+                Ok(())
+            }
+            _ => Err(AxError::Unsupported),
+        }
+    }
+}
+
+/// 将控制台设置为初始进程的控制终端
+pub(crate) fn attach_console(process: &Process) {
+    let _ = CONSOLE.attach(process, true);
+}
+
+/// 会话中的所有进程失去控制终端 `tty`
+fn detach_session(sid: u64, tty: &Tty) {
+    for process in PID2PC.lock().values() {
+        if process.get_sid() == sid && process.is_controlling_tty(tty) {
+            process.set_ctty(None);
+        }
+    }
+}
+
+/// 会话首进程退出时挂断其控制终端
+pub fn session_leader_exit(process: &Process) {
+    if !process.is_session_leader() {
+        return;
+    }
+    if let Some(tty) = process.get_ctty() {
+        if tty.session() == Some(process.get_sid()) {
+            tty.hangup();
+            // 控制台在会话结束后可以被新的会话使用
+            if Arc::ptr_eq(&tty, &CONSOLE) {
+                tty.inner.lock().hung_up = false;
+            }
+        }
+    }
+    process.set_ctty(None);
+}
+
+/// 打开的终端文件，对应 `/dev/console`、`/dev/tty` 与 `/dev/pts/<n>`
+pub struct TtyFile {
+    tty: Arc<Tty>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl TtyFile {
+    /// 打开给定的终端
+    pub fn new(tty: Arc<Tty>, flags: OpenFlags) -> Self {
+        tty.open();
+        Self {
+            tty,
+            flags: Mutex::new(flags),
+        }
+    }
+
+    /// 对应的终端
+    pub fn tty(&self) -> &Arc<Tty> {
+        &self.tty
+    }
+
+    fn nonblock(&self) -> bool {
+        self.flags.lock().contains(OpenFlags::NON_BLOCK)
+    }
+}
+
+impl Drop for TtyFile {
+    fn drop(&mut self) {
+        self.tty.close();
+    }
+}
+
+impl FileIO for TtyFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        self.tty.read(buf, self.nonblock())
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        self.tty.write(buf, self.nonblock())
+    }
+
+    fn flush(&self) -> AxResult {
+        Ok(())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        alloc::format!("/dev/{}", self.tty.name())
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+
+    fn is_hang_up(&self) -> bool {
+        self.tty.is_hung_up()
+    }
+
+    fn ready_to_read(&self) -> bool {
+        self.tty.ready_to_read()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        self.tty.ready_to_write()
+    }
+
+    fn ioctl(&self, request: usize, arg: usize) -> AxResult<()> {
+        self.tty.ioctl(request, arg, false)
+    }
+
+    fn poll_notifier(&self) -> Option<&PollNotifier> {
+        self.tty.poll_notifier()
+    }
+}
+
+/// 若路径为终端设备，则打开对应的终端文件
+///
+/// 路径不是终端设备时返回 None。支持的设备有：
+/// - `/dev/console`：系统控制台
+/// - `/dev/tty`：调用进程的控制终端
+/// - `/dev/ptmx` 与 `/dev/pts/ptmx`：新建一对伪终端，返回主设备端
+/// - `/dev/pts/<n>`：伪终端的从设备端
+pub fn open_tty_device(path: &str, flags: OpenFlags) -> Option<AxResult<Arc<dyn FileIO>>> {
+    let name = path.trim_start_matches('/').strip_prefix("dev/")?;
+    let tty = match name {
+        "console" => console(),
+        "tty" => match current_process().get_ctty() {
+            // `/dev/tty` 总是指向控制终端，不会改变控制终端
+            Some(tty) => return Some(Ok(Arc::new(TtyFile::new(tty, flags)))),
+            None => return Some(Err(AxError::NotFound)),
+        },
+        "ptmx" | "pts/ptmx" => {
+            return Some(pty::open_master(flags).map(|master| Arc::new(master) as Arc<dyn FileIO>))
+        }
+        _ => {
+            let index = name.strip_prefix("pts/")?.parse::<usize>().ok()?;
+            match pty::open_slave(index) {
+                Ok(tty) => tty,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    };
+    tty.attach_on_open(flags);
+    Some(Ok(Arc::new(TtyFile::new(tty, flags))))
+}
+
+/// `/dev` 下终端设备的文件系统节点，用于查询属性与列出目录，实际的读写通过 [`open_tty_device`] 完成
+struct TtyDeviceNode;
+
+impl VfsNodeOps for TtyDeviceNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new(
+            VfsNodePerm::from_bits_truncate(0o666),
+            VfsNodeType::CharDevice,
+            0,
+            0,
+        ))
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+/// 在 `/dev` 下注册终端设备与 devpts 文件系统
+pub fn init_tty_devices() {
+    let _ = axfs::api::add_device("console", Arc::new(TtyDeviceNode));
+    let _ = axfs::api::add_device("tty", Arc::new(TtyDeviceNode));
+    let _ = axfs::api::add_device("ptmx", Arc::new(TtyDeviceNode));
+    let _ = axfs::api::add_device("pts", DEVPTS.root());
+    axfs::api::register_filesystem("devpts", mount_devpts);
+}
+
+/// 挂载 devpts，所有挂载点共享同一组伪终端
+fn mount_devpts(_source: &str) -> AxResult<Arc<dyn VfsOps>> {
+    Ok(DEVPTS.clone())
+}
@@ -0,0 +1,340 @@
+//! 伪终端与 devpts 文件系统
+//!
+//! 打开 `/dev/ptmx` 会新建一对伪终端并返回主设备端，从设备端出现在 `/dev/pts/<n>`。
+//! 写入主设备端的数据作为从设备端的输入，经过行规程处理；从设备端的输出经过输出处理后可以从主设备端读出。
+//! 与 Linux 一致，新建的从设备端处于锁定状态，需要通过 `TIOCSPTLCK` 解锁后才能打开
+extern crate alloc;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags, PollNotifier};
+use axfs_vfs::{
+    VfsDirEntry, VfsError, VfsNodeAttr, VfsNodeOps, VfsNodePerm, VfsNodeRef, VfsNodeType, VfsOps,
+    VfsResult,
+};
+use axsync::Mutex;
+
+use crate::cred::{remove_file_owner, set_file_owner, FileOwner};
+use crate::current_process;
+
+use super::termios::{TIOCGPTN, TIOCSPTLCK};
+use super::{interrupted, read_user, write_user, Tty, TtyDriver, TtyWait};
+
+/// 从设备端的输出，等待主设备端读取
+struct PtyOutput {
+    buf: Mutex<VecDeque<u8>>,
+    notifier: PollNotifier,
+}
+
+/// 伪终端从设备端的驱动，将输出交给主设备端
+struct PtyDriver {
+    output: Arc<PtyOutput>,
+}
+
+impl TtyDriver for PtyDriver {
+    fn write(&self, buf: &[u8]) {
+        self.output.buf.lock().extend(buf);
+        self.output.notifier.notify();
+    }
+
+    fn last_close(&self) {
+        // 从设备端全部关闭后主设备端的读取返回 EIO，需要唤醒等待者
+        self.output.notifier.notify();
+    }
+}
+
+/// 一对伪终端
+struct PtyPair {
+    index: usize,
+    slave: Arc<Tty>,
+    output: Arc<PtyOutput>,
+    /// 从设备端是否被锁定
+    locked: AtomicBool,
+    /// 从设备端是否被打开过
+    slave_opened: AtomicBool,
+}
+
+impl PtyPair {
+    /// 从设备端是否已经被全部关闭
+    fn slave_closed(&self) -> bool {
+        self.slave_opened.load(Ordering::Acquire) && self.slave.open_count() == 0
+    }
+}
+
+/// 当前存在的所有伪终端，以编号为键
+static PTYS: Mutex<BTreeMap<usize, Arc<PtyPair>>> = Mutex::new(BTreeMap::new());
+
+/// 新建一对伪终端，返回主设备端
+pub(super) fn open_master(flags: OpenFlags) -> AxResult<PtyMaster> {
+    let mut ptys = PTYS.lock();
+    // 使用最小的空闲编号
+    let index = (0..).find(|index| !ptys.contains_key(index)).unwrap();
+    let output = Arc::new(PtyOutput {
+        buf: Mutex::new(VecDeque::new()),
+        notifier: PollNotifier::new(),
+    });
+    let slave = Tty::new(
+        format!("pts/{}", index),
+        Box::new(PtyDriver {
+            output: output.clone(),
+        }),
+    );
+    let pair = Arc::new(PtyPair {
+        index,
+        slave,
+        output,
+        locked: AtomicBool::new(true),
+        slave_opened: AtomicBool::new(false),
+    });
+    ptys.insert(index, pair.clone());
+    // 与 Linux 一致，从设备端属于打开主设备端的用户
+    let cred = current_process().cred();
+    set_file_owner(
+        &format!("/dev/pts/{}", index),
+        FileOwner {
+            uid: cred.euid,
+            gid: cred.egid,
+            mode: 0o620,
+            is_dir: false,
+        },
+    );
+    Ok(PtyMaster {
+        pair,
+        flags: Mutex::new(flags),
+    })
+}
+
+/// 打开编号为 index 的伪终端从设备端
+pub(super) fn open_slave(index: usize) -> AxResult<Arc<Tty>> {
+    let pair = PTYS.lock().get(&index).cloned().ok_or(AxError::NotFound)?;
+    if pair.locked.load(Ordering::Acquire) {
+        return Err(AxError::Io);
+    }
+    pair.slave_opened.store(true, Ordering::Release);
+    Ok(pair.slave.clone())
+}
+
+/// 伪终端的主设备端
+pub struct PtyMaster {
+    pair: Arc<PtyPair>,
+    flags: Mutex<OpenFlags>,
+}
+
+impl Drop for PtyMaster {
+    fn drop(&mut self) {
+        // 主设备端关闭即挂断从设备端
+        self.pair.slave.hangup();
+        PTYS.lock().remove(&self.pair.index);
+        remove_file_owner(&format!("/dev/pts/{}", self.pair.index));
+    }
+}
+
+impl FileIO for PtyMaster {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let wait = TtyWait::new(&self.pair.output.notifier);
+        loop {
+            // 先清除通知再检查，此后从设备端的输出会使等待立即返回
+            wait.prepare();
+            let mut output = self.pair.output.buf.lock();
+            if !output.is_empty() {
+                let len = buf.len().min(output.len());
+                for (dst, src) in buf.iter_mut().zip(output.drain(..len)) {
+                    *dst = src;
+                }
+                return Ok(len);
+            }
+            drop(output);
+            if self.pair.slave_closed() {
+                return Err(AxError::Io);
+            }
+            if self.flags.lock().contains(OpenFlags::NON_BLOCK) {
+                return Err(AxError::WouldBlock);
+            }
+            if interrupted() {
+                return Err(AxError::Interrupted);
+            }
+            wait.wait(None);
+        }
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        self.pair.slave.receive(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&self) -> AxResult {
+        Ok(())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.lock().readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.lock().writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        String::from("/dev/ptmx")
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        *self.flags.lock() = flags;
+        true
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        if is_set {
+            *self.flags.lock() |= OpenFlags::CLOEXEC;
+        } else {
+            *self.flags.lock() &= !OpenFlags::CLOEXEC;
+        }
+        true
+    }
+
+    fn is_hang_up(&self) -> bool {
+        self.pair.slave_closed()
+    }
+
+    fn ready_to_read(&self) -> bool {
+        !self.pair.output.buf.lock().is_empty() || self.pair.slave_closed()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+
+    fn ioctl(&self, request: usize, arg: usize) -> AxResult<()> {
+        match request {
+            TIOCGPTN => write_user(arg, self.pair.index as u32),
+            TIOCSPTLCK => {
+                let lock: i32 = read_user(arg)?;
+                self.pair.locked.store(lock != 0, Ordering::Release);
+                Ok(())
+            }
+            // 其余请求作用于从设备端，如设置窗口大小、查询前台进程组
+            _ => self.pair.slave.ioctl(request, arg, true),
+        }
+    }
+
+    fn poll_notifier(&self) -> Option<&PollNotifier> {
+        Some(&self.pair.output.notifier)
+    }
+}
+
+/// devpts 文件系统，挂载在 `/dev/pts`
+pub struct DevPtsFs {
+    root: Arc<DevPtsDir>,
+}
+
+impl DevPtsFs {
+    /// 创建 devpts 文件系统
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(DevPtsDir),
+        }
+    }
+
+    /// 根目录节点
+    pub fn root(&self) -> VfsNodeRef {
+        self.root.clone()
+    }
+}
+
+impl Default for DevPtsFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VfsOps for DevPtsFs {
+    fn root_dir(&self) -> VfsNodeRef {
+        self.root()
+    }
+}
+
+/// devpts 的根目录，内容为 `ptmx` 与当前存在的各个从设备端
+struct DevPtsDir;
+
+/// 伪终端设备节点，`index` 为 None 时表示 `ptmx`
+struct PtsNode {
+    index: Option<usize>,
+}
+
+impl VfsNodeOps for PtsNode {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        let perm = match self.index {
+            Some(_) => VfsNodePerm::from_bits_truncate(0o620),
+            None => VfsNodePerm::from_bits_truncate(0o666),
+        };
+        Ok(VfsNodeAttr::new(perm, VfsNodeType::CharDevice, 0, 0))
+    }
+
+    axfs_vfs::impl_vfs_non_dir_default! {}
+}
+
+impl VfsNodeOps for DevPtsDir {
+    fn get_attr(&self) -> VfsResult<VfsNodeAttr> {
+        Ok(VfsNodeAttr::new_dir(0, 0))
+    }
+
+    fn lookup(self: Arc<Self>, path: &str) -> VfsResult<VfsNodeRef> {
+        let path = path.trim_matches('/');
+        match path {
+            "" | "." => Ok(self),
+            "ptmx" => Ok(Arc::new(PtsNode { index: None })),
+            _ => {
+                let index = path.parse::<usize>().map_err(|_| VfsError::NotFound)?;
+                if PTYS.lock().contains_key(&index) {
+                    Ok(Arc::new(PtsNode { index: Some(index) }))
+                } else {
+                    Err(VfsError::NotFound)
+                }
+            }
+        }
+    }
+
+    fn read_dir(&self, start_idx: usize, dirents: &mut [VfsDirEntry]) -> VfsResult<usize> {
+        let ptys = PTYS.lock();
+        let mut entries = [
+            (String::from("."), VfsNodeType::Dir),
+            (String::from(".."), VfsNodeType::Dir),
+            (String::from("ptmx"), VfsNodeType::CharDevice),
+        ]
+        .into_iter()
+        .chain(
+            ptys.keys()
+                .map(|index| (format!("{}", index), VfsNodeType::CharDevice)),
+        )
+        .skip(start_idx);
+        for (i, ent) in dirents.iter_mut().enumerate() {
+            match entries.next() {
+                Some((name, ty)) => *ent = VfsDirEntry::new(&name, ty),
+                None => return Ok(i),
+            }
+        }
+        Ok(dirents.len())
+    }
+
+    axfs_vfs::impl_vfs_dir_default! {}
+}
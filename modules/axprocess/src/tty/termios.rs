@@ -0,0 +1,292 @@
+//! 终端属性 `termios` 与终端相关的 ioctl 请求号
+//!
+//! 各常量与 Linux 的 asm-generic 定义一致，x86_64、riscv64 与 aarch64 均相同
+
+/// 读取终端属性
+pub const TCGETS: usize = 0x5401;
+/// 立即设置终端属性
+pub const TCSETS: usize = 0x5402;
+/// 等待输出完成后设置终端属性
+pub const TCSETSW: usize = 0x5403;
+/// 等待输出完成并丢弃未读输入后设置终端属性
+pub const TCSETSF: usize = 0x5404;
+/// 暂停或恢复输入输出
+pub const TCXONC: usize = 0x540A;
+/// 丢弃输入或输出队列中的数据
+pub const TCFLSH: usize = 0x540B;
+/// 将终端设置为调用进程的控制终端
+pub const TIOCSCTTY: usize = 0x540E;
+/// 获取前台进程组
+pub const TIOCGPGRP: usize = 0x540F;
+/// 设置前台进程组
+pub const TIOCSPGRP: usize = 0x5410;
+/// 获取输出队列中的字节数
+pub const TIOCOUTQ: usize = 0x5411;
+/// 获取窗口大小
+pub const TIOCGWINSZ: usize = 0x5413;
+/// 设置窗口大小
+pub const TIOCSWINSZ: usize = 0x5414;
+/// 获取输入队列中可读的字节数
+pub const FIONREAD: usize = 0x541B;
+/// 设置非阻塞模式
+pub const FIONBIO: usize = 0x5421;
+/// 放弃控制终端
+pub const TIOCNOTTY: usize = 0x5422;
+/// 获取终端所属的会话
+pub const TIOCGSID: usize = 0x5429;
+/// 获取伪终端的编号
+pub const TIOCGPTN: usize = 0x80045430;
+/// 锁定或解锁伪终端从设备
+pub const TIOCSPTLCK: usize = 0x40045431;
+
+/// `TCFLSH` 的参数：丢弃输入
+pub const TCIFLUSH: usize = 0;
+/// `TCFLSH` 的参数：丢弃输出
+pub const TCOFLUSH: usize = 1;
+/// `TCFLSH` 的参数：丢弃输入与输出
+pub const TCIOFLUSH: usize = 2;
+
+/// `TCXONC` 的参数：暂停输出
+pub const TCOOFF: usize = 0;
+/// `TCXONC` 的参数：恢复输出
+pub const TCOON: usize = 1;
+
+/// 控制字符数组的长度
+pub const NCCS: usize = 19;
+
+/// 中断字符，默认为 Ctrl-C
+pub const VINTR: usize = 0;
+/// 退出字符，默认为 Ctrl-\
+pub const VQUIT: usize = 1;
+/// 删除字符
+pub const VERASE: usize = 2;
+/// 删除整行字符，默认为 Ctrl-U
+pub const VKILL: usize = 3;
+/// 文件结束字符，默认为 Ctrl-D
+pub const VEOF: usize = 4;
+/// 非规范模式下读取的超时时间，单位为 0.1 秒
+pub const VTIME: usize = 5;
+/// 非规范模式下读取的最少字节数
+pub const VMIN: usize = 6;
+/// 恢复输出字符，默认为 Ctrl-Q
+pub const VSTART: usize = 8;
+/// 暂停输出字符，默认为 Ctrl-S
+pub const VSTOP: usize = 9;
+/// 挂起字符，默认为 Ctrl-Z
+pub const VSUSP: usize = 10;
+/// 额外的行结束字符
+pub const VEOL: usize = 11;
+/// 重新显示当前行字符，默认为 Ctrl-R
+pub const VREPRINT: usize = 12;
+/// 删除一个单词字符，默认为 Ctrl-W
+pub const VWERASE: usize = 14;
+/// 将下一个字符按字面输入，默认为 Ctrl-V
+pub const VLNEXT: usize = 15;
+/// 第二个额外的行结束字符
+pub const VEOL2: usize = 16;
+
+bitflags::bitflags! {
+    /// 输入模式
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct InputFlags: u32 {
+        /// 忽略 BREAK
+        const IGNBRK = 0o1;
+        /// BREAK 产生 SIGINT
+        const BRKINT = 0o2;
+        /// 忽略奇偶校验错误
+        const IGNPAR = 0o4;
+        /// 标记奇偶校验错误
+        const PARMRK = 0o10;
+        /// 启用输入奇偶校验
+        const INPCK = 0o20;
+        /// 去掉第 8 位
+        const ISTRIP = 0o40;
+        /// 将输入的 NL 转换为 CR
+        const INLCR = 0o100;
+        /// 忽略输入的 CR
+        const IGNCR = 0o200;
+        /// 将输入的 CR 转换为 NL
+        const ICRNL = 0o400;
+        /// 将输入的大写字母转换为小写
+        const IUCLC = 0o1000;
+        /// 启用输出的 XON/XOFF 流控
+        const IXON = 0o2000;
+        /// 任意字符均可恢复输出
+        const IXANY = 0o4000;
+        /// 启用输入的 XON/XOFF 流控
+        const IXOFF = 0o10000;
+        /// 输入队列满时响铃
+        const IMAXBEL = 0o20000;
+        /// 输入为 UTF-8，用于规范模式下正确删除字符
+        const IUTF8 = 0o40000;
+    }
+}
+
+bitflags::bitflags! {
+    /// 输出模式
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct OutputFlags: u32 {
+        /// 启用输出处理
+        const OPOST = 0o1;
+        /// 将输出的小写字母转换为大写
+        const OLCUC = 0o2;
+        /// 将输出的 NL 转换为 CR-NL
+        const ONLCR = 0o4;
+        /// 将输出的 CR 转换为 NL
+        const OCRNL = 0o10;
+        /// 不在第 0 列输出 CR
+        const ONOCR = 0o20;
+        /// NL 同时完成回车的功能
+        const ONLRET = 0o40;
+    }
+}
+
+bitflags::bitflags! {
+    /// 本地模式
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct LocalFlags: u32 {
+        /// 收到 INTR、QUIT、SUSP 字符时产生对应的信号
+        const ISIG = 0o1;
+        /// 规范模式，按行编辑与读取
+        const ICANON = 0o2;
+        /// 回显输入的字符
+        const ECHO = 0o10;
+        /// ERASE 字符删除前一个字符
+        const ECHOE = 0o20;
+        /// KILL 字符删除当前行
+        const ECHOK = 0o40;
+        /// 即使没有设置 ECHO 也回显 NL
+        const ECHONL = 0o100;
+        /// 产生信号时不清空输入输出队列
+        const NOFLSH = 0o200;
+        /// 后台进程写终端时发送 SIGTTOU
+        const TOSTOP = 0o400;
+        /// 以 ^X 的形式回显控制字符
+        const ECHOCTL = 0o1000;
+        /// 以打印形式回显被删除的字符
+        const ECHOPRT = 0o2000;
+        /// KILL 字符逐个删除当前行的字符
+        const ECHOKE = 0o4000;
+        /// 输出被 DISCARD 字符丢弃中
+        const FLUSHO = 0o10000;
+        /// 有输入时重新显示未读的输入
+        const PENDIN = 0o40000;
+        /// 启用扩展的输入处理，如 WERASE、LNEXT
+        const IEXTEN = 0o100000;
+    }
+}
+
+/// 波特率 38400，即 `B38400`
+const B38400: u32 = 0o17;
+/// 8 位字符，即 `CS8`
+const CS8: u32 = 0o60;
+/// 启用接收，即 `CREAD`
+const CREAD: u32 = 0o200;
+/// 最后一次关闭时挂断，即 `HUPCL`
+const HUPCL: u32 = 0o2000;
+
+/// 终端属性，对应内核中 TCGETS/TCSETS 使用的 `struct termios`
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct Termios {
+    /// 输入模式
+    pub c_iflag: u32,
+    /// 输出模式
+    pub c_oflag: u32,
+    /// 控制模式
+    pub c_cflag: u32,
+    /// 本地模式
+    pub c_lflag: u32,
+    /// 行规程编号，只支持 N_TTY
+    pub c_line: u8,
+    /// 控制字符
+    pub c_cc: [u8; NCCS],
+}
+
+impl Default for Termios {
+    /// 与 Linux 新建终端的默认属性一致，即 `stty sane`
+    fn default() -> Self {
+        let mut c_cc = [0u8; NCCS];
+        c_cc[VINTR] = 0x03;
+        c_cc[VQUIT] = 0x1c;
+        c_cc[VERASE] = 0x7f;
+        c_cc[VKILL] = 0x15;
+        c_cc[VEOF] = 0x04;
+        c_cc[VTIME] = 0;
+        c_cc[VMIN] = 1;
+        c_cc[VSTART] = 0x11;
+        c_cc[VSTOP] = 0x13;
+        c_cc[VSUSP] = 0x1a;
+        c_cc[VREPRINT] = 0x12;
+        c_cc[VWERASE] = 0x17;
+        c_cc[VLNEXT] = 0x16;
+        Self {
+            c_iflag: (InputFlags::ICRNL | InputFlags::IXON | InputFlags::IUTF8).bits(),
+            c_oflag: (OutputFlags::OPOST | OutputFlags::ONLCR).bits(),
+            c_cflag: B38400 | CS8 | CREAD | HUPCL,
+            c_lflag: (LocalFlags::ISIG
+                | LocalFlags::ICANON
+                | LocalFlags::ECHO
+                | LocalFlags::ECHOE
+                | LocalFlags::ECHOK
+                | LocalFlags::ECHOCTL
+                | LocalFlags::ECHOKE
+                | LocalFlags::IEXTEN)
+                .bits(),
+            c_line: 0,
+            c_cc,
+        }
+    }
+}
+
+impl Termios {
+    /// 输入模式
+    pub fn iflag(&self) -> InputFlags {
+        InputFlags::from_bits_retain(self.c_iflag)
+    }
+
+    /// 输出模式
+    pub fn oflag(&self) -> OutputFlags {
+        OutputFlags::from_bits_retain(self.c_oflag)
+    }
+
+    /// 本地模式
+    pub fn lflag(&self) -> LocalFlags {
+        LocalFlags::from_bits_retain(self.c_lflag)
+    }
+
+    /// 是否处于规范模式
+    pub fn is_canonical(&self) -> bool {
+        self.lflag().contains(LocalFlags::ICANON)
+    }
+
+    /// 字符 `ch` 是否为给定的控制字符。控制字符为 0 表示禁用
+    pub fn is_cc(&self, index: usize, ch: u8) -> bool {
+        self.c_cc[index] != 0 && self.c_cc[index] == ch
+    }
+}
+
+/// 终端窗口大小，对应 `struct winsize`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WinSize {
+    /// 行数
+    pub ws_row: u16,
+    /// 列数
+    pub ws_col: u16,
+    /// 宽度，单位为像素
+    pub ws_xpixel: u16,
+    /// 高度，单位为像素
+    pub ws_ypixel: u16,
+}
+
+impl Default for WinSize {
+    fn default() -> Self {
+        Self {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! vDSO：让用户程序无需陷入内核即可读取时间
+//!
+//! 每个地址空间在 `axconfig::VDSO_BASE` 处映射一份预先链接好的 vDSO 映像，并在其前一页映射只读的数据页，
+//! 其地址通过辅助向量 `AT_SYSINFO_EHDR` 告知 libc。数据页保存时钟源的参数，
+//! vDSO 直接读取硬件计数器并换算成纳秒：
+//!
+//! `nanos = ((counter - cycle_base) * mult) >> 32`
+//!
+//! 内核通过顺序锁更新数据页：更新前后各将 `seq` 加一，用户读到奇数或前后不一致时重试。
+//!
+//! vDSO 的源码与链接脚本位于 `vdso/` 目录下，由 `build.rs` 在构建时汇编并链接成映像。
+use axerrno::AxResult;
+use axhal::mem::{virt_to_phys, VirtAddr, PAGE_SIZE_4K};
+use axhal::paging::MappingFlags;
+use axmem::MemorySet;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// 时钟可以在用户态读取
+const VDSO_CLOCK_COUNTER: u32 = 0;
+/// 时钟不可用，vDSO 退回到系统调用
+const VDSO_CLOCK_NONE: u32 = 1;
+
+/// 与用户共享的数据页，布局需要与 `vdso/*/vdso.S` 保持一致
+#[repr(C, align(4096))]
+struct VdsoData {
+    /// 顺序锁，为奇数时表示内核正在更新
+    seq: AtomicU32,
+    /// 时钟模式，见 `VDSO_CLOCK_*`
+    clock_mode: AtomicU32,
+    /// 计数器为该值时对应启动以来的零纳秒
+    cycle_base: AtomicU64,
+    /// 每个计数器周期对应的纳秒数，放大 2^32 倍
+    mult: AtomicU64,
+}
+
+static VDSO_DATA: VdsoData = VdsoData {
+    seq: AtomicU32::new(0),
+    clock_mode: AtomicU32::new(VDSO_CLOCK_NONE),
+    cycle_base: AtomicU64::new(0),
+    mult: AtomicU64::new(0),
+};
+
+/// 按页对齐的 vDSO 映像
+#[repr(C, align(4096))]
+struct VdsoImage<T: ?Sized>(T);
+
+#[cfg(any(
+    target_arch = "riscv64",
+    target_arch = "aarch64",
+    target_arch = "x86_64"
+))]
+static VDSO_IMAGE: &VdsoImage<[u8]> =
+    &VdsoImage(*include_bytes!(concat!(env!("OUT_DIR"), "/vdso.so")));
+#[cfg(not(any(
+    target_arch = "riscv64",
+    target_arch = "aarch64",
+    target_arch = "x86_64"
+)))]
+static VDSO_IMAGE: &VdsoImage<[u8]> = &VdsoImage([]);
+
+/// 根据当前的时钟源重新填写数据页
+///
+/// 需要在时钟初始化之后、第一个用户进程运行之前调用，时钟源参数变化时也需要调用
+pub fn update_clock_source() {
+    let seq = VDSO_DATA.seq.fetch_add(1, Ordering::AcqRel);
+    VDSO_DATA
+        .cycle_base
+        .store(axhal::time::ticks_base(), Ordering::Relaxed);
+    VDSO_DATA
+        .mult
+        .store(axhal::time::ticks_to_nanos(1 << 32), Ordering::Relaxed);
+    VDSO_DATA
+        .clock_mode
+        .store(VDSO_CLOCK_COUNTER, Ordering::Relaxed);
+    VDSO_DATA.seq.store(seq.wrapping_add(2), Ordering::Release);
+}
+
+/// 将 vDSO 与数据页映射到给定的地址空间，返回映像的起始地址
+///
+/// 当前平台不提供 vDSO 时返回 `None`，已经映射过时不会重复映射
+pub fn map_vdso(memory_set: &mut MemorySet) -> AxResult<Option<VirtAddr>> {
+    if axconfig::VDSO_BASE == 0 || VDSO_IMAGE.0.is_empty() {
+        return Ok(None);
+    }
+    let base = VirtAddr::from(axconfig::VDSO_BASE);
+    let data_vaddr = base - PAGE_SIZE_4K;
+    if memory_set.query(data_vaddr).is_err() {
+        memory_set.map_page_without_alloc(
+            data_vaddr,
+            virt_to_phys(VirtAddr::from(&VDSO_DATA as *const _ as usize)),
+            MappingFlags::READ | MappingFlags::USER,
+        )?;
+    }
+    let image = VirtAddr::from(VDSO_IMAGE.0.as_ptr() as usize);
+    for offset in (0..VDSO_IMAGE.0.len()).step_by(PAGE_SIZE_4K) {
+        if memory_set.query(base + offset).is_err() {
+            memory_set.map_page_without_alloc(
+                base + offset,
+                virt_to_phys(image + offset),
+                MappingFlags::READ | MappingFlags::EXECUTE | MappingFlags::USER,
+            )?;
+        }
+    }
+    Ok(Some(base))
+}
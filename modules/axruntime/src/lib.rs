@@ -21,6 +21,8 @@
 
 #[macro_use]
 extern crate axlog;
+#[cfg(feature = "net")]
+extern crate alloc;
 
 #[cfg(all(target_os = "none", not(test)))]
 mod lang_items;
@@ -88,6 +90,17 @@ impl axlog::LogIf for LogIfImpl {
     }
 }
 
+#[cfg(feature = "monolithic")]
+struct TerminateIfImpl;
+
+#[cfg(feature = "monolithic")]
+#[crate_interface::impl_interface]
+impl axtask::TerminateIf for TerminateIfImpl {
+    fn before_terminate() {
+        sync_disks();
+    }
+}
+
 use core::sync::atomic::{AtomicUsize, Ordering};
 
 static INITED_CPUS: AtomicUsize = AtomicUsize::new(0);
@@ -171,9 +184,30 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
         #[cfg(feature = "fs")]
         axfs::init_filesystems(all_devices.block);
 
+        // write the dirty disk blocks back in the background
+        #[cfg(all(feature = "fs", feature = "multitask", feature = "irq"))]
+        axtask::spawn_raw(
+            || loop {
+                axtask::sleep(axfs::api::WRITEBACK_INTERVAL);
+                axfs::api::write_back_expired();
+            },
+            "writeback".into(),
+            axconfig::TASK_STACK_SIZE,
+        );
+
+        #[cfg(all(feature = "fs", feature = "monolithic"))]
+        axprocess::tty::init_tty_devices();
+
         #[cfg(feature = "net")]
         axnet::init_network(all_devices.net);
 
+        // receive packets and wake the sockets waiting for them in the background
+        #[cfg(all(feature = "net", feature = "multitask", feature = "irq"))]
+        axtask::spawn_raw(net_poll, "netpoll".into(), axconfig::TASK_STACK_SIZE);
+
+        #[cfg(all(feature = "fs", feature = "net"))]
+        init_sysfs_net();
+
         #[cfg(feature = "display")]
         axdisplay::init_display(all_devices.display);
     }
@@ -202,6 +236,7 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
 
     unsafe { main() };
 
+    sync_disks();
     #[cfg(feature = "multitask")]
     axtask::exit(0);
     #[cfg(not(feature = "multitask"))]
@@ -211,6 +246,40 @@ pub extern "C" fn rust_main(cpu_id: usize, dtb: usize) -> ! {
     }
 }
 
+/// Writes the dirty disk blocks back before the system terminates.
+fn sync_disks() {
+    #[cfg(feature = "fs")]
+    if let Err(e) = axfs::api::sync() {
+        warn!("failed to sync disks: {:?}", e);
+    }
+}
+
+/// Polls the network interfaces in the background.
+///
+/// Sleeps while no socket has a waker. Otherwise polls every
+/// `axnet::POLL_INTERVAL`, and backs off up to `axnet::MAX_POLL_INTERVAL` while
+/// the polls wake no socket.
+#[cfg(all(feature = "net", feature = "multitask", feature = "irq"))]
+fn net_poll() {
+    static WAIT_QUEUE: axtask::WaitQueue = axtask::WaitQueue::new();
+    axnet::set_poll_waker(alloc::sync::Arc::new(|| {
+        WAIT_QUEUE.notify_one(false);
+    }));
+    let mut interval = axnet::POLL_INTERVAL;
+    loop {
+        if !axnet::has_watched_sockets() {
+            WAIT_QUEUE.wait_until(axnet::has_watched_sockets);
+            interval = axnet::POLL_INTERVAL;
+        }
+        interval = if axnet::poll_interfaces() {
+            axnet::POLL_INTERVAL
+        } else {
+            (interval * 2).min(axnet::MAX_POLL_INTERVAL)
+        };
+        axtask::sleep(interval);
+    }
+}
+
 #[cfg(feature = "alloc")]
 fn init_allocator() {
     use axhal::mem::{memory_regions, phys_to_virt, MemRegionFlags};
@@ -240,6 +309,32 @@ fn init_allocator() {
     }
 }
 
+/// Describes the network interfaces in `/sys/class/net`.
+///
+/// The MTU and the state are looked up each time they are read.
+#[cfg(all(feature = "fs", feature = "net"))]
+fn init_sysfs_net() {
+    fn lookup(name: &str) -> Option<axnet::InterfaceInfo> {
+        axnet::interfaces()
+            .into_iter()
+            .find(|iface| iface.name == name)
+    }
+
+    for iface in axnet::interfaces() {
+        let name = iface.name;
+        if let Err(e) = axfs::api::add_sysfs_net_device(
+            name,
+            iface.index,
+            iface.mac,
+            iface.loopback,
+            move || lookup(name).map_or(0, |iface| iface.mtu),
+            move || lookup(name).is_some(),
+        ) {
+            warn!("failed to add /sys/class/net/{}: {:?}", name, e);
+        }
+    }
+}
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "paging")] {
         use axhal::paging::PageTable;
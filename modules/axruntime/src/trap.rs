@@ -15,4 +15,9 @@ impl axhal::trap::TrapHandler for TrapHandlerImpl {
     fn handle_signal() {
         unimplemented();
     }
+
+    #[cfg(feature = "signal")]
+    fn handle_user_breakpoint(single_step: bool) {
+        axprocess::signal::send_trap_to_current(single_step);
+    }
 }
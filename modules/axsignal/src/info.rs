@@ -2,9 +2,61 @@
 //!
 //! 错误信息：详细定义见 `https://man7.org/linux/man-pages/man2/rt_sigaction.2.html`
 
+use crate::signal_no::SignalNo;
+
+/// 由 kill 等用户调用发送
+pub const SI_USER: i32 = 0;
+/// 由内核发送
+pub const SI_KERNEL: i32 = 0x80;
+/// 由 sigqueue 发送
+pub const SI_QUEUE: i32 = -1;
+/// POSIX 定时器到期
+pub const SI_TIMER: i32 = -2;
+/// POSIX 消息队列状态变化
+pub const SI_MESGQ: i32 = -3;
+/// 异步 I/O 完成
+pub const SI_ASYNCIO: i32 = -4;
+/// 排队的 SIGIO
+pub const SI_SIGIO: i32 = -5;
+/// 由 tkill 或 tgkill 发送
+pub const SI_TKILL: i32 = -6;
+
+/// SIGSEGV：地址没有映射
+pub const SEGV_MAPERR: i32 = 1;
+/// SIGSEGV：没有访问权限
+pub const SEGV_ACCERR: i32 = 2;
+
+/// SIGTRAP：断点
+pub const TRAP_BRKPT: i32 = 1;
+/// SIGTRAP：单步执行
+pub const TRAP_TRACE: i32 = 2;
+
+/// SIGSYS：被 seccomp 过滤器拦截的系统调用
+pub const SYS_SECCOMP: i32 = 1;
+
+/// SIGCHLD：子进程退出
+pub const CLD_EXITED: i32 = 1;
+/// SIGCHLD：子进程被信号杀死
+pub const CLD_KILLED: i32 = 2;
+/// SIGCHLD：子进程被信号杀死并转储
+pub const CLD_DUMPED: i32 = 3;
+/// SIGCHLD：被跟踪的子进程陷入
+pub const CLD_TRAPPED: i32 = 4;
+/// SIGCHLD：子进程暂停
+pub const CLD_STOPPED: i32 = 5;
+/// SIGCHLD：暂停的子进程继续运行
+pub const CLD_CONTINUED: i32 = 6;
+
+/// siginfo 中联合体部分的长度，使得整个结构为 128 字节
+const SI_FIELDS_LEN: usize = 28;
+
 /// The information of the signal
 ///
 /// When the `SigAction` specifies that it needs information, it will return it to the user
+///
+/// 布局与 Linux 的 `siginfo_t` 一致，依赖于 `si_code` 的联合体部分通过对应的方法读写
+#[repr(C, align(8))]
+#[derive(Clone, Copy, Debug)]
 pub struct SigInfo {
     /// The signal number
     pub si_signo: i32,
@@ -12,14 +64,120 @@ pub struct SigInfo {
     pub si_errno: i32,
     /// The code of the signal
     pub si_code: i32,
+    /// 对齐填充
+    _pad: i32,
+    /// 联合体部分，按 32 位为单位存放
+    fields: [u32; SI_FIELDS_LEN],
 }
 
 impl Default for SigInfo {
     fn default() -> Self {
+        Self::new(0, SI_TKILL)
+    }
+}
+
+impl SigInfo {
+    /// 新建一个信号信息，联合体部分全部为0
+    pub fn new(si_signo: i32, si_code: i32) -> Self {
         Self {
-            si_signo: 0,
+            si_signo,
             si_errno: 0,
-            si_code: -6, // SI_TKILL
+            si_code,
+            _pad: 0,
+            fields: [0; SI_FIELDS_LEN],
         }
     }
+
+    /// 由进程发送的信号，如 kill、sigqueue
+    pub fn from_sender(si_signo: i32, si_code: i32, pid: u32, uid: u32) -> Self {
+        let mut info = Self::new(si_signo, si_code);
+        info.set_si_pid(pid);
+        info.set_si_uid(uid);
+        info
+    }
+
+    /// 由访存异常触发的信号，如 SIGSEGV、SIGBUS
+    pub fn from_fault(si_signo: i32, si_code: i32, addr: usize) -> Self {
+        let mut info = Self::new(si_signo, si_code);
+        info.set_si_addr(addr);
+        info
+    }
+
+    /// 子进程状态变化时发送给父进程的 SIGCHLD
+    pub fn from_child(si_code: i32, pid: u32, uid: u32, status: i32) -> Self {
+        let mut info = Self::from_sender(SignalNo::SIGCHLD as i32, si_code, pid, uid);
+        info.set_si_status(status);
+        info
+    }
+
+    /// 被 seccomp 拦截的系统调用触发的 SIGSYS
+    ///
+    /// `call_addr` 为系统调用指令之后的地址，`arch` 为 `AUDIT_ARCH_*` 之一
+    pub fn from_seccomp(call_addr: usize, syscall: i32, arch: u32, errno: i32) -> Self {
+        let mut info = Self::new(SignalNo::SIGSYS as i32, SYS_SECCOMP);
+        info.si_errno = errno;
+        info.set_si_addr(call_addr);
+        info.fields[2] = syscall as u32;
+        info.fields[3] = arch;
+        info
+    }
+
+    fn get_usize(&self, index: usize) -> usize {
+        self.fields[index] as usize | ((self.fields[index + 1] as usize) << 32)
+    }
+
+    fn set_usize(&mut self, index: usize, value: usize) {
+        self.fields[index] = value as u32;
+        self.fields[index + 1] = (value >> 32) as u32;
+    }
+
+    /// 发送者的 pid
+    pub fn si_pid(&self) -> u32 {
+        self.fields[0]
+    }
+
+    /// 设置发送者的 pid
+    pub fn set_si_pid(&mut self, pid: u32) {
+        self.fields[0] = pid;
+    }
+
+    /// 发送者的真实 uid
+    pub fn si_uid(&self) -> u32 {
+        self.fields[1]
+    }
+
+    /// 设置发送者的真实 uid
+    pub fn set_si_uid(&mut self, uid: u32) {
+        self.fields[1] = uid;
+    }
+
+    /// sigqueue 或定时器携带的 `sigval`
+    pub fn si_value(&self) -> usize {
+        self.get_usize(2)
+    }
+
+    /// 设置携带的 `sigval`
+    pub fn set_si_value(&mut self, value: usize) {
+        self.set_usize(2, value);
+    }
+
+    /// SIGCHLD 中子进程的退出码或导致状态变化的信号
+    pub fn si_status(&self) -> i32 {
+        self.fields[2] as i32
+    }
+
+    /// 设置子进程的状态
+    pub fn set_si_status(&mut self, status: i32) {
+        self.fields[2] = status as u32;
+    }
+
+    /// 触发异常的地址
+    pub fn si_addr(&self) -> usize {
+        self.get_usize(0)
+    }
+
+    /// 设置触发异常的地址
+    pub fn set_si_addr(&mut self, addr: usize) {
+        self.set_usize(0, addr);
+    }
 }
@@ -6,7 +6,11 @@
 //! 一次trap，从而检查是否有需要处理的信号。
 #![cfg_attr(not(test), no_std)]
 
+extern crate alloc;
+
 use action::SigAction;
+use alloc::collections::VecDeque;
+use info::SigInfo;
 use signal_no::{SignalNo, MAX_SIG_NUM};
 
 pub mod action;
@@ -62,13 +66,20 @@ impl SignalHandler {
     }
 }
 
+/// 同时排队的信号信息数目上限，超过后实时信号无法再排队
+pub const MAX_QUEUED_SIGNALS: usize = 1024;
+
 /// 接受信号的结构，每一个进程都有一个
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct SignalSet {
     /// 信号掩码
     pub mask: usize,
     /// 未决信号集
     pub pending: usize,
+    /// 未决信号携带的信息，按到达顺序排列
+    ///
+    /// 标准信号至多有一条，实时信号（SIGRTMIN 及以上）每次发送都会排队一条
+    queue: VecDeque<SigInfo>,
 }
 
 impl Default for SignalSet {
@@ -83,6 +94,7 @@ impl SignalSet {
         Self {
             mask: 0,
             pending: 0,
+            queue: VecDeque::new(),
         }
     }
 
@@ -90,6 +102,7 @@ impl SignalSet {
     pub fn clear(&mut self) {
         self.mask = 0;
         self.pending = 0;
+        self.queue.clear();
     }
 
     /// 查询是否有未决信号，若有则返回对应编号
@@ -119,23 +132,86 @@ impl SignalSet {
     ///
     /// 若有则返回信号编号最低的一个，，并且修改原有信号集
     pub fn get_one_signal(&mut self) -> Option<usize> {
-        match self.find_signal() {
-            Some(pos) => {
-                // 修改原有信号集
-                self.pending &= !(1 << (pos - 1));
-                Some(pos)
-            }
-            None => None,
+        self.get_one_signal_info()
+            .map(|info| info.si_signo as usize)
+    }
+
+    /// 查询当前是否有未决信号
+    ///
+    /// 若有则取出信号编号最低的一个及其信息，并且修改原有信号集
+    pub fn get_one_signal_info(&mut self) -> Option<SigInfo> {
+        let sig_num = self.find_signal()?;
+        Some(self.take_signal(sig_num))
+    }
+
+    /// 在给定的信号集合中查找未决信号，忽略掩码
+    ///
+    /// 供 sigtimedwait 等主动等待信号的调用使用
+    pub fn find_signal_in(&self, set: usize) -> Option<usize> {
+        let pending = self.pending & set;
+        if pending == 0 {
+            None
+        } else {
+            Some(pending.trailing_zeros() as usize + 1)
         }
     }
 
+    /// 取出一个指定编号的未决信号的信息
+    ///
+    /// 对于实时信号，只有当其所有排队的实例都被取出后才会清除未决位
+    pub fn take_signal(&mut self, sig_num: usize) -> SigInfo {
+        let info = match self
+            .queue
+            .iter()
+            .position(|info| info.si_signo as usize == sig_num)
+        {
+            Some(index) => self.queue.remove(index).unwrap(),
+            None => SigInfo::new(sig_num as i32, info::SI_KERNEL),
+        };
+        if !self
+            .queue
+            .iter()
+            .any(|info| info.si_signo as usize == sig_num)
+        {
+            // 修改原有信号集
+            self.pending &= !(1 << (sig_num - 1));
+        }
+        info
+    }
+
+    /// 丢弃指定编号的所有未决信号及其信息
+    pub fn discard_signal(&mut self, sig_num: usize) {
+        self.queue.retain(|info| info.si_signo as usize != sig_num);
+        self.pending &= !(1 << (sig_num - 1));
+    }
+
     /// 尝试添加一个bit作为信号
     ///
     /// 若当前信号已经加入到未决信号集中，则不作处理
     ///
     /// 若信号在掩码中，则仍然加入，但是可能不会触发
     pub fn try_add_signal(&mut self, sig_num: usize) {
+        self.try_add_signal_info(SigInfo::new(sig_num as i32, info::SI_KERNEL));
+    }
+
+    /// 添加一个携带信息的信号
+    ///
+    /// 标准信号若已经未决则被合并，实时信号则按顺序排队
+    ///
+    /// 若排队的信号过多导致实时信号无法加入，返回false
+    pub fn try_add_signal_info(&mut self, info: SigInfo) -> bool {
+        let sig_num = info.si_signo as usize;
         let now_mask = 1 << (sig_num - 1);
+        if sig_num < SignalNo::SIGRTMIN as usize {
+            if self.pending & now_mask == 0 {
+                self.queue.push_back(info);
+            }
+        } else if self.queue.len() >= MAX_QUEUED_SIGNALS {
+            return false;
+        } else {
+            self.queue.push_back(info);
+        }
         self.pending |= now_mask;
+        true
     }
 }
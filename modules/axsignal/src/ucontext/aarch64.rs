@@ -1,23 +1,50 @@
 //! 信号处理时保存的用户上下文。
+//!
+//! 布局与 Linux 的 `ucontext_t` 一致，可以直接被 glibc 的 `setcontext` 使用
 
-/// 处理信号时使用的栈
-///
-/// 详细信息见`https://man7.org/linux/man-pages/man2/sigaltstack.2.html`
-#[repr(C)]
+#[cfg(feature = "fp_simd")]
+use axhal::arch::FpState;
+use axhal::arch::TrapFrame;
+
+use super::SignalStack;
+
+/// `fpsimd_context` 的魔数
+const FPSIMD_MAGIC: u32 = 0x4650_8001;
+
+/// sigreturn 时允许用户修改的 pstate 位，即 NZCV 条件标志
+const PSTATE_NZCV: usize = 0xf000_0000;
+
+/// `__reserved` 中的第一条记录，保存 FP/SIMD 寄存器
+#[repr(C, align(16))]
 #[derive(Clone, Copy, Debug)]
-pub struct SignalStack {
-    sp: usize,
-    flags: u32,
-    size: usize,
+struct FpsimdContext {
+    magic: u32,
+    size: u32,
+    fpsr: u32,
+    fpcr: u32,
+    vregs: [u128; 32],
+}
+
+#[repr(C, align(16))]
+#[derive(Clone, Debug)]
+/// `sigcontext` 中的扩展区域，依次存放若干条记录，以全零的头部结尾
+struct Reserved {
+    fpsimd: FpsimdContext,
+    /// 结束标志及剩余的空间
+    rest: [u8; 4096 - core::mem::size_of::<FpsimdContext>()],
 }
 
-impl Default for SignalStack {
+impl Default for Reserved {
     fn default() -> Self {
         Self {
-            sp: 0,
-            // 代表SS_DISABLE，即不使用栈
-            flags: 2,
-            size: 0,
+            fpsimd: FpsimdContext {
+                magic: FPSIMD_MAGIC,
+                size: core::mem::size_of::<FpsimdContext>() as u32,
+                fpsr: 0,
+                fpcr: 0,
+                vregs: [0; 32],
+            },
+            rest: [0; 4096 - core::mem::size_of::<FpsimdContext>()],
         }
     }
 }
@@ -31,31 +58,18 @@ pub struct MContext {
     sp: usize,
     pc: usize,
     pstate: usize,
-    reserved: [usize; 256 * 2],
-}
-
-impl Default for MContext {
-    fn default() -> Self {
-        Self {
-            fault_address: 0,
-            regs: [0; 31],
-            sp: 0,
-            pc: 0,
-            pstate: 0,
-            reserved: [0; 512],
-        }
-    }
+    reserved: Reserved,
 }
 
 impl MContext {
-    fn init_by_pc(pc: usize) -> Self {
+    fn init_by_trap_frame(tf: &TrapFrame) -> Self {
         Self {
             fault_address: 0,
-            regs: [0; 31],
-            sp: 0,
-            pc,
-            pstate: 0,
-            reserved: [0; 512],
+            regs: tf.r,
+            sp: tf.usp,
+            pc: tf.elr,
+            pstate: tf.spsr,
+            reserved: Reserved::default(),
         }
     }
 }
@@ -67,19 +81,55 @@ pub struct SignalUserContext {
     flags: usize,
     link: usize,
     stack: SignalStack,
-    sigmask: [usize; 17],
+    sigmask: u64,
+    /// glibc 的 sigset_t 为 1024 位，这里补齐
+    _unused: [u8; 120],
     mcontext: MContext,
 }
 
 impl SignalUserContext {
-    /// init the user context by the pc and the mask
-    pub fn init(pc: usize, _mask: usize) -> Self {
+    /// init the user context by the trap frame, the mask and the alternate signal stack
+    pub fn init(trap_frame: &TrapFrame, mask: usize, stack: SignalStack) -> Self {
         Self {
             flags: 0,
             link: 0,
-            stack: SignalStack::default(),
-            mcontext: MContext::init_by_pc(pc),
-            sigmask: [0; 17],
+            stack,
+            sigmask: mask as u64,
+            _unused: [0; 120],
+            mcontext: MContext::init_by_trap_frame(trap_frame),
+        }
+    }
+
+    /// 保存当前的 FP/SIMD 状态
+    pub fn save_fp_state(&mut self) {
+        #[cfg(feature = "fp_simd")]
+        {
+            let mut state = FpState::default();
+            state.save();
+            let fpsimd = &mut self.mcontext.reserved.fpsimd;
+            fpsimd.vregs = state.regs;
+            fpsimd.fpcr = state.fpcr;
+            fpsimd.fpsr = state.fpsr;
+        }
+    }
+
+    /// 用保存的上下文恢复 trap 上下文与 FP/SIMD 状态
+    pub fn restore(&self, trap_frame: &mut TrapFrame) {
+        trap_frame.r = self.mcontext.regs;
+        trap_frame.usp = self.mcontext.sp;
+        trap_frame.elr = self.mcontext.pc;
+        trap_frame.spsr = (trap_frame.spsr & !PSTATE_NZCV) | (self.mcontext.pstate & PSTATE_NZCV);
+        #[cfg(feature = "fp_simd")]
+        {
+            let fpsimd = &self.mcontext.reserved.fpsimd;
+            if fpsimd.magic == FPSIMD_MAGIC {
+                let state = FpState {
+                    regs: fpsimd.vregs,
+                    fpcr: fpsimd.fpcr,
+                    fpsr: fpsimd.fpsr,
+                };
+                state.restore();
+            }
         }
     }
 
@@ -87,4 +137,21 @@ impl SignalUserContext {
     pub fn get_pc(&self) -> usize {
         self.mcontext.pc
     }
+
+    /// 保存的信号掩码
+    pub fn get_mask(&self) -> usize {
+        self.sigmask as usize
+    }
+
+    /// 保存的备用信号栈
+    pub fn get_stack(&self) -> SignalStack {
+        self.stack
+    }
+
+    /// `restore` 会读取的用户态浮点状态所在的地址范围 `[start, end)`
+    ///
+    /// 浮点状态直接保存在上下文中，不需要额外检查
+    pub fn user_fp_range(&self) -> Option<(usize, usize)> {
+        None
+    }
 }
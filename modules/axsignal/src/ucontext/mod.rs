@@ -1,5 +1,61 @@
 //! Signal ucontext types and operations.
 
+/// 当前正在备用信号栈上执行
+pub const SS_ONSTACK: u32 = 1;
+/// 备用信号栈被禁用
+pub const SS_DISABLE: u32 = 2;
+/// 进入信号处理函数时自动禁用备用信号栈，返回时恢复
+pub const SS_AUTODISARM: u32 = 1 << 31;
+
+/// 备用信号栈的最小大小
+#[cfg(target_arch = "aarch64")]
+pub const MINSIGSTKSZ: usize = 5120;
+/// 备用信号栈的最小大小
+#[cfg(not(target_arch = "aarch64"))]
+pub const MINSIGSTKSZ: usize = 2048;
+
+/// 处理信号时使用的栈
+///
+/// 详细信息见`https://man7.org/linux/man-pages/man2/sigaltstack.2.html`
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SignalStack {
+    /// 栈的起始地址（低地址）
+    pub sp: usize,
+    /// SS_ONSTACK、SS_DISABLE 等标志
+    pub flags: u32,
+    /// 栈的大小
+    pub size: usize,
+}
+
+impl Default for SignalStack {
+    fn default() -> Self {
+        Self {
+            sp: 0,
+            // 代表SS_DISABLE，即不使用栈
+            flags: SS_DISABLE,
+            size: 0,
+        }
+    }
+}
+
+impl SignalStack {
+    /// 是否被禁用
+    pub fn is_disabled(&self) -> bool {
+        self.flags & SS_DISABLE != 0
+    }
+
+    /// 给定的用户栈指针是否位于这个栈上
+    pub fn contains(&self, sp: usize) -> bool {
+        !self.is_disabled() && sp > self.sp && sp - self.sp <= self.size
+    }
+
+    /// 栈顶地址
+    pub fn top(&self) -> usize {
+        self.sp + self.size
+    }
+}
+
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "x86_64")] {
         mod x86_64;
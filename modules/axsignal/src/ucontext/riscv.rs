@@ -1,55 +1,54 @@
 //! 信号处理时保存的用户上下文。
+//!
+//! 布局与 Linux 的 `ucontext_t` 一致，可以直接被 glibc 的 `setcontext` 使用
 
-/// 处理信号时使用的栈
-///
-/// 详细信息见`https://man7.org/linux/man-pages/man2/sigaltstack.2.html`
-#[repr(C)]
+use axhal::arch::{FpState, GeneralRegisters, TrapFrame};
+
+use super::SignalStack;
+
+/// Linux 中 `__riscv_fp_state` 的大小，按 Q 扩展预留
+const FP_STATE_SIZE: usize = 528;
+
+#[repr(C, align(16))]
 #[derive(Clone, Copy, Debug)]
-pub struct SignalStack {
-    sp: usize,
-    flags: u32,
-    size: usize,
+/// 浮点寄存器，当前只使用 D 扩展的部分
+struct FpRegs {
+    state: FpState,
+    _reserved: [u8; FP_STATE_SIZE - core::mem::size_of::<FpState>()],
 }
 
-impl Default for SignalStack {
+impl Default for FpRegs {
     fn default() -> Self {
         Self {
-            sp: 0,
-            // 代表SS_DISABLE，即不使用栈
-            flags: 2,
-            size: 0,
+            state: FpState::default(),
+            _reserved: [0; FP_STATE_SIZE - core::mem::size_of::<FpState>()],
         }
     }
 }
 
 #[repr(C)]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 /// The `mcontext` struct for the signal action
 pub struct MContext {
-    reserved1: [usize; 16],
     pc: usize,
-    reserved2: [usize; 17],
-    fpstate: [usize; 66],
+    /// x1 ~ x31，顺序与 `GeneralRegisters` 一致
+    regs: GeneralRegisters,
+    fpstate: FpRegs,
 }
 
-impl Default for MContext {
-    fn default() -> Self {
-        Self {
-            reserved1: [0; 16],
-            pc: 0,
-            reserved2: [0; 17],
-            fpstate: [0; 66],
-        }
-    }
-}
+/// fs0/fs1 即 f8/f9，trap 时由 trap.S 保存在 `TrapFrame.fs` 中，返回用户态时从中重新加载
+const FS_REGS: core::ops::Range<usize> = 8..10;
 
 impl MContext {
-    fn init_by_pc(pc: usize) -> Self {
+    fn init_by_trap_frame(tf: &TrapFrame) -> Self {
+        let mut fpstate = FpRegs::default();
+        for (fp, &fs) in fpstate.state.fp[FS_REGS].iter_mut().zip(tf.fs.iter()) {
+            *fp = fs as u64;
+        }
         Self {
-            reserved1: [0; 16],
-            pc,
-            reserved2: [0; 17],
-            fpstate: [0; 66],
+            pc: tf.sepc,
+            regs: tf.regs,
+            fpstate,
         }
     }
 
@@ -66,23 +65,71 @@ pub struct SignalUserContext {
     link: usize,
     stack: SignalStack,
     sigmask: u64,
+    /// glibc 的 sigset_t 为 1024 位，这里补齐
+    _unused: [u8; 120],
     mcontext: MContext,
 }
 
 impl SignalUserContext {
-    /// init the user context by the pc and the mask
-    pub fn init(pc: usize, mask: usize) -> Self {
+    /// init the user context by the trap frame, the mask and the alternate signal stack
+    pub fn init(trap_frame: &TrapFrame, mask: usize, stack: SignalStack) -> Self {
         Self {
             flags: 0,
             link: 0,
-            stack: SignalStack::default(),
-            mcontext: MContext::init_by_pc(pc),
+            stack,
             sigmask: mask as u64,
+            _unused: [0; 120],
+            mcontext: MContext::init_by_trap_frame(trap_frame),
+        }
+    }
+
+    /// 保存当前的浮点状态
+    ///
+    /// f8/f9 以 trap 时保存在 `TrapFrame.fs` 中的值为准，它们已经在 `init` 时写入
+    pub fn save_fp_state(&mut self) {
+        #[cfg(feature = "fp_simd")]
+        {
+            let state = &mut self.mcontext.fpstate.state;
+            let mut fs = [0; 2];
+            fs.copy_from_slice(&state.fp[FS_REGS]);
+            state.save();
+            state.fp[FS_REGS].copy_from_slice(&fs);
+        }
+    }
+
+    /// 用保存的上下文恢复 trap 上下文与浮点状态
+    ///
+    /// f8/f9 写回 `TrapFrame.fs`，否则会在返回用户态时被覆盖
+    pub fn restore(&self, trap_frame: &mut TrapFrame) {
+        trap_frame.sepc = self.mcontext.pc;
+        trap_frame.regs = self.mcontext.regs;
+        let state = &self.mcontext.fpstate.state;
+        for (fs, &fp) in trap_frame.fs.iter_mut().zip(state.fp[FS_REGS].iter()) {
+            *fs = fp as usize;
         }
+        #[cfg(feature = "fp_simd")]
+        state.restore();
     }
 
     /// get the pc from the user context
     pub fn get_pc(&self) -> usize {
         self.mcontext.get_pc()
     }
+
+    /// 保存的信号掩码
+    pub fn get_mask(&self) -> usize {
+        self.sigmask as usize
+    }
+
+    /// 保存的备用信号栈
+    pub fn get_stack(&self) -> SignalStack {
+        self.stack
+    }
+
+    /// `restore` 会读取的用户态浮点状态所在的地址范围 `[start, end)`
+    ///
+    /// 浮点状态直接保存在上下文中，不需要额外检查
+    pub fn user_fp_range(&self) -> Option<(usize, usize)> {
+        None
+    }
 }
@@ -1,26 +1,14 @@
 //! 信号处理时保存的用户上下文。
+//!
+//! 布局与 Linux 的 `ucontext_t` 一致，可以直接被 glibc 的 `setcontext` 使用
 
-/// 处理信号时使用的栈
-///
-/// 详细信息见`https://man7.org/linux/man-pages/man2/sigaltstack.2.html`
-#[repr(C)]
-#[derive(Clone, Copy, Debug)]
-pub struct SignalStack {
-    sp: usize,
-    flags: u32,
-    size: usize,
-}
+use axhal::arch::{FxsaveArea, TrapFrame};
+
+use super::SignalStack;
+
+/// sigreturn 时允许用户修改的 rflags 位，与 Linux 的 FIX_EFLAGS 一致
+const FIX_EFLAGS: u64 = 0x40dd5;
 
-impl Default for SignalStack {
-    fn default() -> Self {
-        Self {
-            sp: 0,
-            // 代表SS_DISABLE，即不使用栈
-            flags: 2,
-            size: 0,
-        }
-    }
-}
 #[repr(C)]
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 /// The `mcontext` struct for the signal action
@@ -52,27 +40,67 @@ pub struct MContext {
     trapno: usize,
     oldmask: usize,
     cr2: usize,
-    // fpregs
-    // TODO
+    // fpregs, 指向 FXSAVE 格式的浮点状态
     fpstate: usize,
     // reserved
     _reserved1: [usize; 8],
 }
 
 impl MContext {
-    fn init_by_pc(pc: usize) -> Self {
+    fn init_by_trap_frame(tf: &TrapFrame) -> Self {
         Self {
-            rip: pc,
+            r8: tf.r8 as _,
+            r9: tf.r9 as _,
+            r10: tf.r10 as _,
+            r11: tf.r11 as _,
+            r12: tf.r12 as _,
+            r13: tf.r13 as _,
+            r14: tf.r14 as _,
+            r15: tf.r15 as _,
+            rdi: tf.rdi as _,
+            rsi: tf.rsi as _,
+            rbp: tf.rbp as _,
+            rbx: tf.rbx as _,
+            rdx: tf.rdx as _,
+            rax: tf.rax as _,
+            rcx: tf.rcx as _,
+            rsp: tf.rsp as _,
+            rip: tf.rip as _,
+            eflags: tf.rflags as _,
+            cs: tf.cs as _,
+            err: tf.error_code as _,
+            trapno: tf.vector as _,
             ..Default::default()
         }
     }
 
+    fn restore_trap_frame(&self, tf: &mut TrapFrame) {
+        tf.r8 = self.r8 as _;
+        tf.r9 = self.r9 as _;
+        tf.r10 = self.r10 as _;
+        tf.r11 = self.r11 as _;
+        tf.r12 = self.r12 as _;
+        tf.r13 = self.r13 as _;
+        tf.r14 = self.r14 as _;
+        tf.r15 = self.r15 as _;
+        tf.rdi = self.rdi as _;
+        tf.rsi = self.rsi as _;
+        tf.rbp = self.rbp as _;
+        tf.rbx = self.rbx as _;
+        tf.rdx = self.rdx as _;
+        tf.rax = self.rax as _;
+        tf.rcx = self.rcx as _;
+        tf.rsp = self.rsp as _;
+        tf.rip = self.rip as _;
+        tf.rflags = (tf.rflags & !FIX_EFLAGS) | (self.eflags as u64 & FIX_EFLAGS);
+    }
+
     fn get_pc(&self) -> usize {
         self.rip
     }
 }
 
-#[repr(C)]
+#[repr(C, align(16))]
 #[derive(Clone)]
 /// The user context saved for the signal action, which can be accessed by the signal handler
 pub struct SignalUserContext {
@@ -81,19 +109,45 @@ pub struct SignalUserContext {
     stack: SignalStack,
     mcontext: MContext,
     sigmask: u64,
-    _fpregs: [usize; 64],
+    fpregs: FxsaveArea,
 }
 
 impl SignalUserContext {
-    /// init the user context by the pc and the mask
-    pub fn init(pc: usize, mask: usize) -> Self {
+    /// init the user context by the trap frame, the mask and the alternate signal stack
+    pub fn init(trap_frame: &TrapFrame, mask: usize, stack: SignalStack) -> Self {
         Self {
             flags: 0,
             link: 0,
-            stack: SignalStack::default(),
-            mcontext: MContext::init_by_pc(pc),
+            stack,
+            mcontext: MContext::init_by_trap_frame(trap_frame),
             sigmask: mask as u64,
-            _fpregs: [0; 64],
+            fpregs: unsafe { core::mem::MaybeUninit::zeroed().assume_init() },
+        }
+    }
+
+    /// 保存当前的浮点状态
+    ///
+    /// 必须在上下文已经写入用户栈之后调用，因为 `fpstate` 指向上下文自身
+    pub fn save_fp_state(&mut self) {
+        #[cfg(feature = "fp_simd")]
+        self.fpregs.save();
+        self.mcontext.fpstate = &self.fpregs as *const FxsaveArea as usize;
+    }
+
+    /// 用保存的上下文恢复 trap 上下文与浮点状态
+    pub fn restore(&self, trap_frame: &mut TrapFrame) {
+        self.mcontext.restore_trap_frame(trap_frame);
+        #[cfg(feature = "fp_simd")]
+        if self.mcontext.fpstate != 0 {
+            let mut fpregs = unsafe { *(self.mcontext.fpstate as *const FxsaveArea) };
+            // 非法的 mxcsr 会导致 fxrstor 触发 #GP
+            let mxcsr_mask = if fpregs.mxcsr_mask == 0 {
+                0xffbf
+            } else {
+                fpregs.mxcsr_mask
+            };
+            fpregs.mxcsr &= mxcsr_mask;
+            fpregs.restore();
         }
     }
 
@@ -101,4 +155,27 @@ impl SignalUserContext {
     pub fn get_pc(&self) -> usize {
         self.mcontext.get_pc()
     }
+
+    /// 保存的信号掩码
+    pub fn get_mask(&self) -> usize {
+        self.sigmask as usize
+    }
+
+    /// 保存的备用信号栈
+    pub fn get_stack(&self) -> SignalStack {
+        self.stack
+    }
+
+    /// `restore` 会读取的用户态浮点状态所在的地址范围 `[start, end)`
+    ///
+    /// `fpstate` 由用户控制，恢复前需要由调用者检查该范围是否可访问
+    pub fn user_fp_range(&self) -> Option<(usize, usize)> {
+        match self.mcontext.fpstate {
+            0 => None,
+            start => Some((
+                start,
+                start.saturating_add(core::mem::size_of::<FxsaveArea>()),
+            )),
+        }
+    }
 }
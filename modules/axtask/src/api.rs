@@ -162,6 +162,10 @@ pub fn sleep_until(deadline: axhal::time::TimeValue) {
 
 /// Exits the current task.
 pub fn exit(exit_code: i32) -> ! {
+    #[cfg(feature = "monolithic")]
+    if current().is_init() {
+        crate_interface::call_interface!(crate::TerminateIf::before_terminate());
+    }
     RUN_QUEUE.lock().exit_current(exit_code)
 }
 
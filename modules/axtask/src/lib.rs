@@ -36,7 +36,7 @@ cfg_if::cfg_if! {
         extern crate alloc;
 
         mod run_queue;
-        pub use run_queue::{IDLE_TASK, RUN_QUEUE, EXITED_TASKS, VforkSet};
+        pub use run_queue::{IDLE_TASK, RUN_QUEUE, EXITED_TASKS, TerminateIf, VforkSet};
         mod task;
         pub use task::{TaskState, VforkCheck};
         mod api;
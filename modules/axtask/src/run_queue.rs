@@ -38,6 +38,13 @@ pub trait VforkSet {
     fn vfork_set(&self, process_id: u64, value: bool);
 }
 
+#[crate_interface::def_interface]
+/// Hook called before the system terminates
+pub trait TerminateIf {
+    /// Called when the init task exits, before the run queue is locked
+    fn before_terminate();
+}
+
 impl AxRunQueue {
     pub fn new() -> SpinNoIrq<Self> {
         let gc_task = TaskInner::new(
@@ -1,7 +1,9 @@
 //! 负责任务时间统计的实现
+use alloc::sync::Arc;
 use axhal::time::{current_time_nanos, NANOS_PER_MICROS, NANOS_PER_SEC};
 #[cfg(feature = "signal")]
 use axsignal::signal_no::SignalNo;
+use core::sync::atomic::{AtomicUsize, Ordering};
 #[cfg(feature = "signal")]
 use crate_interface::{call_interface, def_interface};
 numeric_enum_macro::numeric_enum! {
@@ -29,6 +31,31 @@ impl From<usize> for TimerType {
         }
     }
 }
+/// 同一进程中所有线程共享的 CPU 时间，RLIMIT_CPU 按进程计算
+struct ProcessCpuTime {
+    /// 进程中所有线程在用户态与内核态经过的时间之和，单位为纳秒
+    total_ns: AtomicUsize,
+    /// RLIMIT_CPU 的硬上限，CPU 时间达到后发送 SIGKILL，为 usize::MAX 时不限制
+    hard_limit_ns: AtomicUsize,
+    /// 下一次发送 SIGXCPU 的 CPU 时间，初始为软上限，超过后每秒发送一次，为 usize::MAX 时不限制
+    next_xcpu_ns: AtomicUsize,
+}
+
+impl ProcessCpuTime {
+    fn new() -> Self {
+        Self {
+            total_ns: AtomicUsize::new(0),
+            hard_limit_ns: AtomicUsize::new(usize::MAX),
+            next_xcpu_ns: AtomicUsize::new(usize::MAX),
+        }
+    }
+
+    /// 计入任务经过的 CPU 时间
+    fn add(&self, delta_ns: usize) {
+        self.total_ns.fetch_add(delta_ns, Ordering::Relaxed);
+    }
+}
+
 pub struct TimeStat {
     /// 用户态经过的时间，单位为纳秒
     utime_ns: usize,
@@ -48,6 +75,8 @@ pub struct TimeStat {
     ///
     /// 根据timer_type的种类来进行计算，当归零的时候触发信号，同时进行更新
     timer_remained_ns: usize,
+    /// 所属进程的 CPU 时间与 RLIMIT_CPU 上限
+    process_cpu: Arc<ProcessCpuTime>,
 }
 
 #[cfg(feature = "signal")]
@@ -71,6 +100,7 @@ impl TimeStat {
             timer_type: TimerType::NONE,
             timer_interval_ns: 0,
             timer_remained_ns: 0,
+            process_cpu: Arc::new(ProcessCpuTime::new()),
         }
     }
     /// 清空时间统计，用于exec
@@ -85,10 +115,12 @@ impl TimeStat {
         let now_time_ns = current_time_nanos() as usize;
         let delta = now_time_ns - self.user_tick;
         self.utime_ns += delta;
+        self.process_cpu.add(delta);
         self.kernel_tick = now_time_ns;
         if self.timer_type != TimerType::NONE {
             self.update_timer(delta, tid);
         };
+        self.check_cpu_limit(tid);
     }
     /// 从内核态进入用户态，记录当前时间戳，统计内核态时间
     pub fn switch_into_user_mode(&mut self, tid: isize) {
@@ -96,10 +128,12 @@ impl TimeStat {
         let now_time_ns = current_time_nanos() as usize;
         let delta = now_time_ns - self.kernel_tick;
         self.stime_ns += delta;
+        self.process_cpu.add(delta);
         self.user_tick = now_time_ns;
         if self.timer_type == TimerType::REAL || self.timer_type == TimerType::PROF {
             self.update_timer(delta, tid);
         };
+        self.check_cpu_limit(tid);
     }
     /// 内核态下，当前任务被切换掉，统计内核态时间
     pub fn swtich_from_old_task(&mut self, tid: isize) {
@@ -107,11 +141,13 @@ impl TimeStat {
         let now_time_ns = current_time_nanos() as usize;
         let delta = now_time_ns - self.kernel_tick;
         self.stime_ns += delta;
+        self.process_cpu.add(delta);
         // 需要更新内核态时间戳
         self.kernel_tick = now_time_ns;
         if self.timer_type == TimerType::REAL || self.timer_type == TimerType::PROF {
             self.update_timer(delta, tid);
         };
+        self.check_cpu_limit(tid);
     }
     /// 内核态下，切换到当前任务，更新内核态时间戳
     pub fn switch_to_new_task(&mut self, tid: isize) {
@@ -157,6 +193,63 @@ impl TimeStat {
         self.timer_type != TimerType::NONE
     }
 
+    /// 设定所属进程 RLIMIT_CPU 的软上限与硬上限，单位为纳秒，usize::MAX 表示不限制
+    pub fn set_cpu_limit(&mut self, soft_ns: usize, hard_ns: usize) {
+        let cpu = &self.process_cpu;
+        cpu.hard_limit_ns.store(hard_ns, Ordering::Relaxed);
+        cpu.next_xcpu_ns.store(soft_ns, Ordering::Relaxed);
+    }
+
+    /// 与另一个任务共享进程的 CPU 时间与 RLIMIT_CPU 上限，用于创建线程
+    pub fn share_process_cpu(&mut self, other: &TimeStat) {
+        self.process_cpu = Arc::clone(&other.process_cpu);
+    }
+
+    /// 检查所属进程的 CPU 时间是否达到 RLIMIT_CPU
+    ///
+    /// CPU 时间为进程中所有线程的用户态与内核态时间之和。
+    /// 达到软上限时发送 SIGXCPU，之后每经过一秒再发送一次；达到硬上限时发送 SIGKILL
+    pub fn check_cpu_limit(&mut self, _tid: isize) {
+        let cpu = &self.process_cpu;
+        let cpu_time_ns = cpu.total_ns.load(Ordering::Relaxed);
+        let hard_limit_ns = cpu.hard_limit_ns.load(Ordering::Relaxed);
+        let next_xcpu_ns = cpu.next_xcpu_ns.load(Ordering::Relaxed);
+        if cpu_time_ns < next_xcpu_ns && cpu_time_ns < hard_limit_ns {
+            return;
+        }
+        #[cfg(feature = "signal")]
+        {
+            // 多个线程同时达到上限时只由一个线程发送信号
+            let signal_num = if cpu_time_ns >= hard_limit_ns {
+                // 只发送一次
+                if cpu
+                    .hard_limit_ns
+                    .compare_exchange(
+                        hard_limit_ns,
+                        usize::MAX,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    )
+                    .is_err()
+                {
+                    return;
+                }
+                SignalNo::SIGKILL
+            } else {
+                let next = cpu_time_ns + NANOS_PER_SEC as usize;
+                if cpu
+                    .next_xcpu_ns
+                    .compare_exchange(next_xcpu_ns, next, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_err()
+                {
+                    return;
+                }
+                SignalNo::SIGXCPU
+            };
+            call_interface!(SignalCaller::send_signal(_tid, signal_num as isize));
+        }
+    }
+
     /// 更新计时器，同时判断是否要发出信号
     pub fn update_timer(&mut self, delta: usize, _tid: isize) {
         if self.timer_remained_ns == 0 {
@@ -345,6 +345,20 @@ impl TaskInner {
         unsafe { (*time).set_timer(timer_interval_ns, timer_remained_ns, timer_type) }
     }
 
+    #[inline]
+    /// 设置 RLIMIT_CPU 的软上限与硬上限，单位为纳秒，usize::MAX 表示不限制
+    pub fn set_cpu_limit(&self, soft_ns: usize, hard_ns: usize) {
+        let time = self.time.get();
+        unsafe { (*time).set_cpu_limit(soft_ns, hard_ns) }
+    }
+
+    #[inline]
+    /// 与另一个任务共享所属进程的 CPU 时间，二者共同计入 RLIMIT_CPU
+    pub fn share_process_cpu_time(&self, other: &TaskInner) {
+        let time = self.time.get();
+        unsafe { (*time).share_process_cpu(&*other.time.get()) }
+    }
+
     #[inline]
     /// 重置统计时间
     pub fn time_stat_clear(&self) {
@@ -457,6 +471,12 @@ impl TaskInner {
         self.ctx.get().as_mut().unwrap().fs_base = value;
     }
 
+    #[cfg(target_arch = "x86_64")]
+    /// 获取保存在任务上下文中的 `fs_base`
+    pub fn get_tls_force(&self) -> usize {
+        unsafe { (*self.ctx.get()).fs_base }
+    }
+
     /// 获取父进程blocked_by_vfork布尔值
     pub fn is_vfork(&self) -> bool {
         call_interface!(VforkCheck::check_vfork(self.get_process_id()))
@@ -151,6 +151,41 @@ impl WaitQueue {
         timeout
     }
 
+    /// Blocks the current task and put it into the wait queue at most once,
+    /// unless the given `condition` is already true.
+    ///
+    /// The task wakes up when other tasks notify it, the optional `deadline` is
+    /// reached, or it is unblocked by others (e.g. a signal). The condition is
+    /// checked with `RUN_QUEUE` locked, so a notification sent after it is
+    /// evaluated will never be lost. It must not sleep.
+    ///
+    /// Returns false if the condition was already true.
+    #[cfg(feature = "irq")]
+    pub fn wait_once_unless<F>(
+        &self,
+        deadline: Option<axhal::time::TimeValue>,
+        condition: F,
+    ) -> bool
+    where
+        F: Fn() -> bool,
+    {
+        let curr = crate::current();
+        let mut rq = RUN_QUEUE.lock();
+        if condition() {
+            return false;
+        }
+        if let Some(deadline) = deadline {
+            crate::timers::set_alarm_wakeup(deadline, curr.clone());
+        }
+        rq.block_current(|task| {
+            task.set_in_wait_queue(true);
+            self.queue.lock().push_back(task);
+        });
+        drop(rq);
+        self.cancel_events(curr);
+        true
+    }
+
     /// Wakes up one task in the wait queue, usually the first one.
     ///
     /// If `resched` is true, the current task will be preempted when the
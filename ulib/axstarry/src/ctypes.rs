@@ -1,22 +1,24 @@
+use axfs::api::{FileSystemInfo, FileType, Kstat};
+use axfs::fops::FileAttr;
 use axhal::{
     paging::MappingFlags,
     time::{current_time_nanos, nanos_to_ticks, MICROS_PER_SEC, NANOS_PER_MICROS, NANOS_PER_SEC},
 };
 use bitflags::*;
 use core::panic;
+use core::time::Duration;
 /// The nano seconds number per second
 pub const NSEC_PER_SEC: usize = 1_000_000_000;
-bitflags! {
-    /// 指定 sys_wait4 的选项
-    pub struct WaitFlags: u32 {
-        /// 不挂起当前进程，直接返回
-        const WNOHANG = 1 << 0;
-        /// 报告已执行结束的用户进程的状态
-        const WIMTRACED = 1 << 1;
-        /// 报告还未结束的用户进程的状态
-        const WCONTINUED = 1 << 3;
-    }
-}
+/// waitid 等待任意子进程
+pub const P_ALL: usize = 0;
+/// waitid 等待指定进程号的子进程
+pub const P_PID: usize = 1;
+/// waitid 等待指定进程组中的子进程
+pub const P_PGID: usize = 2;
+/// waitid 等待 pidfd 指向的子进程
+pub const P_PIDFD: usize = 3;
+/// pidfd_open 创建非阻塞的 pidfd，与 O_NONBLOCK 相同
+pub const PIDFD_NONBLOCK: usize = 0o4000;
 /// sys_times 中指定的结构体类型
 #[repr(C)]
 pub struct Tms {
@@ -119,6 +121,17 @@ impl TimeSecs {
             } // 设为指定时间
         }
     }
+
+    /// 将 utimensat 给出的时间转换为要设置的时间
+    ///
+    /// nsec 为 UTIME_NOW 时为当前时间，为 UTIME_OMIT 时为 None
+    pub fn to_utime(&self) -> Option<Duration> {
+        match self.tv_nsec {
+            UTIME_NOW => Some(Duration::from_nanos(current_time_nanos())),
+            UTIME_OMIT => None,
+            _ => Some(Duration::new(self.tv_sec as u64, self.tv_nsec as u32)),
+        }
+    }
 }
 
 bitflags! {
@@ -186,20 +199,19 @@ pub struct UtsName {
     pub domainname: [u8; 65],
 }
 
-impl Default for UtsName {
-    fn default() -> Self {
+impl UtsName {
+    /// 主机名与域名来自进程的 UTS 命名空间，其余字段固定
+    pub fn new(nodename: &str, domainname: &str) -> Self {
         Self {
             sysname: Self::from_str("Starry"),
-            nodename: Self::from_str("Starry - machine[0]"),
+            nodename: Self::from_str(nodename),
             release: Self::from_str("100"),
             version: Self::from_str("1.0"),
             machine: Self::from_str("RISC-V 64 on SIFIVE FU740"),
-            domainname: Self::from_str("https://github.com/Azure-stars/arceos"),
+            domainname: Self::from_str(domainname),
         }
     }
-}
 
-impl UtsName {
     fn from_str(info: &str) -> [u8; 65] {
         let mut data: [u8; 65] = [0; 65];
         data[..info.len()].copy_from_slice(info.as_bytes());
@@ -232,22 +244,6 @@ impl SigMaskFlag {
     }
 }
 
-/// sys_prlimit64 使用的数组
-#[repr(C)]
-pub struct RLimit {
-    /// 软上限
-    pub rlim_cur: u64,
-    /// 硬上限
-    pub rlim_max: u64,
-}
-// sys_prlimit64 使用的选项
-/// 用户栈大小
-pub const RLIMIT_STACK: i32 = 3;
-/// 可以打开的 fd 数
-pub const RLIMIT_NOFILE: i32 = 7;
-/// 用户地址空间的最大大小
-pub const RLIMIT_AS: i32 = 9;
-
 /// robust list
 #[repr(C)]
 pub struct RobustList {
@@ -304,11 +300,39 @@ numeric_enum_macro::numeric_enum! {
         F_GETFL = 3,
         /// 设置 flags 信息
         F_SETFL = 4,
+        /// 查询与给定记录锁冲突的锁
+        F_GETLK = 5,
+        /// 设置或释放记录锁，冲突时返回 EAGAIN
+        F_SETLK = 6,
+        /// 设置或释放记录锁，冲突时等待
+        F_SETLKW = 7,
+        /// 查询与给定 OFD 锁冲突的锁
+        F_OFD_GETLK = 36,
+        /// 设置或释放 OFD 锁，冲突时返回 EAGAIN
+        F_OFD_SETLK = 37,
+        /// 设置或释放 OFD 锁，冲突时等待
+        F_OFD_SETLKW = 38,
         /// 复制 fd，然后设置 cloexec 信息，即 exec 成功时删除该 fd
         F_DUPFD_CLOEXEC = 1030,
     }
 }
 
+/// sys_fcntl64 的加锁命令使用的 `struct flock`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Flock {
+    /// 锁的类型，为 F_RDLCK、F_WRLCK 或 F_UNLCK
+    pub l_type: i16,
+    /// `l_start` 的起点，为 SEEK_SET、SEEK_CUR 或 SEEK_END
+    pub l_whence: i16,
+    /// 锁定范围的起始位置
+    pub l_start: i64,
+    /// 锁定范围的长度，为 0 时直到文件末尾，为负数时锁定 `l_start` 之前的字节
+    pub l_len: i64,
+    /// F_GETLK 返回持有冲突锁的进程号，OFD 锁为 -1
+    pub l_pid: i32,
+}
+
 /// syscall_info 用到的 结构体
 #[repr(C)]
 #[derive(Debug)]
@@ -459,7 +483,7 @@ bitflags! {
 #[repr(C)]
 #[derive(Debug)]
 pub struct FsStat {
-    /// 是个 magic number，每个知名的 fs 都各有定义，但显然我们没有
+    /// 文件系统类型的 magic number，如 ext4 为 0xef53
     pub f_type: i64,
     /// 最优传输块大小
     pub f_bsize: i64,
@@ -473,32 +497,32 @@ pub struct FsStat {
     pub f_files: u64,
     /// 空闲的 inode 数
     pub f_ffree: u64,
-    /// 文件系统编号，但实际上对于不同的OS差异很大，所以不会特地去用
+    /// 文件系统编号，用于区分同一类型的不同文件系统
     pub f_fsid: [i32; 2],
-    /// 文件名长度限制，这个OS默认FAT已经使用了加长命名
+    /// 文件名长度限制
     pub f_namelen: isize,
-    /// 片大小
+    /// 片大小，即块数所用的单位
     pub f_frsize: isize,
-    /// 一些选项，但其实也没用到
+    /// 挂载选项，如 ST_RDONLY
     pub f_flags: isize,
     /// 空余 padding
     pub f_spare: [isize; 4],
 }
 
-/// 获取一个基础的fsstat
-pub fn get_fs_stat() -> FsStat {
+/// 根据文件系统给出的统计信息构造 statfs 的结果
+pub fn fs_stat_from_info(info: &FileSystemInfo) -> FsStat {
     FsStat {
-        f_type: 0,
-        f_bsize: 1024,
-        f_blocks: 0x4000_0000 / 512,
-        f_bfree: 1,
-        f_bavail: 1,
-        f_files: 1,
-        f_ffree: 1,
-        f_fsid: [0, 0],
-        f_namelen: 256,
-        f_frsize: 0x1000,
-        f_flags: 0,
+        f_type: info.fs_type() as i64,
+        f_bsize: info.block_size() as i64,
+        f_blocks: info.blocks(),
+        f_bfree: info.blocks_free(),
+        f_bavail: info.blocks_avail(),
+        f_files: info.files(),
+        f_ffree: info.files_free(),
+        f_fsid: [info.fsid() as i32, (info.fsid() >> 32) as i32],
+        f_namelen: info.name_len() as isize,
+        f_frsize: info.block_size() as isize,
+        f_flags: info.flags().bits() as isize,
         f_spare: [0, 0, 0, 0],
     }
 }
@@ -512,6 +536,14 @@ bitflags! {
         const S_IFDIR = 1 << 14;
         /// character device
         const S_IFCHR = 1 << 13;
+        /// symbolic link
+        const S_IFLNK = (1 << 15) | (1 << 13);
+        /// block device
+        const S_IFBLK = (1 << 14) | (1 << 13);
+        /// FIFO
+        const S_IFIFO = 1 << 12;
+        /// socket
+        const S_IFSOCK = (1 << 15) | (1 << 14);
         /// 是否设置 uid/gid/sticky
         //const S_ISUID = 1 << 14;
         //const S_ISGID = 1 << 13;
@@ -546,6 +578,191 @@ pub fn normal_file_mode(file_type: StMode) -> StMode {
     file_type | StMode::S_IWUSR | StMode::S_IRUSR | StMode::S_IRGRP | StMode::S_IROTH
 }
 
+/// 文件类型在 st_mode 中对应的类型位
+pub fn file_type_mode(ty: FileType) -> StMode {
+    match ty {
+        FileType::Fifo => StMode::S_IFIFO,
+        FileType::CharDevice => StMode::S_IFCHR,
+        FileType::Dir => StMode::S_IFDIR,
+        FileType::BlockDevice => StMode::S_IFBLK,
+        FileType::File => StMode::S_IFREG,
+        FileType::SymLink => StMode::S_IFLNK,
+        FileType::Socket => StMode::S_IFSOCK,
+    }
+}
+
+/// 根据文件系统给出的 inode 属性构造 stat，设备号由调用者填写
+pub fn stat_from_attr(attr: &FileAttr) -> Kstat {
+    Kstat {
+        st_ino: attr.ino(),
+        st_mode: file_type_mode(attr.file_type()).bits() | attr.perm().bits() as u32,
+        st_nlink: attr.nlink() as _,
+        st_uid: attr.uid(),
+        st_gid: attr.gid(),
+        st_rdev: attr.rdev(),
+        st_size: attr.size(),
+        st_blksize: axfs::BLOCK_SIZE as u32,
+        st_blocks: attr.blocks(),
+        st_atime_sec: attr.atime().as_secs() as isize,
+        st_atime_nsec: attr.atime().subsec_nanos() as isize,
+        st_mtime_sec: attr.mtime().as_secs() as isize,
+        st_mtime_nsec: attr.mtime().subsec_nanos() as isize,
+        st_ctime_sec: attr.ctime().as_secs() as isize,
+        st_ctime_nsec: attr.ctime().subsec_nanos() as isize,
+        ..Kstat::default()
+    }
+}
+
+bitflags! {
+    /// statx 中请求与返回的字段
+    #[derive(Debug, Clone, Copy)]
+    pub struct StatxMask: u32 {
+        /// stx_mode 中的文件类型
+        const TYPE = 0x1;
+        /// stx_mode 中的权限位
+        const MODE = 0x2;
+        /// stx_nlink
+        const NLINK = 0x4;
+        /// stx_uid
+        const UID = 0x8;
+        /// stx_gid
+        const GID = 0x10;
+        /// stx_atime
+        const ATIME = 0x20;
+        /// stx_mtime
+        const MTIME = 0x40;
+        /// stx_ctime
+        const CTIME = 0x80;
+        /// stx_ino
+        const INO = 0x100;
+        /// stx_size
+        const SIZE = 0x200;
+        /// stx_blocks
+        const BLOCKS = 0x400;
+        /// 与 stat 相同的所有字段
+        const BASIC_STATS = 0x7ff;
+        /// stx_btime，即文件的创建时间
+        const BTIME = 0x800;
+        /// 保留给以后扩展 statx 结构体，不能请求
+        const RESERVED = 0x8000_0000;
+    }
+}
+
+/// statx 中的时间戳
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatxTimestamp {
+    /// 秒
+    pub tv_sec: i64,
+    /// 纳秒
+    pub tv_nsec: u32,
+    /// padding
+    pub __reserved: i32,
+}
+
+impl From<Duration> for StatxTimestamp {
+    fn from(time: Duration) -> Self {
+        Self {
+            tv_sec: time.as_secs() as i64,
+            tv_nsec: time.subsec_nanos(),
+            __reserved: 0,
+        }
+    }
+}
+
+/// statx 返回的文件属性，共 256 字节
+/// 具体参数定义信息来自 `https://man7.org/linux/man-pages/man2/statx.2.html`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Statx {
+    /// 实际填写了的字段，见 [`StatxMask`]
+    pub stx_mask: u32,
+    /// 块大小
+    pub stx_blksize: u32,
+    /// 文件的额外属性，如只可追加
+    pub stx_attributes: u64,
+    /// 硬链接数
+    pub stx_nlink: u32,
+    /// 用户id
+    pub stx_uid: u32,
+    /// 用户组id
+    pub stx_gid: u32,
+    /// 文件类型与权限
+    pub stx_mode: u16,
+    /// padding
+    pub __spare0: u16,
+    /// inode 编号
+    pub stx_ino: u64,
+    /// 文件大小
+    pub stx_size: u64,
+    /// 以 512 字节为单位的块个数
+    pub stx_blocks: u64,
+    /// stx_attributes 中文件系统支持的属性
+    pub stx_attributes_mask: u64,
+    /// 最后一次访问时间
+    pub stx_atime: StatxTimestamp,
+    /// 创建时间
+    pub stx_btime: StatxTimestamp,
+    /// 最后一次改变状态时间
+    pub stx_ctime: StatxTimestamp,
+    /// 最后一次修改时间
+    pub stx_mtime: StatxTimestamp,
+    /// 设备文件的主设备号
+    pub stx_rdev_major: u32,
+    /// 设备文件的次设备号
+    pub stx_rdev_minor: u32,
+    /// 文件所在设备的主设备号
+    pub stx_dev_major: u32,
+    /// 文件所在设备的次设备号
+    pub stx_dev_minor: u32,
+    /// 挂载点编号
+    pub stx_mnt_id: u64,
+    /// direct I/O 时内存的对齐要求
+    pub stx_dio_mem_align: u32,
+    /// direct I/O 时文件偏移的对齐要求
+    pub stx_dio_offset_align: u32,
+    /// 空余 padding
+    pub __spare3: [u64; 12],
+}
+
+/// 设备号中的主设备号，编码方式与 glibc 的 makedev 相同
+pub fn dev_major(dev: u64) -> u32 {
+    (((dev >> 32) & 0xffff_f000) | ((dev >> 8) & 0xfff)) as u32
+}
+
+/// 设备号中的次设备号，编码方式与 glibc 的 makedev 相同
+pub fn dev_minor(dev: u64) -> u32 {
+    (((dev >> 12) & 0xffff_ff00) | (dev & 0xff)) as u32
+}
+
+/// 根据 stat 的结果构造 statx 的结果，填写 stat 中已有的所有字段
+pub fn statx_from_stat(kst: &Kstat) -> Statx {
+    let timestamp = |sec: isize, nsec: isize| StatxTimestamp {
+        tv_sec: sec as i64,
+        tv_nsec: nsec as u32,
+        __reserved: 0,
+    };
+    Statx {
+        stx_mask: StatxMask::BASIC_STATS.bits(),
+        stx_blksize: kst.st_blksize,
+        stx_nlink: kst.st_nlink as u32,
+        stx_uid: kst.st_uid,
+        stx_gid: kst.st_gid,
+        stx_mode: kst.st_mode as u16,
+        stx_ino: kst.st_ino,
+        stx_size: kst.st_size,
+        stx_blocks: kst.st_blocks,
+        stx_atime: timestamp(kst.st_atime_sec, kst.st_atime_nsec),
+        stx_ctime: timestamp(kst.st_ctime_sec, kst.st_ctime_nsec),
+        stx_mtime: timestamp(kst.st_mtime_sec, kst.st_mtime_nsec),
+        stx_rdev_major: dev_major(kst.st_rdev),
+        stx_rdev_minor: dev_minor(kst.st_rdev),
+        stx_dev_major: dev_major(kst.st_dev),
+        stx_dev_minor: dev_minor(kst.st_dev),
+        ..Statx::default()
+    }
+}
+
 /// prctl 中 PR_NAME_SIZE 要求的缓冲区长度
 pub const PR_NAME_SIZE: usize = 16;
 
@@ -560,6 +777,14 @@ numeric_enum_macro::numeric_enum! {
         PR_SET_NAME = 15,
         /// get the name of the process
         PR_GET_NAME = 16,
+        /// get the seccomp mode of the process
+        PR_GET_SECCOMP = 21,
+        /// enter strict mode or install a seccomp filter
+        PR_SET_SECCOMP = 22,
+        /// set the no_new_privs bit, which cannot be unset
+        PR_SET_NO_NEW_PRIVS = 38,
+        /// get the no_new_privs bit
+        PR_GET_NO_NEW_PRIVS = 39,
     }
 }
 
@@ -590,3 +815,90 @@ pub struct CloneArgs {
     /// 该信息 Starry 暂未支持
     pub cgroup: u64,
 }
+
+numeric_enum_macro::numeric_enum! {
+    #[repr(usize)]
+    #[allow(non_camel_case_types)]
+    #[derive(Eq, PartialEq, Debug, Copy, Clone)]
+    /// sys_ptrace 的请求
+    pub enum PtraceRequest {
+        /// 由父进程跟踪自身
+        PTRACE_TRACEME = 0,
+        /// 读取代码段中的一个字
+        PTRACE_PEEKTEXT = 1,
+        /// 读取数据段中的一个字
+        PTRACE_PEEKDATA = 2,
+        /// 读取 `struct user` 中的一个字
+        PTRACE_PEEKUSER = 3,
+        /// 写入代码段中的一个字
+        PTRACE_POKETEXT = 4,
+        /// 写入数据段中的一个字
+        PTRACE_POKEDATA = 5,
+        /// 写入 `struct user` 中的一个字
+        PTRACE_POKEUSER = 6,
+        /// 恢复运行
+        PTRACE_CONT = 7,
+        /// 杀死被跟踪进程
+        PTRACE_KILL = 8,
+        /// 单步执行
+        PTRACE_SINGLESTEP = 9,
+        /// 读取通用寄存器
+        PTRACE_GETREGS = 12,
+        /// 写入通用寄存器
+        PTRACE_SETREGS = 13,
+        /// 跟踪指定进程，并使其停止
+        PTRACE_ATTACH = 16,
+        /// 解除跟踪
+        PTRACE_DETACH = 17,
+        /// 恢复运行，并在下一次系统调用的进入或返回处停止
+        PTRACE_SYSCALL = 24,
+        /// 设置跟踪选项
+        PTRACE_SETOPTIONS = 0x4200,
+        /// 获取事件停止的附加信息
+        PTRACE_GETEVENTMSG = 0x4201,
+        /// 获取导致停止的信号信息
+        PTRACE_GETSIGINFO = 0x4202,
+        /// 修改导致停止的信号信息
+        PTRACE_SETSIGINFO = 0x4203,
+        /// 按 `NT_*` 类型读取寄存器
+        PTRACE_GETREGSET = 0x4204,
+        /// 按 `NT_*` 类型写入寄存器
+        PTRACE_SETREGSET = 0x4205,
+        /// 跟踪指定进程，但不使其停止
+        PTRACE_SEIZE = 0x4206,
+    }
+}
+
+/// PTRACE_GETREGSET 中通用寄存器的类型
+pub const NT_PRSTATUS: usize = 1;
+
+numeric_enum_macro::numeric_enum! {
+    #[repr(usize)]
+    #[allow(non_camel_case_types)]
+    #[derive(Eq, PartialEq, Debug, Copy, Clone)]
+    /// sys_syslog 的操作类型
+    pub enum SyslogAction {
+        /// 关闭日志，不做处理
+        SYSLOG_ACTION_CLOSE = 0,
+        /// 打开日志，不做处理
+        SYSLOG_ACTION_OPEN = 1,
+        /// 读取并消耗未读的日志，没有日志时阻塞
+        SYSLOG_ACTION_READ = 2,
+        /// 读取缓冲区中的全部日志
+        SYSLOG_ACTION_READ_ALL = 3,
+        /// 读取全部日志后清空
+        SYSLOG_ACTION_READ_CLEAR = 4,
+        /// 清空日志
+        SYSLOG_ACTION_CLEAR = 5,
+        /// 关闭控制台输出，不做处理
+        SYSLOG_ACTION_CONSOLE_OFF = 6,
+        /// 打开控制台输出，不做处理
+        SYSLOG_ACTION_CONSOLE_ON = 7,
+        /// 设置控制台日志级别，不做处理
+        SYSLOG_ACTION_CONSOLE_LEVEL = 8,
+        /// 获取未读日志的字节数
+        SYSLOG_ACTION_SIZE_UNREAD = 9,
+        /// 获取日志缓冲区的大小
+        SYSLOG_ACTION_SIZE_BUFFER = 10,
+    }
+}
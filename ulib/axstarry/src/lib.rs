@@ -21,6 +21,8 @@ mod syscall_task;
 
 mod syscall;
 
+mod syscall_trace;
+
 mod ctypes;
 use ctypes::*;
 
@@ -1,3 +1,4 @@
+use crate::syscall_trace::{self, TraceClass};
 use crate::{deal_result, SyscallResult};
 use axlog::info;
 
@@ -5,8 +6,28 @@ use axlog::info;
 pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     #[cfg(feature = "futex")]
     crate::syscall_task::check_dead_wait();
+    // 被跟踪时在系统调用进入处停止，跟踪者可能修改系统调用号与参数
+    #[cfg(feature = "signal")]
+    let (mut syscall_id, mut args) = match axprocess::ptrace::syscall_enter(syscall_id, args) {
+        Some(call) => call,
+        None => {
+            let ans = -(crate::SyscallError::ENOSYS.code() as isize);
+            return axprocess::ptrace::syscall_exit(ans);
+        }
+    };
+    // seccomp 过滤器决定是否执行该系统调用，跟踪者可能在 seccomp 停止中修改系统调用号与参数
+    #[cfg(feature = "signal")]
+    if let Some(ans) = axprocess::seccomp::syscall_filter(
+        &axprocess::current_process(),
+        &mut syscall_id,
+        &mut args,
+    ) {
+        return axprocess::ptrace::syscall_exit(ans);
+    }
     #[allow(unused_mut, unused_assignments)]
     let mut ans: Option<SyscallResult> = None;
+    #[allow(unused_mut, unused_assignments)]
+    let mut trace = None;
 
     if let Ok(net_syscall_id) = crate::syscall_net::NetSyscallId::try_from(syscall_id) {
         info!(
@@ -14,6 +35,8 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             net_syscall_id, args
         );
 
+        #[allow(unused_assignments)]
+        trace = syscall_trace::syscall_enter(TraceClass::NET, net_syscall_id, &args);
         #[allow(unused_assignments)]
         ans = Some(crate::syscall_net::net_syscall(net_syscall_id, args));
     }
@@ -24,6 +47,8 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             mem_syscall_id, args
         );
         #[allow(unused_assignments)]
+        trace = syscall_trace::syscall_enter(TraceClass::MEM, mem_syscall_id, &args);
+        #[allow(unused_assignments)]
         ans = Some(crate::syscall_mem::mem_syscall(mem_syscall_id, args));
     }
 
@@ -33,6 +58,8 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             fs_syscall_id, args
         );
         #[allow(unused_assignments)]
+        trace = syscall_trace::syscall_enter(TraceClass::FS, fs_syscall_id, &args);
+        #[allow(unused_assignments)]
         ans = Some(crate::syscall_fs::fs_syscall(fs_syscall_id, args));
     }
 
@@ -42,18 +69,28 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
             task_syscall_id, args
         );
         #[allow(unused_assignments)]
+        trace = syscall_trace::syscall_enter(TraceClass::TASK, task_syscall_id, &args);
+        #[allow(unused_assignments)]
         ans = Some(crate::syscall_task::task_syscall(task_syscall_id, args));
     }
 
     if ans.is_none() {
         panic!("unknown syscall id: {}", syscall_id);
     }
-    let ans = deal_result(ans.unwrap());
+    #[allow(unused_mut)]
+    let mut ans = deal_result(ans.unwrap());
+    if let Some(trace) = trace {
+        trace.finish(ans);
+    }
     if syscall_id != 96 && syscall_id != 98 {
         info!(
             "[syscall] id = {}, args = {:?}, return {}",
             syscall_id, args, ans
         );
     }
+    #[cfg(feature = "signal")]
+    {
+        ans = axprocess::ptrace::syscall_exit(ans);
+    }
     ans
 }
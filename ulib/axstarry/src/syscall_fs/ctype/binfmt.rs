@@ -0,0 +1,142 @@
+//! `/proc/sys/fs/binfmt_misc` 下的文件
+//!
+//! - `register`：写入注册串以添加注册项
+//! - `status`：读取注册项整体是否启用，写入 `1`、`0` 启用或停用，写入 `-1` 删除所有注册项
+//! - `<name>`：读取注册项的描述，写入 `1`、`0`、`-1` 启用、停用或删除该注册项
+use alloc::format;
+use alloc::string::{String, ToString};
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axprocess::binfmt::{self, BinfmtEntry};
+use axsync::Mutex;
+
+const BINFMT_MISC_DIR: &str = "/proc/sys/fs/binfmt_misc";
+
+enum BinfmtNode {
+    Register,
+    Status,
+    Entry(String),
+}
+
+/// `/proc/sys/fs/binfmt_misc` 下的文件
+pub struct BinfmtMiscFile {
+    node: BinfmtNode,
+    /// 读取的位置
+    offset: Mutex<usize>,
+    flags: OpenFlags,
+}
+
+/// 若路径位于 `/proc/sys/fs/binfmt_misc` 下，则打开对应的文件
+///
+/// 路径不在该目录下时返回 None，对应的注册项不存在时返回 NotFound
+pub fn open_binfmt_file(path: &str, flags: OpenFlags) -> Option<AxResult<BinfmtMiscFile>> {
+    let name = path.strip_prefix(BINFMT_MISC_DIR)?.strip_prefix('/')?;
+    let node = match name {
+        "register" => BinfmtNode::Register,
+        "status" => BinfmtNode::Status,
+        name if binfmt::entry(name).is_some() => BinfmtNode::Entry(name.to_string()),
+        _ => return Some(Err(AxError::NotFound)),
+    };
+    Some(Ok(BinfmtMiscFile {
+        node,
+        offset: Mutex::new(0),
+        flags,
+    }))
+}
+
+/// 解析写入 `status` 与注册项文件的命令
+fn parse_command(buf: &[u8]) -> AxResult<i32> {
+    core::str::from_utf8(buf)
+        .map_err(|_| AxError::InvalidInput)?
+        .trim()
+        .parse()
+        .map_err(|_| AxError::InvalidInput)
+}
+
+impl BinfmtMiscFile {
+    fn content(&self) -> AxResult<String> {
+        match &self.node {
+            // 与 Linux 一致，register 只能写入
+            BinfmtNode::Register => Err(AxError::InvalidInput),
+            BinfmtNode::Status => Ok(String::from(if binfmt::is_enabled() {
+                "enabled\n"
+            } else {
+                "disabled\n"
+            })),
+            BinfmtNode::Entry(name) => binfmt::entry(name)
+                .map(|entry| entry.describe())
+                .ok_or(AxError::NotFound),
+        }
+    }
+}
+
+impl FileIO for BinfmtMiscFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let content = self.content()?;
+        let mut offset = self.offset.lock();
+        let rest = content.as_bytes().get(*offset..).unwrap_or(&[]);
+        let len = rest.len().min(buf.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        *offset += len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        match &self.node {
+            BinfmtNode::Register => {
+                let spec = core::str::from_utf8(buf).map_err(|_| AxError::InvalidInput)?;
+                binfmt::register(BinfmtEntry::parse(spec)?)?;
+            }
+            BinfmtNode::Status => match parse_command(buf)? {
+                1 => binfmt::set_enabled(true),
+                0 => binfmt::set_enabled(false),
+                -1 => binfmt::clear(),
+                _ => return Err(AxError::InvalidInput),
+            },
+            BinfmtNode::Entry(name) => match parse_command(buf)? {
+                1 => binfmt::set_entry_enabled(name, true)?,
+                0 => binfmt::set_entry_enabled(name, false)?,
+                -1 => binfmt::unregister(name)?,
+                _ => return Err(AxError::InvalidInput),
+            },
+        }
+        Ok(buf.len())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        let name = match &self.node {
+            BinfmtNode::Register => "register",
+            BinfmtNode::Status => "status",
+            BinfmtNode::Entry(name) => name,
+        };
+        format!("{}/{}", BINFMT_MISC_DIR, name)
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        self.flags
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+}
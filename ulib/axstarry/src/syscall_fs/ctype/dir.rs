@@ -1,4 +1,4 @@
-use crate::{normal_file_mode, StMode};
+use crate::stat_from_attr;
 extern crate alloc;
 use alloc::string::{String, ToString};
 use axerrno::{AxError, AxResult};
@@ -50,27 +50,11 @@ impl FileIO for DirDesc {
     }
 
     fn get_stat(&self) -> AxResult<Kstat> {
-        let kstat = Kstat {
+        let attr = api::lookup(&self.dir_path)?.get_attr()?;
+        Ok(Kstat {
             st_dev: 1,
-            st_ino: 0,
-            st_mode: normal_file_mode(StMode::S_IFDIR).bits(),
-            st_nlink: 1,
-            st_uid: 0,
-            st_gid: 0,
-            st_rdev: 0,
-            _pad0: 0,
-            st_size: 0,
-            st_blksize: 0,
-            _pad1: 0,
-            st_blocks: 0,
-            st_atime_sec: 0,
-            st_atime_nsec: 0,
-            st_mtime_sec: 0,
-            st_mtime_nsec: 0,
-            st_ctime_sec: 0,
-            st_ctime_nsec: 0,
-        };
-        Ok(kstat)
+            ..stat_from_attr(&attr)
+        })
     }
 }
 
@@ -3,15 +3,17 @@ use bitflags::bitflags;
 extern crate alloc;
 use alloc::{
     collections::{BTreeMap, BTreeSet},
-    sync::Arc,
+    sync::{Arc, Weak},
     vec::Vec,
 };
 use axerrno::{AxError, AxResult};
 
-use axfs::api::{FileIO, FileIOType, SeekFrom};
+use axfs::api::{FileIO, FileIOType, PollNotifier, PollWaker, SeekFrom};
 
+use super::poll_waiter::PollWaiter;
 use crate::SyscallError;
-use axprocess::{current_process, yield_now_task};
+#[cfg(feature = "signal")]
+use axprocess::current_process;
 use axsync::Mutex;
 
 bitflags! {
@@ -63,13 +65,77 @@ pub struct EpollFile {
     /// 定义内部可变变量
     /// 由于存在clone，所以要用arc指针包围
     pub inner: Arc<Mutex<EpollFileInner>>,
+    /// 被监控文件的唤醒者与 epoll_wait 之间共享的状态
+    shared: Arc<EpollShared>,
 }
 
 pub struct EpollFileInner {
     /// 监控的所有事件，通过map来进行映射，根据fd找到对应的event
-    monitor_list: BTreeMap<i32, EpollEvent>,
-    /// 响应的事件集
-    _response_list: BTreeSet<i32>,
+    monitor_list: BTreeMap<i32, EpollItem>,
+}
+
+/// 一个被监控的文件
+struct EpollItem {
+    /// 用户注册的事件，data 原样返回给用户
+    event: EpollEvent,
+    /// 被监控的文件，文件被真正关闭后自动移出监控
+    file: Weak<dyn FileIO>,
+    /// 登记在文件通知列表上的唤醒者
+    waker: Arc<dyn PollWaker>,
+    /// 文件不会主动通知，每次等待都需要重新检查
+    recheck: bool,
+    /// 设置了 EPOLLONESHOT 且已经报告过一次，直到 MOD 之前不再报告
+    disabled: bool,
+}
+
+struct EpollShared {
+    /// 可能就绪的fd，由唤醒者加入，epoll_wait 检查后移除
+    ///
+    /// 水平触发的fd在报告后仍然留在其中，直到检查发现其不再就绪
+    ready_list: Mutex<BTreeSet<i32>>,
+    /// 阻塞在 epoll_wait 上的任务
+    waiter: PollWaiter,
+    /// epoll 文件自身的就绪通知，用于嵌套 epoll 或对 epoll fd 进行 poll
+    notifier: PollNotifier,
+}
+
+/// 登记在被监控文件上的唤醒者，文件状态变化时将对应fd加入就绪候选集合
+struct EpollItemWaker {
+    shared: Weak<EpollShared>,
+    fd: i32,
+    exclusive: bool,
+}
+
+impl PollWaker for EpollItemWaker {
+    fn wake(&self) {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.ready_list.lock().insert(self.fd);
+            shared.waiter.wake();
+            shared.notifier.notify();
+        }
+    }
+
+    fn exclusive(&self) -> bool {
+        self.exclusive
+    }
+}
+
+/// 检查文件当前满足的事件，错误和挂断总是会被报告
+fn poll_file(file: &dyn FileIO, request: EpollEventType) -> EpollEventType {
+    let mut ret_event_type = EpollEventType::empty();
+    if file.is_hang_up() {
+        ret_event_type |= EpollEventType::EPOLLHUP;
+    }
+    if file.in_exceptional_conditions() {
+        ret_event_type |= EpollEventType::EPOLLERR;
+    }
+    if request.contains(EpollEventType::EPOLLIN) && file.ready_to_read() {
+        ret_event_type |= EpollEventType::EPOLLIN;
+    }
+    if request.contains(EpollEventType::EPOLLOUT) && file.ready_to_write() {
+        ret_event_type |= EpollEventType::EPOLLOUT;
+    }
+    ret_event_type
 }
 
 impl EpollFile {
@@ -78,8 +144,12 @@ impl EpollFile {
         Self {
             inner: Arc::new(Mutex::new(EpollFileInner {
                 monitor_list: BTreeMap::new(),
-                _response_list: BTreeSet::new(),
             })),
+            shared: Arc::new(EpollShared {
+                ready_list: Mutex::new(BTreeSet::new()),
+                waiter: PollWaiter::new(false),
+                notifier: PollNotifier::new(),
+            }),
         }
     }
 
@@ -88,6 +158,7 @@ impl EpollFile {
     pub fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            shared: self.shared.clone(),
         }
     }
 
@@ -102,13 +173,23 @@ impl EpollFile {
 
     /// 控制指定的事件，改变其对应的事件内容
     ///
+    /// `file` 为 `fd` 当前对应的文件，添加时会在其上登记唤醒者
+    ///
     /// 成功返回0，错误返回对应的编号
     pub fn epoll_ctl(
         &self,
         op: EpollCtl,
         fd: i32,
         event: EpollEvent,
+        file: &Arc<dyn FileIO>,
     ) -> Result<isize, SyscallError> {
+        // 不能监控自身
+        if let Some(epoll_file) = file.as_any().downcast_ref::<EpollFile>() {
+            if Arc::ptr_eq(&epoll_file.inner, &self.inner) {
+                return Err(SyscallError::EINVAL);
+            }
+        }
+        let exclusive = event.event_type.contains(EpollEventType::EPOLLEXCLUSIVE);
         let mut inner = self.inner.lock();
         match op {
             // 添加事件
@@ -116,97 +197,169 @@ impl EpollFile {
                 if inner.monitor_list.contains_key(&fd) {
                     return Err(SyscallError::EEXIST);
                 }
-                inner.monitor_list.insert(fd, event);
+                // EPOLLEXCLUSIVE 不能与 EPOLLONESHOT 同时使用
+                if exclusive && event.event_type.contains(EpollEventType::EPOLLONESHOT) {
+                    return Err(SyscallError::EINVAL);
+                }
+                let waker: Arc<dyn PollWaker> = Arc::new(EpollItemWaker {
+                    shared: Arc::downgrade(&self.shared),
+                    fd,
+                    exclusive,
+                });
+                if let Some(notifier) = file.poll_notifier() {
+                    notifier.register(&waker);
+                }
+                inner.monitor_list.insert(
+                    fd,
+                    EpollItem {
+                        event,
+                        file: Arc::downgrade(file),
+                        waker,
+                        recheck: file.poll_notifier().is_none(),
+                        disabled: false,
+                    },
+                );
             }
             // 删除事件
             EpollCtl::DEL => {
-                if !inner.monitor_list.contains_key(&fd) {
-                    return Err(SyscallError::ENOENT);
+                let item = match inner.monitor_list.remove(&fd) {
+                    Some(item) => item,
+                    None => return Err(SyscallError::ENOENT),
+                };
+                if let Some(file) = item.file.upgrade() {
+                    if let Some(notifier) = file.poll_notifier() {
+                        notifier.unregister(&item.waker);
+                    }
                 }
-                inner.monitor_list.remove(&fd);
+                self.shared.ready_list.lock().remove(&fd);
+                return Ok(0);
             }
             // 修改对应事件
             EpollCtl::MOD => {
                 // 对于不存在的事件，返回错误
                 // 即modify要求原先文件存在对应事件，才能进行“修改”
-                if !inner.monitor_list.contains_key(&fd) {
-                    return Err(SyscallError::ENOENT);
+                let item = match inner.monitor_list.get_mut(&fd) {
+                    Some(item) => item,
+                    None => return Err(SyscallError::ENOENT),
+                };
+                // EPOLLEXCLUSIVE 只能在添加时指定，且之后不能再被修改
+                if exclusive
+                    || item
+                        .event
+                        .event_type
+                        .contains(EpollEventType::EPOLLEXCLUSIVE)
+                {
+                    return Err(SyscallError::EINVAL);
                 }
-                inner.monitor_list.insert(fd, event);
+                item.event = event;
+                item.disabled = false;
             }
         }
+        drop(inner);
+        // 新的事件可能已经就绪，让正在等待的任务重新检查
+        self.shared.ready_list.lock().insert(fd);
+        self.shared.waiter.wake();
+        self.shared.notifier.notify();
         Ok(0)
     }
 
     /// 获取list中所有的epoll事件
     pub fn get_events(&self) -> Vec<EpollEvent> {
-        let mut ans = Vec::new();
-        for (fd, event) in self.inner.lock().monitor_list.iter() {
-            let mut nevent = *event;
-            if *fd as u64 != nevent.data {
-                nevent.data = *fd as u64;
+        self.inner
+            .lock()
+            .monitor_list
+            .values()
+            .map(|item| item.event)
+            .collect()
+    }
+
+    /// 本轮需要检查的fd：被唤醒过的，以及不会主动通知的
+    fn candidates(&self, inner: &EpollFileInner, take: bool) -> BTreeSet<i32> {
+        let mut ready_list = self.shared.ready_list.lock();
+        let mut candidates = if take {
+            core::mem::take(&mut *ready_list)
+        } else {
+            ready_list.clone()
+        };
+        drop(ready_list);
+        for (fd, item) in inner.monitor_list.iter() {
+            if item.recheck {
+                candidates.insert(*fd);
             }
-            ans.push(nevent);
         }
-        ans
+        candidates
     }
 
-    /// 实现epoll wait，在规定超时时间内收集达到触发条件的事件
-    ///
-    /// 实现原理和ppoll很像
-    pub fn epoll_wait(&self, expire_time: usize) -> AxResult<Vec<EpollEvent>> {
-        let events = self.get_events();
+    /// 收集至多 `max_event` 个已经就绪的事件，并根据触发方式更新就绪候选集合
+    fn collect_events(&self, max_event: usize) -> Vec<EpollEvent> {
+        let mut inner = self.inner.lock();
         let mut ret_events = Vec::new();
-        loop {
-            let current_process = current_process();
-            let fd_table = current_process.fd_manager.fd_table.lock();
-            for req_event in events.iter() {
-                if let Some(file) = &fd_table[req_event.data as usize] {
-                    let mut ret_event_type = EpollEventType::empty();
-                    if file.is_hang_up() {
-                        ret_event_type |= EpollEventType::EPOLLHUP;
-                    }
-                    if file.in_exceptional_conditions() {
-                        ret_event_type |= EpollEventType::EPOLLERR;
-                    }
-                    if file.ready_to_read()
-                        && req_event.event_type.contains(EpollEventType::EPOLLIN)
-                    {
-                        ret_event_type |= EpollEventType::EPOLLIN;
-                    }
-                    if file.ready_to_write()
-                        && req_event.event_type.contains(EpollEventType::EPOLLOUT)
-                    {
-                        ret_event_type |= EpollEventType::EPOLLOUT;
-                    }
-                    if !ret_event_type.is_empty() {
-                        let mut ret_event = *req_event;
-                        ret_event.event_type = ret_event_type;
-                        ret_events.push(ret_event);
-                    }
-                    // 若文件存在但未响应，此时不加入到ret中，并以此作为是否终止的条件
-                } else {
-                    // 若文件不存在，认为不存在也是一种响应，所以要加入到ret中，并以此作为是否终止的条件
-                    ret_events.push(EpollEvent {
-                        event_type: EpollEventType::EPOLLERR,
-                        data: req_event.data,
-                    });
+        let mut keep = BTreeSet::new();
+        let mut closed = Vec::new();
+        for fd in self.candidates(&inner, true) {
+            let item = match inner.monitor_list.get_mut(&fd) {
+                Some(item) if !item.disabled => item,
+                _ => continue,
+            };
+            let file = match item.file.upgrade() {
+                Some(file) => file,
+                None => {
+                    closed.push(fd);
+                    continue;
                 }
+            };
+            let ret_event_type = poll_file(file.as_ref(), item.event.event_type);
+            if ret_event_type.is_empty() {
+                // 未就绪，等待下一次通知
+                continue;
             }
+            if ret_events.len() >= max_event {
+                keep.insert(fd);
+                continue;
+            }
+            ret_events.push(EpollEvent {
+                event_type: ret_event_type,
+                data: item.event.data,
+            });
+            if item.event.event_type.contains(EpollEventType::EPOLLONESHOT) {
+                item.disabled = true;
+            } else if !item.event.event_type.contains(EpollEventType::EPOLLET) {
+                // 水平触发：只要仍然就绪，下一次 epoll_wait 还会报告
+                keep.insert(fd);
+            }
+        }
+        for fd in closed {
+            inner.monitor_list.remove(&fd);
+        }
+        self.shared.ready_list.lock().extend(keep);
+        ret_events
+    }
+
+    /// 实现epoll wait，在规定超时时间内收集达到触发条件的事件
+    ///
+    /// 没有就绪事件时阻塞，直到被监控的文件通知状态变化、超时或者收到信号
+    pub fn epoll_wait(&self, expire_time: usize, max_event: usize) -> AxResult<Vec<EpollEvent>> {
+        loop {
+            self.shared.waiter.prepare();
+            let ret_events = self.collect_events(max_event);
             if !ret_events.is_empty() {
                 // 此时收到了响应，直接返回
                 return Ok(ret_events);
             }
-            // 否则直接block
-            if current_ticks() as usize > expire_time {
+            if current_ticks() as usize >= expire_time {
                 return Ok(ret_events);
             }
-            yield_now_task();
-
             #[cfg(feature = "signal")]
-            if current_process.have_signals().is_some() {
-                return Err(AxError::Timeout);
+            if current_process().have_signals().is_some() {
+                return Err(AxError::Interrupted);
             }
+            let recheck = self
+                .inner
+                .lock()
+                .monitor_list
+                .values()
+                .any(|item| item.recheck && !item.disabled);
+            self.shared.waiter.wait(expire_time, recheck);
         }
     }
 }
@@ -240,30 +393,17 @@ impl FileIO for EpollFile {
     }
     fn ready_to_read(&self) -> bool {
         // 如果当前epoll事件确实正在等待事件响应，那么可以认为事件准备好read，尽管无法读到实际内容
-        let events = self.get_events();
-        let process = current_process();
-        let fd_table = process.fd_manager.fd_table.lock();
-        for req_event in events.iter() {
-            if let Some(file) = fd_table[req_event.data as usize].as_ref() {
-                let mut ret_event_type = EpollEventType::empty();
-                if file.is_hang_up() {
-                    ret_event_type |= EpollEventType::EPOLLHUP;
-                }
-                if file.in_exceptional_conditions() {
-                    ret_event_type |= EpollEventType::EPOLLERR;
-                }
-                if file.ready_to_read() && req_event.event_type.contains(EpollEventType::EPOLLIN) {
-                    ret_event_type |= EpollEventType::EPOLLIN;
-                }
-                if file.ready_to_write() && req_event.event_type.contains(EpollEventType::EPOLLOUT)
-                {
-                    ret_event_type |= EpollEventType::EPOLLOUT;
-                }
-                if !ret_event_type.is_empty() {
-                    return true;
-                }
-            }
-        }
-        false
+        let inner = self.inner.lock();
+        self.candidates(&inner, false).iter().any(|fd| {
+            inner.monitor_list.get(fd).map_or(false, |item| {
+                !item.disabled
+                    && item.file.upgrade().map_or(false, |file| {
+                        !poll_file(file.as_ref(), item.event.event_type).is_empty()
+                    })
+            })
+        })
+    }
+    fn poll_notifier(&self) -> Option<&PollNotifier> {
+        Some(&self.shared.notifier)
     }
 }
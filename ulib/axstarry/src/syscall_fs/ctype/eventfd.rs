@@ -1,6 +1,6 @@
 use alloc::sync::Arc;
 use axerrno::{AxError, AxResult};
-use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axfs::api::{FileIO, FileIOType, OpenFlags, PollNotifier};
 use axsync::Mutex;
 use axtask::yield_now;
 use bitflags::bitflags;
@@ -19,6 +19,7 @@ bitflags! {
 pub struct EventFd {
     value: Arc<Mutex<u64>>,
     flags: u32,
+    notifier: PollNotifier,
 }
 
 impl EventFd {
@@ -26,6 +27,7 @@ impl EventFd {
         EventFd {
             value: Arc::new(Mutex::new(initval)),
             flags,
+            notifier: PollNotifier::new(),
         }
     }
 
@@ -52,6 +54,8 @@ impl FileIO for EventFd {
             if !self.has_semaphore_set() && *value_guard != 0 {
                 buf[0..len].copy_from_slice(&value_guard.to_ne_bytes());
                 *value_guard = 0;
+                drop(value_guard);
+                self.notifier.notify();
                 return Ok(len);
             }
 
@@ -61,6 +65,8 @@ impl FileIO for EventFd {
                 let result: u64 = 1;
                 buf[0..len].copy_from_slice(&result.to_ne_bytes());
                 let _ = value_guard.checked_add_signed(-1);
+                drop(value_guard);
+                self.notifier.notify();
                 return Ok(len);
             }
 
@@ -98,6 +104,8 @@ impl FileIO for EventFd {
                 // no overflow
                 Some(_) => {
                     *value_guard += val;
+                    drop(value_guard);
+                    self.notifier.notify();
                     return Ok(len);
                 }
                 // overflow
@@ -150,6 +158,10 @@ impl FileIO for EventFd {
 
         status
     }
+
+    fn poll_notifier(&self) -> Option<&PollNotifier> {
+        Some(&self.notifier)
+    }
 }
 
 #[cfg(test)]
@@ -6,11 +6,14 @@ use alloc::sync::Arc;
 use alloc::vec;
 use alloc::vec::Vec;
 use axerrno::AxResult;
-use axfs::api::{File, FileIO, FileIOType, Kstat, OpenFlags, Read, Seek, SeekFrom, Write};
+use axfs::api::{
+    release_owner_locks, File, FileIO, FileIOType, Kstat, LockOwner, OpenFlags, Read, Seek,
+    SeekFrom, Write,
+};
 
 use axlog::debug;
 
-use crate::{new_file, normal_file_mode, StMode, TimeSecs};
+use crate::{new_file, stat_from_attr, TimeSecs};
 use axprocess::link::get_link_count;
 use axsync::Mutex;
 
@@ -52,13 +55,18 @@ impl FileIO for FileDesc {
         // 如果seek时超出了文件原有大小，则在write的时候进行补零操作
         let mut file = self.file.lock();
         let old_offset = file.seek(SeekFrom::Current(0)).unwrap();
+        // 超出 RLIMIT_FSIZE 的部分不写入
+        let len = axprocess::rlimit::limit_file_write(old_offset, buf.len())?;
         let size = file.metadata().unwrap().size();
-        if old_offset > size {
+        // 能够直接扩展文件的文件系统（如 tmpfs）会留下空洞，不必补零
+        if old_offset > size
+            && (file.set_len(old_offset).is_err() || file.metadata()?.size() != old_offset)
+        {
             file.seek(SeekFrom::Start(size)).unwrap();
             let temp_buf: Vec<u8> = vec![0u8; (old_offset - size) as usize];
             file.write(&temp_buf)?;
         }
-        file.write(buf)
+        file.write(&buf[..len])
     }
 
     fn flush(&self) -> AxResult {
@@ -87,7 +95,11 @@ impl FileIO for FileDesc {
     }
 
     fn truncate(&self, len: usize) -> AxResult<()> {
-        self.file.lock().truncate(len)
+        let mut file = self.file.lock();
+        if len as u64 > file.metadata()?.size() {
+            axprocess::rlimit::check_file_size(len as u64)?;
+        }
+        file.truncate(len)
     }
 
     fn get_stat(&self) -> AxResult<Kstat> {
@@ -95,7 +107,10 @@ impl FileIO for FileDesc {
         let attr = file.get_attr()?;
         let stat = self.stat.lock();
         let inode_map = INODE_NAME_MAP.lock();
-        let inode_number = if let Some(inode_number) = inode_map.get(&self.path) {
+        // 文件系统没有给出 inode 号时，使用按路径分配的 inode 号
+        let inode_number = if attr.ino() != 0 {
+            attr.ino()
+        } else if let Some(inode_number) = inode_map.get(&self.path) {
             *inode_number
         } else {
             // return Err(axerrno::AxError::NotFound);
@@ -108,26 +123,25 @@ impl FileIO for FileDesc {
             drop(inode_map);
             number
         };
-        let kstat = Kstat {
+        let mut kstat = Kstat {
             st_dev: 1,
             st_ino: inode_number,
-            st_mode: normal_file_mode(StMode::S_IFREG).bits() | 0o644,
-            st_nlink: get_link_count(&(self.path.as_str().to_string())) as _,
-            st_uid: 0,
-            st_gid: 0,
-            st_rdev: 0,
-            _pad0: 0,
-            st_size: attr.size(),
-            st_blksize: axfs::BLOCK_SIZE as u32,
-            _pad1: 0,
-            st_blocks: attr.blocks(),
-            st_atime_sec: stat.atime.tv_sec as isize,
-            st_atime_nsec: stat.atime.tv_nsec as isize,
-            st_mtime_sec: stat.mtime.tv_sec as isize,
-            st_mtime_nsec: stat.mtime.tv_nsec as isize,
-            st_ctime_sec: stat.ctime.tv_sec as isize,
-            st_ctime_nsec: stat.ctime.tv_nsec as isize,
+            ..stat_from_attr(&attr)
         };
+        // 模拟的硬链接不在文件系统中，链接数以记录为准
+        let link_count = get_link_count(&self.path);
+        if link_count > 0 {
+            kstat.st_nlink = link_count as _;
+        }
+        // 文件系统不记录时间戳时，使用 utimensat 设置在文件描述符上的时间
+        if attr.mtime().is_zero() {
+            kstat.st_atime_sec = stat.atime.tv_sec as isize;
+            kstat.st_atime_nsec = stat.atime.tv_nsec as isize;
+            kstat.st_mtime_sec = stat.mtime.tv_sec as isize;
+            kstat.st_mtime_nsec = stat.mtime.tv_nsec as isize;
+            kstat.st_ctime_sec = stat.ctime.tv_sec as isize;
+            kstat.st_ctime_nsec = stat.ctime.tv_nsec as isize;
+        }
         Ok(kstat)
     }
 
@@ -193,6 +207,50 @@ impl FileDesc {
             }),
         }
     }
+
+    /// 为文件的一段范围分配空间或打洞，对应 fallocate
+    /// # Arguments
+    /// * `offset`: 范围的起始位置
+    /// * `len`: 范围的长度
+    /// * `keep_size`: 分配空间时是否保持文件大小不变
+    /// * `punch_hole`: 是否释放该范围的空间
+    pub fn allocate(&self, offset: u64, len: u64, keep_size: bool, punch_hole: bool) -> AxResult {
+        let file = self.file.lock();
+        if punch_hole {
+            return file.punch_hole(offset, len);
+        }
+        if !keep_size && offset.saturating_add(len) > file.metadata()?.size() {
+            axprocess::rlimit::check_file_size(offset.saturating_add(len))?;
+        }
+        file.allocate(offset, len, keep_size)
+    }
+
+    /// 将文件指针移动到 `offset` 之后的第一段数据处，`hole` 为真时移动到第一个空洞处
+    ///
+    /// 返回新的文件指针，之后没有数据时返回 None
+    pub fn seek_data(&self, offset: u64, hole: bool) -> AxResult<Option<u64>> {
+        self.file.lock().seek_data(offset, hole)
+    }
+
+    /// 打开的文件的标识，作为 OFD 锁与 flock 锁的持有者
+    pub fn lock_id(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// 文件所在节点的标识，文件锁按节点区分文件
+    pub fn node_id(&self) -> usize {
+        self.file.lock().node_id()
+    }
+}
+
+impl Drop for FileDesc {
+    fn drop(&mut self) {
+        // 打开的文件的最后一个引用被关闭时，释放其持有的 OFD 锁与 flock 锁
+        release_owner_locks(LockOwner::Ofd(self.lock_id()));
+        release_owner_locks(LockOwner::Flock(self.lock_id()));
+        // 同时向 inotify 报告文件被关闭，dup 得到的描述符共享同一个 FileDesc，只报告一次
+        self.file.lock().notify_close();
+    }
 }
 
 /// 新建一个文件描述符
@@ -0,0 +1,458 @@
+//! inotify：监视文件系统的变化
+//!
+//! 文件系统的操作通过 [`FsWatcher`] 报告给每个 inotify 实例，实例按照各个监视项的路径与掩码
+//! 筛选事件并放入队列，读取时以 `struct inotify_event` 的格式返回。
+//! 监视项按照路径而非 inode 匹配，因此相对于目录 fd 进行的操作不会产生事件。
+//! 阻塞的读取登记在实例的 [`PollNotifier`] 上睡眠，事件入队时被唤醒。
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, FsEvent, FsWatcher, OpenFlags, PollNotifier, PollWaker};
+use axprocess::tty::termios::FIONREAD;
+use axsync::Mutex;
+use bitflags::bitflags;
+
+use super::poll_waiter::PollWaiter;
+
+bitflags! {
+    /// 监视项的掩码与事件的类型，见 <https://man7.org/linux/man-pages/man7/inotify.7.html>
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct InotifyMask: u32 {
+        /// 文件被访问
+        const IN_ACCESS = 0x1;
+        /// 文件被修改
+        const IN_MODIFY = 0x2;
+        /// 元数据被修改
+        const IN_ATTRIB = 0x4;
+        /// 以可写方式打开的文件被关闭
+        const IN_CLOSE_WRITE = 0x8;
+        /// 以只读方式打开的文件被关闭
+        const IN_CLOSE_NOWRITE = 0x10;
+        /// 文件被打开
+        const IN_OPEN = 0x20;
+        /// 文件被移出监视的目录
+        const IN_MOVED_FROM = 0x40;
+        /// 文件被移入监视的目录
+        const IN_MOVED_TO = 0x80;
+        /// 在监视的目录下创建了文件
+        const IN_CREATE = 0x100;
+        /// 监视的目录下的文件被删除
+        const IN_DELETE = 0x200;
+        /// 监视的文件本身被删除
+        const IN_DELETE_SELF = 0x400;
+        /// 监视的文件本身被移动
+        const IN_MOVE_SELF = 0x800;
+        /// 文件系统被卸载
+        const IN_UNMOUNT = 0x2000;
+        /// 事件队列溢出
+        const IN_Q_OVERFLOW = 0x4000;
+        /// 监视项被移除
+        const IN_IGNORED = 0x8000;
+        /// 只监视目录
+        const IN_ONLYDIR = 0x100_0000;
+        /// 不跟随末尾的符号链接
+        const IN_DONT_FOLLOW = 0x200_0000;
+        /// 文件被删除后不再报告其事件
+        const IN_EXCL_UNLINK = 0x400_0000;
+        /// 只在监视项不存在时创建，否则返回 EEXIST
+        const IN_MASK_CREATE = 0x1000_0000;
+        /// 将掩码添加到已有的监视项中，而非替换
+        const IN_MASK_ADD = 0x2000_0000;
+        /// 事件的主体是目录
+        const IN_ISDIR = 0x4000_0000;
+        /// 报告一个事件后移除监视项
+        const IN_ONESHOT = 0x8000_0000;
+    }
+}
+
+impl InotifyMask {
+    /// 可以被监视的事件
+    pub const ALL_EVENTS: Self = Self::from_bits_truncate(0xfff);
+}
+
+bitflags! {
+    /// `inotify_init1` 的参数
+    #[derive(Clone, Copy, Debug)]
+    pub struct InotifyFlags: u32 {
+        const IN_NONBLOCK = 0x800;
+        const IN_CLOEXEC = 0x80000;
+    }
+}
+
+/// 队列中最多保存的事件数，对应 `/proc/sys/fs/inotify/max_queued_events`
+pub const MAX_QUEUED_EVENTS: usize = 16384;
+
+/// `struct inotify_event` 中定长部分的大小
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// 队列中的一个事件
+#[derive(PartialEq, Eq)]
+struct InotifyEvent {
+    wd: i32,
+    mask: InotifyMask,
+    cookie: u32,
+    /// 事件发生在监视的目录下时为文件名，否则为空
+    name: String,
+}
+
+impl InotifyEvent {
+    /// 名字部分的长度，包含结尾的 `\0`，并补齐到 `struct inotify_event` 的大小的整数倍
+    fn name_len(&self) -> usize {
+        if self.name.is_empty() {
+            0
+        } else {
+            (self.name.len() + 1).next_multiple_of(EVENT_HEADER_SIZE)
+        }
+    }
+
+    fn size(&self) -> usize {
+        EVENT_HEADER_SIZE + self.name_len()
+    }
+
+    /// 以 `struct inotify_event` 的格式写入 `buf`，`buf` 的长度需为 `self.size()`
+    fn write_to(&self, buf: &mut [u8]) {
+        buf[0..4].copy_from_slice(&self.wd.to_ne_bytes());
+        buf[4..8].copy_from_slice(&self.mask.bits().to_ne_bytes());
+        buf[8..12].copy_from_slice(&self.cookie.to_ne_bytes());
+        buf[12..16].copy_from_slice(&(self.name_len() as u32).to_ne_bytes());
+        let name = &mut buf[EVENT_HEADER_SIZE..];
+        name[..self.name.len()].copy_from_slice(self.name.as_bytes());
+        name[self.name.len()..].fill(0);
+    }
+}
+
+/// 一个监视项
+struct Watch {
+    /// 被监视文件的绝对路径
+    path: String,
+    mask: InotifyMask,
+}
+
+struct InotifyInner {
+    watches: BTreeMap<i32, Watch>,
+    next_wd: i32,
+    events: VecDeque<InotifyEvent>,
+    /// 正在进行的重命名的 cookie 与原路径，用于在 `MOVED_TO` 到来时更新监视项的路径
+    moving: Option<(u32, String)>,
+}
+
+impl InotifyInner {
+    /// 将事件放入队列，返回队列是否发生了变化
+    fn push(&mut self, event: InotifyEvent) -> bool {
+        // 与队尾相同的事件合并为一个
+        if self.events.back() == Some(&event) {
+            return false;
+        }
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            if self
+                .events
+                .back()
+                .is_some_and(|e| e.mask == InotifyMask::IN_Q_OVERFLOW)
+            {
+                return false;
+            }
+            // 溢出时丢弃事件，并在队尾放入一个 IN_Q_OVERFLOW
+            self.events.push_back(InotifyEvent {
+                wd: -1,
+                mask: InotifyMask::IN_Q_OVERFLOW,
+                cookie: 0,
+                name: String::new(),
+            });
+            return true;
+        }
+        self.events.push_back(event);
+        true
+    }
+
+    /// 移除监视项并报告 IN_IGNORED
+    fn remove_watch(&mut self, wd: i32) -> bool {
+        if self.watches.remove(&wd).is_none() {
+            return false;
+        }
+        self.push(InotifyEvent {
+            wd,
+            mask: InotifyMask::IN_IGNORED,
+            cookie: 0,
+            name: String::new(),
+        });
+        true
+    }
+
+    /// 将路径位于 `old` 下的监视项移动到 `new` 下
+    fn move_watches(&mut self, old: &str, new: &str) {
+        for watch in self.watches.values_mut() {
+            if watch.path == old {
+                watch.path = new.to_string();
+            } else if let Some(rest) = watch.path.strip_prefix(old) {
+                if rest.starts_with('/') {
+                    watch.path = new.to_string() + rest;
+                }
+            }
+        }
+    }
+}
+
+/// inotify 实例对应的文件
+pub struct InotifyFile {
+    inner: Mutex<InotifyInner>,
+    flags: Mutex<OpenFlags>,
+    notifier: PollNotifier,
+}
+
+/// 去掉路径末尾的 `/`，使同一个文件的路径只有一种写法
+fn trim_path(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+impl InotifyFile {
+    pub fn new(flags: InotifyFlags) -> Self {
+        let mut status = OpenFlags::RDONLY;
+        status.set(
+            OpenFlags::NON_BLOCK,
+            flags.contains(InotifyFlags::IN_NONBLOCK),
+        );
+        status.set(OpenFlags::CLOEXEC, flags.contains(InotifyFlags::IN_CLOEXEC));
+        Self {
+            inner: Mutex::new(InotifyInner {
+                watches: BTreeMap::new(),
+                next_wd: 1,
+                events: VecDeque::new(),
+                moving: None,
+            }),
+            flags: Mutex::new(status),
+            notifier: PollNotifier::new(),
+        }
+    }
+
+    /// 监视绝对路径为 `path` 的文件，返回监视项的编号
+    ///
+    /// 若该文件已被监视，则修改原有监视项的掩码
+    pub fn add_watch(&self, path: &str, mask: InotifyMask) -> AxResult<i32> {
+        let path = trim_path(path);
+        let new_mask = mask & (InotifyMask::ALL_EVENTS | InotifyMask::IN_ONESHOT);
+        let mut inner = self.inner.lock();
+        if let Some((&wd, watch)) = inner.watches.iter_mut().find(|(_, w)| w.path == path) {
+            if mask.contains(InotifyMask::IN_MASK_CREATE) {
+                return Err(AxError::AlreadyExists);
+            }
+            if mask.contains(InotifyMask::IN_MASK_ADD) {
+                watch.mask |= new_mask;
+            } else {
+                watch.mask = new_mask;
+            }
+            return Ok(wd);
+        }
+        let wd = inner.next_wd;
+        inner.next_wd += 1;
+        inner.watches.insert(
+            wd,
+            Watch {
+                path: path.to_string(),
+                mask: new_mask,
+            },
+        );
+        Ok(wd)
+    }
+
+    /// 移除监视项，监视项不存在时返回 `InvalidInput`
+    pub fn rm_watch(&self, wd: i32) -> AxResult {
+        if !self.inner.lock().remove_watch(wd) {
+            return Err(AxError::InvalidInput);
+        }
+        self.notifier.notify();
+        Ok(())
+    }
+
+    /// 从队列中取出能放入 `buf` 的事件，队列为空时在 `waiter` 上等待
+    fn read_events(&self, buf: &mut [u8], waiter: &PollWaiter) -> AxResult<usize> {
+        loop {
+            // 先清除通知再检查队列，检查之后入队的事件会使等待立即返回
+            waiter.prepare();
+            let mut inner = self.inner.lock();
+            if let Some(first) = inner.events.front() {
+                // 缓冲区至少要能容纳一个完整的事件
+                if first.size() > buf.len() {
+                    return Err(AxError::InvalidInput);
+                }
+                let mut len = 0;
+                while let Some(event) = inner.events.front() {
+                    let size = event.size();
+                    if len + size > buf.len() {
+                        break;
+                    }
+                    event.write_to(&mut buf[len..len + size]);
+                    len += size;
+                    inner.events.pop_front();
+                }
+                return Ok(len);
+            }
+            drop(inner);
+            if self.is_nonblocking() {
+                return Err(AxError::WouldBlock);
+            }
+            #[cfg(feature = "signal")]
+            if axprocess::current_process().have_signals().is_some() {
+                return Err(AxError::Interrupted);
+            }
+            waiter.wait(usize::MAX, false);
+        }
+    }
+
+    fn is_nonblocking(&self) -> bool {
+        self.flags.lock().contains(OpenFlags::NON_BLOCK)
+    }
+}
+
+impl FsWatcher for InotifyFile {
+    fn handle(&self, path: &str, event: FsEvent, cookie: u32) {
+        let path = trim_path(path);
+        // 根目录没有父目录，其事件只会报告给对它本身的监视
+        let (parent, name) = match path.rfind('/') {
+            Some(pos) if path != "/" => (&path[..pos.max(1)], &path[pos + 1..]),
+            _ => ("", ""),
+        };
+        let mask = InotifyMask::from_bits_truncate(event.bits());
+        let isdir = mask & InotifyMask::IN_ISDIR;
+        let mut inner = self.inner.lock();
+        let mut events = Vec::new();
+        let mut removed = Vec::new();
+        for (&wd, watch) in inner.watches.iter() {
+            let reported = if watch.path == path {
+                // 针对被监视的文件本身的事件
+                let mask = if mask.contains(InotifyMask::IN_DELETE) {
+                    removed.push(wd);
+                    InotifyMask::IN_DELETE_SELF
+                } else if mask.contains(InotifyMask::IN_MOVED_FROM) {
+                    InotifyMask::IN_MOVE_SELF
+                } else if mask.intersects(InotifyMask::IN_CREATE | InotifyMask::IN_MOVED_TO) {
+                    InotifyMask::empty()
+                } else {
+                    mask & !InotifyMask::IN_ISDIR
+                };
+                mask & watch.mask
+            } else if watch.path == parent {
+                // 被监视的目录下的文件的事件
+                mask & watch.mask
+            } else {
+                continue;
+            };
+            if reported.is_empty() {
+                continue;
+            }
+            let name = if watch.path == path { "" } else { name };
+            events.push(InotifyEvent {
+                wd,
+                mask: reported | isdir,
+                cookie: if name.is_empty() { 0 } else { cookie },
+                name: name.to_string(),
+            });
+            if watch.mask.contains(InotifyMask::IN_ONESHOT) {
+                removed.push(wd);
+            }
+        }
+        let mut changed = false;
+        for event in events {
+            changed |= inner.push(event);
+        }
+        for wd in removed {
+            changed |= inner.remove_watch(wd);
+        }
+        // 重命名完成后，监视项跟随文件移动到新的路径
+        if mask.contains(InotifyMask::IN_MOVED_FROM) {
+            inner.moving = Some((cookie, path.to_string()));
+        } else if mask.contains(InotifyMask::IN_MOVED_TO) {
+            if let Some((moving, old)) = inner.moving.take() {
+                if moving == cookie {
+                    inner.move_watches(&old, path);
+                }
+            }
+        }
+        drop(inner);
+        if changed {
+            self.notifier.notify();
+        }
+    }
+}
+
+impl FileIO for InotifyFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let waiter = Arc::new(PollWaiter::new(false));
+        let waker: Arc<dyn PollWaker> = waiter.clone();
+        self.notifier.register(&waker);
+        let result = self.read_events(buf, &waiter);
+        self.notifier.unregister(&waker);
+        result
+    }
+
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        String::from("anon_inode:inotify")
+    }
+
+    fn ready_to_read(&self) -> bool {
+        !self.inner.lock().events.is_empty()
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        *self.flags.lock()
+    }
+
+    fn set_status(&self, flags: OpenFlags) -> bool {
+        let mut now = self.flags.lock();
+        now.set(OpenFlags::NON_BLOCK, flags.contains(OpenFlags::NON_BLOCK));
+        true
+    }
+
+    fn set_close_on_exec(&self, is_set: bool) -> bool {
+        self.flags.lock().set(OpenFlags::CLOEXEC, is_set);
+        true
+    }
+
+    fn ioctl(&self, request: usize, arg1: usize) -> AxResult<()> {
+        if request != FIONREAD {
+            return Err(AxError::Unsupported);
+        }
+        // 返回队列中所有事件的总字节数
+        let len: usize = self.inner.lock().events.iter().map(|e| e.size()).sum();
+        let ptr = arg1 as *mut i32;
+        if ptr.is_null()
+            || axprocess::current_process()
+                .manual_alloc_type_for_lazy(ptr as *const i32)
+                .is_err()
+        {
+            return Err(AxError::BadAddress);
+        }
+        unsafe {
+            *ptr = len as i32;
+        }
+        Ok(())
+    }
+
+    fn poll_notifier(&self) -> Option<&PollNotifier> {
+        Some(&self.notifier)
+    }
+}
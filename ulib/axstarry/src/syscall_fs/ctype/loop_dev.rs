@@ -0,0 +1,190 @@
+//! 回环设备 `/dev/loopN` 与 `/dev/loop-control` 的 ioctl
+//!
+//! 回环设备把一个普通文件当作块设备，绑定磁盘镜像后即可通过 mount 挂载
+extern crate alloc;
+use alloc::sync::Arc;
+use axfs::api::{
+    add_loop_device, free_loop_device, is_loop_control, loop_device_of, remove_loop_device, FileIO,
+    LoopDevice,
+};
+use axprocess::current_process;
+use bitflags::bitflags;
+
+use super::FileDesc;
+use crate::{SyscallError, SyscallResult};
+
+/// 绑定 arg 所指的文件描述符对应的文件
+const LOOP_SET_FD: usize = 0x4C00;
+/// 解除绑定
+const LOOP_CLR_FD: usize = 0x4C01;
+/// 设置偏移、大小上限与标志
+const LOOP_SET_STATUS64: usize = 0x4C04;
+/// 获取设备状态
+const LOOP_GET_STATUS64: usize = 0x4C05;
+/// 获取设备的字节数
+const BLKGETSIZE64: usize = 0x8008_1272;
+
+/// 创建编号为 arg 的回环设备
+const LOOP_CTL_ADD: usize = 0x4C80;
+/// 删除编号为 arg 的回环设备
+const LOOP_CTL_REMOVE: usize = 0x4C81;
+/// 获取一个空闲的回环设备，返回其编号
+const LOOP_CTL_GET_FREE: usize = 0x4C82;
+
+/// 文件名字段的长度
+const LO_NAME_SIZE: usize = 64;
+
+bitflags! {
+    /// 回环设备的标志
+    #[derive(Clone, Copy, Debug)]
+    pub struct LoopFlags: u32 {
+        /// 只读
+        const LO_FLAGS_READ_ONLY = 1;
+        /// 最后一次关闭时自动解除绑定
+        const LO_FLAGS_AUTOCLEAR = 4;
+        /// 扫描分区表
+        const LO_FLAGS_PARTSCAN = 8;
+        /// 直接读写后端文件
+        const LO_FLAGS_DIRECT_IO = 16;
+    }
+}
+
+/// 回环设备的状态，对应 `struct loop_info64`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct LoopInfo64 {
+    /// 后端文件所在的设备号
+    pub lo_device: u64,
+    /// 后端文件的 inode 号
+    pub lo_inode: u64,
+    /// 回环设备的设备号
+    pub lo_rdevice: u64,
+    /// 数据在后端文件中的偏移
+    pub lo_offset: u64,
+    /// 设备大小的上限，0 表示到文件末尾
+    pub lo_sizelimit: u64,
+    /// 设备编号
+    pub lo_number: u32,
+    /// 加密类型，不支持
+    pub lo_encrypt_type: u32,
+    /// 密钥长度，不支持
+    pub lo_encrypt_key_size: u32,
+    /// 标志
+    pub lo_flags: u32,
+    /// 后端文件名
+    pub lo_file_name: [u8; LO_NAME_SIZE],
+    /// 加密算法名，不支持
+    pub lo_crypt_name: [u8; LO_NAME_SIZE],
+    /// 密钥，不支持
+    pub lo_encrypt_key: [u8; 32],
+    /// 加密参数，不支持
+    pub lo_init: [u64; 2],
+}
+
+/// 检查用户地址处可以容纳一个 T
+fn user_ptr<T>(addr: usize) -> Result<*mut T, SyscallError> {
+    let ptr = addr as *mut T;
+    if ptr.is_null()
+        || current_process()
+            .manual_alloc_type_for_lazy(ptr as *const T)
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    Ok(ptr)
+}
+
+/// 若文件为回环设备或 `/dev/loop-control`，则处理其 ioctl 请求，否则返回 None
+pub fn loop_ioctl(file: &Arc<dyn FileIO>, request: usize, arg: usize) -> Option<SyscallResult> {
+    let desc = file.as_any().downcast_ref::<FileDesc>()?;
+    let node = axfs::api::lookup(&desc.path).ok()?;
+    if is_loop_control(&node) {
+        return Some(control_ioctl(request, arg));
+    }
+    let dev = loop_device_of(&node)?;
+    Some(device_ioctl(&dev, file.writable(), request, arg))
+}
+
+fn control_ioctl(request: usize, arg: usize) -> SyscallResult {
+    match request {
+        LOOP_CTL_GET_FREE => Ok(free_loop_device()?.index() as isize),
+        LOOP_CTL_ADD => Ok(add_loop_device(arg)?.index() as isize),
+        LOOP_CTL_REMOVE => match remove_loop_device(arg) {
+            Ok(()) => Ok(0),
+            Err(axerrno::AxError::NotFound) => Err(SyscallError::ENODEV),
+            Err(err) => Err(err.into()),
+        },
+        _ => Err(SyscallError::ENOTTY),
+    }
+}
+
+/// 处理回环设备的 ioctl，`writable` 为设备文件是否以可写方式打开
+fn device_ioctl(dev: &LoopDevice, writable: bool, request: usize, arg: usize) -> SyscallResult {
+    match request {
+        LOOP_SET_FD => {
+            let file = match current_process().fd_manager.fd_table.lock().get(arg) {
+                Some(Some(file)) => file.clone(),
+                _ => return Err(SyscallError::EBADF),
+            };
+            let desc = file
+                .as_any()
+                .downcast_ref::<FileDesc>()
+                .ok_or(SyscallError::EINVAL)?;
+            let node = axfs::api::lookup(&desc.path)?;
+            // 设备或后端文件不可写时，设备只读
+            let read_only = !writable || !file.writable();
+            dev.attach(node, &desc.path, read_only)?;
+            Ok(0)
+        }
+        LOOP_CLR_FD => dev.detach().map(|_| 0).map_err(|_| SyscallError::ENXIO),
+        LOOP_SET_STATUS64 => {
+            let info = unsafe { *user_ptr::<LoopInfo64>(arg)? };
+            let flags = LoopFlags::from_bits_truncate(info.lo_flags);
+            dev.set_status(
+                info.lo_offset,
+                info.lo_sizelimit,
+                flags.contains(LoopFlags::LO_FLAGS_AUTOCLEAR),
+            )
+            .map(|_| 0)
+            .map_err(|_| SyscallError::ENXIO)
+        }
+        LOOP_GET_STATUS64 => {
+            let ptr = user_ptr::<LoopInfo64>(arg)?;
+            let backing = dev.backing().ok_or(SyscallError::ENXIO)?;
+            let mut flags = LoopFlags::empty();
+            if backing.read_only {
+                flags |= LoopFlags::LO_FLAGS_READ_ONLY;
+            }
+            if backing.autoclear {
+                flags |= LoopFlags::LO_FLAGS_AUTOCLEAR;
+            }
+            let mut info = LoopInfo64 {
+                lo_device: 0,
+                lo_inode: 0,
+                lo_rdevice: 0,
+                lo_offset: backing.offset,
+                lo_sizelimit: backing.size_limit,
+                lo_number: dev.index() as u32,
+                lo_encrypt_type: 0,
+                lo_encrypt_key_size: 0,
+                lo_flags: flags.bits(),
+                lo_file_name: [0; LO_NAME_SIZE],
+                lo_crypt_name: [0; LO_NAME_SIZE],
+                lo_encrypt_key: [0; 32],
+                lo_init: [0; 2],
+            };
+            // 保留结尾的 0
+            let name = backing.path.as_bytes();
+            let len = name.len().min(LO_NAME_SIZE - 1);
+            info.lo_file_name[..len].copy_from_slice(&name[..len]);
+            unsafe { *ptr = info };
+            Ok(0)
+        }
+        BLKGETSIZE64 => {
+            let ptr = user_ptr::<u64>(arg)?;
+            unsafe { *ptr = dev.size() };
+            Ok(0)
+        }
+        _ => Err(SyscallError::ENOTTY),
+    }
+}
@@ -0,0 +1,97 @@
+//! `/proc/meminfo`
+//!
+//! 内存总量与空闲量来自内核的页分配器，Buffers、Dirty 与 Writeback 来自硬盘块缓存
+use alloc::format;
+use alloc::string::String;
+use axerrno::AxResult;
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axsync::Mutex;
+
+const MEMINFO_PATH: &str = "/proc/meminfo";
+
+const PAGE_SIZE: usize = 0x1000;
+
+/// `/proc/meminfo`，内容在打开时生成
+pub struct MemInfoFile {
+    content: String,
+    /// 读取的位置
+    offset: Mutex<usize>,
+    flags: OpenFlags,
+}
+
+/// 若路径为 `/proc/meminfo`，则打开之
+pub fn open_meminfo_file(path: &str, flags: OpenFlags) -> Option<AxResult<MemInfoFile>> {
+    if path != MEMINFO_PATH {
+        return None;
+    }
+    Some(Ok(MemInfoFile {
+        content: meminfo(),
+        offset: Mutex::new(0),
+        flags,
+    }))
+}
+
+/// 按照 Linux 的格式生成各项统计，单位为 kB
+fn meminfo() -> String {
+    let total = axconfig::PHYS_MEMORY_SIZE;
+    let free = axalloc::global_allocator().available_pages() * PAGE_SIZE;
+    let cache = axfs::api::block_cache_stats();
+    [
+        ("MemTotal", total),
+        ("MemFree", free),
+        ("MemAvailable", free + cache.cached - cache.dirty),
+        ("Buffers", cache.cached),
+        ("Cached", 0),
+        ("SwapCached", 0),
+        ("SwapTotal", 0),
+        ("SwapFree", 0),
+        ("Dirty", cache.dirty),
+        ("Writeback", cache.writeback),
+    ]
+    .iter()
+    .map(|(name, bytes)| format!("{:<16}{:>8} kB\n", format!("{}:", name), bytes / 1024))
+    .collect()
+}
+
+impl FileIO for MemInfoFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut offset = self.offset.lock();
+        let rest = self.content.as_bytes().get(*offset..).unwrap_or(&[]);
+        let len = rest.len().min(buf.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        *offset += len;
+        Ok(len)
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.readable()
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        String::from(MEMINFO_PATH)
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        self.flags
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+}
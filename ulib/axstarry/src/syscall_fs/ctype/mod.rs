@@ -1,13 +1,28 @@
+pub mod binfmt;
+
 pub mod dir;
 
 pub mod file;
 
+pub mod inotify;
+
+pub mod loop_dev;
+
+pub mod meminfo;
+
 pub mod mount;
 
+pub mod ns;
+
 pub mod pipe;
 
+#[cfg(feature = "signal")]
+pub mod sysctl;
+
 pub use file::FileDesc;
 
 pub mod epoll;
 
 pub mod eventfd;
+
+pub mod poll_waiter;
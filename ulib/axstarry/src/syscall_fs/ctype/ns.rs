@@ -0,0 +1,103 @@
+//! `/proc/<pid>/ns` 下的文件
+//!
+//! 每个文件指向进程所处的一个命名空间，打开后可以作为 setns 的参数。
+//! 这些文件是符号链接，readlink 得到 `<类型>:[<编号>]`，编号相同说明处于同一命名空间。
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axprocess::flags::CloneFlags;
+use axprocess::namespace::NsRef;
+use axprocess::{current_process, Process, PID2PC};
+
+/// 指向命名空间的文件
+pub struct NsFile {
+    ns: NsRef,
+    path: String,
+    flags: OpenFlags,
+}
+
+impl NsFile {
+    /// 指向的命名空间
+    pub fn ns(&self) -> &NsRef {
+        &self.ns
+    }
+}
+
+/// 解析 `/proc/<pid>/ns/<类型>`，返回对应进程所处的命名空间
+///
+/// 路径不是命名空间文件时返回 None，进程或命名空间类型不存在时返回 NotFound
+fn lookup_ns(path: &str) -> Option<AxResult<NsRef>> {
+    let (pid, name) = path.strip_prefix("/proc/")?.split_once("/ns/")?;
+    let flag = match name {
+        "pid" => CloneFlags::CLONE_NEWPID,
+        "mnt" => CloneFlags::CLONE_NEWNS,
+        "uts" => CloneFlags::CLONE_NEWUTS,
+        "net" => CloneFlags::CLONE_NEWNET,
+        _ => return Some(Err(AxError::NotFound)),
+    };
+    let process = match ns_owner(pid) {
+        Some(process) => process,
+        None => return Some(Err(AxError::NotFound)),
+    };
+    Some(process.ns_ref(flag).ok_or(AxError::NotFound))
+}
+
+/// `/proc` 下的进程目录名对应的进程，数字为当前 PID 命名空间中的进程号
+fn ns_owner(pid: &str) -> Option<Arc<Process>> {
+    let process = current_process();
+    if pid == "self" {
+        return Some(process);
+    }
+    let pid = process.pid_from_user(pid.parse().ok()?)?;
+    PID2PC.lock().get(&pid).cloned()
+}
+
+/// 若路径为命名空间文件，则打开之
+pub fn open_ns_file(path: &str, flags: OpenFlags) -> Option<AxResult<NsFile>> {
+    Some(lookup_ns(path)?.map(|ns| NsFile {
+        ns,
+        path: String::from(path),
+        flags,
+    }))
+}
+
+/// 若路径为命名空间文件，则返回其链接内容
+pub fn ns_file_link(path: &str) -> Option<AxResult<String>> {
+    Some(lookup_ns(path)?.map(|ns| format!("{}:[{}]", ns.name(), ns.id())))
+}
+
+impl FileIO for NsFile {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        self.path.clone()
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        self.flags
+    }
+
+    fn ready_to_read(&self) -> bool {
+        false
+    }
+
+    fn ready_to_write(&self) -> bool {
+        false
+    }
+}
@@ -1,4 +1,4 @@
-use axfs::api::{FileIO, FileIOType};
+use axfs::api::{FileIO, FileIOType, PollNotifier};
 extern crate alloc;
 use alloc::sync::{Arc, Weak};
 use axerrno::AxResult;
@@ -15,29 +15,48 @@ pub struct Pipe {
     writable: bool,
     buffer: Arc<Mutex<PipeRingBuffer>>,
     non_block: bool,
+    /// 读写两端共享，任一端读写或关闭都会通知等待者
+    notifier: Arc<PollNotifier>,
 }
 
 impl Pipe {
     /// create readable pipe
-    pub fn read_end_with_buffer(buffer: Arc<Mutex<PipeRingBuffer>>, non_block: bool) -> Self {
+    pub fn read_end_with_buffer(
+        buffer: Arc<Mutex<PipeRingBuffer>>,
+        notifier: Arc<PollNotifier>,
+        non_block: bool,
+    ) -> Self {
         Self {
             readable: true,
             writable: false,
             buffer,
             non_block,
+            notifier,
         }
     }
     /// create writable pipe
-    pub fn write_end_with_buffer(buffer: Arc<Mutex<PipeRingBuffer>>, non_block: bool) -> Self {
+    pub fn write_end_with_buffer(
+        buffer: Arc<Mutex<PipeRingBuffer>>,
+        notifier: Arc<PollNotifier>,
+        non_block: bool,
+    ) -> Self {
         Self {
             readable: false,
             writable: true,
             buffer,
             non_block,
+            notifier,
         }
     }
 }
 
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // 一端关闭，另一端会观察到挂断
+        self.notifier.notify();
+    }
+}
+
 const RING_BUFFER_SIZE: usize = 0x4000;
 
 #[derive(Copy, Clone, PartialEq)]
@@ -112,8 +131,17 @@ impl PipeRingBuffer {
 pub fn make_pipe(non_block: bool) -> (Arc<Pipe>, Arc<Pipe>) {
     trace!("kernel: make_pipe");
     let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
-    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone(), non_block));
-    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone(), non_block));
+    let notifier = Arc::new(PollNotifier::new());
+    let read_end = Arc::new(Pipe::read_end_with_buffer(
+        buffer.clone(),
+        notifier.clone(),
+        non_block,
+    ));
+    let write_end = Arc::new(Pipe::write_end_with_buffer(
+        buffer.clone(),
+        notifier,
+        non_block,
+    ));
     buffer.lock().set_write_end(&write_end);
     (read_end, write_end)
 }
@@ -150,13 +178,14 @@ impl FileIO for Pipe {
                     *byte_ref = ring_buffer.read_byte();
                     already_read += 1;
                     if already_read == want_to_read {
-                        return Ok(want_to_read);
+                        break;
                     }
                 } else {
                     break;
                 }
             }
-
+            drop(ring_buffer);
+            self.notifier.notify();
             return Ok(already_read);
         }
     }
@@ -187,13 +216,14 @@ impl FileIO for Pipe {
                     ring_buffer.write_byte(*byte_ref);
                     already_write += 1;
                     if already_write == want_to_write {
-                        drop(ring_buffer);
-                        return Ok(want_to_write);
+                        break;
                     }
                 } else {
                     break;
                 }
             }
+            drop(ring_buffer);
+            self.notifier.notify();
             return Ok(already_write);
         }
     }
@@ -235,4 +265,8 @@ impl FileIO for Pipe {
     fn ready_to_write(&self) -> bool {
         self.writable && self.buffer.lock().available_write() != 0
     }
+
+    fn poll_notifier(&self) -> Option<&PollNotifier> {
+        Some(&self.notifier)
+    }
 }
@@ -0,0 +1,132 @@
+//! epoll/poll/select 共用的等待机制
+//!
+//! 等待者登记在各个文件的 [`PollNotifier`] 上，文件状态变化时被唤醒后再重新检查就绪状态，
+//! 从而不必在等待期间反复让出 CPU 轮询。
+extern crate alloc;
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use axfs::api::{FileIO, PollWaker};
+use axhal::time::{current_time, ticks_to_nanos, TimeValue};
+use axtask::WaitQueue;
+
+/// 对于不会主动通知的文件（没有 [`PollNotifier`](axfs::api::PollNotifier)），等待时重新检查的间隔
+const POLL_RECHECK_INTERVAL: Duration = Duration::from_millis(1);
+
+/// 一个可以被文件就绪通知唤醒的等待者
+pub struct PollWaiter {
+    /// 自上次 [`PollWaiter::prepare`] 以来是否收到过通知
+    pending: AtomicBool,
+    wait_queue: WaitQueue,
+    exclusive: bool,
+}
+
+impl PollWaiter {
+    /// 新建一个等待者，`exclusive` 对应 EPOLLEXCLUSIVE
+    pub fn new(exclusive: bool) -> Self {
+        Self {
+            pending: AtomicBool::new(false),
+            wait_queue: WaitQueue::new(),
+            exclusive,
+        }
+    }
+
+    /// 开始新一轮检查前调用，此后收到的通知都会使下一次等待立即返回
+    pub fn prepare(&self) {
+        self.pending.store(false, Ordering::SeqCst);
+    }
+
+    /// 是否已收到通知
+    pub fn is_pending(&self) -> bool {
+        self.pending.load(Ordering::SeqCst)
+    }
+
+    /// 阻塞直到收到通知、到达超时时间或者被信号打断
+    ///
+    /// `expire_time` 为以 tick 计的绝对时间，`usize::MAX` 表示不超时；
+    /// `recheck` 为真时最多等待 [`POLL_RECHECK_INTERVAL`]
+    pub fn wait(&self, expire_time: usize, recheck: bool) {
+        let mut deadline = if expire_time == usize::MAX {
+            None
+        } else {
+            Some(TimeValue::from_nanos(ticks_to_nanos(expire_time as u64)))
+        };
+        if recheck {
+            let recheck_time = current_time() + POLL_RECHECK_INTERVAL;
+            deadline = Some(deadline.map_or(recheck_time, |d| d.min(recheck_time)));
+        }
+        self.wait_queue
+            .wait_once_unless(deadline, || self.pending.load(Ordering::SeqCst));
+    }
+}
+
+impl PollWaker for PollWaiter {
+    fn wake(&self) {
+        self.pending.store(true, Ordering::SeqCst);
+        self.wait_queue.notify_all(true);
+    }
+
+    fn exclusive(&self) -> bool {
+        self.exclusive
+    }
+}
+
+/// 一次 poll/select 调用期间在一组文件上的登记，析构时自动注销
+pub struct PollRegistration {
+    waiter: Arc<PollWaiter>,
+    waker: Arc<dyn PollWaker>,
+    files: Vec<Arc<dyn FileIO>>,
+    recheck: bool,
+}
+
+impl PollRegistration {
+    /// 新建一个空的登记
+    pub fn new() -> Self {
+        let waiter = Arc::new(PollWaiter::new(false));
+        Self {
+            waker: waiter.clone(),
+            waiter,
+            files: Vec::new(),
+            recheck: false,
+        }
+    }
+
+    /// 在文件上登记当前等待者
+    pub fn add(&mut self, file: &Arc<dyn FileIO>) {
+        if let Some(notifier) = file.poll_notifier() {
+            notifier.register(&self.waker);
+            self.files.push(Arc::clone(file));
+        } else {
+            self.recheck = true;
+        }
+    }
+
+    /// 见 [`PollWaiter::prepare`]
+    pub fn prepare(&self) {
+        self.waiter.prepare();
+    }
+
+    /// 见 [`PollWaiter::wait`]
+    pub fn wait(&self, expire_time: usize) {
+        self.waiter.wait(expire_time, self.recheck);
+    }
+}
+
+impl Default for PollRegistration {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for PollRegistration {
+    fn drop(&mut self) {
+        for file in self.files.iter() {
+            if let Some(notifier) = file.poll_notifier() {
+                notifier.unregister(&self.waker);
+            }
+        }
+    }
+}
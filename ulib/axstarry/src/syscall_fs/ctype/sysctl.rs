@@ -0,0 +1,102 @@
+//! `/proc/sys/kernel` 下的文件
+//!
+//! - `core_pattern`：读取或写入 core 文件的命名模式
+use alloc::format;
+use alloc::string::String;
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axprocess::coredump;
+use axsync::Mutex;
+
+const SYSCTL_KERNEL_DIR: &str = "/proc/sys/kernel";
+
+enum SysctlNode {
+    CorePattern,
+}
+
+/// `/proc/sys/kernel` 下的文件
+pub struct SysctlFile {
+    node: SysctlNode,
+    /// 读取的位置
+    offset: Mutex<usize>,
+    flags: OpenFlags,
+}
+
+/// 若路径位于 `/proc/sys/kernel` 下，则打开对应的文件
+///
+/// 路径不在该目录下时返回 None，对应的文件不存在时返回 NotFound
+pub fn open_sysctl_file(path: &str, flags: OpenFlags) -> Option<AxResult<SysctlFile>> {
+    let name = path.strip_prefix(SYSCTL_KERNEL_DIR)?.strip_prefix('/')?;
+    let node = match name {
+        "core_pattern" => SysctlNode::CorePattern,
+        _ => return Some(Err(AxError::NotFound)),
+    };
+    Some(Ok(SysctlFile {
+        node,
+        offset: Mutex::new(0),
+        flags,
+    }))
+}
+
+impl SysctlFile {
+    fn content(&self) -> String {
+        match self.node {
+            SysctlNode::CorePattern => format!("{}\n", coredump::core_pattern()),
+        }
+    }
+}
+
+impl FileIO for SysctlFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let content = self.content();
+        let mut offset = self.offset.lock();
+        let rest = content.as_bytes().get(*offset..).unwrap_or(&[]);
+        let len = rest.len().min(buf.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        *offset += len;
+        Ok(len)
+    }
+
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        let value = core::str::from_utf8(buf).map_err(|_| AxError::InvalidInput)?;
+        match self.node {
+            SysctlNode::CorePattern => coredump::set_core_pattern(value)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        let name = match self.node {
+            SysctlNode::CorePattern => "core_pattern",
+        };
+        format!("{}/{}", SYSCTL_KERNEL_DIR, name)
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        self.flags
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+}
@@ -22,18 +22,28 @@ pub enum FsSyscallId {
     DUP = 23,
     DUP3 = 24,
     FCNTL64 = 25,
+    INOTIFY_INIT1 = 26,
+    INOTIFY_ADD_WATCH = 27,
+    INOTIFY_RM_WATCH = 28,
     IOCTL = 29,
+    FLOCK = 32,
     MKDIRAT = 34,
     UNLINKAT = 35,
+    SYMLINKAT = 36,
     LINKAT = 37,
     RENAMEAT = 38,
     UNMOUNT = 39,
     MOUNT = 40,
     STATFS = 43,
+    FSTATFS = 44,
     FTRUNCATE64 = 46,
+    FALLOCATE = 47,
     FACCESSAT = 48,
     CHDIR = 49,
+    FCHMOD = 52,
     FCHMODAT = 53,
+    FCHOWNAT = 54,
+    FCHOWN = 55,
     OPENAT = 56,
     CLOSE = 57,
     PIPE2 = 59,
@@ -56,6 +66,7 @@ pub enum FsSyscallId {
     UTIMENSAT = 88,
     RENAMEAT2 = 276,
     COPYFILERANGE = 285,
+    STATX = 291,
 }
 }
 
@@ -80,6 +91,7 @@ numeric_enum_macro::numeric_enum! {
         DUP2 = 33,
         DUP3 = 292,
         FCNTL64 = 72,
+        FLOCK = 73,
         IOCTL = 16,
         MKDIRAT = 258,
         RENAME = 82,
@@ -87,18 +99,29 @@ numeric_enum_macro::numeric_enum! {
         RMDIR = 84,
         UNLINKAT = 263,
         LINKAT = 265,
+        SYMLINKAT = 266,
+        SYMLINK = 88,
         UNMOUNT = 166,
         MOUNT = 165,
         STATFS = 137,
+        FSTATFS = 138,
         FTRUNCATE64 = 77,
+        FALLOCATE = 285,
         FACCESSAT = 269,
         ACCESS = 21,
         CHDIR = 80,
+        FCHMOD = 91,
+        FCHOWN = 93,
         FCHMODAT = 268,
+        FCHOWNAT = 260,
         OPENAT = 257,
         CLOSE = 3,
         PIPE = 22,
         PIPE2 = 293,
+        INOTIFY_INIT = 253,
+        INOTIFY_ADD_WATCH = 254,
+        INOTIFY_RM_WATCH = 255,
+        INOTIFY_INIT1 = 294,
         GETDENTS64 = 217,
         LSEEK = 8,
         READ = 0,
@@ -124,5 +147,6 @@ numeric_enum_macro::numeric_enum! {
         RENAMEAT = 264,
         RENAMEAT2 = 316,
         COPYFILERANGE = 326,
+        STATX = 332,
     }
 }
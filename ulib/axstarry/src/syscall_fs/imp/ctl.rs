@@ -1,19 +1,25 @@
 //! 对文件系统的管理,包括目录项的创建、文件权限设置等内容
-use axfs::api::{remove_dir, remove_file, rename, OpenFlags, Permissions};
+use axerrno::{AxError, AxResult};
+use axfs::api::{remove_dir, remove_file, rename, FsEvent, OpenFlags};
 use axlog::{debug, error, info};
 use core::ptr::copy_nonoverlapping;
 
 use crate::{
-    syscall_fs::ctype::{file::new_fd, FileDesc},
-    DirEnt, DirEntType, Fcntl64Cmd, RenameFlags, SyscallError, SyscallResult, TimeSecs,
+    syscall_fs::ctype::{loop_dev::loop_ioctl, FileDesc},
+    DirEnt, DirEntType, Fcntl64Cmd, RenameFlags, SyscallError, SyscallResult, TimeSecs, UTIME_NOW,
+    UTIME_OMIT,
 };
 use axhal::mem::VirtAddr;
 use axprocess::{
+    cred::{self, Cap, Privileges},
     current_process,
     link::{deal_with_path, FilePath, AT_FDCWD},
+    tty::termios::FIONBIO,
 };
 
-use super::{syscall_unlinkat, AT_REMOVEDIR};
+use super::{
+    fcntl_lock, syscall_unlinkat, AT_EACCESS, AT_EMPTY_PATH, AT_REMOVEDIR, AT_SYMLINK_NOFOLLOW,
+};
 
 extern crate alloc;
 use alloc::string::ToString;
@@ -87,9 +93,11 @@ pub fn syscall_mkdirat(args: [usize; 6]) -> SyscallResult {
         // 文件已存在
         return Err(SyscallError::EEXIST);
     }
+    cred::check_modify_entry(path.path(), false)?;
     let _ = axfs::api::create_dir(path.path());
     // 只要文件夹存在就返回0
     if axfs::api::path_exists(path.path()) {
+        cred::file_created(path.path(), mode, true);
         Ok(0)
     } else {
         Err(SyscallError::EPERM)
@@ -123,6 +131,9 @@ pub fn syscall_chdir(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::EINVAL);
     };
     debug!("Into syscall_chdir. path: {:?}", path.path());
+    if let Err(AxError::PermissionDenied) = cred::check_access(path.path(), Cap::EXECUTE) {
+        return Err(SyscallError::EACCES);
+    }
     match axfs::api::set_current_dir(path.path()) {
         Ok(_) => Ok(0),
         Err(_) => Err(SyscallError::EINVAL),
@@ -307,6 +318,8 @@ pub fn syscall_renameat2(args: [usize; 6]) -> SyscallResult {
         // 相同文件不用改
         return Ok(0);
     }
+    cred::check_modify_entry(old_path.path(), true)?;
+    cred::check_modify_entry(new_path.path(), axfs::api::path_exists(new_path.path()))?;
     if !flags.contains(RenameFlags::EXCHANGE) {
         // 当新文件存在，先删掉新文件
         // 此时若存在新文件，默认是没有 NOREPLACE 的
@@ -328,6 +341,7 @@ pub fn syscall_renameat2(args: [usize; 6]) -> SyscallResult {
             error!("error: {:?}", err);
             return Err(SyscallError::EPERM);
         }
+        cred::rename_file_owner(old_path.path(), new_path.path());
     } else {
         // 当前不支持交换
         axlog::warn!("renameat2 exchange not implemented");
@@ -418,6 +432,18 @@ pub fn syscall_fcntl64(args: [usize; 6]) -> SyscallResult {
                 Err(SyscallError::EINVAL)
             }
         }
+        Ok(
+            cmd @ (Fcntl64Cmd::F_GETLK
+            | Fcntl64Cmd::F_SETLK
+            | Fcntl64Cmd::F_SETLKW
+            | Fcntl64Cmd::F_OFD_GETLK
+            | Fcntl64Cmd::F_OFD_SETLK
+            | Fcntl64Cmd::F_OFD_SETLKW),
+        ) => {
+            // 等待锁时不能持有文件描述符表
+            drop(fd_table);
+            fcntl_lock(&file, cmd, arg)
+        }
         _ => Err(SyscallError::EINVAL),
     }
 }
@@ -444,13 +470,35 @@ pub fn syscall_ioctl(args: [usize; 6]) -> SyscallResult {
         debug!("fd {} is none", fd);
         return Err(SyscallError::EBADF);
     }
-    if process.manual_alloc_for_lazy(argp.into()).is_err() {
-        return Err(SyscallError::EFAULT); // 地址不合法
-    }
-
     let file = fd_table[fd].clone().unwrap();
-    let _ = file.ioctl(request, argp);
-    Ok(0)
+    drop(fd_table);
+    // 回环设备的部分请求需要返回值，单独处理
+    if let Some(result) = loop_ioctl(&file, request, argp) {
+        return result;
+    }
+    // argp 可能是整数参数，其合法性由具体的实现检查
+    match file.ioctl(request, argp) {
+        Ok(()) => Ok(0),
+        // 设置非阻塞模式对所有文件都有效，未单独处理时通过文件状态设置
+        Err(AxError::Unsupported) if request == FIONBIO => {
+            if process
+                .manual_alloc_type_for_lazy(argp as *const i32)
+                .is_err()
+            {
+                return Err(SyscallError::EFAULT);
+            }
+            let mut flags = file.get_status();
+            if unsafe { *(argp as *const i32) } != 0 {
+                flags |= OpenFlags::NON_BLOCK;
+            } else {
+                flags &= !OpenFlags::NON_BLOCK;
+            }
+            file.set_status(flags);
+            Ok(0)
+        }
+        Err(AxError::Unsupported) => Err(SyscallError::ENOTTY),
+        Err(err) => Err(err.into()),
+    }
 }
 
 /// 53
@@ -469,13 +517,89 @@ pub fn syscall_fchmodat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
     let mode = args[2];
-    let file_path = deal_with_path(dir_fd, Some(path), false).unwrap();
-    axfs::api::metadata(file_path.path())
-        .map(|mut metadata| {
-            metadata.set_permissions(Permissions::from_bits_truncate(mode as u16));
-            Ok(0)
-        })
-        .unwrap_or_else(|_| Err(SyscallError::ENOENT))
+    let file_path = deal_with_path(dir_fd, Some(path), false).ok_or(SyscallError::EINVAL)?;
+    chmod_path(file_path.path(), mode as u32)
+}
+
+/// 修改文件权限
+/// # Arguments
+/// * `fd`: usize, 文件描述符
+/// * `mode`: usize, 文件的权限
+pub fn syscall_fchmod(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let mode = args[1];
+    let process = current_process();
+    let fd_table = process.fd_manager.fd_table.lock();
+    let file = fd_table
+        .get(fd)
+        .and_then(|file| file.clone())
+        .ok_or(SyscallError::EBADF)?;
+    drop(fd_table);
+    chmod_path(&file.get_path(), mode as u32)
+}
+
+/// 修改给定路径的文件的权限位，文件系统支持时写入文件的 inode
+fn chmod_path(path: &str, mode: u32) -> SyscallResult {
+    cred::check_search(path)?;
+    // 只读挂载的文件不能修改
+    axfs::api::lookup_writable(path, true)?;
+    cred::chmod(path, mode).map_err(owner_error)?;
+    Ok(0)
+}
+
+/// 修改文件属主与权限时的错误码，没有权限时为 EPERM 而非 EACCES
+fn owner_error(err: AxError) -> SyscallError {
+    match err {
+        AxError::PermissionDenied => SyscallError::EPERM,
+        err => err.into(),
+    }
+}
+
+/// 修改文件的属主与属组
+///
+/// owner 或 group 为 -1 时对应的一项保持不变
+/// # Arguments
+/// * `dir_fd`: usize, 目录的文件描述符
+/// * `path`: *const u8, 文件的路径
+/// * `owner`: u32, 新的属主
+/// * `group`: u32, 新的属组
+/// * `flags`: usize, 选项，支持 AT_EMPTY_PATH
+pub fn syscall_fchownat(args: [usize; 6]) -> SyscallResult {
+    let dir_fd = args[0];
+    let path = args[1] as *const u8;
+    let owner = args[2] as u32;
+    let group = args[3] as u32;
+    let flags = args[4];
+    let file_path = if flags & AT_EMPTY_PATH != 0 && dir_fd != AT_FDCWD {
+        let process = current_process();
+        let fd_table = process.fd_manager.fd_table.lock();
+        let file = fd_table
+            .get(dir_fd)
+            .and_then(|file| file.clone())
+            .ok_or(SyscallError::EBADF)?;
+        file.get_path()
+    } else {
+        deal_with_path(dir_fd, Some(path), false)
+            .ok_or(SyscallError::EINVAL)?
+            .path()
+            .to_string()
+    };
+    cred::check_search(&file_path)?;
+    axfs::api::lookup_writable(&file_path, true)?;
+    let owner = (owner != u32::MAX).then_some(owner);
+    let group = (group != u32::MAX).then_some(group);
+    cred::chown(&file_path, owner, group).map_err(owner_error)?;
+    Ok(0)
+}
+
+/// 修改文件描述符所指文件的属主与属组
+/// # Arguments
+/// * `fd`: usize, 文件描述符
+/// * `owner`: u32, 新的属主
+/// * `group`: u32, 新的属组
+pub fn syscall_fchown(args: [usize; 6]) -> SyscallResult {
+    let (fd, owner, group) = (args[0], args[1], args[2]);
+    syscall_fchownat([fd, 0, owner, group, AT_EMPTY_PATH, 0])
 }
 
 /// 48
@@ -496,38 +620,22 @@ pub fn syscall_faccessat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
     let mode = args[2];
-    // todo: 有问题,实际上需要考虑当前进程对应的用户UID和文件拥有者之间的关系
-    // 现在一律当作root用户处理
-    let file_path = deal_with_path(dir_fd, Some(path), false).unwrap();
-    axfs::api::metadata(file_path.path())
-        .map(|metadata| {
-            if mode == 0 {
-                //F_OK
-                // 文件存在返回0,不存在返回-1
-                if axfs::api::path_exists(file_path.path()) {
-                    Ok(0)
-                } else {
-                    Err(SyscallError::ENOENT)
-                }
-            } else {
-                // 逐位对比
-                let mut ret = true;
-                if mode & 1 != 0 {
-                    // X_OK
-                    ret &= metadata.permissions().contains(Permissions::OWNER_EXEC)
-                }
-                if mode & 2 != 0 {
-                    // W_OK
-                    ret &= metadata.permissions().contains(Permissions::OWNER_WRITE)
-                }
-                if mode & 4 != 0 {
-                    // R_OK
-                    ret &= metadata.permissions().contains(Permissions::OWNER_READ)
-                }
-                Ok(ret as isize - 1)
-            }
-        })
-        .unwrap_or_else(|_| Err(SyscallError::ENOENT))
+    let flags = args[3];
+    let file_path = deal_with_path(dir_fd, Some(path), false).ok_or(SyscallError::EINVAL)?;
+    // 默认使用真实用户号与组号进行检查，真实用户号不为 0 时不具有特权
+    let mut cred = current_process().cred();
+    if flags & AT_EACCESS == 0 {
+        cred.fsuid = cred.uid;
+        cred.fsgid = cred.gid;
+        cred.cap_effective = if cred.uid == 0 {
+            cred.cap_permitted
+        } else {
+            Privileges::empty()
+        };
+    }
+    let want = Cap::from_mode_bits(mode as u32 & 0o7);
+    cred::access_as(&cred, file_path.path(), want)?;
+    Ok(0)
 }
 
 /// 48
@@ -540,8 +648,6 @@ pub fn syscall_access(args: [usize; 6]) -> SyscallResult {
     let path = args[0];
     let mode = args[1];
     let temp_args = [AT_FDCWD, path, mode, 0, 0, 0];
-    // todo: 有问题,实际上需要考虑当前进程对应的用户UID和文件拥有者之间的关系
-    // 现在一律当作root用户处理
     syscall_faccessat(temp_args)
 }
 
@@ -556,18 +662,20 @@ pub fn syscall_rmdir(args: [usize; 6]) -> SyscallResult {
 
 /// 88
 /// 用于修改文件或目录的时间戳(timestamp)
-/// 如果 fir_fd < 0,它和 path 共同决定要找的文件；
-/// 如果 fir_fd >=0,它就是文件对应的 fd
+/// path 为空指针时修改 dir_fd 所指的文件，即 futimens；
+/// 否则 dir_fd 与 path 共同决定要找的文件
+///
+/// 时间写入文件系统中文件的 inode，文件系统不记录时间戳时只记录在文件描述符上
 /// # Arguments
 /// * `dir_fd`: usize, 目录的文件描述符
 /// * `path`: *const u8, 文件的路径
 /// * `times`: *const TimeSecs, 时间戳
-/// * `flags`: usize, 选项
+/// * `flags`: usize, 选项，支持 AT_SYMLINK_NOFOLLOW
 pub fn syscall_utimensat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
     let times = args[2] as *const TimeSecs;
-    let _flags = args[3];
+    let flags = args[3];
     let process = current_process();
     // info!("dir_fd: {}, path: {}", dir_fd as usize, path as usize);
     if dir_fd != AT_FDCWD && (dir_fd as isize) < 0 {
@@ -590,32 +698,28 @@ pub fn syscall_utimensat(args: [usize; 6]) -> SyscallResult {
         }
         unsafe { (*times, *(times.add(1))) } //  注意传入的TimeVal中 sec和nsec都是usize, 但TimeValue中nsec是u32
     };
-    // 感觉以下仿照maturin的实现不太合理,并没有真的把时间写给文件,只是写给了一个新建的临时的fd
-    if (dir_fd as isize) > 0 {
-        // let file = process_inner.fd_manager.fd_table[dir_fd].clone();
-        // if !file.unwrap().lock().set_time(new_atime, new_mtime) {
-        //     error!("Set time failed: unknown reason.");
-        //     return ErrorNo::EPERM as isize;
-        // }
+    let follow = flags & AT_SYMLINK_NOFOLLOW == 0;
+    if path.is_null() {
         let fd_table = process.fd_manager.fd_table.lock();
-        if dir_fd > fd_table.len() || fd_table[dir_fd].is_none() {
+        if dir_fd >= fd_table.len() || fd_table[dir_fd].is_none() {
             return Err(SyscallError::EBADF);
         }
-        if let Some(file) = fd_table[dir_fd].as_ref() {
-            if let Some(fat_file) = file.as_any().downcast_ref::<FileDesc>() {
-                // if !fat_file.set_time(new_atime, new_mtime) {
-                //     error!("Set time failed: unknown reason.");
-                //     return ErrorNo::EPERM as isize;
-                // }
-                fat_file.stat.lock().atime.set_as_utime(&new_atime);
-                fat_file.stat.lock().mtime.set_as_utime(&new_mtime);
-            } else {
-                return Err(SyscallError::EPERM);
+        let file = fd_table[dir_fd].clone().unwrap();
+        drop(fd_table);
+        let Some(file_desc) = file.as_any().downcast_ref::<FileDesc>() else {
+            return Err(SyscallError::EPERM);
+        };
+        check_set_times(&file_desc.path, &new_atime, &new_mtime)?;
+        match set_file_times(&file_desc.path, &new_atime, &new_mtime, follow) {
+            Err(AxError::Unsupported) => {
+                file_desc.stat.lock().atime.set_as_utime(&new_atime);
+                file_desc.stat.lock().mtime.set_as_utime(&new_mtime);
+                Ok(0)
             }
+            result => result.map(|_| 0).map_err(Into::into),
         }
-        Ok(0)
     } else {
-        let file_path = deal_with_path(dir_fd, Some(path), false).unwrap();
+        let file_path = deal_with_path(dir_fd, Some(path), false).ok_or(SyscallError::EINVAL)?;
         if !axfs::api::path_exists(file_path.path()) {
             error!("Set time failed: file {} doesn't exist!", file_path.path());
             if !axfs::api::path_exists(file_path.dir().unwrap()) {
@@ -624,9 +728,36 @@ pub fn syscall_utimensat(args: [usize; 6]) -> SyscallResult {
                 return Err(SyscallError::ENOENT);
             }
         }
-        let file = new_fd(file_path.path().to_string(), 0.into()).unwrap();
-        file.stat.lock().atime.set_as_utime(&new_atime);
-        file.stat.lock().mtime.set_as_utime(&new_mtime);
-        Ok(0)
+        cred::check_search(file_path.path())?;
+        check_set_times(file_path.path(), &new_atime, &new_mtime)?;
+        match set_file_times(file_path.path(), &new_atime, &new_mtime, follow) {
+            // 文件系统不记录时间戳，忽略即可
+            Err(AxError::Unsupported) => Ok(0),
+            result => result.map(|_| 0).map_err(Into::into),
+        }
+    }
+}
+
+/// 检查当前进程能否修改文件的时间戳
+///
+/// 设置为指定的时间需要是文件的属主，设置为当前时间时对文件有写权限即可
+fn check_set_times(path: &str, atime: &TimeSecs, mtime: &TimeSecs) -> Result<(), SyscallError> {
+    let cred = current_process().cred();
+    if cred.owns(&cred::file_owner(path)?) {
+        return Ok(());
     }
+    let explicit = |time: &TimeSecs| time.tv_nsec != UTIME_NOW && time.tv_nsec != UTIME_OMIT;
+    if explicit(atime) || explicit(mtime) {
+        return Err(SyscallError::EPERM);
+    }
+    cred::check_access(path, Cap::WRITE)?;
+    Ok(())
+}
+
+/// 修改文件的访问与修改时间，文件系统不记录时间戳时返回 Unsupported
+fn set_file_times(path: &str, atime: &TimeSecs, mtime: &TimeSecs, follow: bool) -> AxResult {
+    let node = axfs::api::lookup_writable(path, follow)?;
+    node.set_times(atime.to_utime(), mtime.to_utime())?;
+    axfs::api::notify_change(path, FsEvent::ATTRIB);
+    Ok(())
 }
@@ -4,7 +4,10 @@
 extern crate alloc;
 use crate::{SyscallError, SyscallResult};
 use alloc::sync::Arc;
-use axhal::{mem::VirtAddr, time::current_ticks};
+use axhal::{
+    mem::VirtAddr,
+    time::{current_ticks, nanos_to_ticks, NANOS_PER_MILLIS},
+};
 use axprocess::current_process;
 
 use crate::syscall_fs::ctype::epoll::{EpollCtl, EpollEvent, EpollFile};
@@ -52,17 +55,18 @@ pub fn syscall_epoll_ctl(args: [usize; 6]) -> SyscallResult {
     }
     let fd_table = process.fd_manager.fd_table.lock();
     let event = unsafe { *event };
-    if fd_table[fd as usize].is_none() {
-        return Err(SyscallError::EBADF);
-    }
+    let target = match fd_table.get(fd as usize) {
+        Some(Some(file)) => Arc::clone(file),
+        _ => return Err(SyscallError::EBADF),
+    };
     let op = if let Ok(val) = EpollCtl::try_from(op) {
         val
     } else {
         return Err(SyscallError::EINVAL);
     };
-    if let Some(file) = fd_table[epfd as usize].as_ref() {
+    if let Some(Some(file)) = fd_table.get(epfd as usize) {
         if let Some(epoll_file) = file.as_any().downcast_ref::<EpollFile>() {
-            epoll_file.epoll_ctl(op, fd, event)
+            epoll_file.epoll_ctl(op, fd, event, &target)
         } else {
             Err(SyscallError::EBADF)
         }
@@ -97,7 +101,7 @@ pub fn syscall_epoll_wait(args: [usize; 6]) -> SyscallResult {
     }
 
     let fd_table = process.fd_manager.fd_table.lock();
-    let epoll_file = if let Some(file) = fd_table[epfd as usize].as_ref() {
+    let epoll_file = if let Some(Some(file)) = fd_table.get(epfd as usize) {
         if let Some(epoll_file) = file.as_any().downcast_ref::<EpollFile>() {
             epoll_file.clone()
         } else {
@@ -106,13 +110,16 @@ pub fn syscall_epoll_wait(args: [usize; 6]) -> SyscallResult {
     } else {
         return Err(SyscallError::EBADF);
     };
+    // 等待期间不能持有fd表的锁
+    drop(fd_table);
 
-    let timeout = if timeout > 0 {
-        current_ticks() as usize + timeout as usize
+    // timeout 以毫秒为单位，为 0 时立即返回，为负数时无限等待
+    let timeout = if timeout >= 0 {
+        current_ticks() as usize + nanos_to_ticks(timeout as u64 * NANOS_PER_MILLIS) as usize
     } else {
         usize::MAX
     };
-    let ret_events = epoll_file.epoll_wait(timeout);
+    let ret_events = epoll_file.epoll_wait(timeout, max_event);
     if ret_events.is_err() {
         return Err(SyscallError::EINTR);
     }
@@ -0,0 +1,88 @@
+//! inotify 相关的系统调用
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use axfs::api::{FileIO, FsWatcher};
+use axprocess::cred::{self, Cap};
+use axprocess::current_process;
+use axprocess::link::{deal_with_path, AT_FDCWD};
+
+use crate::syscall_fs::ctype::inotify::{InotifyFile, InotifyFlags, InotifyMask};
+use crate::{SyscallError, SyscallResult};
+
+/// 取出 fd 对应的 inotify 文件
+fn inotify_file(fd: usize) -> Result<Arc<dyn FileIO>, SyscallError> {
+    let process = current_process();
+    let fd_table = process.fd_manager.fd_table.lock();
+    let file = fd_table
+        .get(fd)
+        .and_then(|file| file.clone())
+        .ok_or(SyscallError::EBADF)?;
+    if file.as_any().downcast_ref::<InotifyFile>().is_none() {
+        return Err(SyscallError::EINVAL);
+    }
+    Ok(file)
+}
+
+/// 创建一个 inotify 实例
+/// # Arguments
+/// * `flags`: u32, 支持 IN_NONBLOCK 与 IN_CLOEXEC
+pub fn syscall_inotify_init1(args: [usize; 6]) -> SyscallResult {
+    let flags = InotifyFlags::from_bits(args[0] as u32).ok_or(SyscallError::EINVAL)?;
+    let file = Arc::new(InotifyFile::new(flags));
+    let process = current_process();
+    let mut fd_table = process.fd_manager.fd_table.lock();
+    let fd_num = process
+        .alloc_fd(&mut fd_table)
+        .map_err(|_| SyscallError::EMFILE)?;
+    axfs::api::add_fs_watcher(&(file.clone() as Arc<dyn FsWatcher>));
+    fd_table[fd_num] = Some(file);
+    Ok(fd_num as isize)
+}
+
+/// 监视给定路径的文件，返回监视项的编号
+/// # Arguments
+/// * `fd`: usize, inotify 实例的文件描述符
+/// * `path`: *const u8, 被监视文件的路径
+/// * `mask`: u32, 需要报告的事件与选项
+pub fn syscall_inotify_add_watch(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let path = args[1] as *const u8;
+    let mask = InotifyMask::from_bits_truncate(args[2] as u32);
+    let file = inotify_file(fd)?;
+    if !mask.intersects(InotifyMask::ALL_EVENTS)
+        || mask.contains(InotifyMask::IN_MASK_ADD | InotifyMask::IN_MASK_CREATE)
+    {
+        return Err(SyscallError::EINVAL);
+    }
+    let path = deal_with_path(AT_FDCWD, Some(path), false)
+        .ok_or(SyscallError::EINVAL)?
+        .path()
+        .to_string();
+    // 监视项记录的是链接解析后的路径，除非要求监视链接本身
+    let (path, node) = if mask.contains(InotifyMask::IN_DONT_FOLLOW) {
+        let node = axfs::api::lookup_link(&path)?;
+        (path, node)
+    } else {
+        let node = axfs::api::lookup(&path)?;
+        (axfs::api::real_path(&path)?, node)
+    };
+    cred::check_access(&path, Cap::READ)?;
+    if mask.contains(InotifyMask::IN_ONLYDIR) && !node.get_attr()?.is_dir() {
+        return Err(SyscallError::ENOTDIR);
+    }
+    let inotify = file.as_any().downcast_ref::<InotifyFile>().unwrap();
+    Ok(inotify.add_watch(&path, mask)? as isize)
+}
+
+/// 移除监视项，并报告 IN_IGNORED 事件
+/// # Arguments
+/// * `fd`: usize, inotify 实例的文件描述符
+/// * `wd`: i32, 监视项的编号
+pub fn syscall_inotify_rm_watch(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let wd = args[1] as i32;
+    let file = inotify_file(fd)?;
+    let inotify = file.as_any().downcast_ref::<InotifyFile>().unwrap();
+    inotify.rm_watch(wd)?;
+    Ok(0)
+}
@@ -1,22 +1,32 @@
 //! 负责与 IO 相关的系统调用
 extern crate alloc;
 use crate::syscall_net::Socket;
+use crate::syscall_trace::open_trace_file;
 use crate::{IoVec, SyscallError, SyscallResult};
 use alloc::string::ToString;
 use alloc::sync::Arc;
 use alloc::vec;
 use axerrno::AxError;
-use axfs::api::{FileIOType, OpenFlags, SeekFrom};
+use axfs::api::{path_exists, FileIOType, OpenFlags, SeekFrom};
 
 use axlog::{debug, info};
+use axprocess::cred;
 use axprocess::current_process;
-use axprocess::link::{create_link, deal_with_path, real_path, AT_FDCWD};
+use axprocess::link::{create_link, deal_with_path, real_path, FilePath, AT_FDCWD};
+use axprocess::tty::open_tty_device;
 
+use super::release_posix_locks;
+
+#[cfg(feature = "signal")]
+use crate::syscall_fs::ctype::sysctl::open_sysctl_file;
 use crate::syscall_fs::ctype::{
+    binfmt::open_binfmt_file,
     dir::new_dir,
-    file::{new_fd, new_inode},
-    pipe::make_pipe,
     epoll::{EpollCtl, EpollEvent, EpollEventType, EpollFile},
+    file::{new_fd, new_inode, FileDesc},
+    meminfo::open_meminfo_file,
+    ns::{ns_file_link, open_ns_file},
+    pipe::make_pipe,
 };
 /// 功能:从一个文件描述符中读取；
 /// # Arguments
@@ -319,11 +329,11 @@ pub fn syscall_dup3(args: [usize; 6]) -> SyscallResult {
         debug!("fd {} is not opened", fd);
         return Err(SyscallError::EPERM);
     }
+    if new_fd >= process.fd_limit() {
+        // 超出了资源限制
+        return Err(SyscallError::EBADF);
+    }
     if new_fd >= fd_table.len() {
-        if new_fd >= (process.fd_manager.get_limit() as usize) {
-            // 超出了资源限制
-            return Err(SyscallError::EBADF);
-        }
         for _i in fd_table.len()..new_fd + 1 {
             fd_table.push(None);
         }
@@ -334,7 +344,13 @@ pub fn syscall_dup3(args: [usize; 6]) -> SyscallResult {
     // }
     info!("dup3 fd {} to new fd {}", fd, new_fd);
     // 就算new_fd已经被打开了,也可以被重新替代掉
-    fd_table[new_fd] = fd_table[fd].clone();
+    let file = fd_table[fd].clone();
+    let replaced = core::mem::replace(&mut fd_table[new_fd], file);
+    drop(fd_table);
+    // 被替代的文件相当于被关闭
+    if let (Some(replaced), true) = (replaced, fd != new_fd) {
+        release_posix_locks(&replaced);
+    }
     Ok(new_fd as isize)
 }
 
@@ -352,14 +368,35 @@ pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
     let fd = args[0];
     let path = args[1] as *const u8;
     let flags = args[2];
-    let _mode = args[3] as u8;
+    let mode = args[3] as u32;
     let force_dir = OpenFlags::from(flags).is_dir();
     let path = if let Some(path) = deal_with_path(fd, Some(path), force_dir) {
         path
     } else {
         return Err(SyscallError::EINVAL);
     };
+    // O_NOFOLLOW 要求路径的最后一级不是符号链接
+    if OpenFlags::from(flags).contains(OpenFlags::NOFOLLOW)
+        && axfs::api::lookup_link(path.path())
+            .and_then(|node| node.get_attr())
+            .is_ok_and(|attr| attr.is_symlink())
+    {
+        return Err(SyscallError::ELOOP);
+    }
+    // 之后的检查与记录都针对链接指向的文件
+    let path = match axfs::api::real_path(path.path()) {
+        Ok(real) if real.trim_end_matches('/') != path.path().trim_end_matches('/') => {
+            let real = if path.is_dir() { real + "/" } else { real };
+            FilePath::new(&real).map_err(|_| SyscallError::ENOENT)?
+        }
+        Ok(_) => path,
+        Err(AxError::FilesystemLoop) => return Err(SyscallError::ELOOP),
+        Err(_) => path,
+    };
     let process = current_process();
+    // 检查路径上各级目录的查找权限与文件本身的访问权限
+    cred::check_open(path.path(), flags.into())?;
+    let existed = path_exists(path.path());
     let mut fd_table = process.fd_manager.fd_table.lock();
     let fd_num: usize = if let Ok(fd) = process.alloc_fd(&mut fd_table) {
         fd
@@ -367,6 +404,47 @@ pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::EMFILE);
     };
     debug!("allocated fd_num: {}", fd_num);
+    // 系统调用跟踪记录文件不在文件系统中，单独处理
+    if let Some(file) = open_trace_file(path.path(), flags.into()) {
+        let file = file.map_err(|_| SyscallError::ENOENT)?;
+        fd_table[fd_num] = Some(Arc::new(file));
+        return Ok(fd_num as isize);
+    }
+    // binfmt_misc 的注册接口同样不在文件系统中
+    if let Some(file) = open_binfmt_file(path.path(), flags.into()) {
+        let file = file.map_err(|_| SyscallError::ENOENT)?;
+        fd_table[fd_num] = Some(Arc::new(file));
+        return Ok(fd_num as isize);
+    }
+    // 内存统计在打开时生成
+    if let Some(file) = open_meminfo_file(path.path(), flags.into()) {
+        let file = file.map_err(|_| SyscallError::ENOENT)?;
+        fd_table[fd_num] = Some(Arc::new(file));
+        return Ok(fd_num as isize);
+    }
+    // 命名空间文件指向进程所处的命名空间
+    if let Some(file) = open_ns_file(path.path(), flags.into()) {
+        let file = file.map_err(|_| SyscallError::ENOENT)?;
+        fd_table[fd_num] = Some(Arc::new(file));
+        return Ok(fd_num as isize);
+    }
+    // core_pattern 等内核参数由对应的子系统维护
+    #[cfg(feature = "signal")]
+    if let Some(file) = open_sysctl_file(path.path(), flags.into()) {
+        let file = file.map_err(|_| SyscallError::ENOENT)?;
+        fd_table[fd_num] = Some(Arc::new(file));
+        return Ok(fd_num as isize);
+    }
+    // 终端设备的读写由终端子系统处理
+    if let Some(file) = open_tty_device(path.path(), flags.into()) {
+        let file = file.map_err(|err| match err {
+            // 没有控制终端时打开 /dev/tty
+            AxError::NotFound if path.path() == "/dev/tty" => SyscallError::ENXIO,
+            err => err.into(),
+        })?;
+        fd_table[fd_num] = Some(file);
+        return Ok(fd_num as isize);
+    }
     // 分配 inode
     new_inode(path.path().to_string()).unwrap();
     // 如果是DIR
@@ -375,6 +453,9 @@ pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
         debug!("open dir");
         if let Ok(dir) = new_dir(path.path().to_string(), flags.into()) {
             debug!("new dir_desc successfully allocated: {}", path.path());
+            if !existed {
+                cred::file_created(path.path(), mode, true);
+            }
             fd_table[fd_num] = Some(Arc::new(dir));
             Ok(fd_num as isize)
         } else {
@@ -387,6 +468,9 @@ pub fn syscall_openat(args: [usize; 6]) -> SyscallResult {
         debug!("open file");
         if let Ok(file) = new_fd(path.path().to_string(), flags.into()) {
             debug!("new file_desc successfully allocated");
+            if !existed {
+                cred::file_created(path.path(), mode, false);
+            }
             fd_table[fd_num] = Some(Arc::new(file));
             let _ = create_link(&path, &path); // 不需要检查是否成功,因为如果成功,说明是新建的文件,如果失败,说明已经存在了
             Ok(fd_num as isize)
@@ -433,22 +517,24 @@ pub fn syscall_close(args: [usize; 6]) -> SyscallResult {
         debug!("fd {} is none", fd);
         return Err(SyscallError::EPERM);
     }
-    // let file = process_inner.fd_manager.fd_table[fd].unwrap();
+    let closed_file = fd_table[fd].clone().unwrap();
     for i in 0..fd_table.len() {
         if let Some(file) = fd_table[i].as_ref() {
             if let Some(epoll_file) = file.as_any().downcast_ref::<EpollFile>() {
                 if epoll_file.contains(fd as i32) {
-                    let ev = EpollEvent{
+                    let ev = EpollEvent {
                         event_type: EpollEventType::EPOLLMSG,
-                        data: 0
+                        data: 0,
                     };
-                    epoll_file.epoll_ctl(EpollCtl::DEL, fd as i32, ev)?;
-               }
+                    epoll_file.epoll_ctl(EpollCtl::DEL, fd as i32, ev, &closed_file)?;
+                }
             }
         }
     }
 
     fd_table[fd] = None;
+    drop(fd_table);
+    release_posix_locks(&closed_file);
     // for i in 0..process_inner.fd_table.len() {
     //     if let Some(file) = process_inner.fd_table[i].as_ref() {
     //         debug!("fd: {} has file", i);
@@ -602,6 +688,15 @@ pub fn syscall_readlinkat(args: [usize; 6]) -> SyscallResult {
         return Ok(len as isize);
     }
 
+    // 命名空间文件链接到 `<类型>:[<编号>]`
+    if let Some(link) = ns_file_link(path.path()) {
+        let link = link.map_err(|_| SyscallError::ENOENT)?;
+        let len = bufsiz.min(link.len());
+        let slice = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+        slice.copy_from_slice(&link.as_bytes()[..len]);
+        return Ok(len as isize);
+    }
+
     // 获取进程自身的符号链接信息
     if path.path() == "/proc/self/exe" {
         // 获取该进程符号链接对应的真正地址
@@ -613,6 +708,14 @@ pub fn syscall_readlinkat(args: [usize; 6]) -> SyscallResult {
         return Ok(file_real_path.len() as isize);
     }
 
+    // 文件系统中的符号链接
+    if let Ok(target) = axfs::api::read_link(path.path()) {
+        let len = bufsiz.min(target.len());
+        let slice = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+        slice.copy_from_slice(&target.as_bytes()[..len]);
+        return Ok(len as isize);
+    }
+
     if *path.path() != real_path(&(path.path().to_string())) {
         // 说明链接存在
         let path = path.path();
@@ -669,6 +772,21 @@ pub fn syscall_lseek(args: [usize; 6]) -> SyscallResult {
         } else if whence == 2 {
             // 即SEEK_END
             file.seek(SeekFrom::End(offset as i64))
+        } else if whence == 3 || whence == 4 {
+            // 即SEEK_DATA与SEEK_HOLE，只有普通文件支持
+            let file = file
+                .as_any()
+                .downcast_ref::<FileDesc>()
+                .ok_or(SyscallError::EINVAL)?;
+            if offset < 0 {
+                return Err(SyscallError::ENXIO);
+            }
+            return match file.seek_data(offset as u64, whence == 4) {
+                Ok(Some(now_offset)) => Ok(now_offset as isize),
+                // 偏移量已经超出文件末尾，或者之后没有数据
+                Ok(None) => Err(SyscallError::ENXIO),
+                Err(err) => Err(err.into()),
+            };
         } else {
             return Err(SyscallError::EINVAL);
         };
@@ -684,8 +802,7 @@ pub fn syscall_lseek(args: [usize; 6]) -> SyscallResult {
 }
 
 /// 82
-/// 写回硬盘
-#[allow(unused)]
+/// 将文件的修改写回硬盘
 /// # Arguments
 /// * `fd`: usize
 pub fn syscall_fsync(args: [usize; 6]) -> SyscallResult {
@@ -695,16 +812,30 @@ pub fn syscall_fsync(args: [usize; 6]) -> SyscallResult {
         debug!("fd {} is out of range", fd);
         return Err(SyscallError::EBADF);
     }
-    let fd_table = process.fd_manager.fd_table.lock();
-    if let Some(file) = fd_table[fd].clone() {
-        // if file.flush().is_err() {}
-        Ok(0)
-    } else {
+    let file = process.fd_manager.fd_table.lock()[fd].clone();
+    let Some(file) = file else {
         debug!("fd {} is none", fd);
-        Err(SyscallError::EBADF)
+        return Err(SyscallError::EBADF);
+    };
+    match file.flush() {
+        Err(AxError::Io) => Err(SyscallError::EIO),
+        // 以只读方式打开的文件无法单独写回，改为写回所有硬盘
+        Err(AxError::PermissionDenied) => {
+            axfs::api::sync().map(|_| 0).map_err(|_| SyscallError::EIO)
+        }
+        // 管道、内存中的文件等没有需要写回的数据
+        _ => Ok(0),
     }
 }
 
+/// 81
+/// 将所有文件系统缓存的修改写回硬盘
+pub fn syscall_sync() -> SyscallResult {
+    // sync 总是成功，写回失败的数据会留在缓存中
+    let _ = axfs::api::sync();
+    Ok(0)
+}
+
 /**
 该系统调用应复制文件描述符 fd_in 中的至多 len 个字节到文件描述符 fd_out 中。
 若 off_in 为 NULL,则复制时应从文件描述符 fd_in 本身的文件偏移处开始读取,并将其文件偏移增加成功复制的字节数；否则,从 *off_in 指定的文件偏移处开始读取,不改变 fd_in 的文件偏移,而是将 *off_in 增加成功复制的字节数。
@@ -788,6 +919,64 @@ pub fn syscall_copyfilerange(args: [usize; 6]) -> SyscallResult {
     Ok(write_len as isize)
 }
 
+/// fallocate 的模式：不改变文件大小
+const FALLOC_FL_KEEP_SIZE: usize = 1;
+/// fallocate 的模式：释放一段范围的空间，必须与 FALLOC_FL_KEEP_SIZE 一起使用
+const FALLOC_FL_PUNCH_HOLE: usize = 2;
+
+/// 为文件的一段范围分配空间，或者释放这段范围的空间
+/// # Arguments
+/// * `fd`: usize
+/// * `mode`: usize, 0、FALLOC_FL_KEEP_SIZE 或 FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE
+/// * `offset`: i64, 范围的起始位置
+/// * `len`: i64, 范围的长度
+pub fn syscall_fallocate(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let mode = args[1];
+    let offset = args[2] as i64;
+    let len = args[3] as i64;
+    info!(
+        "fd: {}, mode: {:#x}, offset: {}, len: {}",
+        fd, mode, offset, len
+    );
+    let (keep_size, punch_hole) = match mode {
+        0 => (false, false),
+        FALLOC_FL_KEEP_SIZE => (true, false),
+        m if m == FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE => (true, true),
+        _ => return Err(SyscallError::EOPNOTSUPP),
+    };
+    if offset < 0 || len <= 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    if offset.checked_add(len).is_none() {
+        return Err(SyscallError::EFBIG);
+    }
+    let process = current_process();
+    let file = process
+        .fd_manager
+        .fd_table
+        .lock()
+        .get(fd)
+        .and_then(|file| file.clone())
+        .ok_or(SyscallError::EBADF)?;
+    if !file.writable() {
+        return Err(SyscallError::EBADF);
+    }
+    let Some(file) = file.as_any().downcast_ref::<FileDesc>() else {
+        return Err(match file.get_type() {
+            FileIOType::DirDesc => SyscallError::EISDIR,
+            FileIOType::Pipe => SyscallError::ESPIPE,
+            _ => SyscallError::ENODEV,
+        });
+    };
+    match file.allocate(offset as u64, len as u64, keep_size, punch_hole) {
+        Ok(()) => Ok(0),
+        // 文件系统不支持预分配或打洞
+        Err(AxError::Unsupported) => Err(SyscallError::EOPNOTSUPP),
+        Err(err) => Err(err.into()),
+    }
+}
+
 /// # Arguments
 /// * `fd`: usize
 /// * `len`: usize
@@ -4,30 +4,66 @@
 extern crate alloc;
 
 use crate::{SyscallError, SyscallResult};
+use alloc::{format, string::ToString};
+use axerrno::AxError;
 use axlog::debug;
-use axprocess::link::{create_link, deal_with_path, remove_link, FilePath};
+use axprocess::link::{
+    create_link, deal_with_path, raw_ptr_to_ref_str, remove_link, FilePath, AT_FDCWD,
+};
+use axprocess::{cred, current_process};
 
 /// Special value used to indicate openat should use the current working directory.
 pub const AT_REMOVEDIR: usize = 0x200; // Remove directory instead of unlinking file.
 
+/// faccessat 使用有效用户号而非真实用户号进行检查
+pub const AT_EACCESS: usize = 0x200;
+
+/// 路径为空时操作 dirfd 本身指向的文件
+pub const AT_EMPTY_PATH: usize = 0x1000;
+
+/// 路径的最后一级为符号链接时，操作链接本身
+pub const AT_SYMLINK_NOFOLLOW: usize = 0x100;
+
+/// linkat 在路径的最后一级为符号链接时，链接到其指向的文件
+pub const AT_SYMLINK_FOLLOW: usize = 0x400;
+
 /// 功能:创建文件的链接；
 /// # Arguments
 /// * `old_dir_fd`: usize, 原来的文件所在目录的文件描述符。
 /// * `old_path`: *const u8, 文件原来的名字。如果old_path是相对路径,则它是相对于old_dir_fd目录而言的。如果old_path是相对路径,且old_dir_fd的值为AT_FDCWD,则它是相对于当前路径而言的。如果old_path是绝对路径,则old_dir_fd被忽略。
 /// * `new_dir_fd`: usize, 新文件名所在的目录。
 /// * `new_path`: *const u8, 文件的新名字。new_path的使用规则同old_path。
-/// * `flags`: usize, 在2.6.18内核之前,应置为0。其它的值详见`man 2 linkat`。
+/// * `flags`: usize, 可设置为0、AT_SYMLINK_FOLLOW或AT_EMPTY_PATH。设置AT_EMPTY_PATH且old_path为空时，链接old_dir_fd本身指向的文件。其它的值详见`man 2 linkat`。
 /// # Return
 /// 成功执行,返回0。失败,返回-1。
-#[allow(dead_code)]
+///
+/// 文件系统不支持硬链接时，使用 [`create_link`] 模拟的链接
 pub fn sys_linkat(args: [usize; 6]) -> SyscallResult {
     let old_dir_fd = args[0];
     let old_path = args[1] as *const u8;
     let new_dir_fd = args[2];
     let new_path = args[3] as *const u8;
-    let _flags = args[4];
+    let flags = args[4];
+    if flags & !(AT_SYMLINK_FOLLOW | AT_EMPTY_PATH) != 0 {
+        return Err(SyscallError::EINVAL);
+    }
 
-    let old_path = if let Some(path) = deal_with_path(old_dir_fd, Some(old_path), false) {
+    // AT_EMPTY_PATH 且路径为空时，为 old_dir_fd 本身指向的文件建立链接
+    let empty_old_path = flags & AT_EMPTY_PATH != 0
+        && !old_path.is_null()
+        && unsafe { raw_ptr_to_ref_str(old_path) }.is_empty();
+    let old_path = if empty_old_path {
+        if old_dir_fd == AT_FDCWD {
+            return Err(SyscallError::ENOENT);
+        }
+        let process = current_process();
+        let fd_table = process.fd_manager.fd_table.lock();
+        let file = fd_table
+            .get(old_dir_fd)
+            .and_then(|file| file.clone())
+            .ok_or(SyscallError::EBADF)?;
+        FilePath::new(&file.get_path())?
+    } else if let Some(path) = deal_with_path(old_dir_fd, Some(old_path), false) {
         path
     } else {
         return Err(SyscallError::EINVAL);
@@ -37,11 +73,89 @@ pub fn sys_linkat(args: [usize; 6]) -> SyscallResult {
     } else {
         return Err(SyscallError::EINVAL);
     };
-    if create_link(&old_path, &new_path) {
-        Ok(0)
+    let source = if flags & AT_SYMLINK_FOLLOW != 0 {
+        axfs::api::real_path(old_path.path())?
     } else {
-        Err(SyscallError::EINVAL)
+        old_path.path().to_string()
+    };
+    cred::check_modify_entry(new_path.path(), false)?;
+    match axfs::api::hard_link(&source, new_path.path()) {
+        Ok(()) => Ok(0),
+        // 不能为目录建立硬链接
+        Err(AxError::IsADirectory) => Err(SyscallError::EPERM),
+        Err(AxError::Unsupported) => {
+            if axfs::api::path_exists(new_path.path()) {
+                return Err(SyscallError::EEXIST);
+            }
+            // 新路径指向原来的文件
+            if create_link(&new_path, &old_path) {
+                Ok(0)
+            } else {
+                Err(SyscallError::EINVAL)
+            }
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// 功能:创建符号链接；
+/// # Arguments
+/// * `target`: *const u8, 链接指向的路径，原样保存，可以不存在
+/// * `new_dir_fd`: usize, 链接所在目录的文件描述符，规则同linkat
+/// * `link_path`: *const u8, 链接的路径
+/// # Return
+/// 成功执行,返回0。失败,返回-1。
+///
+/// 文件系统不支持符号链接时，若指向的文件存在，则使用 [`create_link`] 模拟
+pub fn syscall_symlinkat(args: [usize; 6]) -> SyscallResult {
+    let target = args[0] as *const u8;
+    let new_dir_fd = args[1];
+    let link_path = args[2] as *const u8;
+    if target.is_null()
+        || current_process()
+            .manual_alloc_for_lazy((target as usize).into())
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
     }
+    let target = unsafe { raw_ptr_to_ref_str(target) }.to_string();
+    let link_path =
+        deal_with_path(new_dir_fd, Some(link_path), false).ok_or(SyscallError::EINVAL)?;
+    cred::check_modify_entry(link_path.path(), false)?;
+    match axfs::api::symlink(&target, link_path.path()) {
+        Ok(()) => Ok(0),
+        Err(AxError::Unsupported) => {
+            if axfs::api::path_exists(link_path.path()) {
+                return Err(SyscallError::EEXIST);
+            }
+            // 相对路径相对于链接所在的目录
+            let target = if target.starts_with('/') {
+                target
+            } else {
+                let dir = link_path.path().trim_end_matches(|c| c != '/');
+                format!("{}{}", dir, target)
+            };
+            let target = FilePath::new(&target).map_err(|_| SyscallError::EPERM)?;
+            if create_link(&link_path, &target) {
+                Ok(0)
+            } else {
+                // 文件系统不支持符号链接
+                Err(SyscallError::EPERM)
+            }
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// 功能:创建符号链接；
+/// # Arguments
+/// * `target`: *const u8, 链接指向的路径
+/// * `link_path`: *const u8, 链接的路径
+/// # Return
+/// 成功执行,返回0。失败,返回-1。
+pub fn syscall_symlink(args: [usize; 6]) -> SyscallResult {
+    let temp_args = [args[0], AT_FDCWD, args[1], 0, 0, 0];
+    syscall_symlinkat(temp_args)
 }
 
 /// 功能:移除指定文件的链接
@@ -51,7 +165,7 @@ pub fn sys_linkat(args: [usize; 6]) -> SyscallResult {
 /// 成功执行,返回0。失败,返回-1。
 pub fn syscall_unlink(args: [usize; 6]) -> SyscallResult {
     let path = args[0] as *const u8;
-    let temp_args = [axprocess::link::AT_FDCWD, path as usize, 0, 0, 0, 0];
+    let temp_args = [AT_FDCWD, path as usize, 0, 0, 0, 0];
     syscall_unlinkat(temp_args)
 }
 
@@ -71,12 +185,15 @@ pub fn syscall_unlinkat(args: [usize; 6]) -> SyscallResult {
     if path.start_with(&FilePath::new("/proc").unwrap()) {
         return Ok(-1);
     }
+    cred::check_modify_entry(path.path(), true)?;
 
-    // unlink file
+    // unlink file, 模拟的链接只删除记录
     if flags == 0 {
         if remove_link(&path).is_none() {
-            debug!("unlink file error");
-            return Err(SyscallError::EINVAL);
+            if let Err(e) = axfs::api::remove_file(path.path()) {
+                debug!("unlink file error: {:?}", e);
+                return Err(e.into());
+            }
         }
     }
     // remove dir
@@ -91,5 +208,9 @@ pub fn syscall_unlinkat(args: [usize; 6]) -> SyscallResult {
         debug!("flags error");
         return Err(SyscallError::EINVAL);
     }
+    // 最后一个链接被删除后，文件的属主记录也随之失效
+    if !axfs::api::path_exists(path.path()) {
+        cred::remove_file_owner(path.path());
+    }
     Ok(0)
 }
@@ -0,0 +1,224 @@
+//! 建议性文件锁：fcntl 的记录锁、OFD 锁以及 flock
+use alloc::sync::Arc;
+use axerrno::AxError;
+use axfs::api::{
+    cancel_lock_wait, release_locks, set_lock, test_lock, unlock_range, FileIO, FileLock, LockKind,
+    LockOwner, PollWaker, SeekFrom,
+};
+use axprocess::current_process;
+
+use crate::syscall_fs::ctype::poll_waiter::PollWaiter;
+use crate::syscall_fs::ctype::FileDesc;
+use crate::{Fcntl64Cmd, Flock, SyscallError, SyscallResult};
+
+/// 共享锁
+const F_RDLCK: i16 = 0;
+/// 互斥锁
+const F_WRLCK: i16 = 1;
+/// 释放锁
+const F_UNLCK: i16 = 2;
+
+/// flock：加共享锁
+const LOCK_SH: usize = 1;
+/// flock：加互斥锁
+const LOCK_EX: usize = 2;
+/// flock：冲突时不等待
+const LOCK_NB: usize = 4;
+/// flock：释放锁
+const LOCK_UN: usize = 8;
+
+/// 只有普通文件可以加锁
+fn lock_target(file: &Arc<dyn FileIO>) -> Result<&FileDesc, SyscallError> {
+    file.as_any()
+        .downcast_ref::<FileDesc>()
+        .ok_or(SyscallError::EINVAL)
+}
+
+/// 将 `struct flock` 描述的范围转换为 `[start, end)`，`end` 为 `u64::MAX` 时直到文件末尾
+fn lock_range(file: &FileDesc, flock: &Flock) -> Result<(u64, u64), SyscallError> {
+    let base = match flock.l_whence {
+        0 => 0,
+        1 => file.seek(SeekFrom::Current(0))? as i64,
+        2 => file.file.lock().metadata()?.size() as i64,
+        _ => return Err(SyscallError::EINVAL),
+    };
+    let start = base
+        .checked_add(flock.l_start)
+        .ok_or(SyscallError::EOVERFLOW)?;
+    let (start, end) = match flock.l_len {
+        0 => (start, None),
+        len if len > 0 => (
+            start,
+            Some(start.checked_add(len).ok_or(SyscallError::EOVERFLOW)?),
+        ),
+        len => (
+            start.checked_add(len).ok_or(SyscallError::EINVAL)?,
+            Some(start),
+        ),
+    };
+    if start < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    Ok((start as u64, end.map_or(u64::MAX, |end| end as u64)))
+}
+
+/// 加锁，`wait` 为真时睡眠等待冲突的锁被释放
+fn wait_for_lock(node: usize, lock: FileLock, wait: bool) -> SyscallResult {
+    if !wait {
+        set_lock(node, lock, None)?;
+        return Ok(0);
+    }
+    let waiter = Arc::new(PollWaiter::new(false));
+    let waker: Arc<dyn PollWaker> = waiter.clone();
+    let result = loop {
+        // 先清除通知再尝试加锁，此后释放的锁会使等待立即返回
+        waiter.prepare();
+        match set_lock(node, lock, Some(&waker)) {
+            Ok(()) => break Ok(0),
+            Err(AxError::WouldBlock) => {
+                #[cfg(feature = "signal")]
+                if current_process().have_signals().is_some() {
+                    break Err(SyscallError::EINTR);
+                }
+                waiter.wait(usize::MAX, false);
+            }
+            Err(e) => break Err(e.into()),
+        }
+    };
+    cancel_lock_wait(node, lock.owner, &waker);
+    result
+}
+
+/// 处理 fcntl 的加锁命令
+/// # Arguments
+/// * `file`: 被加锁的文件
+/// * `cmd`: F_GETLK、F_SETLK、F_SETLKW 或对应的 OFD 命令
+/// * `arg`: *mut Flock, 锁的描述
+pub fn fcntl_lock(file: &Arc<dyn FileIO>, cmd: Fcntl64Cmd, arg: usize) -> SyscallResult {
+    let process = current_process();
+    let ptr = arg as *mut Flock;
+    if ptr.is_null() || process.manual_alloc_type_for_lazy(ptr).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let mut flock = unsafe { *ptr };
+    let desc = lock_target(file)?;
+    let ofd = matches!(
+        cmd,
+        Fcntl64Cmd::F_OFD_GETLK | Fcntl64Cmd::F_OFD_SETLK | Fcntl64Cmd::F_OFD_SETLKW
+    );
+    let owner = if ofd {
+        // OFD 锁要求 l_pid 为 0
+        if flock.l_pid != 0 {
+            return Err(SyscallError::EINVAL);
+        }
+        LockOwner::Ofd(desc.lock_id())
+    } else {
+        LockOwner::Posix(process.pid())
+    };
+    let (start, end) = lock_range(desc, &flock)?;
+    let node = desc.node_id();
+    let kind = match flock.l_type {
+        F_RDLCK => Some(LockKind::Read),
+        F_WRLCK => Some(LockKind::Write),
+        F_UNLCK => None,
+        _ => return Err(SyscallError::EINVAL),
+    };
+    let pid = if ofd { -1 } else { process.pid() as i32 };
+    match cmd {
+        Fcntl64Cmd::F_GETLK | Fcntl64Cmd::F_OFD_GETLK => {
+            let lock = FileLock {
+                owner,
+                kind: kind.ok_or(SyscallError::EINVAL)?,
+                start,
+                end,
+                pid,
+            };
+            // 没有冲突时只将 l_type 改为 F_UNLCK，否则返回冲突的锁
+            match test_lock(node, &lock) {
+                Some(conflict) => {
+                    flock.l_type = match conflict.kind {
+                        LockKind::Read => F_RDLCK,
+                        LockKind::Write => F_WRLCK,
+                    };
+                    flock.l_whence = 0;
+                    flock.l_start = conflict.start as i64;
+                    flock.l_len = if conflict.end == u64::MAX {
+                        0
+                    } else {
+                        (conflict.end - conflict.start) as i64
+                    };
+                    flock.l_pid = conflict.pid;
+                }
+                None => flock.l_type = F_UNLCK,
+            }
+            unsafe { *ptr = flock };
+            Ok(0)
+        }
+        _ => {
+            let Some(kind) = kind else {
+                unlock_range(node, owner, start, end);
+                return Ok(0);
+            };
+            // 读锁要求文件以可读方式打开，写锁要求以可写方式打开
+            let allowed = match kind {
+                LockKind::Read => desc.readable(),
+                LockKind::Write => desc.writable(),
+            };
+            if !allowed {
+                return Err(SyscallError::EBADF);
+            }
+            let lock = FileLock {
+                owner,
+                kind,
+                start,
+                end,
+                pid,
+            };
+            let wait = matches!(cmd, Fcntl64Cmd::F_SETLKW | Fcntl64Cmd::F_OFD_SETLKW);
+            wait_for_lock(node, lock, wait)
+        }
+    }
+}
+
+/// 关闭文件的任一描述符时，释放当前进程在该文件上的记录锁
+pub fn release_posix_locks(file: &Arc<dyn FileIO>) {
+    if let Ok(desc) = lock_target(file) {
+        release_locks(desc.node_id(), LockOwner::Posix(current_process().pid()));
+    }
+}
+
+/// 对整个文件加锁或解锁
+/// # Arguments
+/// * `fd`: usize, 文件描述符
+/// * `operation`: usize, LOCK_SH、LOCK_EX 或 LOCK_UN，可以与 LOCK_NB 组合
+pub fn syscall_flock(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let operation = args[1];
+    let process = current_process();
+    let file = process
+        .fd_manager
+        .fd_table
+        .lock()
+        .get(fd)
+        .and_then(|file| file.clone())
+        .ok_or(SyscallError::EBADF)?;
+    let desc = lock_target(&file)?;
+    let owner = LockOwner::Flock(desc.lock_id());
+    let kind = match operation & !LOCK_NB {
+        LOCK_SH => LockKind::Read,
+        LOCK_EX => LockKind::Write,
+        LOCK_UN => {
+            release_locks(desc.node_id(), owner);
+            return Ok(0);
+        }
+        _ => return Err(SyscallError::EINVAL),
+    };
+    let lock = FileLock {
+        owner,
+        kind,
+        start: 0,
+        end: u64::MAX,
+        pid: process.pid() as i32,
+    };
+    wait_for_lock(desc.node_id(), lock, operation & LOCK_NB == 0)
+}
@@ -4,16 +4,20 @@ extern crate alloc;
 mod ctl;
 mod epoll;
 mod eventfd;
+mod inotify;
 mod io;
 mod link;
+mod lock;
 mod mount;
 mod poll;
 mod stat;
 pub use ctl::*;
 pub use epoll::*;
 pub use eventfd::*;
+pub use inotify::*;
 pub use io::*;
 pub use link::*;
+pub use lock::*;
 pub use mount::*;
 pub use poll::*;
 pub use stat::*;
@@ -1,90 +1,88 @@
 use crate::{SyscallError, SyscallResult};
+use axerrno::AxError;
 use axprocess::{
+    cred::Privileges,
     current_process,
     link::{deal_with_path, raw_ptr_to_ref_str, AT_FDCWD},
 };
 
-// use super::{deal_with_path, AT_FDCWD};
-use crate::syscall_fs::ctype::mount::{check_mounted, mount_fat_fs, umount_fat_fs};
+use crate::syscall_fs::ctype::mount::{MountFlags, UmountFlags};
 extern crate alloc;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use axlog::debug;
+
+/// 将挂载相关的错误转换为系统调用的错误码
+fn mount_error(err: AxError) -> SyscallError {
+    match err {
+        // 未知的文件系统类型
+        AxError::Unsupported => SyscallError::ENODEV,
+        // 磁盘文件系统的来源不是块设备或文件
+        AxError::IsADirectory => SyscallError::ENOTBLK,
+        // 来源中没有合法的文件系统
+        AxError::InvalidData => SyscallError::EINVAL,
+        err => err.into(),
+    }
+}
+
+/// 从用户地址读取字符串，地址为空时返回 None
+fn read_user_str(ptr: *const u8) -> Result<Option<String>, SyscallError> {
+    if ptr.is_null() {
+        return Ok(None);
+    }
+    if current_process()
+        .manual_alloc_for_lazy((ptr as usize).into())
+        .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    Ok(Some(unsafe { raw_ptr_to_ref_str(ptr) }.to_string()))
+}
+
 /// 功能:挂载文件系统；
 /// # Arguments
-/// * `special`: *const u8, 挂载设备
+/// * `special`: *const u8, 挂载设备，对于 tmpfs、proc 等不需要设备的文件系统可以为任意字符串
 /// * `dir`: *const u8, 挂载点
 /// * `fs_type`: *const u8, 挂载的文件系统类型
-/// * `flags`: usize, 挂载参数
-/// * `data`: *const u8, 传递给文件系统的字符串参数,可为NULL
+/// * `flags`: usize, 挂载参数，支持 MS_RDONLY、MS_BIND 与 MS_REMOUNT
+/// * `data`: *const u8, 传递给文件系统的字符串参数，如 tmpfs 的 size=，可为NULL
 /// 返回值:成功返回0,失败返回-1
 pub fn syscall_mount(args: [usize; 6]) -> SyscallResult {
     let special = args[0] as *const u8;
     let dir = args[1] as *const u8;
     let fs_type = args[2] as *const u8;
-    let _flags = args[3];
-    let _data = args[4] as *const u8;
-    let device_path = deal_with_path(AT_FDCWD, Some(special), false).unwrap();
-    // 这里dir必须以"/"结尾,但在shell中输入时,不需要以"/"结尾
-    let mount_path = deal_with_path(AT_FDCWD, Some(dir), true).unwrap();
+    let flags = MountFlags::from_bits_truncate(args[3] as u32);
+    let data = args[4] as *const u8;
 
-    let process = current_process();
-    if process
-        .manual_alloc_for_lazy((fs_type as usize).into())
-        .is_err()
-    {
-        return Err(SyscallError::EINVAL);
-    }
-
-    let fs_type = unsafe { raw_ptr_to_ref_str(fs_type).to_string() };
-    let mut _data_str = "".to_string();
-    if !_data.is_null() {
-        if process
-            .manual_alloc_for_lazy((_data as usize).into())
-            .is_err()
-        {
-            return Err(SyscallError::EINVAL);
-        }
-        // data可以为NULL, 必须判断, 否则会panic, 发生LoadPageFault
-        _data_str = unsafe { raw_ptr_to_ref_str(_data) }.to_string();
-    }
-    if device_path.is_dir() {
-        debug!("device_path should not be a dir");
+    if !current_process().cred().capable(Privileges::SYS_ADMIN) {
         return Err(SyscallError::EPERM);
     }
-    if !mount_path.is_dir() {
-        debug!("mount_path should be a dir");
-        return Err(SyscallError::EPERM);
+    let mount_path = deal_with_path(AT_FDCWD, Some(dir), true).ok_or(SyscallError::EINVAL)?;
+    let mount_path = axfs::api::canonicalize(mount_path.path()).map_err(mount_error)?;
+    // 挂载点必须是已经存在的目录
+    let node = axfs::api::lookup(&mount_path).map_err(mount_error)?;
+    if !node.get_attr().map_err(mount_error)?.is_dir() {
+        return Err(SyscallError::ENOTDIR);
     }
+    // data 可以为 NULL，此时使用文件系统的默认参数
+    let data = read_user_str(data)?.unwrap_or_default();
+    let read_only = flags.contains(MountFlags::MS_RDONLY);
 
-    // 如果mount_path不存在,则创建
-    if !axfs::api::path_exists(mount_path.path()) {
-        if let Err(e) = axfs::api::create_dir(mount_path.path()) {
-            debug!("create mount path error: {:?}", e);
-            return Err(SyscallError::EPERM);
-        }
-    }
-
-    if fs_type != "vfat" {
-        debug!("fs_type can only be vfat.");
-        return Err(SyscallError::EPERM);
-    }
-    // 检查挂载点路径是否存在
-    if !axfs::api::path_exists(mount_path.path()) {
-        debug!("mount path not exist");
-        return Err(SyscallError::EPERM);
-    }
-    // 查挂载点是否已经被挂载
-    if check_mounted(&mount_path) {
-        debug!("mount path includes mounted fs");
-        return Err(SyscallError::EPERM);
-    }
-    // 挂载
-    if !mount_fat_fs(&device_path, &mount_path) {
-        debug!("mount error");
-        return Err(SyscallError::EPERM);
-    }
-
-    Ok(0)
+    let result = if flags.contains(MountFlags::MS_REMOUNT) {
+        axfs::api::remount(&mount_path, read_only)
+    } else if flags.contains(MountFlags::MS_BIND) {
+        let source = deal_with_path(AT_FDCWD, Some(special), false).ok_or(SyscallError::EINVAL)?;
+        axfs::api::bind_mount(source.path(), &mount_path, read_only)
+    } else {
+        let fs_type = read_user_str(fs_type)?.ok_or(SyscallError::EINVAL)?;
+        let source = read_user_str(special)?.unwrap_or_else(|| String::from("none"));
+        // 磁盘文件系统的来源是设备或镜像文件的路径，其他文件系统的来源只是一个名字
+        let source = match deal_with_path(AT_FDCWD, Some(special), false) {
+            Some(path) if axfs::api::path_exists(path.path()) => path.path().to_string(),
+            _ => source,
+        };
+        axfs::api::mount(&source, &mount_path, &fs_type, &data, read_only)
+    };
+    result.map(|_| 0).map_err(mount_error)
 }
 
 /// 功能:卸载文件系统；
@@ -92,27 +90,30 @@ pub fn syscall_mount(args: [usize; 6]) -> SyscallResult {
 /// 返回值:成功返回0,失败返回-1
 /// # Arguments
 /// * `dir`: *const u8, 指定卸载目录
-/// * `flags`: usize, 卸载参数
+/// * `flags`: usize, 卸载参数，支持 MNT_DETACH，MNT_FORCE 与 UMOUNT_NOFOLLOW 被忽略
 pub fn syscall_umount(args: [usize; 6]) -> SyscallResult {
     let dir = args[0] as *const u8;
-    let flags = args[1];
-    let mount_path = deal_with_path(AT_FDCWD, Some(dir), true).unwrap();
-
-    if flags != 0 {
-        debug!("flags unimplemented");
+    let flags = UmountFlags::from_bits(args[1] as u32).ok_or(SyscallError::EINVAL)?;
+    if flags.contains(UmountFlags::MNT_EXPIRE) {
+        debug!("MNT_EXPIRE unimplemented");
+        return Err(SyscallError::EINVAL);
+    }
+    if !current_process().cred().capable(Privileges::SYS_ADMIN) {
         return Err(SyscallError::EPERM);
     }
+    let mount_path = deal_with_path(AT_FDCWD, Some(dir), true).ok_or(SyscallError::EINVAL)?;
+    let mount_path = axfs::api::canonicalize(mount_path.path()).map_err(mount_error)?;
 
-    // 检查挂载点路径是否存在
-    if !axfs::api::path_exists(mount_path.path()) {
-        debug!("mount path not exist");
-        return Err(SyscallError::EPERM);
+    if !axfs::api::path_exists(&mount_path) {
+        return Err(SyscallError::ENOENT);
     }
-    // 从挂载点中删除
-    if !umount_fat_fs(&mount_path) {
-        debug!("umount error");
-        return Err(SyscallError::EPERM);
+    // 只能卸载挂载点
+    if !axfs::api::mount_table().contains(&mount_path) {
+        return Err(SyscallError::EINVAL);
     }
-
-    Ok(0)
+    // 未指定 MNT_DETACH 时，挂载点正在被使用则返回 EBUSY
+    let detach = flags.contains(UmountFlags::MNT_DETACH);
+    axfs::api::umount(&mount_path, detach)
+        .map(|_| 0)
+        .map_err(mount_error)
 }
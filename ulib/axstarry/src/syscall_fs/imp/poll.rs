@@ -1,8 +1,9 @@
 use axfs::api::FileIO;
 use axhal::{mem::VirtAddr, time::current_ticks};
-use axprocess::{current_process, yield_now_task};
+use axprocess::current_process;
 use bitflags::bitflags;
 extern crate alloc;
+use crate::syscall_fs::ctype::poll_waiter::PollRegistration;
 use crate::{SyscallError, SyscallResult, TimeSecs, TimeVal};
 use alloc::{sync::Arc, vec::Vec};
 bitflags! {
@@ -110,15 +111,27 @@ impl ShadowBitset {
 /// fds：一个PollFd列表
 /// expire_time：时间戳,用来记录是否超时
 ///
+/// 没有事件就绪时，登记在所有文件的就绪通知上并阻塞，直到被唤醒后重新检查
+///
 /// 返回值：(usize, Vec<PollFd>) 第一个参数遵守 ppoll 系统调用的返回值约定,第二个参数为返回的 `PollFd` 列表
 fn ppoll(mut fds: Vec<PollFd>, expire_time: usize) -> (isize, Vec<PollFd>) {
+    let process = current_process();
+    let files: Vec<Option<Arc<dyn FileIO>>> = {
+        let fd_table = process.fd_manager.fd_table.lock();
+        fds.iter()
+            .map(|poll_fd| fd_table.get(poll_fd.fd as usize).cloned().flatten())
+            .collect()
+    };
+    let mut registration = PollRegistration::new();
+    for file in files.iter().flatten() {
+        registration.add(file);
+    }
     loop {
+        registration.prepare();
         // 满足事件要求而被触发的事件描述符数量
         let mut set: isize = 0;
-        let process = current_process();
-        for poll_fd in &mut fds {
-            let fd_table = process.fd_manager.fd_table.lock();
-            if let Some(file) = fd_table[poll_fd.fd as usize].as_ref() {
+        for (poll_fd, file) in fds.iter_mut().zip(files.iter()) {
+            if let Some(file) = file.as_ref() {
                 poll_fd.revents = PollEvents::empty();
                 // let file = file.lock();
                 if file.in_exceptional_conditions() {
@@ -146,17 +159,17 @@ fn ppoll(mut fds: Vec<PollFd>, expire_time: usize) -> (isize, Vec<PollFd>) {
         if set > 0 {
             return (set, fds);
         }
-        if current_ticks() as usize > expire_time {
+        if current_ticks() as usize >= expire_time {
             // 过期了,直接返回
             return (0, fds);
         }
-        yield_now_task();
 
         #[cfg(feature = "signal")]
         if process.have_signals().is_some() {
             // 有信号,此时停止处理,直接返回
             return (0, fds);
         }
+        registration.wait(expire_time);
     }
 }
 
@@ -233,8 +246,13 @@ pub fn syscall_poll(args: [usize; 6]) -> SyscallResult {
             fds.push(*(ufds.add(i)));
         }
     }
-    let expire_time =
-        current_ticks() as usize + TimeVal::from_micro(timeout_msecs).turn_to_ticks() as usize;
+    // 超时时间为负数时无限等待
+    let expire_time = if (timeout_msecs as isize) < 0 {
+        usize::MAX
+    } else {
+        current_ticks() as usize
+            + TimeVal::from_micro(timeout_msecs * 1000).turn_to_ticks() as usize
+    };
 
     let (set, ret_fds) = ppoll(fds, expire_time);
     // 将得到的fd存储到原先的指针中
@@ -249,11 +267,8 @@ pub fn syscall_poll(args: [usize; 6]) -> SyscallResult {
 /// 根据给定的地址和长度新建一个fd set,包括文件描述符指针数组,文件描述符数值数组,以及一个bitset
 fn init_fd_set(addr: *mut usize, len: usize) -> Result<PpollFdSet, SyscallError> {
     let process = current_process();
-    if len >= process.fd_manager.get_limit() as usize {
-        axlog::error!(
-            "[pselect6()] len {len} >= limit {}",
-            process.fd_manager.get_limit()
-        );
+    if len >= process.fd_limit() {
+        axlog::error!("[pselect6()] len {len} >= limit {}", process.fd_limit());
         return Err(SyscallError::EINVAL);
     }
 
@@ -352,19 +367,25 @@ pub fn syscall_pselect6(args: [usize; 6]) -> SyscallResult {
 
     axlog::debug!("[pselect6()]: r: {rfds:?}, w: {wfds:?}, e: {efds:?}");
 
-    loop {
-        // Why yield first?
-        //
-        // 当用户程序中出现如下结构：
-        // while (true) { select(); }
-        // 如果存在 ready 的 fd,select() 立即返回,
-        // 但并不完全满足用户程序的要求,可能出现死循环。
-        //
-        // 因此先 yield 避免其他进程 starvation。
-        //
-        // 可见 iperf 测例。
-        yield_now_task();
+    let mut registration = PollRegistration::new();
+    for file in rfiles.iter().chain(wfiles.iter()).chain(efiles.iter()) {
+        registration.add(file);
+    }
+
+    // Why yield first?
+    //
+    // 当用户程序中出现如下结构：
+    // while (true) { select(); }
+    // 如果存在 ready 的 fd,select() 立即返回,
+    // 但并不完全满足用户程序的要求,可能出现死循环。
+    //
+    // 因此先 yield 避免其他进程 starvation。
+    //
+    // 可见 iperf 测例。
+    axprocess::yield_now_task();
 
+    loop {
+        registration.prepare();
         let mut set = 0;
         if rset.valid() {
             for i in 0..rfds.len() {
@@ -393,12 +414,13 @@ pub fn syscall_pselect6(args: [usize; 6]) -> SyscallResult {
         if set > 0 {
             return Ok(set as isize);
         }
-        if current_ticks() as usize > expire_time {
+        if current_ticks() as usize >= expire_time {
             return Ok(0);
         }
         #[cfg(feature = "signal")]
         if process.have_signals().is_some() {
             return Err(SyscallError::EINTR);
         }
+        registration.wait(expire_time);
     }
 }
@@ -1,16 +1,39 @@
 //! 获取文件系统状态信息
 //!
 
-use crate::{get_fs_stat, FsStat, SyscallError, SyscallResult};
-use axfs::api::{FileIOType, Kstat};
-use axlog::{debug, error, info};
+use crate::{
+    fs_stat_from_info, stat_from_attr, statx_from_stat, FsStat, Statx, StatxMask, SyscallError,
+    SyscallResult,
+};
+use axfs::api::{FileIOType, FileSystemInfo, Kstat};
+use axlog::{debug, info};
 use axprocess::{
-    current_process,
+    cred, current_process,
     link::{deal_with_path, raw_ptr_to_ref_str, FilePath, AT_FDCWD},
 };
 
 use crate::syscall_fs::ctype::mount::get_stat_in_fs;
 
+use super::{AT_EMPTY_PATH, AT_SYMLINK_NOFOLLOW};
+
+/// statx 不自动挂载路径的最后一级，这里没有自动挂载，可以忽略
+const AT_NO_AUTOMOUNT: usize = 0x800;
+
+/// statx 与远程文件系统同步的方式，两位同时设置是非法的
+const AT_STATX_SYNC_TYPE: usize = 0x6000;
+
+/// 管道所在的 pipefs 的 magic number
+const PIPEFS_MAGIC: u64 = 0x5049_5045;
+
+/// socket 所在的 sockfs 的 magic number
+const SOCKFS_MAGIC: u64 = 0x534f_434b;
+
+/// eventfd 等匿名文件所在的 anon_inodefs 的 magic number
+const ANON_INODE_FS_MAGIC: u64 = 0x0904_1934;
+
+/// 伪文件系统的块大小
+const PSEUDO_FS_BLOCK_SIZE: u64 = 0x1000;
+
 /// 实现 stat 系列系统调用
 /// # Arguments
 /// * `fd` - usize
@@ -36,7 +59,13 @@ pub fn syscall_fstat(args: [usize; 6]) -> SyscallResult {
     }
 
     match file.get_stat() {
-        Ok(stat) => {
+        Ok(mut stat) => {
+            cred::fill_stat(
+                &file.get_path(),
+                &mut stat.st_uid,
+                &mut stat.st_gid,
+                &mut stat.st_mode,
+            );
             unsafe {
                 *kst = stat;
             }
@@ -54,10 +83,12 @@ pub fn syscall_fstat(args: [usize; 6]) -> SyscallResult {
 /// * `dir_fd` - usize
 /// * `path` - *const u8
 /// * `kst` - *mut Kstat
+/// * `flags` - usize，支持 AT_SYMLINK_NOFOLLOW
 pub fn syscall_fstatat(args: [usize; 6]) -> SyscallResult {
     let dir_fd = args[0];
     let path = args[1] as *const u8;
     let kst = args[2] as *mut Kstat;
+    let flags = args[3];
     let file_path = if let Some(file_path) = deal_with_path(dir_fd, Some(path), false) {
         file_path
     } else {
@@ -74,15 +105,41 @@ pub fn syscall_fstatat(args: [usize; 6]) -> SyscallResult {
         }
         panic!("Wrong path at syscall_fstatat: {}(dir_fd={})", path, dir_fd);
     };
+    unsafe {
+        *kst = stat_path(&file_path, flags & AT_SYMLINK_NOFOLLOW == 0)?;
+    }
+    Ok(0)
+}
+
+/// 获取路径所指文件的状态信息，`follow` 为假时不跟随最后一级的符号链接
+fn stat_path(file_path: &FilePath, follow: bool) -> Result<Kstat, SyscallError> {
     info!("path : {}", file_path.path());
+    cred::check_search(file_path.path())?;
+    // 路径的最后一级为符号链接时，给出链接本身的信息
+    if !follow {
+        if let Ok(node) = axfs::api::lookup_link(file_path.path()) {
+            let attr = node.get_attr()?;
+            if attr.is_symlink() {
+                return Ok(Kstat {
+                    st_dev: 1,
+                    ..stat_from_attr(&attr)
+                });
+            }
+        }
+    }
     if !axfs::api::path_exists(file_path.path()) {
         return Err(SyscallError::ENOENT);
     }
-    match get_stat_in_fs(&file_path) {
-        Ok(stat) => unsafe {
-            *kst = stat;
-            Ok(0)
-        },
+    match get_stat_in_fs(file_path) {
+        Ok(mut stat) => {
+            cred::fill_stat(
+                file_path.path(),
+                &mut stat.st_uid,
+                &mut stat.st_gid,
+                &mut stat.st_mode,
+            );
+            Ok(stat)
+        }
         Err(error_no) => {
             debug!("get stat error: {:?}", error_no);
             Err(error_no)
@@ -97,7 +154,7 @@ pub fn syscall_fstatat(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_lstat(args: [usize; 6]) -> SyscallResult {
     let path = args[0];
     let kst = args[1];
-    let temp_args = [AT_FDCWD, path, kst, 0, 0, 0];
+    let temp_args = [AT_FDCWD, path, kst, AT_SYMLINK_NOFOLLOW, 0, 0];
     syscall_fstatat(temp_args)
 }
 
@@ -119,16 +176,112 @@ pub fn syscall_stat(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_statfs(args: [usize; 6]) -> SyscallResult {
     let path = args[0] as *const u8;
     let stat = args[1] as *mut FsStat;
-    let file_path = deal_with_path(AT_FDCWD, Some(path), false).unwrap();
-    if file_path.equal_to(&FilePath::new("/").unwrap()) {
-        // 目前只支持访问根目录文件系统的信息
-        unsafe {
-            *stat = get_fs_stat();
-        }
+    let file_path = deal_with_path(AT_FDCWD, Some(path), false).ok_or(SyscallError::EINVAL)?;
+    cred::check_search(file_path.path())?;
+    let info = axfs::api::statfs(file_path.path())?;
+    unsafe {
+        *stat = fs_stat_from_info(&info);
+    }
+    Ok(0)
+}
 
-        Ok(0)
-    } else {
-        error!("Only support fs_stat for root");
-        Err(SyscallError::EINVAL)
+/// 获取文件描述符所指文件所在的文件系统的信息
+/// # Arguments
+/// * `fd` - usize
+/// * `stat` - *mut FsStat
+pub fn syscall_fstatfs(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let stat = args[1] as *mut FsStat;
+    let file = current_process()
+        .fd_manager
+        .fd_table
+        .lock()
+        .get(fd)
+        .and_then(|file| file.clone())
+        .ok_or(SyscallError::EBADF)?;
+    // 管道、socket 等不在目录树中的文件属于各自的伪文件系统
+    let info = match file.get_type() {
+        FileIOType::FileDesc | FileIOType::DirDesc => axfs::api::statfs(&file.get_path())?,
+        FileIOType::Pipe => FileSystemInfo::new(PIPEFS_MAGIC, PSEUDO_FS_BLOCK_SIZE),
+        FileIOType::Socket => FileSystemInfo::new(SOCKFS_MAGIC, PSEUDO_FS_BLOCK_SIZE),
+        _ => FileSystemInfo::new(ANON_INODE_FS_MAGIC, PSEUDO_FS_BLOCK_SIZE),
+    };
+    unsafe {
+        *stat = fs_stat_from_info(&info);
+    }
+    Ok(0)
+}
+
+/// 获取文件状态信息的扩展版本，可以请求需要的字段并得到文件的创建时间
+/// # Arguments
+/// * `dir_fd` - usize
+/// * `path` - *const u8
+/// * `flags` - usize，支持 AT_EMPTY_PATH、AT_SYMLINK_NOFOLLOW
+/// * `mask` - u32，需要的字段，见 [`StatxMask`]
+/// * `statx` - *mut Statx
+pub fn syscall_statx(args: [usize; 6]) -> SyscallResult {
+    let dir_fd = args[0];
+    let path = args[1] as *const u8;
+    let flags = args[2];
+    let mask = StatxMask::from_bits_retain(args[3] as u32);
+    let statx = args[4] as *mut Statx;
+    if flags & !(AT_SYMLINK_NOFOLLOW | AT_NO_AUTOMOUNT | AT_EMPTY_PATH | AT_STATX_SYNC_TYPE) != 0
+        || flags & AT_STATX_SYNC_TYPE == AT_STATX_SYNC_TYPE
+        || mask.contains(StatxMask::RESERVED)
+    {
+        return Err(SyscallError::EINVAL);
+    }
+    if path.is_null() {
+        return Err(SyscallError::EFAULT);
+    }
+    let empty_path = unsafe { raw_ptr_to_ref_str(path) }.is_empty();
+    if empty_path && flags & AT_EMPTY_PATH == 0 {
+        return Err(SyscallError::ENOENT);
+    }
+    if empty_path && dir_fd != AT_FDCWD {
+        let file = current_process()
+            .fd_manager
+            .fd_table
+            .lock()
+            .get(dir_fd)
+            .and_then(|file| file.clone())
+            .ok_or(SyscallError::EBADF)?;
+        // 管道等不在目录树中的文件由其自身给出状态信息
+        let stat = match file.get_type() {
+            FileIOType::FileDesc | FileIOType::DirDesc => None,
+            FileIOType::Stdin | FileIOType::Stdout | FileIOType::Stderr => Some(Kstat {
+                st_mode: 0o20000 | 0o620,
+                st_ino: 1,
+                st_nlink: 1,
+                ..Kstat::default()
+            }),
+            _ => Some(file.get_stat()?),
+        };
+        if let Some(stat) = stat {
+            unsafe {
+                *statx = statx_from_stat(&stat);
+            }
+            return Ok(0);
+        }
+    }
+    let file_path = deal_with_path(dir_fd, Some(path), false).ok_or(SyscallError::EBADF)?;
+    let follow = flags & AT_SYMLINK_NOFOLLOW == 0;
+    let stat = stat_path(&file_path, follow)?;
+    let mut result = statx_from_stat(&stat);
+    if mask.contains(StatxMask::BTIME) {
+        let node = if follow {
+            axfs::api::lookup(file_path.path())
+        } else {
+            axfs::api::lookup_link(file_path.path())
+        };
+        // 只有部分文件系统记录了创建时间
+        if let Some(btime) = node.ok().and_then(|node| node.get_attr().ok()?.btime()) {
+            result.stx_btime = btime.into();
+            result.stx_mask |= StatxMask::BTIME.bits();
+        }
+    }
+    unsafe {
+        *statx = result;
     }
+    Ok(0)
 }
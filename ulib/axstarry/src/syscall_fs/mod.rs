@@ -4,6 +4,8 @@ mod ctype;
 pub mod imp;
 
 use crate::SyscallResult;
+pub use ctype::ns::NsFile;
+pub use ctype::poll_waiter::PollWaiter;
 pub use ctype::FileDesc;
 mod fs_syscall_id;
 pub use fs_syscall_id::FsSyscallId::{self, *};
@@ -32,25 +34,32 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         READV => syscall_readv(args),
         WRITEV => syscall_writev(args),
         FCNTL64 => syscall_fcntl64(args),
+        FLOCK => syscall_flock(args),
         FSTATAT => syscall_fstatat(args),
         STATFS => syscall_statfs(args),
+        FSTATFS => syscall_fstatfs(args),
+        STATX => syscall_statx(args),
         FCHMODAT => syscall_fchmodat(args),
+        FCHOWNAT => syscall_fchownat(args),
+        FCHMOD => syscall_fchmod(args),
+        FCHOWN => syscall_fchown(args),
         FACCESSAT => syscall_faccessat(args),
         LSEEK => syscall_lseek(args),
         PREAD64 => syscall_pread64(args),
         PREADLINKAT => syscall_readlinkat(args),
         PWRITE64 => syscall_pwrite64(args),
         SENDFILE64 => syscall_sendfile64(args),
-        FSYNC => Ok(0),
+        FSYNC => syscall_fsync(args),
         FTRUNCATE64 => {
             syscall_ftruncate64(args)
             // 0
         }
+        FALLOCATE => syscall_fallocate(args),
         IOCTL => syscall_ioctl(args),
-        // 不做处理即可
-        SYNC => Ok(0),
+        SYNC => syscall_sync(),
         COPYFILERANGE => syscall_copyfilerange(args),
         LINKAT => sys_linkat(args),
+        SYMLINKAT => syscall_symlinkat(args),
         UNLINKAT => syscall_unlinkat(args),
         UTIMENSAT => syscall_utimensat(args),
         EPOLL_CREATE => syscall_epoll_create1(args),
@@ -58,6 +67,9 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         EPOLL_WAIT => syscall_epoll_wait(args),
         PPOLL => syscall_ppoll(args),
         PSELECT6 => syscall_pselect6(args),
+        INOTIFY_INIT1 => syscall_inotify_init1(args),
+        INOTIFY_ADD_WATCH => syscall_inotify_add_watch(args),
+        INOTIFY_RM_WATCH => syscall_inotify_rm_watch(args),
 
         #[cfg(target_arch = "x86_64")]
         // eventfd syscall in x86_64 does not support flags, use 0 instead
@@ -65,6 +77,8 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         #[cfg(target_arch = "x86_64")]
         EVENTFD2 => syscall_eventfd(args),
         #[cfg(target_arch = "x86_64")]
+        INOTIFY_INIT => syscall_inotify_init1([0; 6]),
+        #[cfg(target_arch = "x86_64")]
         DUP2 => syscall_dup2(args),
         #[cfg(target_arch = "x86_64")]
         LSTAT => syscall_lstat(args),
@@ -91,6 +105,8 @@ pub fn fs_syscall(syscall_id: fs_syscall_id::FsSyscallId, args: [usize; 6]) -> S
         #[cfg(target_arch = "x86_64")]
         READLINK => syscall_readlink(args),
         #[cfg(target_arch = "x86_64")]
+        SYMLINK => syscall_symlink(args),
+        #[cfg(target_arch = "x86_64")]
         CREAT => Err(axerrno::LinuxError::EPERM),
     }
 }
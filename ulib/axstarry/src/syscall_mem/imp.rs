@@ -1,9 +1,11 @@
 use crate::{syscall_fs::FileDesc, MMAPFlags, SyscallError, SyscallResult, MMAPPROT};
 extern crate alloc;
 
+use axerrno::AxError;
 use axhal::{arch::flush_tlb, mem::VirtAddr, paging::MappingFlags};
-use axmem::MemorySet;
+use axmem::{MemorySet, SharedMem};
 
+use axprocess::cred::{Cap, IpcPerm};
 use axprocess::current_process;
 use bitflags::bitflags;
 
@@ -72,21 +74,25 @@ pub fn syscall_mmap(args: [usize; 6]) -> SyscallResult {
         if fd >= process.fd_manager.fd_table.lock().len() as i32 || fd < 0 {
             return Err(SyscallError::EINVAL);
         }
-        let file = match &process.fd_manager.fd_table.lock()[fd as usize] {
+        let (file, path) = match &process.fd_manager.fd_table.lock()[fd as usize] {
             // 文件描述符表里面存的是文件描述符，这很合理罢
-            Some(file) => alloc::boxed::Box::new(
-                file.as_any()
+            Some(file) => {
+                let file_desc = file
+                    .as_any()
                     .downcast_ref::<FileDesc>()
-                    .expect("Try to mmap with a non-file backend")
-                    .file
-                    .lock()
-                    .clone(),
-            ),
+                    .expect("Try to mmap with a non-file backend");
+                (
+                    alloc::boxed::Box::new(file_desc.file.lock().clone()),
+                    file_desc.path.clone(),
+                )
+            }
             // fd not found
             None => return Err(SyscallError::EINVAL),
         };
 
-        let backend = MemBackend::new(file, offset as u64);
+        let backend = MemBackend::new(file, offset as u64)
+            .with_path(path)
+            .with_shared(flags.contains(MMAPFlags::MAP_SHARED));
         process
             .memory_set
             .lock()
@@ -107,10 +113,55 @@ pub fn syscall_munmap(args: [usize; 6]) -> SyscallResult {
     let len = args[1];
     let process = current_process();
     process.memory_set.lock().munmap(start.into(), len);
+    process.unlock_memory(start, len);
     flush_tlb(None);
     Ok(0)
 }
 
+/// mlock2 的标志位：只锁定，不立即分配物理页
+const MLOCK_ONFAULT: usize = 1;
+
+/// 锁定一段内存并在返回前分配好物理页
+fn lock_memory(start: usize, len: usize, populate: bool) -> SyscallResult {
+    current_process()
+        .lock_memory(start, len, populate)
+        .map_err(|err| match err {
+            AxError::PermissionDenied => SyscallError::EPERM,
+            _ => SyscallError::ENOMEM,
+        })?;
+    Ok(0)
+}
+
+/// 锁定一段内存
+/// # Arguments
+/// * `start` - usize
+/// * `len` - usize
+pub fn syscall_mlock(args: [usize; 6]) -> SyscallResult {
+    lock_memory(args[0], args[1], true)
+}
+
+/// 锁定一段内存，可以指定缺页时才分配物理页
+/// # Arguments
+/// * `start` - usize
+/// * `len` - usize
+/// * `flags` - usize, 仅支持 MLOCK_ONFAULT
+pub fn syscall_mlock2(args: [usize; 6]) -> SyscallResult {
+    let flags = args[2];
+    if flags & !MLOCK_ONFAULT != 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    lock_memory(args[0], args[1], flags & MLOCK_ONFAULT == 0)
+}
+
+/// 解除一段内存的锁定
+/// # Arguments
+/// * `start` - usize
+/// * `len` - usize
+pub fn syscall_munlock(args: [usize; 6]) -> SyscallResult {
+    current_process().unlock_memory(args[0], args[1]);
+    Ok(0)
+}
+
 /// # Arguments
 /// * `start` - usize
 /// * `len` - usize
@@ -166,6 +217,7 @@ pub fn syscall_shmget(args: [usize; 6]) -> SyscallResult {
     let flags = args[2] as i32;
 
     let pid = current_process().pid();
+    let cred = current_process().cred();
 
     // 9 bits for permission
     let mode: u16 = (flags as u16) & ((1 << 10) - 1);
@@ -176,7 +228,9 @@ pub fn syscall_shmget(args: [usize; 6]) -> SyscallResult {
     };
 
     if key == IPC_PRIVATE {
-        let Ok((shmid, mem)) = MemorySet::create_shared_mem(key, size, pid, 0, 0, mode) else {
+        let Ok((shmid, mem)) =
+            MemorySet::create_shared_mem(key, size, pid, cred.euid, cred.egid, mode)
+        else {
             return Err(SyscallError::EINVAL);
         };
 
@@ -192,14 +246,22 @@ pub fn syscall_shmget(args: [usize; 6]) -> SyscallResult {
         match key_map.get(&key) {
             Some(shmid) => {
                 if flags.contains(ShmFlags::IPC_CREAT) && flags.contains(ShmFlags::IPC_EXCL) {
-                    Err(SyscallError::EEXIST)
-                } else {
-                    Ok(*shmid as isize)
+                    return Err(SyscallError::EEXIST);
                 }
+                // 请求的权限位必须是已有共享内存权限的子集
+                if let Some(mem) = MemorySet::get_shared_mem(*shmid) {
+                    let mode = mode as u32;
+                    let want = Cap::from_mode_bits(((mode >> 6) | (mode >> 3) | mode) & 0o7);
+                    if !cred.ipc_access(&shm_perm(&mem), want) {
+                        return Err(SyscallError::EACCES);
+                    }
+                }
+                Ok(*shmid as isize)
             }
             None => {
                 if flags.contains(ShmFlags::IPC_CREAT) {
-                    let Ok((shmid, mem)) = MemorySet::create_shared_mem(key, size, pid, 0, 0, mode)
+                    let Ok((shmid, mem)) =
+                        MemorySet::create_shared_mem(key, size, pid, cred.euid, cred.egid, mode)
                     else {
                         return Err(SyscallError::EINVAL);
                     };
@@ -215,6 +277,17 @@ pub fn syscall_shmget(args: [usize; 6]) -> SyscallResult {
     }
 }
 
+/// 共享内存的属主与权限
+fn shm_perm(mem: &SharedMem) -> IpcPerm {
+    IpcPerm {
+        uid: mem.info.uid(),
+        gid: mem.info.gid(),
+        cuid: mem.info.cuid(),
+        cgid: mem.info.cgid(),
+        mode: mem.info.mode() as u32,
+    }
+}
+
 bitflags! {
     #[derive(Debug)]
     struct ShmAtFlags: i32 {
@@ -247,6 +320,14 @@ pub fn syscall_shmat(args: [usize; 6]) -> SyscallResult {
     };
     let size = mem.size();
 
+    let mut want = Cap::READ;
+    if !flags.contains(ShmAtFlags::SHM_RDONLY) {
+        want |= Cap::WRITE;
+    }
+    if !process.cred().ipc_access(&shm_perm(&mem), want) {
+        return Err(SyscallError::EACCES);
+    }
+
     let addr = if addr == 0 {
         match memory.find_free_area(addr.into(), size) {
             Some(addr) => addr,
@@ -21,6 +21,9 @@ pub enum MemSyscallId {
     MMAP = 222,
     MSYNC = 227,
     MPROTECT = 226,
+    MLOCK = 228,
+    MUNLOCK = 229,
+    MLOCK2 = 284,
     MEMBARRIER = 283,
 }
 }
@@ -41,6 +44,9 @@ numeric_enum_macro::numeric_enum! {
         MMAP = 9,
         MSYNC = 26,
         MPROTECT = 10,
+        MLOCK = 149,
+        MUNLOCK = 150,
+        MLOCK2 = 325,
         MEMBARRIER = 324,
     }
 }
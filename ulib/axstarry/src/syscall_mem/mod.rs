@@ -17,6 +17,9 @@ pub fn mem_syscall(syscall_id: mem_syscall_id::MemSyscallId, args: [usize; 6]) -
         MMAP => syscall_mmap(args),
         MSYNC => syscall_msync(args),
         MPROTECT => syscall_mprotect(args),
+        MLOCK => syscall_mlock(args),
+        MUNLOCK => syscall_munlock(args),
+        MLOCK2 => syscall_mlock2(args),
         MEMBARRIER => Ok(0),
         SHMGET => syscall_shmget(args),
         SHMCTL => Ok(0),
@@ -1,4 +1,5 @@
 extern crate alloc;
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::{
     mem::size_of,
@@ -8,11 +9,12 @@ use core::{
 
 use alloc::string::String;
 use axerrno::{AxError, AxResult};
-use axfs::api::{FileIO, FileIOType, OpenFlags, Read, Write};
+use axfs::api::{FileIO, FileIOType, OpenFlags, PollNotifier, Read, Write};
 use axprocess::current_process;
 use axlog::{debug, warn};
 use axnet::{
-    from_core_sockaddr, into_core_sockaddr, poll_interfaces, IpAddr, SocketAddr, TcpSocket, UdpSocket
+    from_core_sockaddr, into_core_sockaddr, poll_interfaces, IpAddr, SocketAddr, SocketWaker,
+    TcpSocket, UdpSocket,
 };
 use axnet::NetlinkSocket;
 use axsync::Mutex;
@@ -452,6 +454,8 @@ pub struct Socket {
     send_buf_size: AtomicU64,
     recv_buf_size: AtomicU64,
     congestion: Mutex<String>,
+    /// 就绪通知，由协议栈在该 socket 的收发与状态变化时触发
+    notifier: Arc<PollNotifier>,
 }
 
 /// The transport protocol used by the socket
@@ -525,9 +529,11 @@ impl Socket {
             Domain::AF_INET => {
                 match socket_type {
                     SocketType::SOCK_STREAM | SocketType::SOCK_SEQPACKET => {
-                        SocketInner::Tcp(TcpSocket::new())
+                        SocketInner::Tcp(TcpSocket::new_in(current_process().net_ns()))
+                    }
+                    SocketType::SOCK_DGRAM => {
+                        SocketInner::Udp(UdpSocket::new_in(current_process().net_ns()))
                     }
-                    SocketType::SOCK_DGRAM => SocketInner::Udp(UdpSocket::new()),
                     _ => unimplemented!(),
                 }
             }
@@ -540,6 +546,8 @@ impl Socket {
                 }
             }
         };
+        let notifier = Arc::new(PollNotifier::new());
+        watch_inner(&inner, &notifier);
         Self {
             domain,
             socket_type,
@@ -550,6 +558,7 @@ impl Socket {
             send_buf_size: AtomicU64::new(64 * 1024),
             recv_buf_size: AtomicU64::new(64 * 1024),
             congestion: Mutex::new(String::from("reno")),
+            notifier,
         }
     }
 
@@ -660,18 +669,22 @@ impl Socket {
             SocketInner::Netlink(_) => unimplemented!("accept on netlink socket"),
         };
         let addr = new_socket.peer_addr()?;
+        let new_inner = SocketInner::Tcp(new_socket);
+        let notifier = Arc::new(PollNotifier::new());
+        watch_inner(&new_inner, &notifier);
 
         Ok((
             Self {
                 domain: self.domain.clone(),
                 socket_type: self.socket_type.clone(),
-                inner: Mutex::new(SocketInner::Tcp(new_socket)),
+                inner: Mutex::new(new_inner),
                 close_exec: false,
                 recv_timeout: Mutex::new(None),
                 dont_route: false,
                 send_buf_size: AtomicU64::new(64 * 1024),
                 recv_buf_size: AtomicU64::new(64 * 1024),
                 congestion: Mutex::new(String::from("reno")),
+                notifier,
             },
             from_core_sockaddr(addr).into(),
         ))
@@ -680,11 +693,14 @@ impl Socket {
     /// Connect to the given address.
     pub fn connect(&self, addr: SocketAddr) -> AxResult {
         let inner = self.inner.lock();
-        match &*inner {
+        let ret = match &*inner {
             SocketInner::Tcp(s) => s.connect(into_core_sockaddr(addr.into())),
             SocketInner::Udp(s) => s.connect(into_core_sockaddr(addr.into())),
             SocketInner::Netlink(_) => unimplemented!("connect on netlink socket"),
-        }
+        };
+        drop(inner);
+        self.notifier.notify();
+        ret
     }
 
     #[allow(unused)]
@@ -701,17 +717,21 @@ impl Socket {
     /// let the socket send data to the given address
     pub fn sendto(&self, buf: &[u8], addr: SocketAddr) -> AxResult<usize> {
         let inner = self.inner.lock();
-        match &*inner {
+        let ret = match &*inner {
             SocketInner::Tcp(s) => s.send(buf),
             SocketInner::Udp(s) => s.send_to(buf, into_core_sockaddr(addr.into())),
             SocketInner::Netlink(_) => unimplemented!("sendto on netlink socket"),
-        }
+        };
+        drop(inner);
+        // 立即发出数据，使对端（如 loopback 上的）被唤醒
+        poll_interfaces();
+        ret
     }
 
     /// let the socket receive data and write it to the given buffer
     pub fn recv_from(&self, buf: &mut [u8]) -> AxResult<(usize, SocketAddr)> {
         let inner = self.inner.lock();
-        match &*inner {
+        let ret = match &*inner {
             SocketInner::Tcp(s) => {
                 let addr = s.peer_addr()?;
 
@@ -739,7 +759,9 @@ impl Socket {
                 };
                 s.recv(buf).map(|len| (len, idel_addr))
             },
-        }
+        };
+        drop(inner);
+        ret
     }
 
     /// For shutdown(fd, SHUT_WR)
@@ -752,6 +774,8 @@ impl Socket {
             SocketInner::Tcp(s) => s.close(),
             SocketInner::Netlink(_) => unimplemented!("shutdown on netlink socket"),
         };
+        drop(inner);
+        self.notifier.notify();
     }
 
     /// For shutdown(fd, SHUT_RDWR)
@@ -768,26 +792,34 @@ impl Socket {
             }),
             SocketInner::Netlink(_) => unimplemented!("abort on netlink socket"),
         }
+        drop(inner);
+        self.notifier.notify();
     }
 }
 
 impl FileIO for Socket {
     fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
         let mut inner = self.inner.lock();
-        match &mut *inner {
+        let ret = match &mut *inner {
             SocketInner::Tcp(s) => s.read(buf),
             SocketInner::Udp(s) => s.read(buf),
             SocketInner::Netlink(_) => unimplemented!("read on netlink socket"),
-        }
+        };
+        drop(inner);
+        ret
     }
 
     fn write(&self, buf: &[u8]) -> AxResult<usize> {
         let mut inner = self.inner.lock();
-        match &mut *inner {
+        let ret = match &mut *inner {
             SocketInner::Tcp(s) => s.write(buf),
             SocketInner::Udp(s) => s.write(buf),
             SocketInner::Netlink(_) => unimplemented!("write on netlink socket"),
-        }
+        };
+        drop(inner);
+        // 立即发出数据，使对端（如 loopback 上的）被唤醒
+        poll_interfaces();
+        ret
     }
 
     fn flush(&self) -> AxResult {
@@ -849,6 +881,22 @@ impl FileIO for Socket {
     fn ready_to_write(&self) -> bool {
         self.writable()
     }
+
+    fn poll_notifier(&self) -> Option<&PollNotifier> {
+        Some(&self.notifier)
+    }
+}
+
+/// 在协议栈中登记 socket 的唤醒者，其就绪状态变化时通知 `notifier`
+fn watch_inner(inner: &SocketInner, notifier: &Arc<PollNotifier>) {
+    let notifier = notifier.clone();
+    let waker: SocketWaker = Arc::new(move || notifier.notify());
+    match inner {
+        SocketInner::Tcp(s) => s.set_waker(waker),
+        SocketInner::Udp(s) => s.set_waker(waker),
+        // netlink socket 的数据在 sendto 时同步生成，不需要等待
+        SocketInner::Netlink(_) => {}
+    }
 }
 
 /// Turn a socket address buffer into a SocketAddr
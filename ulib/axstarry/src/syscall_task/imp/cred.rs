@@ -0,0 +1,515 @@
+//! 支持与用户、用户组及特权相关的 syscall
+extern crate alloc;
+use alloc::vec::Vec;
+use axhal::mem::VirtAddr;
+use axprocess::{
+    cred::{Credentials, Privileges, NGROUPS_MAX},
+    current_process, PID2PC,
+};
+
+use crate::{SyscallError, SyscallResult};
+
+/// 表示不修改对应 id 的参数值
+const NO_CHANGE: u32 = u32::MAX;
+
+/// capget/capset 的第一版接口，特权集合为 32 位
+const LINUX_CAPABILITY_VERSION_1: u32 = 0x1998_0330;
+/// capget/capset 的第二版接口，特权集合为 64 位
+const LINUX_CAPABILITY_VERSION_2: u32 = 0x2007_1026;
+/// capget/capset 的第三版接口，与第二版格式相同
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// capget/capset 的头部
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CapUserHeader {
+    version: u32,
+    pid: i32,
+}
+
+/// capget/capset 的数据，64 位的集合分为两个结构体，先低 32 位后高 32 位
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+/// 向用户地址写入一个值
+fn write_user<T>(ptr: *mut T, value: T) -> Result<(), SyscallError> {
+    if ptr.is_null()
+        || current_process()
+            .manual_alloc_type_for_lazy(ptr as *const T)
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    unsafe {
+        *ptr = value;
+    }
+    Ok(())
+}
+
+/// 从用户地址读取一个值
+fn read_user<T: Copy>(ptr: *const T) -> Result<T, SyscallError> {
+    if ptr.is_null() || current_process().manual_alloc_type_for_lazy(ptr).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    Ok(unsafe { *ptr })
+}
+
+/// 在持有锁的情况下修改当前进程的身份，成功后按照新旧身份调整特权
+fn update_cred(
+    f: impl FnOnce(&mut Credentials) -> Result<(), SyscallError>,
+) -> Result<(), SyscallError> {
+    let process = current_process();
+    let mut cred = process.cred.lock();
+    let old = cred.clone();
+    let mut new = cred.clone();
+    f(&mut new)?;
+    new.fixup_privileges(&old);
+    *cred = new;
+    Ok(())
+}
+
+/// 获取真实用户号
+pub fn syscall_getuid() -> SyscallResult {
+    Ok(current_process().cred().uid as isize)
+}
+
+/// 获取有效用户号，即以哪个用户的权限进行检查
+pub fn syscall_geteuid() -> SyscallResult {
+    Ok(current_process().cred().euid as isize)
+}
+
+/// 获取真实组号
+pub fn syscall_getgid() -> SyscallResult {
+    Ok(current_process().cred().gid as isize)
+}
+
+/// 获取有效组号
+pub fn syscall_getegid() -> SyscallResult {
+    Ok(current_process().cred().egid as isize)
+}
+
+/// 设置用户号
+///
+/// 拥有 SETUID 特权时同时设置真实、有效与保存的用户号，否则只能把有效用户号设为真实或保存的用户号
+/// # Arguments
+/// * `uid` - u32
+pub fn syscall_setuid(args: [usize; 6]) -> SyscallResult {
+    let uid = args[0] as u32;
+    if uid == NO_CHANGE {
+        return Err(SyscallError::EINVAL);
+    }
+    update_cred(|cred| {
+        if cred.capable(Privileges::SETUID) {
+            cred.uid = uid;
+            cred.suid = uid;
+        } else if uid != cred.uid && uid != cred.suid {
+            return Err(SyscallError::EPERM);
+        }
+        cred.euid = uid;
+        cred.fsuid = uid;
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// 设置组号，规则与 setuid 相同，所需的特权为 SETGID
+/// # Arguments
+/// * `gid` - u32
+pub fn syscall_setgid(args: [usize; 6]) -> SyscallResult {
+    let gid = args[0] as u32;
+    if gid == NO_CHANGE {
+        return Err(SyscallError::EINVAL);
+    }
+    update_cred(|cred| {
+        if cred.capable(Privileges::SETGID) {
+            cred.gid = gid;
+            cred.sgid = gid;
+        } else if gid != cred.gid && gid != cred.sgid {
+            return Err(SyscallError::EPERM);
+        }
+        cred.egid = gid;
+        cred.fsgid = gid;
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// 设置真实与有效用户号，为 -1 的一项保持不变
+///
+/// 没有 SETUID 特权时，真实用户号只能设为原来的真实或有效用户号，有效用户号只能设为原来的真实、有效或保存的用户号。
+/// 若设置了真实用户号，或有效用户号被设为与原真实用户号不同的值，保存的用户号变为新的有效用户号
+/// # Arguments
+/// * `ruid` - u32
+/// * `euid` - u32
+pub fn syscall_setreuid(args: [usize; 6]) -> SyscallResult {
+    let (ruid, euid) = (args[0] as u32, args[1] as u32);
+    update_cred(|cred| {
+        let privileged = cred.capable(Privileges::SETUID);
+        if ruid != NO_CHANGE && !privileged && ruid != cred.uid && ruid != cred.euid {
+            return Err(SyscallError::EPERM);
+        }
+        if euid != NO_CHANGE
+            && !privileged
+            && euid != cred.uid
+            && euid != cred.euid
+            && euid != cred.suid
+        {
+            return Err(SyscallError::EPERM);
+        }
+        let old_uid = cred.uid;
+        if ruid != NO_CHANGE {
+            cred.uid = ruid;
+        }
+        if euid != NO_CHANGE {
+            cred.euid = euid;
+        }
+        if ruid != NO_CHANGE || (euid != NO_CHANGE && euid != old_uid) {
+            cred.suid = cred.euid;
+        }
+        cred.fsuid = cred.euid;
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// 设置真实与有效组号，规则与 setreuid 相同，所需的特权为 SETGID
+/// # Arguments
+/// * `rgid` - u32
+/// * `egid` - u32
+pub fn syscall_setregid(args: [usize; 6]) -> SyscallResult {
+    let (rgid, egid) = (args[0] as u32, args[1] as u32);
+    update_cred(|cred| {
+        let privileged = cred.capable(Privileges::SETGID);
+        if rgid != NO_CHANGE && !privileged && rgid != cred.gid && rgid != cred.egid {
+            return Err(SyscallError::EPERM);
+        }
+        if egid != NO_CHANGE
+            && !privileged
+            && egid != cred.gid
+            && egid != cred.egid
+            && egid != cred.sgid
+        {
+            return Err(SyscallError::EPERM);
+        }
+        let old_gid = cred.gid;
+        if rgid != NO_CHANGE {
+            cred.gid = rgid;
+        }
+        if egid != NO_CHANGE {
+            cred.egid = egid;
+        }
+        if rgid != NO_CHANGE || (egid != NO_CHANGE && egid != old_gid) {
+            cred.sgid = cred.egid;
+        }
+        cred.fsgid = cred.egid;
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// 设置真实、有效与保存的用户号，为 -1 的一项保持不变
+///
+/// 没有 SETUID 特权时，每一项只能设为原来的真实、有效或保存的用户号之一
+/// # Arguments
+/// * `ruid` - u32
+/// * `euid` - u32
+/// * `suid` - u32
+pub fn syscall_setresuid(args: [usize; 6]) -> SyscallResult {
+    let ids = [args[0] as u32, args[1] as u32, args[2] as u32];
+    update_cred(|cred| {
+        let allowed = [cred.uid, cred.euid, cred.suid];
+        if !cred.capable(Privileges::SETUID)
+            && ids
+                .iter()
+                .any(|id| *id != NO_CHANGE && !allowed.contains(id))
+        {
+            return Err(SyscallError::EPERM);
+        }
+        for (dst, id) in [&mut cred.uid, &mut cred.euid, &mut cred.suid]
+            .into_iter()
+            .zip(ids)
+        {
+            if id != NO_CHANGE {
+                *dst = id;
+            }
+        }
+        cred.fsuid = cred.euid;
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// 设置真实、有效与保存的组号，规则与 setresuid 相同，所需的特权为 SETGID
+/// # Arguments
+/// * `rgid` - u32
+/// * `egid` - u32
+/// * `sgid` - u32
+pub fn syscall_setresgid(args: [usize; 6]) -> SyscallResult {
+    let ids = [args[0] as u32, args[1] as u32, args[2] as u32];
+    update_cred(|cred| {
+        let allowed = [cred.gid, cred.egid, cred.sgid];
+        if !cred.capable(Privileges::SETGID)
+            && ids
+                .iter()
+                .any(|id| *id != NO_CHANGE && !allowed.contains(id))
+        {
+            return Err(SyscallError::EPERM);
+        }
+        for (dst, id) in [&mut cred.gid, &mut cred.egid, &mut cred.sgid]
+            .into_iter()
+            .zip(ids)
+        {
+            if id != NO_CHANGE {
+                *dst = id;
+            }
+        }
+        cred.fsgid = cred.egid;
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// 获取真实、有效与保存的用户号
+/// # Arguments
+/// * `ruid` - *mut u32
+/// * `euid` - *mut u32
+/// * `suid` - *mut u32
+pub fn syscall_getresuid(args: [usize; 6]) -> SyscallResult {
+    let cred = current_process().cred();
+    write_user(args[0] as *mut u32, cred.uid)?;
+    write_user(args[1] as *mut u32, cred.euid)?;
+    write_user(args[2] as *mut u32, cred.suid)?;
+    Ok(0)
+}
+
+/// 获取真实、有效与保存的组号
+/// # Arguments
+/// * `rgid` - *mut u32
+/// * `egid` - *mut u32
+/// * `sgid` - *mut u32
+pub fn syscall_getresgid(args: [usize; 6]) -> SyscallResult {
+    let cred = current_process().cred();
+    write_user(args[0] as *mut u32, cred.gid)?;
+    write_user(args[1] as *mut u32, cred.egid)?;
+    write_user(args[2] as *mut u32, cred.sgid)?;
+    Ok(0)
+}
+
+/// 设置文件系统用户号，总是返回原来的值
+///
+/// 没有 SETUID 特权时只能设为真实、有效、保存或原来的文件系统用户号之一，否则不做修改
+/// # Arguments
+/// * `fsuid` - u32
+pub fn syscall_setfsuid(args: [usize; 6]) -> SyscallResult {
+    let fsuid = args[0] as u32;
+    let old = current_process().cred().fsuid;
+    let _ = update_cred(|cred| {
+        if cred.capable(Privileges::SETUID)
+            || [cred.uid, cred.euid, cred.suid, cred.fsuid].contains(&fsuid)
+        {
+            cred.fsuid = fsuid;
+            Ok(())
+        } else {
+            Err(SyscallError::EPERM)
+        }
+    });
+    Ok(old as isize)
+}
+
+/// 设置文件系统组号，规则与 setfsuid 相同，所需的特权为 SETGID
+/// # Arguments
+/// * `fsgid` - u32
+pub fn syscall_setfsgid(args: [usize; 6]) -> SyscallResult {
+    let fsgid = args[0] as u32;
+    let old = current_process().cred().fsgid;
+    let _ = update_cred(|cred| {
+        if cred.capable(Privileges::SETGID)
+            || [cred.gid, cred.egid, cred.sgid, cred.fsgid].contains(&fsgid)
+        {
+            cred.fsgid = fsgid;
+            Ok(())
+        } else {
+            Err(SyscallError::EPERM)
+        }
+    });
+    Ok(old as isize)
+}
+
+/// 获取附加组
+///
+/// size 为 0 时只返回附加组的数目，否则 size 不能小于附加组的数目
+/// # Arguments
+/// * `size` - i32
+/// * `list` - *mut u32
+pub fn syscall_getgroups(args: [usize; 6]) -> SyscallResult {
+    let size = args[0] as i32;
+    let list = args[1] as *mut u32;
+    let groups = current_process().cred().groups;
+    if size < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    if size == 0 {
+        return Ok(groups.len() as isize);
+    }
+    if (size as usize) < groups.len() {
+        return Err(SyscallError::EINVAL);
+    }
+    if groups.is_empty() {
+        return Ok(0);
+    }
+    let process = current_process();
+    if process
+        .manual_alloc_range_for_lazy(
+            VirtAddr::from(list as usize),
+            VirtAddr::from(list as usize + groups.len() * core::mem::size_of::<u32>()),
+        )
+        .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let dst = unsafe { core::slice::from_raw_parts_mut(list, groups.len()) };
+    dst.copy_from_slice(&groups);
+    Ok(groups.len() as isize)
+}
+
+/// 设置附加组，需要 SETGID 特权
+/// # Arguments
+/// * `size` - usize
+/// * `list` - *const u32
+pub fn syscall_setgroups(args: [usize; 6]) -> SyscallResult {
+    let size = args[0];
+    let list = args[1] as *const u32;
+    if size > NGROUPS_MAX {
+        return Err(SyscallError::EINVAL);
+    }
+    let mut groups = Vec::new();
+    if size > 0 {
+        if list.is_null()
+            || current_process()
+                .manual_alloc_range_for_lazy(
+                    VirtAddr::from(list as usize),
+                    VirtAddr::from(list as usize + size * core::mem::size_of::<u32>()),
+                )
+                .is_err()
+        {
+            return Err(SyscallError::EFAULT);
+        }
+        groups.extend_from_slice(unsafe { core::slice::from_raw_parts(list, size) });
+    }
+    groups.sort_unstable();
+    groups.dedup();
+    update_cred(|cred| {
+        if !cred.capable(Privileges::SETGID) {
+            return Err(SyscallError::EPERM);
+        }
+        cred.groups = groups;
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// 检查 capget/capset 的版本，返回数据结构体的个数
+///
+/// 版本不被支持时，把首选的版本写回头部并返回 EINVAL
+fn cap_data_count(header: *mut CapUserHeader) -> Result<(CapUserHeader, usize), SyscallError> {
+    let hdr: CapUserHeader = read_user(header)?;
+    match hdr.version {
+        LINUX_CAPABILITY_VERSION_1 => Ok((hdr, 1)),
+        LINUX_CAPABILITY_VERSION_2 | LINUX_CAPABILITY_VERSION_3 => Ok((hdr, 2)),
+        _ => {
+            write_user(
+                header,
+                CapUserHeader {
+                    version: LINUX_CAPABILITY_VERSION_3,
+                    ..hdr
+                },
+            )?;
+            Err(SyscallError::EINVAL)
+        }
+    }
+}
+
+/// 获取进程的特权集合
+/// # Arguments
+/// * `header` - *mut CapUserHeader
+/// * `data` - *mut CapUserData
+pub fn syscall_capget(args: [usize; 6]) -> SyscallResult {
+    let header = args[0] as *mut CapUserHeader;
+    let data = args[1] as *mut CapUserData;
+    let (hdr, count) = match cap_data_count(header) {
+        Ok(result) => result,
+        // 只探测版本时不算错误
+        Err(SyscallError::EINVAL) if data.is_null() => return Ok(0),
+        Err(err) => return Err(err),
+    };
+    if data.is_null() {
+        return Ok(0);
+    }
+    let cred = if hdr.pid == 0 {
+        current_process().cred()
+    } else if hdr.pid < 0 {
+        return Err(SyscallError::EINVAL);
+    } else {
+        PID2PC
+            .lock()
+            .get(&(hdr.pid as u64))
+            .ok_or(SyscallError::ESRCH)?
+            .cred()
+    };
+    for i in 0..count {
+        let shift = i * 32;
+        let entry = CapUserData {
+            effective: (cred.cap_effective.bits() >> shift) as u32,
+            permitted: (cred.cap_permitted.bits() >> shift) as u32,
+            inheritable: (cred.cap_inheritable.bits() >> shift) as u32,
+        };
+        write_user(unsafe { data.add(i) }, entry)?;
+    }
+    Ok(0)
+}
+
+/// 设置当前进程的特权集合
+///
+/// 生效的集合必须是允许的集合的子集，允许的集合不能扩大；
+/// 没有 SETPCAP 特权时，可继承的集合只能在原来可继承与允许的集合的并集内
+/// # Arguments
+/// * `header` - *mut CapUserHeader
+/// * `data` - *const CapUserData
+pub fn syscall_capset(args: [usize; 6]) -> SyscallResult {
+    let header = args[0] as *mut CapUserHeader;
+    let data = args[1] as *const CapUserData;
+    let (hdr, count) = cap_data_count(header)?;
+    let process = current_process();
+    if hdr.pid != 0 && hdr.pid as u64 != process.pid() {
+        return Err(SyscallError::EPERM);
+    }
+    let (mut effective, mut permitted, mut inheritable) = (0u64, 0u64, 0u64);
+    for i in 0..count {
+        let entry: CapUserData = read_user(unsafe { data.add(i) })?;
+        let shift = i * 32;
+        effective |= (entry.effective as u64) << shift;
+        permitted |= (entry.permitted as u64) << shift;
+        inheritable |= (entry.inheritable as u64) << shift;
+    }
+    let effective = Privileges::from_bits_truncate(effective);
+    let permitted = Privileges::from_bits_truncate(permitted);
+    let inheritable = Privileges::from_bits_truncate(inheritable);
+    let mut cred = process.cred.lock();
+    if !cred.capable(Privileges::SETPCAP)
+        && !(cred.cap_inheritable | cred.cap_permitted).contains(inheritable)
+    {
+        return Err(SyscallError::EPERM);
+    }
+    if !cred.cap_permitted.contains(permitted) || !permitted.contains(effective) {
+        return Err(SyscallError::EPERM);
+    }
+    cred.cap_effective = effective;
+    cred.cap_permitted = permitted;
+    cred.cap_inheritable = inheritable;
+    Ok(0)
+}
@@ -1,21 +1,41 @@
+#[cfg(feature = "signal")]
+mod ptrace;
+
+#[cfg(feature = "signal")]
+mod seccomp;
+
 #[cfg(feature = "signal")]
 mod signal;
 
 #[cfg(feature = "futex")]
 mod futex;
 
+mod cred;
+
+mod namespace;
+
 mod schedule;
 
 mod task;
 
 mod utils;
 
+#[cfg(feature = "signal")]
+pub use ptrace::*;
+
+#[cfg(feature = "signal")]
+pub use seccomp::*;
+
 #[cfg(feature = "signal")]
 pub use signal::*;
 
 #[cfg(feature = "futex")]
 pub use futex::*;
 
+pub use cred::*;
+
+pub use namespace::*;
+
 pub use schedule::*;
 
 pub use task::*;
@@ -0,0 +1,155 @@
+//! 支持命名空间相关的 syscall
+extern crate alloc;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use axerrno::AxError;
+use axhal::mem::VirtAddr;
+use axprocess::{
+    cred::Privileges, current_process, flags::CloneFlags, namespace::NsRef, pidfd::PidFd,
+};
+
+use crate::syscall_fs::NsFile;
+use crate::{SyscallError, SyscallResult};
+
+/// 主机名与域名的最大长度
+const HOST_NAME_MAX: usize = 64;
+
+/// 可以通过 setns 进入的命名空间
+const SETNS_FLAGS: [CloneFlags; 4] = [
+    CloneFlags::CLONE_NEWPID,
+    CloneFlags::CLONE_NEWNS,
+    CloneFlags::CLONE_NEWUTS,
+    CloneFlags::CLONE_NEWNET,
+];
+
+fn namespace_error(err: AxError) -> SyscallError {
+    match err {
+        AxError::PermissionDenied => SyscallError::EPERM,
+        AxError::NoMemory => SyscallError::ENOMEM,
+        // PID 命名空间嵌套过深
+        AxError::StorageFull => SyscallError::ENOSPC,
+        _ => SyscallError::EINVAL,
+    }
+}
+
+/// 让当前进程离开部分命名空间，进入新建的命名空间
+///
+/// 进程之间本来就不共享文件描述符表与文件系统信息，`CLONE_FILES`、`CLONE_FS` 等标志无需处理
+/// # Arguments
+/// * `flags` - usize，CLONE_NEW* 等 clone 标志
+pub fn syscall_unshare(args: [usize; 6]) -> SyscallResult {
+    let flags = CloneFlags::from_bits(args[0] as u32).ok_or(SyscallError::EINVAL)?;
+    let allowed = CloneFlags::CLONE_FILES
+        | CloneFlags::CLONE_FS
+        | CloneFlags::CLONE_SYSVSEM
+        | CloneFlags::CLONE_THREAD
+        | CloneFlags::CLONE_SIGHAND
+        | CloneFlags::CLONE_VM
+        | CloneFlags::CLONE_NEWNS
+        | CloneFlags::CLONE_NEWCGROUP
+        | CloneFlags::CLONE_NEWUTS
+        | CloneFlags::CLONE_NEWIPC
+        | CloneFlags::CLONE_NEWUSER
+        | CloneFlags::CLONE_NEWPID
+        | CloneFlags::CLONE_NEWNET
+        | CloneFlags::CLONE_NEWTIME;
+    if !allowed.contains(flags) {
+        return Err(SyscallError::EINVAL);
+    }
+    current_process()
+        .unshare(flags)
+        .map(|_| 0)
+        .map_err(namespace_error)
+}
+
+/// 让当前进程进入 fd 指向的命名空间
+///
+/// fd 可以是 `/proc/<pid>/ns` 下的文件，此时 nstype 为 0 或与文件的类型相同；
+/// 也可以是 pidfd，此时进入 nstype 指定的目标进程的各个命名空间
+/// # Arguments
+/// * `fd` - usize
+/// * `nstype` - usize，CLONE_NEW* 标志
+pub fn syscall_setns(args: [usize; 6]) -> SyscallResult {
+    let fd = args[0];
+    let nstype = CloneFlags::from_bits(args[1] as u32).ok_or(SyscallError::EINVAL)?;
+    let process = current_process();
+    let file = match process.fd_manager.fd_table.lock().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(SyscallError::EBADF),
+    };
+    let targets: Vec<NsRef> = if let Some(ns_file) = file.as_any().downcast_ref::<NsFile>() {
+        if !nstype.is_empty() && nstype != ns_file.ns().flag() {
+            return Err(SyscallError::EINVAL);
+        }
+        vec![ns_file.ns().clone()]
+    } else if let Some(pidfd) = file.as_any().downcast_ref::<PidFd>() {
+        let supported = SETNS_FLAGS
+            .iter()
+            .fold(CloneFlags::empty(), |all, &flag| all | flag);
+        if nstype.is_empty() || !supported.contains(nstype) {
+            return Err(SyscallError::EINVAL);
+        }
+        let target = pidfd.process();
+        if target.get_zombie() {
+            return Err(SyscallError::ESRCH);
+        }
+        SETNS_FLAGS
+            .iter()
+            .filter(|&&flag| nstype.contains(flag))
+            .map(|&flag| target.ns_ref(flag).ok_or(SyscallError::EINVAL))
+            .collect::<Result<_, _>>()?
+    } else {
+        return Err(SyscallError::EINVAL);
+    };
+    for ns in targets {
+        process.setns(ns).map_err(namespace_error)?;
+    }
+    Ok(0)
+}
+
+/// 从用户地址读取主机名或域名，并检查权限
+fn read_uts_name(name: *const u8, len: usize) -> Result<String, SyscallError> {
+    if len > HOST_NAME_MAX {
+        return Err(SyscallError::EINVAL);
+    }
+    let process = current_process();
+    if !process.cred().capable(Privileges::SYS_ADMIN) {
+        return Err(SyscallError::EPERM);
+    }
+    if len == 0 {
+        return Ok(String::new());
+    }
+    let start = name as usize;
+    if name.is_null()
+        || process
+            .manual_alloc_range_for_lazy(VirtAddr::from(start), VirtAddr::from(start + len))
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let bytes = unsafe { core::slice::from_raw_parts(name, len) };
+    core::str::from_utf8(bytes)
+        .map(String::from)
+        .map_err(|_| SyscallError::EINVAL)
+}
+
+/// 设置当前 UTS 命名空间的主机名
+/// # Arguments
+/// * `name` - *const u8
+/// * `len` - usize，不超过 64
+pub fn syscall_sethostname(args: [usize; 6]) -> SyscallResult {
+    let name = read_uts_name(args[0] as *const u8, args[1])?;
+    *current_process().uts_ns().nodename.lock() = name;
+    Ok(0)
+}
+
+/// 设置当前 UTS 命名空间的域名
+/// # Arguments
+/// * `name` - *const u8
+/// * `len` - usize，不超过 64
+pub fn syscall_setdomainname(args: [usize; 6]) -> SyscallResult {
+    let name = read_uts_name(args[0] as *const u8, args[1])?;
+    *current_process().uts_ns().domainname.lock() = name;
+    Ok(0)
+}
@@ -0,0 +1,312 @@
+//! 支持进程跟踪相关的 syscall
+//! 跟踪停止与内存、寄存器访问的实现位于 `axprocess::ptrace`
+use core::mem::size_of;
+
+use axerrno::AxError;
+use axlog::info;
+use axprocess::ptrace::{self, PtraceOptions, ResumeMode, UserRegs};
+use axprocess::signal::send_signal_to_process;
+use axprocess::{current_process, Process};
+use axsignal::info::SigInfo;
+use axsignal::signal_no::{SignalNo, MAX_SIG_NUM};
+
+use crate::{IoVec, PtraceRequest, SyscallError, SyscallResult, NT_PRSTATUS};
+
+/// 将跟踪模块返回的错误转化为对应的错误码
+fn ptrace_error(err: AxError) -> SyscallError {
+    match err {
+        AxError::PermissionDenied => SyscallError::EPERM,
+        AxError::BadState | AxError::NotFound => SyscallError::ESRCH,
+        _ => SyscallError::EIO,
+    }
+}
+
+/// 从当前进程的用户地址读取一个值
+fn read_from_user<T: Copy>(ptr: *const T) -> Result<T, SyscallError> {
+    if ptr.is_null() || current_process().manual_alloc_type_for_lazy(ptr).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    Ok(unsafe { *ptr })
+}
+
+/// 向当前进程的用户地址写入一个值
+fn write_to_user<T>(ptr: *mut T, value: T) -> SyscallResult {
+    if ptr.is_null()
+        || current_process()
+            .manual_alloc_type_for_lazy(ptr as *const T)
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    unsafe {
+        *ptr = value;
+    }
+    Ok(0)
+}
+
+/// 读取处于跟踪停止的线程的通用寄存器
+fn read_regs(tracee: &Process, tid: u64) -> Result<UserRegs, SyscallError> {
+    let task = ptrace::stopped_task(tracee, tid).map_err(ptrace_error)?;
+    let syscall_nr = tracee.ptrace.state().thread(task.id().as_u64()).syscall_nr;
+    #[cfg(target_arch = "x86_64")]
+    let tls = task.get_tls_force();
+    #[cfg(not(target_arch = "x86_64"))]
+    let tls = 0;
+    let trap_frame = unsafe { &*task.get_first_trap_frame() };
+    Ok(UserRegs::from_trap_frame(trap_frame, syscall_nr, tls))
+}
+
+/// 修改处于跟踪停止的线程的通用寄存器
+fn write_regs(tracee: &Process, tid: u64, regs: &UserRegs) -> Result<(), SyscallError> {
+    let task = ptrace::stopped_task(tracee, tid).map_err(ptrace_error)?;
+    regs.apply(unsafe { &mut *task.get_first_trap_frame() });
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        task.set_tls_force(regs.fs_base as usize);
+    }
+    let mut state = tracee.ptrace.state();
+    let thread = state.thread(task.id().as_u64());
+    if thread.syscall_nr.is_some() {
+        // 处于系统调用停止时，允许跟踪者修改系统调用号
+        thread.syscall_nr = Some(regs.syscall_nr());
+    }
+    Ok(())
+}
+
+/// x86_64 中 `struct user` 的大小，超出通用寄存器的部分读出为 0
+#[cfg(target_arch = "x86_64")]
+const USER_STRUCT_SIZE: usize = 912;
+
+/// 读取 `struct user` 中给定偏移处的一个字
+#[cfg(target_arch = "x86_64")]
+fn peek_user(tracee: &Process, tid: u64, offset: usize) -> Result<usize, SyscallError> {
+    if offset % size_of::<usize>() != 0 || offset >= USER_STRUCT_SIZE {
+        return Err(SyscallError::EIO);
+    }
+    if offset >= size_of::<UserRegs>() {
+        return Ok(0);
+    }
+    let regs = read_regs(tracee, tid)?;
+    let words = &regs as *const UserRegs as *const usize;
+    Ok(unsafe { *words.add(offset / size_of::<usize>()) })
+}
+
+/// 修改 `struct user` 中给定偏移处的一个字，只支持通用寄存器部分
+#[cfg(target_arch = "x86_64")]
+fn poke_user(tracee: &Process, tid: u64, offset: usize, value: usize) -> Result<(), SyscallError> {
+    if offset % size_of::<usize>() != 0 || offset >= size_of::<UserRegs>() {
+        return Err(SyscallError::EIO);
+    }
+    let mut regs = read_regs(tracee, tid)?;
+    let words = &mut regs as *mut UserRegs as *mut usize;
+    unsafe {
+        *words.add(offset / size_of::<usize>()) = value;
+    }
+    write_regs(tracee, tid, &regs)
+}
+
+/// 通过 iovec 读写通用寄存器
+fn access_regset(tracee: &Process, tid: u64, iov: *mut IoVec, write: bool) -> SyscallResult {
+    let process = current_process();
+    if iov.is_null()
+        || process
+            .manual_alloc_type_for_lazy(iov as *const IoVec)
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let iov = unsafe { &mut *iov };
+    let len = iov.len.min(size_of::<UserRegs>());
+    if len > 0
+        && process
+            .manual_alloc_range_for_lazy(
+                (iov.base as usize).into(),
+                (iov.base as usize + len - 1).into(),
+            )
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    let mut regs = read_regs(tracee, tid)?;
+    let regs_ptr = &mut regs as *mut UserRegs as *mut u8;
+    unsafe {
+        if write {
+            core::ptr::copy_nonoverlapping(iov.base, regs_ptr, len);
+        } else {
+            core::ptr::copy_nonoverlapping(regs_ptr, iov.base, len);
+        }
+    }
+    if write {
+        write_regs(tracee, tid, &regs)?;
+    }
+    // 与 Linux 一致，返回实际读写的长度
+    iov.len = len;
+    Ok(0)
+}
+
+/// 跟踪其他进程，或者由父进程跟踪自身
+///
+/// 对于 PEEK 类请求，与 Linux 的系统调用接口一致，读到的值写入 `data` 指向的地址
+/// # Arguments
+/// * `request` - usize
+/// * `pid` - usize
+/// * `addr` - usize
+/// * `data` - usize
+pub fn syscall_ptrace(args: [usize; 6]) -> SyscallResult {
+    let request = PtraceRequest::try_from(args[0]).map_err(|_| SyscallError::EIO)?;
+    let pid = args[1] as u64;
+    let addr = args[2];
+    let data = args[3];
+    info!(
+        "ptrace: request: {:?}, pid: {}, addr: {:#x}, data: {:#x}",
+        request, pid, addr, data
+    );
+    let process = current_process();
+    if request == PtraceRequest::PTRACE_TRACEME {
+        process
+            .ptrace
+            .attach(process.get_parent(), PtraceOptions::empty())
+            .map_err(ptrace_error)?;
+        return Ok(0);
+    }
+    // 其余请求中的 pid 是当前 PID 命名空间中的线程号
+    let pid = process.pid_from_user(pid).ok_or(SyscallError::ESRCH)?;
+    match request {
+        PtraceRequest::PTRACE_ATTACH | PtraceRequest::PTRACE_SEIZE => {
+            let tracee = ptrace::find_process_by_tid(pid).map_err(|_| SyscallError::ESRCH)?;
+            if tracee.pid() == process.pid() {
+                return Err(SyscallError::EPERM);
+            }
+            // 只能跟踪身份相同且特权不多于自身的进程
+            if !process.cred().can_ptrace(&tracee.cred()) {
+                return Err(SyscallError::EPERM);
+            }
+            let options = if request == PtraceRequest::PTRACE_SEIZE {
+                PtraceOptions::from_bits(data as u32).ok_or(SyscallError::EINVAL)?
+            } else {
+                PtraceOptions::empty()
+            };
+            tracee
+                .ptrace
+                .attach(process.pid(), options)
+                .map_err(ptrace_error)?;
+            if request == PtraceRequest::PTRACE_ATTACH {
+                // 被跟踪进程会在递送 SIGSTOP 时进入跟踪停止
+                let _ = send_signal_to_process(tracee.pid() as isize, SignalNo::SIGSTOP as isize);
+            }
+            return Ok(0);
+        }
+        _ => {}
+    }
+
+    let tracee = ptrace::find_tracee(pid, process.pid()).map_err(|_| SyscallError::ESRCH)?;
+    if request == PtraceRequest::PTRACE_KILL {
+        let _ = send_signal_to_process(tracee.pid() as isize, SignalNo::SIGKILL as isize);
+        return Ok(0);
+    }
+    // 其余请求都要求被跟踪进程中有线程处于跟踪停止，请求作用于其中的一个
+    let tid = tracee
+        .ptrace
+        .state()
+        .stopped_thread(pid)
+        .ok_or(SyscallError::ESRCH)?;
+    match request {
+        PtraceRequest::PTRACE_PEEKTEXT | PtraceRequest::PTRACE_PEEKDATA => {
+            let mut buf = [0u8; size_of::<usize>()];
+            ptrace::access_memory(&tracee, addr, &mut buf, false).map_err(|_| SyscallError::EIO)?;
+            write_to_user(data as *mut usize, usize::from_ne_bytes(buf))
+        }
+        PtraceRequest::PTRACE_POKETEXT | PtraceRequest::PTRACE_POKEDATA => {
+            let mut buf = data.to_ne_bytes();
+            ptrace::access_memory(&tracee, addr, &mut buf, true).map_err(|_| SyscallError::EIO)?;
+            Ok(0)
+        }
+        #[cfg(target_arch = "x86_64")]
+        PtraceRequest::PTRACE_PEEKUSER => {
+            let value = peek_user(&tracee, tid, addr)?;
+            write_to_user(data as *mut usize, value)
+        }
+        #[cfg(target_arch = "x86_64")]
+        PtraceRequest::PTRACE_POKEUSER => {
+            poke_user(&tracee, tid, addr, data)?;
+            Ok(0)
+        }
+        #[cfg(target_arch = "x86_64")]
+        PtraceRequest::PTRACE_GETREGS => {
+            let regs = read_regs(&tracee, tid)?;
+            write_to_user(data as *mut UserRegs, regs)
+        }
+        #[cfg(target_arch = "x86_64")]
+        PtraceRequest::PTRACE_SETREGS => {
+            let regs = read_from_user(data as *const UserRegs)?;
+            write_regs(&tracee, tid, &regs)?;
+            Ok(0)
+        }
+        PtraceRequest::PTRACE_GETREGSET | PtraceRequest::PTRACE_SETREGSET => {
+            if addr != NT_PRSTATUS {
+                return Err(SyscallError::EINVAL);
+            }
+            access_regset(
+                &tracee,
+                tid,
+                data as *mut IoVec,
+                request == PtraceRequest::PTRACE_SETREGSET,
+            )
+        }
+        PtraceRequest::PTRACE_CONT
+        | PtraceRequest::PTRACE_SYSCALL
+        | PtraceRequest::PTRACE_SINGLESTEP
+        | PtraceRequest::PTRACE_DETACH => {
+            if data > MAX_SIG_NUM {
+                return Err(SyscallError::EIO);
+            }
+            let mode = match request {
+                PtraceRequest::PTRACE_SYSCALL => ResumeMode::Syscall,
+                PtraceRequest::PTRACE_SINGLESTEP => ResumeMode::SingleStep,
+                _ => ResumeMode::Continue,
+            };
+            let task = ptrace::stopped_task(&tracee, tid).map_err(ptrace_error)?;
+            let trap_frame = unsafe { &mut *task.get_first_trap_frame() };
+            if !ptrace::set_single_step(trap_frame, mode == ResumeMode::SingleStep) {
+                return Err(SyscallError::EIO);
+            }
+            if request == PtraceRequest::PTRACE_DETACH {
+                tracee.ptrace.detach(data);
+            } else {
+                tracee
+                    .ptrace
+                    .resume(tid, mode, data)
+                    .map_err(ptrace_error)?;
+            }
+            Ok(0)
+        }
+        PtraceRequest::PTRACE_SETOPTIONS => {
+            let options = PtraceOptions::from_bits(data as u32).ok_or(SyscallError::EINVAL)?;
+            tracee.ptrace.state().options = options;
+            Ok(0)
+        }
+        PtraceRequest::PTRACE_GETEVENTMSG => {
+            let msg = tracee.ptrace.state().thread(tid).event_msg;
+            write_to_user(data as *mut usize, msg)
+        }
+        PtraceRequest::PTRACE_GETSIGINFO => {
+            let info = tracee
+                .ptrace
+                .state()
+                .thread(tid)
+                .stop
+                .as_ref()
+                .map(|stop| stop.info)
+                .ok_or(SyscallError::ESRCH)?;
+            write_to_user(data as *mut SigInfo, info)
+        }
+        PtraceRequest::PTRACE_SETSIGINFO => {
+            let info = read_from_user(data as *const SigInfo)?;
+            if let Some(stop) = tracee.ptrace.state().thread(tid).stop.as_mut() {
+                stop.info = info;
+            }
+            Ok(0)
+        }
+        _ => Err(SyscallError::EIO),
+    }
+}
@@ -0,0 +1,88 @@
+//! 支持 seccomp 相关的 syscall
+extern crate alloc;
+use alloc::vec::Vec;
+use axhal::mem::VirtAddr;
+use axprocess::{
+    current_process,
+    seccomp::{
+        self, SockFilter, SockFprog, BPF_MAXINSNS, SECCOMP_GET_ACTION_AVAIL, SECCOMP_MODE_FILTER,
+        SECCOMP_MODE_STRICT, SECCOMP_SET_MODE_FILTER, SECCOMP_SET_MODE_STRICT,
+    },
+};
+
+use crate::{SyscallError, SyscallResult};
+
+/// 从用户地址读取一个值
+fn read_user<T: Copy>(ptr: *const T) -> Result<T, SyscallError> {
+    if ptr.is_null() || current_process().manual_alloc_type_for_lazy(ptr).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    Ok(unsafe { *ptr })
+}
+
+/// 读取用户传入的 `struct sock_fprog` 及其指向的指令
+fn read_fprog(fprog: *const SockFprog) -> Result<Vec<SockFilter>, SyscallError> {
+    let fprog = read_user(fprog)?;
+    let len = fprog.len as usize;
+    if len == 0 || len > BPF_MAXINSNS {
+        return Err(SyscallError::EINVAL);
+    }
+    let start = fprog.filter as usize;
+    let end = start + len * core::mem::size_of::<SockFilter>();
+    if fprog.filter.is_null()
+        || current_process()
+            .manual_alloc_range_for_lazy(VirtAddr::from(start), VirtAddr::from(end))
+            .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    Ok(unsafe { core::slice::from_raw_parts(fprog.filter, len) }.to_vec())
+}
+
+/// 按照 mode 进入严格模式或安装过滤器，`prctl(PR_SET_SECCOMP)` 与 `seccomp(2)` 共用
+pub fn set_seccomp_mode(mode: usize, flags: usize, fprog: *const SockFprog) -> SyscallResult {
+    let process = current_process();
+    match mode {
+        SECCOMP_MODE_STRICT => seccomp::set_mode_strict(&process)?,
+        SECCOMP_MODE_FILTER => {
+            let prog = read_fprog(fprog)?;
+            seccomp::attach_filter(&process, prog, flags)?;
+        }
+        _ => return Err(SyscallError::EINVAL),
+    }
+    Ok(0)
+}
+
+/// 设置或查询 seccomp
+/// # Arguments
+/// * `op`: usize, 见 `SECCOMP_SET_MODE_*` 与 `SECCOMP_GET_*`
+/// * `flags`: usize, 安装过滤器时的 `SECCOMP_FILTER_FLAG_*`
+/// * `args`: *const u8, 过滤器或查询的参数
+pub fn syscall_seccomp(args: [usize; 6]) -> SyscallResult {
+    let op = args[0];
+    let flags = args[1];
+    let uargs = args[2];
+    match op {
+        SECCOMP_SET_MODE_STRICT => {
+            if flags != 0 || uargs != 0 {
+                return Err(SyscallError::EINVAL);
+            }
+            set_seccomp_mode(SECCOMP_MODE_STRICT, 0, core::ptr::null())
+        }
+        SECCOMP_SET_MODE_FILTER => {
+            set_seccomp_mode(SECCOMP_MODE_FILTER, flags, uargs as *const SockFprog)
+        }
+        SECCOMP_GET_ACTION_AVAIL => {
+            if flags != 0 {
+                return Err(SyscallError::EINVAL);
+            }
+            if seccomp::action_available(read_user(uargs as *const u32)?) {
+                Ok(0)
+            } else {
+                Err(SyscallError::EOPNOTSUPP)
+            }
+        }
+        // 不支持用户态通知，SECCOMP_GET_NOTIF_SIZES 同样返回 EINVAL
+        _ => Err(SyscallError::EINVAL),
+    }
+}
@@ -1,13 +1,91 @@
 //! 支持信号相关的 syscall
 //! 与信号处理相关的系统调用
 
-use axhal::cpu::this_cpu_id;
+extern crate alloc;
+use alloc::{vec, vec::Vec};
+use axerrno::AxError;
+use axhal::{
+    cpu::this_cpu_id,
+    time::{current_ticks, ticks_to_nanos, TimeValue},
+    KERNEL_PROCESS_ID,
+};
 use axlog::{debug, info};
-use axprocess::{current_process, current_task, yield_now_task};
+use axprocess::signal::process_group_members;
+use axprocess::{current_process, current_task, yield_now_task, PID2PC, TID2TASK};
 use axsignal::action::SigAction;
-use axsignal::signal_no::SignalNo;
+use axsignal::info::{SigInfo, SI_TKILL, SI_USER};
+use axsignal::signal_no::{SignalNo, MAX_SIG_NUM};
+use axsignal::ucontext::{SignalStack, MINSIGSTKSZ, SS_AUTODISARM, SS_DISABLE, SS_ONSTACK};
 
-use crate::{SigMaskFlag, SyscallError, SyscallResult, SIGSET_SIZE_IN_BYTE};
+use crate::{SigMaskFlag, SyscallError, SyscallResult, TimeSecs, SIGSET_SIZE_IN_BYTE};
+
+/// 将发送信号时的错误转化为对应的错误码
+fn send_signal_error(err: AxError) -> SyscallError {
+    match err {
+        AxError::WouldBlock => SyscallError::EAGAIN,
+        _ => SyscallError::ESRCH,
+    }
+}
+
+/// 将当前 PID 命名空间中的进程号或线程号转换为全局编号，不存在时返回 ESRCH
+fn global_pid(pid: isize) -> Result<isize, SyscallError> {
+    if pid <= 0 {
+        return Err(SyscallError::ESRCH);
+    }
+    current_process()
+        .pid_from_user(pid as u64)
+        .map(|pid| pid as isize)
+        .ok_or(SyscallError::ESRCH)
+}
+
+/// 判断线程 tid 是否属于线程组 tgid，二者均为全局编号
+fn thread_in_group(tgid: isize, tid: isize) -> bool {
+    TID2TASK
+        .lock()
+        .get(&(tid as u64))
+        .map_or(false, |task| task.get_process_id() == tgid as u64)
+}
+
+/// 检查当前进程能否向进程 pid 发送信号 signum
+///
+/// 发送者的真实或有效用户号需要与目标的真实或保存的用户号相同，或拥有 KILL 特权。
+/// 与 Linux 一致，同一会话中的进程之间总是可以发送 SIGCONT
+fn check_kill_permission(pid: u64, signum: isize) -> Result<(), SyscallError> {
+    let target = PID2PC
+        .lock()
+        .get(&pid)
+        .cloned()
+        .ok_or(SyscallError::ESRCH)?;
+    let process = current_process();
+    if process.cred().can_signal(&target.cred()) {
+        return Ok(());
+    }
+    if signum == SignalNo::SIGCONT as isize && target.get_sid() == process.get_sid() {
+        return Ok(());
+    }
+    Err(SyscallError::EPERM)
+}
+
+/// 检查当前进程能否向线程 tid 发送信号 signum
+fn check_tkill_permission(tid: isize, signum: isize) -> Result<(), SyscallError> {
+    let pid = TID2TASK
+        .lock()
+        .get(&(tid as u64))
+        .map(|task| task.get_process_id())
+        .ok_or(SyscallError::ESRCH)?;
+    check_kill_permission(pid, signum)
+}
+
+/// 以当前进程的身份构造 kill 等产生的信号信息
+fn sender_info(signum: isize, code: i32) -> SigInfo {
+    let process = current_process();
+    SigInfo::from_sender(
+        signum as i32,
+        code,
+        process.pid_to_user(process.pid()) as u32,
+        process.cred().uid,
+    )
+}
 
 /// # Arguments
 /// * `signum` - usize
@@ -175,7 +253,40 @@ pub fn syscall_sigprocmask(args: [usize; 6]) -> SyscallResult {
     Ok(0)
 }
 
-/// 向pid指定的进程发送信号
+/// 向一组进程发送信号
+///
+/// 只要向其中一个进程发送成功即返回成功；没有目标进程时返回 ESRCH，对所有目标都没有权限时返回 EPERM
+fn kill_processes(targets: Vec<u64>, signum: isize) -> SyscallResult {
+    if targets.is_empty() {
+        return Err(SyscallError::ESRCH);
+    }
+    let mut result = Err(SyscallError::EPERM);
+    for pid in targets {
+        if let Err(err) = check_kill_permission(pid, signum) {
+            if result.is_err() {
+                result = Err(err);
+            }
+            continue;
+        }
+        result = Ok(0);
+        // 信号 0 只检查目标是否存在以及是否有权限
+        if signum != 0 {
+            // 不关心是否成功
+            let _ = axprocess::signal::send_signal_info_to_process(
+                pid as isize,
+                sender_info(signum, SI_USER),
+            );
+        }
+    }
+    result
+}
+
+/// 向pid指定的进程或进程组发送信号
+///
+/// - pid 大于 0 时发送给该进程
+/// - pid 为 0 时发送给当前进程所在进程组中的所有进程
+/// - pid 为 -1 时发送给除 init 进程与当前进程以外的所有进程
+/// - pid 小于 -1 时发送给进程组 -pid 中的所有进程
 ///
 /// 由于处理信号的单位在线程上，所以若进程中有多个线程，则会发送给主线程
 /// # Arguments
@@ -184,15 +295,31 @@ pub fn syscall_sigprocmask(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_kill(args: [usize; 6]) -> SyscallResult {
     let pid = args[0] as isize;
     let signum = args[1] as isize;
-    if pid > 0 && signum > 0 {
-        // 不关心是否成功
-        let _ = axprocess::signal::send_signal_to_process(pid, signum);
-        Ok(0)
-    } else if pid == 0 {
-        Err(SyscallError::ESRCH)
-    } else {
-        Err(SyscallError::EINVAL)
+    if signum < 0 || signum as usize > MAX_SIG_NUM {
+        return Err(SyscallError::EINVAL);
+    }
+    if pid > 0 {
+        return kill_processes(vec![global_pid(pid)? as u64], signum);
     }
+    let process = current_process();
+    let pid_ns = process.pid_ns();
+    let targets: Vec<u64> = match pid {
+        0 => process_group_members(process.get_pgid()),
+        -1 => PID2PC
+            .lock()
+            .values()
+            .filter(|other| !other.get_zombie())
+            .map(|other| other.pid())
+            .filter(|&other| other != KERNEL_PROCESS_ID && other != process.pid())
+            .collect(),
+        _ => process_group_members(global_pid(pid.unsigned_abs() as isize)? as u64),
+    };
+    // 只能向当前 PID 命名空间中可见的进程发送信号
+    let targets = targets
+        .into_iter()
+        .filter(|&other| pid_ns.pid_of(other).is_some())
+        .collect();
+    kill_processes(targets, signum)
 }
 
 /// 向tid指定的线程发送信号
@@ -209,9 +336,221 @@ pub fn syscall_tkill(args: [usize; 6]) -> SyscallResult {
         tid
     );
     if tid > 0 && signum > 0 {
-        let _ = axprocess::signal::send_signal_to_thread(tid, signum);
+        let tid = global_pid(tid)?;
+        check_tkill_permission(tid, signum)?;
+        let info = sender_info(signum, SI_TKILL);
+        let _ = axprocess::signal::send_signal_info_to_thread(tid, info);
         Ok(0)
     } else {
         Err(SyscallError::EINVAL)
     }
 }
+
+/// 向线程组 tgid 中的线程 tid 发送信号
+/// # Arguments
+/// * `tgid` - isize
+/// * `tid` - isize
+/// * `signum` - isize
+pub fn syscall_tgkill(args: [usize; 6]) -> SyscallResult {
+    let tgid = args[0] as isize;
+    let tid = args[1] as isize;
+    let signum = args[2] as isize;
+    if tgid <= 0 || tid <= 0 || signum < 0 || signum as usize > MAX_SIG_NUM {
+        return Err(SyscallError::EINVAL);
+    }
+    let tgid = global_pid(tgid)?;
+    let tid = global_pid(tid)?;
+    if !thread_in_group(tgid, tid) {
+        return Err(SyscallError::ESRCH);
+    }
+    check_kill_permission(tgid as u64, signum)?;
+    if signum == 0 {
+        return Ok(0);
+    }
+    let info = sender_info(signum, SI_TKILL);
+    axprocess::signal::send_signal_info_to_thread(tid, info)
+        .map(|_| 0)
+        .map_err(send_signal_error)
+}
+
+/// 读取用户传入的 siginfo，并检查其是否合法
+///
+/// 向其他进程发送时，不允许伪造由内核或 kill 产生的信号
+fn read_user_siginfo(
+    target_pid: isize,
+    signum: isize,
+    uinfo: *const SigInfo,
+) -> Result<SigInfo, SyscallError> {
+    if signum <= 0 || signum as usize > MAX_SIG_NUM {
+        return Err(SyscallError::EINVAL);
+    }
+    let process = current_process();
+    if process.manual_alloc_type_for_lazy(uinfo).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let mut info = unsafe { *uinfo };
+    if (info.si_code >= 0 || info.si_code == SI_TKILL)
+        && process.pid_from_user(target_pid as u64) != Some(process.pid())
+    {
+        return Err(SyscallError::EPERM);
+    }
+    info.si_signo = signum as i32;
+    Ok(info)
+}
+
+/// 向进程发送携带信息的信号，即 sigqueue 的实现
+/// # Arguments
+/// * `tgid` - isize
+/// * `signum` - isize
+/// * `uinfo` - *const SigInfo
+pub fn syscall_rt_sigqueueinfo(args: [usize; 6]) -> SyscallResult {
+    let tgid = args[0] as isize;
+    let signum = args[1] as isize;
+    let uinfo = args[2] as *const SigInfo;
+    let info = read_user_siginfo(tgid, signum, uinfo)?;
+    let tgid = global_pid(tgid)?;
+    check_kill_permission(tgid as u64, signum)?;
+    axprocess::signal::send_signal_info_to_process(tgid, info)
+        .map(|_| 0)
+        .map_err(send_signal_error)
+}
+
+/// 向线程组中的指定线程发送携带信息的信号
+/// # Arguments
+/// * `tgid` - isize
+/// * `tid` - isize
+/// * `signum` - isize
+/// * `uinfo` - *const SigInfo
+pub fn syscall_rt_tgsigqueueinfo(args: [usize; 6]) -> SyscallResult {
+    let tgid = args[0] as isize;
+    let tid = args[1] as isize;
+    let signum = args[2] as isize;
+    let uinfo = args[3] as *const SigInfo;
+    let info = read_user_siginfo(tgid, signum, uinfo)?;
+    let tgid = global_pid(tgid)?;
+    let tid = global_pid(tid)?;
+    if !thread_in_group(tgid, tid) {
+        return Err(SyscallError::ESRCH);
+    }
+    check_kill_permission(tgid as u64, signum)?;
+    axprocess::signal::send_signal_info_to_thread(tid, info)
+        .map(|_| 0)
+        .map_err(send_signal_error)
+}
+
+/// 同步等待 set 中的信号，并取出其信息
+///
+/// 返回取出的信号编号
+/// # Arguments
+/// * `set` - *const usize
+/// * `info` - *mut SigInfo
+/// * `timeout` - *const TimeSecs
+/// * `sigsetsize` - usize
+pub fn syscall_sigtimedwait(args: [usize; 6]) -> SyscallResult {
+    let set = args[0] as *const usize;
+    let info = args[1] as *mut SigInfo;
+    let timeout = args[2] as *const TimeSecs;
+    let sigsetsize = args[3];
+    if sigsetsize != SIGSET_SIZE_IN_BYTE {
+        return Err(SyscallError::EINVAL);
+    }
+    let process = current_process();
+    if process.manual_alloc_type_for_lazy(set).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    if !info.is_null() && process.manual_alloc_type_for_lazy(info).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    // SIGKILL 和 SIGSTOP 不能被等待
+    let set = unsafe { *set }
+        & !(1 << (SignalNo::SIGKILL as usize - 1))
+        & !(1 << (SignalNo::SIGSTOP as usize - 1));
+    let expire_time = if timeout.is_null() {
+        usize::MAX
+    } else {
+        if process.manual_alloc_type_for_lazy(timeout).is_err() {
+            return Err(SyscallError::EFAULT);
+        }
+        current_ticks() as usize + unsafe { (*timeout).get_ticks() }
+    };
+    let deadline = (expire_time != usize::MAX)
+        .then(|| TimeValue::from_nanos(ticks_to_nanos(expire_time as u64)));
+    let tid = current_task().id().as_u64();
+    loop {
+        // 在检查之前记录，检查之后产生的信号会使等待立即返回
+        let generation = process.signal_wait.generation();
+        let mut signal_modules = process.signal_modules.lock();
+        let signal_set = &mut signal_modules.get_mut(&tid).unwrap().signal_set;
+        if let Some(sig_num) = signal_set.find_signal_in(set) {
+            let sig_info = signal_set.take_signal(sig_num);
+            drop(signal_modules);
+            if !info.is_null() {
+                unsafe {
+                    *info = sig_info;
+                }
+            }
+            return Ok(sig_num as isize);
+        }
+        drop(signal_modules);
+        if current_ticks() as usize >= expire_time {
+            return Err(SyscallError::EAGAIN);
+        }
+        if process.have_signals().is_some() {
+            // 被不在 set 中的信号打断
+            return Err(SyscallError::EINTR);
+        }
+        process.signal_wait.wait(generation, deadline);
+    }
+}
+
+/// 设置或获取备用信号栈
+/// # Arguments
+/// * `ss` - *const SignalStack
+/// * `old_ss` - *mut SignalStack
+pub fn syscall_sigaltstack(args: [usize; 6]) -> SyscallResult {
+    let ss = args[0] as *const SignalStack;
+    let old_ss = args[1] as *mut SignalStack;
+    let process = current_process();
+    if !ss.is_null() && process.manual_alloc_type_for_lazy(ss).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    if !old_ss.is_null() && process.manual_alloc_type_for_lazy(old_ss).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let current_task = current_task();
+    let user_sp = unsafe { (*current_task.get_first_trap_frame()).get_sp() };
+    let mut signal_modules = process.signal_modules.lock();
+    let signal_module = signal_modules.get_mut(&current_task.id().as_u64()).unwrap();
+    let on_stack = signal_module.alt_stack.contains(user_sp);
+    if !old_ss.is_null() {
+        let mut old = signal_module.alt_stack;
+        if on_stack {
+            old.flags = SS_ONSTACK | (old.flags & SS_AUTODISARM);
+        }
+        unsafe {
+            *old_ss = old;
+        }
+    }
+    if !ss.is_null() {
+        let new = unsafe { *ss };
+        if on_stack {
+            // 正在备用信号栈上执行时不能修改
+            return Err(SyscallError::EPERM);
+        }
+        match new.flags & !SS_AUTODISARM {
+            SS_DISABLE => signal_module.alt_stack = SignalStack::default(),
+            0 | SS_ONSTACK => {
+                if new.size < MINSIGSTKSZ {
+                    return Err(SyscallError::ENOMEM);
+                }
+                signal_module.alt_stack = SignalStack {
+                    sp: new.sp,
+                    flags: new.flags & SS_AUTODISARM,
+                    size: new.size,
+                };
+            }
+            _ => return Err(SyscallError::EINVAL),
+        }
+    }
+    Ok(0)
+}
@@ -1,16 +1,19 @@
 use axtask::current;
 /// 处理与任务（线程）有关的系统调用
+use core::sync::atomic::Ordering;
 use core::time::Duration;
 use core::{mem::size_of, ptr::slice_from_raw_parts_mut};
 
-use axconfig::TASK_STACK_SIZE;
+use axfs::api::OpenFlags;
 use axhal::time::current_time;
 use axprocess::{
-    current_process, current_task, exit_current_task,
-    flags::{CloneFlags, WaitStatus},
+    binfmt, cred, current_process, current_task, exit_current_task,
+    flags::{CloneFlags, WaitFlags, WaitResult, WaitStatus, WaitTarget},
     futex::clear_wait,
     link::{deal_with_path, raw_ptr_to_ref_str, AT_FDCWD},
-    set_child_tid, sleep_now_task, wait_pid, yield_now_task, Process, PID2PC,
+    pidfd::PidFd,
+    rlimit::{RLimit, RLIM_NLIMITS},
+    set_child_tid, sleep_now_task, wait_child, yield_now_task, Process, PID2PC,
 };
 
 // use axtask::{
@@ -18,20 +21,24 @@ use axprocess::{
 //     AxTaskRef,
 // };
 use crate::{
-    CloneArgs, PrctlOption, RLimit, SyscallError, SyscallResult, TimeSecs, WaitFlags, PR_NAME_SIZE,
-    RLIMIT_AS, RLIMIT_NOFILE, RLIMIT_STACK,
+    CloneArgs, PrctlOption, SyscallError, SyscallResult, TimeSecs, PIDFD_NONBLOCK, PR_NAME_SIZE,
 };
+use axerrno::AxError;
 use axlog::{info, warn};
-use axtask::TaskId;
 extern crate alloc;
 
 use alloc::{string::ToString, sync::Arc, vec::Vec};
 
 #[cfg(feature = "signal")]
-use axsignal::signal_no::SignalNo;
-
+use super::set_seccomp_mode;
+#[cfg(feature = "signal")]
+use crate::{P_ALL, P_PGID, P_PID, P_PIDFD};
+#[cfg(feature = "signal")]
+use axprocess::seccomp::SockFprog;
 #[cfg(feature = "signal")]
-use axprocess::signal::SignalModule;
+use axprocess::signal::child_info;
+#[cfg(feature = "signal")]
+use axsignal::{info::SigInfo, signal_no::SignalNo};
 // pub static TEST_FILTER: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
 
 /// # Arguments
@@ -100,6 +107,10 @@ pub fn syscall_exec(args: [usize; 6]) -> SyscallResult {
         return Err(SyscallError::EISDIR);
     }
     let path = path.path().to_string();
+    // 不存在的文件交由 exec 处理
+    if let Err(AxError::PermissionDenied) = cred::check_exec(&path) {
+        return Err(SyscallError::EACCES);
+    }
 
     let mut args_vec = Vec::new();
     // args相当于argv，指向了参数所在的地址
@@ -139,17 +150,26 @@ pub fn syscall_exec(args: [usize; 6]) -> SyscallResult {
     // if filter(testcase) == false {
     //     return -1;
     // }
+    // 在释放原有地址空间之前确定最终加载的程序，脚本交给其解释器执行
+    let prm = binfmt::resolve(&path, args_vec)?;
     let curr_process = current_process();
 
     // 设置 file_path
-    curr_process.set_file_path(path.clone());
+    curr_process.set_file_path(prm.path.clone());
 
     // 清空futex信号列表
     clear_wait(curr_process.pid(), true);
-    let argc = args_vec.len();
-    if curr_process.exec(path, args_vec, &envs_vec).is_err() {
+    let argc = prm.args.len();
+    if curr_process
+        .exec(prm.path.clone(), prm.args, &envs_vec)
+        .is_err()
+    {
         exit_current_task(0);
     }
+    // 根据可执行文件的 S_ISUID 与 S_ISGID 更新身份
+    cred::exec_credentials(&curr_process, &prm.cred_path);
+    #[cfg(feature = "signal")]
+    axprocess::ptrace::exec_event(&curr_process);
     Ok(argc as isize)
 }
 
@@ -183,6 +203,12 @@ pub fn syscall_clone(args: [usize; 6]) -> SyscallResult {
         ctid = args[4];
     }
     let clone_flags = CloneFlags::from_bits((flags & !0x3f) as u32).unwrap();
+    // clone 的 pidfd 与 parent_tid 共用同一个参数，且 pidfd 只能指向进程
+    if clone_flags.contains(CloneFlags::CLONE_PIDFD)
+        && clone_flags.intersects(CloneFlags::CLONE_THREAD | CloneFlags::CLONE_PARENT_SETTID)
+    {
+        return Err(SyscallError::EINVAL);
+    }
 
     let stack = if user_stack == 0 {
         None
@@ -193,7 +219,7 @@ pub fn syscall_clone(args: [usize; 6]) -> SyscallResult {
     #[cfg(feature = "signal")]
     let sig_child = SignalNo::from(flags & 0x3f) == SignalNo::SIGCHLD;
 
-    if let Ok(new_task_id) = curr_process.clone_task(
+    let result = curr_process.clone_task(
         clone_flags,
         stack,
         ptid,
@@ -201,11 +227,33 @@ pub fn syscall_clone(args: [usize; 6]) -> SyscallResult {
         ctid,
         #[cfg(feature = "signal")]
         sig_child,
-    ) {
-        Ok(new_task_id as isize)
-    } else {
-        Err(SyscallError::ENOMEM)
+    );
+    let new_task_id = result.map_err(clone_error)?;
+    if clone_flags.contains(CloneFlags::CLONE_PIDFD) {
+        clone_pidfd(new_task_id, ptid as *mut i32)?;
     }
+    Ok(clone_return_id(&curr_process, new_task_id))
+}
+
+/// 将 clone 失败的原因转换为错误码
+fn clone_error(err: AxError) -> SyscallError {
+    match err {
+        // 超出 RLIMIT_NPROC
+        AxError::WouldBlock => SyscallError::EAGAIN,
+        // 创建命名空间需要 CAP_SYS_ADMIN
+        AxError::PermissionDenied => SyscallError::EPERM,
+        AxError::InvalidInput => SyscallError::EINVAL,
+        // PID 命名空间嵌套过深
+        AxError::StorageFull => SyscallError::ENOSPC,
+        _ => SyscallError::ENOMEM,
+    }
+}
+
+/// clone 返回给父进程的 id：新建进程时为父进程所见的进程号，新建线程时为父进程所见的线程号
+///
+/// 线程号与进程号在 PID 命名空间中统一编号，因此二者的转换方式相同
+fn clone_return_id(process: &Process, new_task_id: u64) -> isize {
+    process.pid_to_user(new_task_id) as isize
 }
 
 /// 创建子进程的新函数，所有信息保存在 CloneArgs
@@ -225,6 +273,11 @@ pub fn syscall_clone3(args: [usize; 6]) -> SyscallResult {
     };
 
     let clone_flags = CloneFlags::from_bits(args.flags as u32).unwrap();
+    if clone_flags.contains(CloneFlags::CLONE_PIDFD)
+        && clone_flags.contains(CloneFlags::CLONE_THREAD)
+    {
+        return Err(SyscallError::EINVAL);
+    }
 
     let stack = if args.stack == 0 {
         None
@@ -235,7 +288,7 @@ pub fn syscall_clone3(args: [usize; 6]) -> SyscallResult {
     let sig_child = SignalNo::from(args.exit_signal as usize & 0x3f) == SignalNo::SIGCHLD;
 
     warn!("stack size  {}", args.stack_size);
-    if let Ok(new_task_id) = curr_process.clone_task(
+    let result = curr_process.clone_task(
         clone_flags,
         stack,
         args.parent_tid as usize,
@@ -243,11 +296,12 @@ pub fn syscall_clone3(args: [usize; 6]) -> SyscallResult {
         args.child_tid as usize,
         #[cfg(feature = "signal")]
         sig_child,
-    ) {
-        Ok(new_task_id as isize)
-    } else {
-        Err(SyscallError::ENOMEM)
+    );
+    let new_task_id = result.map_err(clone_error)?;
+    if clone_flags.contains(CloneFlags::CLONE_PIDFD) {
+        clone_pidfd(new_task_id, args.pidfd as *mut i32)?;
     }
+    Ok(clone_return_id(&curr_process, new_task_id))
 }
 
 /// 创建一个子进程，挂起父进程，直到子进程exec或者exit，父进程才继续执行
@@ -256,48 +310,191 @@ pub fn syscall_vfork() -> SyscallResult {
     syscall_clone(args)
 }
 
-/// 等待子进程完成任务，若子进程没有完成，则自身yield
-/// 当前仅支持WNOHANG选项，即若未完成时则不予等待，直接返回0
+/// 等待一次满足 `options` 的子进程状态变化，若暂时没有则自身yield
+///
+/// `target` 中是当前 PID 命名空间中的进程号与进程组号，返回结果中的进程号也已经转换回来。
+/// 指定了 WNOHANG 且没有可报告的状态变化时返回 `None`
+fn wait_for_child(
+    target: WaitTarget,
+    options: WaitFlags,
+) -> Result<Option<WaitResult>, SyscallError> {
+    let process = current_process();
+    let to_global = |pid: u64| process.pid_from_user(pid).ok_or(SyscallError::ECHILD);
+    let target = match target {
+        WaitTarget::Pid(pid) => WaitTarget::Pid(to_global(pid)?),
+        WaitTarget::Pgid(pgid) if pgid != 0 => WaitTarget::Pgid(to_global(pgid)?),
+        target => target,
+    };
+    loop {
+        match wait_child(target, options) {
+            Ok(mut result) => {
+                result.pid = process.pid_to_user(result.pid);
+                return Ok(Some(result));
+            }
+            Err(WaitStatus::NotExist) => return Err(SyscallError::ECHILD),
+            Err(_) => {
+                if options.contains(WaitFlags::WNOHANG) {
+                    // 不予等待，直接返回
+                    return Ok(None);
+                }
+                // wait回来之后，如果还需要wait，先检查是否有信号未处理
+                #[cfg(feature = "signal")]
+                if current_process().have_signals().is_some() {
+                    return Err(SyscallError::EINTR);
+                }
+                // 执行yield操作，切换任务
+                yield_now_task();
+            }
+        }
+    }
+}
+
+/// 等待子进程退出或停止，若子进程状态没有变化，则自身yield
 /// # Arguments
-/// * `pid` - isize
+/// * `pid` - isize，-1 代表任意子进程，0 代表同组的子进程，小于 -1 代表进程组 -pid 中的子进程
 /// * `exit_code_ptr` - *mut i32
-/// * `option` - WaitFlags
+/// * `option` - WaitFlags，支持 WNOHANG、WUNTRACED 与 WCONTINUED
 pub fn syscall_wait4(args: [usize; 6]) -> SyscallResult {
-    let pid = args[0] as isize;
+    let pid = args[0] as i32 as isize;
     let exit_code_ptr = args[1] as *mut i32;
-    let option = WaitFlags::from_bits(args[2] as u32).unwrap();
-    loop {
-        let answer = unsafe { wait_pid(pid, exit_code_ptr) };
-        match answer {
-            Ok(pid) => {
-                return Ok(pid as isize);
-            }
-            Err(status) => {
-                match status {
-                    WaitStatus::NotExist => {
-                        return Err(SyscallError::EPERM);
-                    }
-                    WaitStatus::Running => {
-                        if option.contains(WaitFlags::WNOHANG) {
-                            // 不予等待，直接返回0
-                            return Ok(0);
-                        } else {
-                            // wait回来之后，如果还需要wait，先检查是否有信号未处理
-                            #[cfg(feature = "signal")]
-                            if current_process().have_signals().is_some() {
-                                return Err(SyscallError::EINTR);
-                            }
-                            // 执行yield操作，切换任务
-                            yield_now_task();
-                        }
-                    }
-                    _ => {
-                        panic!("Shouldn't reach here!");
-                    }
-                }
+    let option = WaitFlags::from_bits(args[2] as u32).ok_or(SyscallError::EINVAL)?;
+    if option.intersects(WaitFlags::WEXITED | WaitFlags::WNOWAIT) {
+        return Err(SyscallError::EINVAL);
+    }
+    let result = match wait_for_child(WaitTarget::from(pid), option | WaitFlags::WEXITED)? {
+        Some(result) => result,
+        None => return Ok(0),
+    };
+    if !exit_code_ptr.is_null() {
+        if current_process()
+            .manual_alloc_type_for_lazy(exit_code_ptr)
+            .is_err()
+        {
+            return Err(SyscallError::EFAULT);
+        }
+        unsafe {
+            *exit_code_ptr = result.state.wait_status();
+        }
+    }
+    Ok(result.pid as isize)
+}
+
+/// 等待子进程的状态变化，并通过 siginfo 报告变化的原因
+///
+/// 与 wait4 不同，需要显式指定等待退出（WEXITED）、停止（WSTOPPED）或继续（WCONTINUED），
+/// 并且可以通过 WNOWAIT 只查看而不取走状态变化
+/// # Arguments
+/// * `idtype` - usize，P_ALL、P_PID、P_PGID 或 P_PIDFD
+/// * `id` - usize，按照 idtype 解释的进程号、进程组号或 pidfd
+/// * `info` - *mut SigInfo，指定 WNOHANG 且没有状态变化时被清零
+/// * `options` - WaitFlags
+#[cfg(feature = "signal")]
+pub fn syscall_waitid(args: [usize; 6]) -> SyscallResult {
+    let idtype = args[0];
+    let id = args[1] as i32;
+    let info = args[2] as *mut SigInfo;
+    let mut options = WaitFlags::from_bits(args[3] as u32).ok_or(SyscallError::EINVAL)?;
+    if !options.intersects(WaitFlags::WEXITED | WaitFlags::WSTOPPED | WaitFlags::WCONTINUED) {
+        return Err(SyscallError::EINVAL);
+    }
+    let process = current_process();
+    let mut nonblocking_pidfd = false;
+    let target = match idtype {
+        P_ALL => WaitTarget::Any,
+        P_PID if id > 0 => WaitTarget::Pid(id as u64),
+        P_PGID if id >= 0 => WaitTarget::Pgid(id as u64),
+        P_PIDFD if id >= 0 => {
+            let file = match process.fd_manager.fd_table.lock().get(id as usize) {
+                Some(Some(file)) => file.clone(),
+                _ => return Err(SyscallError::EBADF),
+            };
+            let pidfd = file
+                .as_any()
+                .downcast_ref::<PidFd>()
+                .ok_or(SyscallError::EINVAL)?;
+            if pidfd.is_nonblocking() && !options.contains(WaitFlags::WNOHANG) {
+                // 非阻塞的 pidfd 在子进程没有状态变化时返回 EAGAIN
+                nonblocking_pidfd = true;
+                options |= WaitFlags::WNOHANG;
             }
+            WaitTarget::Pid(process.pid_to_user(pidfd.process().pid()))
+        }
+        _ => return Err(SyscallError::EINVAL),
+    };
+    let result = wait_for_child(target, options)?;
+    if result.is_none() && nonblocking_pidfd {
+        return Err(SyscallError::EAGAIN);
+    }
+    if !info.is_null() {
+        if process.manual_alloc_type_for_lazy(info).is_err() {
+            return Err(SyscallError::EFAULT);
+        }
+        let siginfo = match result {
+            Some(result) => child_info(result.pid, result.uid, result.state),
+            None => SigInfo::new(0, 0),
         };
+        unsafe {
+            *info = siginfo;
+        }
+    }
+    Ok(0)
+}
+
+/// 创建指向给定进程的文件描述符
+/// # Arguments
+/// * `pid` - usize，进程号
+/// * `flags` - usize，可以为 0 或 PIDFD_NONBLOCK
+pub fn syscall_pidfd_open(args: [usize; 6]) -> SyscallResult {
+    let pid = args[0] as i32;
+    let flags = args[1];
+    if pid <= 0 || flags & !PIDFD_NONBLOCK != 0 {
+        return Err(SyscallError::EINVAL);
     }
+    let pid = current_process()
+        .pid_from_user(pid as u64)
+        .ok_or(SyscallError::ESRCH)?;
+    let target = PID2PC
+        .lock()
+        .get(&pid)
+        .cloned()
+        .ok_or(SyscallError::ESRCH)?;
+    let open_flags = if flags & PIDFD_NONBLOCK != 0 {
+        OpenFlags::NON_BLOCK
+    } else {
+        OpenFlags::empty()
+    };
+    install_pidfd(target, open_flags)
+}
+
+/// 在当前进程中为给定进程分配一个 pidfd
+fn install_pidfd(target: Arc<Process>, flags: OpenFlags) -> SyscallResult {
+    let process = current_process();
+    let mut fd_table = process.fd_manager.fd_table.lock();
+    let fd_num = process
+        .alloc_fd(&mut fd_table)
+        .map_err(|_| SyscallError::EMFILE)?;
+    fd_table[fd_num] = Some(Arc::new(PidFd::new(target, flags)));
+    Ok(fd_num as isize)
+}
+
+/// clone 指定了 CLONE_PIDFD 时，为新的子进程分配 pidfd 并写入 `pidfd_ptr`
+fn clone_pidfd(pid: u64, pidfd_ptr: *mut i32) -> SyscallResult {
+    let target = PID2PC
+        .lock()
+        .get(&pid)
+        .cloned()
+        .ok_or(SyscallError::ESRCH)?;
+    let fd = install_pidfd(target, OpenFlags::empty())?;
+    if current_process()
+        .manual_alloc_type_for_lazy(pidfd_ptr)
+        .is_err()
+    {
+        return Err(SyscallError::EFAULT);
+    }
+    unsafe {
+        *pidfd_ptr = fd as i32;
+    }
+    Ok(fd)
 }
 
 /// To yield the current task
@@ -353,7 +550,7 @@ pub fn syscall_sleep(args: [usize; 6]) -> SyscallResult {
 pub fn syscall_set_tid_address(args: [usize; 6]) -> SyscallResult {
     let tid = args[0];
     set_child_tid(tid);
-    Ok(current_task().id().as_u64() as isize)
+    syscall_gettid()
 }
 
 /// 设置任务资源限制
@@ -362,82 +559,184 @@ pub fn syscall_set_tid_address(args: [usize; 6]) -> SyscallResult {
 ///
 /// # Arguments
 /// * `pid` - usize
-/// * `resource` - i32
+/// * `resource` - usize
 /// * `new_limit` - *const RLimit
 /// * `old_limit` - *mut RLimit
 pub fn syscall_prlimit64(args: [usize; 6]) -> SyscallResult {
-    let pid = args[0];
-    let resource = args[1] as i32;
+    let pid = args[0] as u64;
+    let resource = args[1];
     let new_limit = args[2] as *const RLimit;
     let old_limit = args[3] as *mut RLimit;
-    // 当pid不为0，其实没有权利去修改其他的进程的资源限制
+    if resource >= RLIM_NLIMITS {
+        return Err(SyscallError::EINVAL);
+    }
     let curr_process = current_process();
-    if pid == 0 || pid == curr_process.pid() as usize {
-        match resource {
-            RLIMIT_STACK => {
-                if old_limit as usize != 0 {
-                    unsafe {
-                        *old_limit = RLimit {
-                            rlim_cur: TASK_STACK_SIZE as u64,
-                            rlim_max: TASK_STACK_SIZE as u64,
-                        };
-                    }
-                }
-            }
-            RLIMIT_NOFILE => {
-                // 仅支持修改最大文件数
-                if old_limit as usize != 0 {
-                    let limit = curr_process.fd_manager.get_limit();
-                    unsafe {
-                        *old_limit = RLimit {
-                            rlim_cur: limit as u64,
-                            rlim_max: limit as u64,
-                        };
-                    }
-                }
-                if new_limit as usize != 0 {
-                    let new_limit = unsafe { (*new_limit).rlim_cur };
-                    curr_process.fd_manager.set_limit(new_limit);
-                }
-            }
-            RLIMIT_AS => {
-                const USER_MEMORY_LIMIT: usize = 0xffff_ffff;
-                if old_limit as usize != 0 {
-                    unsafe {
-                        *old_limit = RLimit {
-                            rlim_cur: USER_MEMORY_LIMIT as u64,
-                            rlim_max: USER_MEMORY_LIMIT as u64,
-                        };
-                    }
-                }
-            }
-            _ => {}
+    let pid = if pid == 0 {
+        curr_process.pid()
+    } else {
+        curr_process.pid_from_user(pid).ok_or(SyscallError::ESRCH)?
+    };
+    let process = if pid == curr_process.pid() {
+        Arc::clone(&curr_process)
+    } else {
+        let process = PID2PC
+            .lock()
+            .get(&pid)
+            .cloned()
+            .ok_or(SyscallError::ESRCH)?;
+        // 只能访问同一用户的进程，除非拥有 CAP_SYS_RESOURCE
+        let cred = curr_process.cred();
+        let target = process.cred();
+        if !cred.capable(cred::Privileges::SYS_RESOURCE)
+            && !(cred.uid == target.uid
+                && cred.uid == target.euid
+                && cred.uid == target.suid
+                && cred.gid == target.gid
+                && cred.gid == target.egid
+                && cred.gid == target.sgid)
+        {
+            return Err(SyscallError::EPERM);
+        }
+        process
+    };
+    let new_limit = if new_limit.is_null() {
+        None
+    } else {
+        if curr_process.manual_alloc_type_for_lazy(new_limit).is_err() {
+            return Err(SyscallError::EFAULT);
+        }
+        let new_limit = unsafe { *new_limit };
+        if new_limit.rlim_cur > new_limit.rlim_max {
+            return Err(SyscallError::EINVAL);
+        }
+        Some(new_limit)
+    };
+    if !old_limit.is_null() && curr_process.manual_alloc_type_for_lazy(old_limit).is_err() {
+        return Err(SyscallError::EFAULT);
+    }
+    let old = process.get_rlimit(resource);
+    if let Some(new_limit) = new_limit {
+        process
+            .set_rlimit(resource, new_limit)
+            .map_err(|err| match err {
+                AxError::PermissionDenied => SyscallError::EPERM,
+                err => SyscallError::from(err),
+            })?;
+    }
+    if !old_limit.is_null() {
+        unsafe {
+            *old_limit = old;
         }
     }
     Ok(0)
 }
 
-/// not support
-pub fn syscall_getpgid() -> SyscallResult {
-    Ok(0)
+/// 获取资源限制，等价于 pid 为 0 的 prlimit64
+/// # Arguments
+/// * `resource` - usize
+/// * `old_limit` - *mut RLimit
+pub fn syscall_getrlimit(args: [usize; 6]) -> SyscallResult {
+    syscall_prlimit64([0, args[0], 0, args[1], 0, 0])
+}
+
+/// 设置资源限制，等价于 pid 为 0 的 prlimit64
+/// # Arguments
+/// * `resource` - usize
+/// * `new_limit` - *const RLimit
+pub fn syscall_setrlimit(args: [usize; 6]) -> SyscallResult {
+    syscall_prlimit64([0, args[0], args[1], 0, 0, 0])
 }
 
+/// 获取进程组号
 /// # Arguments
-/// * `pgid`: usize
+/// * `pid`: usize, 进程号，为 0 时表示当前进程
+pub fn syscall_getpgid(args: [usize; 6]) -> SyscallResult {
+    let pid = args[0] as u64;
+    let curr_process = current_process();
+    if pid == 0 {
+        return Ok(curr_process.pid_to_user(curr_process.get_pgid()) as isize);
+    }
+    let pid = curr_process.pid_from_user(pid).ok_or(SyscallError::ESRCH)?;
+    match PID2PC.lock().get(&pid) {
+        Some(process) => Ok(curr_process.pid_to_user(process.get_pgid()) as isize),
+        None => Err(SyscallError::ESRCH),
+    }
+}
+
+/// 获取进程的会话号
+/// # Arguments
+/// * `pid`: usize, 进程号，为 0 时表示当前进程
+pub fn syscall_getsid(args: [usize; 6]) -> SyscallResult {
+    let pid = args[0] as u64;
+    let curr_process = current_process();
+    if pid == 0 {
+        return Ok(curr_process.pid_to_user(curr_process.get_sid()) as isize);
+    }
+    let pid = curr_process.pid_from_user(pid).ok_or(SyscallError::ESRCH)?;
+    match PID2PC.lock().get(&pid) {
+        Some(process) => Ok(curr_process.pid_to_user(process.get_sid()) as isize),
+        None => Err(SyscallError::ESRCH),
+    }
+}
+
+/// 设置进程组号
+///
+/// 只能设置当前进程或其子进程的进程组，且只能加入同一会话中的进程组
+/// # Arguments
+/// * `pid`: usize, 进程号，为 0 时表示当前进程
+/// * `pgid`: usize, 进程组号，为 0 时表示与 pid 相同
 pub fn syscall_setpgid(args: [usize; 6]) -> SyscallResult {
-    let pgid = args[0];
-    info!("not support setpgid, try to set {}", pgid);
+    let pgid = args[1] as isize;
+    if pgid < 0 {
+        return Err(SyscallError::EINVAL);
+    }
+    let process = current_process();
+    let pid = if args[0] == 0 {
+        process.pid()
+    } else {
+        process
+            .pid_from_user(args[0] as u64)
+            .ok_or(SyscallError::ESRCH)?
+    };
+    let pgid = if pgid == 0 {
+        pid
+    } else {
+        process
+            .pid_from_user(pgid as u64)
+            .ok_or(SyscallError::EPERM)?
+    };
+    let pid2pc = PID2PC.lock();
+    let target = pid2pc.get(&pid).ok_or(SyscallError::ESRCH)?;
+    if pid != process.pid() && target.get_parent() != process.pid() {
+        return Err(SyscallError::ESRCH);
+    }
+    // 会话首进程不能改变进程组，也不能把其他会话的进程移入
+    if target.is_session_leader() || target.get_sid() != process.get_sid() {
+        return Err(SyscallError::EPERM);
+    }
+    if pgid != pid
+        && !pid2pc
+            .values()
+            .any(|other| other.get_pgid() == pgid && other.get_sid() == process.get_sid())
+    {
+        return Err(SyscallError::EPERM);
+    }
+    target.set_pgid(pgid);
     Ok(0)
 }
 
 /// 当前不涉及多核情况
 pub fn syscall_getpid() -> SyscallResult {
-    Ok(current_process().pid() as isize)
+    let process = current_process();
+    Ok(process.pid_to_user(process.pid()) as isize)
 }
 
 /// To get the parent process id
+///
+/// 父进程不在当前 PID 命名空间中时返回 0
 pub fn syscall_getppid() -> SyscallResult {
-    Ok(current_process().get_parent() as isize)
+    let process = current_process();
+    Ok(process.pid_to_user(process.get_parent()) as isize)
 }
 
 /// # Arguments
@@ -447,72 +746,27 @@ pub fn syscall_umask(args: [usize; 6]) -> SyscallResult {
     Ok(current_process().fd_manager.set_mask(new_mask) as isize)
 }
 
-/// 获取用户 id。在实现多用户权限前默认为最高权限
-pub fn syscall_getuid() -> SyscallResult {
-    Ok(0)
-}
-
-/// 获取有效用户 id，即相当于哪个用户的权限。在实现多用户权限前默认为最高权限
-pub fn syscall_geteuid() -> SyscallResult {
-    Ok(0)
-}
-
-/// 获取用户组 id。在实现多用户权限前默认为最高权限
-pub fn syscall_getgid() -> SyscallResult {
-    Ok(0)
-}
-
-/// 获取有效用户组 id，即相当于哪个用户的权限。在实现多用户权限前默认为最高权限
-pub fn syscall_getegid() -> SyscallResult {
-    Ok(0)
-}
-
-/// 获取当前任务的线程 id
+/// 获取当前任务的线程 id，即线程在所属 PID 命名空间中的编号
 pub fn syscall_gettid() -> SyscallResult {
-    Ok(current_task().id().as_u64() as isize)
+    let tid = current_task().id().as_u64();
+    Ok(current_process().pid_to_user(tid) as isize)
 }
 
 /// Creates a session and sets the process group ID
 ///
-/// The calling process is the leader of the new session
+/// The calling process is the leader of the new session and of a new process group,
+/// and has no controlling terminal
 pub fn syscall_setsid() -> SyscallResult {
     let process = current_process();
-    let task = current_task();
-
-    let task_id = task.id().as_u64();
-
-    // 当前 process 已经是 process group leader
-    if process.pid() == task_id {
+    let pid = process.pid();
+    // 进程组组长不能创建新会话，否则原进程组的其他成员会与组长处于不同会话
+    if PID2PC.lock().values().any(|other| other.get_pgid() == pid) {
         return Err(SyscallError::EPERM);
     }
-
-    // 从当前 process 的 thread group 中移除 calling thread
-    process.tasks.lock().retain(|t| t.id().as_u64() != task_id);
-
-    // 新建 process group 并加入
-    let new_process = Process::new(
-        TaskId::new().as_u64(),
-        process.get_parent(),
-        process.memory_set.clone(),
-        process.get_heap_bottom(),
-        process.fd_manager.fd_table.lock().clone(),
-    );
-    #[cfg(feature = "signal")]
-    new_process
-        .signal_modules
-        .lock()
-        .insert(task_id, SignalModule::init_signal(None));
-
-    new_process.tasks.lock().push(task.as_task_ref().clone());
-    task.set_leader(true);
-    task.set_process_id(new_process.pid());
-
-    // 修改 PID2PC
-    PID2PC
-        .lock()
-        .insert(new_process.pid(), Arc::new(new_process));
-
-    Ok(task_id as isize)
+    process.set_sid(pid);
+    process.set_pgid(pid);
+    process.set_ctty(None);
+    Ok(process.pid_to_user(pid) as isize)
 }
 
 /// arch_prc
@@ -588,6 +842,28 @@ pub fn syscall_prctl(args: [usize; 6]) -> SyscallResult {
             }
         }
         Ok(PrctlOption::PR_SET_NAME) => Ok(0),
+        Ok(PrctlOption::PR_SET_NO_NEW_PRIVS) => {
+            // 只能设置不能清除，其余参数必须为 0
+            if args[1] != 1 || args[2..5].iter().any(|&arg| arg != 0) {
+                return Err(SyscallError::EINVAL);
+            }
+            current_process()
+                .no_new_privs
+                .store(true, Ordering::Release);
+            Ok(0)
+        }
+        Ok(PrctlOption::PR_GET_NO_NEW_PRIVS) => {
+            if args[1..5].iter().any(|&arg| arg != 0) {
+                return Err(SyscallError::EINVAL);
+            }
+            Ok(current_process().no_new_privs.load(Ordering::Acquire) as isize)
+        }
+        #[cfg(feature = "signal")]
+        Ok(PrctlOption::PR_GET_SECCOMP) => Ok(current_process().seccomp.lock().mode() as isize),
+        #[cfg(feature = "signal")]
+        Ok(PrctlOption::PR_SET_SECCOMP) => {
+            set_seccomp_mode(args[1], 0, args[2] as *const SockFprog)
+        }
         _ => Ok(0),
     }
 }
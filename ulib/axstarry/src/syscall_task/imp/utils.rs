@@ -1,13 +1,20 @@
+extern crate alloc;
+use alloc::sync::Arc;
 use core::{slice::from_raw_parts_mut, time::Duration};
 
+use axfs::api::PollWaker;
 use axhal::time::{current_time, current_time_nanos, nanos_to_ticks, NANOS_PER_SEC};
 
-use axprocess::{current_process, current_task, time_stat_output};
+use axprocess::{cred::Privileges, current_process, current_task, time_stat_output};
 use rand::{rngs::SmallRng, Fill, SeedableRng};
 
+use crate::syscall_fs::PollWaiter;
+use crate::syscall_trace::{
+    clear_syslog, read_syslog, syslog_buffer_size, syslog_notifier, syslog_unread_size,
+};
 use crate::{
-    ClockId, ITimerVal, RusageFlags, SysInfo, SyscallError, SyscallResult, TimeSecs, TimeVal, Tms,
-    UtsName,
+    ClockId, ITimerVal, RusageFlags, SysInfo, SyscallError, SyscallResult, SyslogAction, TimeSecs,
+    TimeVal, Tms, UtsName,
 };
 
 /// 返回值为当前经过的时钟中断数
@@ -55,13 +62,43 @@ pub fn syscall_clock_get_time(args: [usize; 6]) -> SyscallResult {
     Ok(0)
 }
 
+/// 获取当前线程所在的 CPU 与 NUMA 节点，vDSO 中的 getcpu 也会陷入到这里
+/// # Arguments
+/// * `cpu` - *mut u32
+/// * `node` - *mut u32
+pub fn syscall_getcpu(args: [usize; 6]) -> SyscallResult {
+    let cpu = args[0] as *mut u32;
+    let node = args[1] as *mut u32;
+    let process = current_process();
+    for ptr in [cpu, node] {
+        if !ptr.is_null()
+            && process
+                .manual_alloc_type_for_lazy(ptr as *const u32)
+                .is_err()
+        {
+            return Err(SyscallError::EFAULT);
+        }
+    }
+    unsafe {
+        if !cpu.is_null() {
+            *cpu = axhal::cpu::this_cpu_id() as u32;
+        }
+        if !node.is_null() {
+            // 只有一个 NUMA 节点
+            *node = 0;
+        }
+    }
+    Ok(0)
+}
+
 /// 获取系统信息
 /// # Arguments
 /// * `uts` - *mut UtsName
 pub fn syscall_uname(args: [usize; 6]) -> SyscallResult {
     let uts = args[0] as *mut UtsName;
+    let uts_ns = current_process().uts_ns();
     unsafe {
-        *uts = UtsName::default();
+        *uts = UtsName::new(&uts_ns.nodename.lock(), &uts_ns.domainname.lock());
     }
     Ok(0)
 }
@@ -303,3 +340,81 @@ pub fn syscall_clock_nanosleep(args: [usize; 6]) -> SyscallResult {
     }
     Ok(0)
 }
+
+/// 读取或控制内核日志缓冲区，其中保存的是内核系统调用跟踪的记录
+///
+/// 日志中有所有进程的系统调用记录，除了查询缓冲区大小，其余操作都需要 CAP_SYSLOG
+/// # Arguments
+/// * `action` - usize, 操作类型，见 `SyslogAction`
+/// * `buf` - *mut u8, 读取操作的缓冲区
+/// * `len` - usize, 缓冲区长度
+pub fn syscall_syslog(args: [usize; 6]) -> SyscallResult {
+    let action = SyslogAction::try_from(args[0]).map_err(|_| SyscallError::EINVAL)?;
+    let buf = args[1] as *mut u8;
+    let len = args[2];
+    if !matches!(
+        action,
+        SyslogAction::SYSLOG_ACTION_CLOSE
+            | SyslogAction::SYSLOG_ACTION_OPEN
+            | SyslogAction::SYSLOG_ACTION_SIZE_BUFFER
+    ) && !current_process().cred().capable(Privileges::SYSLOG)
+    {
+        return Err(SyscallError::EPERM);
+    }
+    match action {
+        SyslogAction::SYSLOG_ACTION_READ
+        | SyslogAction::SYSLOG_ACTION_READ_ALL
+        | SyslogAction::SYSLOG_ACTION_READ_CLEAR => {
+            if (len as isize) < 0 {
+                return Err(SyscallError::EINVAL);
+            }
+            if len == 0 {
+                return Ok(0);
+            }
+            if buf.is_null()
+                || current_process()
+                    .manual_alloc_range_for_lazy(
+                        (buf as usize).into(),
+                        (buf as usize + len - 1).into(),
+                    )
+                    .is_err()
+            {
+                return Err(SyscallError::EFAULT);
+            }
+            let buf = unsafe { from_raw_parts_mut(buf, len) };
+            if action == SyslogAction::SYSLOG_ACTION_READ {
+                let waiter = Arc::new(PollWaiter::new(false));
+                let waker: Arc<dyn PollWaker> = waiter.clone();
+                syslog_notifier().register(&waker);
+                let result = loop {
+                    // 先清除通知再读取，此后写入的记录会使等待立即返回
+                    waiter.prepare();
+                    let read_len = read_syslog(buf, true);
+                    // 缓冲区放不下下一条记录时也直接返回，避免一直阻塞
+                    if read_len > 0 || syslog_unread_size() > 0 {
+                        break Ok(read_len as isize);
+                    }
+                    #[cfg(feature = "signal")]
+                    if current_process().have_signals().is_some() {
+                        break Err(SyscallError::EINTR);
+                    }
+                    waiter.wait(usize::MAX, false);
+                };
+                syslog_notifier().unregister(&waker);
+                return result;
+            }
+            let read_len = read_syslog(buf, false);
+            if action == SyslogAction::SYSLOG_ACTION_READ_CLEAR {
+                clear_syslog();
+            }
+            Ok(read_len as isize)
+        }
+        SyslogAction::SYSLOG_ACTION_CLEAR => {
+            clear_syslog();
+            Ok(0)
+        }
+        SyslogAction::SYSLOG_ACTION_SIZE_UNREAD => Ok(syslog_unread_size() as isize),
+        SyslogAction::SYSLOG_ACTION_SIZE_BUFFER => Ok(syslog_buffer_size() as isize),
+        _ => Ok(0),
+    }
+}
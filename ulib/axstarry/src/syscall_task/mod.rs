@@ -21,11 +21,16 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
         TIMES => syscall_time(args),
         UNAME => syscall_uname(args),
         GETTIMEOFDAY => syscall_get_time_of_day(args),
-        GETPGID => syscall_getpgid(),
+        GETCPU => syscall_getcpu(args),
+        GETPGID => syscall_getpgid(args),
+        GETSID => syscall_getsid(args),
         SETPGID => syscall_setpgid(args),
         GETPID => syscall_getpid(),
         GETPPID => syscall_getppid(),
         WAIT4 => syscall_wait4(args),
+        #[cfg(feature = "signal")]
+        WAITID => syscall_waitid(args),
+        PIDFD_OPEN => syscall_pidfd_open(args),
         GETRANDOM => syscall_getrandom(args),
         #[cfg(feature = "signal")]
         SIGSUSPEND => syscall_sigsuspend(args),
@@ -36,7 +41,19 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
         #[cfg(feature = "signal")]
         TKILL => syscall_tkill(args),
         #[cfg(feature = "signal")]
-        TGKILL => syscall_tkill(args),
+        TGKILL => syscall_tgkill(args),
+        #[cfg(feature = "signal")]
+        RT_SIGQUEUEINFO => syscall_rt_sigqueueinfo(args),
+        #[cfg(feature = "signal")]
+        RT_TGSIGQUEUEINFO => syscall_rt_tgsigqueueinfo(args),
+        #[cfg(feature = "signal")]
+        SIGTIMEDWAIT => syscall_sigtimedwait(args),
+        #[cfg(feature = "signal")]
+        SIGALTSTACK => syscall_sigaltstack(args),
+        #[cfg(feature = "signal")]
+        PTRACE => syscall_ptrace(args),
+        #[cfg(feature = "signal")]
+        SECCOMP => syscall_seccomp(args),
         #[cfg(feature = "signal")]
         SIGPROCMASK => syscall_sigprocmask(args),
         #[cfg(feature = "signal")]
@@ -44,11 +61,31 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
         EXIT_GROUP => syscall_exit(args),
         SET_TID_ADDRESS => syscall_set_tid_address(args),
         PRLIMIT64 => syscall_prlimit64(args),
+        GETRLIMIT => syscall_getrlimit(args),
+        SETRLIMIT => syscall_setrlimit(args),
         CLOCK_GET_TIME => syscall_clock_get_time(args),
         GETUID => syscall_getuid(),
         GETEUID => syscall_geteuid(),
         GETGID => syscall_getgid(),
         GETEGID => syscall_getegid(),
+        SETUID => syscall_setuid(args),
+        SETGID => syscall_setgid(args),
+        SETREUID => syscall_setreuid(args),
+        SETREGID => syscall_setregid(args),
+        SETRESUID => syscall_setresuid(args),
+        SETRESGID => syscall_setresgid(args),
+        GETRESUID => syscall_getresuid(args),
+        GETRESGID => syscall_getresgid(args),
+        SETFSUID => syscall_setfsuid(args),
+        SETFSGID => syscall_setfsgid(args),
+        GETGROUPS => syscall_getgroups(args),
+        SETGROUPS => syscall_setgroups(args),
+        CAPGET => syscall_capget(args),
+        CAPSET => syscall_capset(args),
+        UNSHARE => syscall_unshare(args),
+        SETNS => syscall_setns(args),
+        SETHOSTNAME => syscall_sethostname(args),
+        SETDOMAINNAME => syscall_setdomainname(args),
         GETTID => syscall_gettid(),
         #[cfg(feature = "futex")]
         FUTEX => syscall_futex(args),
@@ -62,9 +99,8 @@ pub fn task_syscall(syscall_id: task_syscall_id::TaskSyscallId, args: [usize; 6]
         SETSID => syscall_setsid(),
         GETRUSAGE => syscall_getrusage(args),
         UMASK => syscall_umask(args),
+        SYSLOG => syscall_syslog(args),
         // 不做处理即可
-        SIGTIMEDWAIT => Ok(0),
-        SYSLOG => Ok(0),
         MADVICE => Ok(0),
         SCHED_SETAFFINITY => Ok(0),
         SCHED_GETAFFINITY => syscall_sched_getaffinity(args),
@@ -31,6 +31,7 @@ pub enum TaskSyscallId {
     GET_MEMPOLICY = 236,
     SETPGID = 154,
     GETPGID = 155,
+    GETSID = 156,
     SETSID = 157,
     GETRUSAGE = 165,
     UMASK = 166,
@@ -43,11 +44,28 @@ pub enum TaskSyscallId {
     GETEGID = 177,
     GETTID = 178,
     SYSINFO = 179,
+    // 用户与特权
+    SETREGID = 143,
+    SETGID = 144,
+    SETREUID = 145,
+    SETUID = 146,
+    SETRESUID = 147,
+    GETRESUID = 148,
+    SETRESGID = 149,
+    GETRESGID = 150,
+    SETFSUID = 151,
+    SETFSGID = 152,
+    GETGROUPS = 158,
+    SETGROUPS = 159,
+    CAPGET = 90,
+    CAPSET = 91,
     CLONE = 220,
     CLONE3 = 435,
     EXECVE = 221,
     MADVICE = 233,
     WAIT4 = 260,
+    WAITID = 95,
+    PIDFD_OPEN = 434,
     GETRANDOM = 278,
     SCHED_YIELD = 124,
     CLOCK_GET_TIME = 113,
@@ -55,11 +73,24 @@ pub enum TaskSyscallId {
     TIMES = 153,
     UNAME = 160,
     GETTIMEOFDAY = 169,
+    GETCPU = 168,
     PRLIMIT64 = 261,
+    GETRLIMIT = 163,
+    SETRLIMIT = 164,
+    SECCOMP = 277,
+    // 命名空间
+    UNSHARE = 97,
+    SETNS = 268,
+    SETHOSTNAME = 161,
+    SETDOMAINNAME = 162,
     // 信号模块
     KILL = 129,
     TKILL = 130,
     TGKILL = 131,
+    RT_SIGQUEUEINFO = 138,
+    RT_TGSIGQUEUEINFO = 240,
+    SIGALTSTACK = 132,
+    PTRACE = 117,
     SIGSUSPEND = 133,
     SIGACTION = 134,
     SIGPROCMASK = 135,
@@ -102,15 +133,33 @@ numeric_enum_macro::numeric_enum! {
         GETEUID = 107,
         GETGID = 104,
         GETPGID = 121,
+        GETSID = 124,
         SETPGID = 109,
         GETEGID = 108,
         GETTID = 186,
         SYSINFO = 99,
+        // 用户与特权
+        SETUID = 105,
+        SETGID = 106,
+        SETREUID = 113,
+        SETREGID = 114,
+        GETGROUPS = 115,
+        SETGROUPS = 116,
+        SETRESUID = 117,
+        GETRESUID = 118,
+        SETRESGID = 119,
+        GETRESGID = 120,
+        SETFSUID = 122,
+        SETFSGID = 123,
+        CAPGET = 125,
+        CAPSET = 126,
         CLONE = 56,
         CLONE3 = 435,
         EXECVE = 59,
         MADVICE = 28,
         WAIT4 = 61,
+        WAITID = 247,
+        PIDFD_OPEN = 434,
         GETRANDOM = 318,
         SCHED_YIELD = 24,
         CLOCK_GET_TIME = 228,
@@ -118,12 +167,25 @@ numeric_enum_macro::numeric_enum! {
         TIMES = 100,
         UNAME = 63,
         GETTIMEOFDAY = 96,
+        GETCPU = 309,
         PRLIMIT64 = 302,
+        GETRLIMIT = 97,
+        SETRLIMIT = 160,
+        SECCOMP = 317,
         RSEQ = 334,
+        // 命名空间
+        UNSHARE = 272,
+        SETNS = 308,
+        SETHOSTNAME = 170,
+        SETDOMAINNAME = 171,
         // 信号模块
         KILL = 62,
         TKILL = 200,
         TGKILL = 234,
+        RT_SIGQUEUEINFO = 129,
+        RT_TGSIGQUEUEINFO = 297,
+        SIGALTSTACK = 131,
+        PTRACE = 101,
         SIGSUSPEND = 130,
         SIGACTION = 13,
         SIGPROCMASK = 14,
@@ -0,0 +1,352 @@
+//! 将系统调用的参数与返回值解码为类似 strace 的文本
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+
+use axprocess::current_process;
+use axprocess::link::{raw_ptr_to_ref_str, AT_FDCWD};
+
+use crate::SyscallError;
+
+/// 字符串参数最多展示的字节数
+const MAX_STR_LEN: usize = 64;
+
+/// 参数的解码方式
+#[derive(Clone, Copy)]
+enum Arg {
+    /// 有符号十进制整数
+    Int,
+    /// 无符号十进制整数
+    UInt,
+    /// 十六进制数，如地址与标志位
+    Hex,
+    /// 八进制的权限位
+    Mode,
+    /// 文件描述符，AT_FDCWD 会被特殊显示
+    Fd,
+    /// 以 `\0` 结尾的路径字符串
+    Path,
+    /// open 的标志位
+    OpenFlags,
+    /// 长度由第 n 个参数给出的用户缓冲区
+    Buf(usize),
+    /// 长度由第 n 个参数给出的 socket 地址
+    SockAddr(usize),
+    /// 信号编号
+    Signal,
+}
+
+use Arg::*;
+
+/// 根据系统调用名称给出各个参数的解码方式
+///
+/// 名称与各架构的系统调用号无关，因此同一张表可以在所有架构上使用。
+/// 返回 None 表示未知的系统调用，此时所有参数按十六进制输出
+fn arg_layout(name: &str) -> Option<&'static [Arg]> {
+    let layout: &'static [Arg] = match name {
+        // fs
+        "OPENAT" => &[Fd, Path, OpenFlags, Mode],
+        "OPEN" => &[Path, OpenFlags, Mode],
+        "CREAT" | "MKDIR" | "ACCESS" => &[Path, Mode],
+        "CLOSE" | "DUP" | "FSYNC" => &[Fd],
+        "DUP2" => &[Fd, Fd],
+        "DUP3" => &[Fd, Fd, Hex],
+        "READ" | "READV" | "WRITEV" | "GETDENTS64" => &[Fd, Hex, UInt],
+        "WRITE" => &[Fd, Buf(2), UInt],
+        "PREAD64" => &[Fd, Hex, UInt, Int],
+        "PWRITE64" => &[Fd, Buf(2), UInt, Int],
+        "LSEEK" => &[Fd, Int, UInt],
+        "FSTAT" | "FSTATFS" => &[Fd, Hex],
+        "STAT" | "LSTAT" | "STATFS" => &[Path, Hex],
+        "FSTATAT" => &[Fd, Path, Hex, Hex],
+        "STATX" => &[Fd, Path, Hex, Hex, Hex],
+        "MKDIRAT" | "FCHMODAT" => &[Fd, Path, Mode],
+        "RMDIR" | "UNLINK" | "CHDIR" => &[Path],
+        "UNLINKAT" => &[Fd, Path, Hex],
+        "RENAME" | "SYMLINK" => &[Path, Path],
+        "SYMLINKAT" => &[Path, Fd, Path],
+        "LINKAT" | "RENAMEAT" | "RENAMEAT2" => &[Fd, Path, Fd, Path, Hex],
+        "FACCESSAT" => &[Fd, Path, Mode, Hex],
+        "FCHMOD" => &[Fd, Mode],
+        "FCHOWN" => &[Fd, Int, Int],
+        "READLINK" => &[Path, Hex, UInt],
+        "PREADLINKAT" => &[Fd, Path, Hex, UInt],
+        "FCNTL64" => &[Fd, UInt, Hex],
+        "FLOCK" => &[Fd, Hex],
+        "IOCTL" => &[Fd, Hex, Hex],
+        "MOUNT" => &[Path, Path, Path, Hex, Hex],
+        "UNMOUNT" => &[Path, Hex],
+        "FTRUNCATE64" => &[Fd, Int],
+        "FALLOCATE" => &[Fd, Hex, Int, Int],
+        "UTIMENSAT" => &[Fd, Path, Hex, Hex],
+        "PIPE" => &[Hex],
+        "PIPE2" => &[Hex, Hex],
+        "GETCWD" => &[Hex, UInt],
+        "SENDFILE64" => &[Fd, Fd, Hex, UInt],
+        "COPYFILERANGE" => &[Fd, Hex, Fd, Hex, UInt, Hex],
+        "EPOLL_CTL" => &[Fd, Int, Fd, Hex],
+        "EPOLL_WAIT" => &[Fd, Hex, Int, Int],
+        "INOTIFY_INIT" => &[],
+        "INOTIFY_INIT1" => &[Hex],
+        "INOTIFY_ADD_WATCH" => &[Fd, Path, Hex],
+        "INOTIFY_RM_WATCH" => &[Fd, Int],
+        // net
+        "SOCKET" => &[Int, Int, Int],
+        "BIND" | "CONNECT" => &[Fd, SockAddr(2), UInt],
+        "LISTEN" | "SHUTDOWN" => &[Fd, Int],
+        "ACCEPT" | "GETSOCKNAME" | "GETPEERNAME" => &[Fd, Hex, Hex],
+        "ACCEPT4" => &[Fd, Hex, Hex, Hex],
+        "SENDTO" => &[Fd, Buf(2), UInt, Hex, SockAddr(5), UInt],
+        "RECVFROM" => &[Fd, Hex, UInt, Hex, Hex, Hex],
+        "SETSOCKOPT" | "GETSOCKOPT" => &[Fd, Int, Int, Hex, Hex],
+        // mem
+        "MMAP" => &[Hex, UInt, Hex, Hex, Fd, Hex],
+        "MUNMAP" => &[Hex, UInt],
+        "MPROTECT" => &[Hex, UInt, Hex],
+        "BRK" => &[Hex],
+        // task
+        "EXECVE" => &[Path, Hex, Hex],
+        "EXIT" | "EXIT_GROUP" => &[Int],
+        "KILL" | "TKILL" => &[Int, Signal],
+        "TGKILL" => &[Int, Int, Signal],
+        "WAIT4" => &[Int, Hex, Hex, Hex],
+        "CLONE" => &[Hex, Hex, Hex, Hex, Hex],
+        "SIGACTION" => &[Signal, Hex, Hex],
+        "SIGPROCMASK" => &[Int, Hex, Hex],
+        "NANO_SLEEP" => &[Hex, Hex],
+        "GETPID" | "GETPPID" | "GETTID" | "GETUID" | "GETEUID" | "GETGID" | "GETEGID"
+        | "SCHED_YIELD" | "SETSID" | "SYNC" | "FORK" | "VFORK" | "SIGRETURN" => &[],
+        _ => return None,
+    };
+    Some(layout)
+}
+
+/// 读取用户态的字符串，读取失败时返回 None
+fn read_user_str(addr: usize) -> Option<&'static str> {
+    if addr == 0
+        || current_process()
+            .manual_alloc_for_lazy(addr.into())
+            .is_err()
+    {
+        return None;
+    }
+    Some(unsafe { raw_ptr_to_ref_str(addr as *const u8) })
+}
+
+/// 读取用户态给定长度的缓冲区，读取失败时返回 None
+fn read_user_bytes(addr: usize, len: usize) -> Option<&'static [u8]> {
+    if addr == 0 {
+        return None;
+    }
+    if len > 0
+        && current_process()
+            .manual_alloc_range_for_lazy(addr.into(), (addr + len - 1).into())
+            .is_err()
+    {
+        return None;
+    }
+    Some(unsafe { core::slice::from_raw_parts(addr as *const u8, len) })
+}
+
+/// 以带转义的 C 字符串形式输出，超出长度的部分以 `...` 表示
+fn push_escaped(out: &mut String, bytes: &[u8]) {
+    out.push('"');
+    for &byte in bytes.iter().take(MAX_STR_LEN) {
+        match byte {
+            b'"' => out.push_str("\\\""),
+            b'\\' => out.push_str("\\\\"),
+            b'\n' => out.push_str("\\n"),
+            b'\t' => out.push_str("\\t"),
+            0x20..=0x7e => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "\\x{:02x}", byte);
+            }
+        }
+    }
+    out.push('"');
+    if bytes.len() > MAX_STR_LEN {
+        out.push_str("...");
+    }
+}
+
+/// open 的标志位，与 Linux 用户态的定义一致
+const OPEN_FLAG_NAMES: &[(usize, &str)] = &[
+    (0o100, "O_CREAT"),
+    (0o200, "O_EXCL"),
+    (0o400, "O_NOCTTY"),
+    (0o1000, "O_TRUNC"),
+    (0o2000, "O_APPEND"),
+    (0o4000, "O_NONBLOCK"),
+    (0o10000, "O_DSYNC"),
+    #[cfg(target_arch = "aarch64")]
+    (0o40000, "O_DIRECTORY"),
+    #[cfg(target_arch = "aarch64")]
+    (0o100000, "O_NOFOLLOW"),
+    #[cfg(not(target_arch = "aarch64"))]
+    (0o200000, "O_DIRECTORY"),
+    #[cfg(not(target_arch = "aarch64"))]
+    (0o400000, "O_NOFOLLOW"),
+    (0o2000000, "O_CLOEXEC"),
+];
+
+fn push_open_flags(out: &mut String, flags: usize) {
+    out.push_str(match flags & 0o3 {
+        0 => "O_RDONLY",
+        1 => "O_WRONLY",
+        _ => "O_RDWR",
+    });
+    let mut rest = flags & !0o3;
+    for &(bit, name) in OPEN_FLAG_NAMES {
+        if rest & bit != 0 {
+            out.push('|');
+            out.push_str(name);
+            rest &= !bit;
+        }
+    }
+    if rest != 0 {
+        let _ = write!(out, "|{:#o}", rest);
+    }
+}
+
+fn push_sockaddr(out: &mut String, addr: usize, len: usize) {
+    const AF_UNIX: u16 = 1;
+    const AF_INET: u16 = 2;
+    const AF_INET6: u16 = 10;
+    let Some(bytes) = read_user_bytes(addr, len.min(128)).filter(|bytes| bytes.len() >= 2) else {
+        let _ = write!(out, "{:#x}", addr);
+        return;
+    };
+    let family = u16::from_ne_bytes([bytes[0], bytes[1]]);
+    match family {
+        AF_INET if bytes.len() >= 8 => {
+            let port = u16::from_be_bytes([bytes[2], bytes[3]]);
+            let _ = write!(
+                out,
+                "{{AF_INET, {}.{}.{}.{}:{}}}",
+                bytes[4], bytes[5], bytes[6], bytes[7], port
+            );
+        }
+        AF_INET6 if bytes.len() >= 24 => {
+            let port = u16::from_be_bytes([bytes[2], bytes[3]]);
+            out.push_str("{AF_INET6, [");
+            for (i, group) in bytes[8..24].chunks(2).enumerate() {
+                if i > 0 {
+                    out.push(':');
+                }
+                let _ = write!(out, "{:x}", u16::from_be_bytes([group[0], group[1]]));
+            }
+            let _ = write!(out, "]:{}}}", port);
+        }
+        AF_UNIX => {
+            let path = &bytes[2..];
+            let end = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+            out.push_str("{AF_UNIX, ");
+            push_escaped(out, &path[..end]);
+            out.push('}');
+        }
+        _ => {
+            let _ = write!(out, "{{family={}}}", family);
+        }
+    }
+}
+
+fn push_arg(out: &mut String, kind: Arg, args: &[usize; 6], index: usize) {
+    let value = args[index];
+    match kind {
+        Int => {
+            let _ = write!(out, "{}", value as isize);
+        }
+        UInt => {
+            let _ = write!(out, "{}", value);
+        }
+        Hex => {
+            let _ = write!(out, "{:#x}", value);
+        }
+        Mode => {
+            let _ = write!(out, "{:#o}", value);
+        }
+        Fd => {
+            if value as i32 == AT_FDCWD as i32 {
+                out.push_str("AT_FDCWD");
+            } else {
+                let _ = write!(out, "{}", value as i32);
+            }
+        }
+        Path => match read_user_str(value) {
+            Some(path) => push_escaped(out, path.as_bytes()),
+            None => {
+                let _ = write!(out, "{:#x}", value);
+            }
+        },
+        OpenFlags => push_open_flags(out, value),
+        Buf(len_index) => {
+            let len = args[len_index];
+            match read_user_bytes(value, len.min(MAX_STR_LEN + 1)) {
+                Some(bytes) => push_escaped(out, bytes),
+                None => {
+                    let _ = write!(out, "{:#x}", value);
+                }
+            }
+        }
+        SockAddr(len_index) => {
+            if value == 0 {
+                out.push_str("NULL");
+            } else {
+                push_sockaddr(out, value, args[len_index]);
+            }
+        }
+        Signal => {
+            #[cfg(feature = "signal")]
+            {
+                let signal = axsignal::signal_no::SignalNo::from(value);
+                if signal != axsignal::signal_no::SignalNo::ERR {
+                    let _ = write!(out, "{:?}", signal);
+                    return;
+                }
+            }
+            let _ = write!(out, "{}", value);
+        }
+    }
+}
+
+/// 将系统调用的名称与参数解码为 `name(arg0, arg1, ...)` 的形式
+///
+/// 参数中的用户指针在系统调用执行之前读取，以免 execve 等调用替换地址空间后无法访问
+pub(super) fn decode_call(name: &str, args: &[usize; 6]) -> String {
+    let mut out = name.to_lowercase();
+    out.push('(');
+    match arg_layout(name) {
+        Some(layout) => {
+            for (index, &kind) in layout.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                push_arg(&mut out, kind, args, index);
+            }
+        }
+        None => {
+            let _ = write!(
+                out,
+                "{:#x}, {:#x}, {:#x}, {:#x}, {:#x}, {:#x}",
+                args[0], args[1], args[2], args[3], args[4], args[5]
+            );
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// 将系统调用的返回值解码为 ` = ret` 或 ` = -1 ERRNO (描述)` 的形式
+pub(super) fn decode_return(ret: isize) -> String {
+    // 与 Linux 一致，只有 [-4095, -1] 范围内的返回值表示错误
+    if (-4095..0).contains(&ret) {
+        if let Ok(err) = SyscallError::try_from(-ret as i32) {
+            return format!(" = -1 {:?} ({})", err, err.as_str());
+        }
+    }
+    if ret > 0xffff {
+        format!(" = {:#x}", ret)
+    } else {
+        format!(" = {}", ret)
+    }
+}
@@ -0,0 +1,106 @@
+//! `/proc/<pid>/syscalls` 文件
+//!
+//! 读取时按顺序返回该进程的跟踪记录，已读过的记录不会重复返回；
+//! 写入时设置该进程的过滤条件
+use alloc::format;
+use alloc::string::String;
+use axerrno::{AxError, AxResult};
+use axfs::api::{FileIO, FileIOType, OpenFlags};
+use axprocess::{current_process, PID2PC};
+use axsync::Mutex;
+
+use super::{read_process_log, set_filter, TraceClass};
+
+/// 某个进程的系统调用跟踪记录文件
+pub struct SyscallTraceFile {
+    pid: u64,
+    /// 下一条要读取的记录序号
+    seq: Mutex<u64>,
+    flags: OpenFlags,
+}
+
+impl SyscallTraceFile {
+    /// 打开给定进程的跟踪记录文件
+    pub fn new(pid: u64, flags: OpenFlags) -> Self {
+        Self {
+            pid,
+            seq: Mutex::new(0),
+            flags,
+        }
+    }
+}
+
+/// 若路径为 `/proc/<pid>/syscalls` 或 `/proc/self/syscalls`，则打开对应的跟踪记录文件
+///
+/// 路径不是跟踪记录文件时返回 None，对应的进程不存在时返回 NotFound。
+/// 与 ptrace 一致，只能打开自身可以跟踪的进程的文件，否则返回 PermissionDenied
+pub fn open_trace_file(path: &str, flags: OpenFlags) -> Option<AxResult<SyscallTraceFile>> {
+    let pid = path
+        .trim_start_matches('/')
+        .strip_prefix("proc/")?
+        .strip_suffix("/syscalls")?;
+    let process = current_process();
+    let pid = if pid == "self" {
+        process.pid()
+    } else {
+        // 路径中是当前 PID 命名空间中的进程号
+        let Some(pid) = process.pid_from_user(pid.parse().ok()?) else {
+            return Some(Err(AxError::NotFound));
+        };
+        let Some(target) = PID2PC.lock().get(&pid).cloned() else {
+            return Some(Err(AxError::NotFound));
+        };
+        if !process.cred().can_ptrace(&target.cred()) {
+            return Some(Err(AxError::PermissionDenied));
+        }
+        pid
+    };
+    Some(Ok(SyscallTraceFile::new(pid, flags)))
+}
+
+impl FileIO for SyscallTraceFile {
+    fn read(&self, buf: &mut [u8]) -> AxResult<usize> {
+        let mut seq = self.seq.lock();
+        Ok(read_process_log(self.pid, &mut seq, buf))
+    }
+
+    /// 写入过滤条件，如 `fs net`、`all`、`none`
+    fn write(&self, buf: &[u8]) -> AxResult<usize> {
+        let spec = core::str::from_utf8(buf).map_err(|_| AxError::InvalidInput)?;
+        let classes = TraceClass::parse(spec).ok_or(AxError::InvalidInput)?;
+        set_filter(self.pid, classes);
+        Ok(buf.len())
+    }
+
+    fn readable(&self) -> bool {
+        self.flags.readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.writable()
+    }
+
+    fn executable(&self) -> bool {
+        false
+    }
+
+    fn get_type(&self) -> FileIOType {
+        FileIOType::Other
+    }
+
+    fn get_path(&self) -> String {
+        format!("/proc/{}/syscalls", self.pid)
+    }
+
+    fn get_status(&self) -> OpenFlags {
+        self.flags
+    }
+
+    fn ready_to_read(&self) -> bool {
+        true
+    }
+
+    fn ready_to_write(&self) -> bool {
+        true
+    }
+}
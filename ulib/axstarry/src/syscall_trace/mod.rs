@@ -0,0 +1,283 @@
+//! 内核内置的系统调用跟踪
+//!
+//! 与 ptrace 不同，跟踪在内核中完成：被选中的进程每次发起系统调用时，
+//! 其名称、解码后的参数与返回值会被写入一个全局的环形缓冲区。
+//! 缓冲区可以通过 `/proc/<pid>/syscalls` 按进程读取，也可以通过 `syslog` 读取全部记录。
+//!
+//! 跟踪按进程开启，并可以只跟踪某几类系统调用，例如：
+//! `echo "fs net" > /proc/self/syscalls`。被跟踪进程 fork 出的子进程会继承其过滤条件，
+//! 进程退出时其过滤条件随之清除。
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Debug;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use axfs::api::PollNotifier;
+use axprocess::current_process;
+use axsync::Mutex;
+use axtask::current;
+use bitflags::bitflags;
+
+mod decode;
+
+mod file;
+
+pub use file::open_trace_file;
+
+bitflags! {
+    /// 系统调用的分类，与系统调用模块的划分一致
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct TraceClass: u32 {
+        /// 文件系统相关
+        const FS = 1 << 0;
+        /// 网络相关
+        const NET = 1 << 1;
+        /// 内存管理相关
+        const MEM = 1 << 2;
+        /// 任务、信号与时间相关
+        const TASK = 1 << 3;
+    }
+}
+
+impl TraceClass {
+    /// 解析过滤条件，如 `fs net`、`all`、`none`，以空白或逗号分隔
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut classes = TraceClass::empty();
+        for word in spec.split(|c: char| c.is_whitespace() || c == ',') {
+            classes |= match word {
+                "" => continue,
+                "fs" => TraceClass::FS,
+                "net" => TraceClass::NET,
+                "mem" => TraceClass::MEM,
+                "task" => TraceClass::TASK,
+                "all" => TraceClass::all(),
+                "none" | "off" => TraceClass::empty(),
+                _ => return None,
+            };
+        }
+        Some(classes)
+    }
+}
+
+/// 环形缓冲区的容量，按记录文本的总字节数计算
+const TRACE_LOG_CAPACITY: usize = 64 * 1024;
+
+/// 一条系统调用记录
+struct TraceRecord {
+    /// 全局递增的序号，读者据此记录读取进度
+    seq: u64,
+    pid: u64,
+    /// 完整的一行文本，以换行结尾
+    line: String,
+}
+
+/// 保存跟踪记录的环形缓冲区，写满后丢弃最旧的记录
+struct TraceLog {
+    records: VecDeque<TraceRecord>,
+    bytes: usize,
+    next_seq: u64,
+    /// `syslog` 以破坏性方式读取时的进度
+    syslog_seq: u64,
+}
+
+impl TraceLog {
+    const fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+            bytes: 0,
+            next_seq: 0,
+            syslog_seq: 0,
+        }
+    }
+
+    fn push(&mut self, pid: u64, line: String) {
+        self.bytes += line.len();
+        self.records.push_back(TraceRecord {
+            seq: self.next_seq,
+            pid,
+            line,
+        });
+        self.next_seq += 1;
+        while self.bytes > TRACE_LOG_CAPACITY {
+            let record = self.records.pop_front().unwrap();
+            self.bytes -= record.line.len();
+        }
+    }
+
+    /// 从序号 `*seq` 开始，把满足条件的完整记录复制到 buf 中，并推进 `*seq`
+    ///
+    /// 返回写入的字节数。放不下的记录留到下次读取
+    fn read_from(&self, seq: &mut u64, pid: Option<u64>, buf: &mut [u8]) -> usize {
+        let mut len = 0;
+        for record in self.records.iter().filter(|record| record.seq >= *seq) {
+            if pid.is_some_and(|pid| pid != record.pid) {
+                *seq = record.seq + 1;
+                continue;
+            }
+            let line = record.line.as_bytes();
+            if len + line.len() > buf.len() {
+                break;
+            }
+            buf[len..len + line.len()].copy_from_slice(line);
+            len += line.len();
+            *seq = record.seq + 1;
+        }
+        len
+    }
+
+    /// 序号不小于 seq 的记录总字节数
+    fn size_from(&self, seq: u64) -> usize {
+        self.records
+            .iter()
+            .filter(|record| record.seq >= seq)
+            .map(|record| record.line.len())
+            .sum()
+    }
+}
+
+static TRACE_LOG: Mutex<TraceLog> = Mutex::new(TraceLog::new());
+
+/// 有新记录写入时唤醒阻塞在 `syslog` 读取上的进程
+static SYSLOG_NOTIFIER: PollNotifier = PollNotifier::new();
+
+/// 写入一条记录并唤醒等待记录的读者
+fn append_log(pid: u64, line: String) {
+    TRACE_LOG.lock().push(pid, line);
+    SYSLOG_NOTIFIER.notify();
+}
+
+/// 每个进程的过滤条件，不在其中的进程不被跟踪
+static TRACE_FILTERS: Mutex<BTreeMap<u64, TraceClass>> = Mutex::new(BTreeMap::new());
+
+/// 是否存在被跟踪的进程，未开启跟踪时系统调用路径只需检查这一个标志
+static TRACE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 设置进程的过滤条件，条件为空即停止跟踪该进程
+pub fn set_filter(pid: u64, classes: TraceClass) {
+    let mut filters = TRACE_FILTERS.lock();
+    if classes.is_empty() {
+        filters.remove(&pid);
+    } else {
+        filters.insert(pid, classes);
+    }
+    TRACE_ENABLED.store(!filters.is_empty(), Ordering::Release);
+}
+
+/// 进程退出时移除其过滤条件
+struct ProcessExitImpl;
+
+#[crate_interface::impl_interface]
+impl axprocess::ProcessExitIf for ProcessExitImpl {
+    fn process_exit(pid: u64) {
+        set_filter(pid, TraceClass::empty());
+    }
+}
+
+/// 获取进程的过滤条件
+pub fn filter_of(pid: u64) -> TraceClass {
+    TRACE_FILTERS
+        .lock()
+        .get(&pid)
+        .copied()
+        .unwrap_or(TraceClass::empty())
+}
+
+/// 一次正在执行的系统调用的跟踪记录，在系统调用返回时补全返回值
+pub struct PendingTrace {
+    pid: u64,
+    line: String,
+    /// 是否为创建进程的系统调用，返回后子进程继承过滤条件
+    is_fork: bool,
+}
+
+/// 在系统调用进入时调用
+///
+/// 若当前进程需要跟踪该类系统调用，则解码参数并返回待补全的记录
+pub fn syscall_enter<T: Debug>(
+    class: TraceClass,
+    id: T,
+    args: &[usize; 6],
+) -> Option<PendingTrace> {
+    if !TRACE_ENABLED.load(Ordering::Acquire) {
+        return None;
+    }
+    let pid = current_process().pid();
+    if !filter_of(pid).contains(class) {
+        return None;
+    }
+    let name = format!("{:?}", id);
+    let line = format!(
+        "[{} {}] {}",
+        pid,
+        current().id().as_u64(),
+        decode::decode_call(&name, args)
+    );
+    if name == "EXIT" || name == "EXIT_GROUP" {
+        // 这两个系统调用不会返回，直接记录
+        append_log(pid, line + " = ?\n");
+        return None;
+    }
+    Some(PendingTrace {
+        pid,
+        line,
+        is_fork: matches!(name.as_str(), "CLONE" | "CLONE3" | "FORK" | "VFORK"),
+    })
+}
+
+impl PendingTrace {
+    /// 在系统调用返回时调用，补全返回值并写入环形缓冲区
+    pub fn finish(mut self, ret: isize) {
+        if self.is_fork && ret > 0 && axprocess::PID2PC.lock().contains_key(&(ret as u64)) {
+            let classes = filter_of(self.pid);
+            set_filter(ret as u64, classes);
+        }
+        self.line.push_str(&decode::decode_return(ret));
+        self.line.push('\n');
+        append_log(self.pid, self.line);
+    }
+}
+
+/// 从 seq 开始读取给定进程的记录，返回读取的字节数
+pub fn read_process_log(pid: u64, seq: &mut u64, buf: &mut [u8]) -> usize {
+    TRACE_LOG.lock().read_from(seq, Some(pid), buf)
+}
+
+/// 以 `syslog` 的方式读取记录
+///
+/// `consume` 为真时从上次破坏性读取的位置开始读取并推进该位置，
+/// 否则读取缓冲区中现存的全部记录
+pub fn read_syslog(buf: &mut [u8], consume: bool) -> usize {
+    let mut log = TRACE_LOG.lock();
+    if consume {
+        let mut seq = log.syslog_seq;
+        let len = log.read_from(&mut seq, None, buf);
+        log.syslog_seq = seq;
+        len
+    } else {
+        let mut seq = 0;
+        log.read_from(&mut seq, None, buf)
+    }
+}
+
+/// 清空 `syslog` 的未读记录，之后的破坏性读取只会读到新的记录
+pub fn clear_syslog() {
+    let mut log = TRACE_LOG.lock();
+    log.syslog_seq = log.next_seq;
+}
+
+/// `syslog` 尚未读取的字节数
+pub fn syslog_unread_size() -> usize {
+    let log = TRACE_LOG.lock();
+    log.size_from(log.syslog_seq)
+}
+
+/// 新记录写入时的通知者，`syslog` 阻塞读取时在其上等待
+pub fn syslog_notifier() -> &'static PollNotifier {
+    &SYSLOG_NOTIFIER
+}
+
+/// 环形缓冲区的容量
+pub const fn syslog_buffer_size() -> usize {
+    TRACE_LOG_CAPACITY
+}
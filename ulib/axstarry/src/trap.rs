@@ -41,6 +41,13 @@ impl axhal::trap::TrapHandler for TrapHandlerImpl {
 
     #[cfg(feature = "signal")]
     fn handle_signal() {
+        // 控制台没有输入中断，在返回用户态前取出输入，使前台进程能及时收到 Ctrl-C 等产生的信号
+        axprocess::tty::poll_console();
         axprocess::signal::handle_signals();
     }
+
+    #[cfg(feature = "signal")]
+    fn handle_user_breakpoint(single_step: bool) {
+        axprocess::signal::send_trap_to_current(single_step);
+    }
 }